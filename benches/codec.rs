@@ -0,0 +1,112 @@
+//! Criterion benchmarks of EBCC compression and decompression on the
+//! synthetic fields of [`ebcc::testdata`].
+
+#![expect(missing_docs)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ebcc::{ebcc_decode_into, ebcc_encode, testdata, EBCCConfig, EbccDim};
+use ndarray::Array;
+
+#[cfg(feature = "arrow")]
+use ::arrow_array as _;
+#[cfg(feature = "arrow")]
+use ::arrow_schema as _;
+#[cfg(feature = "blake3")]
+use ::blake3 as _;
+#[cfg(feature = "bytemuck")]
+use ::bytemuck as _;
+#[cfg(feature = "half")]
+use ::half as _;
+#[cfg(feature = "mmap")]
+use ::memmap2 as _;
+#[cfg(feature = "nalgebra")]
+use ::nalgebra as _;
+#[cfg(feature = "ndarray015")]
+use ::ndarray015 as _;
+#[cfg(feature = "ndarray017")]
+use ::ndarray017 as _;
+#[cfg(feature = "netcdf")]
+use ::netcdf as _;
+#[cfg(feature = "rayon")]
+use ::rayon as _;
+#[cfg(feature = "serde")]
+use ::serde as _;
+#[cfg(feature = "async")]
+use ::tokio as _;
+#[cfg(feature = "tracing")]
+use ::tracing as _;
+use ::{crc as _, crc32fast as _, ebcc_sys as _, proptest as _, thiserror as _, xxhash_rust as _};
+
+const SHAPE: (usize, usize, usize) = (4, 256, 512);
+
+fn datasets() -> [(&'static str, Array<f32, EbccDim>); 3] {
+    [
+        ("temperature", testdata::temperature(SHAPE)),
+        ("precipitation", testdata::precipitation(SHAPE, 42)),
+        ("fronts", testdata::fronts(SHAPE)),
+    ]
+}
+
+const fn configs() -> [(&'static str, EBCCConfig); 3] {
+    [
+        ("jpeg2000-only", EBCCConfig::jpeg2000_only(30.0)),
+        (
+            "absolute-0.1",
+            EBCCConfig::max_absolute_error_bounded(0.1).with_base_cr(30.0),
+        ),
+        (
+            "relative-0.001",
+            EBCCConfig::relative_error_bounded(0.001).with_base_cr(30.0),
+        ),
+    ]
+}
+
+fn bytes(data: &Array<f32, EbccDim>) -> u64 {
+    u64::try_from(data.len() * size_of::<f32>()).unwrap_or(u64::MAX)
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    group.sample_size(10);
+
+    for (dataset, data) in datasets() {
+        group.throughput(Throughput::Bytes(bytes(&data)));
+
+        for (config_name, config) in configs() {
+            group.bench_with_input(BenchmarkId::new(config_name, dataset), &data, |b, data| {
+                b.iter(|| ebcc_encode(data.view(), &config));
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+
+    for (dataset, data) in datasets() {
+        group.throughput(Throughput::Bytes(bytes(&data)));
+
+        for (config_name, config) in configs() {
+            let Ok(compressed) = ebcc_encode(data.view(), &config) else {
+                continue;
+            };
+            let mut decompressed = Array::zeros(data.dim());
+
+            group.bench_with_input(
+                BenchmarkId::new(config_name, dataset),
+                &compressed,
+                |b, compressed| {
+                    b.iter(|| ebcc_decode_into(compressed, decompressed.view_mut()));
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
@@ -0,0 +1,290 @@
+//! Benchmark sweeps over a grid of EBCC configurations.
+
+use std::fmt;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use ebcc::{
+    ebcc_decode_into, ebcc_encode, EBCCConfig, EBCCError, EBCCResidualType, EBCCResult, EbccDim,
+};
+use ndarray::{Array, ArrayView};
+use serde::{Deserialize, Serialize};
+
+use crate::report;
+use crate::stats::{compression_ratio, ErrorStats};
+
+/// Grid of configurations to benchmark, as read from a YAML file, e.g.
+///
+/// ```yaml
+/// bounds:
+///   - absolute: 0.1
+///   - relative: 0.001
+///   - jpeg2000-only
+/// base_crs: [10, 30, 100]
+/// repeats: 3
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchGrid {
+    /// Error bounds of the residual layer
+    pub bounds: Vec<BenchBound>,
+    /// Compression ratios of the JPEG2000 base layer
+    pub base_crs: Vec<f32>,
+    /// Number of times each configuration is run, of which the fastest run
+    /// is reported
+    #[serde(default = "default_repeats")]
+    pub repeats: usize,
+}
+
+const fn default_repeats() -> usize {
+    1
+}
+
+/// Error bound of the residual layer in a [`BenchGrid`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", from = "BenchBoundRepr")]
+pub enum BenchBound {
+    /// No residual compression - base JPEG2000 only
+    Jpeg2000Only,
+    /// Absolute maximum error bound
+    Absolute(f32),
+    /// Relative error bound
+    Relative(f32),
+}
+
+/// YAML representation of a [`BenchBound`], which is either a single-entry
+/// `absolute` or `relative` map or the `jpeg2000-only` string
+#[derive(Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+enum BenchBoundRepr {
+    Absolute { absolute: f32 },
+    Relative { relative: f32 },
+    Jpeg2000Only(Jpeg2000OnlyRepr),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Jpeg2000OnlyRepr {
+    Jpeg2000Only,
+}
+
+impl From<BenchBoundRepr> for BenchBound {
+    fn from(bound: BenchBoundRepr) -> Self {
+        match bound {
+            BenchBoundRepr::Absolute { absolute } => Self::Absolute(absolute),
+            BenchBoundRepr::Relative { relative } => Self::Relative(relative),
+            BenchBoundRepr::Jpeg2000Only(Jpeg2000OnlyRepr::Jpeg2000Only) => Self::Jpeg2000Only,
+        }
+    }
+}
+
+impl From<BenchBound> for EBCCResidualType {
+    fn from(bound: BenchBound) -> Self {
+        match bound {
+            BenchBound::Jpeg2000Only => Self::Jpeg2000Only,
+            BenchBound::Absolute(error) => Self::AbsoluteError(error),
+            BenchBound::Relative(error) => Self::RelativeError(error),
+        }
+    }
+}
+
+impl From<EBCCResidualType> for BenchBound {
+    fn from(residual: EBCCResidualType) -> Self {
+        match residual {
+            EBCCResidualType::Jpeg2000Only => Self::Jpeg2000Only,
+            EBCCResidualType::AbsoluteError(error) => Self::Absolute(error),
+            EBCCResidualType::RelativeError(error) => Self::Relative(error),
+        }
+    }
+}
+
+impl fmt::Display for BenchBound {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Jpeg2000Only => fmt.write_str("jpeg2000-only"),
+            Self::Absolute(error) => write!(fmt, "absolute {error}"),
+            Self::Relative(error) => write!(fmt, "relative {error}"),
+        }
+    }
+}
+
+/// Measurements of one configuration of a [`BenchGrid`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchResult {
+    /// Error bound of the residual layer
+    pub bound: BenchBound,
+    /// Compression ratio of the JPEG2000 base layer
+    pub base_cr: f32,
+    /// Size of the compressed data, in bytes
+    pub compressed_bytes: usize,
+    /// Achieved compression ratio
+    pub ratio: f64,
+    /// Compression throughput, in MB/s of uncompressed data
+    pub encode_mb_per_s: f64,
+    /// Decompression throughput, in MB/s of uncompressed data
+    pub decode_mb_per_s: f64,
+    /// Maximum absolute error
+    pub max_error: f64,
+    /// Root mean squared error
+    pub rmse: f64,
+}
+
+/// Benchmark every combination of error bound and base compression ratio of
+/// the `grid` on the `data`.
+pub fn run(data: ArrayView<f32, EbccDim>, grid: &BenchGrid) -> EBCCResult<Vec<BenchResult>> {
+    if grid.repeats == 0 {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "Benchmarks must be repeated at least once",
+        )));
+    }
+
+    let original_bytes = data.len() * size_of::<f32>();
+    let mut decompressed = Array::zeros(data.dim());
+
+    let mut results = Vec::with_capacity(grid.bounds.len() * grid.base_crs.len());
+    for &bound in &grid.bounds {
+        for &base_cr in &grid.base_crs {
+            let config = EBCCConfig {
+                residual_compression_type: bound.into(),
+                ..EBCCConfig::new()
+            }
+            .with_base_cr(base_cr);
+            config.validate()?;
+
+            let mut compressed = Vec::new();
+            let (mut encode_time, mut decode_time) = (Duration::MAX, Duration::MAX);
+            for _ in 0..grid.repeats {
+                let start = Instant::now();
+                compressed = ebcc_encode(data, &config)?;
+                encode_time = encode_time.min(start.elapsed());
+
+                let start = Instant::now();
+                ebcc_decode_into(&compressed, decompressed.view_mut())?;
+                decode_time = decode_time.min(start.elapsed());
+            }
+
+            let stats = ErrorStats::new(data, decompressed.view());
+            results.push(BenchResult {
+                bound,
+                base_cr,
+                compressed_bytes: compressed.len(),
+                ratio: compression_ratio(original_bytes, compressed.len()),
+                encode_mb_per_s: throughput(original_bytes, encode_time),
+                decode_mb_per_s: throughput(original_bytes, decode_time),
+                max_error: stats.max_error,
+                rmse: stats.rmse,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+/// Write the benchmark `results` as CSV with a header row.
+pub fn write_csv(mut writer: impl Write, results: &[BenchResult]) -> EBCCResult<()> {
+    writeln!(
+        writer,
+        "bound,error,base_cr,compressed_bytes,ratio,encode_mb_per_s,decode_mb_per_s,max_error,rmse"
+    )?;
+
+    for result in results {
+        let (bound, error) = match result.bound {
+            BenchBound::Jpeg2000Only => ("jpeg2000-only", 0.0),
+            BenchBound::Absolute(error) => ("absolute", error),
+            BenchBound::Relative(error) => ("relative", error),
+        };
+        writeln!(
+            writer,
+            "{bound},{error},{},{},{},{},{},{},{}",
+            result.base_cr,
+            result.compressed_bytes,
+            result.ratio,
+            result.encode_mb_per_s,
+            result.decode_mb_per_s,
+            result.max_error,
+            result.rmse,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Write the benchmark `results` as a JSON array.
+pub fn write_json(writer: impl Write, results: &[BenchResult]) -> EBCCResult<()> {
+    report::write_json(writer, &results)
+}
+
+/// Parse a [`BenchGrid`] from YAML.
+pub fn parse_grid(yaml: &str) -> EBCCResult<BenchGrid> {
+    serde_yaml::from_str(yaml)
+        .map_err(|err| EBCCError::InvalidConfig(format!("Invalid benchmark configs: {err}")))
+}
+
+fn throughput(bytes: usize, time: Duration) -> f64 {
+    #[expect(clippy::cast_precision_loss)]
+    let megabytes = bytes as f64 / 1e6;
+    megabytes / time.as_secs_f64().max(f64::MIN_POSITIVE)
+}
+
+#[cfg(test)]
+mod tests {
+    use ebcc::testdata;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_grid() -> EBCCResult<()> {
+        let grid = parse_grid(
+            "bounds:\n  - absolute: 0.1\n  - relative: 0.001\n  - jpeg2000-only\nbase_crs: [10, 30]\n",
+        )?;
+        assert_eq!(
+            grid,
+            BenchGrid {
+                bounds: vec![
+                    BenchBound::Absolute(0.1),
+                    BenchBound::Relative(0.001),
+                    BenchBound::Jpeg2000Only,
+                ],
+                base_crs: vec![10.0, 30.0],
+                repeats: 1,
+            }
+        );
+
+        assert!(parse_grid("bounds: []\nbase_cr: [10]\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bench() -> EBCCResult<()> {
+        let data = testdata::temperature((1, 32, 48));
+        let grid = BenchGrid {
+            bounds: vec![BenchBound::Absolute(0.1), BenchBound::Absolute(0.01)],
+            base_crs: vec![10.0, 30.0],
+            repeats: 2,
+        };
+
+        let results = run(data.view(), &grid)?;
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|result| match result.bound {
+            BenchBound::Absolute(error) => result.max_error <= f64::from(error) + 1e-6,
+            _ => false,
+        }));
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &results)?;
+        let csv = String::from_utf8(csv).unwrap_or_default();
+        assert_eq!(csv.lines().count(), 5);
+        assert!(csv.lines().all(|line| line.split(',').count() == 9));
+
+        let mut json = Vec::new();
+        write_json(&mut json, &results)?;
+        assert!(String::from_utf8(json)
+            .unwrap_or_default()
+            .contains("\"absolute\": 0.1"));
+
+        assert!(run(data.view(), &BenchGrid { repeats: 0, ..grid }).is_err());
+
+        Ok(())
+    }
+}
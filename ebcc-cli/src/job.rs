@@ -0,0 +1,279 @@
+//! Job files of the `run` subcommand, which describe an
+//! [`EBCCJobSpec`] in TOML or JSON.
+
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use ebcc::container::EBCCContainerConfig;
+use ebcc::runner::{EBCCJobInput, EBCCJobLayout, EBCCJobSpec};
+use ebcc::{EBCCConfig, EBCCConfigOverride, EBCCError, EBCCResult};
+use serde::Deserialize;
+
+use crate::bench::BenchBound;
+
+/// Compression job, as read from a TOML file, e.g.
+///
+/// ```toml
+/// threads = 4
+///
+/// [output]
+/// dir = "compressed"
+/// layout = "container"
+///
+/// [config]
+/// bound = { absolute = 0.1 }
+/// base_cr = 30
+///
+/// [variables.precipitation]
+/// bound = { relative = 0.001 }
+///
+/// [[inputs]]
+/// path = "t2m-2024.npy"
+/// variable = "temperature"
+///
+/// [[inputs]]
+/// path = "tp-2024.f32"
+/// variable = "precipitation"
+/// shape = [365, 721, 1440]
+/// ```
+///
+/// or from a JSON file with the same structure. Relative paths are resolved
+/// against the directory of the job file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobFile {
+    /// Input files, which are compressed in this order
+    pub inputs: Vec<JobInput>,
+    /// Configuration of all variables without their own configuration
+    #[serde(default)]
+    pub config: JobConfig,
+    /// Configurations of individual variables, which override the `config`
+    #[serde(default)]
+    pub variables: BTreeMap<String, JobConfig>,
+    /// Directory and layout of the compressed files
+    pub output: JobOutput,
+    /// Maximum number of inputs that are compressed in parallel
+    #[serde(default = "default_threads")]
+    pub threads: NonZeroUsize,
+}
+
+const fn default_threads() -> NonZeroUsize {
+    NonZeroUsize::MIN
+}
+
+/// Input file of a [`JobFile`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobInput {
+    /// Input `.npy` file, or raw little-endian `f32` file with a `shape`
+    pub path: PathBuf,
+    /// Variable of the data, which selects its configuration
+    pub variable: String,
+    /// Shape `[frames, height, width]` or `[height, width]` of a raw input
+    /// file
+    #[serde(default)]
+    pub shape: Option<Vec<usize>>,
+}
+
+/// Error bound and base compression ratio of a [`JobFile`], which inherit
+/// the defaults of [`EBCCConfig::new`] if they are missing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobConfig {
+    /// Error bound of the residual layer
+    pub bound: Option<BenchBound>,
+    /// Compression ratio of the JPEG2000 base layer
+    pub base_cr: Option<f32>,
+}
+
+/// Output directory and layout of a [`JobFile`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobOutput {
+    /// Directory into which the compressed files are written
+    pub dir: PathBuf,
+    /// Layout of the compressed files, either one `"payload"` or one
+    /// `"container"` per input
+    #[serde(default)]
+    pub layout: JobLayout,
+}
+
+/// Layout of the compressed files, see [`EBCCJobLayout`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobLayout {
+    /// One EBCC payload per input
+    #[default]
+    Payload,
+    /// One EBCC container per input
+    Container,
+}
+
+impl From<JobLayout> for EBCCJobLayout {
+    fn from(layout: JobLayout) -> Self {
+        match layout {
+            JobLayout::Payload => Self::Payload,
+            JobLayout::Container => Self::Container,
+        }
+    }
+}
+
+/// Parse the job file at `path`, which is read as JSON if it has the `.json`
+/// extension and as TOML otherwise, into a job specification.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidConfig`] if the job file is invalid
+pub fn parse_job(path: &Path, source: &str) -> EBCCResult<EBCCJobSpec> {
+    let job: JobFile = if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        serde_json::from_str(source)
+            .map_err(|err| EBCCError::InvalidConfig(format!("Invalid job file: {err}")))?
+    } else {
+        toml::from_str(source)
+            .map_err(|err| EBCCError::InvalidConfig(format!("Invalid job file: {err}")))?
+    };
+
+    job.into_spec(path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+impl JobFile {
+    /// Convert the job file into a job specification, whose relative paths
+    /// are resolved against the `base` directory.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidConfig`] if an input shape is invalid
+    pub fn into_spec(self, base: &Path) -> EBCCResult<EBCCJobSpec> {
+        let default = self.config.apply(EBCCConfig::new());
+        let config = self.variables.into_iter().fold(
+            EBCCContainerConfig::new(default),
+            |config, (variable, overrides)| {
+                config.with_variable(variable, overrides.into_override())
+            },
+        );
+
+        let mut spec = EBCCJobSpec::new(config, base.join(self.output.dir))
+            .with_layout(self.output.layout.into())
+            .with_threads(self.threads);
+        for input in self.inputs {
+            let mut job_input = EBCCJobInput::new(base.join(&input.path), input.variable);
+            if let Some(shape) = input.shape {
+                job_input = job_input.with_shape(parse_shape(&shape)?);
+            }
+            spec = spec.with_input(job_input);
+        }
+
+        Ok(spec)
+    }
+}
+
+impl JobConfig {
+    fn apply(self, mut config: EBCCConfig) -> EBCCConfig {
+        if let Some(bound) = self.bound {
+            config.residual_compression_type = bound.into();
+        }
+        if let Some(base_cr) = self.base_cr {
+            config = config.with_base_cr(base_cr);
+        }
+        config
+    }
+
+    fn into_override(self) -> EBCCConfigOverride {
+        let mut overrides = EBCCConfigOverride::new();
+        if let Some(bound) = self.bound {
+            overrides = overrides.with_residual_compression_type(bound.into());
+        }
+        if let Some(base_cr) = self.base_cr {
+            overrides = overrides.with_base_cr(base_cr);
+        }
+        overrides
+    }
+}
+
+fn parse_shape(shape: &[usize]) -> EBCCResult<(usize, usize, usize)> {
+    match *shape {
+        [height, width] => Ok((1, height, width)),
+        [frames, height, width] => Ok((frames, height, width)),
+        _ => Err(EBCCError::InvalidConfig(format!(
+            "Invalid job file: expected a [frames, height, width] or [height, width] shape but \
+             got {shape:?}",
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ebcc::EBCCResidualType;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_job() -> EBCCResult<()> {
+        let toml = r#"
+threads = 2
+
+[output]
+dir = "out"
+layout = "container"
+
+[config]
+bound = { absolute = 0.1 }
+
+[variables.precipitation]
+bound = { relative = 0.001 }
+base_cr = 10
+
+[[inputs]]
+path = "t2m.npy"
+variable = "temperature"
+
+[[inputs]]
+path = "/data/tp.f32"
+variable = "precipitation"
+shape = [32, 48]
+"#;
+        let spec = parse_job(Path::new("jobs/job.toml"), toml)?;
+
+        assert_eq!(spec.output_dir, Path::new("jobs/out"));
+        assert_eq!(spec.layout, EBCCJobLayout::Container);
+        assert_eq!(spec.threads.get(), 2);
+        assert_eq!(
+            spec.inputs,
+            [
+                EBCCJobInput::new("jobs/t2m.npy", "temperature"),
+                EBCCJobInput::new("/data/tp.f32", "precipitation").with_shape((1, 32, 48)),
+            ]
+        );
+        assert_eq!(
+            spec.config.resolve_variable("temperature"),
+            EBCCConfig::max_absolute_error_bounded(0.1)
+        );
+        let precipitation = spec.config.resolve_variable("precipitation");
+        assert_eq!(
+            precipitation.residual_compression_type,
+            EBCCResidualType::RelativeError(0.001)
+        );
+        assert_eq!(precipitation.base_cr.to_bits(), 10.0_f32.to_bits());
+
+        // JSON job files have the same structure
+        let json = r#"{"inputs": [{"path": "t2m.npy", "variable": "temperature"}],
+            "output": {"dir": "out"}}"#;
+        let spec = parse_job(Path::new("job.json"), json)?;
+        assert_eq!(spec.layout, EBCCJobLayout::Payload);
+        assert_eq!(spec.threads, NonZeroUsize::MIN);
+        assert_eq!(spec.config, EBCCContainerConfig::new(EBCCConfig::new()));
+
+        assert!(parse_job(Path::new("job.toml"), "inputs = []").is_err());
+        assert!(parse_job(
+            Path::new("job.json"),
+            r#"{"inputs": [{"path": "a", "variable": "b", "shape": [1]}], "output": {"dir": "o"}}"#,
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,470 @@
+//! Command-line interface to the EBCC compressor.
+//!
+//! The `ebcc` binary compresses and decompresses `.npy` files with `float32`
+//! data, or raw little-endian `f32` files together with their `--shape`,
+//! inspects and verifies EBCC compressed data, and runs compression jobs
+//! that are described by TOML or JSON job files.
+//!
+//! With `--json`, every subcommand prints its results as JSON, whose schema
+//! is documented in the [`report`] module.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use ebcc::container::{is_ebcc_container, verify_integrity, EbccContainer};
+use ebcc::runner::run_job;
+use ebcc::{
+    ebcc_decode_into, ebcc_encode, ebcc_inspect, ebcc_ssim, EBCCConfig, EBCCDecodeOptions,
+    EBCCError, EBCCHeader, EBCCQuantileSketch, EBCCResidualType, EBCCResult, EbccDim,
+    EBCC_STREAM_MAGIC, EBCC_TILED_MAGIC,
+};
+use ndarray::{Array, ArrayView};
+
+mod bench;
+mod job;
+mod npy;
+mod report;
+mod stats;
+
+use crate::report::{
+    write_json, write_report, CompressReport, DecompressReport, ErrorReport, InfoReport,
+    Percentiles, RunReport, VerifyReport,
+};
+use crate::stats::{compression_ratio, ErrorStats};
+
+/// Compress and decompress `float32` data with EBCC.
+#[derive(Debug, Parser)]
+#[command(name = "ebcc", version, about)]
+struct Cli {
+    /// Print the results, and any error, as JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Compress a `.npy` or raw `f32` file and print a compression report
+    Compress(CompressArgs),
+    /// Decompress EBCC data into a `.npy` or raw `f32` file
+    Decompress(DecompressArgs),
+    /// Print information about EBCC compressed data
+    Info {
+        /// EBCC compressed input file
+        input: PathBuf,
+    },
+    /// Verify the framing and checksums of an EBCC container without
+    /// decoding its frames
+    Verify {
+        /// EBCC container file
+        input: PathBuf,
+    },
+    /// Benchmark a grid of error bounds and base compression ratios on a
+    /// `.npy` or raw `f32` file
+    Bench(BenchArgs),
+    /// Run the compression job of a TOML or JSON job file, printing its
+    /// progress to stderr
+    Run {
+        /// TOML job file, or JSON job file with the `.json` extension
+        job: PathBuf,
+    },
+}
+
+#[derive(Debug, Args)]
+struct CompressArgs {
+    /// Input `.npy` file, or raw little-endian `f32` file with `--shape`
+    input: PathBuf,
+    /// Output file for the EBCC compressed data
+    output: PathBuf,
+    /// Shape `frames,height,width` or `height,width` of a raw input file
+    #[arg(long, value_parser = parse_shape)]
+    shape: Option<(usize, usize, usize)>,
+    /// Absolute maximum error bound
+    #[arg(long, conflicts_with = "relative_error")]
+    error: Option<f32>,
+    /// Relative error bound, as a fraction of the data range
+    #[arg(long)]
+    relative_error: Option<f32>,
+    /// Compression ratio of the JPEG2000 base layer
+    #[arg(long)]
+    base_cr: Option<f32>,
+    /// Store a quantile sketch of every frame, which `info` prints
+    #[arg(long)]
+    quantile_sketch: bool,
+    /// Lower bound of the physical value range, e.g. `0` for precipitation,
+    /// to which the decompressed data is clamped
+    #[arg(long, allow_negative_numbers = true)]
+    min: Option<f32>,
+    /// Upper bound of the physical value range to which the decompressed
+    /// data is clamped
+    #[arg(long, allow_negative_numbers = true)]
+    max: Option<f32>,
+}
+
+#[derive(Debug, Args)]
+struct DecompressArgs {
+    /// EBCC compressed input file
+    input: PathBuf,
+    /// Output `.npy` file, or raw little-endian `f32` file for any other
+    /// extension
+    output: PathBuf,
+    /// Shape `frames,height,width` or `height,width` of the decompressed
+    /// data, only required if it is not recorded in the compressed data
+    #[arg(long, value_parser = parse_shape)]
+    shape: Option<(usize, usize, usize)>,
+}
+
+#[derive(Debug, Args)]
+struct BenchArgs {
+    /// Input `.npy` file, or raw little-endian `f32` file with `--shape`
+    input: PathBuf,
+    /// Shape `frames,height,width` or `height,width` of a raw input file
+    #[arg(long, value_parser = parse_shape)]
+    shape: Option<(usize, usize, usize)>,
+    /// YAML file with the `bounds` and `base_crs` to sweep, and optionally
+    /// the number of `repeats` of each configuration
+    #[arg(long)]
+    configs: PathBuf,
+    /// Output format of the results, which is always JSON with `--json`
+    #[arg(long, value_enum, default_value_t = BenchFormat::Csv)]
+    format: BenchFormat,
+    /// Output file for the results, by default they are printed
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BenchFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// JSON array of objects
+    Json,
+}
+
+fn main() -> ExitCode {
+    // `--version` reports the features that the ebcc library was built with
+    //  and the versions of the C libraries that it is linked with
+    let long_version = format!(
+        "{}\nlibrary: {}\nlinked: {}",
+        env!("CARGO_PKG_VERSION"),
+        ebcc::capabilities(),
+        ebcc::version(),
+    );
+    let matches = Cli::command().long_version(long_version).get_matches();
+    let cli = match Cli::from_arg_matches(&matches) {
+        Ok(cli) => cli,
+        Err(err) => err.exit(),
+    };
+
+    let json = cli.json;
+    let result = match cli.command {
+        Command::Compress(args) => compress(&args, json),
+        Command::Decompress(args) => decompress(&args, json),
+        Command::Info { input } => info(&input, json),
+        Command::Verify { input } => verify(&input, json),
+        Command::Bench(args) => bench(&args, json),
+        Command::Run { job } => run(&job, json),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(err) if json => {
+            // scripts read the error from stdout, like the results
+            if let Err(err) = write_json(io::stdout().lock(), &ErrorReport::from(&err)) {
+                eprintln!("error: {err}");
+            }
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn compress(args: &CompressArgs, json: bool) -> EBCCResult<ExitCode> {
+    let data = read_data(&args.input, args.shape)?;
+
+    let mut config = match (args.error, args.relative_error) {
+        (Some(error), _) => EBCCConfig::max_absolute_error_bounded(error),
+        (None, Some(error)) => EBCCConfig::relative_error_bounded(error),
+        (None, None) => EBCCConfig::new(),
+    };
+    if let Some(base_cr) = args.base_cr {
+        config = config.with_base_cr(base_cr);
+    }
+    if args.quantile_sketch {
+        config = config.with_quantile_sketch();
+    }
+    if args.min.is_some() || args.max.is_some() {
+        config = config.with_value_range(args.min, args.max);
+    }
+
+    let compressed = ebcc_encode(data.view(), &config)?;
+    fs::write(&args.output, &compressed)?;
+
+    let mut decompressed = Array::zeros(data.dim());
+    ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+    let report = compress_report(
+        data.view(),
+        decompressed.view(),
+        compressed.len(),
+        config.residual_compression_type,
+    )?;
+    write_report(io::stdout().lock(), &report, json)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn decompress(args: &DecompressArgs, json: bool) -> EBCCResult<ExitCode> {
+    if is_container_file(&args.input)? {
+        let mut container = EbccContainer::open(BufReader::new(File::open(&args.input)?))?;
+        let (height, width) = container.frame_shape();
+        // the shape is read from the untrusted container
+        let shape = (container.live_frames(), height, width);
+        EBCCDecodeOptions::new().check_shape(shape)?;
+        let mut decompressed = Array::zeros(shape);
+        container.decode_into(decompressed.view_mut())?;
+        write_data(&args.output, decompressed.view())?;
+        return print_decompress_report(decompressed.view(), json);
+    }
+
+    let compressed = fs::read(&args.input)?;
+    let shape = match (EBCCHeader::parse(&compressed)?, args.shape) {
+        (Some(header), _) => header.shape.into(),
+        (None, Some(shape)) => shape,
+        (None, None) => {
+            return Err(EBCCError::InvalidInput(String::from(
+                "The shape of the compressed data is not recorded, pass it with --shape",
+            )))
+        }
+    };
+
+    // the shape may be read from the untrusted header
+    EBCCDecodeOptions::new().check_shape(shape)?;
+    let mut decompressed = Array::zeros(shape);
+    ebcc_decode_into(&compressed, decompressed.view_mut())?;
+    write_data(&args.output, decompressed.view())?;
+
+    print_decompress_report(decompressed.view(), json)
+}
+
+fn print_decompress_report(
+    decompressed: ArrayView<f32, EbccDim>,
+    json: bool,
+) -> EBCCResult<ExitCode> {
+    let report = DecompressReport {
+        shape: decompressed.dim().into(),
+        decompressed_bytes: decompressed.len() * size_of::<f32>(),
+    };
+    // decompression only prints a report with --json
+    if json {
+        write_json(io::stdout().lock(), &report)?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn info(input: &Path, json: bool) -> EBCCResult<ExitCode> {
+    let report = info_report(input)?;
+    write_report(io::stdout().lock(), &report, json)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn info_report(input: &Path) -> EBCCResult<InfoReport> {
+    if is_container_file(input)? {
+        let container = EbccContainer::open(BufReader::new(File::open(input)?))?;
+        return Ok(InfoReport::Container {
+            frames: container.frames(),
+            live_frames: container.live_frames(),
+            frame_shape: container.frame_shape().into(),
+            orphaned_bytes: container.orphaned_bytes(),
+        });
+    }
+
+    let compressed = fs::read(input)?;
+    let size = compressed.len();
+
+    let inspection = ebcc_inspect(&compressed)?;
+    let report = if let Some(header) = inspection.header {
+        InfoReport::Payload {
+            size,
+            version: header.version,
+            dtype: format!("{:?}", header.dtype).to_lowercase(),
+            shape: header.shape,
+            fingerprint: header
+                .has_config_fingerprint
+                .then(|| format!("{:016x}", header.config_fingerprint)),
+            payload_bytes: header.payload_len,
+            checksum: header.checksum.to_string(),
+            checksum_algorithm: header.checksum.algorithm().to_string(),
+            data_checksum: header
+                .decompressed_checksum
+                .map(|checksum| format!("{checksum:08x}")),
+            percentiles: EBCCQuantileSketch::merge(&inspection.quantile_sketches).map(|sketch| {
+                Percentiles {
+                    p1: sketch.quantile(0.01),
+                    p50: sketch.quantile(0.5),
+                    p99: sketch.quantile(0.99),
+                }
+            }),
+        }
+    } else if compressed.starts_with(EBCC_STREAM_MAGIC) {
+        InfoReport::Stream { size }
+    } else if compressed.starts_with(EBCC_TILED_MAGIC) {
+        InfoReport::Tiles { size }
+    } else {
+        InfoReport::Legacy { size }
+    };
+
+    Ok(report)
+}
+
+fn verify(input: &Path, json: bool) -> EBCCResult<ExitCode> {
+    let report = VerifyReport::from(verify_integrity(BufReader::new(File::open(input)?))?);
+    write_report(io::stdout().lock(), &report, json)?;
+
+    Ok(if report.ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn bench(args: &BenchArgs, json: bool) -> EBCCResult<ExitCode> {
+    let grid = bench::parse_grid(&fs::read_to_string(&args.configs)?)?;
+    let data = read_data(&args.input, args.shape)?;
+
+    let results = bench::run(data.view(), &grid)?;
+
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(output) => Box::new(BufWriter::new(File::create(output)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    let format = if json { BenchFormat::Json } else { args.format };
+    match format {
+        BenchFormat::Csv => bench::write_csv(&mut writer, &results)?,
+        BenchFormat::Json => bench::write_json(&mut writer, &results)?,
+    }
+    writer.flush()?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run(job: &Path, json: bool) -> EBCCResult<ExitCode> {
+    let spec = job::parse_job(job, &fs::read_to_string(job)?)?;
+
+    let report = run_job(
+        &spec,
+        |input| read_data(&input.path, input.shape),
+        |progress| {
+            eprintln!(
+                "[{}/{}] {} -> {}",
+                progress.completed,
+                progress.total,
+                progress.output.input.display(),
+                progress.output.output.display(),
+            );
+        },
+    )?;
+    write_report(io::stdout().lock(), &RunReport::from(report), json)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn compress_report(
+    data: ArrayView<f32, EbccDim>,
+    decompressed: ArrayView<f32, EbccDim>,
+    compressed_bytes: usize,
+    residual_compression_type: EBCCResidualType,
+) -> EBCCResult<CompressReport> {
+    let original_bytes = data.len() * size_of::<f32>();
+    let stats = ErrorStats::new(data, decompressed);
+
+    Ok(CompressReport {
+        shape: data.dim().into(),
+        bound: residual_compression_type.into(),
+        original_bytes,
+        compressed_bytes,
+        ratio: compression_ratio(original_bytes, compressed_bytes),
+        max_error: stats.max_error,
+        rmse: stats.rmse,
+        ssim: ebcc_ssim(data, decompressed)?,
+    })
+}
+
+fn read_data(path: &Path, shape: Option<(usize, usize, usize)>) -> EBCCResult<Array<f32, EbccDim>> {
+    let reader = BufReader::new(File::open(path)?);
+
+    match shape {
+        Some(shape) => npy::read_raw(reader, shape),
+        None if is_npy(path) => npy::read_npy(reader),
+        None => Err(EBCCError::InvalidInput(String::from(
+            "Raw input files require their --shape",
+        ))),
+    }
+}
+
+fn write_data(path: &Path, data: ArrayView<f32, EbccDim>) -> EBCCResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    if is_npy(path) {
+        npy::write_npy(&mut writer, data)?;
+    } else {
+        npy::write_raw(&mut writer, data)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn is_npy(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "npy")
+}
+
+fn is_container_file(path: &Path) -> EBCCResult<bool> {
+    let mut magic = Vec::with_capacity(8);
+    File::open(path)?.take(8).read_to_end(&mut magic)?;
+    Ok(is_ebcc_container(&magic))
+}
+
+fn parse_shape(shape: &str) -> Result<(usize, usize, usize), String> {
+    let dims = shape
+        .split(',')
+        .map(|dim| dim.trim().parse::<usize>().map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match *dims.as_slice() {
+        [height, width] => Ok((1, height, width)),
+        [frames, height, width] => Ok((frames, height, width)),
+        _ => Err(format!(
+            "expected frames,height,width or height,width but got {shape:?}",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shape() {
+        assert_eq!(parse_shape("2,32,64"), Ok((2, 32, 64)));
+        assert_eq!(parse_shape("32, 64"), Ok((1, 32, 64)));
+        assert!(parse_shape("32").is_err());
+        assert!(parse_shape("a,b,c").is_err());
+    }
+
+    #[test]
+    fn test_cli() {
+        use clap::CommandFactory;
+
+        Cli::command().debug_assert();
+    }
+}
@@ -0,0 +1,220 @@
+//! Minimal reader and writer for `.npy` files with `float32` data.
+
+use std::io::{Read, Write};
+
+use ebcc::{EBCCError, EBCCResult, EbccDim};
+use ndarray::{Array, ArrayView};
+
+/// Magic bytes at the start of every `.npy` file
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// Alignment of the `.npy` header, including the magic bytes and version
+const NPY_HEADER_ALIGN: usize = 64;
+
+/// Read a C-order `float32` array with two or three dimensions from a `.npy`
+/// file, where a 2D array is read as a single frame.
+pub fn read_npy(mut reader: impl Read) -> EBCCResult<Array<f32, EbccDim>> {
+    let mut preamble = [0; 8];
+    reader.read_exact(&mut preamble)?;
+    let (magic, [major, _minor]) = preamble.split_at(NPY_MAGIC.len()) else {
+        return Err(invalid("truncated preamble"));
+    };
+    if magic != NPY_MAGIC {
+        return Err(invalid("missing magic bytes"));
+    }
+
+    let header_len = match major {
+        1 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            usize::from(u16::from_le_bytes(len))
+        }
+        2 | 3 => {
+            let mut len = [0; 4];
+            reader.read_exact(&mut len)?;
+            usize::try_from(u32::from_le_bytes(len)).map_err(|_| invalid("header too long"))?
+        }
+        major => return Err(invalid(&format!("unsupported version {major}"))),
+    };
+
+    let mut header = vec![0; header_len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8(header).map_err(|_| invalid("non-UTF-8 header"))?;
+
+    match header_value(&header, "descr") {
+        Some("'<f4'" | "\"<f4\"") => (),
+        Some(descr) => {
+            return Err(invalid(&format!(
+                "unsupported dtype {descr}, only little-endian float32 is supported",
+            )))
+        }
+        None => return Err(invalid("missing dtype")),
+    }
+    if header_value(&header, "fortran_order") != Some("False") {
+        return Err(invalid("only C-order arrays are supported"));
+    }
+    let shape = header_value(&header, "shape")
+        .and_then(|shape| shape.strip_prefix('('))
+        .and_then(|shape| shape.strip_suffix(')'))
+        .ok_or_else(|| invalid("missing shape"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse::<usize>().map_err(|_| invalid("invalid shape")))
+        .collect::<EBCCResult<Vec<_>>>()?;
+    let shape = match *shape.as_slice() {
+        [height, width] => (1, height, width),
+        [frames, height, width] => (frames, height, width),
+        _ => {
+            return Err(invalid(&format!(
+                "unsupported shape {shape:?}, only 2D and 3D arrays are supported",
+            )))
+        }
+    };
+
+    read_raw(reader, shape)
+}
+
+/// Write a `float32` array to a `.npy` file.
+pub fn write_npy(mut writer: impl Write, data: ArrayView<f32, EbccDim>) -> EBCCResult<()> {
+    let (frames, height, width) = data.dim();
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({frames}, {height}, {width}), }}"
+    );
+    // the header is padded with spaces and terminated by a newline
+    let unpadded_len = NPY_MAGIC.len() + 2 + 2 + header.len() + 1;
+    let padding = unpadded_len.next_multiple_of(NPY_HEADER_ALIGN) - unpadded_len;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+    let header_len = u16::try_from(header.len()).map_err(|_| invalid("header too long"))?;
+
+    writer.write_all(NPY_MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&header_len.to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    write_raw(writer, data)
+}
+
+/// Read a 3D array of the given `shape` from raw little-endian `f32` data.
+pub fn read_raw(
+    mut reader: impl Read,
+    shape: (usize, usize, usize),
+) -> EBCCResult<Array<f32, EbccDim>> {
+    let (frames, height, width) = shape;
+    let bytes = frames
+        .checked_mul(height)
+        .and_then(|len| len.checked_mul(width))
+        .and_then(|len| len.checked_mul(size_of::<f32>()))
+        .ok_or_else(|| EBCCError::InvalidInput(format!("Shape {shape:?} is too large")))?;
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if data.len() != bytes {
+        return Err(EBCCError::InvalidInput(format!(
+            "Expected {bytes} bytes of f32 data for shape {shape:?} but found {}",
+            data.len(),
+        )));
+    }
+
+    let data = data
+        .chunks_exact(size_of::<f32>())
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap_or_default()))
+        .collect();
+    Array::from_shape_vec(shape, data).map_err(|err| EBCCError::InvalidInput(err.to_string()))
+}
+
+/// Write a 3D array as raw little-endian `f32` data.
+pub fn write_raw(mut writer: impl Write, data: ArrayView<f32, EbccDim>) -> EBCCResult<()> {
+    let bytes = data
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect::<Vec<_>>();
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Find the literal value of the `key` in a `.npy` header dictionary.
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let (_, value) = header
+        .split_once(&format!("'{key}'"))
+        .or_else(|| header.split_once(&format!("\"{key}\"")))?;
+    let value = value.trim_start().strip_prefix(':')?.trim_start();
+
+    // a tuple value contains commas, so it ends at the closing parenthesis
+    let end = if value.starts_with('(') {
+        value.find(')')? + 1
+    } else {
+        value.find([',', '}'])?
+    };
+    value.get(..end).map(str::trim)
+}
+
+fn invalid(message: &str) -> EBCCError {
+    EBCCError::InvalidInput(format!("Invalid .npy file: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_npy_roundtrip() -> EBCCResult<()> {
+        let data = Array::from_shape_fn((2, 3, 5), |(t, i, j)| (t * 100 + i * 10 + j) as f32);
+
+        let mut bytes = Vec::new();
+        write_npy(&mut bytes, data.view())?;
+        assert!(bytes.starts_with(NPY_MAGIC));
+        assert_eq!((bytes.len() - data.len() * 4) % NPY_HEADER_ALIGN, 0);
+
+        assert_eq!(read_npy(bytes.as_slice())?, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_npy_2d_header() -> EBCCResult<()> {
+        let header = b"{'descr': '<f4', 'fortran_order': False, 'shape': (2, 2), }  \n";
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(
+            &u16::try_from(header.len())
+                .unwrap_or_default()
+                .to_le_bytes(),
+        );
+        bytes.extend_from_slice(header);
+        for x in [1.0_f32, 2.0, 3.0, 4.0] {
+            bytes.extend_from_slice(&x.to_le_bytes());
+        }
+
+        let data = read_npy(bytes.as_slice())?;
+        assert_eq!(data.dim(), (1, 2, 2));
+        assert_eq!(
+            data.iter().copied().collect::<Vec<_>>(),
+            [1.0, 2.0, 3.0, 4.0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_npy_unsupported() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(NPY_MAGIC);
+        bytes.extend_from_slice(&[1, 0]);
+        let header = b"{'descr': '<f8', 'fortran_order': False, 'shape': (2,), }\n";
+        bytes.extend_from_slice(
+            &u16::try_from(header.len())
+                .unwrap_or_default()
+                .to_le_bytes(),
+        );
+        bytes.extend_from_slice(header);
+        assert!(matches!(
+            read_npy(bytes.as_slice()),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        assert!(read_npy(&b"not an npy file"[..]).is_err());
+        assert!(read_raw(&[0_u8; 12][..], (1, 2, 2)).is_err());
+    }
+}
@@ -0,0 +1,513 @@
+//! Reports of the CLI subcommands, which are printed either as
+//! human-readable text or, with `--json`, as a single JSON object.
+//!
+//! The JSON schema is stable: fields are only ever added, never renamed or
+//! removed. Sizes are in bytes, shapes are arrays of `[frames, height,
+//! width]`, and checksums and fingerprints are lowercase hex strings, since
+//! 64-bit integers are not exactly representable in every JSON parser.
+//!
+//! - `compress`: `{"shape", "bound", "original_bytes", "compressed_bytes",
+//!   "ratio", "max_error", "rmse", "ssim"}`, where the `bound` is
+//!   `{"absolute": error}`, `{"relative": error}`, or `"jpeg2000-only"`
+//! - `decompress`: `{"shape", "decompressed_bytes"}`
+//! - `info`: an object whose `"format"` is one of
+//!   - `"container"`: `{"frames", "live_frames", "frame_shape",
+//!     "orphaned_bytes"}`
+//!   - `"payload"`: `{"size", "version", "dtype", "shape", "fingerprint",
+//!     "payload_bytes", "checksum", "checksum_algorithm", "data_checksum",
+//!     "percentiles"}`, where the `fingerprint`, the `data_checksum`, and the
+//!     `{"p1", "p50", "p99"}` `percentiles` may be `null`
+//!   - `"stream"`, `"tiles"`, or `"legacy"`: `{"size"}`
+//! - `verify`: `{"ok", "frames", "deleted_frames", "verified_bytes",
+//!   "corrupted_frames"}`
+//! - `run`: `{"outputs", "original_bytes", "compressed_bytes", "ratio"}`,
+//!   where every output is `{"input", "output", "variable",
+//!   "original_bytes", "compressed_bytes", "ratio"}`, in the order of the
+//!   job's inputs
+//! - `bench`: an array of `{"bound", "base_cr", "compressed_bytes", "ratio",
+//!   "encode_mb_per_s", "decode_mb_per_s", "max_error", "rmse"}`
+//! - any failed subcommand: `{"error": {"kind", "message"}}`, where the
+//!   `kind` is one of `"invalid-input"`, `"invalid-config"`, `"compression"`,
+//!   `"decompression"`, `"io"`, `"timed-out"`, `"access-denied"`,
+//!   `"out-of-memory"`, or `"other"`
+
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+
+use ebcc::container::IntegrityReport;
+use ebcc::runner::{EBCCJobOutput, EBCCJobReport};
+use ebcc::{EBCCError, EBCCErrorKind, EBCCResult};
+use serde::Serialize;
+
+use crate::bench::BenchBound;
+use crate::stats::compression_ratio;
+
+/// Report of the `compress` subcommand
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompressReport {
+    /// Shape of the compressed data
+    pub shape: [usize; 3],
+    /// Error bound of the residual layer
+    pub bound: BenchBound,
+    /// Size of the original data, in bytes
+    pub original_bytes: usize,
+    /// Size of the compressed data, in bytes
+    pub compressed_bytes: usize,
+    /// Achieved compression ratio
+    pub ratio: f64,
+    /// Maximum absolute error
+    pub max_error: f64,
+    /// Root mean squared error
+    pub rmse: f64,
+    /// Structural similarity of the decompressed data
+    pub ssim: f64,
+}
+
+impl fmt::Display for CompressReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let [frames, height, width] = self.shape;
+        writeln!(fmt, "shape:          {frames}x{height}x{width}")?;
+        writeln!(fmt, "bound:          {}", self.bound)?;
+        writeln!(fmt, "original:       {} bytes", self.original_bytes)?;
+        writeln!(fmt, "compressed:     {} bytes", self.compressed_bytes)?;
+        writeln!(fmt, "ratio:          {:.2}", self.ratio)?;
+        writeln!(fmt, "max error:      {:e}", self.max_error)?;
+        writeln!(fmt, "rmse:           {:e}", self.rmse)?;
+        writeln!(fmt, "ssim:           {:.6}", self.ssim)
+    }
+}
+
+/// Report of the `decompress` subcommand
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecompressReport {
+    /// Shape of the decompressed data
+    pub shape: [usize; 3],
+    /// Size of the decompressed data, in bytes
+    pub decompressed_bytes: usize,
+}
+
+impl fmt::Display for DecompressReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let [frames, height, width] = self.shape;
+        writeln!(fmt, "shape:          {frames}x{height}x{width}")?;
+        writeln!(fmt, "decompressed:   {} bytes", self.decompressed_bytes)
+    }
+}
+
+/// Report of the `info` subcommand, which depends on the format of the
+/// compressed data
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "format", rename_all = "kebab-case")]
+pub enum InfoReport {
+    /// EBCC container
+    Container {
+        /// Number of frames, including deleted frames
+        frames: usize,
+        /// Number of frames that have not been deleted
+        live_frames: usize,
+        /// Shape `[height, width]` of every frame
+        frame_shape: [usize; 2],
+        /// Number of bytes that no live frame refers to
+        orphaned_bytes: u64,
+    },
+    /// EBCC payload with a header
+    Payload {
+        /// Size of the compressed data, in bytes
+        size: usize,
+        /// Version of the header format
+        version: u32,
+        /// Element data type
+        dtype: String,
+        /// Shape of the compressed data
+        shape: [usize; 3],
+        /// Fingerprint of the compression configuration, as hex, if recorded
+        fingerprint: Option<String>,
+        /// Size of the payload after the header, in bytes
+        payload_bytes: u64,
+        /// Checksum of the payload, as hex
+        checksum: String,
+        /// Algorithm of the payload checksum, e.g. `crc32`
+        checksum_algorithm: String,
+        /// CRC-32 checksum of the decompressed data, as hex, if recorded
+        data_checksum: Option<String>,
+        /// Percentiles of the data, if a quantile sketch was recorded
+        percentiles: Option<Percentiles>,
+    },
+    /// EBCC frame stream
+    Stream {
+        /// Size of the compressed data, in bytes
+        size: usize,
+    },
+    /// EBCC adaptive tiles
+    Tiles {
+        /// Size of the compressed data, in bytes
+        size: usize,
+    },
+    /// Legacy EBCC payload without a header
+    Legacy {
+        /// Size of the compressed data, in bytes
+        size: usize,
+    },
+}
+
+/// Approximate percentiles of the data of an EBCC payload
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Percentiles {
+    /// First percentile
+    pub p1: f32,
+    /// Median
+    pub p50: f32,
+    /// 99th percentile
+    pub p99: f32,
+}
+
+impl fmt::Display for InfoReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Container {
+                frames,
+                live_frames,
+                frame_shape: [height, width],
+                orphaned_bytes,
+            } => {
+                writeln!(fmt, "format:         EBCC container")?;
+                writeln!(fmt, "frames:         {frames}")?;
+                writeln!(fmt, "live frames:    {live_frames}")?;
+                writeln!(fmt, "frame shape:    {height}x{width}")?;
+                writeln!(fmt, "orphaned bytes: {orphaned_bytes}")
+            }
+            Self::Payload {
+                size,
+                version,
+                dtype,
+                shape: [frames, height, width],
+                fingerprint,
+                payload_bytes,
+                checksum,
+                checksum_algorithm,
+                data_checksum,
+                percentiles,
+            } => {
+                writeln!(fmt, "size:           {size} bytes")?;
+                writeln!(fmt, "format:         EBCC payload v{version}")?;
+                writeln!(fmt, "dtype:          {dtype}")?;
+                writeln!(fmt, "shape:          {frames}x{height}x{width}")?;
+                if let Some(fingerprint) = fingerprint {
+                    writeln!(fmt, "fingerprint:    {fingerprint}")?;
+                }
+                writeln!(fmt, "payload:        {payload_bytes} bytes")?;
+                writeln!(fmt, "checksum:       {checksum} ({checksum_algorithm})")?;
+                if let Some(data_checksum) = data_checksum {
+                    writeln!(fmt, "data checksum:  {data_checksum}")?;
+                }
+                if let Some(Percentiles { p1, p50, p99 }) = percentiles {
+                    writeln!(fmt, "percentiles:    p1 {p1} p50 {p50} p99 {p99}")?;
+                }
+                Ok(())
+            }
+            Self::Stream { size } => {
+                writeln!(fmt, "size:           {size} bytes")?;
+                writeln!(fmt, "format:         EBCC frame stream")
+            }
+            Self::Tiles { size } => {
+                writeln!(fmt, "size:           {size} bytes")?;
+                writeln!(fmt, "format:         EBCC adaptive tiles")
+            }
+            Self::Legacy { size } => {
+                writeln!(fmt, "size:           {size} bytes")?;
+                writeln!(fmt, "format:         legacy EBCC payload without header")
+            }
+        }
+    }
+}
+
+/// Report of the `verify` subcommand
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    /// Whether all frames that have not been deleted are intact
+    pub ok: bool,
+    /// Number of frames, including deleted frames
+    pub frames: usize,
+    /// Number of deleted frames
+    pub deleted_frames: usize,
+    /// Number of record bytes whose checksums were verified
+    pub verified_bytes: u64,
+    /// Indices of the corrupted frames, in ascending order
+    pub corrupted_frames: Vec<usize>,
+}
+
+impl From<IntegrityReport> for VerifyReport {
+    fn from(report: IntegrityReport) -> Self {
+        Self {
+            ok: report.is_ok(),
+            frames: report.frames,
+            deleted_frames: report.deleted_frames,
+            verified_bytes: report.verified_bytes,
+            corrupted_frames: report.corrupted_frames,
+        }
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "frames:         {}", self.frames)?;
+        writeln!(fmt, "deleted frames: {}", self.deleted_frames)?;
+        writeln!(fmt, "verified bytes: {}", self.verified_bytes)?;
+        if self.ok {
+            writeln!(fmt, "status:         ok")
+        } else {
+            writeln!(
+                fmt,
+                "status:         corrupted frames {:?}",
+                self.corrupted_frames
+            )
+        }
+    }
+}
+
+/// Report of the `run` subcommand
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunReport {
+    /// Compressed file of every input, in the order of the job's inputs
+    pub outputs: Vec<RunOutput>,
+    /// Total size of the original data, in bytes
+    pub original_bytes: usize,
+    /// Total size of the compressed files, in bytes
+    pub compressed_bytes: u64,
+    /// Achieved overall compression ratio
+    pub ratio: f64,
+}
+
+/// Compressed file of one input of a [`RunReport`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunOutput {
+    /// Path of the input file
+    pub input: PathBuf,
+    /// Path of the compressed file
+    pub output: PathBuf,
+    /// Variable of the data
+    pub variable: String,
+    /// Size of the original data, in bytes
+    pub original_bytes: usize,
+    /// Size of the compressed file, in bytes
+    pub compressed_bytes: u64,
+    /// Achieved compression ratio
+    pub ratio: f64,
+}
+
+impl From<EBCCJobOutput> for RunOutput {
+    fn from(output: EBCCJobOutput) -> Self {
+        Self {
+            ratio: file_compression_ratio(output.original_bytes, output.compressed_bytes),
+            input: output.input,
+            output: output.output,
+            variable: output.variable,
+            original_bytes: output.original_bytes,
+            compressed_bytes: output.compressed_bytes,
+        }
+    }
+}
+
+impl From<EBCCJobReport> for RunReport {
+    fn from(report: EBCCJobReport) -> Self {
+        let outputs = report
+            .outputs
+            .into_iter()
+            .map(RunOutput::from)
+            .collect::<Vec<_>>();
+        let original_bytes = outputs.iter().map(|output| output.original_bytes).sum();
+        let compressed_bytes = outputs.iter().map(|output| output.compressed_bytes).sum();
+
+        Self {
+            ratio: file_compression_ratio(original_bytes, compressed_bytes),
+            outputs,
+            original_bytes,
+            compressed_bytes,
+        }
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for output in &self.outputs {
+            writeln!(
+                fmt,
+                "{} -> {} ({}): ratio {:.2}",
+                output.input.display(),
+                output.output.display(),
+                output.variable,
+                output.ratio,
+            )?;
+        }
+        writeln!(fmt, "inputs:         {}", self.outputs.len())?;
+        writeln!(fmt, "original:       {} bytes", self.original_bytes)?;
+        writeln!(fmt, "compressed:     {} bytes", self.compressed_bytes)?;
+        writeln!(fmt, "ratio:          {:.2}", self.ratio)
+    }
+}
+
+/// Compression ratio of a compressed file, whose size may exceed the address
+/// space
+fn file_compression_ratio(original_bytes: usize, compressed_bytes: u64) -> f64 {
+    compression_ratio(
+        original_bytes,
+        usize::try_from(compressed_bytes).unwrap_or(usize::MAX),
+    )
+}
+
+/// Report of a failed subcommand
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorReport {
+    /// The error
+    pub error: ErrorDetails,
+}
+
+/// Category and message of an [`EBCCError`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorDetails {
+    /// Category of the error
+    pub kind: &'static str,
+    /// Human-readable message of the error
+    pub message: String,
+}
+
+impl From<&EBCCError> for ErrorReport {
+    fn from(err: &EBCCError) -> Self {
+        let kind = match err.kind() {
+            EBCCErrorKind::InvalidInput => "invalid-input",
+            EBCCErrorKind::InvalidConfig => "invalid-config",
+            EBCCErrorKind::Compression => "compression",
+            EBCCErrorKind::Decompression => "decompression",
+            EBCCErrorKind::Io => "io",
+            EBCCErrorKind::TimedOut => "timed-out",
+            EBCCErrorKind::AccessDenied => "access-denied",
+            EBCCErrorKind::OutOfMemory => "out-of-memory",
+            _ => "other",
+        };
+
+        Self {
+            error: ErrorDetails {
+                kind,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+/// Write the `report` as human-readable text, or as JSON if `json` is set.
+pub fn write_report<R: fmt::Display + Serialize>(
+    mut writer: impl Write,
+    report: &R,
+    json: bool,
+) -> EBCCResult<()> {
+    if json {
+        write_json(writer, report)
+    } else {
+        write!(writer, "{report}")?;
+        Ok(())
+    }
+}
+
+/// Write the `value` as pretty-printed JSON, followed by a newline.
+pub fn write_json(mut writer: impl Write, value: &impl Serialize) -> EBCCResult<()> {
+    serde_json::to_writer_pretty(&mut writer, value).map_err(|err| EBCCError::Io(err.into()))?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn to_json(value: &impl Serialize) -> EBCCResult<Value> {
+        let mut json = Vec::new();
+        write_json(&mut json, value)?;
+        serde_json::from_slice(&json).map_err(|err| EBCCError::Io(err.into()))
+    }
+
+    #[test]
+    fn test_json_schema() -> EBCCResult<()> {
+        let compress = to_json(&CompressReport {
+            shape: [1, 32, 48],
+            bound: BenchBound::Absolute(0.5),
+            original_bytes: 6144,
+            compressed_bytes: 512,
+            ratio: 12.0,
+            max_error: 0.25,
+            rmse: 0.125,
+            ssim: 0.5,
+        })?;
+        assert_eq!(compress.pointer("/shape"), Some(&json!([1, 32, 48])));
+        assert_eq!(
+            compress.pointer("/bound"),
+            Some(&json!({ "absolute": 0.5 }))
+        );
+        assert_eq!(compress.pointer("/compressed_bytes"), Some(&json!(512)));
+
+        let info = to_json(&InfoReport::Payload {
+            size: 100,
+            version: 1,
+            dtype: String::from("f32"),
+            shape: [1, 32, 48],
+            fingerprint: Some(format!("{:016x}", u64::MAX)),
+            payload_bytes: 40,
+            checksum: format!("{:08x}", 42),
+            checksum_algorithm: String::from("crc32"),
+            data_checksum: None,
+            percentiles: None,
+        })?;
+        assert_eq!(info.pointer("/format"), Some(&json!("payload")));
+        assert_eq!(
+            info.pointer("/fingerprint"),
+            Some(&json!("ffffffffffffffff"))
+        );
+        assert_eq!(info.pointer("/data_checksum"), Some(&Value::Null));
+        assert_eq!(
+            to_json(&InfoReport::Legacy { size: 3 })?.pointer("/format"),
+            Some(&json!("legacy"))
+        );
+
+        let verify = to_json(&VerifyReport {
+            ok: false,
+            frames: 3,
+            deleted_frames: 0,
+            verified_bytes: 64,
+            corrupted_frames: vec![1],
+        })?;
+        assert_eq!(verify.pointer("/ok"), Some(&json!(false)));
+        assert_eq!(verify.pointer("/corrupted_frames"), Some(&json!([1])));
+
+        let run = to_json(&RunReport {
+            outputs: vec![RunOutput {
+                input: PathBuf::from("t2m.npy"),
+                output: PathBuf::from("out/t2m.ebcc"),
+                variable: String::from("temperature"),
+                original_bytes: 64,
+                compressed_bytes: 16,
+                ratio: 4.0,
+            }],
+            original_bytes: 64,
+            compressed_bytes: 16,
+            ratio: 4.0,
+        })?;
+        assert_eq!(
+            run.pointer("/outputs/0/output"),
+            Some(&json!("out/t2m.ebcc"))
+        );
+        assert_eq!(run.pointer("/compressed_bytes"), Some(&json!(16)));
+
+        let error = to_json(&ErrorReport::from(&EBCCError::EmptyInput))?;
+        assert_eq!(error.pointer("/error/kind"), Some(&json!("invalid-input")));
+
+        let mut text = Vec::new();
+        write_report(&mut text, &InfoReport::Stream { size: 3 }, false)?;
+        assert!(String::from_utf8(text)
+            .unwrap_or_default()
+            .contains("EBCC frame stream"));
+
+        Ok(())
+    }
+}
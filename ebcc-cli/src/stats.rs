@@ -0,0 +1,58 @@
+//! Error statistics of decompressed data.
+
+use ebcc::EbccDim;
+use ndarray::ArrayView;
+
+/// Pointwise error statistics of decompressed data against the original.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorStats {
+    /// Maximum absolute error
+    pub max_error: f64,
+    /// Root mean squared error
+    pub rmse: f64,
+}
+
+impl ErrorStats {
+    /// Compute the error statistics of the `decompressed` data against the
+    /// `original` data, which must have the same shape.
+    #[expect(clippy::cast_precision_loss)]
+    pub fn new(original: ArrayView<f32, EbccDim>, decompressed: ArrayView<f32, EbccDim>) -> Self {
+        let (max_error, squared_error) = original.iter().zip(decompressed.iter()).fold(
+            (0.0_f64, 0.0_f64),
+            |(max_error, squared_error), (&x, &y)| {
+                let error = (f64::from(x) - f64::from(y)).abs();
+                (max_error.max(error), error.mul_add(error, squared_error))
+            },
+        );
+
+        Self {
+            max_error,
+            rmse: (squared_error / original.len().max(1) as f64).sqrt(),
+        }
+    }
+}
+
+/// Compression ratio of `original_bytes` that were compressed into
+/// `compressed_bytes`.
+#[expect(clippy::cast_precision_loss)]
+pub fn compression_ratio(original_bytes: usize, compressed_bytes: usize) -> f64 {
+    original_bytes as f64 / compressed_bytes as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+
+    #[test]
+    fn test_error_stats() {
+        let original = Array::from_elem((1, 2, 2), 1.0_f32);
+        let decompressed = Array::from_shape_vec((1, 2, 2), vec![3.0, 1.0, 1.0, 1.0])
+            .unwrap_or_else(|_| original.clone());
+
+        let stats = ErrorStats::new(original.view(), decompressed.view());
+        assert!((stats.max_error - 2.0).abs() < 1e-12);
+        assert!((stats.rmse - 1.0).abs() < 1e-12);
+    }
+}
@@ -7,18 +7,199 @@ use std::path::{Path, PathBuf};
 fn main() {
     println!("cargo::rerun-if-changed=build.rs");
     println!("cargo::rerun-if-changed=EBCC");
+    println!("cargo::rerun-if-changed=cmake");
 
     let out_dir = env::var("OUT_DIR")
         .map(PathBuf::from)
         .expect("missing OUT_DIR");
 
     let target = env::var("TARGET").expect("missing TARGET");
-    let no_threads = target == "wasm32-unknown-unknown" || target.starts_with("wasm32-wasi");
+
+    // The version of the vendored EBCC library is the build metadata of the
+    //  crate version, e.g. 0.3.0+ebcc.0.1.4
+    let version = env::var("CARGO_PKG_VERSION").expect("missing CARGO_PKG_VERSION");
+    let ebcc_version = version
+        .split_once("+ebcc.")
+        .map_or("unknown", |(_, ebcc_version)| ebcc_version);
+    println!("cargo::rustc-env=EBCC_SYS_EBCC_VERSION={ebcc_version}");
+
+    let std = env::var_os("CARGO_FEATURE_STD").is_some();
+    let encode = env::var_os("CARGO_FEATURE_ENCODE").is_some();
+    let decode = env::var_os("CARGO_FEATURE_DECODE").is_some();
+
+    // OpenJPEG and zstd are built without thread support on targets without
+    //  threads and with the single-threaded feature
+    let no_threads = target == "wasm32-unknown-unknown"
+        || target.starts_with("wasm32-wasi")
+        || env::var_os("CARGO_FEATURE_SINGLE_THREADED").is_some();
+
+    println!("cargo::rerun-if-env-changed=EBCC_SYS_USE_SYSTEM_LIBS");
+    let system_libs = env::var_os("CARGO_FEATURE_SYSTEM_LIBS").is_some()
+        || env::var("EBCC_SYS_USE_SYSTEM_LIBS").is_ok_and(|var| !matches!(&*var, "" | "0"));
+
+    println!("cargo::rerun-if-changed=include");
+    let include_dir = env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .expect("missing CARGO_MANIFEST_DIR")
+        .join("include");
+
+    let rust_alloc = env::var_os("CARGO_FEATURE_RUST_ALLOC").is_some();
+
+    println!("cargo::rustc-check-cfg=cfg(ebcc_sys_threads_hook)");
+    println!("cargo::rustc-check-cfg=cfg(ebcc_sys_errors_hook)");
 
     let ebcc_src = Path::new("EBCC").join("src");
 
-    // Build the static library using CMake from src/ directory
-    let mut config = cmake::Config::new(&ebcc_src);
+    println!("cargo::rerun-if-env-changed=EBCC_LIB_DIR");
+    println!("cargo::rerun-if-env-changed=EBCC_INCLUDE_DIR");
+    println!("cargo::rerun-if-env-changed=EBCC_STATIC");
+    let prebuilt = env::var_os("EBCC_LIB_DIR");
+
+    // Libraries that are not built here would still allocate with malloc, so
+    //  their buffers would be released by the wrong allocator
+    assert!(
+        !rust_alloc || (prebuilt.is_none() && !system_libs),
+        "the rust-alloc feature cannot be combined with a prebuilt EBCC library (EBCC_LIB_DIR) \
+         or with system libraries (the system-libs feature or EBCC_SYS_USE_SYSTEM_LIBS)"
+    );
+
+    let ebcc_include = if let Some(lib_dir) = prebuilt {
+        // the thread support of a prebuilt OpenJPEG library is unknown
+        println!("cargo::rustc-env=EBCC_SYS_OPENJPEG_THREADS=1");
+        link_prebuilt_ebcc(Path::new(&lib_dir), system_libs);
+        env::var_os("EBCC_INCLUDE_DIR").map_or_else(|| ebcc_src.clone(), PathBuf::from)
+    } else {
+        // the vendored OpenJPEG library with thread support reads its number
+        //  of threads through the getenv hook, which needs thread-locals
+        let threads_hook = std && !no_threads && !system_libs;
+        let headers = hook_headers(&include_dir, rust_alloc, threads_hook);
+        build_ebcc(
+            &ebcc_src,
+            &target,
+            !(encode && decode),
+            no_threads,
+            system_libs,
+            &headers,
+        );
+        // system OpenJPEG libraries may have been built with thread support
+        let threads = !no_threads || system_libs;
+        println!(
+            "cargo::rustc-env=EBCC_SYS_OPENJPEG_THREADS={}",
+            u8::from(threads)
+        );
+        ebcc_src
+    };
+
+    let mut bindings = bindgen::Builder::default()
+        .header(format!("{}", ebcc_include.join("ebcc_codec.h").display()))
+        .clang_arg(format!("-I{}", ebcc_include.display()));
+    // Only generate bindings for the enabled encode and decode functions
+    if encode {
+        bindings = bindings
+            .allowlist_function("ebcc_encode")
+            .allowlist_function("ebcc_encode_chunking")
+            .allowlist_function("ebcc_encode_chunking_compat");
+    }
+    if decode {
+        bindings = bindings
+            .allowlist_function("ebcc_decode")
+            .allowlist_function("ebcc_decode_chunking");
+    }
+    let bindings = bindings
+        // Tell bindgen to generate bindings for these types and functions
+        .allowlist_type("codec_config_t")
+        .allowlist_type("residual_t")
+        .allowlist_function("free_buffer")
+        .allowlist_var("EBCC_CHUNKING_HEADER_MAGIC")
+        .allowlist_var("EBCC_CHUNKING_HEADER_VERSION")
+        .allowlist_var("EBCC_MAX_INTERNAL_IMAGE_DIM")
+        .allowlist_var("EBCC_MIN_INTERNAL_IMAGE_DIM")
+        .allowlist_var("NDIMS")
+        // Use constified enum module for better enum handling
+        .constified_enum_module("residual_t")
+        // Generate comments from C headers
+        .generate_comments(true)
+        // Use core instead of std for no_std compatibility
+        .use_core()
+        // Generate layout tests
+        .layout_tests(true)
+        // Don't generate recursively for system headers
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+        // MSRV 1.82
+        .rust_target(match bindgen::RustTarget::stable(82, 0) {
+            Ok(target) => target,
+            #[expect(clippy::panic)]
+            Err(err) => panic!("{err}"),
+        })
+        .generate()
+        .expect("Unable to generate bindings");
+
+    bindings
+        .write_to_file(out_dir.join("bindings.rs"))
+        .expect("Couldn't write bindings!");
+}
+
+/// Select the hook headers in the `include_dir`, which are force-included
+/// into all C sources that are built here, and enable the `cfg`s of the
+/// hooks that they install.
+///
+/// With `rust_alloc`, the allocations are redirected to the hooks in
+/// `src/rust_alloc.rs`. With the `threads_hook`, `OpenJPEG` reads its number
+/// of threads through the `getenv` hook in `src/threads.rs`. The messages
+/// that the C sources print to stderr are always reported through the hook
+/// in `src/errors.rs`.
+fn hook_headers(include_dir: &Path, rust_alloc: bool, threads_hook: bool) -> Vec<PathBuf> {
+    let mut headers = Vec::new();
+
+    if rust_alloc {
+        headers.push(include_dir.join("ebcc_sys_alloc.h"));
+    }
+
+    if threads_hook {
+        headers.push(include_dir.join("ebcc_sys_hooks.h"));
+        println!("cargo::rustc-cfg=ebcc_sys_threads_hook");
+    }
+
+    headers.push(include_dir.join("ebcc_sys_errors.h"));
+    println!("cargo::rustc-cfg=ebcc_sys_errors_hook");
+
+    headers
+}
+
+/// Build the vendored EBCC library, and `OpenJPEG` and zstd unless the
+/// `system_libs` are used, with `CMake` and link against them statically.
+///
+/// EBCC's own `CMake` project always builds the vendored `OpenJPEG` and zstd
+/// copies, so the `system_libs` are used by building only EBCC's own sources
+/// with the `CMake` project in the `cmake` directory instead.
+///
+/// If `encode_or_decode_only` is set, the unused half of the libraries is
+/// discarded when linking. If `no_threads` is set, `OpenJPEG` and zstd are
+/// built without thread support. The hook `headers`, e.g. of the allocator,
+/// are force-included into all C sources.
+fn build_ebcc(
+    ebcc_src: &Path,
+    target: &str,
+    encode_or_decode_only: bool,
+    no_threads: bool,
+    system_libs: bool,
+    headers: &[PathBuf],
+) {
+    // Build the static library using CMake from src/ directory, or only
+    //  EBCC's own sources from there if the system libraries are used
+    let mut config = if system_libs {
+        let mut config = cmake::Config::new("cmake");
+        config.define(
+            "EBCC_SOURCE_DIR",
+            env::var("CARGO_MANIFEST_DIR")
+                .map(PathBuf::from)
+                .expect("missing CARGO_MANIFEST_DIR")
+                .join(ebcc_src),
+        );
+        config
+    } else {
+        cmake::Config::new(ebcc_src)
+    };
     if let Ok(ar) = env::var("AR") {
         config.define("CMAKE_AR", ar);
     }
@@ -38,15 +219,42 @@ fn main() {
         config.define("CMAKE_STRIP", strip);
     }
     // < openjp2 config
-    if no_threads {
+    if no_threads && !system_libs {
         config.define("OPJ_USE_THREAD", "OFF");
     }
     // > openjp2 config
     // < zstd config
-    if no_threads {
+    if no_threads && !system_libs {
         config.define("ZSTD_MULTITHREAD_SUPPORT", "OFF");
     }
     // > zstd config
+    // < encode / decode config
+    if encode_or_decode_only && !target.contains("msvc") {
+        // place every function into its own section, such that the linker
+        //  discards the unused encoder or decoder of EBCC, OpenJPEG, and zstd
+        config.cflag("-ffunction-sections");
+        config.cflag("-fdata-sections");
+    }
+    // > encode / decode config
+    // < system libraries config
+    if system_libs {
+        // compile EBCC against the headers of the system libraries, the
+        //  cmake project then skips the vendored copies
+        for include in find_system_libs() {
+            config.cflag(format!("-I{}", include.display()));
+        }
+        config.define("EBCC_USE_SYSTEM_LIBS", "ON");
+    }
+    // > system libraries config
+    // < hooks config
+    for header in headers {
+        if target.contains("msvc") {
+            config.cflag(format!("/FI{}", header.display()));
+        } else {
+            config.cflag(format!("-include {}", header.display()));
+        }
+    }
+    // > hooks config
     let ebcc_out = config.build();
 
     // Tell cargo to look for libraries in the CMake build directory
@@ -59,48 +267,59 @@ fn main() {
         ebcc_out.join("lib64").display()
     );
 
-    // Link against the static EBCC library and its dependencies
+    // Link against the static EBCC library and its vendored dependencies,
+    //  pkg-config has already linked the system libraries
     println!("cargo::rustc-link-lib=static=ebcc");
-    println!("cargo::rustc-link-lib=static=openjp2");
-    println!("cargo::rustc-link-lib=static=zstd");
+    if !system_libs {
+        println!("cargo::rustc-link-lib=static=openjp2");
+        println!("cargo::rustc-link-lib=static=zstd");
+    }
+}
 
-    let bindings = bindgen::Builder::default()
-        .header(format!("{}", ebcc_src.join("ebcc_codec.h").display()))
-        .clang_arg(format!("-I{}", ebcc_src.display()))
-        // Tell bindgen to generate bindings for these types and functions
-        .allowlist_type("codec_config_t")
-        .allowlist_type("residual_t")
-        .allowlist_function("ebcc_encode")
-        .allowlist_function("ebcc_decode")
-        .allowlist_function("ebcc_encode_chunking")
-        .allowlist_function("ebcc_encode_chunking_compat")
-        .allowlist_function("ebcc_decode_chunking")
-        .allowlist_function("free_buffer")
-        .allowlist_var("EBCC_CHUNKING_HEADER_MAGIC")
-        .allowlist_var("EBCC_CHUNKING_HEADER_VERSION")
-        .allowlist_var("EBCC_MAX_INTERNAL_IMAGE_DIM")
-        .allowlist_var("EBCC_MIN_INTERNAL_IMAGE_DIM")
-        .allowlist_var("NDIMS")
-        // Use constified enum module for better enum handling
-        .constified_enum_module("residual_t")
-        // Generate comments from C headers
-        .generate_comments(true)
-        // Use core instead of std for no_std compatibility
-        .use_core()
-        // Generate layout tests
-        .layout_tests(true)
-        // Don't generate recursively for system headers
-        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
-        // MSRV 1.82
-        .rust_target(match bindgen::RustTarget::stable(82, 0) {
-            Ok(target) => target,
-            #[expect(clippy::panic)]
-            Err(err) => panic!("{err}"),
-        })
-        .generate()
-        .expect("Unable to generate bindings");
+/// Link against the prebuilt EBCC library in `lib_dir` instead of building
+/// it with `CMake`, e.g. where EBCC is provided as an HPC environment module.
+///
+/// A shared library is linked dynamically and brings its own dependencies.
+/// A static library, which is used if there is no shared library or if
+/// `EBCC_STATIC=1` is set, is linked together with `OpenJPEG` and zstd,
+/// either from the `system_libs` or from the same `lib_dir`.
+fn link_prebuilt_ebcc(lib_dir: &Path, system_libs: bool) {
+    println!("cargo::rustc-link-search=native={}", lib_dir.display());
 
-    bindings
-        .write_to_file(out_dir.join("bindings.rs"))
-        .expect("Couldn't write bindings!");
+    let link_static = env::var("EBCC_STATIC").map_or_else(
+        |_| {
+            !["libebcc.so", "libebcc.dylib", "ebcc.dll"]
+                .iter()
+                .any(|shared| lib_dir.join(shared).exists())
+        },
+        |var| !matches!(&*var, "" | "0"),
+    );
+
+    if !link_static {
+        println!("cargo::rustc-link-lib=dylib=ebcc");
+        return;
+    }
+
+    println!("cargo::rustc-link-lib=static=ebcc");
+    if system_libs {
+        find_system_libs();
+    } else {
+        println!("cargo::rustc-link-lib=static=openjp2");
+        println!("cargo::rustc-link-lib=static=zstd");
+    }
+}
+
+/// Find the system `libopenjp2` and `libzstd` with pkg-config, which prints
+/// their link flags, and return their include paths.
+fn find_system_libs() -> Vec<PathBuf> {
+    let mut include_paths = Vec::new();
+    for name in ["libopenjp2", "libzstd"] {
+        let library = match pkg_config::Config::new().probe(name) {
+            Ok(library) => library,
+            #[expect(clippy::panic)]
+            Err(err) => panic!("cannot find the system {name}: {err}"),
+        };
+        include_paths.extend(library.include_paths);
+    }
+    include_paths
 }
@@ -0,0 +1,34 @@
+/*
+ * Routes the allocations of EBCC, OpenJPEG, and zstd through the Rust global
+ * allocator. This header is force-included into every C source that is built
+ * with the `rust-alloc` feature of the ebcc-sys crate, which defines the
+ * ebcc_sys_* functions.
+ */
+
+#ifndef EBCC_SYS_ALLOC_H
+#define EBCC_SYS_ALLOC_H
+
+/* declare the standard allocation functions before they are redirected */
+#include <stddef.h>
+#include <stdlib.h>
+#ifdef _MSC_VER
+#include <malloc.h>
+#endif
+
+void *ebcc_sys_malloc(size_t size);
+void *ebcc_sys_calloc(size_t count, size_t size);
+void *ebcc_sys_realloc(void *ptr, size_t size);
+void ebcc_sys_free(void *ptr);
+void *ebcc_sys_aligned_alloc(size_t alignment, size_t size);
+int ebcc_sys_posix_memalign(void **ptr, size_t alignment, size_t size);
+
+#define malloc(size) ebcc_sys_malloc(size)
+#define calloc(count, size) ebcc_sys_calloc(count, size)
+#define realloc(ptr, size) ebcc_sys_realloc(ptr, size)
+#define free(ptr) ebcc_sys_free(ptr)
+#define aligned_alloc(alignment, size) ebcc_sys_aligned_alloc(alignment, size)
+#define posix_memalign(ptr, alignment, size) ebcc_sys_posix_memalign(ptr, alignment, size)
+#define _aligned_malloc(size, alignment) ebcc_sys_aligned_alloc(alignment, size)
+#define _aligned_free(ptr) ebcc_sys_free(ptr)
+
+#endif /* EBCC_SYS_ALLOC_H */
@@ -0,0 +1,64 @@
+/*
+ * Reports the error messages that the vendored EBCC, OpenJPEG, and zstd
+ * libraries print to stderr to the ebcc-sys crate, which defines the
+ * ebcc_sys_report_error function. This header is force-included into every
+ * C source that is built by the ebcc-sys crate.
+ */
+
+#ifndef EBCC_SYS_ERRORS_H
+#define EBCC_SYS_ERRORS_H
+
+/* declare the standard functions before they are redirected */
+#include <stdarg.h>
+#include <stdio.h>
+
+/* maximum length of a reported message, longer messages are truncated */
+#define EBCC_SYS_ERROR_MESSAGE_SIZE 512
+
+void ebcc_sys_report_error(const char *message);
+
+/*
+ * The wrappers are defined before the standard functions are redirected,
+ * such that they still print the message to stderr
+ */
+
+static inline int ebcc_sys_vfprintf(FILE *stream, const char *format, va_list args) {
+    if (stream == stderr) {
+        char message[EBCC_SYS_ERROR_MESSAGE_SIZE];
+        va_list report_args;
+        va_copy(report_args, args);
+        if (vsnprintf(message, sizeof(message), format, report_args) >= 0) {
+            ebcc_sys_report_error(message);
+        }
+        va_end(report_args);
+    }
+    return vfprintf(stream, format, args);
+}
+
+static inline int ebcc_sys_fprintf(FILE *stream, const char *format, ...) {
+    va_list args;
+    int written;
+    va_start(args, format);
+    written = ebcc_sys_vfprintf(stream, format, args);
+    va_end(args);
+    return written;
+}
+
+static inline int ebcc_sys_fputs(const char *message, FILE *stream) {
+    if (stream == stderr) {
+        ebcc_sys_report_error(message);
+    }
+    return fputs(message, stream);
+}
+
+static inline void ebcc_sys_perror(const char *message) {
+    ebcc_sys_report_error(message);
+    perror(message);
+}
+
+#define vfprintf ebcc_sys_vfprintf
+#define fprintf ebcc_sys_fprintf
+#define fputs ebcc_sys_fputs
+#define perror ebcc_sys_perror
+
+#endif /* EBCC_SYS_ERRORS_H */
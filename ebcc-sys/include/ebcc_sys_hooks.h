@@ -0,0 +1,22 @@
+/*
+ * Hooks of the ebcc-sys crate into the vendored EBCC, OpenJPEG, and zstd
+ * libraries. This header is force-included into every C source that is
+ * built by the ebcc-sys crate, which defines the ebcc_sys_* functions.
+ */
+
+#ifndef EBCC_SYS_HOOKS_H
+#define EBCC_SYS_HOOKS_H
+
+/* declare the standard functions before they are redirected */
+#include <stdlib.h>
+
+/*
+ * OpenJPEG reads its default number of threads from the OPJ_NUM_THREADS
+ * environment variable whenever it creates a codec, which the hook
+ * overrides while a deterministic encode is running
+ */
+char *ebcc_sys_getenv(const char *name);
+
+#define getenv(name) ebcc_sys_getenv(name)
+
+#endif /* EBCC_SYS_HOOKS_H */
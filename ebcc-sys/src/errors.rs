@@ -0,0 +1,59 @@
+//! Error message hook, which the vendored C libraries call whenever they
+//! print a message to stderr, such that the `OpenJPEG` and zstd errors behind
+//! a failed EBCC call can be reported to Rust.
+
+use core::ffi::{c_char, CStr};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Observer of the error messages, stored as a type-erased `fn(&CStr)`
+/// pointer that is null if no observer is set
+static OBSERVER: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Set the `observer` of the error messages, see
+/// [`crate::set_error_observer`].
+pub fn set_error_observer(observer: fn(&CStr)) {
+    OBSERVER.store(observer as *mut (), Ordering::Release);
+}
+
+/// Hook for messages that are printed to stderr.
+///
+/// The `message` is reported to the observer, if one is set.
+///
+/// # Safety
+///
+/// The `message` must be a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ebcc_sys_report_error(message: *const c_char) {
+    let observer = OBSERVER.load(Ordering::Acquire);
+    if observer.is_null() || message.is_null() {
+        return;
+    }
+
+    // Safety: non-null pointers are only stored from fn(&CStr)
+    let observer = unsafe { core::mem::transmute::<*mut (), fn(&CStr)>(observer) };
+    // Safety: message is a valid null-terminated string
+    observer(unsafe { CStr::from_ptr(message) });
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::AtomicBool;
+
+    use super::*;
+
+    #[test]
+    fn test_error_hook() {
+        static REPORTED: AtomicBool = AtomicBool::new(false);
+
+        crate::set_error_observer(|message| {
+            if message == c"opj_decode: failed to decode tile" {
+                REPORTED.store(true, Ordering::Release);
+            }
+        });
+
+        // Safety: the message is null-terminated
+        unsafe { ebcc_sys_report_error(c"opj_decode: failed to decode tile".as_ptr()) };
+        assert!(REPORTED.load(Ordering::Acquire));
+    }
+}
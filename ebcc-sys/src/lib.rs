@@ -18,11 +18,121 @@
 //!
 //! Low-level bindigs to the [EBCC] compressor.
 //!
+//! The `encode` and `decode` features, which are both enabled by default,
+//! select which of EBCC's encode and decode functions are bound. If only one
+//! of them is enabled, the unused half of EBCC, `OpenJPEG`, and zstd is
+//! discarded when linking, which shrinks, e.g., decode-only WASM binaries.
+//!
+//! By default, the vendored `OpenJPEG` and zstd libraries are built with
+//! `CMake` and linked statically. The `system-libs` feature, or setting the
+//! `EBCC_SYS_USE_SYSTEM_LIBS=1` environment variable at build time, instead
+//! finds the system `libopenjp2` and `libzstd` with `pkg-config` and links
+//! against them, which speeds up clean builds and follows the policies of
+//! distributions that forbid vendored libraries.
+//!
+//! Setting the `EBCC_LIB_DIR` environment variable at build time skips
+//! building EBCC altogether and links against the prebuilt `libebcc` in that
+//! directory, e.g. where EBCC is provided as an HPC environment module. A
+//! shared library is preferred, `EBCC_STATIC=1` selects a static library,
+//! which is linked together with `OpenJPEG` and zstd. The bindings are
+//! generated from the `ebcc_codec.h` header in `EBCC_INCLUDE_DIR`, or from
+//! the vendored header if it is not set.
+//!
+//! With the `rust-alloc` feature, the vendored EBCC, `OpenJPEG`, and zstd
+//! libraries allocate all memory, including the buffers that are returned
+//! to Rust and released with [`free_buffer`], through the Rust global
+//! allocator instead of `malloc`. An application with a custom global
+//! allocator, e.g. jemalloc with strict accounting, then accounts for all
+//! memory, and Rust and C never free each other's allocations. The feature
+//! cannot be combined with a prebuilt `libebcc` or with the `system-libs`
+//! feature, whose libraries would still allocate with `malloc`, and the build
+//! fails instead. An observer that is set with `set_allocation_observer` is
+//! notified of every allocation and deallocation, e.g. to account for the
+//! memory of individual EBCC calls.
+//!
+//! A deterministic encode runs within [`with_single_openjpeg_thread`], which
+//! limits the vendored `OpenJPEG` library to a single thread by hooking its
+//! lookup of the `OPJ_NUM_THREADS` environment variable, without modifying
+//! the environment.
+//!
+//! The EBCC, `OpenJPEG`, and zstd libraries that are built by this crate
+//! report the error messages that they print to stderr to the observer that
+//! is set with [`set_error_observer`], such that the `OpenJPEG` and zstd
+//! errors behind a failed EBCC call can be reported.
+//!
+//! Besides EBCC's encode, decode, and chunking functions, the [`openjpeg`]
+//! and [`zstd`] modules bind the version queries of the linked `OpenJPEG`
+//! and zstd libraries, such that system or prebuilt libraries can be
+//! reported at runtime.
+//!
 //! [EBCC]: https://github.com/spcl/EBCC
 
+#![no_std]
 #![allow(missing_docs)] // bindgen
 
-use std::ffi::c_uint;
+#[cfg(feature = "rust-alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::ffi::{c_uint, CStr};
+
+/// Version of the EBCC C library that these bindings were generated for,
+/// which is recorded as the build metadata of the crate version.
+pub const EBCC_VERSION: &str = env!("EBCC_SYS_EBCC_VERSION");
+
+/// Whether the `OpenJPEG` library that EBCC is linked with may encode with
+/// multiple threads, which it does if the `OPJ_NUM_THREADS` environment
+/// variable asks for more than one thread.
+///
+/// The vendored `OpenJPEG` library is built without thread support with the
+/// `single-threaded` feature and on WebAssembly targets.
+pub const OPENJPEG_THREADS: bool = matches!(env!("EBCC_SYS_OPENJPEG_THREADS").as_bytes(), b"1");
+
+/// Whether [`with_single_openjpeg_thread`] limits `OpenJPEG` to a single
+/// thread, which requires the vendored `OpenJPEG` library with thread support
+/// and the `std` feature.
+///
+/// A prebuilt or system `OpenJPEG` library only reads the `OPJ_NUM_THREADS`
+/// environment variable.
+pub const OPENJPEG_THREADS_HOOK: bool = cfg!(ebcc_sys_threads_hook);
+
+/// Whether the EBCC, `OpenJPEG`, and zstd libraries report their error
+/// messages to the observer that is set with [`set_error_observer`], which
+/// requires that EBCC is built by this crate.
+///
+/// A prebuilt EBCC library only prints its error messages to stderr.
+pub const ERROR_HOOK: bool = cfg!(ebcc_sys_errors_hook);
+
+/// Set the `observer` of the error messages that the C libraries print.
+///
+/// The `observer` is called, on the reporting thread, whenever the EBCC,
+/// `OpenJPEG`, or zstd libraries print an error message to stderr, e.g. to
+/// attach the message to the error of a failed EBCC call. The messages are
+/// still printed to stderr. The `observer` replaces any previously set
+/// observer. It is called from within the C libraries and must thus neither
+/// unwind nor call back into them. Without the [`ERROR_HOOK`], the `observer`
+/// is never called.
+pub fn set_error_observer(observer: fn(&CStr)) {
+    #[cfg(ebcc_sys_errors_hook)]
+    errors::set_error_observer(observer);
+    #[cfg(not(ebcc_sys_errors_hook))]
+    let _ = observer;
+}
+
+/// Run `f` such that the `OpenJPEG` codecs that EBCC creates meanwhile on
+/// the calling thread use a single thread, regardless of the
+/// `OPJ_NUM_THREADS` environment variable.
+///
+/// The environment is not modified, and codecs that other threads create in
+/// the meantime are not affected. Without the [`OPENJPEG_THREADS_HOOK`], `f`
+/// is run unchanged.
+pub fn with_single_openjpeg_thread<T>(f: impl FnOnce() -> T) -> T {
+    #[cfg(ebcc_sys_threads_hook)]
+    let _guard = threads::SingleThreadGuard::new();
+
+    f()
+}
 
 #[allow(unsafe_code)] // sys-crate
 #[allow(clippy::indexing_slicing)] // bindgen tests
@@ -30,10 +140,26 @@ mod bindings {
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
 
-pub use bindings::{
-    codec_config_t, ebcc_decode, ebcc_decode_chunking, ebcc_encode, ebcc_encode_chunking,
-    ebcc_encode_chunking_compat, free_buffer, residual_t,
-};
+#[cfg(feature = "rust-alloc")]
+#[allow(unsafe_code)] // sys-crate
+mod rust_alloc;
+
+#[cfg(ebcc_sys_threads_hook)]
+#[allow(unsafe_code)] // sys-crate
+mod threads;
+
+#[cfg(ebcc_sys_errors_hook)]
+#[allow(unsafe_code)] // sys-crate
+mod errors;
+
+#[cfg(feature = "rust-alloc")]
+pub use rust_alloc::{set_allocation_observer, AllocationChange};
+
+pub use bindings::{codec_config_t, free_buffer, residual_t};
+#[cfg(feature = "decode")]
+pub use bindings::{ebcc_decode, ebcc_decode_chunking};
+#[cfg(feature = "encode")]
+pub use bindings::{ebcc_encode, ebcc_encode_chunking, ebcc_encode_chunking_compat};
 
 pub const EBCC_CHUNKING_HEADER_MAGIC: &[u8] = const {
     let magic: &[u8] = bindings::EBCC_CHUNKING_HEADER_MAGIC;
@@ -91,3 +217,66 @@ pub const EBCC_NDIMS: usize = const {
 
     bindings::NDIMS as usize
 };
+
+/// Bindings to the version query of the `OpenJPEG` library that EBCC is
+/// linked with.
+pub mod openjpeg {
+    use core::ffi::c_char;
+
+    extern "C" {
+        pub fn opj_version() -> *const c_char;
+    }
+}
+
+/// Bindings to the stable API of the zstd library that EBCC is statically
+/// linked with.
+#[allow(non_upper_case_globals)] // C names
+pub mod zstd {
+    #[cfg(feature = "encode")]
+    use core::ffi::c_int;
+    #[cfg(any(feature = "encode", feature = "decode"))]
+    use core::ffi::c_void;
+    use core::ffi::{c_char, c_uint};
+
+    #[cfg(feature = "encode")]
+    extern "C" {
+        pub fn ZSTD_compressBound(src_size: usize) -> usize;
+        pub fn ZSTD_compress(
+            dst: *mut c_void,
+            dst_capacity: usize,
+            src: *const c_void,
+            src_size: usize,
+            compression_level: c_int,
+        ) -> usize;
+    }
+
+    #[cfg(feature = "decode")]
+    extern "C" {
+        pub fn ZSTD_decompress(
+            dst: *mut c_void,
+            dst_capacity: usize,
+            src: *const c_void,
+            compressed_size: usize,
+        ) -> usize;
+    }
+
+    // `ZSTD_ErrorCode`s of the zstd errors that the bindings distinguish,
+    //  whose values are stable across zstd versions
+    pub const ZSTD_error_prefix_unknown: c_uint = 10;
+    pub const ZSTD_error_version_unsupported: c_uint = 12;
+    pub const ZSTD_error_frameParameter_unsupported: c_uint = 14;
+    pub const ZSTD_error_frameParameter_windowTooLarge: c_uint = 16;
+    pub const ZSTD_error_corruption_detected: c_uint = 20;
+    pub const ZSTD_error_checksum_wrong: c_uint = 22;
+    pub const ZSTD_error_literals_headerWrong: c_uint = 24;
+    pub const ZSTD_error_memory_allocation: c_uint = 64;
+    pub const ZSTD_error_srcSize_wrong: c_uint = 72;
+
+    extern "C" {
+        pub fn ZSTD_isError(code: usize) -> c_uint;
+        pub fn ZSTD_getErrorCode(code: usize) -> c_uint;
+        pub fn ZSTD_getErrorName(code: usize) -> *const c_char;
+        pub fn ZSTD_versionNumber() -> c_uint;
+        pub fn ZSTD_versionString() -> *const c_char;
+    }
+}
@@ -0,0 +1,269 @@
+//! Allocation hooks of the `rust-alloc` feature, which the vendored C
+//! libraries call instead of `malloc`, `free`, and friends.
+//!
+//! Every allocation is preceded by a header of `align` bytes, whose last two
+//! words store the size and the alignment of the allocation, such that
+//! `free` and `realloc` can reconstruct its [`Layout`].
+//!
+//! Every allocation and deallocation, including its header, is reported to
+//! the observer that is set with [`set_allocation_observer`].
+
+use alloc::alloc::{alloc, alloc_zeroed, dealloc, realloc, Layout};
+use core::ffi::{c_int, c_void};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Alignment of `malloc`, which is sufficient for any fundamental type
+const MALLOC_ALIGN: usize = 16;
+
+/// `errno` values of `posix_memalign`, which are the same on all platforms
+///  that provide it
+const EINVAL: c_int = 22;
+const ENOMEM: c_int = 12;
+
+const _: () = assert!(2 * size_of::<usize>() <= MALLOC_ALIGN);
+
+/// Change of the memory that the vendored C libraries have allocated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationChange {
+    /// The given number of bytes were allocated
+    Allocated(usize),
+    /// The given number of bytes were freed
+    Freed(usize),
+}
+
+/// Observer of the [`AllocationChange`]s, stored as a type-erased
+/// `fn(AllocationChange)` pointer that is null if no observer is set
+static OBSERVER: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Set the `observer` that is called, on the allocating thread, whenever the
+/// vendored C libraries allocate or free memory, e.g. to account for the
+/// memory of EBCC calls.
+///
+/// The `observer` replaces any previously set observer. It is called from
+/// within the C libraries and must thus neither unwind nor call back into
+/// them.
+pub fn set_allocation_observer(observer: fn(AllocationChange)) {
+    OBSERVER.store(observer as *mut (), Ordering::Release);
+}
+
+/// Report the `change` to the observer, if one is set.
+fn observe(change: AllocationChange) {
+    let observer = OBSERVER.load(Ordering::Acquire);
+    if observer.is_null() {
+        return;
+    }
+
+    // Safety: non-null pointers are only stored from fn(AllocationChange)
+    let observer = unsafe { core::mem::transmute::<*mut (), fn(AllocationChange)>(observer) };
+    observer(change);
+}
+
+/// Hook for `malloc`.
+///
+/// # Safety
+///
+/// The returned allocation must only be freed with [`ebcc_sys_free`].
+#[no_mangle]
+pub unsafe extern "C" fn ebcc_sys_malloc(size: usize) -> *mut c_void {
+    // Safety: MALLOC_ALIGN is a valid alignment
+    unsafe { allocate(size, MALLOC_ALIGN, false) }
+}
+
+/// Hook for `calloc`.
+///
+/// # Safety
+///
+/// The returned allocation must only be freed with [`ebcc_sys_free`].
+#[no_mangle]
+pub unsafe extern "C" fn ebcc_sys_calloc(count: usize, size: usize) -> *mut c_void {
+    let Some(size) = count.checked_mul(size) else {
+        return ptr::null_mut();
+    };
+    // Safety: MALLOC_ALIGN is a valid alignment
+    unsafe { allocate(size, MALLOC_ALIGN, true) }
+}
+
+/// Hook for `realloc`.
+///
+/// # Safety
+///
+/// `ptr` must be null or have been allocated by these hooks and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn ebcc_sys_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    if ptr.is_null() {
+        // Safety: MALLOC_ALIGN is a valid alignment
+        return unsafe { allocate(size, MALLOC_ALIGN, false) };
+    }
+
+    // Safety: ptr was allocated by these hooks and is not used afterwards
+    let (base, layout) = unsafe { read_header(ptr) };
+    let align = layout.align();
+    let Some(new_layout) = header_layout(size, align) else {
+        return ptr::null_mut();
+    };
+
+    // Safety: base was allocated with layout, and the new size is non-zero
+    let base = unsafe { realloc(base, layout, new_layout.size()) };
+    if base.is_null() {
+        return ptr::null_mut();
+    }
+    observe(AllocationChange::Freed(layout.size()));
+    observe(AllocationChange::Allocated(new_layout.size()));
+
+    // Safety: base is valid for the header of the new layout
+    unsafe { write_header(base, size, align) }
+}
+
+/// Hook for `free`.
+///
+/// # Safety
+///
+/// `ptr` must be null or have been allocated by these hooks and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn ebcc_sys_free(ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+
+    // Safety: ptr was allocated by these hooks and is not used afterwards
+    let (base, layout) = unsafe { read_header(ptr) };
+    // Safety: base was allocated with layout
+    unsafe { dealloc(base, layout) };
+    observe(AllocationChange::Freed(layout.size()));
+}
+
+/// Hook for `aligned_alloc` and `_aligned_malloc`.
+///
+/// # Safety
+///
+/// The returned allocation must only be freed with [`ebcc_sys_free`].
+#[no_mangle]
+pub unsafe extern "C" fn ebcc_sys_aligned_alloc(alignment: usize, size: usize) -> *mut c_void {
+    if !alignment.is_power_of_two() {
+        return ptr::null_mut();
+    }
+    // Safety: alignment is a power of two
+    unsafe { allocate(size, alignment.max(MALLOC_ALIGN), false) }
+}
+
+/// Hook for `posix_memalign`.
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes. The allocation must only be freed with
+/// [`ebcc_sys_free`].
+#[no_mangle]
+pub unsafe extern "C" fn ebcc_sys_posix_memalign(
+    ptr: *mut *mut c_void,
+    alignment: usize,
+    size: usize,
+) -> c_int {
+    if !alignment.is_power_of_two() || alignment % size_of::<*mut c_void>() != 0 {
+        return EINVAL;
+    }
+
+    // Safety: alignment is a power of two
+    let allocation = unsafe { allocate(size, alignment.max(MALLOC_ALIGN), false) };
+    if allocation.is_null() {
+        return ENOMEM;
+    }
+
+    // Safety: the caller provides a valid pointer to store the allocation in
+    unsafe { ptr.write(allocation) };
+    0
+}
+
+/// Layout of an allocation of `size` bytes with `align`ment, including its
+/// header
+fn header_layout(size: usize, align: usize) -> Option<Layout> {
+    Layout::from_size_align(size.checked_add(align)?, align).ok()
+}
+
+/// Allocate `size` bytes with `align`ment, which must be a power of two and
+/// at least [`MALLOC_ALIGN`], and return a pointer behind the header.
+unsafe fn allocate(size: usize, align: usize, zeroed: bool) -> *mut c_void {
+    let Some(layout) = header_layout(size, align) else {
+        return ptr::null_mut();
+    };
+
+    // Safety: the layout has a non-zero size
+    let base = unsafe {
+        if zeroed {
+            alloc_zeroed(layout)
+        } else {
+            alloc(layout)
+        }
+    };
+    if base.is_null() {
+        return ptr::null_mut();
+    }
+    observe(AllocationChange::Allocated(layout.size()));
+
+    // Safety: base is valid for the header of the layout
+    unsafe { write_header(base, size, align) }
+}
+
+/// Write the header of an allocation at `base` and return a pointer behind it.
+unsafe fn write_header(base: *mut u8, size: usize, align: usize) -> *mut c_void {
+    // Safety: the header is `align` bytes long, so the pointer behind it and
+    //  the two words before that pointer are within the allocation
+    unsafe {
+        let ptr = base.add(align).cast::<c_void>();
+        ptr.cast::<usize>().sub(2).write(size);
+        ptr.cast::<usize>().sub(1).write(align);
+        ptr
+    }
+}
+
+/// Read the header of the allocation behind which `ptr` points and return
+/// the base pointer and layout of the allocation.
+const unsafe fn read_header(ptr: *mut c_void) -> (*mut u8, Layout) {
+    // Safety: ptr was allocated by these hooks, so it is preceded by a header
+    unsafe {
+        let size = ptr.cast::<usize>().sub(2).read();
+        let align = ptr.cast::<usize>().sub(1).read();
+        let base = ptr.cast::<u8>().sub(align);
+        (base, Layout::from_size_align_unchecked(size + align, align))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alloc_hooks() {
+        // Safety: every allocation is written within bounds and freed once
+        unsafe {
+            let ptr = ebcc_sys_malloc(3).cast::<u8>();
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % MALLOC_ALIGN, 0);
+            ptr.copy_from_nonoverlapping([1, 2, 3].as_ptr(), 3);
+
+            let ptr = ebcc_sys_realloc(ptr.cast(), 1000).cast::<u8>();
+            assert!(!ptr.is_null());
+            assert_eq!(*ptr.add(2), 3);
+            ebcc_sys_free(ptr.cast());
+
+            let ptr = ebcc_sys_calloc(4, 8).cast::<u64>();
+            assert_eq!(*ptr.add(3), 0);
+            ebcc_sys_free(ptr.cast());
+            assert!(ebcc_sys_calloc(usize::MAX, 2).is_null());
+
+            let ptr = ebcc_sys_aligned_alloc(64, 10);
+            assert_eq!(ptr as usize % 64, 0);
+            let ptr = ebcc_sys_realloc(ptr, 100);
+            assert_eq!(ptr as usize % 64, 0);
+            ebcc_sys_free(ptr);
+
+            let mut ptr = ptr::null_mut();
+            assert_eq!(ebcc_sys_posix_memalign(&raw mut ptr, 128, 0), 0);
+            assert_eq!(ptr as usize % 128, 0);
+            ebcc_sys_free(ptr);
+            assert_eq!(ebcc_sys_posix_memalign(&raw mut ptr, 3, 8), EINVAL);
+
+            ebcc_sys_free(ptr::null_mut());
+        }
+    }
+}
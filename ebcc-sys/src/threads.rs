@@ -0,0 +1,106 @@
+//! Thread count hook, which the vendored C libraries call instead of
+//! `getenv`, such that the `OpenJPEG` codecs can be limited to a single
+//! thread without modifying the environment.
+
+use core::cell::Cell;
+use core::ffi::{c_char, CStr};
+use core::marker::PhantomData;
+
+/// Environment variable from which `OpenJPEG` reads its default number of
+/// threads when it creates a codec
+const OPJ_NUM_THREADS: &CStr = c"OPJ_NUM_THREADS";
+
+/// Value of `OPJ_NUM_THREADS` that asks for a single thread
+const SINGLE_THREAD: &CStr = c"1";
+
+std::thread_local! {
+    /// Number of running calls on this thread that require a single
+    /// `OpenJPEG` thread
+    static SINGLE_THREAD_CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+extern "C" {
+    fn getenv(name: *const c_char) -> *mut c_char;
+}
+
+/// Guard of a call on the current thread that requires a single `OpenJPEG`
+/// thread, which ends the call when it is dropped, even if the call unwinds.
+///
+/// The guard is neither [`Send`] nor [`Sync`], such that it is dropped on
+/// the thread that started the call.
+pub struct SingleThreadGuard(PhantomData<*const ()>);
+
+impl SingleThreadGuard {
+    /// Start a call on the current thread that requires a single `OpenJPEG`
+    /// thread.
+    pub fn new() -> Self {
+        SINGLE_THREAD_CALLS.with(|calls| calls.set(calls.get() + 1));
+        Self(PhantomData)
+    }
+}
+
+impl Drop for SingleThreadGuard {
+    fn drop(&mut self) {
+        SINGLE_THREAD_CALLS.with(|calls| calls.set(calls.get() - 1));
+    }
+}
+
+/// Hook for `getenv`.
+///
+/// While any call on the current thread requires a single `OpenJPEG` thread,
+/// `OPJ_NUM_THREADS` is reported as `1` to that thread. All other variables,
+/// and `OPJ_NUM_THREADS` on other threads, are looked up in the environment.
+///
+/// # Safety
+///
+/// The `name` must be a valid null-terminated string, like for `getenv`. The
+/// returned string must not be modified.
+#[no_mangle]
+pub unsafe extern "C" fn ebcc_sys_getenv(name: *const c_char) -> *mut c_char {
+    if SINGLE_THREAD_CALLS.with(|calls| calls.get() > 0) {
+        // Safety: name is a valid null-terminated string
+        if unsafe { CStr::from_ptr(name) } == OPJ_NUM_THREADS {
+            return SINGLE_THREAD.as_ptr().cast_mut();
+        }
+    }
+
+    // Safety: name is a valid null-terminated string
+    unsafe { getenv(name) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_getenv_hook() {
+        let threads = || {
+            // Safety: OPJ_NUM_THREADS is null-terminated and the returned
+            //         string, if any, is only read
+            unsafe {
+                let threads = ebcc_sys_getenv(OPJ_NUM_THREADS.as_ptr());
+                (!threads.is_null()).then(|| CStr::from_ptr(threads))
+            }
+        };
+
+        // the environment of the test process is not modified
+        let outside = threads();
+        assert_eq!(
+            crate::with_single_openjpeg_thread(threads),
+            Some(SINGLE_THREAD)
+        );
+        assert_eq!(threads(), outside);
+
+        // the override only applies to the calling thread
+        let outside_single = outside == Some(SINGLE_THREAD);
+        let other_single = crate::with_single_openjpeg_thread(|| {
+            std::thread::spawn(move || threads() == Some(SINGLE_THREAD)).join()
+        });
+        assert_eq!(other_single.ok(), Some(outside_single));
+
+        // other variables are still looked up in the environment
+        // Safety: PATH is null-terminated and the returned string is not
+        //         modified
+        assert!(!unsafe { ebcc_sys_getenv(c"PATH".as_ptr()) }.is_null());
+    }
+}
@@ -3,35 +3,49 @@
 //! This example demonstrates how to use the EBCC Rust bindings for
 //! compressing and decompressing climate data.
 
-use ebcc::{ebcc_decode_into, ebcc_encode, EBCCConfig, EBCCResult};
+use ebcc::{ebcc_decode_into, ebcc_encode, testdata, EBCCConfig, EBCCResult};
 use ndarray::Array;
 
-use ::{ebcc_sys as _, thiserror as _};
-
-#[expect(clippy::cast_precision_loss, clippy::suboptimal_flops)]
+#[cfg(feature = "arrow")]
+use ::arrow_array as _;
+#[cfg(feature = "arrow")]
+use ::arrow_schema as _;
+#[cfg(feature = "blake3")]
+use ::blake3 as _;
+#[cfg(feature = "bytemuck")]
+use ::bytemuck as _;
+#[cfg(feature = "half")]
+use ::half as _;
+#[cfg(feature = "mmap")]
+use ::memmap2 as _;
+#[cfg(feature = "nalgebra")]
+use ::nalgebra as _;
+#[cfg(feature = "ndarray015")]
+use ::ndarray015 as _;
+#[cfg(feature = "ndarray017")]
+use ::ndarray017 as _;
+#[cfg(feature = "netcdf")]
+use ::netcdf as _;
+#[cfg(feature = "rayon")]
+use ::rayon as _;
+#[cfg(feature = "serde")]
+use ::serde as _;
+#[cfg(feature = "async")]
+use ::tokio as _;
+#[cfg(feature = "tracing")]
+use ::tracing as _;
+use ::{
+    crc as _, crc32fast as _, criterion as _, ebcc_sys as _, proptest as _, thiserror as _,
+    xxhash_rust as _,
+};
+
+#[expect(clippy::cast_precision_loss)]
 fn main() -> EBCCResult<()> {
     println!("EBCC Basic Compression Example");
     println!("=============================");
 
-    // Create some synthetic climate data (ERA5-like grid)
-    let height = 721;
-    let width = 1440;
-    let frames = 1;
-
-    // Generate synthetic temperature data (in Kelvin)
-    let data = Array::from_shape_fn((frames, height, width), |(_f, i, j)| {
-        // Simple synthetic temperature field with spatial variation
-        let lat = -90.0 + ((i as f32) / (height as f32)) * 180.0;
-        let lon = -180.0 + ((j as f32) / (width as f32)) * 360.0;
-
-        // Temperature decreases with latitude, with some variation
-        #[allow(clippy::let_and_return)]
-        let temp = 273.15
-            + 30.0 * (1.0 - lat.abs() / 90.0)
-            + 5.0 * (lon / 180.0).sin()
-            + 2.0 * (lat / 90.0 * 4.0).sin();
-        temp
-    });
+    // Create some synthetic climate data (ERA5-like grid) in Kelvin
+    let data = testdata::temperature((1, 721, 1440));
 
     let total_elements = data.len();
 
@@ -0,0 +1,27 @@
+//! Open, verify, and decode arbitrary bytes as an EBCC container.
+
+#![no_main]
+
+use std::io::Cursor;
+
+use ebcc::container::EbccContainer;
+use libfuzzer_sys::fuzz_target;
+use ndarray::Array;
+
+fuzz_target!(|compressed_data: &[u8]| {
+    let Ok(mut container) = EbccContainer::open(Cursor::new(compressed_data)) else {
+        return;
+    };
+
+    let _ = container.verify_integrity();
+
+    let (height, width) = container.frame_shape();
+    if height.saturating_mul(width) > 1 << 20 {
+        return;
+    }
+
+    let mut frame = Array::zeros((height, width));
+    for index in 0..container.frames().min(16) {
+        let _ = container.decode_frame_into(index, frame.view_mut());
+    }
+});
@@ -0,0 +1,23 @@
+//! Decode arbitrary bytes into a small output array.
+//!
+//! Corrupted compressed data must be reported as an error instead of
+//! panicking, aborting, or causing undefined behaviour.
+
+#![no_main]
+
+use ebcc::ebcc_decode_into;
+use libfuzzer_sys::fuzz_target;
+use ndarray::Array;
+
+fuzz_target!(|input: (u8, bool, bool, &[u8])| {
+    let (frames, tall, wide, compressed_data) = input;
+
+    let shape = (
+        usize::from(frames % 4) + 1,
+        if tall { 64 } else { 32 },
+        if wide { 64 } else { 32 },
+    );
+
+    let mut decompressed = Array::zeros(shape);
+    let _ = ebcc_decode_into(compressed_data, decompressed.view_mut());
+});
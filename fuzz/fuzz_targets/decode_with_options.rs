@@ -0,0 +1,22 @@
+//! Decode arbitrary bytes with strict decode options.
+//!
+//! Untrusted compressed data must be rejected before it reaches the EBCC C
+//! library unless its header matches the output.
+
+#![no_main]
+
+use ebcc::{ebcc_decode_with_options, EBCCDecodeOptions};
+use libfuzzer_sys::fuzz_target;
+use ndarray::Array;
+
+fuzz_target!(|input: (u8, bool, &[u8])| {
+    let (frames, strict_header, compressed_data) = input;
+
+    let options = EBCCDecodeOptions::new()
+        .with_max_output_bytes(1 << 20)
+        .with_max_frames(4)
+        .with_strict_header(strict_header);
+
+    let mut decompressed = Array::zeros((usize::from(frames % 4) + 1, 32, 32));
+    let _ = ebcc_decode_with_options(compressed_data, decompressed.view_mut(), &options);
+});
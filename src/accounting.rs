@@ -0,0 +1,401 @@
+//! Accounting of the memory that EBCC calls allocate.
+
+use std::cell::Cell;
+#[cfg(feature = "rust-alloc")]
+use std::sync::Once;
+
+/// Memory usage of the calls measured with [`ebcc_measure_resources`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EBCCResourceUsage {
+    /// Peak number of bytes that were additionally allocated at the same
+    /// time, which only includes the working memory of the EBCC C library
+    /// with the `rust-alloc` feature, see [`ebcc_measure_resources`]
+    pub peak_bytes: usize,
+    /// Total number of bytes that were allocated
+    pub allocated_bytes: usize,
+    /// Number of encodes that the EBCC C library failed and that were
+    /// retried with tiles, see [`ebcc_encode`][crate::ebcc_encode]
+    pub tiling_fallbacks: usize,
+}
+
+/// Allocation report of the calls measured with
+/// [`ebcc_measure_allocations`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EBCCAllocationStats {
+    /// Peak number of bytes that were additionally allocated at the same
+    /// time, see [`EBCCResourceUsage::peak_bytes`]
+    pub peak_bytes: usize,
+    /// Number of full copies of the input, compressed, or decompressed data
+    /// that were made on the Rust side, e.g. of the input data that the EBCC
+    /// C library modifies
+    pub copies: usize,
+}
+
+/// Bytes that are currently allocated, and the usage and copies so far, of
+/// the ongoing measurement on this thread
+#[derive(Clone, Copy)]
+struct Measurement {
+    current_bytes: usize,
+    usage: EBCCResourceUsage,
+    copies: usize,
+}
+
+thread_local! {
+    static MEASUREMENT: Cell<Option<Measurement>> = const { Cell::new(None) };
+}
+
+/// Run the `job`, e.g. an encode or decode call, and measure the memory that
+/// the EBCC calls within it allocate on this thread.
+///
+/// The measured buffers are the Rust-side copies of the input data and of
+/// the compressed bytes, the compressed and decompressed buffers that the
+/// EBCC C library returns, and the encoded outputs, including those that are
+/// returned to the caller.
+///
+/// With the `rust-alloc` feature, all memory that the vendored EBCC,
+/// `OpenJPEG`, and zstd libraries allocate on this thread is measured as
+/// well, including their working memory, which usually dominates the peak.
+/// Without it, the C libraries allocate with `malloc`, which is not visible
+/// to this crate, such that only the buffers that they return are measured
+/// and the reported usage is a lower bound of the actual memory usage.
+///
+/// Calls that run on other threads, e.g. in
+/// [`ebcc_encode_batch_parallel`][crate::ebcc_encode_batch_parallel], and
+/// the allocations of `OpenJPEG`'s worker threads are not measured.
+///
+/// Inputs whose header is invalid or does not match the output shape are
+/// rejected before anything is allocated or copied.
+///
+/// Measurements can be nested, in which case the outer measurement includes
+/// the inner one.
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_encode, ebcc_measure_resources, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_elem((1, 32, 32), 1.0_f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let (compressed, usage) = ebcc_measure_resources(|| ebcc_encode(data.view(), &config));
+/// let compressed = compressed?;
+/// assert!(usage.peak_bytes >= compressed.len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_measure_resources<T>(job: impl FnOnce() -> T) -> (T, EBCCResourceUsage) {
+    let (result, measurement) = measure(job);
+    (result, measurement.usage)
+}
+
+/// Run the `job`, e.g. an encode or decode call, and report the allocations
+/// and data copies of the EBCC calls within it on this thread.
+///
+/// The peak number of allocated bytes is measured like in
+/// [`ebcc_measure_resources`].
+///
+/// A successful [`ebcc_encode`][crate::ebcc_encode] copies the input data
+/// once for the EBCC C library, which may modify its input, and the
+/// compressed payload once into its single output allocation.
+/// [`ebcc_encode_mut`][crate::ebcc_encode_mut] passes data in standard order
+/// straight to the C library and only makes the output copy. Similarly, a
+/// successful [`ebcc_decode_into`][crate::ebcc_decode_into] copies the
+/// compressed payload once for the C library and the decoded data once into
+/// the caller's output. It skips the payload copy for residual-only and
+/// stored-raw payloads, which it decodes without the C library, while
+/// [`ebcc_decode_mut_into`][crate::ebcc_decode_mut_into] only makes the
+/// output copy. The buffers that the C library returns cannot be avoided,
+/// since it allocates them itself.
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_encode_mut, ebcc_measure_allocations, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let mut data = Array::from_elem((1, 32, 32), 1.0_f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let (compressed, stats) = ebcc_measure_allocations(|| ebcc_encode_mut(data.view_mut(), &config));
+/// compressed?;
+/// assert_eq!(stats.copies, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_measure_allocations<T>(job: impl FnOnce() -> T) -> (T, EBCCAllocationStats) {
+    let (result, measurement) = measure(job);
+    (
+        result,
+        EBCCAllocationStats {
+            peak_bytes: measurement.usage.peak_bytes,
+            copies: measurement.copies,
+        },
+    )
+}
+
+/// Run the `job` and return the measurement of the EBCC calls within it.
+fn measure<T>(job: impl FnOnce() -> T) -> (T, Measurement) {
+    #[cfg(feature = "rust-alloc")]
+    observe_c_allocations();
+
+    let empty = Measurement {
+        current_bytes: 0,
+        usage: EBCCResourceUsage::default(),
+        copies: 0,
+    };
+    let outer = MEASUREMENT.replace(Some(empty));
+
+    // restore the outer measurement even if the job unwinds
+    let guard = RestoreMeasurement { outer };
+    let result = job();
+    let measurement = MEASUREMENT.get().unwrap_or(empty);
+    drop(guard);
+
+    (result, measurement)
+}
+
+/// Guard that ends a measurement and folds it into the outer one
+struct RestoreMeasurement {
+    outer: Option<Measurement>,
+}
+
+impl Drop for RestoreMeasurement {
+    fn drop(&mut self) {
+        let inner = MEASUREMENT.get();
+        let outer = self.outer.map(|mut outer| {
+            if let Some(inner) = inner {
+                outer.usage.peak_bytes = outer
+                    .usage
+                    .peak_bytes
+                    .max(outer.current_bytes.saturating_add(inner.usage.peak_bytes));
+                outer.usage.allocated_bytes = outer
+                    .usage
+                    .allocated_bytes
+                    .saturating_add(inner.usage.allocated_bytes);
+                outer.copies = outer.copies.saturating_add(inner.copies);
+                outer.usage.tiling_fallbacks = outer
+                    .usage
+                    .tiling_fallbacks
+                    .saturating_add(inner.usage.tiling_fallbacks);
+                outer.current_bytes = outer.current_bytes.saturating_add(inner.current_bytes);
+            }
+            outer
+        });
+        MEASUREMENT.set(outer);
+    }
+}
+
+/// Record the allocations of the vendored C libraries, which the
+/// `rust-alloc` feature routes through the Rust global allocator, in the
+/// measurement of the allocating thread.
+#[cfg(feature = "rust-alloc")]
+fn observe_c_allocations() {
+    static OBSERVE: Once = Once::new();
+
+    OBSERVE.call_once(|| {
+        ebcc_sys::set_allocation_observer(|change| match change {
+            ebcc_sys::AllocationChange::Allocated(bytes) => record_alloc(bytes),
+            ebcc_sys::AllocationChange::Freed(bytes) => record_free(bytes),
+        });
+    });
+}
+
+/// Record that `bytes` were allocated.
+pub fn record_alloc(bytes: usize) {
+    // the C libraries may allocate while the thread-local is destroyed
+    let _ = MEASUREMENT.try_with(|measurement| {
+        if let Some(mut m) = measurement.get() {
+            m.current_bytes = m.current_bytes.saturating_add(bytes);
+            m.usage.peak_bytes = m.usage.peak_bytes.max(m.current_bytes);
+            m.usage.allocated_bytes = m.usage.allocated_bytes.saturating_add(bytes);
+            measurement.set(Some(m));
+        }
+    });
+}
+
+/// Record that `bytes` were freed.
+pub fn record_free(bytes: usize) {
+    // the C libraries may free while the thread-local is destroyed
+    let _ = MEASUREMENT.try_with(|measurement| {
+        if let Some(mut m) = measurement.get() {
+            m.current_bytes = m.current_bytes.saturating_sub(bytes);
+            measurement.set(Some(m));
+        }
+    });
+}
+
+/// Record that a full copy of some data was made.
+pub fn record_copy() {
+    MEASUREMENT.with(|measurement| {
+        if let Some(mut m) = measurement.get() {
+            m.copies = m.copies.saturating_add(1);
+            measurement.set(Some(m));
+        }
+    });
+}
+
+/// Record that a failed encode was retried with tiles.
+pub fn record_tiling_fallback() {
+    MEASUREMENT.with(|measurement| {
+        if let Some(mut m) = measurement.get() {
+            m.usage.tiling_fallbacks = m.usage.tiling_fallbacks.saturating_add(1);
+            measurement.set(Some(m));
+        }
+    });
+}
+
+/// Temporary allocation of some bytes that is recorded until it is dropped.
+pub struct TrackedAlloc {
+    bytes: usize,
+}
+
+impl TrackedAlloc {
+    /// Record the allocation of `bytes` until the returned value is dropped.
+    #[must_use]
+    pub fn new(bytes: usize) -> Self {
+        record_alloc(bytes);
+        Self { bytes }
+    }
+}
+
+impl Drop for TrackedAlloc {
+    fn drop(&mut self) {
+        record_free(self.bytes);
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{
+        ebcc_decode_into, ebcc_decode_mut_into, ebcc_encode, ebcc_encode_mut, testdata,
+        EBCCBaseMode, EBCCConfig, EBCCError, EBCCResult,
+    };
+
+    #[test]
+    fn test_measure_resources() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let (compressed, encode_usage) =
+            ebcc_measure_resources(|| ebcc_encode(data.view(), &config));
+        let compressed = compressed?;
+        // the input copy and the compressed output are measured
+        assert!(encode_usage.peak_bytes >= data.len() * 4);
+        assert!(encode_usage.peak_bytes >= compressed.len());
+        assert!(encode_usage.allocated_bytes >= encode_usage.peak_bytes);
+
+        let mut decompressed = Array::zeros(data.dim());
+        let (result, decode_usage) =
+            ebcc_measure_resources(|| ebcc_decode_into(&compressed, decompressed.view_mut()));
+        result?;
+        assert!(decode_usage.peak_bytes >= data.len() * 4);
+
+        // mismatching shapes are rejected before anything is copied
+        let mut decompressed = Array::zeros((3, 32, 48));
+        let (result, mismatch_usage) =
+            ebcc_measure_resources(|| ebcc_decode_into(&compressed, decompressed.view_mut()));
+        assert!(matches!(result, Err(EBCCError::ShapeMismatch { .. })));
+        assert_eq!(mismatch_usage, EBCCResourceUsage::default());
+
+        // nested measurements are included in the outer one
+        let ((_, inner), outer) = ebcc_measure_resources(|| {
+            let _buffer = TrackedAlloc::new(100);
+            ebcc_measure_resources(|| ebcc_encode(data.view(), &config))
+        });
+        assert_eq!(inner, encode_usage);
+        assert_eq!(outer.peak_bytes, encode_usage.peak_bytes + 100);
+        assert_eq!(outer.allocated_bytes, encode_usage.allocated_bytes + 100);
+
+        // nothing is recorded outside of a measurement
+        record_alloc(100);
+        assert_eq!(
+            ebcc_measure_resources(|| ()).1,
+            EBCCResourceUsage::default()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "rust-alloc")]
+    fn test_measure_c_allocations() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        // the working memory of the C library is measured on top of the
+        //  input copy, the returned buffer, and the output
+        let (compressed, usage) = ebcc_measure_resources(|| ebcc_encode(data.view(), &config));
+        let compressed = compressed?;
+        assert!(usage.allocated_bytes > 2 * (data.len() * 4 + compressed.len()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_measure_allocations() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        // the copying APIs copy the data once on each side of the C library
+        let (compressed, stats) = ebcc_measure_allocations(|| ebcc_encode(data.view(), &config));
+        let compressed = compressed?;
+        assert_eq!(stats.copies, 2);
+        let mut decompressed = Array::zeros(data.dim());
+        let (result, stats) =
+            ebcc_measure_allocations(|| ebcc_decode_into(&compressed, decompressed.view_mut()));
+        result?;
+        assert_eq!(stats.copies, 2);
+
+        // the in-place APIs only copy into their output
+        let mut input = data.clone();
+        let (compressed_mut, stats) =
+            ebcc_measure_allocations(|| ebcc_encode_mut(input.view_mut(), &config));
+        let mut compressed_mut = compressed_mut?;
+        assert!(stats.copies <= 1);
+        assert_eq!(compressed_mut, compressed);
+        let mut decompressed_mut = Array::zeros(data.dim());
+        let (result, stats) = ebcc_measure_allocations(|| {
+            ebcc_decode_mut_into(&mut compressed_mut, decompressed_mut.view_mut())
+        });
+        result?;
+        assert!(stats.copies <= 1);
+        assert_eq!(decompressed_mut, decompressed);
+
+        // stored payloads never reach the C library and are decoded without
+        //  the payload copy
+        let stored = config.with_base_mode(EBCCBaseMode::Stored);
+        let compressed_stored = ebcc_encode(data.view(), &stored)?;
+        let mut decompressed_stored = Array::zeros(data.dim());
+        let (result, stats) = ebcc_measure_allocations(|| {
+            ebcc_decode_into(&compressed_stored, decompressed_stored.view_mut())
+        });
+        result?;
+        assert_eq!(stats.copies, 1);
+        assert_eq!(decompressed_stored, data);
+
+        // mismatching shapes are rejected before anything is copied
+        let mut decompressed = Array::zeros((3, 32, 48));
+        let (result, stats) =
+            ebcc_measure_allocations(|| ebcc_decode_into(&compressed, decompressed.view_mut()));
+        assert!(matches!(result, Err(EBCCError::ShapeMismatch { .. })));
+        assert_eq!(stats, EBCCAllocationStats::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,594 @@
+//! Adaptive per-tile base compression ratios within a frame.
+
+use std::ops::Range;
+
+use ebcc_sys::EBCC_MIN_INTERNAL_IMAGE_DIM;
+use ndarray::{s, Array2, ArrayView, ArrayViewMut};
+
+use crate::accounting::record_tiling_fallback;
+use crate::codec::{
+    copy_decompressed, decompressed_view, ebcc_decode_nested, ebcc_encode_c_buffer, EbccDim,
+};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCChecksummedData, EBCCError, EBCCResult};
+use crate::finite::validate_only_finite_data;
+use crate::params::validate_regular_ebcc_shape;
+use crate::reader::Format;
+use crate::size::{u64_to_usize, usize_to_u64};
+use crate::trace::warn_event;
+
+/// Magic bytes at the start of EBCC data that was compressed with
+/// [`ebcc_encode_adaptive`].
+pub const EBCC_TILED_MAGIC: &[u8; 8] = b"EBCCTILE";
+
+/// Version of the format of EBCC data that was compressed with
+/// [`ebcc_encode_adaptive`].
+///
+/// Version 2 records the [fingerprint][EBCCConfig::fingerprint] of the
+/// configuration in the header, which only tags every optional configuration
+/// parameter since version 3. Data of version 1, which does not record it,
+/// can still be decoded.
+pub const EBCC_TILED_VERSION: u32 = 3;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC tiled data");
+
+/// Maximum factor by which a tile's base compression ratio may deviate from
+/// the configured [`EBCCConfig::base_cr`]
+const MAX_BASE_CR_SPREAD: f64 = 8.0;
+
+/// Number of bisection steps used to meet the base-layer size budget
+const BUDGET_BISECTION_STEPS: usize = 64;
+
+/// Maximum side length of the tiles into which a frame is split when the
+/// EBCC C library fails to encode it whole
+const FALLBACK_TILE_DIM: usize = 1024;
+
+/// Encode a 3D data array with EBCC, adapting the base compression ratio of
+/// each spatial tile to its content.
+///
+/// A uniform [`base_cr`][EBCCConfig::base_cr] wastes bits on smooth tiles,
+/// e.g. over the ocean, and starves complex tiles, e.g. over mountains. This
+/// function splits the `data` into tiles of `tile_shape = (height, width)`
+/// across all frames, and distributes the base-layer size budget of the
+/// uniform `base_cr`, i.e. `data size / base_cr`, over the tiles in
+/// proportion to their size and standard deviation. Each tile's base
+/// compression ratio stays within a factor of 8 of the `base_cr`, and the
+/// total base-layer budget is never exceeded. Edge tiles that would be
+/// smaller than EBCC's minimum tile size are merged into their neighbour.
+///
+/// Each tile is compressed independently and its base compression ratio is
+/// recorded with the compressed data, where it can be inspected with
+/// [`ebcc_adaptive_base_crs`]. The compressed data is decoded with
+/// [`ebcc_decode_into`][crate::ebcc_decode_into]. The tiles are checksummed
+/// with CRC-32, regardless of the
+/// [`checksum_algorithm`][EBCCConfig::checksum_algorithm] of the `config`.
+///
+/// <div class="warning">
+///
+/// **Warning:** Range-relative error bounds are calculated independently for
+/// each tile.
+///
+/// </div>
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if either dimension of the `tile_shape` is
+///   smaller than EBCC's minimum tile size
+/// - all errors that [`ebcc_encode`][crate::ebcc_encode] can return
+pub fn ebcc_encode_adaptive(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    tile_shape: (usize, usize),
+) -> EBCCResult<Vec<u8>> {
+    let (tile_height, tile_width) = tile_shape;
+    if tile_height < EBCC_MIN_INTERNAL_IMAGE_DIM || tile_width < EBCC_MIN_INTERNAL_IMAGE_DIM {
+        return Err(EBCCError::InvalidInput(format!(
+            "Tiles of shape {tile_shape:?} are smaller than EBCC's minimum tile size of {EBCC_MIN_INTERNAL_IMAGE_DIM}",
+        )));
+    }
+
+    validate_regular_ebcc_shape(data.dim())?;
+    config.validate()?;
+    config.limits.check_shape(data.dim())?;
+    if config.check_finite {
+        validate_only_finite_data(&data)?;
+    }
+
+    let tiles = split_tiles(data, tile_shape);
+    let base_crs = allocate_base_crs(&tiles, config.base_cr);
+
+    encode_tiles(data.dim(), tile_shape, tiles, &base_crs, config)
+}
+
+/// Encode the `data` in uniform tiles after the EBCC C library failed to
+/// encode it whole, e.g. because a very large frame exceeded its allocation
+/// or tile geometry limits.
+///
+/// The frames are split into tiles of at most 1024x1024 that are each half
+/// as large as the frame, which are encoded with the uniform
+/// [`base_cr`][EBCCConfig::base_cr]. The tiles are encoded recursively, so
+/// a tile that still fails is split again. Returns [`None`] if the frames
+/// are too small to be split.
+///
+/// The fallback is reported as a warning with the `tracing` feature, and is
+/// counted in the [`tiling_fallbacks`][crate::EBCCResourceUsage::tiling_fallbacks]
+/// of [`ebcc_measure_resources`][crate::ebcc_measure_resources].
+pub fn ebcc_encode_tiled_fallback(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+) -> Option<EBCCResult<Vec<u8>>> {
+    let (_, height, width) = data.dim();
+    let fallback_tile = |len: usize| {
+        len.div_ceil(2)
+            .clamp(EBCC_MIN_INTERNAL_IMAGE_DIM, FALLBACK_TILE_DIM)
+    };
+    let tile_shape = (fallback_tile(height), fallback_tile(width));
+
+    let tiles = split_tiles(data, tile_shape);
+    if tiles.len() < 2 {
+        return None;
+    }
+
+    warn_event!(
+        shape = ?data.shape(),
+        tiles = tiles.len(),
+        "ebcc_encode failed, retrying with tiles",
+    );
+    record_tiling_fallback();
+
+    let base_crs = vec![config.base_cr; tiles.len()];
+    Some(encode_tiles(
+        data.dim(),
+        tile_shape,
+        tiles,
+        &base_crs,
+        config,
+    ))
+}
+
+/// Split the frames of the `data` into tiles of `tile_shape`, in row-major
+/// order
+fn split_tiles(
+    data: ArrayView<f32, EbccDim>,
+    (tile_height, tile_width): (usize, usize),
+) -> Vec<ArrayView<f32, EbccDim>> {
+    let (_, height, width) = data.dim();
+    let rows = tile_bounds(height, tile_height);
+    let cols = tile_bounds(width, tile_width);
+
+    rows.iter()
+        .flat_map(|rows| cols.iter().map(move |cols| (rows.clone(), cols.clone())))
+        .map(|(rows, cols)| data.slice_move(s![.., rows, cols]))
+        .collect()
+}
+
+/// Encode the `tiles` of data of `shape`, each with its own base compression
+/// ratio, into the [`EBCC_TILED_MAGIC`] format
+fn encode_tiles(
+    (frames, height, width): (usize, usize, usize),
+    (tile_height, tile_width): (usize, usize),
+    tiles: Vec<ArrayView<f32, EbccDim>>,
+    base_crs: &[f32],
+    config: &EBCCConfig,
+) -> EBCCResult<Vec<u8>> {
+    let mut table = Vec::new();
+    let mut payloads = Vec::new();
+    for (tile, &base_cr) in tiles.into_iter().zip(base_crs) {
+        // the data has already been checked for non-finite values
+        let tile_config = config.clone().with_base_cr(base_cr).skip_finite_check();
+        let payload = ebcc_encode_c_buffer(tile, &tile_config)?;
+        let payload = payload.as_slice();
+
+        table.extend_from_slice(&base_cr.to_bits().to_le_bytes());
+        table.extend_from_slice(&usize_to_u64(payload.len())?.to_le_bytes());
+        table.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+        payloads.extend_from_slice(payload);
+    }
+
+    let mut compressed_data = Vec::with_capacity(HEADER_LEN + table.len() + payloads.len());
+    compressed_data.extend_from_slice(EBCC_TILED_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_TILED_VERSION.to_le_bytes());
+    for dim in [frames, height, width, tile_height, tile_width] {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(&config.fingerprint().to_le_bytes());
+    compressed_data.extend_from_slice(&table);
+    compressed_data.extend_from_slice(&payloads);
+
+    Ok(compressed_data)
+}
+
+/// Read the base compression ratio of each tile of EBCC data that was
+/// compressed with [`ebcc_encode_adaptive`].
+///
+/// # Returns
+///
+/// A 2D array with the base compression ratio of each tile, where the tiles
+/// are arranged as in the frame.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` was not compressed
+///   with [`ebcc_encode_adaptive`] or is truncated or corrupted
+/// - [`EBCCError::ChecksumMismatch`] if the checksum of a tile does not match
+/// - [`EBCCError::DecompressionError`] if the format version is not
+///   supported
+pub fn ebcc_adaptive_base_crs(compressed_data: &[u8]) -> EBCCResult<Array2<f32>> {
+    let tiled = TiledData::parse(compressed_data)?;
+
+    Array2::from_shape_vec(
+        (tiled.rows.len(), tiled.cols.len()),
+        tiled.tiles.iter().map(|tile| tile.base_cr).collect(),
+    )
+    .map_err(|_| FORMAT.corrupted())
+}
+
+/// Read the [fingerprint][EBCCConfig::fingerprint] of the configuration that
+/// EBCC data was compressed with by [`ebcc_encode_adaptive`], or [`None`] if
+/// the data has version 1 and does not record it.
+pub fn tiled_config_fingerprint(compressed_data: &[u8]) -> EBCCResult<Option<u64>> {
+    TiledData::parse(compressed_data).map(|tiled| tiled.config_fingerprint)
+}
+
+/// Check if the `compressed_data` starts with the [`EBCC_TILED_MAGIC`] bytes.
+#[must_use]
+pub fn is_ebcc_tiled(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_TILED_MAGIC)
+}
+
+/// Decode EBCC data that was compressed with [`ebcc_encode_adaptive`] into a
+/// 3D data array.
+pub fn ebcc_decode_tiled_into(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    decode_tiled_into(compressed_data, decompressed_data, 0)
+}
+
+/// Decode tiled EBCC data like [`ebcc_decode_tiled_into`], which is nested
+/// `depth` levels deep inside other EBCC payloads.
+pub fn decode_tiled_into(
+    compressed_data: &[u8],
+    mut decompressed_data: ArrayViewMut<f32, EbccDim>,
+    depth: usize,
+) -> EBCCResult<()> {
+    let tiled = TiledData::parse(compressed_data)?;
+
+    let output_shape = <[usize; 3]>::from(decompressed_data.dim());
+    if tiled.shape != output_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: tiled.shape,
+            actual: output_shape,
+        });
+    }
+
+    let [frames, ..] = tiled.shape;
+    let mut payload = Vec::new();
+    let mut records = tiled.tiles.iter();
+    for rows in &tiled.rows {
+        for cols in &tiled.cols {
+            let tile = records.next().ok_or_else(|| FORMAT.corrupted())?;
+
+            // C function may modify the input
+            payload.clear();
+            payload.extend_from_slice(tile.payload);
+
+            let tile_shape = (frames, rows.len(), cols.len());
+            let decompressed_buffer = ebcc_decode_nested(&mut payload, tile_shape, depth + 1)?;
+            copy_decompressed(
+                decompressed_data.slice_mut(s![.., rows.clone(), cols.clone()]),
+                decompressed_view(tile_shape, &decompressed_buffer)?,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+const HEADER_LEN: usize = 8 + 4 + 6 * 8;
+
+/// One tile of EBCC data that was compressed with [`ebcc_encode_adaptive`]
+struct Tile<'a> {
+    base_cr: f32,
+    payload: &'a [u8],
+}
+
+/// Parsed EBCC data that was compressed with [`ebcc_encode_adaptive`]
+struct TiledData<'a> {
+    shape: [usize; 3],
+    config_fingerprint: Option<u64>,
+    rows: Vec<Range<usize>>,
+    cols: Vec<Range<usize>>,
+    tiles: Vec<Tile<'a>>,
+}
+
+impl<'a> TiledData<'a> {
+    fn parse(compressed_data: &'a [u8]) -> EBCCResult<Self> {
+        let Some(mut data) = compressed_data.strip_prefix(EBCC_TILED_MAGIC.as_slice()) else {
+            return Err(EBCCError::InvalidInput(String::from(
+                "Missing EBCC tiled data header",
+            )));
+        };
+
+        let version = u32::from_le_bytes(FORMAT.read_array(&mut data)?);
+        if !(1..=EBCC_TILED_VERSION).contains(&version) {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC tiled data version: {version}").into(),
+            ));
+        }
+
+        let mut dims = [0; 5];
+        for dim in &mut dims {
+            *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut data)?))?;
+        }
+        // fingerprints before version 3 did not tag the optional parameters
+        let config_fingerprint = if version >= 2 {
+            Some(u64::from_le_bytes(FORMAT.read_array(&mut data)?))
+        } else {
+            None
+        }
+        .filter(|_| version >= 3);
+        let [frames, height, width, tile_height, tile_width] = dims;
+        if tile_height < EBCC_MIN_INTERNAL_IMAGE_DIM || tile_width < EBCC_MIN_INTERNAL_IMAGE_DIM {
+            return Err(FORMAT.corrupted());
+        }
+        validate_regular_ebcc_shape((frames, height, width))?;
+
+        let rows = tile_bounds(height, tile_height);
+        let cols = tile_bounds(width, tile_width);
+
+        let mut table = Vec::with_capacity(rows.len() * cols.len());
+        for _ in 0..(rows.len() * cols.len()) {
+            let base_cr = f32::from_bits(u32::from_le_bytes(FORMAT.read_array(&mut data)?));
+            let len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut data)?))?;
+            let checksum = u32::from_le_bytes(FORMAT.read_array(&mut data)?);
+            table.push((base_cr, len, checksum));
+        }
+
+        let mut tiles = Vec::with_capacity(table.len());
+        for (base_cr, len, checksum) in table {
+            let Some((payload, rest)) = data.split_at_checked(len) else {
+                return Err(FORMAT.truncated());
+            };
+            if crc32fast::hash(payload) != checksum {
+                return Err(EBCCError::ChecksumMismatch {
+                    data: EBCCChecksummedData::CompressedPayload,
+                });
+            }
+            data = rest;
+            tiles.push(Tile { base_cr, payload });
+        }
+        if !data.is_empty() {
+            return Err(FORMAT.corrupted());
+        }
+
+        Ok(Self {
+            shape: [frames, height, width],
+            config_fingerprint,
+            rows,
+            cols,
+            tiles,
+        })
+    }
+}
+
+/// Split an axis of length `len` into tiles of length `tile`, where a final
+/// tile that would be shorter than EBCC's minimum tile size is merged into
+/// the previous tile
+fn tile_bounds(len: usize, tile: usize) -> Vec<Range<usize>> {
+    let mut bounds: Vec<Range<usize>> = Vec::with_capacity(len.div_ceil(tile));
+
+    let mut start = 0;
+    while start < len {
+        let end = len.min(start.saturating_add(tile));
+        match bounds.last_mut() {
+            Some(last) if end - start < EBCC_MIN_INTERNAL_IMAGE_DIM => last.end = end,
+            _ => bounds.push(start..end),
+        }
+        start = end;
+    }
+
+    bounds
+}
+
+/// Distribute the base-layer size budget of the uniform `base_cr` over the
+/// `tiles` in proportion to their size and standard deviation, and return the
+/// base compression ratio of each tile
+#[expect(clippy::cast_precision_loss)]
+fn allocate_base_crs(tiles: &[ArrayView<f32, EbccDim>], base_cr: f32) -> Vec<f32> {
+    let base_cr = f64::from(base_cr);
+    let min_base_cr = base_cr / MAX_BASE_CR_SPREAD;
+    let max_base_cr = base_cr * MAX_BASE_CR_SPREAD;
+
+    let sizes = tiles
+        .iter()
+        .map(|tile| tile.len() as f64)
+        .collect::<Vec<_>>();
+    let std_devs = tiles
+        .iter()
+        .map(|tile| f64::from(tile.std(0.0)))
+        .collect::<Vec<_>>();
+
+    let budget = sizes.iter().sum::<f64>() / base_cr;
+    let mean_std_dev = std_devs.iter().sum::<f64>() / f64::max(1.0, sizes.len() as f64);
+    // constant tiles still receive a small share of the budget, and constant
+    //  data is split uniformly
+    let floor = if mean_std_dev > 0.0 {
+        mean_std_dev * 1e-3
+    } else {
+        1.0
+    };
+    let weights = sizes
+        .iter()
+        .zip(&std_devs)
+        .map(|(size, std_dev)| size * (std_dev + floor))
+        .collect::<Vec<_>>();
+
+    let tile_budgets = |scale: f64| {
+        sizes.iter().zip(&weights).map(move |(size, weight)| {
+            (scale * weight).clamp(size / max_base_cr, size / min_base_cr)
+        })
+    };
+
+    // find the largest scale whose clamped tile budgets still fit the budget
+    let (mut lo, mut hi) = (
+        0.0,
+        budget / weights.iter().copied().fold(f64::INFINITY, f64::min),
+    );
+    for _ in 0..BUDGET_BISECTION_STEPS {
+        let mid = (lo + hi) / 2.0;
+        if tile_budgets(mid).sum::<f64>() <= budget {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    #[expect(clippy::cast_possible_truncation)]
+    tile_budgets(lo)
+        .zip(&sizes)
+        .map(|(tile_budget, size)| (size / tile_budget) as f32)
+        .collect()
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+#[expect(clippy::indexing_slicing)]
+mod tests {
+    use ndarray::{Array, Axis};
+
+    use super::*;
+    use crate::{
+        codec::ebcc_decode_c_buffer_mut, ebcc_decode_into, ebcc_encode, ebcc_measure_resources,
+        testdata,
+    };
+
+    #[test]
+    fn test_tile_bounds() {
+        assert_eq!(tile_bounds(64, 32), [0..32, 32..64]);
+        assert_eq!(tile_bounds(80, 32), [0..32, 32..80]);
+        assert_eq!(tile_bounds(100, 32), [0..32, 32..64, 64..100]);
+        assert_eq!(tile_bounds(40, 64).len(), 1);
+    }
+
+    #[test]
+    fn test_adaptive_roundtrip() -> EBCCResult<()> {
+        // a smooth left half and a rough right half
+        let mut data = testdata::temperature((2, 64, 128));
+        let noise = testdata::noise((2, 64, 64), 20.0, 42);
+        data.slice_mut(s![.., .., 64..])
+            .zip_mut_with(&noise, |x, n| *x += n);
+
+        let config = EBCCConfig::max_absolute_error_bounded(0.1).with_base_cr(50.0);
+        let compressed = ebcc_encode_adaptive(data.view(), &config, (32, 32))?;
+
+        let base_crs = ebcc_adaptive_base_crs(&compressed)?;
+        assert_eq!(base_crs.dim(), (2, 4));
+        assert!(base_crs
+            .iter()
+            .all(|base_cr| (6.0..=401.0).contains(base_cr)));
+
+        // the rough tiles receive more of the budget than the smooth tiles
+        assert!(base_crs[(0, 0)] > base_crs[(0, 3)]);
+        assert!(base_crs[(1, 1)] > base_crs[(1, 2)]);
+
+        // the base-layer budget of the uniform base CR is not exceeded
+        let budget: f32 = base_crs.iter().map(|base_cr| 1.0 / base_cr).sum();
+        assert!(budget <= 8.0 / 50.0 * (1.0 + 1e-4));
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        assert!(decompressed
+            .iter()
+            .zip(data.iter())
+            .all(|(a, b)| (a - b).abs() <= 0.1 + 1e-6));
+
+        let mut wrong_shape = Array::zeros((2, 128, 64));
+        assert!(ebcc_decode_into(&compressed, wrong_shape.view_mut()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_adaptive_constant_data_is_uniform() -> EBCCResult<()> {
+        let data = Array::from_elem((1, 64, 96), 1.0_f32);
+        let config = EBCCConfig::jpeg2000_only(20.0);
+        let compressed = ebcc_encode_adaptive(data.view(), &config, (32, 32))?;
+
+        let base_crs = ebcc_adaptive_base_crs(&compressed)?;
+        assert!(base_crs.iter().all(|base_cr| (base_cr - 20.0).abs() < 1e-3));
+
+        assert!(ebcc_encode_adaptive(data.view(), &config, (16, 32)).is_err());
+        assert!(ebcc_adaptive_base_crs(&ebcc_encode(data.view(), &config)?).is_err());
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        assert_eq!(
+            decompressed.index_axis(Axis(0), 0),
+            data.index_axis(Axis(0), 0)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiled_fallback() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 64, 96));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let (compressed, usage) =
+            ebcc_measure_resources(|| ebcc_encode_tiled_fallback(data.view(), &config));
+        assert!(compressed.is_some());
+        let mut compressed = compressed.unwrap_or_else(|| Ok(Vec::new()))?;
+        assert_eq!(usage.tiling_fallbacks, 1);
+
+        // the uniform base CR is used for every tile
+        let base_crs = ebcc_adaptive_base_crs(&compressed)?;
+        assert_eq!(base_crs.dim(), (2, 2));
+        assert!(base_crs
+            .iter()
+            .all(|base_cr| base_cr.to_bits() == config.base_cr.to_bits()));
+
+        // the tiled payload is decoded like any other EBCC payload
+        let decompressed = ebcc_decode_c_buffer_mut(&mut compressed, data.dim())?;
+        assert!(decompressed
+            .as_slice()
+            .iter()
+            .zip(data.iter())
+            .all(|(a, b)| (a - b).abs() <= 0.1 + 1e-6));
+
+        // frames that cannot be split are not retried
+        let small = testdata::temperature((1, 48, 48));
+        let (compressed, usage) =
+            ebcc_measure_resources(|| ebcc_encode_tiled_fallback(small.view(), &config));
+        assert!(compressed.is_none());
+        assert_eq!(usage.tiling_fallbacks, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiled_version_1() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 64, 64));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let compressed = ebcc_encode_adaptive(data.view(), &config, (32, 32))?;
+        assert_eq!(
+            tiled_config_fingerprint(&compressed)?,
+            Some(config.fingerprint())
+        );
+
+        // version 1 has no config fingerprint after the dimensions
+        let mut legacy = compressed.clone();
+        legacy[8..12].copy_from_slice(&1_u32.to_le_bytes());
+        legacy.drain(HEADER_LEN - 8..HEADER_LEN);
+        assert_eq!(tiled_config_fingerprint(&legacy)?, None);
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, expected.view_mut())?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&legacy, decompressed.view_mut())?;
+        assert_eq!(decompressed, expected);
+
+        Ok(())
+    }
+}
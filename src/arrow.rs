@@ -0,0 +1,427 @@
+//! Arrow columns of compressed chunks and their metadata.
+//!
+//! Compressed chunks are stored as one row each, with the bytes of the
+//! [`ebcc_encode`][crate::ebcc_encode] payload next to the serialized
+//! [`EBCCConfig`], the shape, config fingerprint, and checksum from its
+//! [`EBCCHeader`], and the [`EBCCEncodeStats`] of the chunk, such that they
+//! can be written into Parquet files and filtered by their metadata without
+//! decoding them.
+
+use std::borrow::Borrow;
+use std::sync::Arc;
+
+use arrow_array::{
+    Array, ArrayRef, BinaryArray, FixedSizeListArray, Float32Array, Float64Array, LargeBinaryArray,
+    StringArray, StructArray, UInt64Array,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+use crate::manifest::EBCCEncodeStats;
+use crate::size::usize_to_u64;
+
+const COMPRESSED: &str = "compressed";
+const SHAPE: &str = "shape";
+const CONFIG: &str = "config";
+const CONFIG_FINGERPRINT: &str = "config_fingerprint";
+const CHECKSUM_ALGORITHM: &str = "checksum_algorithm";
+const CHECKSUM: &str = "checksum";
+const COMPRESSION_RATIO: &str = "compression_ratio";
+const MAX_ABS_ERROR: &str = "max_abs_error";
+const RMSE: &str = "rmse";
+const MIN: &str = "min";
+const MAX: &str = "max";
+const MEAN: &str = "mean";
+
+/// Arrow schema of the columns of compressed chunks that
+/// [`ebcc_to_arrow_struct`] produces and [`ebcc_from_arrow_struct`] reads.
+///
+/// | column               | type                              |
+/// |----------------------|-----------------------------------|
+/// | `compressed`         | `LargeBinary`                     |
+/// | `shape`              | `FixedSizeList<UInt64, 3>`        |
+/// | `config`             | `Binary`                          |
+/// | `config_fingerprint` | `UInt64`                          |
+/// | `checksum_algorithm` | `Utf8`                            |
+/// | `checksum`           | `Binary`                          |
+/// | `compression_ratio`  | `Float64`                         |
+/// | `max_abs_error`      | `Float32`                         |
+/// | `rmse`               | `Float64`                         |
+/// | `min`                | `Float32`                         |
+/// | `max`                | `Float32`                         |
+/// | `mean`               | `Float64`                         |
+///
+/// The `shape` is `[frames, height, width]`, the `config` is serialized with
+/// [`EBCCConfig::to_bytes`], the `config_fingerprint` is its
+/// [`EBCCConfig::fingerprint`], and the `checksum` has the
+/// [`EBCCChecksum::digest`][crate::EBCCChecksum::digest] bytes of the payload
+/// checksum. The remaining columns are the [`EBCCEncodeStats`] of the same
+/// name. None of the columns are nullable.
+#[must_use]
+pub fn ebcc_arrow_schema() -> Schema {
+    Schema::new(vec![
+        Field::new(COMPRESSED, DataType::LargeBinary, false),
+        Field::new(SHAPE, DataType::FixedSizeList(shape_item_field(), 3), false),
+        Field::new(CONFIG, DataType::Binary, false),
+        Field::new(CONFIG_FINGERPRINT, DataType::UInt64, false),
+        Field::new(CHECKSUM_ALGORITHM, DataType::Utf8, false),
+        Field::new(CHECKSUM, DataType::Binary, false),
+        Field::new(COMPRESSION_RATIO, DataType::Float64, false),
+        Field::new(MAX_ABS_ERROR, DataType::Float32, false),
+        Field::new(RMSE, DataType::Float64, false),
+        Field::new(MIN, DataType::Float32, false),
+        Field::new(MAX, DataType::Float32, false),
+        Field::new(MEAN, DataType::Float64, false),
+    ])
+}
+
+/// Store `compressed` chunks with their `configs` and `stats` as the rows of
+/// an Arrow [`StructArray`] with the [`ebcc_arrow_schema`].
+///
+/// The `stats` are usually produced together with the chunks by
+/// [`ebcc_encode_with_stats`][crate::ebcc_encode_with_stats].
+/// The shape, config fingerprint, and checksum columns are read from the
+/// [`EBCCHeader`] of each chunk. The array can be converted into a
+/// `RecordBatch` with `RecordBatch::from` to write it into a Parquet file.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the number of `compressed` chunks,
+///   `configs`, and `stats` differ, if a chunk does not start with an
+///   [`EBCCHeader`], e.g. because it is a legacy headerless payload, an EBCC
+///   frame stream, or an EBCC container, or if the header of a chunk does
+///   not match its configuration or statistics
+/// - [`EBCCError::DecompressionError`] if the header version, data type, or
+///   flags of a chunk are not supported
+///
+/// # Examples
+///
+/// ```rust
+/// use ebcc::{ebcc_encode_with_stats, ebcc_from_arrow_struct, ebcc_to_arrow_struct, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((2, 32, 48), |(t, y, x)| (t + y + x) as f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.01);
+/// let (compressed, stats) = ebcc_encode_with_stats(data.view(), &config)?;
+///
+/// let chunks = ebcc_to_arrow_struct(&[&compressed], &[&config], &[stats.clone()])?;
+/// assert_eq!(
+///     ebcc_from_arrow_struct(&chunks)?,
+///     vec![(compressed.as_slice(), config, stats)]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_to_arrow_struct(
+    compressed: &[impl AsRef<[u8]>],
+    configs: &[impl Borrow<EBCCConfig>],
+    stats: &[EBCCEncodeStats],
+) -> EBCCResult<StructArray> {
+    if compressed.len() != configs.len() || compressed.len() != stats.len() {
+        return Err(EBCCError::InvalidInput(format!(
+            "{} compressed chunks require {} configurations and statistics, got {} and {}",
+            compressed.len(),
+            compressed.len(),
+            configs.len(),
+            stats.len(),
+        )));
+    }
+
+    let headers = compressed
+        .iter()
+        .zip(configs)
+        .zip(stats)
+        .map(|((chunk, config), stats)| {
+            let chunk = chunk.as_ref();
+            let header = chunk_header(chunk)?;
+            if !header_matches(&header, chunk, config.borrow(), stats) {
+                return Err(EBCCError::InvalidInput(String::from(
+                    "EBCC Arrow chunk does not match its configuration or statistics",
+                )));
+            }
+            Ok(header)
+        })
+        .collect::<EBCCResult<Vec<_>>>()?;
+
+    let mut shapes = Vec::with_capacity(headers.len() * 3);
+    for header in &headers {
+        for dim in header.shape {
+            shapes.push(usize_to_u64(dim)?);
+        }
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(LargeBinaryArray::from_iter_values(
+            compressed.iter().map(AsRef::as_ref),
+        )),
+        Arc::new(
+            FixedSizeListArray::try_new(
+                shape_item_field(),
+                3,
+                Arc::new(UInt64Array::from(shapes)),
+                None,
+            )
+            .map_err(|err| arrow_error(&err))?,
+        ),
+        Arc::new(BinaryArray::from_iter_values(
+            configs.iter().map(|config| config.borrow().to_bytes()),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            headers.iter().map(|header| header.config_fingerprint),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            headers
+                .iter()
+                .map(|header| header.checksum.algorithm().to_string()),
+        )),
+        Arc::new(BinaryArray::from_iter_values(
+            headers.iter().map(|header| header.checksum.digest()),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            stats.iter().map(|stats| stats.compression_ratio),
+        )),
+        Arc::new(Float32Array::from_iter_values(
+            stats.iter().map(|stats| stats.max_abs_error),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            stats.iter().map(|stats| stats.rmse),
+        )),
+        Arc::new(Float32Array::from_iter_values(
+            stats.iter().map(|stats| stats.min),
+        )),
+        Arc::new(Float32Array::from_iter_values(
+            stats.iter().map(|stats| stats.max),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            stats.iter().map(|stats| stats.mean),
+        )),
+    ];
+
+    StructArray::try_new(ebcc_arrow_schema().fields().clone(), columns, None)
+        .map_err(|err| arrow_error(&err))
+}
+
+/// Read the compressed chunks, their configurations, and their statistics
+/// back from the rows of an Arrow [`StructArray`] with the
+/// [`ebcc_arrow_schema`], e.g. one that was read from a Parquet file.
+///
+/// The compressed chunks are borrowed from the `chunks` without copying.
+/// The shape, config, config fingerprint, and checksum columns of each row
+/// are checked against the [`EBCCHeader`] of its compressed chunk, such that
+/// metadata that was modified independently of the chunk is detected.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if a column is missing, has the wrong type,
+///   or contains nulls, if a chunk does not start with an [`EBCCHeader`], if
+///   a config is not serialized with [`EBCCConfig::to_bytes`], or if the
+///   metadata of a row does not match the header of its chunk
+/// - [`EBCCError::DecompressionError`] if the header version, data type, or
+///   flags of a chunk, or the checksum algorithm of a config, are not
+///   supported
+pub fn ebcc_from_arrow_struct(
+    chunks: &StructArray,
+) -> EBCCResult<Vec<(&[u8], EBCCConfig, EBCCEncodeStats)>> {
+    let compressed = column::<LargeBinaryArray>(chunks, COMPRESSED)?;
+    let shapes = column::<FixedSizeListArray>(chunks, SHAPE)?;
+    let shape_values = shapes
+        .values()
+        .as_any()
+        .downcast_ref::<UInt64Array>()
+        .filter(|values| shapes.value_length() == 3 && values.null_count() == 0)
+        .ok_or_else(|| invalid_column(SHAPE))?;
+    let configs = column::<BinaryArray>(chunks, CONFIG)?;
+    let config_fingerprints = column::<UInt64Array>(chunks, CONFIG_FINGERPRINT)?;
+    let checksum_algorithms = column::<StringArray>(chunks, CHECKSUM_ALGORITHM)?;
+    let checksums = column::<BinaryArray>(chunks, CHECKSUM)?;
+    let compression_ratios = column::<Float64Array>(chunks, COMPRESSION_RATIO)?;
+    let max_abs_errors = column::<Float32Array>(chunks, MAX_ABS_ERROR)?;
+    let rmses = column::<Float64Array>(chunks, RMSE)?;
+    let mins = column::<Float32Array>(chunks, MIN)?;
+    let maxs = column::<Float32Array>(chunks, MAX)?;
+    let means = column::<Float64Array>(chunks, MEAN)?;
+
+    (0..chunks.len())
+        .map(|row| {
+            let chunk = compressed.value(row);
+            let header = chunk_header(chunk)?;
+            let config = EBCCConfig::from_bytes(configs.value(row))?;
+
+            let offset = usize::try_from(shapes.value_offset(row)).unwrap_or(usize::MAX);
+            let shape = shape_values
+                .values()
+                .get(offset..offset.saturating_add(3))
+                .ok_or_else(|| invalid_column(SHAPE))?;
+            let header_shape = header
+                .shape
+                .iter()
+                .map(|dim| usize_to_u64(*dim))
+                .collect::<EBCCResult<Vec<_>>>()?;
+
+            if shape != header_shape.as_slice()
+                || config.fingerprint() != header.config_fingerprint
+                || config_fingerprints.value(row) != header.config_fingerprint
+                || checksum_algorithms.value(row) != header.checksum.algorithm().to_string()
+                || checksums.value(row) != header.checksum.digest()
+            {
+                return Err(EBCCError::InvalidInput(format!(
+                    "Metadata of EBCC Arrow row {row} does not match the header of its compressed chunk",
+                )));
+            }
+
+            let stats = EBCCEncodeStats {
+                shape: header.shape,
+                config_fingerprint: header.config_fingerprint,
+                base_cr: config.base_cr,
+                base_mode: config.base_mode,
+                residual_compression_type: config.residual_compression_type,
+                checksum_algorithm: header.checksum.algorithm(),
+                checksum: Vec::from(header.checksum.digest()),
+                compressed_len: usize_to_u64(chunk.len())?,
+                compression_ratio: compression_ratios.value(row),
+                max_abs_error: max_abs_errors.value(row),
+                rmse: rmses.value(row),
+                min: mins.value(row),
+                max: maxs.value(row),
+                mean: means.value(row),
+            };
+
+            Ok((chunk, config, stats))
+        })
+        .collect()
+}
+
+/// Check that the `header` of the `chunk` matches the `config` and the
+/// `stats` that are stored next to it.
+fn header_matches(
+    header: &EBCCHeader,
+    chunk: &[u8],
+    config: &EBCCConfig,
+    stats: &EBCCEncodeStats,
+) -> bool {
+    header.config_fingerprint == config.fingerprint()
+        && header.config_fingerprint == stats.config_fingerprint
+        && header.shape == stats.shape
+        && header.checksum.algorithm() == stats.checksum_algorithm
+        && header.checksum.digest() == stats.checksum.as_slice()
+        && usize_to_u64(chunk.len()).is_ok_and(|len| len == stats.compressed_len)
+}
+
+fn shape_item_field() -> Arc<Field> {
+    Arc::new(Field::new("item", DataType::UInt64, false))
+}
+
+/// Parse the header of a compressed chunk, which is required.
+fn chunk_header(chunk: &[u8]) -> EBCCResult<EBCCHeader> {
+    EBCCHeader::parse(chunk)?.ok_or_else(|| {
+        EBCCError::InvalidInput(String::from(
+            "EBCC Arrow chunks must be payloads that start with an EBCC header",
+        ))
+    })
+}
+
+/// Get the non-null column with the `name` and type `T`.
+fn column<'a, T: Array + 'static>(chunks: &'a StructArray, name: &str) -> EBCCResult<&'a T> {
+    chunks
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<T>())
+        .filter(|column| column.null_count() == 0)
+        .ok_or_else(|| invalid_column(name))
+}
+
+fn invalid_column(name: &str) -> EBCCError {
+    EBCCError::InvalidInput(format!(
+        "EBCC Arrow column `{name}` is missing, has the wrong type, or contains nulls",
+    ))
+}
+
+fn arrow_error(err: &ArrowError) -> EBCCError {
+    EBCCError::InvalidInput(format!("Invalid EBCC Arrow data: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ebcc_encode_with_stats, testdata, EBCCChecksumAlgorithm};
+
+    #[test]
+    fn test_arrow_roundtrip() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let configs = [
+            EBCCConfig::max_absolute_error_bounded(0.1),
+            EBCCConfig::max_absolute_error_bounded(0.5)
+                .with_checksum_algorithm(EBCCChecksumAlgorithm::Xxh3),
+        ];
+
+        let (compressed, stats): (Vec<_>, Vec<_>) = configs
+            .iter()
+            .map(|config| ebcc_encode_with_stats(data.view(), config))
+            .collect::<EBCCResult<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+
+        let chunks = ebcc_to_arrow_struct(&compressed, &configs, &stats)?;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.fields(), ebcc_arrow_schema().fields());
+        assert_eq!(
+            column::<UInt64Array>(&chunks, CONFIG_FINGERPRINT)?
+                .values()
+                .to_vec(),
+            configs
+                .iter()
+                .map(EBCCConfig::fingerprint)
+                .collect::<Vec<_>>()
+        );
+
+        let roundtrip = ebcc_from_arrow_struct(&chunks)?;
+        assert_eq!(
+            roundtrip,
+            compressed
+                .iter()
+                .zip(&configs)
+                .zip(&stats)
+                .map(|((compressed, config), stats)| {
+                    (compressed.as_slice(), config.clone(), stats.clone())
+                })
+                .collect::<Vec<_>>()
+        );
+
+        // sliced arrays are read with their offsets
+        let sliced = chunks.slice(1, 1);
+        assert_eq!(
+            ebcc_from_arrow_struct(&sliced)?,
+            roundtrip.get(1..).unwrap_or_default()
+        );
+
+        // metadata that does not match the chunk is rejected
+        let (fields, mut columns, _) = chunks.into_parts();
+        if let Some(column) = columns.first_mut() {
+            *column = Arc::new(LargeBinaryArray::from_iter_values(
+                compressed.iter().rev().map(Vec::as_slice),
+            ));
+        }
+        let swapped =
+            StructArray::try_new(fields, columns, None).map_err(|err| arrow_error(&err))?;
+        assert!(ebcc_from_arrow_struct(&swapped).is_err());
+
+        // the configurations and statistics must match the chunks
+        assert!(
+            ebcc_to_arrow_struct(&compressed, &configs, stats.get(..1).unwrap_or_default())
+                .is_err()
+        );
+        assert!(ebcc_to_arrow_struct(&compressed, &[&configs[1], &configs[0]], &stats).is_err());
+        let reversed = stats.iter().rev().cloned().collect::<Vec<_>>();
+        assert!(ebcc_to_arrow_struct(&compressed, &configs, &reversed).is_err());
+        assert!(ebcc_to_arrow_struct(
+            &[[0_u8; 8]],
+            &configs[..1],
+            stats.get(..1).unwrap_or_default()
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
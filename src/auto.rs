@@ -0,0 +1,172 @@
+//! Automatic selection of the EBCC configuration from a sample of the data.
+
+use ndarray::{Array, ArrayView, Axis};
+
+use crate::codec::{ebcc_encode, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::finite::validate_only_finite_data;
+use crate::params::validate_regular_ebcc_shape;
+use crate::verify::data_range;
+
+/// Maximum number of evenly spaced frames that are profiled
+const AUTO_SAMPLE_FRAMES: usize = 4;
+
+/// Base compression ratios that are tried for a maximum error target
+const AUTO_BASE_CRS: [f32; 7] = [5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0];
+
+/// Smallest error bound, relative to the data range, that is tried for a
+/// compression ratio target
+const AUTO_MIN_RELATIVE_ERROR: f32 = 1e-6;
+
+/// Number of bisection steps over the error bound
+const AUTO_BISECTION_STEPS: usize = 16;
+
+/// Target of the automatic configuration selection with
+/// [`EBCCConfig::auto_for`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EBCCTarget {
+    /// Reach at least this compression ratio with the smallest possible
+    /// absolute error bound
+    CompressionRatio(f32),
+    /// Guarantee this absolute error bound with the best possible
+    /// compression ratio
+    MaxError(f32),
+}
+
+/// Select the configuration for the `data` that meets the `target`, see
+/// [`EBCCConfig::auto_for`].
+pub fn auto_config(data: ArrayView<f32, EbccDim>, target: EBCCTarget) -> EBCCResult<EBCCConfig> {
+    if data.is_empty() {
+        return Err(EBCCError::EmptyDimension);
+    }
+    validate_regular_ebcc_shape(data.dim())?;
+    validate_only_finite_data(&data)?;
+
+    let sample = sample_frames(data);
+    let sample = sample.view();
+
+    // the sample has already been checked for non-finite values
+    let sample_len = |config: &EBCCConfig| -> EBCCResult<usize> {
+        Ok(ebcc_encode(sample, &config.clone().skip_finite_check())?.len())
+    };
+
+    match target {
+        EBCCTarget::MaxError(error) => {
+            if !(error.is_finite() && error > 0.0) {
+                return Err(EBCCError::InvalidConfig(format!(
+                    "Target error bound must be finite and positive, got {error}"
+                )));
+            }
+
+            // the best base compression ratio depends on how much of the
+            //  data's variance the base layer captures, so it is profiled
+            let mut best: Option<(usize, EBCCConfig)> = None;
+            for base_cr in AUTO_BASE_CRS {
+                let config = EBCCConfig::max_absolute_error_bounded(error).with_base_cr(base_cr);
+                let len = sample_len(&config)?;
+                if best.as_ref().is_none_or(|(best_len, _)| len < *best_len) {
+                    best = Some((len, config));
+                }
+            }
+
+            best.map(|(_, config)| config)
+                .ok_or_else(|| EBCCError::CompressionError("No configuration was profiled".into()))
+        }
+        EBCCTarget::CompressionRatio(ratio) => {
+            if !(ratio.is_finite() && ratio >= 1.0) {
+                return Err(EBCCError::InvalidConfig(format!(
+                    "Target compression ratio must be finite and at least 1, got {ratio}"
+                )));
+            }
+
+            // the residual layer needs some of the budget, so the base layer
+            //  is compressed more strongly than the target
+            let base_cr = ratio * 2.0;
+            let fallback = EBCCConfig::jpeg2000_only(ratio);
+
+            let range = data_range(sample);
+            if range <= 0.0 {
+                return Ok(fallback);
+            }
+
+            #[expect(clippy::cast_precision_loss)]
+            let raw_len = (sample.len() * std::mem::size_of::<f32>()) as f32;
+            #[expect(clippy::cast_precision_loss)]
+            let reaches = |error: f32| -> EBCCResult<bool> {
+                let config = EBCCConfig::max_absolute_error_bounded(error).with_base_cr(base_cr);
+                Ok(raw_len / (sample_len(&config)? as f32) >= ratio)
+            };
+
+            if !reaches(range)? {
+                return Ok(fallback);
+            }
+
+            // bisect in log space for the smallest error bound that still
+            //  reaches the target, since the compression ratio changes with
+            //  the order of magnitude of the error bound
+            let (mut lo, mut hi) = ((range * AUTO_MIN_RELATIVE_ERROR).ln(), range.ln());
+            for _ in 0..AUTO_BISECTION_STEPS {
+                let mid = (lo + hi) / 2.0;
+                if reaches(mid.exp())? {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+
+            Ok(EBCCConfig::max_absolute_error_bounded(hi.exp()).with_base_cr(base_cr))
+        }
+    }
+}
+
+/// Select up to four evenly spaced frames of the `data`, which capture its
+/// variability over time, as a sample for profiling.
+pub fn sample_frames(data: ArrayView<f32, EbccDim>) -> Array<f32, EbccDim> {
+    let (frames, _, _) = data.dim();
+    let samples = frames.min(AUTO_SAMPLE_FRAMES);
+    let indices = (0..samples)
+        .map(|i| i * frames / samples)
+        .collect::<Vec<_>>();
+    data.select(Axis(0), &indices)
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::{testdata, EBCCResidualType};
+
+    #[test]
+    fn test_auto_config() -> EBCCResult<()> {
+        let data = testdata::temperature((6, 32, 48));
+
+        let config = EBCCConfig::auto_for(data.view(), EBCCTarget::MaxError(0.1))?;
+        assert_eq!(
+            config.residual_compression_type,
+            EBCCResidualType::AbsoluteError(0.1)
+        );
+        assert!(AUTO_BASE_CRS
+            .iter()
+            .any(|base_cr| base_cr.to_bits() == config.base_cr.to_bits()));
+
+        let config = EBCCConfig::auto_for(data.view(), EBCCTarget::CompressionRatio(10.0))?;
+        assert!(matches!(
+            config.residual_compression_type,
+            EBCCResidualType::AbsoluteError(_) | EBCCResidualType::Jpeg2000Only
+        ));
+        config.validate()?;
+
+        for target in [
+            EBCCTarget::MaxError(0.0),
+            EBCCTarget::MaxError(f32::NAN),
+            EBCCTarget::CompressionRatio(0.5),
+        ] {
+            assert!(matches!(
+                EBCCConfig::auto_for(data.view(), target),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+//! Batch encoding of many chunks with bounded memory.
+
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::thread;
+
+use ndarray::ArrayView;
+
+use crate::accounting::record_alloc;
+use crate::capture::propagate_capture;
+use crate::codec::{ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::{write_header, EBCCHeader};
+
+/// Encode an iterator of 3D chunks, e.g. of a Zarr-style chunked store, one
+/// at a time.
+///
+/// The chunks are only encoded when the returned iterator is advanced, such
+/// that only one chunk is in flight at a time, and the staging buffer for
+/// the copy of the input data is reused across chunks. Every item is
+/// identical to the output of [`ebcc_encode`][crate::ebcc_encode] for the
+/// corresponding chunk.
+///
+/// # Examples
+///
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+/// use ebcc::{ebcc_encode_batch, EBCCConfig};
+/// use ndarray::{Array, Axis};
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((4, 32, 32), |(t, y, x)| (t + y + x) as f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let chunks = ebcc_encode_batch(data.axis_chunks_iter(Axis(0), 2), &config)
+///     .collect::<ebcc::EBCCResult<Vec<_>>>()?;
+/// assert_eq!(chunks.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_batch<'a, I: IntoIterator<Item = ArrayView<'a, f32, EbccDim>>>(
+    chunks: I,
+    config: &EBCCConfig,
+) -> EbccBatchEncoder<I::IntoIter> {
+    EbccBatchEncoder::new(chunks.into_iter(), config.clone(), NonZeroUsize::MIN)
+}
+
+/// Encode an iterator of 3D chunks, e.g. of a Zarr-style chunked store, with
+/// up to `in_flight` chunks encoded in parallel.
+///
+/// Whenever the returned iterator runs out of encoded chunks, it takes up to
+/// `in_flight` chunks from the `chunks` and encodes them on scoped threads,
+/// such that at most `in_flight` chunks and their encodings are held in
+/// memory at a time. Every thread reuses its staging buffer across batches.
+/// The items are returned in the order of the `chunks`, and are identical to
+/// the output of [`ebcc_encode_batch`].
+///
+/// The number of chunks in flight is capped at the
+/// [`max_threads`][crate::EBCCLimits::max_threads] of the
+/// [`config.limits`][EBCCConfig::limits].
+///
+/// Note that the EBCC C library is not thread-safe and is only called by one
+/// thread at a time, so only the remaining work, e.g. validating and copying
+/// the chunks, runs in parallel.
+pub fn ebcc_encode_batch_parallel<'a, I: IntoIterator<Item = ArrayView<'a, f32, EbccDim>>>(
+    chunks: I,
+    config: &EBCCConfig,
+    in_flight: NonZeroUsize,
+) -> EbccBatchEncoder<I::IntoIter> {
+    let in_flight = config.limits.threads(in_flight);
+    EbccBatchEncoder::new(chunks.into_iter(), config.clone(), in_flight)
+}
+
+/// Iterator over the encoded chunks of [`ebcc_encode_batch`] and
+/// [`ebcc_encode_batch_parallel`].
+#[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct EbccBatchEncoder<I> {
+    chunks: I,
+    config: EBCCConfig,
+    scratch: Vec<Vec<f32>>,
+    encoded: VecDeque<EBCCResult<Vec<u8>>>,
+}
+
+impl<I> EbccBatchEncoder<I> {
+    fn new(chunks: I, config: EBCCConfig, in_flight: NonZeroUsize) -> Self {
+        Self {
+            chunks,
+            config,
+            scratch: vec![Vec::new(); in_flight.get()],
+            encoded: VecDeque::with_capacity(in_flight.get()),
+        }
+    }
+
+    /// The configuration that the chunks are encoded with.
+    pub const fn config(&self) -> &EBCCConfig {
+        &self.config
+    }
+}
+
+impl<'a, I: Iterator<Item = ArrayView<'a, f32, EbccDim>>> Iterator for EbccBatchEncoder<I> {
+    type Item = EBCCResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(encoded) = self.encoded.pop_front() {
+            return Some(encoded);
+        }
+
+        let chunks = self
+            .chunks
+            .by_ref()
+            .take(self.scratch.len())
+            .collect::<Vec<_>>();
+
+        let config = &self.config;
+        match chunks.as_slice() {
+            [] => return None,
+            [chunk] => {
+                let scratch = self.scratch.first_mut()?;
+                return Some(encode_chunk(*chunk, config, scratch));
+            }
+            _ => (),
+        }
+
+        thread::scope(|scope| {
+            let threads = chunks
+                .into_iter()
+                .zip(self.scratch.iter_mut())
+                .map(|(chunk, scratch)| {
+                    scope.spawn(propagate_capture(move || {
+                        encode_chunk(chunk, config, scratch)
+                    }))
+                })
+                .collect::<Vec<_>>();
+
+            self.encoded.extend(threads.into_iter().map(|thread| {
+                thread.join().unwrap_or_else(|_| {
+                    Err(EBCCError::CompressionError(
+                        "EBCC batch encoding thread panicked".into(),
+                    ))
+                })
+            }));
+        });
+
+        self.encoded.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.chunks.size_hint();
+        (
+            lower.saturating_add(self.encoded.len()),
+            upper.and_then(|upper| upper.checked_add(self.encoded.len())),
+        )
+    }
+}
+
+fn encode_chunk(
+    chunk: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let payload = ebcc_encode_c_buffer_with_scratch(chunk, config, scratch)?;
+    let payload = payload.as_slice();
+
+    let mut compressed_data =
+        Vec::with_capacity(EBCCHeader::encoded_len_with(config.checksum_algorithm) + payload.len());
+    record_alloc(compressed_data.capacity());
+    write_header(&mut compressed_data, chunk, config, payload)?;
+    compressed_data.extend_from_slice(payload);
+
+    Ok(compressed_data)
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Axis;
+
+    use super::*;
+    use crate::{ebcc_encode, testdata};
+
+    #[test]
+    fn test_encode_batch() -> EBCCResult<()> {
+        let data = testdata::temperature((5, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let expected = data
+            .axis_chunks_iter(Axis(0), 2)
+            .map(|chunk| ebcc_encode(chunk, &config))
+            .collect::<EBCCResult<Vec<_>>>()?;
+
+        for in_flight in [1, 2, 4] {
+            let Some(in_flight) = NonZeroUsize::new(in_flight) else {
+                continue;
+            };
+            let encoded =
+                ebcc_encode_batch_parallel(data.axis_chunks_iter(Axis(0), 2), &config, in_flight)
+                    .collect::<EBCCResult<Vec<_>>>()?;
+            assert_eq!(encoded, expected);
+        }
+
+        // errors are reported per chunk
+        let invalid = EBCCConfig::max_absolute_error_bounded(-1.0);
+        assert!(
+            ebcc_encode_batch(data.axis_chunks_iter(Axis(0), 2), &invalid)
+                .all(|encoded| encoded.is_err())
+        );
+
+        Ok(())
+    }
+}
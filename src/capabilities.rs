@@ -0,0 +1,156 @@
+//! Report of the optional features and formats that this build of EBCC
+//! includes.
+
+use std::fmt;
+
+/// Optional features that this build of EBCC was compiled with and the
+/// formats that it decodes, see [`capabilities`].
+///
+/// The report is intended for support requests and provenance records, e.g.
+/// next to the [fingerprint][crate::EBCCConfig::fingerprint] of the
+/// configuration that some data was compressed with. Its [`Display`]
+/// implementation lists threading and all Cargo features as `+feature` or
+/// `-feature`, followed by `formats=` and the comma-separated decoded formats.
+///
+/// [`Display`]: fmt::Display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[expect(clippy::struct_excessive_bools)] // independent features
+pub struct EBCCCapabilities {
+    /// Version of the `ebcc` crate, including the version of the EBCC C
+    /// library as build metadata
+    pub version: &'static str,
+    /// Whether the `OpenJPEG` library that EBCC is linked with may use
+    /// threads, see [`ebcc_sys::OPENJPEG_THREADS`]
+    pub threads: bool,
+    /// Whether the `rayon` feature for parallel encoding is enabled
+    pub rayon: bool,
+    /// Whether the `async` feature for offloading to a thread pool is
+    /// enabled
+    pub async_offload: bool,
+    /// Whether the `tracing` feature for instrumentation is enabled
+    pub tracing: bool,
+    /// Whether the `bytemuck` feature for casting stream headers is enabled
+    pub bytemuck: bool,
+    /// Whether the `conformance` feature for conformance vectors is enabled
+    pub conformance: bool,
+    /// All Cargo features of the `ebcc` crate, except for `default`, and
+    /// whether they are enabled
+    pub features: &'static [(&'static str, bool)],
+    /// Names of all formats that [`ebcc_decode_into`][crate::ebcc_decode_into]
+    /// recognises, in the order in which it checks for them
+    pub formats: &'static [&'static str],
+}
+
+/// List the given Cargo features and whether they are enabled.
+macro_rules! features {
+    ($($feature:literal),* $(,)?) => {
+        &[$(($feature, cfg!(feature = $feature))),*]
+    };
+}
+
+/// Report the optional features that this build of EBCC was compiled with.
+///
+/// # Examples
+///
+/// ```rust
+/// let capabilities = ebcc::capabilities();
+/// println!("ebcc {} ({capabilities})", capabilities.version);
+/// ```
+#[must_use]
+pub const fn capabilities() -> EBCCCapabilities {
+    EBCCCapabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        threads: ebcc_sys::OPENJPEG_THREADS,
+        rayon: cfg!(feature = "rayon"),
+        async_offload: cfg!(feature = "async"),
+        tracing: cfg!(feature = "tracing"),
+        bytemuck: cfg!(feature = "bytemuck"),
+        conformance: cfg!(feature = "conformance"),
+        features: features!(
+            "std",
+            "encode",
+            "decode",
+            "system-libs",
+            "rust-alloc",
+            "single-threaded",
+            "arrow",
+            "async",
+            "blake3",
+            "bytemuck",
+            "conformance",
+            "half",
+            "mmap",
+            "nalgebra",
+            "ndarray",
+            "ndarray015",
+            "ndarray017",
+            "netcdf",
+            "rayon",
+            "serde",
+            "tracing",
+        ),
+        formats: &crate::codec::DECODED_FORMATS,
+    }
+}
+
+impl fmt::Display for EBCCCapabilities {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let threads = [("threads", self.threads)];
+        let features = threads.iter().chain(self.features);
+
+        for (feature, enabled) in features {
+            write!(fmt, "{}{feature} ", if *enabled { '+' } else { '-' })?;
+        }
+
+        write!(fmt, "formats={}", self.formats.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = capabilities();
+
+        assert!(capabilities.version.contains("+ebcc."));
+        assert_eq!(capabilities.rayon, cfg!(feature = "rayon"));
+
+        let report = capabilities.to_string();
+        let (features, formats) = report.rsplit_once(' ').unwrap_or_default();
+        assert!(features.contains("threads"));
+        assert_eq!(features.split(' ').count(), capabilities.features.len() + 1);
+        assert!(features
+            .split(' ')
+            .all(|feature| feature.starts_with('+') || feature.starts_with('-')));
+        assert_eq!(
+            formats.strip_prefix("formats="),
+            Some(capabilities.formats.join(",").as_str())
+        );
+        assert!(capabilities.formats.contains(&"stored"));
+        assert!(capabilities.formats.contains(&"tiled"));
+    }
+
+    #[test]
+    fn test_capabilities_list_all_features() {
+        let manifest = include_str!("../Cargo.toml");
+        let manifest_features = manifest
+            .lines()
+            .skip_while(|line| *line != "[features]")
+            .skip(1)
+            .take_while(|line| !line.starts_with('['))
+            .filter_map(|line| line.split_once(" = ").map(|(feature, _)| feature))
+            .filter(|feature| *feature != "default")
+            .collect::<Vec<_>>();
+
+        let features = capabilities()
+            .features
+            .iter()
+            .map(|(feature, _)| *feature)
+            .collect::<Vec<_>>();
+
+        assert_eq!(features, manifest_features);
+    }
+}
@@ -0,0 +1,968 @@
+//! Capture of reproduction bundles for failed encode and decode calls.
+//!
+//! # Bundle format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_CAPTURE_MAGIC`] and the format version as `u32`
+//! - the operation as `u8`, the shape as three `u64`s, and the optional
+//!   chunk shape as a `u8` that is `1` if it is present followed by three
+//!   `u64`s
+//! - the content hash as `u64`
+//! - the error message, the configuration, serialized with
+//!   [`EBCCConfig::to_bytes`], and the file name of the raw input, each as
+//!   its `u64` length followed by its bytes, where an empty configuration or
+//!   file name means that there is none
+
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::mem;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use ndarray::{Array, ArrayView};
+
+use crate::codec::{
+    ebcc_decode_chunking_into, ebcc_decode_into, ebcc_encode, ebcc_encode_chunking,
+    ebcc_encode_chunking_compat, EBCCChunkShape, EBCCCompatChunkShape, EbccDim,
+};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::limits::EBCCDecodeOptions;
+use crate::reader::Format;
+use crate::size::u64_to_usize;
+use crate::trace::warn_event;
+
+/// Magic bytes at the start of every serialized [`EBCCCaptureBundle`].
+pub const EBCC_CAPTURE_MAGIC: &[u8; 8] = b"EBCCCAPT";
+
+/// Version of the serialized [`EBCCCaptureBundle`] format.
+const EBCC_CAPTURE_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC capture bundle");
+
+/// Options of [`ebcc_with_capture`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct EBCCCaptureOptions {
+    /// Optional directory into which the raw input of every failed call is
+    /// written, together with its serialized [`EBCCCaptureBundle`], which
+    /// [`EBCCCaptureBundle::load`] reads back, and a text description of it
+    pub data_dir: Option<PathBuf>,
+}
+
+impl EBCCCaptureOptions {
+    /// Create new capture options, which only capture the metadata of
+    /// failed calls.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { data_dir: None }
+    }
+
+    /// Also write the raw input of every failed call into the `data_dir`,
+    /// such that the call can be [replayed][EBCCCaptureBundle::replay].
+    #[must_use]
+    pub fn with_data_dir(mut self, data_dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = Some(data_dir.into());
+        self
+    }
+}
+
+/// Operation of a failed call that was captured in an [`EBCCCaptureBundle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EBCCCaptureOperation {
+    /// [`ebcc_encode`] of `f32` data, or any other encoder that produces the
+    /// same payload, e.g. [`ebcc_encode_mut`][crate::ebcc_encode_mut]
+    Encode,
+    /// [`ebcc_decode_into`] of compressed data, or any other decoder of the
+    /// same formats, e.g. [`ebcc_decode_mut_into`][crate::ebcc_decode_mut_into]
+    Decode,
+    /// [`ebcc_encode_chunking`] of `f32` data
+    EncodeChunking,
+    /// [`ebcc_encode_chunking_compat`] of `f32` data
+    EncodeChunkingCompat,
+    /// [`ebcc_decode_chunking_into`] of chunked compressed data
+    DecodeChunking,
+}
+
+impl EBCCCaptureOperation {
+    const fn code(self) -> u8 {
+        match self {
+            Self::Encode => 0,
+            Self::Decode => 1,
+            Self::EncodeChunking => 2,
+            Self::EncodeChunkingCompat => 3,
+            Self::DecodeChunking => 4,
+        }
+    }
+
+    fn from_code(code: u8) -> EBCCResult<Self> {
+        match code {
+            0 => Ok(Self::Encode),
+            1 => Ok(Self::Decode),
+            2 => Ok(Self::EncodeChunking),
+            3 => Ok(Self::EncodeChunkingCompat),
+            4 => Ok(Self::DecodeChunking),
+            _ => Err(FORMAT.corrupted()),
+        }
+    }
+}
+
+impl fmt::Display for EBCCCaptureOperation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Encode => fmt.write_str("encode"),
+            Self::Decode => fmt.write_str("decode"),
+            Self::EncodeChunking => fmt.write_str("encode-chunking"),
+            Self::EncodeChunkingCompat => fmt.write_str("encode-chunking-compat"),
+            Self::DecodeChunking => fmt.write_str("decode-chunking"),
+        }
+    }
+}
+
+/// Exact inputs of a failed call, which [`ebcc_with_capture`] records to
+/// reproduce the failure outside of the process that hit it.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct EBCCCaptureBundle {
+    /// Operation of the failed call
+    pub operation: EBCCCaptureOperation,
+    /// Shape `[frames, height, width]` of the data that was encoded or
+    /// decoded into
+    pub shape: [usize; 3],
+    /// Configuration of a failed encode
+    pub config: Option<EBCCConfig>,
+    /// Chunk shape of a failed [`ebcc_encode_chunking`], or the explicit
+    /// chunk shape of a failed [`ebcc_encode_chunking_compat`]
+    pub chunk_shape: Option<[usize; 3]>,
+    /// XXH3 hash of the raw input, i.e. of the little-endian bytes of the
+    /// data in standard order for encodes and of the compressed data for
+    /// decodes
+    pub content_hash: u64,
+    /// Message of the error that the call returned
+    pub error: String,
+    /// Path of the raw input, if it was written into the
+    /// [`data_dir`][EBCCCaptureOptions::data_dir]
+    pub data_path: Option<PathBuf>,
+}
+
+impl EBCCCaptureBundle {
+    /// Replay the failed call from the raw input at the
+    /// [`data_path`][Self::data_path], e.g. in a debugger or against a
+    /// patched build of the EBCC C library.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the bundle has no raw input, if the
+    ///   raw input does not match the [`content_hash`][Self::content_hash],
+    ///   or if an encode has no configuration or a chunked encode no valid
+    ///   chunk shape
+    /// - [`EBCCError::Io`] if reading the raw input fails
+    /// - [`EBCCError::OutputTooLarge`], [`EBCCError::TooManyFrames`], or
+    ///   [`EBCCError::FrameTooLarge`] if the shape of a decode exceeds the
+    ///   default [`EBCCDecodeOptions`] limits
+    /// - all errors that the replayed call can return, which reproduce the
+    ///   captured failure
+    pub fn replay(&self) -> EBCCResult<()> {
+        let Some(data_path) = &self.data_path else {
+            return Err(EBCCError::InvalidInput(String::from(
+                "Captured EBCC call has no raw input to replay",
+            )));
+        };
+
+        let input = fs::read(data_path)?;
+        if xxhash_rust::xxh3::xxh3_64(&input) != self.content_hash {
+            return Err(EBCCError::InvalidInput(format!(
+                "Captured EBCC input {} does not match its content hash {:016x}",
+                data_path.display(),
+                self.content_hash,
+            )));
+        }
+
+        let shape = self.shape.into();
+
+        match self.operation {
+            EBCCCaptureOperation::Encode => {
+                ebcc_encode(self.replay_data(&input)?.view(), self.replay_config()?).map(|_| ())
+            }
+            EBCCCaptureOperation::EncodeChunking => {
+                let Some(chunk_shape) = self.replay_chunk_shape()? else {
+                    return Err(EBCCError::InvalidInput(String::from(
+                        "Captured EBCC chunked encode has no chunk shape to replay",
+                    )));
+                };
+                ebcc_encode_chunking(
+                    self.replay_data(&input)?.view(),
+                    self.replay_config()?,
+                    chunk_shape,
+                )
+                .map(|_| ())
+            }
+            EBCCCaptureOperation::EncodeChunkingCompat => {
+                let chunk_shape = self
+                    .replay_chunk_shape()?
+                    .map_or(EBCCCompatChunkShape::Auto, EBCCCompatChunkShape::Explicit);
+                ebcc_encode_chunking_compat(
+                    self.replay_data(&input)?.view(),
+                    self.replay_config()?,
+                    chunk_shape,
+                )
+                .map(|_| ())
+            }
+            EBCCCaptureOperation::Decode => {
+                EBCCDecodeOptions::new().check_shape(shape)?;
+                let mut decompressed = Array::zeros(shape);
+                ebcc_decode_into(&input, decompressed.view_mut())
+            }
+            EBCCCaptureOperation::DecodeChunking => {
+                EBCCDecodeOptions::new().check_shape(shape)?;
+                let mut decompressed = Array::zeros(shape);
+                ebcc_decode_chunking_into(&input, decompressed.view_mut())
+            }
+        }
+    }
+
+    /// Read a bundle that [`ebcc_with_capture`] wrote into its
+    /// [`data_dir`][EBCCCaptureOptions::data_dir] as a `.bundle` file, e.g.
+    /// on another machine, such that it can be
+    /// [replayed][EBCCCaptureBundle::replay].
+    ///
+    /// The [`data_path`][Self::data_path] of the bundle is resolved relative
+    /// to the directory of the bundle file, such that the directory can be
+    /// moved as a whole.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::Io`] if reading the bundle file fails
+    /// - all errors that [`EBCCCaptureBundle::from_bytes`] can return
+    pub fn load(path: impl AsRef<Path>) -> EBCCResult<Self> {
+        let path = path.as_ref();
+        let mut bundle = Self::from_bytes(&fs::read(path)?)?;
+
+        if let (Some(data_path), Some(dir)) = (&mut bundle.data_path, path.parent()) {
+            *data_path = dir.join(&*data_path);
+        }
+
+        Ok(bundle)
+    }
+
+    /// Serialize the bundle, with the file name of its
+    /// [`data_path`][Self::data_path] but not the raw input itself.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::from(EBCC_CAPTURE_MAGIC.as_slice());
+        bytes.extend_from_slice(&EBCC_CAPTURE_VERSION.to_le_bytes());
+
+        bytes.push(self.operation.code());
+        for dim in self.shape {
+            bytes.extend_from_slice(&(dim as u64).to_le_bytes());
+        }
+        match self.chunk_shape {
+            None => bytes.push(0),
+            Some(chunk_shape) => {
+                bytes.push(1);
+                for dim in chunk_shape {
+                    bytes.extend_from_slice(&(dim as u64).to_le_bytes());
+                }
+            }
+        }
+        bytes.extend_from_slice(&self.content_hash.to_le_bytes());
+
+        write_bytes(&mut bytes, self.error.as_bytes());
+        write_bytes(
+            &mut bytes,
+            &self
+                .config
+                .as_ref()
+                .map(EBCCConfig::to_bytes)
+                .unwrap_or_default(),
+        );
+        write_bytes(
+            &mut bytes,
+            self.data_path
+                .as_deref()
+                .and_then(Path::file_name)
+                .and_then(OsStr::to_str)
+                .unwrap_or_default()
+                .as_bytes(),
+        );
+
+        bytes
+    }
+
+    /// Restore a bundle that was serialized with
+    /// [`EBCCCaptureBundle::to_bytes`], whose [`data_path`][Self::data_path]
+    /// is then only the file name of the raw input.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the `bytes` are not a serialized
+    ///   bundle, are truncated or corrupted, or have trailing bytes
+    /// - all errors that [`EBCCConfig::from_bytes`] can return
+    pub fn from_bytes(bytes: &[u8]) -> EBCCResult<Self> {
+        let Some(mut reader) = bytes.strip_prefix(EBCC_CAPTURE_MAGIC.as_slice()) else {
+            return Err(EBCCError::InvalidInput(String::from(
+                "EBCC capture bundle does not start with the expected magic bytes",
+            )));
+        };
+
+        let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+        if !(1..=EBCC_CAPTURE_VERSION).contains(&version) {
+            return Err(EBCCError::InvalidInput(format!(
+                "Unsupported EBCC capture bundle version: {version}"
+            )));
+        }
+
+        let [operation] = FORMAT.read_array(&mut reader)?;
+        let operation = EBCCCaptureOperation::from_code(operation)?;
+        let shape = read_shape(&mut reader)?;
+        let chunk_shape = match FORMAT.read_array(&mut reader)? {
+            [0] => None,
+            [1] => Some(read_shape(&mut reader)?),
+            _ => return Err(FORMAT.corrupted()),
+        };
+        let content_hash = u64::from_le_bytes(FORMAT.read_array(&mut reader)?);
+
+        let error =
+            String::from_utf8(read_bytes(&mut reader)?.to_vec()).map_err(|_| FORMAT.corrupted())?;
+        let config = match read_bytes(&mut reader)? {
+            [] => None,
+            config => Some(EBCCConfig::from_bytes(config)?),
+        };
+        let data_path = match read_bytes(&mut reader)? {
+            [] => None,
+            file_name => {
+                let file_name = std::str::from_utf8(file_name).map_err(|_| FORMAT.corrupted())?;
+                // only a file name next to the bundle, never another path
+                if Path::new(file_name).file_name() != Some(OsStr::new(file_name)) {
+                    return Err(FORMAT.corrupted());
+                }
+                Some(PathBuf::from(file_name))
+            }
+        };
+
+        if !reader.is_empty() {
+            return Err(EBCCError::InvalidInput(String::from(
+                "EBCC capture bundle has trailing bytes",
+            )));
+        }
+
+        Ok(Self {
+            operation,
+            shape,
+            config,
+            chunk_shape,
+            content_hash,
+            error,
+            data_path,
+        })
+    }
+
+    fn replay_config(&self) -> EBCCResult<&EBCCConfig> {
+        self.config.as_ref().ok_or_else(|| {
+            EBCCError::InvalidInput(String::from(
+                "Captured EBCC encode has no configuration to replay",
+            ))
+        })
+    }
+
+    fn replay_data(&self, input: &[u8]) -> EBCCResult<Array<f32, EbccDim>> {
+        let data = input
+            .chunks_exact(size_of::<f32>())
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap_or_default()))
+            .collect();
+
+        Array::from_shape_vec(<(usize, usize, usize)>::from(self.shape), data).map_err(|_| {
+            EBCCError::SliceLenMismatch {
+                shape: self.shape,
+                len: input.len() / size_of::<f32>(),
+            }
+        })
+    }
+
+    fn replay_chunk_shape(&self) -> EBCCResult<Option<EBCCChunkShape>> {
+        let Some(chunk_shape) = self.chunk_shape else {
+            return Ok(None);
+        };
+
+        match chunk_shape.map(NonZeroUsize::new) {
+            [Some(frames), Some(height), Some(width)] => Ok(Some([frames, height, width])),
+            _ => Err(EBCCError::InvalidInput(format!(
+                "Captured EBCC chunk shape {chunk_shape:?} has an empty dimension"
+            ))),
+        }
+    }
+}
+
+/// Bundles of an ongoing capture, which all threads that it covers share
+#[derive(Debug)]
+struct CaptureSink {
+    data_dir: Option<PathBuf>,
+    bundles: Mutex<Vec<EBCCCaptureBundle>>,
+}
+
+impl CaptureSink {
+    /// Record a bundle of the failed call of the `input` with the `err`.
+    fn record(&self, input: CapturedInput, err: &EBCCError) {
+        let bundle = capture_bundle(input, err, self.data_dir.as_deref());
+        self.bundles
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(bundle);
+    }
+}
+
+/// Ongoing capture on this thread
+struct Capture {
+    sink: Arc<CaptureSink>,
+    in_call: bool,
+}
+
+thread_local! {
+    static CAPTURE: RefCell<Option<Capture>> = const { RefCell::new(None) };
+}
+
+/// Run the `job`, e.g. an encode or decode call, and capture an
+/// [`EBCCCaptureBundle`] for every failed encode or decode call within it.
+///
+/// Intermittent failures of the EBCC C library, e.g. in production, can
+/// then be reproduced from the bundles, which record the shape, the
+/// configuration, and a hash of the input, and, with a
+/// [`data_dir`][EBCCCaptureOptions::data_dir], the raw input itself. Only
+/// the outermost failed call is captured, e.g. a failed
+/// [`ebcc_encode_slice`][crate::ebcc_encode_slice] but not also the
+/// [`ebcc_encode`] within it. Calls that succeed are not captured and only
+/// pay for checking whether a capture is ongoing, except for calls that
+/// modify their input in place, e.g.
+/// [`ebcc_encode_mut`][crate::ebcc_encode_mut], whose input is copied
+/// before the call while a capture is ongoing.
+///
+/// The capture covers this thread and all threads that it is extended to
+/// with an [`EBCCCaptureContext`]. The worker threads of
+/// [`ebcc_encode_batch_parallel`][crate::ebcc_encode_batch_parallel], of an
+/// [`EbccCompressionService`][crate::EbccCompressionService], and of the
+/// [`runner`][crate::runner] inherit the capture of the thread that hands
+/// them their work. Calls that fail after the `job` has returned, e.g. of
+/// service jobs that are still running, are not captured.
+///
+/// Captures can be nested, in which case the inner capture takes over until
+/// it ends.
+///
+/// # Examples
+///
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+/// use ebcc::{ebcc_encode, ebcc_with_capture, EBCCCaptureOptions, EBCCConfig};
+/// use ndarray::Array;
+///
+/// let data = Array::from_elem((1, 32, 32), f32::NAN);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let (compressed, bundles) =
+///     ebcc_with_capture(&EBCCCaptureOptions::new(), || ebcc_encode(data.view(), &config));
+/// assert!(compressed.is_err());
+/// assert_eq!(bundles.len(), 1);
+/// ```
+pub fn ebcc_with_capture<T>(
+    options: &EBCCCaptureOptions,
+    job: impl FnOnce() -> T,
+) -> (T, Vec<EBCCCaptureBundle>) {
+    let context = EBCCCaptureContext {
+        sink: Arc::new(CaptureSink {
+            data_dir: options.data_dir.clone(),
+            bundles: Mutex::new(Vec::new()),
+        }),
+        in_call: false,
+    };
+
+    let result = context.run(job);
+    let bundles = mem::take(
+        &mut *context
+            .sink
+            .bundles
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner),
+    );
+
+    (result, bundles)
+}
+
+/// Handle to the capture that is ongoing on a thread, which extends an
+/// [`ebcc_with_capture`] to other threads, e.g. to the producer threads of
+/// an application.
+///
+/// # Examples
+///
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+/// use std::thread;
+///
+/// use ebcc::{ebcc_encode, ebcc_with_capture, EBCCCaptureContext, EBCCCaptureOptions, EBCCConfig};
+/// use ndarray::Array;
+///
+/// let data = Array::from_elem((1, 32, 32), f32::NAN);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let (_, bundles) = ebcc_with_capture(&EBCCCaptureOptions::new(), || {
+///     let context = EBCCCaptureContext::current();
+///     thread::scope(|scope| {
+///         scope.spawn(|| match &context {
+///             Some(context) => context.run(|| ebcc_encode(data.view(), &config)),
+///             None => ebcc_encode(data.view(), &config),
+///         });
+///     });
+/// });
+/// assert_eq!(bundles.len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EBCCCaptureContext {
+    sink: Arc<CaptureSink>,
+    in_call: bool,
+}
+
+impl EBCCCaptureContext {
+    /// Returns the capture that is ongoing on this thread, if any.
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        CAPTURE.with_borrow(|capture| {
+            capture.as_ref().map(|capture| Self {
+                sink: Arc::clone(&capture.sink),
+                in_call: capture.in_call,
+            })
+        })
+    }
+
+    /// Run the `job` on this thread within the capture.
+    ///
+    /// If the context was taken inside a captured call, e.g. by a worker of
+    /// a failed call that is captured as a whole, the calls of the `job` are
+    /// not captured on their own.
+    pub fn run<T>(&self, job: impl FnOnce() -> T) -> T {
+        let outer = CAPTURE.replace(Some(Capture {
+            sink: Arc::clone(&self.sink),
+            in_call: self.in_call,
+        }));
+
+        // restore the outer capture even if the job unwinds
+        let _guard = RestoreCapture { outer };
+        job()
+    }
+}
+
+/// Guard that ends a capture on this thread and restores the outer one
+struct RestoreCapture {
+    outer: Option<Capture>,
+}
+
+impl Drop for RestoreCapture {
+    fn drop(&mut self) {
+        CAPTURE.set(self.outer.take());
+    }
+}
+
+/// Wrap the `job` such that it runs within the capture that is ongoing on
+/// this thread, if any, e.g. on a worker thread.
+pub fn propagate_capture<T>(job: impl FnOnce() -> T) -> impl FnOnce() -> T {
+    let context = EBCCCaptureContext::current();
+
+    move || match context {
+        Some(context) => context.run(job),
+        None => job(),
+    }
+}
+
+/// Input of an encode or decode call that is captured if it fails
+pub struct CaptureInput<'a> {
+    operation: EBCCCaptureOperation,
+    shape: (usize, usize, usize),
+    config: Option<&'a EBCCConfig>,
+    chunk_shape: Option<[usize; 3]>,
+    raw: CaptureRaw<'a>,
+}
+
+/// Raw input of a captured call
+enum CaptureRaw<'a> {
+    /// Data that is encoded
+    Data(ArrayView<'a, f32, EbccDim>),
+    /// Compressed data that is decoded
+    Compressed(&'a [u8]),
+}
+
+impl<'a> CaptureInput<'a> {
+    /// Input of an encode of the `data` with the `config`
+    pub fn encode(data: ArrayView<'a, f32, EbccDim>, config: &'a EBCCConfig) -> Self {
+        Self {
+            operation: EBCCCaptureOperation::Encode,
+            shape: data.dim(),
+            config: Some(config),
+            chunk_shape: None,
+            raw: CaptureRaw::Data(data),
+        }
+    }
+
+    /// Input of a decode of the `compressed_data` into the `shape`
+    pub const fn decode(compressed_data: &'a [u8], shape: (usize, usize, usize)) -> Self {
+        Self {
+            operation: EBCCCaptureOperation::Decode,
+            shape,
+            config: None,
+            chunk_shape: None,
+            raw: CaptureRaw::Compressed(compressed_data),
+        }
+    }
+
+    /// Change the input into the one of the chunked `operation` with the
+    /// `chunk_shape`.
+    pub const fn chunked(
+        mut self,
+        operation: EBCCCaptureOperation,
+        chunk_shape: Option<[usize; 3]>,
+    ) -> Self {
+        self.operation = operation;
+        self.chunk_shape = chunk_shape;
+        self
+    }
+
+    /// Copy the input, e.g. before a call that may modify it in place.
+    pub fn snapshot(self) -> CapturedInput {
+        CapturedInput {
+            operation: self.operation,
+            shape: self.shape.into(),
+            config: self.config.cloned(),
+            chunk_shape: self.chunk_shape,
+            raw: match self.raw {
+                CaptureRaw::Data(data) => data.iter().flat_map(|x| x.to_le_bytes()).collect(),
+                CaptureRaw::Compressed(compressed_data) => compressed_data.to_vec(),
+            },
+        }
+    }
+}
+
+/// Owned copy of a [`CaptureInput`]
+pub struct CapturedInput {
+    operation: EBCCCaptureOperation,
+    shape: [usize; 3],
+    config: Option<EBCCConfig>,
+    chunk_shape: Option<[usize; 3]>,
+    raw: Vec<u8>,
+}
+
+/// Start a captured call if a capture is ongoing on this thread and is not
+/// already inside a captured call, and return the capture's sink.
+fn begin_call() -> Option<Arc<CaptureSink>> {
+    CAPTURE.with_borrow_mut(|capture| match capture {
+        Some(capture) if !capture.in_call => {
+            capture.in_call = true;
+            Some(Arc::clone(&capture.sink))
+        }
+        _ => None,
+    })
+}
+
+/// Run the encode or decode `call` of the `input` and capture a bundle if it
+/// fails while a capture is ongoing.
+pub fn capture_call<T>(input: CaptureInput, call: impl FnOnce() -> EBCCResult<T>) -> EBCCResult<T> {
+    let Some(sink) = begin_call() else {
+        return call();
+    };
+
+    // end the call even if it unwinds
+    let guard = EndCall;
+    let result = call();
+    drop(guard);
+
+    if let Err(err) = &result {
+        sink.record(input.snapshot(), err);
+    }
+
+    result
+}
+
+/// Run the encode or decode `call`, which may modify its `data` in place,
+/// and capture a bundle of the `input` [snapshot][CaptureInput::snapshot]
+/// from before the call if the call fails while a capture is ongoing.
+pub fn capture_call_mut<D: ?Sized, T>(
+    data: &mut D,
+    input: impl FnOnce(&D) -> CapturedInput,
+    call: impl FnOnce(&mut D) -> EBCCResult<T>,
+) -> EBCCResult<T> {
+    let Some(sink) = begin_call() else {
+        return call(data);
+    };
+
+    // end the call even if it unwinds
+    let guard = EndCall;
+    let captured = input(data);
+    let result = call(data);
+    drop(guard);
+
+    if let Err(err) = &result {
+        sink.record(captured, err);
+    }
+
+    result
+}
+
+/// Guard that ends the outermost captured call
+struct EndCall;
+
+impl Drop for EndCall {
+    fn drop(&mut self) {
+        CAPTURE.with_borrow_mut(|capture| {
+            if let Some(capture) = capture {
+                capture.in_call = false;
+            }
+        });
+    }
+}
+
+fn capture_bundle(
+    input: CapturedInput,
+    err: &EBCCError,
+    data_dir: Option<&Path>,
+) -> EBCCCaptureBundle {
+    let CapturedInput {
+        operation,
+        shape,
+        config,
+        chunk_shape,
+        raw,
+    } = input;
+
+    let mut bundle = EBCCCaptureBundle {
+        operation,
+        shape,
+        config,
+        chunk_shape,
+        content_hash: xxhash_rust::xxh3::xxh3_64(&raw),
+        error: err.to_string(),
+        data_path: None,
+    };
+
+    if let Some(data_dir) = data_dir {
+        match write_bundle(data_dir, &mut bundle, &raw) {
+            Ok(()) => (),
+            #[cfg_attr(not(feature = "tracing"), expect(unused_variables))]
+            Err(err) => {
+                warn_event!(
+                    error = %err,
+                    "failed to write the captured input of a failed EBCC {operation}"
+                );
+            }
+        }
+    }
+
+    bundle
+}
+
+/// Write the `raw` input, the serialized `bundle`, and a description of it
+/// into the `data_dir`, named after the operation and content hash.
+fn write_bundle(
+    data_dir: &Path,
+    bundle: &mut EBCCCaptureBundle,
+    raw: &[u8],
+) -> std::io::Result<()> {
+    fs::create_dir_all(data_dir)?;
+
+    let stem = format!("ebcc-{}-{:016x}", bundle.operation, bundle.content_hash);
+    let data_path = data_dir.join(&stem).with_extension("bin");
+    fs::write(&data_path, raw)?;
+    bundle.data_path = Some(data_path);
+
+    fs::write(
+        data_dir.join(&stem).with_extension("bundle"),
+        bundle.to_bytes(),
+    )?;
+    fs::write(
+        data_dir.join(stem).with_extension("txt"),
+        format!("{bundle:#?}\n"),
+    )
+}
+
+fn read_shape(reader: &mut &[u8]) -> EBCCResult<[usize; 3]> {
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?;
+    }
+    Ok(shape)
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    bytes.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(value);
+}
+
+fn read_bytes<'a>(reader: &mut &'a [u8]) -> EBCCResult<&'a [u8]> {
+    let len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?;
+    let Some((value, rest)) = reader.split_at_checked(len) else {
+        return Err(FORMAT.truncated());
+    };
+    *reader = rest;
+
+    Ok(value)
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::testdata;
+
+    #[test]
+    fn test_capture_failed_calls() -> EBCCResult<()> {
+        let data_dir =
+            std::env::temp_dir().join(format!("ebcc-capture-test-{}", std::process::id()));
+        let options = EBCCCaptureOptions::new().with_data_dir(&data_dir);
+
+        let mut data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let compressed = ebcc_encode(data.view(), &config)?;
+
+        // successful calls are not captured
+        let (result, bundles) = ebcc_with_capture(&options, || {
+            let mut decompressed = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, decompressed.view_mut())
+        });
+        result?;
+        assert!(bundles.is_empty());
+
+        data.fill(f32::NAN);
+        let mut corrupted = compressed;
+        corrupted.truncate(corrupted.len() / 2);
+
+        let (result, bundles) = ebcc_with_capture(&options, || {
+            let encoded = ebcc_encode(data.view(), &config);
+            let mut decompressed = Array::zeros(data.dim());
+            let decoded = ebcc_decode_into(&corrupted, decompressed.view_mut());
+            (encoded, decoded)
+        });
+        assert!(result.0.is_err() && result.1.is_err());
+        let [encode, decode] = bundles.as_slice() else {
+            return Err(EBCCError::InvalidInput(format!("{bundles:?}")));
+        };
+
+        assert_eq!(encode.operation, EBCCCaptureOperation::Encode);
+        assert_eq!(encode.shape, [2, 32, 48]);
+        assert_eq!(encode.config.as_ref(), Some(&config));
+        assert!(matches!(encode.replay(), Err(EBCCError::NonFinite { .. })));
+
+        assert_eq!(decode.operation, EBCCCaptureOperation::Decode);
+        assert_eq!(decode.content_hash, xxhash_rust::xxh3::xxh3_64(&corrupted));
+        assert_eq!(
+            decode.replay().map_err(|err| err.to_string()),
+            Err(decode.error.clone())
+        );
+
+        // nested captures take over from the outer capture
+        let ((inner, result), outer) = ebcc_with_capture(&EBCCCaptureOptions::new(), || {
+            let (result, inner) = ebcc_with_capture(&EBCCCaptureOptions::new(), || {
+                ebcc_encode(data.view(), &config)
+            });
+            (
+                inner,
+                result.and_then(|_| ebcc_encode(data.view(), &config)),
+            )
+        });
+        assert!(result.is_err());
+        assert_eq!(inner.len(), 1);
+        assert!(inner.iter().all(|bundle| bundle.data_path.is_none()));
+        assert!(outer.is_empty());
+
+        fs::remove_dir_all(&data_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_bundle_load() -> EBCCResult<()> {
+        let data_dir =
+            std::env::temp_dir().join(format!("ebcc-capture-load-test-{}", std::process::id()));
+        let options = EBCCCaptureOptions::new().with_data_dir(&data_dir);
+
+        let mut data = testdata::temperature((2, 32, 48));
+        data.fill(f32::NAN);
+        let config = EBCCConfig::max_absolute_error_bounded(0.1).with_quantile_sketch();
+        let chunk_shape =
+            [1, 32, 48].map(|dim| NonZeroUsize::new(dim).unwrap_or(NonZeroUsize::MIN));
+
+        let (_, bundles) = ebcc_with_capture(&options, || {
+            let mut copy = data.clone();
+            let encoded_mut = crate::ebcc_encode_mut(copy.view_mut(), &config);
+            let mut compressed = Vec::new();
+            let encoded_into = crate::ebcc_encode_into(data.view(), &config, &mut compressed);
+            let chunked = ebcc_encode_chunking(data.view(), &config, chunk_shape);
+            (encoded_mut, encoded_into, chunked)
+        });
+        let [encode_mut, encode_into, chunked] = bundles.as_slice() else {
+            return Err(EBCCError::InvalidInput(format!("{bundles:?}")));
+        };
+        assert_eq!(encode_mut.operation, EBCCCaptureOperation::Encode);
+        assert_eq!(encode_into.operation, EBCCCaptureOperation::Encode);
+        assert_eq!(chunked.operation, EBCCCaptureOperation::EncodeChunking);
+        assert_eq!(chunked.chunk_shape, Some([1, 32, 48]));
+
+        for bundle in &bundles {
+            assert_eq!(
+                EBCCCaptureBundle::from_bytes(&bundle.to_bytes())?.shape,
+                bundle.shape
+            );
+
+            // the bundle is reloaded from its directory, e.g. on another machine
+            let Some(data_path) = &bundle.data_path else {
+                return Err(EBCCError::InvalidInput(format!("{bundle:?}")));
+            };
+            let loaded = EBCCCaptureBundle::load(data_path.with_extension("bundle"))?;
+            assert_eq!(&loaded, bundle);
+            assert!(matches!(loaded.replay(), Err(EBCCError::NonFinite { .. })));
+        }
+
+        let mut bytes = chunked.to_bytes();
+        bytes.push(0);
+        assert!(matches!(
+            EBCCCaptureBundle::from_bytes(&bytes),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        fs::remove_dir_all(&data_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_other_threads() {
+        let mut data = testdata::temperature((4, 32, 48));
+        data.fill(f32::NAN);
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        // the workers of parallel batches inherit the capture
+        let (encoded, bundles) = ebcc_with_capture(&EBCCCaptureOptions::new(), || {
+            crate::ebcc_encode_batch_parallel(
+                data.axis_chunks_iter(ndarray::Axis(0), 1),
+                &config,
+                NonZeroUsize::MIN.saturating_add(3),
+            )
+            .collect::<Vec<_>>()
+        });
+        assert!(encoded.iter().all(Result::is_err));
+        assert_eq!(bundles.len(), 4);
+        assert!(bundles.iter().all(|bundle| bundle.shape == [1, 32, 48]));
+
+        // threads of the application run within the context of the capture
+        let ((), bundles) = ebcc_with_capture(&EBCCCaptureOptions::new(), || {
+            let context = EBCCCaptureContext::current();
+            std::thread::scope(|scope| {
+                for _ in 0..2 {
+                    scope.spawn(|| {
+                        context
+                            .as_ref()
+                            .map(|context| context.run(|| ebcc_encode(data.view(), &config)))
+                    });
+                }
+            });
+        });
+        assert_eq!(bundles.len(), 2);
+        assert!(EBCCCaptureContext::current().is_none());
+    }
+}
@@ -0,0 +1,123 @@
+//! Buffers that are returned by the EBCC C library.
+
+use alloc::vec::Vec;
+use core::{fmt, ptr, slice};
+
+#[cfg(all(feature = "std", feature = "ndarray"))]
+use crate::accounting::{record_alloc, record_free};
+
+/// Buffer that was allocated by the EBCC C library and is freed on drop, or
+/// that was allocated in Rust by the residual-only codec.
+pub struct CBuffer<T> {
+    inner: CBufferInner<T>,
+}
+
+enum CBufferInner<T> {
+    C { ptr: ptr::NonNull<T>, len: usize },
+    Rust(Vec<T>),
+}
+
+impl<T> CBuffer<T> {
+    /// Take ownership of a non-empty buffer allocated by EBCC.
+    ///
+    /// Returns [`None`] if `ptr` is null or `len` is zero. A non-null `ptr` is
+    /// freed in both cases.
+    ///
+    /// # Safety
+    ///
+    /// If `ptr` is non-null, it must have been allocated by EBCC, must be
+    /// valid for reads of `len` initialized elements, and must not be used or
+    /// freed elsewhere afterwards.
+    #[expect(unsafe_code)]
+    pub(crate) unsafe fn new(ptr: *mut T, len: usize) -> Option<Self> {
+        let ptr = ptr::NonNull::new(ptr)?;
+        // with rust-alloc, the C allocation has already been recorded
+        #[cfg(all(feature = "std", feature = "ndarray", not(feature = "rust-alloc")))]
+        record_alloc(len.saturating_mul(core::mem::size_of::<T>()));
+        let buffer = Self {
+            inner: CBufferInner::C { ptr, len },
+        };
+        (len > 0).then_some(buffer)
+    }
+
+    /// Wrap a buffer that was allocated in Rust.
+    #[must_use]
+    // the allocation is only recorded with the full API
+    #[cfg_attr(
+        not(all(feature = "std", feature = "ndarray")),
+        expect(clippy::missing_const_for_fn)
+    )]
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        #[cfg(all(feature = "std", feature = "ndarray"))]
+        record_alloc(vec.capacity().saturating_mul(core::mem::size_of::<T>()));
+        Self {
+            inner: CBufferInner::Rust(vec),
+        }
+    }
+
+    /// View the buffer as a slice.
+    #[must_use]
+    pub fn as_slice(&self) -> &[T] {
+        match &self.inner {
+            #[expect(unsafe_code)]
+            // Safety: the buffer is valid for reads of len elements
+            CBufferInner::C { ptr, len } => unsafe { slice::from_raw_parts(ptr.as_ptr(), *len) },
+            CBufferInner::Rust(vec) => vec,
+        }
+    }
+
+    /// View the buffer as a mutable slice, e.g. to decode it in-place once
+    /// it is no longer needed.
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match &mut self.inner {
+            #[expect(unsafe_code)]
+            // Safety: the buffer is uniquely owned and valid for reads and
+            //         writes of len elements
+            CBufferInner::C { ptr, len } => unsafe {
+                slice::from_raw_parts_mut(ptr.as_ptr(), *len)
+            },
+            CBufferInner::Rust(vec) => vec,
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CBuffer<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+// Safety: the buffer is uniquely owned and EBCC's buffers can be freed on any
+//         thread
+#[expect(unsafe_code, clippy::non_send_fields_in_send_ty)]
+unsafe impl<T: Send> Send for CBuffer<T> {}
+
+// Safety: the buffer is only read through shared references
+#[expect(unsafe_code)]
+unsafe impl<T: Sync> Sync for CBuffer<T> {}
+
+impl<T> Drop for CBuffer<T> {
+    fn drop(&mut self) {
+        #[cfg(all(feature = "std", feature = "ndarray"))]
+        {
+            let len = match &self.inner {
+                // with rust-alloc, the C deallocation is recorded when it is freed
+                #[cfg(feature = "rust-alloc")]
+                CBufferInner::C { .. } => 0,
+                #[cfg(not(feature = "rust-alloc"))]
+                CBufferInner::C { len, .. } => *len,
+                CBufferInner::Rust(vec) => vec.capacity(),
+            };
+            record_free(len.saturating_mul(core::mem::size_of::<T>()));
+        }
+
+        if let CBufferInner::C { ptr, .. } = &self.inner {
+            #[expect(unsafe_code)]
+            // Safety: the buffer was allocated by EBCC and is not used afterwards
+            unsafe {
+                ebcc_sys::free_buffer(ptr.as_ptr().cast::<core::ffi::c_void>());
+            }
+        }
+    }
+}
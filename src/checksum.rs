@@ -0,0 +1,249 @@
+//! Selectable checksum algorithms of the [`EBCCHeader`] payload checksum.
+//!
+//! [`EBCCHeader`]: crate::EBCCHeader
+
+use alloc::format;
+use core::fmt;
+
+use crate::error::{EBCCError, EBCCResult};
+
+/// Length of the longest checksum digest, in bytes
+const MAX_DIGEST_LEN: usize = 32;
+
+/// Algorithm of the checksum of the compressed payload in the
+/// [`EBCCHeader`][crate::EBCCHeader], see
+/// [`EBCCConfig::with_checksum_algorithm`][crate::EBCCConfig::with_checksum_algorithm].
+///
+/// The algorithm is recorded in the header, such that decoding verifies the
+/// checksum with the same algorithm that was used for encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum EBCCChecksumAlgorithm {
+    /// 32-bit CRC-32 (IEEE 802.3), which all payloads from before the
+    /// algorithm was recorded use
+    #[default]
+    Crc32,
+    /// 32-bit CRC-32C (Castagnoli), as used by iSCSI and many object stores
+    Crc32c,
+    /// 64-bit XXH3 hash
+    Xxh3,
+    /// 256-bit BLAKE3 cryptographic hash
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+impl EBCCChecksumAlgorithm {
+    /// Code of the algorithm in the header flags
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    pub(crate) const fn code(self) -> u32 {
+        match self {
+            Self::Crc32 => 0,
+            Self::Crc32c => 1,
+            Self::Xxh3 => 2,
+            #[cfg(feature = "blake3")]
+            Self::Blake3 => 3,
+        }
+    }
+
+    pub(crate) fn from_code(code: u32) -> EBCCResult<Self> {
+        match code {
+            0 => Ok(Self::Crc32),
+            1 => Ok(Self::Crc32c),
+            2 => Ok(Self::Xxh3),
+            #[cfg(feature = "blake3")]
+            3 => Ok(Self::Blake3),
+            #[cfg(not(feature = "blake3"))]
+            3 => Err(EBCCError::DecompressionError(
+                "EBCC payload is checksummed with BLAKE3, which requires the `blake3` feature"
+                    .into(),
+            )),
+            code => Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC checksum algorithm: {code}").into(),
+            )),
+        }
+    }
+
+    /// Length of the digest of the algorithm, in bytes
+    #[must_use]
+    pub const fn digest_len(self) -> usize {
+        match self {
+            Self::Crc32 | Self::Crc32c => 4,
+            Self::Xxh3 => 8,
+            #[cfg(feature = "blake3")]
+            Self::Blake3 => 32,
+        }
+    }
+
+    /// Compute the checksum of the `data` with this algorithm.
+    #[must_use]
+    pub fn checksum(self, data: &[u8]) -> EBCCChecksum {
+        match self {
+            Self::Crc32 => EBCCChecksum::new(self, &crc32fast::hash(data).to_le_bytes()),
+            Self::Crc32c => {
+                const CRC32C: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISCSI);
+                EBCCChecksum::new(self, &CRC32C.checksum(data).to_le_bytes())
+            }
+            Self::Xxh3 => EBCCChecksum::new(self, &xxhash_rust::xxh3::xxh3_64(data).to_le_bytes()),
+            #[cfg(feature = "blake3")]
+            Self::Blake3 => EBCCChecksum::new(self, blake3::hash(data).as_bytes()),
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Crc32 => "crc32",
+            Self::Crc32c => "crc32c",
+            Self::Xxh3 => "xxh3",
+            #[cfg(feature = "blake3")]
+            Self::Blake3 => "blake3",
+        }
+    }
+}
+
+impl fmt::Display for EBCCChecksumAlgorithm {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.name())
+    }
+}
+
+/// Checksum of the compressed payload in the
+/// [`EBCCHeader`][crate::EBCCHeader], together with its
+/// [`algorithm`][Self::algorithm].
+///
+/// The checksum is displayed as lowercase hex, where the CRC and XXH3
+/// checksums are displayed as integers and BLAKE3 hashes as bytes, like the
+/// reference tools of each algorithm do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EBCCChecksum {
+    algorithm: EBCCChecksumAlgorithm,
+    digest: [u8; MAX_DIGEST_LEN],
+}
+
+impl EBCCChecksum {
+    /// Reconstruct a checksum from its `algorithm` and `digest` bytes, which
+    /// are little-endian for the CRC and XXH3 checksums.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the `digest` does not have the
+    ///   [`digest_len`][EBCCChecksumAlgorithm::digest_len] of the `algorithm`
+    pub fn from_digest(algorithm: EBCCChecksumAlgorithm, digest: &[u8]) -> EBCCResult<Self> {
+        if digest.len() != algorithm.digest_len() {
+            return Err(EBCCError::InvalidInput(format!(
+                "A {algorithm} digest must be {} bytes long, got {} bytes",
+                algorithm.digest_len(),
+                digest.len(),
+            )));
+        }
+
+        Ok(Self::new(algorithm, digest))
+    }
+
+    /// Checksum whose `digest` has the length of the `algorithm`
+    fn new(algorithm: EBCCChecksumAlgorithm, digest: &[u8]) -> Self {
+        let mut checksum = Self {
+            algorithm,
+            digest: [0; MAX_DIGEST_LEN],
+        };
+        checksum
+            .digest
+            .iter_mut()
+            .zip(digest)
+            .for_each(|(byte, digest)| *byte = *digest);
+        checksum
+    }
+
+    /// The algorithm of the checksum
+    #[must_use]
+    pub const fn algorithm(&self) -> EBCCChecksumAlgorithm {
+        self.algorithm
+    }
+
+    /// The digest bytes of the checksum, which are little-endian for the CRC
+    /// and XXH3 checksums
+    #[must_use]
+    pub fn digest(&self) -> &[u8] {
+        self.digest
+            .get(..self.algorithm.digest_len())
+            .unwrap_or_default()
+    }
+
+    fn digest_prefix<const N: usize>(&self) -> [u8; N] {
+        self.digest.first_chunk().copied().unwrap_or([0; N])
+    }
+}
+
+impl fmt::Display for EBCCChecksum {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.algorithm {
+            EBCCChecksumAlgorithm::Crc32 | EBCCChecksumAlgorithm::Crc32c => {
+                write!(fmt, "{:08x}", u32::from_le_bytes(self.digest_prefix()))
+            }
+            EBCCChecksumAlgorithm::Xxh3 => {
+                write!(fmt, "{:016x}", u64::from_le_bytes(self.digest_prefix()))
+            }
+            #[cfg(feature = "blake3")]
+            EBCCChecksumAlgorithm::Blake3 => self
+                .digest()
+                .iter()
+                .try_for_each(|byte| write!(fmt, "{byte:02x}")),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "ndarray"))]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_checksum_algorithms() -> EBCCResult<()> {
+        let data = b"123456789";
+
+        // check values of the catalogue of parametrised CRC algorithms
+        assert_eq!(
+            EBCCChecksumAlgorithm::Crc32.checksum(data).to_string(),
+            "cbf43926"
+        );
+        assert_eq!(
+            EBCCChecksumAlgorithm::Crc32c.checksum(data).to_string(),
+            "e3069283"
+        );
+        assert_eq!(
+            EBCCChecksumAlgorithm::Xxh3.checksum(b"").to_string(),
+            "2d06800538d394c2"
+        );
+        #[cfg(feature = "blake3")]
+        assert_eq!(
+            EBCCChecksumAlgorithm::Blake3.checksum(b"").to_string(),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+
+        for algorithm in [
+            EBCCChecksumAlgorithm::Crc32,
+            EBCCChecksumAlgorithm::Crc32c,
+            EBCCChecksumAlgorithm::Xxh3,
+            #[cfg(feature = "blake3")]
+            EBCCChecksumAlgorithm::Blake3,
+        ] {
+            assert_eq!(
+                EBCCChecksumAlgorithm::from_code(algorithm.code())?,
+                algorithm
+            );
+
+            let checksum = algorithm.checksum(data);
+            assert_eq!(checksum.digest().len(), algorithm.digest_len());
+            assert_eq!(
+                EBCCChecksum::from_digest(algorithm, checksum.digest())?,
+                checksum
+            );
+            assert!(EBCCChecksum::from_digest(algorithm, &[0; 3]).is_err());
+        }
+
+        assert!(EBCCChecksumAlgorithm::from_code(42).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+//! Postprocessing that clamps the decoded values to a physical value range.
+//!
+//! Lossy compression can reconstruct values slightly outside of their
+//! physical range, e.g. negative precipitation. With an [`EBCCValueRange`],
+//! the bounds are recorded in the payload, see
+//! [`EBCCConfig::with_value_range`], and every decoded value is clamped to
+//! them. Since the original data must lie within the range, clamping only
+//! moves values closer to the original data and the error bound still holds.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_CLAMP_MAGIC`], the format version as `u32`, the bounds
+//!   as `u8` flags (`1` if there is a minimum and `2` if there is a maximum),
+//!   the minimum and maximum as `f32`s (zero if absent), and the number of
+//!   frames, the frame height, and the frame width as `u64`s
+//! - the inner EBCC payload
+
+use ndarray::ArrayView;
+
+use crate::codec::{ebcc_decode_nested, ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+
+/// Magic bytes at the start of every range-clamped EBCC payload.
+pub const EBCC_CLAMP_MAGIC: &[u8; 8] = b"EBCCVRNG";
+
+/// Version of the range-clamped EBCC payload format.
+const EBCC_CLAMP_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC value range data");
+
+/// Length of the range-clamped payload header
+pub const EBCC_CLAMP_HEADER_LEN: usize = 8 + 4 + 1 + 2 * 4 + 3 * 8;
+
+const HAS_MIN: u8 = 1;
+const HAS_MAX: u8 = 2;
+
+/// Physical value range to which the decoded values are clamped, see
+/// [`EBCCConfig::with_value_range`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EBCCValueRange {
+    /// Optional inclusive lower bound, e.g. `0.0` for precipitation
+    pub min: Option<f32>,
+    /// Optional inclusive upper bound, e.g. `1.0` for cloud fraction
+    pub max: Option<f32>,
+}
+
+impl EBCCValueRange {
+    /// Create a new value range with the optional inclusive `min` and `max`
+    /// bounds.
+    #[must_use]
+    pub const fn new(min: Option<f32>, max: Option<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// Validate the bounds of the value range.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidConfig`] if neither bound is given, if a bound
+    ///   is NaN, or if the minimum is larger than the maximum
+    pub fn validate(self) -> EBCCResult<()> {
+        match (self.min, self.max) {
+            (None, None) => Err(EBCCError::InvalidConfig(String::from(
+                "Value range requires a minimum or a maximum",
+            ))),
+            (Some(bound), _) | (_, Some(bound)) if bound.is_nan() => Err(EBCCError::InvalidConfig(
+                String::from("Value range bounds must not be NaN"),
+            )),
+            (Some(min), Some(max)) if min > max => Err(EBCCError::InvalidConfig(format!(
+                "Value range minimum {min} must not be larger than its maximum {max}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `true` if `x` lies within the value range.
+    #[must_use]
+    pub fn contains(self, x: f32) -> bool {
+        self.min.is_none_or(|min| x >= min) && self.max.is_none_or(|max| x <= max)
+    }
+
+    /// Clamp `x` to the value range, keeping NaN values.
+    #[must_use]
+    pub fn clamp(self, x: f32) -> f32 {
+        let x = self.min.map_or(x, |min| if x < min { min } else { x });
+        self.max.map_or(x, |max| if x > max { max } else { x })
+    }
+}
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_CLAMP_MAGIC`].
+pub fn is_clamped(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_CLAMP_MAGIC)
+}
+
+/// Encode a 3D data array into a range-clamped payload with the
+/// [`EBCCConfig::value_range`] of the `config`, which must be set.
+pub fn clamp_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let Some(range) = config.value_range else {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "Value range clamping requires a value range",
+        )));
+    };
+
+    // clamping can only move values closer to the original data if the data
+    //  lies within the range
+    if let Some(x) = data.iter().find(|&&x| !x.is_nan() && !range.contains(x)) {
+        return Err(EBCCError::InvalidInput(format!(
+            "Data value {x} lies outside of the value range {:?} to {:?}",
+            range.min, range.max,
+        )));
+    }
+
+    let inner_config = EBCCConfig {
+        value_range: None,
+        ..config.clone()
+    };
+    let inner = ebcc_encode_c_buffer_with_scratch(data, &inner_config, scratch)?;
+    let inner = inner.as_slice();
+
+    let mut compressed_data = Vec::with_capacity(EBCC_CLAMP_HEADER_LEN + inner.len());
+    compressed_data.extend_from_slice(EBCC_CLAMP_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_CLAMP_VERSION.to_le_bytes());
+    compressed_data.push(range.min.map_or(0, |_| HAS_MIN) | range.max.map_or(0, |_| HAS_MAX));
+    compressed_data.extend_from_slice(&range.min.unwrap_or(0.0).to_le_bytes());
+    compressed_data.extend_from_slice(&range.max.unwrap_or(0.0).to_le_bytes());
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(inner);
+
+    Ok(compressed_data)
+}
+
+/// Decode a range-clamped payload of the expected `shape`, which may be
+/// modified during decoding, into the flattened 3D data array.
+pub fn clamp_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_CLAMP_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_CLAMP_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC value range version: {version}").into(),
+        ));
+    }
+
+    let [flags] = FORMAT.read_array(&mut reader)?;
+    if flags & !(HAS_MIN | HAS_MAX) != 0 {
+        return Err(FORMAT.corrupted());
+    }
+    let min = f32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    let max = f32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    let range = EBCCValueRange::new(
+        (flags & HAS_MIN != 0).then_some(min),
+        (flags & HAS_MAX != 0).then_some(max),
+    );
+    if range.validate().is_err() {
+        return Err(FORMAT.corrupted());
+    }
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+    data_len(expected_shape.into())?;
+
+    let Some(inner) = compressed_data.get_mut(EBCC_CLAMP_HEADER_LEN..) else {
+        return Err(FORMAT.truncated());
+    };
+
+    Ok(ebcc_decode_nested(inner, expected_shape.into(), depth + 1)?
+        .as_slice()
+        .iter()
+        .map(|&x| range.clamp(x))
+        .collect())
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_value_range() -> EBCCResult<()> {
+        // precipitation-like data with many zeros
+        let data = testdata::temperature((2, 32, 48)).mapv(|t| (t - 285.0).max(0.0));
+
+        for config in [
+            EBCCConfig::max_absolute_error_bounded(0.5),
+            EBCCConfig::relative_error_bounded(0.05),
+        ] {
+            let config = config.with_value_range(Some(0.0), Some(100.0));
+
+            let compressed = ebcc_encode(data.view(), &config)?;
+            let mut decompressed = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+            check_error_bound(data.view(), decompressed.view(), &config)?;
+            assert!(decompressed.iter().all(|&x| (0.0..=100.0).contains(&x)));
+        }
+
+        assert!(matches!(
+            ebcc_encode(
+                data.view(),
+                &EBCCConfig::max_absolute_error_bounded(0.5).with_value_range(None, Some(1.0))
+            ),
+            Err(EBCCError::InvalidInput(_))
+        ));
+        for (min, max) in [(None, None), (Some(f32::NAN), None), (Some(1.0), Some(0.0))] {
+            assert!(matches!(
+                ebcc_encode(
+                    data.view(),
+                    &EBCCConfig::max_absolute_error_bounded(0.5).with_value_range(min, max)
+                ),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+
+        Ok(())
+    }
+}
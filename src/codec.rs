@@ -1,16 +1,57 @@
 //! Safe wrapper functions for EBCC compression and decompression.
 
-use std::{io::Read, num::NonZeroUsize, ptr, slice};
+use std::{
+    fmt,
+    io::{Cursor, Read},
+    num::NonZeroUsize,
+    ptr, slice,
+};
 
 pub use ebcc_sys::EBCC_NDIMS;
-use ebcc_sys::{
-    EBCC_CHUNKING_HEADER_MAGIC, EBCC_CHUNKING_HEADER_VERSION, EBCC_MAX_INTERNAL_IMAGE_DIM,
-    EBCC_MIN_INTERNAL_IMAGE_DIM,
-};
-use ndarray::{ArrayView, ArrayViewMut, Dim, Ix};
+use ebcc_sys::{EBCC_CHUNKING_HEADER_MAGIC, EBCC_CHUNKING_HEADER_VERSION};
+use ndarray::{s, Array, ArrayView, ArrayViewMut, Axis, Dim, Ix, Zip};
 
-use crate::config::EBCCConfig;
-use crate::error::{EBCCError, EBCCResult};
+use crate::accounting::{record_alloc, record_copy, TrackedAlloc};
+use crate::adaptive::{
+    decode_tiled_into, ebcc_decode_tiled_into, ebcc_encode_tiled_fallback, is_ebcc_tiled,
+};
+use crate::capture::{capture_call, capture_call_mut, CaptureInput, EBCCCaptureOperation};
+use crate::cbuffer::CBuffer;
+use crate::clamp::{clamp_decode, clamp_encode, is_clamped};
+use crate::coder::{is_residual_coded, residual_coded_decode, residual_coded_encode};
+use crate::config::{
+    EBCCBaseCodec, EBCCBaseMode, EBCCConfig, EBCCExpansionFallback, EBCCExpansionGuard,
+};
+use crate::conserve::{conserve_decode, conserve_encode, is_conserving};
+use crate::container::{is_ebcc_container, EbccContainer};
+use crate::error::{shape_mismatch, EBCCError, EBCCFailure, EBCCResult};
+use crate::finite::validate_only_finite_data;
+use crate::header::{
+    header_payload, header_payload_mut, verify_decompressed_checksum, write_header, EBCCHeader,
+};
+use crate::layered::{is_layered, layered_decode, layered_encode};
+use crate::layout::copy_standard_order;
+use crate::limits::EBCCLimits;
+use crate::params::{validate_ebcc_chunk_shape, validate_regular_ebcc_shape};
+use crate::quantize::{is_quantized, quantize_decode, quantize_encode};
+use crate::residual::{is_residual_only, residual_only_decode, residual_only_encode};
+use crate::roi::{is_roi, roi_decode, roi_encode};
+use crate::size::{check_slice_len, data_len, u64_to_usize};
+use crate::sketch::{is_sketched, sketch_decode, sketch_encode};
+use crate::stage::{is_staged, stage_decode, stage_encode};
+use crate::stored::{is_stored, stored_decode, stored_encode};
+use crate::stream::{
+    ebcc_decode_stream_into, ebcc_visit_stream_body, is_ebcc_stream, EBCC_STREAM_MAGIC,
+};
+#[cfg(feature = "decode")]
+use crate::sync::with_ebcc_call;
+#[cfg(feature = "encode")]
+use crate::sync::with_ebcc_encode_call;
+use crate::sync::{c_call_failure, check_deterministic_encode};
+#[cfg(feature = "tracing")]
+use crate::trace::compression_ratio;
+use crate::trace::{debug_event, debug_span};
+use crate::transform::{is_transformed, transform_decode, transform_encode};
 
 /// EBCC data dimension.
 pub type EbccDim = Dim<[Ix; EBCC_NDIMS]>;
@@ -34,26 +75,56 @@ pub enum EBCCCompatChunkShape {
 /// - `data`: 3D input data array
 /// - `config`: EBCC configuration
 ///
+/// If the EBCC C library fails to encode very large frames, e.g. because of
+/// its allocation or tile geometry limits, the frames are automatically split
+/// into tiles that are encoded independently, like with
+/// [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] but with a uniform
+/// base compression ratio. The fallback is reported as a warning with the
+/// `tracing` feature and counted in the
+/// [`tiling_fallbacks`][crate::EBCCResourceUsage::tiling_fallbacks] of
+/// [`ebcc_measure_resources`][crate::ebcc_measure_resources].
+///
+/// Fortran-ordered (column-major) `data`, e.g. read from Fortran models or
+/// netCDF, is transposed in blocks and needs no caller-side transposition.
+/// Its order is recorded in the header, such that
+/// [`EBCCOutputLayout::AsEncoded`][crate::EBCCOutputLayout::AsEncoded]
+/// decodes it into the same layout.
+///
 /// # Returns
 ///
-/// The compressed data bytes.
+/// The compressed data bytes, which start with a self-describing
+/// [`EBCCHeader`][crate::EBCCHeader] with the shape of the `data`, the
+/// [fingerprint][EBCCConfig::fingerprint] of the `config`, and the checksum
+/// of the EBCC payload.
 ///
 /// # Errors
 ///
-/// - [`EBCCError::InvalidInput`] if the `data` has any zero-size dimension
+/// - [`EBCCError::EmptyDimension`] if the `data` has any zero-size dimension
 /// - [`EBCCError::InvalidInput`] if the size of `data` overflows or would not
 ///   fit into memory
-/// - [`EBCCError::InvalidInput`] if the last two dimensions of `data` are too
-///   small or its EBCC internal image dimensions are outside the supported range
+/// - [`EBCCError::UnsupportedShape`] if the last two dimensions of `data` are
+///   too small or its EBCC internal image dimensions are outside the supported
+///   range
 /// - [`EBCCError::InvalidConfig`] if [`config.validate`][EBCCConfig::validate]
 ///   fails
-/// - [`EBCCError::InvalidInput`] if the `data` contains any non-finite
-///   (infinite or NaN) values
-/// - [`EBCCError::CompressionError`] if compression with EBCC fails
+/// - [`EBCCError::TooManyFrames`] or [`EBCCError::FrameTooLarge`] if the shape
+///   of the `data` exceeds the [`config.limits`][EBCCConfig::limits]
+/// - [`EBCCError::InputTooLarge`] or [`EBCCError::OutputTooLarge`] if the size
+///   of the `data` or of the compressed data exceeds the
+///   [`config.limits`][EBCCConfig::limits]
+/// - [`EBCCError::NonFinite`] if the `data` contains any non-finite (infinite
+///   or NaN) values, unless the check is skipped with
+///   [`EBCCConfig::skip_finite_check`]
+/// - [`EBCCError::CompressionError`] if compression with EBCC fails, and the
+///   frames are too small to retry with tiles
+/// - [`EBCCError::ExpansionTooLarge`] if the compressed data exceeds the
+///   [`config.expansion_guard`][EBCCConfig::expansion_guard] and its
+///   fallback is [`EBCCExpansionFallback::Error`]
 ///
 /// # Examples
 ///
-/// ```rust
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
 /// use ebcc::{ebcc_encode, EBCCConfig};
 /// use ndarray::Array;
 ///
@@ -71,43 +142,595 @@ pub enum EBCCCompatChunkShape {
 /// # }
 /// ```
 pub fn ebcc_encode(data: ArrayView<f32, EbccDim>, config: &EBCCConfig) -> EBCCResult<Vec<u8>> {
-    validate_data_shape(data)?;
-    validate_regular_ebcc_shape(data)?;
-    config.validate()?;
-    validate_only_finite_data(data)?;
+    capture_call(CaptureInput::encode(data.view(), config), || {
+        let payload = ebcc_encode_c_buffer(data, config)?;
+        let payload = payload.as_slice();
+
+        let mut compressed_data = Vec::with_capacity(
+            EBCCHeader::encoded_len_with(config.checksum_algorithm) + payload.len(),
+        );
+        record_alloc(compressed_data.capacity());
+        write_header(&mut compressed_data, data, config, payload)?;
+        compressed_data.extend_from_slice(payload);
+        record_copy();
+
+        Ok(compressed_data)
+    })
+}
+
+/// Encode a 3D data array using EBCC compression, which may modify the
+/// `data` in-place.
+///
+/// [`ebcc_encode`] copies the data, since the EBCC C library may modify its
+/// input. If the `data` is in standard (row-major) order and the `config`
+/// encodes with the EBCC C library directly, i.e. without any of the stages
+/// or base modes that are implemented in Rust, this function instead passes
+/// the `data` straight to the C library. The compressed payload is then only
+/// copied once, into the single output allocation. Otherwise, the data is
+/// copied as in [`ebcc_encode`].
+///
+/// <div class="warning">
+///
+/// **Warning:** After this function returns, the contents of `data` are
+/// unspecified.
+///
+/// </div>
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_encode`] can return
+/// - [`EBCCError::CompressionError`] if the EBCC C library fails to encode
+///   the data in-place, since the modified data cannot be
+///   [retried with tiles][ebcc_encode] like in [`ebcc_encode`]
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_mut_into, ebcc_encode_mut, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let mut data = Array::from_elem((1, 32, 32), 1.0_f32);
+/// let mut compressed = ebcc_encode_mut(data.view_mut(), &EBCCConfig::new())?;
+///
+/// let mut decompressed = Array::zeros((1, 32, 32));
+/// ebcc_decode_mut_into(&mut compressed, decompressed.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_mut(
+    mut data: ArrayViewMut<f32, EbccDim>,
+    config: &EBCCConfig,
+) -> EBCCResult<Vec<u8>> {
+    capture_call_mut(
+        &mut data,
+        |data| CaptureInput::encode(data.view(), config).snapshot(),
+        |data| encode_mut(data.view_mut(), config),
+    )
+}
+
+/// Encode a 3D data array like [`ebcc_encode_mut`], without capturing a
+/// failed call.
+fn encode_mut(mut data: ArrayViewMut<f32, EbccDim>, config: &EBCCConfig) -> EBCCResult<Vec<u8>> {
+    if !encodes_with_ffi_only(config) || !data.is_standard_layout() {
+        return ebcc_encode(data.view(), config);
+    }
+
+    debug_span!("ebcc_encode", shape = ?data.shape());
+    validate_encode_input(data.view(), config)?;
+
+    let shape = data.dim();
+    let Some(input) = data.as_slice_mut() else {
+        return ebcc_encode(data.view(), config);
+    };
+    let payload = ffi_encode(input, shape, config)?;
+    let payload = guard_expansion(data.view(), config, payload)?;
+    let payload = payload.as_slice();
+    config.limits.check_output_bytes(payload.len())?;
+
+    let mut compressed_data =
+        Vec::with_capacity(EBCCHeader::encoded_len_with(config.checksum_algorithm) + payload.len());
+    record_alloc(compressed_data.capacity());
+    // the header only depends on the shape and layout, not on the data
+    write_header(&mut compressed_data, data.view(), config, payload)?;
+    compressed_data.extend_from_slice(payload);
+    record_copy();
+
+    Ok(compressed_data)
+}
+
+/// Encode a flat slice of data with the `(frames, height, width)` `shape`
+/// in standard (row-major) order using EBCC compression.
+///
+/// The compressed data is the same as if the data were passed to
+/// [`ebcc_encode`] as an array view, such that callers do not have to build
+/// an `ndarray` view themselves.
+///
+/// # Errors
+///
+/// - [`EBCCError::SliceLenMismatch`] if the `data` does not have the number
+///   of elements of the `shape`
+/// - all errors that [`ebcc_encode`] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_into_slice, ebcc_encode_slice, EBCCConfig};
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = vec![1.0_f32; 2 * 32 * 32];
+/// let compressed = ebcc_encode_slice(&data, (2, 32, 32), &EBCCConfig::new())?;
+///
+/// let mut decompressed = vec![0.0; data.len()];
+/// ebcc_decode_into_slice(&compressed, &mut decompressed, (2, 32, 32))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_slice(
+    data: &[f32],
+    shape: (usize, usize, usize),
+    config: &EBCCConfig,
+) -> EBCCResult<Vec<u8>> {
+    ebcc_encode(slice_view(data, shape)?, config)
+}
+
+/// Encode a 3D data array using EBCC compression into the caller-provided
+/// `compressed_data` buffer.
+///
+/// The contents of `compressed_data` are replaced with the same bytes that
+/// [`ebcc_encode`] returns, and its capacity is reused, such that a
+/// long-running service that encodes many chunks into the same buffer only
+/// allocates when a chunk compresses to more bytes than any before.
+///
+/// # Returns
+///
+/// The number of compressed bytes, i.e. the new length of `compressed_data`.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_encode`] can return, in which case
+///   `compressed_data` is empty
+///
+/// # Examples
+///
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+/// use ebcc::{ebcc_encode_into, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let mut compressed = Vec::new();
+/// for value in [1.0, 2.0, 3.0] {
+///     let chunk = Array::from_elem((1, 32, 32), value);
+///     let len = ebcc_encode_into(chunk.view(), &config, &mut compressed)?;
+///     assert_eq!(len, compressed.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_into(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    compressed_data: &mut Vec<u8>,
+) -> EBCCResult<usize> {
+    compressed_data.clear();
+
+    let payload = ebcc_encode_c_buffer(data, config)?;
+    let payload = payload.as_slice();
+
+    let capacity = compressed_data.capacity();
+    compressed_data
+        .reserve(EBCCHeader::encoded_len_with(config.checksum_algorithm) + payload.len());
+    record_alloc(compressed_data.capacity() - capacity);
+    if let Err(err) = write_header(compressed_data, data, config, payload) {
+        compressed_data.clear();
+        return Err(err);
+    }
+    compressed_data.extend_from_slice(payload);
+    record_copy();
+
+    Ok(compressed_data.len())
+}
+
+/// Encode a 3D data array using EBCC compression into the caller-provided
+/// `compressed_data` slice.
+///
+/// The first bytes of `compressed_data` are overwritten with the same bytes
+/// that [`ebcc_encode`] returns, and the remaining bytes are left unchanged.
+/// A slice of [`EBCCConfig::max_compressed_size`] bytes, if it is bounded,
+/// is always large enough.
+///
+/// # Returns
+///
+/// The number of compressed bytes that were written.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_encode`] can return
+/// - [`EBCCError::BufferTooSmall`] with the required size if the compressed
+///   data does not fit into `compressed_data`, in which case it is left
+///   unchanged
+pub fn ebcc_encode_into_slice(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    compressed_data: &mut [u8],
+) -> EBCCResult<usize> {
+    let payload = ebcc_encode_c_buffer(data, config)?;
+    let payload = payload.as_slice();
+
+    let header_len = EBCCHeader::encoded_len_with(config.checksum_algorithm);
+    let required = header_len + payload.len();
+    let Some((mut header, rest)) = compressed_data
+        .get_mut(..required)
+        .and_then(|output| output.split_at_mut_checked(header_len))
+    else {
+        return Err(EBCCError::BufferTooSmall {
+            required,
+            available: compressed_data.len(),
+        });
+    };
 
+    write_header(&mut header, data, config, payload)?;
+    rest.copy_from_slice(payload);
+    record_copy();
+
+    Ok(required)
+}
+
+/// Encode a 3D data array using EBCC compression into a C-allocated buffer.
+pub fn ebcc_encode_c_buffer(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+) -> EBCCResult<CBuffer<u8>> {
+    ebcc_encode_c_buffer_with_scratch(data, config, &mut Vec::new())
+}
+
+/// Encode a 3D data array into a payload whose decoded data is
+/// postprocessed, or return [`None`] if the `config` has no postprocessing.
+///
+/// The postprocessing payloads wrap an inner payload, which is encoded
+/// recursively with the postprocessing cleared from the `config`.
+fn postprocessed_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> Option<EBCCResult<Vec<u8>>> {
+    if config.quantile_sketch {
+        return Some(sketch_encode(data, config, scratch));
+    }
+
+    if config.value_range.is_some() {
+        return Some(clamp_encode(data, config, scratch));
+    }
+
+    if config.output_quantization.is_some() {
+        return Some(quantize_encode(data, config, scratch));
+    }
+
+    if config.conservation.is_some() {
+        return Some(conserve_encode(data, config, scratch));
+    }
+
+    None
+}
+
+/// Encode a 3D data array into a payload whose residual of the `JPEG2000`
+/// base layer is coded separately in Rust, or return [`None`] if the
+/// `config` uses the residual stage of the EBCC C library.
+fn residual_layer_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> Option<EBCCResult<Vec<u8>>> {
+    if config.base_mode == EBCCBaseMode::Layered {
+        return Some(layered_encode(data, config, scratch));
+    }
+
+    if config.residual_coder.is_some() || config.base_codec != EBCCBaseCodec::Jpeg2000 {
+        return Some(residual_coded_encode(data, config, scratch));
+    }
+
+    None
+}
+
+/// Encode a 3D data array using EBCC compression into a C-allocated buffer,
+/// reusing the `scratch` buffer for the copy of the input data.
+pub fn ebcc_encode_c_buffer_with_scratch(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<CBuffer<u8>> {
+    capture_call(CaptureInput::encode(data.view(), config), || {
+        let compressed_data = encode_c_buffer(data, config, scratch)?;
+        config
+            .limits
+            .check_output_bytes(compressed_data.as_slice().len())?;
+        Ok(compressed_data)
+    })
+}
+
+/// Encode a 3D data array using EBCC compression into a C-allocated buffer,
+/// without checking the size of the output against the `config`'s limits.
+fn encode_c_buffer(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<CBuffer<u8>> {
+    debug_span!("ebcc_encode", shape = ?data.shape());
+
+    validate_encode_input(data, config)?;
+
+    if let Some(compressed_data) = postprocessed_encode(data, config, scratch) {
+        return compressed_data.map(CBuffer::from_vec);
+    }
+
+    if config.stage.is_some() {
+        let compressed_data = stage_encode(data, config, scratch)?;
+        return guard_expansion(data, config, CBuffer::from_vec(compressed_data));
+    }
+
+    if config.base_mode == EBCCBaseMode::Stored {
+        let compressed_data = {
+            debug_span!("encode");
+            stored_encode(data, config.stored_compression)?
+        };
+
+        debug_event!(
+            compressed_bytes = compressed_data.len(),
+            ratio = compression_ratio(data.len(), compressed_data.len()),
+            "encoded stored EBCC data",
+        );
+
+        return Ok(CBuffer::from_vec(compressed_data));
+    }
+
+    if config.transform.is_some() {
+        let compressed_data = transform_encode(data, config, scratch)?;
+
+        debug_event!(
+            compressed_bytes = compressed_data.len(),
+            ratio = compression_ratio(data.len(), compressed_data.len()),
+            "encoded transformed EBCC data",
+        );
+
+        return guard_expansion(data, config, CBuffer::from_vec(compressed_data));
+    }
+
+    if config.roi.is_some() {
+        let compressed_data = roi_encode(data, config, scratch)?;
+
+        debug_event!(
+            compressed_bytes = compressed_data.len(),
+            ratio = compression_ratio(data.len(), compressed_data.len()),
+            "encoded region-of-interest EBCC data",
+        );
+
+        return guard_expansion(data, config, CBuffer::from_vec(compressed_data));
+    }
+
+    if config.base_mode == EBCCBaseMode::None {
+        let compressed_data = {
+            debug_span!("encode");
+            residual_only_encode(data, config.residual_compression_type)?
+        };
+
+        debug_event!(
+            compressed_bytes = compressed_data.len(),
+            ratio = compression_ratio(data.len(), compressed_data.len()),
+            "encoded residual-only EBCC data",
+        );
+
+        return guard_expansion(data, config, CBuffer::from_vec(compressed_data));
+    }
+
+    if let Some(compressed_data) = residual_layer_encode(data, config, scratch) {
+        let compressed_data = compressed_data?;
+
+        debug_event!(
+            compressed_bytes = compressed_data.len(),
+            ratio = compression_ratio(data.len(), compressed_data.len()),
+            "encoded EBCC data with a separate residual layer",
+        );
+
+        return guard_expansion(data, config, CBuffer::from_vec(compressed_data));
+    }
+
+    // C function may modify the input
+    let _data_copy_alloc = TrackedAlloc::new(data.len() * std::mem::size_of::<f32>());
+    let data_copy = {
+        debug_span!("copy");
+        copy_standard_order(data, scratch);
+        scratch
+    };
+    record_copy();
+
+    match ffi_encode(data_copy, data.dim(), config) {
+        Ok(compressed_data) => guard_expansion(data, config, compressed_data),
+        Err(err) => {
+            // very large frames may exceed the C library's limits, retry with tiles
+            match ebcc_encode_tiled_fallback(data, config) {
+                Some(compressed_data) => {
+                    guard_expansion(data, config, CBuffer::from_vec(compressed_data?))
+                }
+                None => Err(err),
+            }
+        }
+    }
+}
+
+/// Encode the `input` data of the `shape`, in standard order, with the EBCC
+/// C library into a C-allocated buffer.
+///
+/// The C library may modify the `input`.
+#[cfg(feature = "encode")]
+fn ffi_encode(
+    input: &mut [f32],
+    shape: (usize, usize, usize),
+    config: &EBCCConfig,
+) -> EBCCResult<CBuffer<u8>> {
     // Convert to FFI types
-    let mut ffi_config = ffi_config(data.dim().into(), config, [0; EBCC_NDIMS]);
-    let mut data_copy: Vec<f32> = data.iter().copied().collect(); // C function may modify the input
+    let mut ffi_config = ffi_config(shape.into(), config, [0; EBCC_NDIMS]);
 
     // Call the C function
     let mut out_buffer: *mut u8 = ptr::null_mut();
-    #[expect(unsafe_code)]
-    let compressed_size = unsafe {
-        ebcc_sys::ebcc_encode(
-            data_copy.as_mut_ptr(),
-            &raw mut ffi_config,
-            &raw mut out_buffer,
-        )
+    let compressed_size = {
+        debug_span!("encode");
+        #[expect(unsafe_code)]
+        with_ebcc_encode_call(config.deterministic, || unsafe {
+            ebcc_sys::ebcc_encode(input.as_mut_ptr(), &raw mut ffi_config, &raw mut out_buffer)
+        })
     };
 
     // Check for errors
-    if compressed_size == 0 || out_buffer.is_null() {
-        return Err(EBCCError::CompressionError(String::from(
-            "ebcc_encode C function returned null or zero size",
+    let out_is_null = out_buffer.is_null();
+    #[expect(unsafe_code)]
+    // Safety: out_buffer was allocated by EBCC and has compressed_size elements
+    let Some(compressed_data) = (unsafe { CBuffer::new(out_buffer, compressed_size) }) else {
+        return Err(EBCCError::CompressionError(ffi_failure(
+            "ebcc_encode",
+            format_args!("data of shape {:?}", <[usize; 3]>::from(shape)),
+            out_is_null,
         )));
+    };
+
+    debug_event!(
+        compressed_bytes = compressed_size,
+        ratio = compression_ratio(input.len(), compressed_size),
+        "encoded EBCC data",
+    );
+
+    Ok(compressed_data)
+}
+
+/// Encode the `input` data like [`ffi_encode`] without the `encode` feature,
+/// which always fails.
+#[cfg(not(feature = "encode"))]
+fn ffi_encode(
+    _input: &mut [f32],
+    _shape: (usize, usize, usize),
+    _config: &EBCCConfig,
+) -> EBCCResult<CBuffer<u8>> {
+    Err(encode_unsupported())
+}
+
+/// Error of an encode with the EBCC C library without the `encode` feature.
+#[cfg(not(feature = "encode"))]
+pub fn encode_unsupported() -> EBCCError {
+    EBCCError::CompressionError(
+        "Encoding with the EBCC C library requires the `encode` feature".into(),
+    )
+}
+
+/// Error of a decode with the EBCC C library without the `decode` feature.
+#[cfg(not(feature = "decode"))]
+pub fn decode_unsupported() -> EBCCError {
+    EBCCError::DecompressionError(
+        "Decoding with the EBCC C library requires the `decode` feature".into(),
+    )
+}
+
+/// Check if the `config` encodes data with the EBCC C library directly, such
+/// that the data is not needed after the C library may have modified it.
+///
+/// This mirrors the dispatch in [`encode_c_buffer`], where all Rust-side
+/// stages and base modes encode from a read-only view of the data, and the
+/// [`EBCCExpansionFallback::StoreRaw`] stores the original data.
+fn encodes_with_ffi_only(config: &EBCCConfig) -> bool {
+    config.base_mode == EBCCBaseMode::Jpeg2000
+        && !config.quantile_sketch
+        && config.value_range.is_none()
+        && config.output_quantization.is_none()
+        && config.conservation.is_none()
+        && config.stage.is_none()
+        && config.transform.is_none()
+        && config.roi.is_none()
+        && config.residual_coder.is_none()
+        && config.base_codec == EBCCBaseCodec::Jpeg2000
+        && !matches!(
+            config.expansion_guard,
+            Some(EBCCExpansionGuard {
+                fallback: EBCCExpansionFallback::StoreRaw,
+                ..
+            })
+        )
+}
+
+/// Validate the `data` and the `config` before encoding.
+fn validate_encode_input(data: ArrayView<f32, EbccDim>, config: &EBCCConfig) -> EBCCResult<()> {
+    debug_span!("validate");
+    validate_data_shape(data)?;
+    validate_regular_ebcc_shape(data.dim())?;
+    config.validate()?;
+    check_deterministic_encode(config.deterministic)?;
+    config.limits.check_shape(data.dim())?;
+    config
+        .limits
+        .check_input_bytes(data.len().saturating_mul(std::mem::size_of::<f32>()))?;
+    if config.check_finite {
+        validate_only_finite_data(&data)?;
     }
+    Ok(())
+}
 
-    // Copy the compressed data to a Vec and free the C-allocated memory
-    #[expect(unsafe_code)]
-    let compressed_data = unsafe {
-        let slice = slice::from_raw_parts(out_buffer, compressed_size);
-        let vec = slice.to_vec();
-        ebcc_sys::free_buffer(out_buffer.cast::<core::ffi::c_void>());
-        vec
+/// Apply the [`config.expansion_guard`][EBCCConfig::expansion_guard] to the
+/// `compressed_data` of the `data`.
+fn guard_expansion(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    compressed_data: CBuffer<u8>,
+) -> EBCCResult<CBuffer<u8>> {
+    let Some(EBCCExpansionGuard {
+        max_ratio,
+        fallback,
+    }) = config.expansion_guard
+    else {
+        return Ok(compressed_data);
     };
 
-    Ok(compressed_data)
+    let raw_bytes = data.len() * size_of::<f32>();
+    #[expect(clippy::cast_precision_loss)]
+    let within_ratio = |compressed_bytes: usize| {
+        (compressed_bytes as f64) <= (raw_bytes as f64) * f64::from(max_ratio)
+    };
+    let too_large = |compressed_bytes| EBCCError::ExpansionTooLarge {
+        compressed_bytes,
+        raw_bytes,
+        max_ratio,
+    };
+
+    let compressed_bytes = compressed_data.as_slice().len();
+    if within_ratio(compressed_bytes) {
+        return Ok(compressed_data);
+    }
+
+    match fallback {
+        EBCCExpansionFallback::Error => Err(too_large(compressed_bytes)),
+        EBCCExpansionFallback::StoreRaw => {
+            debug_event!(
+                compressed_bytes,
+                raw_bytes,
+                "storing incompressible EBCC data raw",
+            );
+
+            // the stored data must also respect the maximum ratio
+            let stored = stored_encode(data, config.stored_compression)?;
+            if !within_ratio(stored.len()) {
+                return Err(too_large(stored.len()));
+            }
+
+            Ok(CBuffer::from_vec(stored))
+        }
+    }
 }
 
 /// Encode a 3D data array using EBCC chunked compression.
@@ -126,41 +749,86 @@ pub fn ebcc_encode(data: ArrayView<f32, EbccDim>, config: &EBCCConfig) -> EBCCRe
 ///
 /// # Errors
 ///
-/// - [`EBCCError::InvalidInput`] if the `data` has any zero-size dimension
+/// - [`EBCCError::EmptyDimension`] if the `data` has any zero-size dimension
 /// - [`EBCCError::InvalidInput`] if `chunk_shape` has tile dimensions that are
 ///   too small or forms EBCC internal image dimensions outside the supported
 ///   range
 /// - [`EBCCError::InvalidConfig`] if [`config.validate`][EBCCConfig::validate]
 ///   fails
-/// - [`EBCCError::InvalidInput`] if the `data` contains any non-finite
-///   (infinite or NaN) values
+/// - [`EBCCError::TooManyFrames`] or [`EBCCError::FrameTooLarge`] if the shape
+///   of the `data` exceeds the [`config.limits`][EBCCConfig::limits]
+/// - [`EBCCError::NonFinite`] if the `data` contains any non-finite (infinite
+///   or NaN) values, unless the check is skipped with
+///   [`EBCCConfig::skip_finite_check`]
 /// - [`EBCCError::CompressionError`] if compression with EBCC fails
 pub fn ebcc_encode_chunking(
     data: ArrayView<f32, EbccDim>,
     config: &EBCCConfig,
     chunk_shape: EBCCChunkShape,
 ) -> EBCCResult<Vec<u8>> {
-    validate_data_shape(data)?;
-    let chunk_shape = validate_chunk_shape(chunk_shape)?;
-    config.validate()?;
-    validate_only_finite_data(data)?;
+    capture_call(
+        CaptureInput::encode(data.view(), config).chunked(
+            EBCCCaptureOperation::EncodeChunking,
+            Some(chunk_shape.map(NonZeroUsize::get)),
+        ),
+        || encode_chunking(data, config, chunk_shape),
+    )
+}
+
+/// Encode a 3D data array like [`ebcc_encode_chunking`], without capturing
+/// a failed call.
+#[cfg(feature = "encode")]
+fn encode_chunking(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    chunk_shape: EBCCChunkShape,
+) -> EBCCResult<Vec<u8>> {
+    debug_span!("ebcc_encode_chunking", shape = ?data.shape());
+
+    let chunk_shape = {
+        debug_span!("validate");
+        validate_data_shape(data)?;
+        let chunk_shape = validate_chunk_shape(chunk_shape)?;
+        config.validate()?;
+        check_deterministic_encode(config.deterministic)?;
+        validate_jpeg2000_base(config)?;
+        config.limits.check_shape(data.dim())?;
+        if config.check_finite {
+            validate_only_finite_data(&data)?;
+        }
+        chunk_shape
+    };
 
     let mut ffi_config = ffi_config(data.dim().into(), config, chunk_shape);
-    let mut data_copy: Vec<f32> = data.iter().copied().collect(); // C function may modify the input
+    let mut data_copy = Vec::new();
+    {
+        debug_span!("copy");
+        copy_standard_order(data, &mut data_copy); // C function may modify the input
+    }
+    record_copy();
+    let _data_copy_alloc = TrackedAlloc::new(data_copy.capacity() * std::mem::size_of::<f32>());
 
     let mut out_buffer: *mut u8 = ptr::null_mut();
-    #[expect(unsafe_code)]
-    let compressed_size = unsafe {
-        ebcc_sys::ebcc_encode_chunking(
-            data_copy.as_mut_ptr(),
-            &raw mut ffi_config,
-            &raw mut out_buffer,
-        )
+    let compressed_size = {
+        debug_span!("encode", chunk_shape = ?chunk_shape);
+        #[expect(unsafe_code)]
+        with_ebcc_encode_call(config.deterministic, || unsafe {
+            ebcc_sys::ebcc_encode_chunking(
+                data_copy.as_mut_ptr(),
+                &raw mut ffi_config,
+                &raw mut out_buffer,
+            )
+        })
     };
 
     if compressed_size == 0 || out_buffer.is_null() {
-        return Err(EBCCError::CompressionError(String::from(
-            "ebcc_encode_chunking C function returned null or zero size",
+        return Err(EBCCError::CompressionError(ffi_failure(
+            "ebcc_encode_chunking",
+            format_args!(
+                "data of shape {:?} with chunks of shape {chunk_shape:?}",
+                data.shape()
+            ),
+            out_buffer.is_null(),
         )));
     }
 
@@ -169,12 +837,30 @@ pub fn ebcc_encode_chunking(
         let slice = slice::from_raw_parts(out_buffer, compressed_size);
         let vec = slice.to_vec();
         ebcc_sys::free_buffer(out_buffer.cast::<core::ffi::c_void>());
+        record_alloc(vec.capacity());
         vec
     };
 
+    debug_event!(
+        compressed_bytes = compressed_size,
+        ratio = compression_ratio(data.len(), compressed_size),
+        "encoded chunked EBCC data",
+    );
+
     Ok(compressed_data)
 }
 
+/// Encode a 3D data array like [`ebcc_encode_chunking`] without the `encode`
+/// feature, which always fails.
+#[cfg(not(feature = "encode"))]
+fn encode_chunking(
+    _data: ArrayView<f32, EbccDim>,
+    _config: &EBCCConfig,
+    _chunk_shape: EBCCChunkShape,
+) -> EBCCResult<Vec<u8>> {
+    Err(encode_unsupported())
+}
+
 /// Encode a 3D data array using EBCC chunked compression in compatibility mode.
 ///
 /// Passing [`Auto`][EBCCCompatChunkShape::Auto] for `chunk_shape` lets EBCC
@@ -187,185 +873,935 @@ pub fn ebcc_encode_chunking(
 ///
 /// # Errors
 ///
-/// - [`EBCCError::InvalidInput`] if the `data` has any zero-size dimension
+/// - [`EBCCError::EmptyDimension`] if the `data` has any zero-size dimension
 /// - [`EBCCError::InvalidInput`] if explicit `chunk_shape` has tile dimensions
 ///   that are too small or forms EBCC internal image dimensions outside the
 ///   supported range
 /// - [`EBCCError::InvalidConfig`] if [`config.validate`][EBCCConfig::validate]
 ///   fails
-/// - [`EBCCError::InvalidInput`] if the `data` contains any non-finite
-///   (infinite or NaN) values
+/// - [`EBCCError::TooManyFrames`] or [`EBCCError::FrameTooLarge`] if the shape
+///   of the `data` exceeds the [`config.limits`][EBCCConfig::limits]
+/// - [`EBCCError::NonFinite`] if the `data` contains any non-finite (infinite
+///   or NaN) values, unless the check is skipped with
+///   [`EBCCConfig::skip_finite_check`]
 /// - [`EBCCError::CompressionError`] if compression with EBCC fails
 pub fn ebcc_encode_chunking_compat(
     data: ArrayView<f32, EbccDim>,
     config: &EBCCConfig,
     chunk_shape: EBCCCompatChunkShape,
 ) -> EBCCResult<Vec<u8>> {
-    validate_data_shape(data)?;
-    let chunk_shape = compat_chunk_shape(chunk_shape)?;
-    config.validate()?;
-    validate_only_finite_data(data)?;
+    let explicit_chunk_shape = match chunk_shape {
+        EBCCCompatChunkShape::Auto => None,
+        EBCCCompatChunkShape::Explicit(chunk_shape) => Some(chunk_shape.map(NonZeroUsize::get)),
+    };
+
+    capture_call(
+        CaptureInput::encode(data.view(), config).chunked(
+            EBCCCaptureOperation::EncodeChunkingCompat,
+            explicit_chunk_shape,
+        ),
+        || encode_chunking_compat(data, config, chunk_shape),
+    )
+}
+
+/// Encode a 3D data array like [`ebcc_encode_chunking_compat`], without
+/// capturing a failed call.
+#[cfg(feature = "encode")]
+fn encode_chunking_compat(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    chunk_shape: EBCCCompatChunkShape,
+) -> EBCCResult<Vec<u8>> {
+    debug_span!("ebcc_encode_chunking_compat", shape = ?data.shape());
+
+    let chunk_shape = {
+        debug_span!("validate");
+        validate_data_shape(data)?;
+        let chunk_shape = compat_chunk_shape(chunk_shape)?;
+        config.validate()?;
+        check_deterministic_encode(config.deterministic)?;
+        validate_jpeg2000_base(config)?;
+        config.limits.check_shape(data.dim())?;
+        if config.check_finite {
+            validate_only_finite_data(&data)?;
+        }
+        chunk_shape
+    };
+
+    let mut ffi_config = ffi_config(data.dim().into(), config, chunk_shape);
+    let mut data_copy = Vec::new();
+    {
+        debug_span!("copy");
+        copy_standard_order(data, &mut data_copy); // C function may modify the input
+    }
+    record_copy();
+    let _data_copy_alloc = TrackedAlloc::new(data_copy.capacity() * std::mem::size_of::<f32>());
+
+    let mut out_buffer: *mut u8 = ptr::null_mut();
+    let compressed_size = {
+        debug_span!("encode", chunk_shape = ?chunk_shape);
+        #[expect(unsafe_code)]
+        with_ebcc_encode_call(config.deterministic, || unsafe {
+            ebcc_sys::ebcc_encode_chunking_compat(
+                data_copy.as_mut_ptr(),
+                &raw mut ffi_config,
+                &raw mut out_buffer,
+            )
+        })
+    };
+
+    if compressed_size == 0 || out_buffer.is_null() {
+        return Err(EBCCError::CompressionError(ffi_failure(
+            "ebcc_encode_chunking_compat",
+            format_args!(
+                "data of shape {:?} with chunks of shape {chunk_shape:?}",
+                data.shape()
+            ),
+            out_buffer.is_null(),
+        )));
+    }
+
+    #[expect(unsafe_code)]
+    let compressed_data = unsafe {
+        let slice = slice::from_raw_parts(out_buffer, compressed_size);
+        let vec = slice.to_vec();
+        ebcc_sys::free_buffer(out_buffer.cast::<core::ffi::c_void>());
+        record_alloc(vec.capacity());
+        vec
+    };
+
+    debug_event!(
+        compressed_bytes = compressed_size,
+        ratio = compression_ratio(data.len(), compressed_size),
+        "encoded chunked EBCC data",
+    );
+
+    Ok(compressed_data)
+}
+
+/// Encode a 3D data array like [`ebcc_encode_chunking_compat`] without the `encode`
+/// feature, which always fails.
+#[cfg(not(feature = "encode"))]
+fn encode_chunking_compat(
+    _data: ArrayView<f32, EbccDim>,
+    _config: &EBCCConfig,
+    _chunk_shape: EBCCCompatChunkShape,
+) -> EBCCResult<Vec<u8>> {
+    Err(encode_unsupported())
+}
+
+/// Decode into a 3D data array using EBCC decompression.
+///
+/// Since the EBCC C library does not guarantee that its input is left
+/// unmodified, the compressed payload is copied before it is passed to the
+/// library. Residual-only and stored-raw payloads, which are decoded without
+/// the library, are read straight from `compressed_data`. Use
+/// [`ebcc_decode_mut_into`] to avoid the copy if the caller owns the
+/// compressed bytes.
+///
+/// # Arguments
+///
+/// - `compressed_data`: Compressed data bytes produced by [`ebcc_encode`], by
+///   [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive], by an
+///   [`EbccStreamEncoder`][crate::EbccStreamEncoder], or by an
+///   [`EbccContainerWriter`][crate::container::EbccContainerWriter]
+/// - `decompressed_data`: 3D output data array
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::TooManyFrames`] or [`EBCCError::FrameTooLarge`] if the shape
+///   of the `decompressed_data` exceeds the default [`EBCCLimits`]
+/// - [`EBCCError::ShapeMismatch`] if the shape in the
+///   [`EBCCHeader`][crate::EBCCHeader] of the `compressed_data` does not
+///   match the `decompressed_data`
+/// - [`EBCCError::InvalidInput`] if the payload after the
+///   [`EBCCHeader`][crate::EBCCHeader] is truncated
+/// - [`EBCCError::ChecksumMismatch`] if the checksum of the payload, or of
+///   the decompressed data, does not match the
+///   [`EBCCHeader`][crate::EBCCHeader]
+/// - [`EBCCError::DecompressionError`] if the
+///   [`EBCCHeader`][crate::EBCCHeader] is not supported or decompression
+///   with EBCC fails
+/// - [`EBCCError::SizeMismatch`] if the decompressed data does not fit into
+///   `decompressed_data`
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` is an EBCC stream
+///   that is truncated or whose frames do not fit into `decompressed_data`
+/// - [`EBCCError::ShapeMismatch`] or [`EBCCError::InvalidInput`] if the
+///   `compressed_data` was compressed with
+///   [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] and its shape does
+///   not match the `decompressed_data` or it is truncated or corrupted
+/// - all errors that [`EbccContainer::open`] and
+///   [`EbccContainer::decode_into`] can return if the `compressed_data` is an
+///   EBCC container
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_encode, ebcc_decode_into, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_vec((1, 32, 32), vec![1.0f32; 32 * 32]).unwrap();
+/// let config = EBCCConfig::new();
+///
+/// let compressed = ebcc_encode(data.view(), &config)?;
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_into(&compressed, decompressed.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_into(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    let shape = decompressed_data.dim();
+    capture_call(CaptureInput::decode(compressed_data, shape), || {
+        if compressed_data.is_empty() {
+            return Err(EBCCError::EmptyInput);
+        }
+
+        EBCCLimits::default().check_shape(shape)?;
+
+        if is_ebcc_stream(compressed_data) {
+            return ebcc_decode_stream_into(compressed_data, decompressed_data);
+        }
+
+        if is_ebcc_container(compressed_data) {
+            #[cfg(feature = "decode")]
+            return EbccContainer::open(Cursor::new(compressed_data))?
+                .decode_into(decompressed_data);
+            #[cfg(not(feature = "decode"))]
+            return Err(decode_unsupported());
+        }
+
+        ebcc_decode_frames_into(compressed_data, decompressed_data)
+    })
+}
+
+/// Decode into a flat slice of data with the `(frames, height, width)`
+/// `shape` in standard (row-major) order using EBCC decompression.
+///
+/// This function decodes all formats that [`ebcc_decode_into`] decodes.
+///
+/// # Errors
+///
+/// - [`EBCCError::SliceLenMismatch`] if the `decompressed_data` does not
+///   have the number of elements of the `shape`
+/// - all errors that [`ebcc_decode_into`] can return
+pub fn ebcc_decode_into_slice(
+    compressed_data: &[u8],
+    decompressed_data: &mut [f32],
+    shape: (usize, usize, usize),
+) -> EBCCResult<()> {
+    let len = decompressed_data.len();
+    check_slice_len(len, shape)?;
+    let decompressed_data = ArrayViewMut::from_shape(shape, decompressed_data).map_err(|_| {
+        EBCCError::SliceLenMismatch {
+            shape: shape.into(),
+            len,
+        }
+    })?;
+
+    ebcc_decode_into(compressed_data, decompressed_data)
+}
+
+/// Decode into a raw buffer of `len` elements with the
+/// `(frames, height, width)` `shape` in standard (row-major) order using EBCC
+/// decompression.
+///
+/// This function is an escape hatch for applications that decode straight
+/// into memory that they do not own as a Rust slice, e.g. a pinned or mapped
+/// GPU staging buffer. Prefer [`ebcc_decode_into_slice`] whenever a slice is
+/// available.
+///
+/// The `len` and `shape` are checked before the buffer is accessed. If
+/// decoding fails, the contents of the buffer are unspecified.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the `decompressed_data` pointer is null
+///   or not aligned for `f32`
+/// - [`EBCCError::SliceLenMismatch`] if `len` is not the number of elements
+///   of the `shape`
+/// - all errors that [`ebcc_decode_into`] can return
+///
+/// # Safety
+///
+/// If the `decompressed_data` pointer is non-null and aligned, it must be
+/// [valid](std::ptr#safety) for reads and writes of `len` consecutive `f32`
+/// elements, and the memory must not be accessed through any other pointer
+/// until this function returns. The elements do not need to be initialized.
+#[expect(unsafe_code)]
+pub unsafe fn ebcc_decode_into_raw(
+    compressed_data: &[u8],
+    decompressed_data: *mut f32,
+    len: usize,
+    shape: (usize, usize, usize),
+) -> EBCCResult<()> {
+    if decompressed_data.is_null() || !decompressed_data.is_aligned() {
+        return Err(EBCCError::InvalidInput(String::from(
+            "Decoding into a raw buffer requires a non-null and aligned pointer",
+        )));
+    }
+    check_slice_len(len, shape)?;
+
+    // initialize the elements, since a slice must not refer to uninitialized
+    // memory
+    // Safety: the caller guarantees that the non-null and aligned pointer is
+    //         valid for writes of len elements
+    unsafe { ptr::write_bytes(decompressed_data, 0, len) };
+    // Safety: the caller guarantees that the pointer is valid for reads and
+    //         writes of len elements, which are initialized now, and that
+    //         they are not aliased until this function returns
+    let decompressed_data = unsafe { slice::from_raw_parts_mut(decompressed_data, len) };
+
+    ebcc_decode_into_slice(compressed_data, decompressed_data, shape)
+}
+
+/// View a flat slice of `data` with the `shape` in standard order.
+fn slice_view(
+    data: &[f32],
+    shape: (usize, usize, usize),
+) -> EBCCResult<ArrayView<'_, f32, EbccDim>> {
+    check_slice_len(data.len(), shape)?;
+
+    ArrayView::from_shape(shape, data).map_err(|_| EBCCError::SliceLenMismatch {
+        shape: shape.into(),
+        len: data.len(),
+    })
+}
+
+/// Decode into a 3D data array using EBCC decompression, allowing EBCC to
+/// modify the `compressed_data` while decoding.
+///
+/// [`ebcc_decode_into`] has to copy the entire `compressed_data` before
+/// decoding any payload that reaches the EBCC C library, since the library
+/// does not guarantee that its input is left unmodified. If the caller owns the compressed bytes and does not need
+/// them afterwards, this function avoids that copy, so that decoding a 100 MB
+/// chunk does not allocate a second 100 MB buffer.
+///
+/// EBCC frame streams, produced by an
+/// [`EbccStreamEncoder`][crate::EbccStreamEncoder], are never modified and
+/// only copy one segment at a time.
+///
+/// <div class="warning">
+///
+/// **Warning:** After this function returns, the contents of
+/// `compressed_data` are unspecified and should no longer be decoded.
+///
+/// </div>
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_decode_into`] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_mut_into, ebcc_encode, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_elem((1, 32, 32), 1.0_f32);
+/// let mut compressed = ebcc_encode(data.view(), &EBCCConfig::new())?;
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_mut_into(&mut compressed, decompressed.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_mut_into(
+    compressed_data: &mut [u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    let shape = decompressed_data.dim();
+    capture_call_mut(
+        compressed_data,
+        |compressed_data| CaptureInput::decode(compressed_data, shape).snapshot(),
+        |compressed_data| decode_mut_into(compressed_data, decompressed_data),
+    )
+}
+
+/// Decode into a 3D data array like [`ebcc_decode_mut_into`], without
+/// capturing a failed call.
+fn decode_mut_into(
+    compressed_data: &mut [u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    EBCCLimits::default().check_shape(decompressed_data.dim())?;
+
+    if is_ebcc_stream(compressed_data) {
+        return ebcc_decode_stream_into(compressed_data, decompressed_data);
+    }
+
+    if is_ebcc_container(compressed_data) {
+        #[cfg(feature = "decode")]
+        return EbccContainer::open(Cursor::new(&*compressed_data))?.decode_into(decompressed_data);
+        #[cfg(not(feature = "decode"))]
+        return Err(decode_unsupported());
+    }
+
+    ebcc_decode_frames_into_mut(compressed_data, decompressed_data)
+}
+
+/// Decode a single [`ebcc_encode`] payload into a 3D data array.
+///
+/// The header is validated before the payload is copied for the C library,
+/// such that invalid input is rejected without allocating. Tiled,
+/// residual-only, and stored-raw payloads never reach the C library and are
+/// decoded without the copy.
+pub fn ebcc_decode_frames_into(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    capture_call(
+        CaptureInput::decode(compressed_data, decompressed_data.dim()),
+        || decode_frames_into(compressed_data, decompressed_data),
+    )
+}
+
+/// Decode a single [`ebcc_encode`] payload like [`ebcc_decode_frames_into`],
+/// without capturing a failed call.
+fn decode_frames_into(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    if is_ebcc_tiled(compressed_data) {
+        return ebcc_decode_tiled_into(compressed_data, decompressed_data);
+    }
+
+    let (header, payload) = header_payload(compressed_data)?;
+    if let Some(header) = &header {
+        let output_shape = <[usize; 3]>::from(decompressed_data.dim());
+        if header.shape != output_shape {
+            return Err(shape_mismatch(header.shape, output_shape));
+        }
+    }
+
+    let checksum = header.and_then(|header| header.decompressed_checksum);
+
+    if let Some(decompressed) = decode_shared_payload(payload, decompressed_data.dim()) {
+        return copy_checked_into(
+            &CBuffer::from_vec(decompressed?),
+            checksum,
+            decompressed_data,
+        );
+    }
+
+    let mut payload_copy = Vec::from(payload); // C function may modify the input
+    let _copy_alloc = TrackedAlloc::new(payload_copy.capacity());
+    record_copy();
+
+    decode_payload_into(&mut payload_copy, checksum, decompressed_data)
+}
+
+/// Decode a residual-only or stored-raw `payload` of the expected `shape`,
+/// which is decoded in Rust without ever reaching the EBCC C library, straight
+/// from the shared input.
+///
+/// Returns [`None`] for all other payloads, which need a copy that the C
+/// library may modify.
+fn decode_shared_payload(
+    payload: &[u8],
+    shape: (usize, usize, usize),
+) -> Option<EBCCResult<Vec<f32>>> {
+    if is_residual_only(payload) {
+        return Some(residual_only_decode(payload, shape));
+    }
 
-    let mut ffi_config = ffi_config(data.dim().into(), config, chunk_shape);
-    let mut data_copy: Vec<f32> = data.iter().copied().collect(); // C function may modify the input
+    if is_stored(payload) {
+        return Some(stored_decode(payload, shape));
+    }
 
-    let mut out_buffer: *mut u8 = ptr::null_mut();
-    #[expect(unsafe_code)]
-    let compressed_size = unsafe {
-        ebcc_sys::ebcc_encode_chunking_compat(
-            data_copy.as_mut_ptr(),
-            &raw mut ffi_config,
-            &raw mut out_buffer,
-        )
-    };
+    None
+}
 
-    if compressed_size == 0 || out_buffer.is_null() {
-        return Err(EBCCError::CompressionError(String::from(
-            "ebcc_encode_chunking_compat C function returned null or zero size",
-        )));
+/// Decode a single [`ebcc_encode`] payload, with or without a header, which
+/// may be modified during decoding, into a 3D data array.
+pub fn ebcc_decode_frames_into_mut(
+    compressed_data: &mut [u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    let shape = decompressed_data.dim();
+    capture_call_mut(
+        compressed_data,
+        |compressed_data| CaptureInput::decode(compressed_data, shape).snapshot(),
+        |compressed_data| decode_frames_into_mut(compressed_data, decompressed_data),
+    )
+}
+
+/// Decode a single [`ebcc_encode`] payload like
+/// [`ebcc_decode_frames_into_mut`], without capturing a failed call.
+fn decode_frames_into_mut(
+    compressed_data: &mut [u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    if is_ebcc_tiled(compressed_data) {
+        return ebcc_decode_tiled_into(compressed_data, decompressed_data);
     }
 
-    #[expect(unsafe_code)]
-    let compressed_data = unsafe {
-        let slice = slice::from_raw_parts(out_buffer, compressed_size);
-        let vec = slice.to_vec();
-        ebcc_sys::free_buffer(out_buffer.cast::<core::ffi::c_void>());
-        vec
-    };
+    let (payload, checksum) = header_payload_mut(compressed_data, decompressed_data.dim())?;
+    decode_payload_into(payload, checksum, decompressed_data)
+}
 
-    Ok(compressed_data)
+/// Decode an EBCC C library `payload`, which may be modified during
+/// decoding, into a 3D data array and verify its `checksum`, if any.
+fn decode_payload_into(
+    payload: &mut [u8],
+    checksum: Option<u32>,
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    let decompressed_buffer = ebcc_decode_c_buffer_mut(payload, decompressed_data.dim())?;
+    copy_checked_into(&decompressed_buffer, checksum, decompressed_data)
 }
 
-/// Decode into a 3D data array using EBCC decompression.
-///
-/// # Arguments
-///
-/// - `compressed_data`: Compressed data bytes produced by [`ebcc_encode`]
-/// - `decompressed_data`: 3D output data array
-///
-/// # Errors
-///
-/// - [`EBCCError::InvalidInput`] if the `compressed_data` is empty
-/// - [`EBCCError::DecompressionError`] if decompression with EBCC fails
-/// - [`EBCCError::InvalidInput`] if the decompressed data does not fit into
-///   `decompressed_data`
-///
-/// # Examples
-///
-/// ```rust
-/// use ebcc::{ebcc_encode, ebcc_decode_into, EBCCConfig};
-/// use ndarray::Array;
+/// Verify the `checksum`, if any, of a decompressed buffer and copy it into
+/// a 3D data array.
+fn copy_checked_into(
+    decompressed_buffer: &CBuffer<f32>,
+    checksum: Option<u32>,
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    verify_decompressed_checksum(checksum, decompressed_buffer.as_slice())?;
+    let decompressed_view = decompressed_view(decompressed_data.dim(), decompressed_buffer)?;
+
+    copy_decompressed(decompressed_data, decompressed_view);
+
+    Ok(())
+}
+
+/// Copy decompressed data of the same shape into a 3D data array.
 ///
-/// # fn main() -> ebcc::EBCCResult<()> {
-/// let data = Array::from_shape_vec((1, 32, 32), vec![1.0f32; 32 * 32]).unwrap();
-/// let config = EBCCConfig::new();
+/// Contiguous outputs are filled with a single `memcpy` straight from the
+/// decompressed buffer, while other layouts fall back to an element-wise
+/// copy.
+pub fn copy_decompressed(
+    mut decompressed_data: ArrayViewMut<f32, EbccDim>,
+    decompressed_view: ArrayView<f32, EbccDim>,
+) {
+    match (
+        decompressed_data.as_slice_mut(),
+        decompressed_view.as_slice(),
+    ) {
+        (Some(output), Some(input)) if output.len() == input.len() => {
+            output.copy_from_slice(input);
+        }
+        _ => decompressed_data.assign(&decompressed_view),
+    }
+    record_copy();
+}
+
+/// Decode a single [`ebcc_encode`] payload of the expected `shape`, which may
+/// be modified during decoding, into a C-allocated buffer.
 ///
-/// let compressed = ebcc_encode(data.view(), &config)?;
+/// Residual-only, region-of-interest, and stored-raw payloads are rejected
+/// before anything is allocated if they declare a different shape.
+pub fn ebcc_decode_c_buffer_mut(
+    compressed_data: &mut [u8],
+    shape: (usize, usize, usize),
+) -> EBCCResult<CBuffer<f32>> {
+    capture_call_mut(
+        compressed_data,
+        |compressed_data| CaptureInput::decode(compressed_data, shape).snapshot(),
+        |compressed_data| ebcc_decode_nested(compressed_data, shape, 0),
+    )
+}
+
+/// Maximum number of EBCC payloads, e.g. region-of-interest or transformed
+/// payloads, that can be nested inside an EBCC payload.
+pub const MAX_NESTING_DEPTH: usize = 8;
+
+/// Decode an EBCC payload like [`ebcc_decode_c_buffer_mut`], which is nested
+/// `depth` levels deep inside other EBCC payloads.
 ///
-/// let mut decompressed = Array::zeros(data.dim());
-/// ebcc_decode_into(&compressed, decompressed.view_mut())?;
-/// # Ok(())
-/// # }
-/// ```
-pub fn ebcc_decode_into(
-    compressed_data: &[u8],
-    mut decompressed_data: ArrayViewMut<f32, EbccDim>,
-) -> EBCCResult<()> {
+/// Payloads that are nested more than [`MAX_NESTING_DEPTH`] levels deep are
+/// rejected, such that crafted payloads cannot overflow the stack.
+pub fn ebcc_decode_nested(
+    compressed_data: &mut [u8],
+    shape: (usize, usize, usize),
+    depth: usize,
+) -> EBCCResult<CBuffer<f32>> {
+    debug_span!("ebcc_decode", compressed_bytes = compressed_data.len());
+
     if compressed_data.is_empty() {
-        return Err(EBCCError::InvalidInput(String::from(
-            "Compressed data is empty",
+        return Err(EBCCError::EmptyInput);
+    }
+
+    if depth > MAX_NESTING_DEPTH {
+        return Err(EBCCError::InvalidInput(format!(
+            "EBCC payloads are nested more than {MAX_NESTING_DEPTH} levels deep"
         )));
     }
 
-    let mut compressed_data_copy = Vec::from(compressed_data); // C function may modify the input
+    if let Some(format) = NESTED_FORMATS
+        .iter()
+        .find(|format| (format.is_format)(compressed_data))
+    {
+        return (format.decode)(compressed_data, shape, depth);
+    }
 
+    ffi_decode(compressed_data)
+}
+
+/// Decode a single EBCC C library payload, which may be modified during
+/// decoding, into a C-allocated buffer.
+#[cfg(feature = "decode")]
+fn ffi_decode(compressed_data: &mut [u8]) -> EBCCResult<CBuffer<f32>> {
     // Call the C function
     let mut out_buffer: *mut f32 = ptr::null_mut();
-    #[expect(unsafe_code)]
-    let decompressed_size = unsafe {
-        ebcc_sys::ebcc_decode(
-            compressed_data_copy.as_mut_ptr(),
-            compressed_data.len(),
-            &raw mut out_buffer,
-        )
+    let decompressed_size = {
+        debug_span!("decode");
+        #[expect(unsafe_code)]
+        with_ebcc_call(|| unsafe {
+            ebcc_sys::ebcc_decode(
+                compressed_data.as_mut_ptr(),
+                compressed_data.len(),
+                &raw mut out_buffer,
+            )
+        })
     };
 
     // Check for errors
-    if decompressed_size == 0 || out_buffer.is_null() {
-        return Err(EBCCError::DecompressionError(String::from(
-            "ebcc_decode C function returned null or zero size",
+    let out_is_null = out_buffer.is_null();
+    #[expect(unsafe_code)]
+    // Safety: out_buffer was allocated by EBCC and has decompressed_size elements
+    let Some(decompressed_buffer) = (unsafe { CBuffer::new(out_buffer, decompressed_size) }) else {
+        return Err(EBCCError::DecompressionError(ffi_failure(
+            "ebcc_decode",
+            format_args!("{} bytes of compressed data", compressed_data.len()),
+            out_is_null,
         )));
+    };
+
+    debug_event!(
+        decompressed_elements = decompressed_size,
+        ratio = compression_ratio(decompressed_size, compressed_data.len()),
+        "decoded EBCC data",
+    );
+
+    Ok(decompressed_buffer)
+}
+
+/// Decode a payload like [`ffi_decode`] without the `decode` feature, which
+/// always fails.
+#[cfg(not(feature = "decode"))]
+fn ffi_decode(_compressed_data: &mut [u8]) -> EBCCResult<CBuffer<f32>> {
+    Err(decode_unsupported())
+}
+
+/// An EBCC payload format that [`ebcc_decode_nested`] dispatches on before
+/// it falls back to the EBCC C library.
+struct NestedFormat {
+    /// Name of the format in the [`capabilities`][crate::capabilities] report
+    name: &'static str,
+    /// Returns `true` if a payload has this format, e.g. its magic bytes
+    is_format: fn(&[u8]) -> bool,
+    /// Decode a payload of this format of the given shape, which is nested
+    /// `depth` levels deep inside other EBCC payloads
+    decode: NestedDecode,
+}
+
+/// Decoder of a [`NestedFormat`] from the compressed data, shape, and depth.
+type NestedDecode = fn(&mut [u8], (usize, usize, usize), usize) -> EBCCResult<CBuffer<f32>>;
+
+/// The formats of nested EBCC payloads, in the order in which
+/// [`ebcc_decode_nested`] checks them.
+const NESTED_FORMATS: [NestedFormat; 12] = [
+    NestedFormat {
+        name: "residual-only",
+        is_format: is_residual_only,
+        decode: |compressed_data, shape, _depth| {
+            debug_span!("decode");
+            let decompressed_data = residual_only_decode(compressed_data, shape)?;
+
+            debug_event!(
+                decompressed_elements = decompressed_data.len(),
+                ratio = compression_ratio(decompressed_data.len(), compressed_data.len()),
+                "decoded residual-only EBCC data",
+            );
+
+            Ok(CBuffer::from_vec(decompressed_data))
+        },
+    },
+    NestedFormat {
+        name: "sketch",
+        is_format: is_sketched,
+        decode: sketch_decode,
+    },
+    NestedFormat {
+        name: "clamp",
+        is_format: is_clamped,
+        decode: |compressed_data, shape, depth| {
+            clamp_decode(compressed_data, shape, depth).map(CBuffer::from_vec)
+        },
+    },
+    NestedFormat {
+        name: "quantize",
+        is_format: is_quantized,
+        decode: |compressed_data, shape, depth| {
+            quantize_decode(compressed_data, shape, depth).map(CBuffer::from_vec)
+        },
+    },
+    NestedFormat {
+        name: "conserve",
+        is_format: is_conserving,
+        decode: |compressed_data, shape, depth| {
+            conserve_decode(compressed_data, shape, depth).map(CBuffer::from_vec)
+        },
+    },
+    NestedFormat {
+        name: "stage",
+        is_format: is_staged,
+        decode: |compressed_data, shape, depth| {
+            stage_decode(compressed_data, shape, depth).map(CBuffer::from_vec)
+        },
+    },
+    NestedFormat {
+        name: "roi",
+        is_format: is_roi,
+        decode: |compressed_data, shape, depth| {
+            roi_decode(compressed_data, shape, depth).map(CBuffer::from_vec)
+        },
+    },
+    NestedFormat {
+        name: "layered",
+        is_format: is_layered,
+        decode: |compressed_data, shape, depth| {
+            layered_decode(compressed_data, shape, depth, false).map(CBuffer::from_vec)
+        },
+    },
+    NestedFormat {
+        name: "residual-coder",
+        is_format: is_residual_coded,
+        decode: |compressed_data, shape, depth| {
+            residual_coded_decode(compressed_data, shape, depth, false).map(CBuffer::from_vec)
+        },
+    },
+    NestedFormat {
+        name: "stored",
+        is_format: is_stored,
+        decode: |compressed_data, shape, _depth| {
+            stored_decode(compressed_data, shape).map(CBuffer::from_vec)
+        },
+    },
+    NestedFormat {
+        name: "transform",
+        is_format: is_transformed,
+        decode: |compressed_data, shape, depth| {
+            transform_decode(compressed_data, shape, depth).map(CBuffer::from_vec)
+        },
+    },
+    // payloads that were retried with tiles during encoding
+    NestedFormat {
+        name: "tiled",
+        is_format: is_ebcc_tiled,
+        decode: |compressed_data, shape, depth| {
+            let mut decompressed_data = Array::zeros(shape);
+            decode_tiled_into(compressed_data, decompressed_data.view_mut(), depth)?;
+            Ok(CBuffer::from_vec(
+                decompressed_data.into_raw_vec_and_offset().0,
+            ))
+        },
+    },
+];
+
+/// Names of the EBCC formats that [`ebcc_decode_into`] decodes, i.e. the
+/// frame streams and containers that [`ebcc_decode_visit`] dispatches on,
+/// the [`NESTED_FORMATS`], and the payload of the EBCC C library itself.
+#[expect(clippy::indexing_slicing)] // checked during constant evaluation
+pub const DECODED_FORMATS: [&str; NESTED_FORMATS.len() + 3] = {
+    let mut formats = [""; NESTED_FORMATS.len() + 3];
+    formats[0] = "stream";
+    formats[1] = "container";
+
+    let mut i = 0;
+    while i < NESTED_FORMATS.len() {
+        formats[i + 2] = NESTED_FORMATS[i].name;
+        i += 1;
     }
 
-    // Copy the decompressed data to a Vec and free the C-allocated memory
-    #[expect(unsafe_code)]
-    let decompressed_slice = unsafe { slice::from_raw_parts(out_buffer, decompressed_size) };
+    formats[NESTED_FORMATS.len() + 2] = "ebcc";
+    formats
+};
 
-    let Ok(decompressed_view) = ArrayView::from_shape(decompressed_data.dim(), decompressed_slice)
-    else {
-        #[expect(unsafe_code)]
-        unsafe {
-            ebcc_sys::free_buffer(out_buffer.cast::<core::ffi::c_void>());
+/// View a decompressed buffer as a 3D data array of the given `shape`.
+pub fn decompressed_view(
+    shape: (usize, usize, usize),
+    decompressed_buffer: &CBuffer<f32>,
+) -> EBCCResult<ArrayView<'_, f32, EbccDim>> {
+    ArrayView::from_shape(shape, decompressed_buffer.as_slice()).map_err(|_| {
+        EBCCError::SizeMismatch {
+            expected: shape.into(),
+            actual: decompressed_buffer.as_slice().len(),
         }
+    })
+}
 
-        return Err(EBCCError::InvalidInput(format!(
-            "Decompressed data should be of shape {:?} but decompressed to {} elements",
-            decompressed_data.shape(),
-            decompressed_size
-        )));
-    };
+/// Decode EBCC compressed data of the given `shape` and pass the decoded
+/// frames to `visit`, without materializing the full 3D data array.
+///
+/// `visit` is called with the index of the first frame and a view of the
+/// consecutive decoded frames. EBCC frame streams are visited
+/// segment-by-segment, while a single [`ebcc_encode`] or
+/// [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] payload is visited at
+/// once.
+pub fn ebcc_decode_visit(
+    compressed_data: &[u8],
+    shape: (usize, usize, usize),
+    visit: impl FnMut(usize, ArrayView<f32, EbccDim>) -> EBCCResult<()>,
+) -> EBCCResult<()> {
+    capture_call(CaptureInput::decode(compressed_data, shape), || {
+        decode_visit(compressed_data, shape, visit)
+    })
+}
 
-    decompressed_data.assign(&decompressed_view);
+/// Decode EBCC compressed data like [`ebcc_decode_visit`] and store the
+/// decoded values, mapped by `convert`, in the `decompressed_data`.
+///
+/// The decoded frames are only materialized as `f32` one visit at a time,
+/// i.e. one segment of an EBCC frame stream or a whole single payload.
+pub fn ebcc_decode_map_into<T>(
+    compressed_data: &[u8],
+    mut decompressed_data: ArrayViewMut<T, EbccDim>,
+    convert: impl Fn(f32) -> T,
+) -> EBCCResult<()> {
+    ebcc_decode_visit(
+        compressed_data,
+        decompressed_data.dim(),
+        |first_frame, frames| {
+            Zip::from(decompressed_data.slice_mut(s![
+                first_frame..first_frame + frames.len_of(Axis(0)),
+                ..,
+                ..
+            ]))
+            .and(&frames)
+            .for_each(|output, &x| *output = convert(x));
+            Ok(())
+        },
+    )
+}
 
-    #[expect(unsafe_code)]
-    unsafe {
-        ebcc_sys::free_buffer(out_buffer.cast::<core::ffi::c_void>());
+/// Decode EBCC compressed data like [`ebcc_decode_visit`], without capturing
+/// a failed call.
+fn decode_visit(
+    compressed_data: &[u8],
+    shape: (usize, usize, usize),
+    mut visit: impl FnMut(usize, ArrayView<f32, EbccDim>) -> EBCCResult<()>,
+) -> EBCCResult<()> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
     }
 
-    Ok(())
+    EBCCLimits::default().check_shape(shape)?;
+
+    if let Some(mut stream_body) = compressed_data.strip_prefix(EBCC_STREAM_MAGIC.as_slice()) {
+        return ebcc_visit_stream_body(&mut stream_body, shape, visit);
+    }
+
+    if is_ebcc_container(compressed_data) {
+        return EbccContainer::open(Cursor::new(compressed_data))?.visit_frames(shape, visit);
+    }
+
+    if is_ebcc_tiled(compressed_data) {
+        let mut decompressed_data = Array::zeros(shape);
+        ebcc_decode_tiled_into(compressed_data, decompressed_data.view_mut())?;
+        return visit(0, decompressed_data.view());
+    }
+
+    let mut compressed_data_copy = Vec::from(compressed_data); // C function may modify the input
+    let _copy_alloc = TrackedAlloc::new(compressed_data_copy.capacity());
+    record_copy();
+    let (payload, checksum) = header_payload_mut(&mut compressed_data_copy, shape)?;
+    let decompressed_buffer = ebcc_decode_c_buffer_mut(payload, shape)?;
+    verify_decompressed_checksum(checksum, decompressed_buffer.as_slice())?;
+
+    visit(0, decompressed_view(shape, &decompressed_buffer)?)
 }
 
 /// Decode EBCC chunked compressed data into a 3D data array.
 ///
 /// # Errors
 ///
-/// - [`EBCCError::InvalidInput`] if the `compressed_data` is empty
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::TooManyFrames`] or [`EBCCError::FrameTooLarge`] if the shape
+///   of the `decompressed_data` exceeds the default [`EBCCLimits`]
 /// - [`EBCCError::DecompressionError`] if decompression with EBCC fails
-/// - [`EBCCError::InvalidInput`] if the decompressed data does not fit into
+/// - [`EBCCError::SizeMismatch`] if the decompressed data does not fit into
 ///   `decompressed_data`
 pub fn ebcc_decode_chunking_into(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    capture_call(
+        CaptureInput::decode(compressed_data, decompressed_data.dim())
+            .chunked(EBCCCaptureOperation::DecodeChunking, None),
+        || decode_chunking_into(compressed_data, decompressed_data),
+    )
+}
+
+/// Decode EBCC chunked compressed data like [`ebcc_decode_chunking_into`],
+/// without capturing a failed call.
+#[cfg(feature = "decode")]
+fn decode_chunking_into(
     compressed_data: &[u8],
     mut decompressed_data: ArrayViewMut<f32, EbccDim>,
 ) -> EBCCResult<()> {
+    debug_span!(
+        "ebcc_decode_chunking",
+        compressed_bytes = compressed_data.len()
+    );
+
     if compressed_data.is_empty() {
-        return Err(EBCCError::InvalidInput(String::from(
-            "Compressed data is empty",
-        )));
+        return Err(EBCCError::EmptyInput);
     }
 
+    EBCCLimits::default().check_shape(decompressed_data.dim())?;
+
     let encoded_dims = read_dims_from_chunking_header(compressed_data)?;
     let output_dims: [usize; EBCC_NDIMS] = decompressed_data.dim().into();
     if output_dims != encoded_dims {
-        return Err(EBCCError::InvalidInput(format!(
-            "Chunked EBCC data has shape {encoded_dims:?} but output array has shape {output_dims:?}",
-        )));
+        return Err(shape_mismatch(encoded_dims, output_dims));
     }
 
     let mut compressed_data_copy = Vec::from(compressed_data); // C function may modify the input
+    let _copy_alloc = TrackedAlloc::new(compressed_data_copy.capacity());
+    record_copy();
 
     let mut out_buffer: *mut f32 = ptr::null_mut();
-    #[expect(unsafe_code)]
-    let decompressed_size = unsafe {
-        ebcc_sys::ebcc_decode_chunking(
-            compressed_data_copy.as_mut_ptr(),
-            compressed_data.len(),
-            &raw mut out_buffer,
-        )
+    let decompressed_size = {
+        debug_span!("decode");
+        #[expect(unsafe_code)]
+        with_ebcc_call(|| unsafe {
+            ebcc_sys::ebcc_decode_chunking(
+                compressed_data_copy.as_mut_ptr(),
+                compressed_data.len(),
+                &raw mut out_buffer,
+            )
+        })
     };
 
     if decompressed_size == 0 || out_buffer.is_null() {
-        return Err(EBCCError::DecompressionError(String::from(
-            "ebcc_decode_chunking C function returned null or zero size",
+        return Err(EBCCError::DecompressionError(ffi_failure(
+            "ebcc_decode_chunking",
+            format_args!(
+                "{} bytes of chunked data with shape {encoded_dims:?}",
+                compressed_data.len()
+            ),
+            out_buffer.is_null(),
         )));
     }
 
@@ -379,11 +1815,10 @@ pub fn ebcc_decode_chunking_into(
             ebcc_sys::free_buffer(out_buffer.cast::<core::ffi::c_void>());
         }
 
-        return Err(EBCCError::InvalidInput(format!(
-            "Decompressed data should be of shape {:?} but decompressed to {} elements",
-            decompressed_data.shape(),
-            decompressed_size
-        )));
+        return Err(EBCCError::SizeMismatch {
+            expected: decompressed_data.dim().into(),
+            actual: decompressed_size,
+        });
     };
 
     decompressed_data.assign(&decompressed_view);
@@ -393,83 +1828,63 @@ pub fn ebcc_decode_chunking_into(
         ebcc_sys::free_buffer(out_buffer.cast::<core::ffi::c_void>());
     }
 
+    debug_event!(
+        decompressed_elements = decompressed_size,
+        ratio = compression_ratio(decompressed_size, compressed_data.len()),
+        "decoded chunked EBCC data",
+    );
+
     Ok(())
 }
 
-fn validate_data_shape(data: ArrayView<f32, EbccDim>) -> EBCCResult<usize> {
-    if data.shape().contains(&0) {
-        return Err(EBCCError::InvalidInput(String::from(
-            "All dimensions must be > 0",
-        )));
-    }
+/// Decode chunked data like [`ebcc_decode_chunking_into`] without the
+/// `decode` feature, which always fails.
+#[cfg(not(feature = "decode"))]
+fn decode_chunking_into(
+    _compressed_data: &[u8],
+    _decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    Err(decode_unsupported())
+}
 
-    let Some(total_elements) = data
-        .shape()
-        .iter()
-        .try_fold(1usize, |acc, &d| acc.checked_mul(d))
-    else {
-        return Err(EBCCError::InvalidInput(String::from("Dimension overflow")));
+/// Describe the failure of the EBCC C `function` on the given `input`, see
+/// [`c_call_failure`].
+fn ffi_failure(function: &str, input: fmt::Arguments, out_is_null: bool) -> EBCCFailure {
+    let output = if out_is_null {
+        "no output buffer"
+    } else {
+        "an empty output buffer"
     };
 
-    if total_elements > ((isize::MAX as usize) / std::mem::size_of::<f32>()) {
-        return Err(EBCCError::InvalidInput(String::from("Data too large")));
-    }
-
-    Ok(total_elements)
+    c_call_failure(
+        format!("EBCC {function} failed for {input} and returned {output}"),
+        out_is_null,
+    )
 }
 
-fn validate_regular_ebcc_shape(data: ArrayView<f32, EbccDim>) -> EBCCResult<()> {
-    // EBCC flattens all dimensions except the last into one internal image height.
-    let (depth, height, width) = data.dim();
-    let Some(image_height) = depth.checked_mul(height) else {
-        return Err(EBCCError::InvalidInput(String::from("Dimension overflow")));
-    };
-
-    if height < EBCC_MIN_INTERNAL_IMAGE_DIM
-        || !(EBCC_MIN_INTERNAL_IMAGE_DIM..=EBCC_MAX_INTERNAL_IMAGE_DIM).contains(&image_height)
-        || !(EBCC_MIN_INTERNAL_IMAGE_DIM..=EBCC_MAX_INTERNAL_IMAGE_DIM).contains(&width)
-    {
-        return Err(EBCCError::InvalidInput(format!(
-            "EBCC requires tile dimensions of at least {EBCC_MIN_INTERNAL_IMAGE_DIM} and internal image dimensions at most {EBCC_MAX_INTERNAL_IMAGE_DIM}, got shape {depth}x{height}x{width}",
+fn validate_jpeg2000_base(config: &EBCCConfig) -> EBCCResult<()> {
+    if config.base_mode != EBCCBaseMode::Jpeg2000 || config.base_codec != EBCCBaseCodec::Jpeg2000 {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "Chunked EBCC compression requires the JPEG2000 base mode and base codec",
         )));
     }
 
     Ok(())
 }
 
-fn validate_chunk_shape(chunk_shape: EBCCChunkShape) -> EBCCResult<[usize; EBCC_NDIMS]> {
-    let [chunk_depth, chunk_height, chunk_width] = chunk_shape;
-    let Some(image_height) = chunk_depth.checked_mul(chunk_height) else {
-        return Err(EBCCError::InvalidInput(String::from(
-            "Chunk dimension overflow",
-        )));
-    };
-    if chunk_height.get() < EBCC_MIN_INTERNAL_IMAGE_DIM
-        || !(EBCC_MIN_INTERNAL_IMAGE_DIM..=EBCC_MAX_INTERNAL_IMAGE_DIM)
-            .contains(&image_height.get())
-        || !(EBCC_MIN_INTERNAL_IMAGE_DIM..=EBCC_MAX_INTERNAL_IMAGE_DIM).contains(&chunk_width.get())
-    {
-        return Err(EBCCError::InvalidInput(format!(
-            "EBCC requires chunk tile dimensions of at least {EBCC_MIN_INTERNAL_IMAGE_DIM} and internal image dimensions at most {EBCC_MAX_INTERNAL_IMAGE_DIM}, got {chunk_depth}x{chunk_height}x{chunk_width}",
-        )));
+fn validate_data_shape(data: ArrayView<f32, EbccDim>) -> EBCCResult<usize> {
+    if data.shape().contains(&0) {
+        return Err(EBCCError::EmptyDimension);
     }
 
-    let Some(total_elements) = chunk_shape
-        .iter()
-        .try_fold(1usize, |acc, &d| acc.checked_mul(d.get()))
-    else {
-        return Err(EBCCError::InvalidInput(String::from(
-            "Chunk dimension overflow",
-        )));
-    };
+    data_len(data.dim())
+}
 
-    if total_elements > ((isize::MAX as usize) / std::mem::size_of::<f32>()) {
-        return Err(EBCCError::InvalidInput(String::from(
-            "Chunk dimensions are too large",
-        )));
-    }
+fn validate_chunk_shape(chunk_shape: EBCCChunkShape) -> EBCCResult<[usize; EBCC_NDIMS]> {
+    let chunk_shape = chunk_shape.map(NonZeroUsize::get);
+    validate_ebcc_chunk_shape(chunk_shape)?;
 
-    Ok(chunk_shape.map(NonZeroUsize::get))
+    Ok(chunk_shape)
 }
 
 fn compat_chunk_shape(chunk_shape: EBCCCompatChunkShape) -> EBCCResult<[usize; EBCC_NDIMS]> {
@@ -479,18 +1894,6 @@ fn compat_chunk_shape(chunk_shape: EBCCCompatChunkShape) -> EBCCResult<[usize; E
     }
 }
 
-fn validate_only_finite_data(data: ArrayView<f32, EbccDim>) -> EBCCResult<()> {
-    for (i, &value) in data.indexed_iter() {
-        if !value.is_finite() {
-            return Err(EBCCError::InvalidInput(format!(
-                "Non-finite value {value} at index {i:?}"
-            )));
-        }
-    }
-
-    Ok(())
-}
-
 const fn ffi_config(
     dims: [usize; EBCC_NDIMS],
     config: &EBCCConfig,
@@ -508,24 +1911,24 @@ const fn ffi_config(
 
 fn read_dims_from_chunking_header(compressed_data: &[u8]) -> EBCCResult<[usize; EBCC_NDIMS]> {
     let Some(mut compressed_data) = compressed_data.strip_prefix(EBCC_CHUNKING_HEADER_MAGIC) else {
-        return Err(EBCCError::DecompressionError(String::from(
-            "Missing EBCC chunking header",
-        )));
+        return Err(EBCCError::DecompressionError(
+            "Missing EBCC chunking header".into(),
+        ));
     };
 
     let reader = &mut compressed_data;
 
     let version = read_u32_le(reader)?;
     if version != EBCC_CHUNKING_HEADER_VERSION {
-        return Err(EBCCError::DecompressionError(format!(
-            "Unsupported EBCC chunking header version: {version}",
-        )));
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC chunking header version: {version}").into(),
+        ));
     }
 
     let Ok(ndims) = usize::try_from(read_u32_le(reader)?) else {
-        return Err(EBCCError::DecompressionError(String::from(
-            "EBCC chunking dimensionality does not fit into usize",
-        )));
+        return Err(EBCCError::DecompressionError(
+            "EBCC chunking dimensionality does not fit into usize".into(),
+        ));
     };
 
     if ndims != EBCC_NDIMS {
@@ -539,11 +1942,7 @@ fn read_dims_from_chunking_header(compressed_data: &[u8]) -> EBCCResult<[usize;
     let mut dims = [0; EBCC_NDIMS];
     for dim in &mut dims {
         let value = read_u64_le(reader)?;
-        *dim = usize::try_from(value).map_err(|_| {
-            EBCCError::InvalidInput(format!(
-                "Chunked EBCC dimension {value} does not fit into usize",
-            ))
-        })?;
+        *dim = u64_to_usize(value)?;
     }
 
     Ok(dims)
@@ -569,10 +1968,11 @@ fn read_u64_le(reader: &mut &[u8]) -> EBCCResult<u64> {
     Ok(u64::from_le_bytes(array))
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "encode", feature = "decode"))]
 #[expect(clippy::unwrap_used, clippy::indexing_slicing)]
 mod tests {
-    use ndarray::Array;
+    use ebcc_sys::EBCC_MIN_INTERNAL_IMAGE_DIM;
+    use ndarray::{Array, Axis};
 
     use super::*;
 
@@ -596,6 +1996,91 @@ mod tests {
         Ok(())
     }
 
+    /// Environment variable with the path to which
+    /// [`deterministic_encode_child`] writes its compressed data
+    const DETERMINISTIC_OUTPUT: &str = "EBCC_TEST_DETERMINISTIC_OUTPUT";
+
+    /// Encode in a child process of [`test_deterministic_encode`], which sets
+    /// `OPJ_NUM_THREADS` for it, and write the compressed data to a file.
+    #[test]
+    fn deterministic_encode_child() -> EBCCResult<()> {
+        let Some(output) = std::env::var_os(DETERMINISTIC_OUTPUT) else {
+            return Ok(());
+        };
+
+        let data = crate::testdata::temperature((4, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1).with_deterministic(true);
+
+        let compressed = ebcc_encode(data.view(), &config)?;
+        std::fs::write(output, compressed).map_err(|err| EBCCError::InvalidInput(err.to_string()))
+    }
+
+    #[test]
+    fn test_deterministic_encode() -> EBCCResult<()> {
+        // the environment must not be changed while other threads may read
+        //  it, so every OpenJPEG thread count is run in a child process
+        let encode = |threads: usize| -> EBCCResult<Option<Vec<u8>>> {
+            let output = std::env::temp_dir().join(format!(
+                "ebcc-deterministic-{}-{threads}",
+                std::process::id()
+            ));
+            let mut child = std::process::Command::new(
+                std::env::current_exe().map_err(|err| EBCCError::InvalidInput(err.to_string()))?,
+            );
+            child
+                .args(["--exact", "codec::tests::deterministic_encode_child"])
+                .env("OPJ_NUM_THREADS", threads.to_string())
+                .env(DETERMINISTIC_OUTPUT, &output)
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null());
+            let status = child
+                .status()
+                .map_err(|err| EBCCError::InvalidInput(err.to_string()))?;
+            let compressed = status.success().then(|| std::fs::read(&output)).transpose();
+            let _ = std::fs::remove_file(&output);
+            compressed.map_err(|err| EBCCError::InvalidInput(err.to_string()))
+        };
+
+        let expected = encode(1)?;
+        assert!(expected.is_some());
+        assert_eq!(encode(1)?, expected);
+
+        for threads in [2, 4] {
+            // the deterministic output does not depend on the requested
+            //  number of OpenJPEG threads, only prebuilt or system libraries
+            //  that may use threads are rejected
+            let deterministic = encode(threads)?;
+            if ebcc_sys::OPENJPEG_THREADS && !ebcc_sys::OPENJPEG_THREADS_HOOK {
+                assert_eq!(deterministic, None);
+            } else {
+                assert_eq!(deterministic, expected);
+            }
+        }
+
+        // the deterministic chunks are byte-identical for any number of
+        //  encoding threads
+        let data = crate::testdata::temperature((4, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1).with_deterministic(true);
+        let expected = data
+            .axis_chunks_iter(Axis(0), 1)
+            .map(|chunk| ebcc_encode(chunk, &config))
+            .collect::<EBCCResult<Vec<_>>>()?;
+        for max_threads in [1, 2, 4] {
+            let config = config.clone().with_limits(
+                EBCCLimits::new().with_max_threads(NonZeroUsize::new(max_threads).unwrap()),
+            );
+            let chunks = crate::ebcc_encode_batch_parallel(
+                data.axis_chunks_iter(Axis(0), 1),
+                &config,
+                NonZeroUsize::new(4).unwrap(),
+            )
+            .collect::<EBCCResult<Vec<_>>>()?;
+            assert_eq!(chunks, expected);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_invalid_config() {
         let data = Array::from_shape_vec(
@@ -611,6 +2096,111 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_expansion_guard() -> EBCCResult<()> {
+        let data = crate::testdata::noise((1, 32, 32), 1.0, 42);
+        let config = EBCCConfig::max_absolute_error_bounded(1e-4);
+
+        // white noise with a tight error bound expands and is stored raw
+        let compressed = ebcc_encode(
+            data.view(),
+            &config
+                .clone()
+                .with_expansion_guard(EBCCExpansionGuard::store_raw(1.1)),
+        )?;
+        assert!(is_stored(&compressed[EBCCHeader::LEN..]));
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        assert_eq!(decompressed, data);
+
+        // even the stored data cannot be smaller than 1% of the raw data
+        assert!(matches!(
+            ebcc_encode(
+                data.view(),
+                &config
+                    .clone()
+                    .with_expansion_guard(EBCCExpansionGuard::store_raw(0.01))
+            ),
+            Err(EBCCError::ExpansionTooLarge { compressed_bytes, .. })
+                if compressed_bytes == crate::stored::EBCC_STORED_HEADER_LEN + data.len() * 4
+        ));
+
+        assert!(matches!(
+            ebcc_encode(
+                data.view(),
+                &config
+                    .clone()
+                    .with_expansion_guard(EBCCExpansionGuard::new(0.01))
+            ),
+            Err(EBCCError::ExpansionTooLarge { raw_bytes, .. }) if raw_bytes == data.len() * 4
+        ));
+
+        // a generous guard keeps the EBCC payload
+        let compressed = ebcc_encode(
+            data.view(),
+            &config
+                .clone()
+                .with_expansion_guard(EBCCExpansionGuard::store_raw(f32::MAX)),
+        )?;
+        assert!(!is_stored(&compressed[EBCCHeader::LEN..]));
+
+        for max_ratio in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                ebcc_encode(
+                    data.view(),
+                    &config
+                        .clone()
+                        .with_expansion_guard(EBCCExpansionGuard::new(max_ratio))
+                ),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_compressed_size() -> EBCCResult<()> {
+        let data = crate::testdata::noise((2, 32, 32), 1.0, 7);
+        let guarded = EBCCConfig::max_absolute_error_bounded(1e-4)
+            .with_expansion_guard(EBCCExpansionGuard::store_raw(1.1));
+
+        for config in [
+            guarded.clone(),
+            guarded.clone().with_quantile_sketch(),
+            guarded.with_expansion_guard(EBCCExpansionGuard::store_raw(2.0)),
+            EBCCConfig::new().with_base_mode(EBCCBaseMode::Stored),
+            EBCCConfig::new()
+                .with_base_mode(EBCCBaseMode::Stored)
+                .with_stored_compression(crate::EBCCStoredCompression::Zstd),
+        ] {
+            let compressed = ebcc_encode(data.view(), &config)?;
+            let bound = config.max_compressed_size(data.dim());
+            assert!(bound.is_some_and(|bound| compressed.len() <= bound));
+        }
+
+        // stored data has an exact size
+        assert_eq!(
+            EBCCConfig::new()
+                .with_base_mode(EBCCBaseMode::Stored)
+                .max_compressed_size(data.dim()),
+            Some(EBCCHeader::LEN + crate::stored::EBCC_STORED_HEADER_LEN + data.len() * 4)
+        );
+
+        // the EBCC C library output is only bounded by the limits
+        assert_eq!(EBCCConfig::new().max_compressed_size(data.dim()), None);
+        let limits = EBCCLimits::new().with_max_output_bytes(1 << 20);
+        assert_eq!(
+            EBCCConfig::new()
+                .with_limits(limits)
+                .max_compressed_size(data.dim()),
+            Some(EBCCHeader::LEN + (1 << 20))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_nan_input() {
         let mut data = Array::from_shape_vec(
@@ -632,6 +2222,116 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_decode_mut_matches_decode() -> EBCCResult<()> {
+        let data = Array::from_shape_fn((2, 32, 32), |(t, y, x)| (t + y + x) as f32);
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let mut compressed = ebcc_encode(data.view(), &config)?;
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, expected.view_mut())?;
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_mut_into(&mut compressed, decompressed.view_mut())?;
+        assert_eq!(decompressed, expected);
+
+        assert!(ebcc_decode_mut_into(&mut [], decompressed.view_mut()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_encode_into_matches_encode() -> EBCCResult<()> {
+        let data = Array::from_shape_fn((2, 32, 32), |(t, y, x)| (t * y + x) as f32);
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let expected = ebcc_encode(data.view(), &config)?;
+
+        // the buffer's previous contents are replaced and its capacity reused
+        let mut compressed = vec![42; expected.len() + 100];
+        let capacity = compressed.capacity();
+        assert_eq!(
+            ebcc_encode_into(data.view(), &config, &mut compressed)?,
+            expected.len()
+        );
+        assert_eq!(compressed, expected);
+        assert_eq!(compressed.capacity(), capacity);
+
+        let invalid = EBCCConfig::max_absolute_error_bounded(-1.0);
+        assert!(ebcc_encode_into(data.view(), &invalid, &mut compressed).is_err());
+        assert!(compressed.is_empty());
+
+        let mut buffer = vec![42; expected.len() + 1];
+        assert_eq!(
+            ebcc_encode_into_slice(data.view(), &config, &mut buffer)?,
+            expected.len()
+        );
+        assert_eq!(&buffer[..expected.len()], expected.as_slice());
+        assert_eq!(buffer[expected.len()], 42);
+
+        let mut buffer = vec![42; expected.len() - 1];
+        assert!(matches!(
+            ebcc_encode_into_slice(data.view(), &config, &mut buffer),
+            Err(EBCCError::BufferTooSmall { required, available })
+                if required == expected.len() && available == expected.len() - 1
+        ));
+        assert!(buffer.iter().all(|byte| *byte == 42));
+
+        Ok(())
+    }
+
+    #[test]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_slice_api_matches_views() -> EBCCResult<()> {
+        let data = Array::from_shape_fn((2, 32, 48), |(t, y, x)| (t + y * x) as f32);
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let shape = data.dim();
+        let slice = data.as_slice().unwrap_or_default();
+
+        let compressed = ebcc_encode_slice(slice, shape, &config)?;
+        assert_eq!(compressed, ebcc_encode(data.view(), &config)?);
+
+        let mut expected = Array::zeros(shape);
+        ebcc_decode_into(&compressed, expected.view_mut())?;
+
+        let mut decompressed = vec![0.0; slice.len()];
+        ebcc_decode_into_slice(&compressed, &mut decompressed, shape)?;
+        let mut raw = vec![f32::NAN; slice.len()];
+        #[expect(unsafe_code)]
+        // Safety: the pointer is valid for reads and writes of raw.len()
+        //         elements
+        unsafe {
+            ebcc_decode_into_raw(&compressed, raw.as_mut_ptr(), raw.len(), shape)?;
+        }
+        assert_eq!(raw, decompressed);
+
+        assert_eq!(decompressed, expected.into_raw_vec_and_offset().0);
+
+        // the slices must have the number of elements of the shape
+        assert!(matches!(
+            ebcc_encode_slice(&slice[1..], shape, &config),
+            Err(EBCCError::SliceLenMismatch {
+                shape: [2, 32, 48],
+                len: 3071,
+            })
+        ));
+        assert!(matches!(
+            ebcc_decode_into_slice(&compressed, &mut decompressed, (2, 48, 48)),
+            Err(EBCCError::SliceLenMismatch {
+                shape: [2, 48, 48],
+                len: 3072,
+            })
+        ));
+        #[expect(unsafe_code)]
+        // Safety: the null pointer is rejected before it is accessed
+        let null = unsafe { ebcc_decode_into_raw(&compressed, ptr::null_mut(), 3072, shape) };
+        assert!(matches!(null, Err(EBCCError::InvalidInput(_))));
+
+        Ok(())
+    }
+
     #[test]
     #[expect(clippy::cast_precision_loss)]
     fn test_encode_decode_chunking_roundtrip() -> EBCCResult<()> {
@@ -0,0 +1,737 @@
+//! Pluggable residual coders on top of the `JPEG2000` base layer.
+//!
+//! By default, the residual between the data and the `JPEG2000` base layer
+//! is coded by the residual stage that is built into the EBCC C library. A
+//! [`ResidualCoder`] replaces this stage with a scheme that is implemented in
+//! Rust, e.g. to experiment with different error-bounded residual codings
+//! while reusing the base layer. With the [`EBCCBaseCodec::Identity`], the
+//! `JPEG2000` stage is skipped and the coder codes the data itself, such that
+//! pure error-bounded coding can be compared against the hybrid approach.
+//! The coder is referenced by a string ID with
+//! [`EBCCConfig::with_residual_coder`], and its ID is recorded in the
+//! payload, such that every consumer that knows a coder under the same ID can
+//! decode the data.
+//!
+//! Three coders are built in and always available under their IDs:
+//! [`EbccResidualStage`], the default residual stage of the EBCC C library
+//! behind the trait, [`QuantizedResiduals`], which quantizes the residuals
+//! linearly like the layered and residual-only EBCC compression, and
+//! [`SparseCorrections`], which only stores the values that the base layer
+//! misses. Custom coders are registered with
+//! [`ebcc_register_residual_coder`].
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_CODER_MAGIC`], the format version as `u32`, the length of
+//!   the coder ID as `u16`, the UTF-8 bytes of the coder ID, the number of
+//!   frames, the frame height, and the frame width as `u64`s, the absolute
+//!   error bound as `f32`, and the length of the base layer as `u64`
+//! - the base layer, a `JPEG2000`-only EBCC payload, or nothing if the
+//!   approximation is all zeros
+//! - the residuals, as encoded by the coder
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use ndarray::{Array, ArrayView, ArrayViewMut, Zip};
+
+use crate::cbuffer::CBuffer;
+use crate::codec::{
+    decompressed_view, ebcc_decode_c_buffer_mut, ebcc_decode_nested,
+    ebcc_encode_c_buffer_with_scratch, EbccDim, MAX_NESTING_DEPTH,
+};
+use crate::config::{EBCCBaseCodec, EBCCBaseMode, EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::residual::{
+    flush_zero_run, quantize, reconstruct, unzigzag, write_varint, zigzag, CODE_OFFSET,
+    CODE_VERBATIM, CODE_ZERO_RUN,
+};
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::verify::data_range;
+
+/// Magic bytes at the start of every EBCC payload with a residual coder.
+pub const EBCC_CODER_MAGIC: &[u8; 8] = b"EBCCRCOD";
+
+/// Version of the residual coder EBCC payload format.
+const EBCC_CODER_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC residual-coded data");
+
+/// Prefix of the IDs of the built-in residual coders
+const RESERVED_ID_PREFIX: &str = "ebcc.";
+
+/// Registered custom residual coders by their ID
+static CODERS: RwLock<BTreeMap<String, Arc<dyn ResidualCoder>>> = RwLock::new(BTreeMap::new());
+
+/// Error-bounded coder of the residual between the data and its
+/// approximation by the `JPEG2000` base layer.
+///
+/// The reconstruction of every encoded value must be within the absolute
+/// error bound, which is checked when encoding.
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use std::sync::Arc;
+///
+/// use ebcc::{
+///     ebcc_decode_into, ebcc_encode, ebcc_register_residual_coder, EBCCConfig, EBCCResult,
+///     EbccDim, ResidualCoder,
+/// };
+/// use ndarray::{Array, ArrayView, ArrayViewMut};
+///
+/// /// Stores all values verbatim
+/// struct Verbatim;
+///
+/// impl ResidualCoder for Verbatim {
+///     fn encode(
+///         &self,
+///         data: ArrayView<f32, EbccDim>,
+///         _approximation: ArrayView<f32, EbccDim>,
+///         _error_bound: f32,
+///     ) -> EBCCResult<Vec<u8>> {
+///         Ok(data.iter().flat_map(|x| x.to_le_bytes()).collect())
+///     }
+///
+///     fn decode(
+///         &self,
+///         residuals: &[u8],
+///         mut approximation: ArrayViewMut<f32, EbccDim>,
+///         _error_bound: f32,
+///     ) -> EBCCResult<()> {
+///         for (x, bytes) in approximation.iter_mut().zip(residuals.chunks_exact(4)) {
+///             *x = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// ebcc_register_residual_coder("org.example.verbatim", Arc::new(Verbatim))?;
+///
+/// let data = Array::from_shape_fn((1, 32, 32), |(_, y, x)| (y * x) as f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1)
+///     .with_residual_coder("org.example.verbatim");
+/// let compressed = ebcc_encode(data.view(), &config)?;
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_into(&compressed, decompressed.view_mut())?;
+/// assert_eq!(decompressed, data);
+/// # Ok(())
+/// # }
+/// ```
+pub trait ResidualCoder: Send + Sync {
+    /// Encode the residual between the `data` and its `approximation` by
+    /// the base layer, such that [`decode`][Self::decode] reconstructs every
+    /// value within the absolute `error_bound`.
+    ///
+    /// # Errors
+    ///
+    /// - any error that prevents the residual from being encoded
+    fn encode(
+        &self,
+        data: ArrayView<f32, EbccDim>,
+        approximation: ArrayView<f32, EbccDim>,
+        error_bound: f32,
+    ) -> EBCCResult<Vec<u8>>;
+
+    /// Correct the `approximation` by the base layer in-place with the
+    /// encoded `residuals`.
+    ///
+    /// # Errors
+    ///
+    /// - any error that prevents the `residuals` from being decoded, e.g.
+    ///   because they are corrupted
+    fn decode(
+        &self,
+        residuals: &[u8],
+        approximation: ArrayViewMut<f32, EbccDim>,
+        error_bound: f32,
+    ) -> EBCCResult<()>;
+}
+
+/// Built-in [`ResidualCoder`] that quantizes the residuals linearly.
+///
+/// Every residual is rounded to a multiple of twice the error bound, runs of
+/// zero codes are collapsed, and values whose reconstruction would violate
+/// the error bound are stored verbatim, with the same codes as the layered
+/// and residual-only EBCC compression.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuantizedResiduals;
+
+impl QuantizedResiduals {
+    /// ID under which the coder is always available
+    pub const ID: &'static str = "ebcc.quantized";
+}
+
+impl ResidualCoder for QuantizedResiduals {
+    fn encode(
+        &self,
+        data: ArrayView<f32, EbccDim>,
+        approximation: ArrayView<f32, EbccDim>,
+        error_bound: f32,
+    ) -> EBCCResult<Vec<u8>> {
+        let mut verbatim = Vec::new();
+        let mut codes = Vec::new();
+        let mut zero_run = 0_u64;
+
+        for (value, prediction) in data.iter().zip(approximation.iter()) {
+            match quantize(*value, *prediction, error_bound) {
+                Some(0) => zero_run += 1,
+                Some(code) => {
+                    flush_zero_run(&mut codes, &mut zero_run);
+                    write_varint(&mut codes, zigzag(code) + CODE_OFFSET);
+                }
+                None => {
+                    flush_zero_run(&mut codes, &mut zero_run);
+                    write_varint(&mut codes, CODE_VERBATIM);
+                    verbatim.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        flush_zero_run(&mut codes, &mut zero_run);
+
+        let mut residuals = Vec::with_capacity(8 + verbatim.len() + codes.len());
+        residuals.extend_from_slice(&usize_to_u64(verbatim.len() / 4)?.to_le_bytes());
+        residuals.extend_from_slice(&verbatim);
+        residuals.extend_from_slice(&codes);
+
+        Ok(residuals)
+    }
+
+    fn decode(
+        &self,
+        mut residuals: &[u8],
+        mut approximation: ArrayViewMut<f32, EbccDim>,
+        error_bound: f32,
+    ) -> EBCCResult<()> {
+        let verbatim_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut residuals)?))?
+            .checked_mul(4)
+            .ok_or_else(|| FORMAT.corrupted())?;
+        let Some((verbatim, mut codes)) = residuals.split_at_checked(verbatim_len) else {
+            return Err(FORMAT.truncated());
+        };
+        let mut verbatim = verbatim
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap_or_default()));
+
+        let mut values = approximation.iter_mut();
+        while !codes.is_empty() {
+            match FORMAT.read_varint(&mut codes)? {
+                CODE_VERBATIM => {
+                    let value = values.next().ok_or_else(|| FORMAT.corrupted())?;
+                    *value = verbatim.next().ok_or_else(|| FORMAT.truncated())?;
+                }
+                CODE_ZERO_RUN => {
+                    // values in a run of zero codes keep their approximation
+                    let zero_run = u64_to_usize(FORMAT.read_varint(&mut codes)?)?;
+                    let skipped = zero_run.checked_sub(1).ok_or_else(|| FORMAT.corrupted())?;
+                    values.nth(skipped).ok_or_else(|| FORMAT.corrupted())?;
+                }
+                code => {
+                    let value = values.next().ok_or_else(|| FORMAT.corrupted())?;
+                    *value = reconstruct(*value, unzigzag(code - CODE_OFFSET), error_bound);
+                }
+            }
+        }
+
+        if values.next().is_some() || verbatim.next().is_some() {
+            return Err(FORMAT.corrupted());
+        }
+
+        Ok(())
+    }
+}
+
+/// Built-in [`ResidualCoder`] that only stores the values whose
+/// approximation violates the error bound.
+///
+/// Each corrected value is stored verbatim together with the number of
+/// values since the previous correction, which is compact when the base
+/// layer alone already meets the error bound almost everywhere, e.g. with a
+/// low base compression ratio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SparseCorrections;
+
+impl SparseCorrections {
+    /// ID under which the coder is always available
+    pub const ID: &'static str = "ebcc.sparse";
+}
+
+impl ResidualCoder for SparseCorrections {
+    fn encode(
+        &self,
+        data: ArrayView<f32, EbccDim>,
+        approximation: ArrayView<f32, EbccDim>,
+        error_bound: f32,
+    ) -> EBCCResult<Vec<u8>> {
+        let mut residuals = Vec::new();
+        let mut skipped = 0_u64;
+
+        for (value, approximation) in data.iter().zip(approximation.iter()) {
+            if within_error_bound(*value, *approximation, error_bound) {
+                skipped += 1;
+                continue;
+            }
+
+            write_varint(&mut residuals, skipped);
+            residuals.extend_from_slice(&value.to_le_bytes());
+            skipped = 0;
+        }
+
+        Ok(residuals)
+    }
+
+    fn decode(
+        &self,
+        mut residuals: &[u8],
+        mut approximation: ArrayViewMut<f32, EbccDim>,
+        _error_bound: f32,
+    ) -> EBCCResult<()> {
+        let mut values = approximation.iter_mut();
+        while !residuals.is_empty() {
+            let skipped = u64_to_usize(FORMAT.read_varint(&mut residuals)?)?;
+            let value = values.nth(skipped).ok_or_else(|| FORMAT.corrupted())?;
+            *value = f32::from_le_bytes(FORMAT.read_array(&mut residuals)?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Built-in [`ResidualCoder`] that codes the residuals with the residual
+/// stage of the EBCC C library.
+///
+/// This is the default residual coder: payloads without a
+/// [`residual_coder`][EBCCConfig::residual_coder] use the same stage, fused
+/// with the `JPEG2000` base layer into a single EBCC C library payload.
+/// Behind the trait, the residuals are instead compressed on their own by
+/// the C library, such that the stage can be compared with other coders
+/// over the same base layer. The C library bounds the error of the
+/// residuals, not of their sum with the approximation, so the error bound is
+/// tightened by a margin for the `f32` rounding of both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EbccResidualStage;
+
+impl EbccResidualStage {
+    /// ID under which the coder is always available
+    pub const ID: &'static str = "ebcc.c";
+}
+
+impl ResidualCoder for EbccResidualStage {
+    fn encode(
+        &self,
+        data: ArrayView<f32, EbccDim>,
+        approximation: ArrayView<f32, EbccDim>,
+        error_bound: f32,
+    ) -> EBCCResult<Vec<u8>> {
+        let magnitude = data
+            .iter()
+            .chain(approximation.iter())
+            .fold(0.0_f32, |magnitude, value| magnitude.max(value.abs()));
+        let stage_error_bound = (4.0 * f32::EPSILON).mul_add(-magnitude, error_bound);
+        if stage_error_bound.is_nan() || stage_error_bound <= 0.0 {
+            return Err(EBCCError::CompressionError(
+                format!("The error bound {error_bound} is too tight for the EBCC C residual stage")
+                    .into(),
+            ));
+        }
+
+        let residuals = Zip::from(&data)
+            .and(&approximation)
+            .map_collect(|value, approximation| value - approximation);
+        let residuals = ebcc_encode_c_buffer_with_scratch(
+            residuals.view(),
+            &EBCCConfig::max_absolute_error_bounded(stage_error_bound),
+            &mut Vec::new(),
+        )?;
+
+        Ok(residuals.as_slice().to_vec())
+    }
+
+    fn decode(
+        &self,
+        residuals: &[u8],
+        mut approximation: ArrayViewMut<f32, EbccDim>,
+        _error_bound: f32,
+    ) -> EBCCResult<()> {
+        let mut residuals = Vec::from(residuals); // C function may modify the input
+
+        // the residuals are a plain EBCC C library payload, so no wrapper
+        //  formats may be nested inside
+        let residuals = ebcc_decode_nested(&mut residuals, approximation.dim(), MAX_NESTING_DEPTH)?;
+        approximation += &decompressed_view(approximation.dim(), &residuals)?;
+
+        Ok(())
+    }
+}
+
+/// Register the custom residual `coder` under the `id`, replacing and
+/// returning any coder that was previously registered under the same `id`.
+///
+/// Coders are registered for the whole process, such that all encoders and
+/// decoders can use them. IDs starting with `ebcc.` are reserved for the
+/// built-in [`EbccResidualStage`], [`QuantizedResiduals`], and
+/// [`SparseCorrections`].
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidConfig`] if the `id` is reserved, empty, or longer
+///   than [`u16::MAX`] bytes
+pub fn ebcc_register_residual_coder(
+    id: impl Into<String>,
+    coder: Arc<dyn ResidualCoder>,
+) -> EBCCResult<Option<Arc<dyn ResidualCoder>>> {
+    let id = id.into();
+    validate_residual_coder_id(&id)?;
+    if id.starts_with(RESERVED_ID_PREFIX) {
+        return Err(EBCCError::InvalidConfig(format!(
+            "EBCC residual coder IDs starting with {RESERVED_ID_PREFIX:?} are reserved, got \
+             {id:?}"
+        )));
+    }
+
+    Ok(CODERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(id, coder))
+}
+
+/// Unregister and return the custom residual coder with the `id`, if it
+/// exists.
+pub fn ebcc_unregister_residual_coder(id: &str) -> Option<Arc<dyn ResidualCoder>> {
+    CODERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(id)
+}
+
+/// The IDs of all registered custom residual coders, in sorted order.
+#[must_use]
+pub fn ebcc_registered_residual_coders() -> Vec<String> {
+    CODERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .keys()
+        .cloned()
+        .collect()
+}
+
+fn residual_coder(id: &str) -> EBCCResult<Arc<dyn ResidualCoder>> {
+    match id {
+        EbccResidualStage::ID => Ok(Arc::new(EbccResidualStage)),
+        QuantizedResiduals::ID => Ok(Arc::new(QuantizedResiduals)),
+        SparseCorrections::ID => Ok(Arc::new(SparseCorrections)),
+        id => CODERS
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(id)
+            .cloned()
+            .ok_or_else(|| {
+                EBCCError::InvalidConfig(format!("EBCC residual coder {id:?} is not registered"))
+            }),
+    }
+}
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_CODER_MAGIC`].
+pub fn is_residual_coded(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_CODER_MAGIC)
+}
+
+/// Check that the residual coder `id` can be recorded in a payload.
+pub fn validate_residual_coder_id(id: &str) -> EBCCResult<()> {
+    if id.is_empty() || u16::try_from(id.len()).is_err() {
+        return Err(EBCCError::InvalidConfig(format!(
+            "EBCC residual coder ID must have between 1 and {} bytes, got {}",
+            u16::MAX,
+            id.len(),
+        )));
+    }
+
+    Ok(())
+}
+
+/// Encode a 3D data array into a payload with the
+/// [`EBCCConfig::residual_coder`] of the `config`, which must be set.
+pub fn residual_coded_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    // without a residual coder, the default residual stage of the EBCC C
+    //  library codes the residual of a base codec other than JPEG2000
+    let id = config
+        .residual_coder
+        .as_deref()
+        .unwrap_or(EbccResidualStage::ID);
+    let Ok(id_len) = u16::try_from(id.len()) else {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "EBCC residual coder ID is too long",
+        )));
+    };
+    let coder = residual_coder(id)?;
+
+    let error_bound = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => error,
+        EBCCResidualType::RelativeError(error) => data_range(data) * error,
+        EBCCResidualType::Jpeg2000Only => return Err(residual_coder_requires_error_bound()),
+    };
+
+    let mut compressed_data = Vec::with_capacity(8 + 4 + 2 + id.len() + 3 * 8 + 4 + 8);
+    compressed_data.extend_from_slice(EBCC_CODER_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_CODER_VERSION.to_le_bytes());
+    compressed_data.extend_from_slice(&id_len.to_le_bytes());
+    compressed_data.extend_from_slice(id.as_bytes());
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(&error_bound.to_bits().to_le_bytes());
+
+    // without a base layer, the coder codes the data itself
+    let mut approximation = if config.base_codec == EBCCBaseCodec::Identity {
+        compressed_data.extend_from_slice(&0_u64.to_le_bytes());
+        CBuffer::from_vec(vec![0.0; data.len()])
+    } else {
+        let base_config = EBCCConfig {
+            residual_compression_type: EBCCResidualType::Jpeg2000Only,
+            base_mode: EBCCBaseMode::Jpeg2000,
+            expansion_guard: None,
+            residual_coder: None,
+            ..config.clone()
+        };
+        let mut base = ebcc_encode_c_buffer_with_scratch(data, &base_config, scratch)?;
+        compressed_data.extend_from_slice(&usize_to_u64(base.as_slice().len())?.to_le_bytes());
+        compressed_data.extend_from_slice(base.as_slice());
+
+        // the base layer has been stored and is decoded in-place
+        ebcc_decode_c_buffer_mut(base.as_mut_slice(), data.dim())?
+    };
+    let approximation_len = approximation.as_slice().len();
+    let Ok(mut approximation) = ArrayViewMut::from_shape(data.dim(), approximation.as_mut_slice())
+    else {
+        return Err(EBCCError::SizeMismatch {
+            expected: data.dim().into(),
+            actual: approximation_len,
+        });
+    };
+
+    let residuals = coder.encode(data, approximation.view(), error_bound)?;
+
+    // custom coders are checked against the error bound before their
+    //  residuals are stored, reconstructing in-place since the
+    //  approximation is no longer needed
+    coder.decode(&residuals, approximation.view_mut(), error_bound)?;
+    if !Zip::from(&data)
+        .and(&approximation)
+        .all(|value, reconstructed| within_error_bound(*value, *reconstructed, error_bound))
+    {
+        return Err(EBCCError::CompressionError(
+            format!("EBCC residual coder {id:?} violates the error bound {error_bound}").into(),
+        ));
+    }
+
+    compressed_data.extend_from_slice(&residuals);
+
+    Ok(compressed_data)
+}
+
+/// Decode a payload with a residual coder of the expected `shape`, which may
+/// be modified during decoding, into the flattened 3D data array.
+///
+/// If `approximation_only` is set, only the base layer is decoded, and the
+/// residual coder does not need to be registered.
+pub fn residual_coded_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+    approximation_only: bool,
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_CODER_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_CODER_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC residual coder version: {version}").into(),
+        ));
+    }
+
+    let id_len = usize::from(u16::from_le_bytes(FORMAT.read_array(&mut reader)?));
+    let Some((id, rest)) = reader.split_at_checked(id_len) else {
+        return Err(FORMAT.truncated());
+    };
+    reader = rest;
+    let Ok(id) = std::str::from_utf8(id) else {
+        return Err(FORMAT.corrupted());
+    };
+    // the coder is only required once the residuals are decoded
+    let coder = residual_coder(id).map_err(|_| {
+        EBCCError::DecompressionError(
+            format!("EBCC residual coder {id:?} must be registered to decode the data").into(),
+        )
+    });
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+    data_len(expected_shape.into())?;
+
+    let error_bound = f32::from_bits(u32::from_le_bytes(FORMAT.read_array(&mut reader)?));
+    let base_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+
+    let header_len = compressed_data.len() - reader.len();
+    let Some((base, residuals)) = compressed_data
+        .get_mut(header_len..)
+        .and_then(|payload| payload.split_at_mut_checked(base_len))
+    else {
+        return Err(FORMAT.truncated());
+    };
+
+    // an empty base layer is an all-zero approximation
+    let decompressed_data = if base.is_empty() {
+        vec![0.0; data_len(expected_shape.into())?]
+    } else {
+        ebcc_decode_nested(base, expected_shape.into(), depth + 1)?
+            .as_slice()
+            .to_vec()
+    };
+    if approximation_only {
+        return Ok(decompressed_data);
+    }
+
+    let coder = coder?;
+    let Ok(mut decompressed_data) = Array::from_shape_vec(expected_shape, decompressed_data) else {
+        return Err(FORMAT.corrupted());
+    };
+    coder.decode(residuals, decompressed_data.view_mut(), error_bound)?;
+
+    Ok(decompressed_data.into_iter().collect())
+}
+
+pub fn residual_coder_requires_error_bound() -> EBCCError {
+    EBCCError::InvalidConfig(String::from(
+        "Residual coders require an absolute or relative error bound",
+    ))
+}
+
+/// Returns `true` if the `reconstructed` value is within the `error_bound`
+/// of the `value`, or both are the same non-finite value.
+fn within_error_bound(value: f32, reconstructed: f32, error_bound: f32) -> bool {
+    (value - reconstructed).abs() <= error_bound || value.to_bits() == reconstructed.to_bits()
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, verify::check_error_bound};
+
+    /// Keeps the approximation, which violates tight error bounds
+    struct Approximation;
+
+    impl ResidualCoder for Approximation {
+        fn encode(
+            &self,
+            _data: ArrayView<f32, EbccDim>,
+            _approximation: ArrayView<f32, EbccDim>,
+            _error_bound: f32,
+        ) -> EBCCResult<Vec<u8>> {
+            Ok(Vec::new())
+        }
+
+        fn decode(
+            &self,
+            _residuals: &[u8],
+            _approximation: ArrayViewMut<f32, EbccDim>,
+            _error_bound: f32,
+        ) -> EBCCResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_residual_coders() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+
+        for id in [
+            EbccResidualStage::ID,
+            QuantizedResiduals::ID,
+            SparseCorrections::ID,
+        ] {
+            for config in [
+                EBCCConfig::max_absolute_error_bounded(0.1),
+                EBCCConfig::relative_error_bounded(1e-3).with_base_cr(10.0),
+                EBCCConfig::max_absolute_error_bounded(0.1)
+                    .with_base_codec(EBCCBaseCodec::Identity),
+            ] {
+                let config = config.with_residual_coder(id);
+                let compressed = ebcc_encode(data.view(), &config)?;
+
+                let mut decompressed = Array::zeros(data.dim());
+                ebcc_decode_into(&compressed, decompressed.view_mut())?;
+                check_error_bound(data.view(), decompressed.view(), &config)?;
+            }
+        }
+
+        let config =
+            EBCCConfig::max_absolute_error_bounded(1e-3).with_residual_coder("test.approx");
+        assert!(matches!(
+            ebcc_encode(data.view(), &config),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        ebcc_register_residual_coder("test.approx", Arc::new(Approximation))?;
+        assert!(ebcc_registered_residual_coders().contains(&String::from("test.approx")));
+        assert!(matches!(
+            ebcc_encode(data.view(), &config),
+            Err(EBCCError::CompressionError(_))
+        ));
+        assert!(ebcc_unregister_residual_coder("test.approx").is_some());
+
+        // the IDs of the built-in coders are reserved
+        assert!(matches!(
+            ebcc_register_residual_coder(SparseCorrections::ID, Arc::new(Approximation)),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        // the identity base codec defaults to the C residual stage
+        let config =
+            EBCCConfig::max_absolute_error_bounded(0.1).with_base_codec(EBCCBaseCodec::Identity);
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        check_error_bound(data.view(), decompressed.view(), &config)?;
+
+        // residual coders require an error bound and the JPEG2000 base mode
+        for config in [
+            EBCCConfig::jpeg2000_only(10.0),
+            EBCCConfig::max_absolute_error_bounded(0.1).with_base_mode(EBCCBaseMode::Layered),
+            EBCCConfig::max_absolute_error_bounded(0.1).with_base_mode(EBCCBaseMode::None),
+        ] {
+            assert!(matches!(
+                config.with_residual_coder(SparseCorrections::ID).validate(),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+//! Conversions from the array views of other `ndarray` major versions.
+//!
+//! The public API uses the views of `ndarray` 0.16. With the `ndarray015` or
+//! the `ndarray017` feature, the 3D views of `ndarray` 0.15 or 0.17 can be
+//! converted into them without copying, such that downstream crates do not
+//! have to upgrade `ndarray` in lockstep with this crate.
+
+use ndarray::{ArrayView, ArrayViewMut};
+#[cfg(any(feature = "ndarray015", feature = "ndarray017"))]
+use ndarray::{Axis, ShapeBuilder};
+
+use crate::EbccDim;
+
+/// Conversion of a 3D `f32` array view of any supported `ndarray` version
+/// into the [`ArrayView`] of this crate's `ndarray` version, without copying.
+///
+/// # Examples
+///
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+/// use ebcc::{ebcc_encode, EBCCConfig, IntoEbccView};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// // with the ndarray015 feature, an ndarray 0.15 view works the same
+/// let data = Array::from_elem((1, 32, 32), 1.0_f32);
+/// let compressed = ebcc_encode(data.view().into_ebcc_view(), &EBCCConfig::new())?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait IntoEbccView<'a> {
+    /// Convert the view into an [`ArrayView`] of the same data.
+    fn into_ebcc_view(self) -> ArrayView<'a, f32, EbccDim>;
+}
+
+/// Conversion of a mutable 3D `f32` array view of any supported `ndarray`
+/// version into the [`ArrayViewMut`] of this crate's `ndarray` version,
+/// without copying.
+pub trait IntoEbccViewMut<'a> {
+    /// Convert the view into an [`ArrayViewMut`] of the same data.
+    fn into_ebcc_view_mut(self) -> ArrayViewMut<'a, f32, EbccDim>;
+}
+
+impl<'a> IntoEbccView<'a> for ArrayView<'a, f32, EbccDim> {
+    fn into_ebcc_view(self) -> Self {
+        self
+    }
+}
+
+impl<'a> IntoEbccViewMut<'a> for ArrayViewMut<'a, f32, EbccDim> {
+    fn into_ebcc_view_mut(self) -> Self {
+        self
+    }
+}
+
+/// Implement [`IntoEbccView`] and [`IntoEbccViewMut`] for the 3D views of
+/// another `ndarray` version.
+///
+/// Axes with negative strides are inverted before the view is rebuilt from
+/// its pointer, shape, and non-negative strides, and inverted back after.
+macro_rules! impl_into_ebcc_view {
+    ($feature:literal, $ndarray:ident) => {
+        #[cfg(feature = $feature)]
+        impl<'a> IntoEbccView<'a> for $ndarray::ArrayView3<'a, f32> {
+            fn into_ebcc_view(mut self) -> ArrayView<'a, f32, EbccDim> {
+                let mut inverted = [false; 3];
+                for (axis, inverted) in inverted.iter_mut().enumerate() {
+                    *inverted = self.strides().get(axis).is_some_and(|stride| *stride < 0);
+                    if *inverted {
+                        self.invert_axis($ndarray::Axis(axis));
+                    }
+                }
+
+                let strides = non_negative_strides(self.strides());
+                #[expect(unsafe_code)]
+                // Safety: the pointer, shape, and non-negative strides
+                //         describe the same elements as the borrowed view,
+                //         which live for 'a
+                let mut view = unsafe {
+                    ArrayView::from_shape_ptr(self.dim().strides(strides), self.as_ptr())
+                };
+                invert_axes(inverted, |axis| view.invert_axis(axis));
+
+                view
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<'a> IntoEbccViewMut<'a> for $ndarray::ArrayViewMut3<'a, f32> {
+            fn into_ebcc_view_mut(mut self) -> ArrayViewMut<'a, f32, EbccDim> {
+                let mut inverted = [false; 3];
+                for (axis, inverted) in inverted.iter_mut().enumerate() {
+                    *inverted = self.strides().get(axis).is_some_and(|stride| *stride < 0);
+                    if *inverted {
+                        self.invert_axis($ndarray::Axis(axis));
+                    }
+                }
+
+                let strides = non_negative_strides(self.strides());
+                #[expect(unsafe_code)]
+                // Safety: the pointer, shape, and non-negative strides
+                //         describe the same, non-aliasing, elements as the
+                //         mutably borrowed view, which live for 'a
+                let mut view = unsafe {
+                    ArrayViewMut::from_shape_ptr(self.dim().strides(strides), self.as_mut_ptr())
+                };
+                invert_axes(inverted, |axis| view.invert_axis(axis));
+
+                view
+            }
+        }
+    };
+}
+
+impl_into_ebcc_view!("ndarray015", ndarray015);
+impl_into_ebcc_view!("ndarray017", ndarray017);
+
+/// Convert the strides of a view without negative strides.
+#[cfg(any(feature = "ndarray015", feature = "ndarray017"))]
+fn non_negative_strides(strides: &[isize]) -> (usize, usize, usize) {
+    let mut non_negative = [0; 3];
+    for (non_negative, stride) in non_negative.iter_mut().zip(strides) {
+        *non_negative = stride.unsigned_abs();
+    }
+    non_negative.into()
+}
+
+/// Invert all `inverted` axes of a view.
+#[cfg(any(feature = "ndarray015", feature = "ndarray017"))]
+fn invert_axes(inverted: [bool; 3], mut invert_axis: impl FnMut(Axis)) {
+    for (axis, inverted) in inverted.into_iter().enumerate() {
+        if inverted {
+            invert_axis(Axis(axis));
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "ndarray015", feature = "ndarray017")))]
+mod tests {
+    use ndarray::s;
+
+    use super::*;
+    use crate::testdata;
+
+    macro_rules! test_into_ebcc_view {
+        ($name:ident, $feature:literal, $ndarray:ident) => {
+            #[test]
+            #[cfg(feature = $feature)]
+            fn $name() {
+                let data = testdata::temperature((2, 32, 48));
+                let mut other =
+                    $ndarray::Array3::from_shape_vec(data.dim(), data.iter().copied().collect())
+                        .unwrap_or_default();
+
+                assert_eq!(other.view().into_ebcc_view(), data.view());
+                assert_eq!(other.t().into_ebcc_view(), data.t());
+                assert_eq!(
+                    other
+                        .slice($ndarray::s![..;-1, 1..;2, ..;-3])
+                        .into_ebcc_view(),
+                    data.slice(s![..;-1, 1..;2, ..;-3])
+                );
+
+                let mut view = other
+                    .slice_mut($ndarray::s![.., ..;-1, ..])
+                    .into_ebcc_view_mut();
+                view.fill(0.0);
+                assert!(other.iter().all(|x| x.to_bits() == 0));
+            }
+        };
+    }
+
+    test_into_ebcc_view!(test_ndarray015, "ndarray015", ndarray015);
+    test_into_ebcc_view!(test_ndarray017, "ndarray017", ndarray017);
+}
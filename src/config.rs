@@ -1,9 +1,29 @@
 //! Configuration types for EBCC compression.
 
+use ndarray::ArrayView;
+
+use crate::auto::{auto_config, EBCCTarget};
+use crate::checksum::EBCCChecksumAlgorithm;
+use crate::clamp::{EBCCValueRange, EBCC_CLAMP_HEADER_LEN};
+use crate::codec::EbccDim;
+use crate::coder::{residual_coder_requires_error_bound, validate_residual_coder_id};
+use crate::conserve::{EBCCConservation, EBCC_CONSERVE_HEADER_LEN};
 use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+use crate::layered::layered_requires_error_bound;
+use crate::limits::EBCCLimits;
+use crate::params::{validate_base_cr, validate_error_bound};
+use crate::quantize::{quantization_step_too_large, EBCC_QUANTIZE_HEADER_LEN};
+use crate::residual::residual_only_requires_error_bound;
+use crate::roi::{roi_requires_error_bound, EBCCRoi};
+use crate::sketch::{EBCC_SKETCH_HEADER_LEN, SKETCH_QUANTILES};
+use crate::stage::validate_stage_id;
+use crate::stored::stored_size_bound;
+use crate::transform::EBCCTransform;
 
 /// Residual compression types supported by EBCC.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EBCCResidualType {
     /// No residual compression - base JPEG2000 only
     Jpeg2000Only,
@@ -30,14 +50,222 @@ impl EBCCResidualType {
     }
 }
 
+/// Base layer of EBCC compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EBCCBaseMode {
+    /// `JPEG2000` base layer, compressed with the
+    /// [`base_cr`][EBCCConfig::base_cr], followed by the residual layer
+    #[default]
+    Jpeg2000,
+    /// No base layer, the data is predicted and quantized directly
+    ///
+    /// Each frame is predicted from its already reconstructed neighbours and
+    /// the prediction residual is quantized such that the absolute or
+    /// relative error bound of the
+    /// [`residual_compression_type`][EBCCConfig::residual_compression_type]
+    /// holds. This can beat `JPEG2000` plus residual coding for spiky or
+    /// sparse fields, e.g. precipitation. The
+    /// [`base_cr`][EBCCConfig::base_cr] is ignored.
+    ///
+    /// This mode is implemented in Rust rather than by the EBCC C library,
+    /// but its payloads are used and decoded like any other EBCC payload.
+    /// It does not support chunked compression. To code the data with a
+    /// [`ResidualCoder`][crate::ResidualCoder] instead, select the
+    /// [`EBCCBaseCodec::Identity`].
+    None,
+    /// No lossy compression, the data is stored losslessly
+    ///
+    /// The data is stored raw or compressed losslessly with the
+    /// [`stored_compression`][EBCCConfig::stored_compression]. The
+    /// [`base_cr`][EBCCConfig::base_cr] and the
+    /// [`residual_compression_type`][EBCCConfig::residual_compression_type]
+    /// are ignored. This allows mixing lossy and lossless frames in one
+    /// [`container`][crate::container] or stream, which are all decoded the
+    /// same way.
+    ///
+    /// This mode is implemented in Rust rather than by the EBCC C library.
+    /// It does not support chunked compression.
+    Stored,
+    /// `JPEG2000` base layer, compressed with the
+    /// [`base_cr`][EBCCConfig::base_cr], followed by a separately stored
+    /// residual layer
+    ///
+    /// The residual between the data and the decoded base layer is quantized
+    /// such that the absolute or relative error bound of the
+    /// [`residual_compression_type`][EBCCConfig::residual_compression_type]
+    /// holds. Since the residual layer is not mixed into the EBCC C library
+    /// payload, the base layer can be decoded on its own with
+    /// [`ebcc_decode_approximation_into`][crate::ebcc_decode_approximation_into].
+    ///
+    /// The residual layer is implemented in Rust rather than by the EBCC C
+    /// library. This mode does not support chunked compression.
+    Layered,
+}
+
+/// Codec of the base layer that approximates the data before its residual
+/// is coded by a [`ResidualCoder`][crate::ResidualCoder], see
+/// [`EBCCConfig::with_base_codec`].
+///
+/// The base layer is stored as a nested EBCC payload and decoded like any
+/// other, so further codecs, e.g. a wavelet base layer, only need their own
+/// payload format and can be added without a breaking change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum EBCCBaseCodec {
+    /// `JPEG2000` base layer, compressed by the EBCC C library with the
+    /// [`base_cr`][EBCCConfig::base_cr]
+    #[default]
+    Jpeg2000,
+    /// No base layer, the residual coder codes the data itself as the
+    /// residual of an all-zero approximation
+    ///
+    /// Since the same coder can be used with and without the `JPEG2000` base
+    /// layer, pure error-bounded coding can be benchmarked against the
+    /// hybrid approach. The [`base_cr`][EBCCConfig::base_cr] is ignored.
+    Identity,
+}
+
+/// Lossless compression of stored data, see [`EBCCBaseMode::Stored`] and
+/// [`EBCCExpansionFallback::StoreRaw`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EBCCStoredCompression {
+    /// The `f32` values are stored as they are
+    #[default]
+    None,
+    /// The byte planes of the `f32` values are compressed with zstd
+    Zstd,
+}
+
+/// Guard against compressed data that is larger than the raw data, e.g. for
+/// incompressible inputs such as white noise with a tight error bound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EBCCExpansionGuard {
+    /// Maximum ratio of the compressed size to the raw `f32` size, e.g. `1.1`
+    pub max_ratio: f32,
+    /// What happens if the compressed data exceeds the maximum ratio
+    pub fallback: EBCCExpansionFallback,
+}
+
+impl EBCCExpansionGuard {
+    /// Create a new guard that refuses compressed data exceeding the
+    /// `max_ratio` with an [`EBCCError::ExpansionTooLarge`].
+    #[must_use]
+    pub const fn new(max_ratio: f32) -> Self {
+        Self {
+            max_ratio,
+            fallback: EBCCExpansionFallback::Error,
+        }
+    }
+
+    /// Create a new guard that stores the data raw if the compressed data
+    /// exceeds the `max_ratio`, and refuses the stored data with an
+    /// [`EBCCError::ExpansionTooLarge`] if it exceeds the `max_ratio` too.
+    #[must_use]
+    pub const fn store_raw(max_ratio: f32) -> Self {
+        Self {
+            max_ratio,
+            fallback: EBCCExpansionFallback::StoreRaw,
+        }
+    }
+}
+
+/// Fallback of an [`EBCCExpansionGuard`] when the compressed data exceeds
+/// its maximum ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EBCCExpansionFallback {
+    /// Fail with an [`EBCCError::ExpansionTooLarge`]
+    #[default]
+    Error,
+    /// Store the data losslessly instead, with the
+    /// [`stored_compression`][EBCCConfig::stored_compression], which adds at
+    /// most a small header to the raw size
+    ///
+    /// The stored data must also fit within the maximum ratio, otherwise the
+    /// encode still fails with an [`EBCCError::ExpansionTooLarge`]. Without
+    /// compression, a maximum ratio slightly above `1.0`, e.g. `1.1`, leaves
+    /// room for the header. Stored-raw payloads are decoded like any other
+    /// EBCC payload.
+    StoreRaw,
+}
+
 /// Configuration for EBCC compression.
 #[derive(Debug, Clone, PartialEq)]
+#[expect(clippy::struct_excessive_bools)] // independent options
 pub struct EBCCConfig {
     /// Base compression ratio for JPEG2000 layer
     pub base_cr: f32,
 
+    /// Base layer, by default `JPEG2000`
+    pub base_mode: EBCCBaseMode,
+
     /// Type of residual compression to apply
     pub residual_compression_type: EBCCResidualType,
+
+    /// Whether the input data is checked for non-finite values before
+    /// compression
+    pub check_finite: bool,
+
+    /// Limits on the number and size of frames that are encoded
+    pub limits: EBCCLimits,
+
+    /// Whether a checksum of the decompressed data is stored in the
+    /// [`EBCCHeader`][crate::EBCCHeader] and verified when decoding
+    pub checksum_decompressed: bool,
+
+    /// Algorithm of the checksum of the compressed payload in the
+    /// [`EBCCHeader`][crate::EBCCHeader], by default CRC-32
+    ///
+    /// Only the payload header uses this algorithm. The frame index of a
+    /// [container][crate::container] and the tile table of
+    /// [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] data always
+    /// use CRC-32.
+    pub checksum_algorithm: EBCCChecksumAlgorithm,
+
+    /// Optional guard against compressed data that is larger than the raw
+    /// data, which is disabled by default
+    pub expansion_guard: Option<EBCCExpansionGuard>,
+
+    /// Optional per-pixel weights of the error bound, which tighten it over
+    /// regions of interest and relax it elsewhere
+    pub roi: Option<EBCCRoi>,
+
+    /// Lossless compression of stored data, by default none
+    pub stored_compression: EBCCStoredCompression,
+
+    /// Optional invertible transform that is applied to the data before
+    /// encoding and inverted when decoding
+    pub transform: Option<EBCCTransform>,
+
+    /// Whether a quantile sketch of every frame is stored next to the
+    /// compressed data, see [`ebcc_inspect`][crate::ebcc_inspect]
+    pub quantile_sketch: bool,
+
+    /// Optional grid step to which the decoded values are snapped
+    pub output_quantization: Option<f32>,
+
+    /// Optional mean that is conserved by the reconstruction
+    pub conservation: Option<EBCCConservation>,
+
+    /// Optional physical value range to which the decoded values are clamped
+    pub value_range: Option<EBCCValueRange>,
+
+    /// Optional ID of a registered custom [`EBCCStage`][crate::EBCCStage]
+    /// that is applied to the data before encoding
+    pub stage: Option<String>,
+
+    /// Optional ID of a [`ResidualCoder`][crate::ResidualCoder] that codes
+    /// the residual of the base layer instead of the residual stage of the
+    /// EBCC C library
+    pub residual_coder: Option<String>,
+
+    /// Codec of the base layer under a residual coder, by default `JPEG2000`
+    pub base_codec: EBCCBaseCodec,
+
+    /// Whether the encoders run single-threaded, such that identical inputs
+    /// always produce byte-identical outputs
+    pub deterministic: bool,
 }
 
 impl Default for EBCCConfig {
@@ -54,7 +282,24 @@ impl EBCCConfig {
     pub const fn new() -> Self {
         Self {
             base_cr: DEFAULT_BASE_CR,
+            base_mode: EBCCBaseMode::Jpeg2000,
             residual_compression_type: EBCCResidualType::Jpeg2000Only,
+            check_finite: true,
+            limits: EBCCLimits::new(),
+            checksum_decompressed: false,
+            checksum_algorithm: EBCCChecksumAlgorithm::Crc32,
+            expansion_guard: None,
+            roi: None,
+            stored_compression: EBCCStoredCompression::None,
+            transform: None,
+            quantile_sketch: false,
+            output_quantization: None,
+            conservation: None,
+            value_range: None,
+            stage: None,
+            residual_coder: None,
+            base_codec: EBCCBaseCodec::Jpeg2000,
+            deterministic: false,
         }
     }
 
@@ -63,7 +308,24 @@ impl EBCCConfig {
     pub const fn jpeg2000_only(base_cr: f32) -> Self {
         Self {
             base_cr,
+            base_mode: EBCCBaseMode::Jpeg2000,
             residual_compression_type: EBCCResidualType::Jpeg2000Only,
+            check_finite: true,
+            limits: EBCCLimits::new(),
+            checksum_decompressed: false,
+            checksum_algorithm: EBCCChecksumAlgorithm::Crc32,
+            expansion_guard: None,
+            roi: None,
+            stored_compression: EBCCStoredCompression::None,
+            transform: None,
+            quantile_sketch: false,
+            output_quantization: None,
+            conservation: None,
+            value_range: None,
+            stage: None,
+            residual_coder: None,
+            base_codec: EBCCBaseCodec::Jpeg2000,
+            deterministic: false,
         }
     }
 
@@ -72,7 +334,24 @@ impl EBCCConfig {
     pub const fn max_absolute_error_bounded(error: f32) -> Self {
         Self {
             base_cr: DEFAULT_BASE_CR,
+            base_mode: EBCCBaseMode::Jpeg2000,
             residual_compression_type: EBCCResidualType::AbsoluteError(error),
+            check_finite: true,
+            limits: EBCCLimits::new(),
+            checksum_decompressed: false,
+            checksum_algorithm: EBCCChecksumAlgorithm::Crc32,
+            expansion_guard: None,
+            roi: None,
+            stored_compression: EBCCStoredCompression::None,
+            transform: None,
+            quantile_sketch: false,
+            output_quantization: None,
+            conservation: None,
+            value_range: None,
+            stage: None,
+            residual_coder: None,
+            base_codec: EBCCBaseCodec::Jpeg2000,
+            deterministic: false,
         }
     }
 
@@ -81,10 +360,51 @@ impl EBCCConfig {
     pub const fn relative_error_bounded(error: f32) -> Self {
         Self {
             base_cr: DEFAULT_BASE_CR,
+            base_mode: EBCCBaseMode::Jpeg2000,
             residual_compression_type: EBCCResidualType::RelativeError(error),
+            check_finite: true,
+            limits: EBCCLimits::new(),
+            checksum_decompressed: false,
+            checksum_algorithm: EBCCChecksumAlgorithm::Crc32,
+            expansion_guard: None,
+            roi: None,
+            stored_compression: EBCCStoredCompression::None,
+            transform: None,
+            quantile_sketch: false,
+            output_quantization: None,
+            conservation: None,
+            value_range: None,
+            stage: None,
+            residual_coder: None,
+            base_codec: EBCCBaseCodec::Jpeg2000,
+            deterministic: false,
         }
     }
 
+    /// Select a configuration for the `data` automatically, which meets the
+    /// `target` compression ratio or maximum error.
+    ///
+    /// Up to four evenly spaced frames of the `data` are profiled by
+    /// encoding them with candidate configurations. For a
+    /// [`EBCCTarget::MaxError`], the base compression ratio that results in
+    /// the smallest compressed sample is chosen. For a
+    /// [`EBCCTarget::CompressionRatio`], the smallest absolute error bound,
+    /// relative to the dynamic range of the data, is found for which the
+    /// sample still reaches the ratio, falling back to
+    /// [`jpeg2000_only`][Self::jpeg2000_only] compression if no error bound
+    /// does. The ratio of the full data is only estimated from the sample.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidConfig`] if the target error is not finite and
+    ///   positive, or the target ratio is not finite and at least one
+    /// - [`EBCCError::EmptyDimension`], [`EBCCError::UnsupportedShape`], and
+    ///   [`EBCCError::NonFinite`] if the `data` cannot be encoded
+    /// - all errors that [`ebcc_encode`][crate::ebcc_encode] can return
+    pub fn auto_for(data: ArrayView<f32, EbccDim>, target: EBCCTarget) -> EBCCResult<Self> {
+        auto_config(data, target)
+    }
+
     /// Change the JPEG2000 layer base compression ratio.
     #[must_use]
     pub const fn with_base_cr(mut self, base_cr: f32) -> Self {
@@ -92,35 +412,1025 @@ impl EBCCConfig {
         self
     }
 
+    /// Change the base layer of EBCC compression.
+    #[must_use]
+    pub const fn with_base_mode(mut self, base_mode: EBCCBaseMode) -> Self {
+        self.base_mode = base_mode;
+        self
+    }
+
+    /// Change the lossless compression of stored data.
+    #[must_use]
+    pub const fn with_stored_compression(
+        mut self,
+        stored_compression: EBCCStoredCompression,
+    ) -> Self {
+        self.stored_compression = stored_compression;
+        self
+    }
+
+    /// Skip checking the input data for non-finite (infinite or NaN) values
+    /// before compression.
+    ///
+    /// This is intended for callers that have already validated their data,
+    /// e.g. because it was produced by a model that never emits non-finite
+    /// values, and want to avoid scanning it a second time.
+    ///
+    /// <div class="warning">
+    ///
+    /// **Warning:** EBCC does not support non-finite values. Compressing data
+    /// that contains them with the check skipped produces unspecified
+    /// compressed data or a compression error.
+    ///
+    /// </div>
+    #[must_use]
+    pub const fn skip_finite_check(mut self) -> Self {
+        self.check_finite = false;
+        self
+    }
+
+    /// Change the limits on the number and size of frames that are encoded.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: EBCCLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Store a checksum of the decompressed data in the
+    /// [`EBCCHeader`][crate::EBCCHeader], which is verified automatically
+    /// when decoding.
+    ///
+    /// Since EBCC is lossy, the checksum covers the data that decoding the
+    /// payload reconstructs, not the original input data. Encoding therefore
+    /// decodes the payload once to compute the checksum. Decoding then
+    /// detects corruption that the checksum of the compressed payload
+    /// cannot, e.g. a bit flip in memory or a faulty EBCC build, and reports
+    /// it as [`EBCCError::ChecksumMismatch`].
+    #[must_use]
+    pub const fn with_decompressed_checksum(mut self) -> Self {
+        self.checksum_decompressed = true;
+        self
+    }
+
+    /// Change the algorithm of the checksum of the compressed payload.
+    ///
+    /// The algorithm is recorded in the [`EBCCHeader`][crate::EBCCHeader],
+    /// which grows by the digest bytes beyond the first four, and decoding
+    /// verifies the payload with it. The checksum of the decompressed data,
+    /// see [`with_decompressed_checksum`][Self::with_decompressed_checksum],
+    /// always uses CRC-32, as do the checksums of the frame index of a
+    /// [container][crate::container] and of the tile table of
+    /// [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] data.
+    #[must_use]
+    pub const fn with_checksum_algorithm(mut self, algorithm: EBCCChecksumAlgorithm) -> Self {
+        self.checksum_algorithm = algorithm;
+        self
+    }
+
+    /// Change whether the encoders run single-threaded, e.g. to meet a
+    /// reproducibility policy that requires byte-identical archives from
+    /// identical inputs.
+    ///
+    /// `OpenJPEG` parallelises the encoding of the base layer according to
+    /// the `OPJ_NUM_THREADS` environment variable. In deterministic mode, the
+    /// vendored `OpenJPEG` library encodes with a single thread, regardless of
+    /// the variable and without modifying the environment. With a prebuilt or
+    /// system `OpenJPEG` library, whose thread count cannot be overridden, an
+    /// encode instead fails with [`EBCCError::InvalidConfig`] unless the
+    /// variable is unset or `1`. The lossless zstd compression already runs
+    /// single-threaded. Encoding many chunks in parallel, e.g. with
+    /// [`ebcc_encode_batch_parallel`][crate::ebcc_encode_batch_parallel], is
+    /// not affected, since every chunk is still encoded on one thread.
+    ///
+    /// The mode does not change the format of the compressed data, nor the
+    /// [`fingerprint`][Self::fingerprint] of the configuration.
+    #[must_use]
+    pub const fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Store a quantile sketch, i.e. the percentiles, of the original data of
+    /// every frame next to the compressed data.
+    ///
+    /// The sketches can be read with [`ebcc_inspect`][crate::ebcc_inspect]
+    /// without decoding anything, e.g. to approximate percentiles over many
+    /// frames. Each sketch adds about 400 bytes per frame.
+    #[must_use]
+    pub const fn with_quantile_sketch(mut self) -> Self {
+        self.quantile_sketch = true;
+        self
+    }
+
+    /// Snap the decoded values to the nearest multiple of the grid `step`,
+    /// e.g. `0.01` for temperatures in K, which is recorded with the
+    /// compressed data.
+    ///
+    /// The absolute or relative error bound still holds after snapping, and
+    /// is therefore tightened by half a `step` during encoding. The `step`
+    /// must be smaller than twice the error bound, and cannot be combined
+    /// with the [`EBCCBaseMode::Stored`].
+    #[must_use]
+    pub const fn with_output_quantization(mut self, step: f32) -> Self {
+        self.output_quantization = Some(step);
+        self
+    }
+
+    /// Conserve the mean of all frames, or of every frame, such that the
+    /// reconstruction has the same mean, and integral, as the original data
+    /// up to the rounding of its `f64` sum.
+    ///
+    /// The mean is conserved by shifting the reconstruction, so the absolute
+    /// or relative error bound is halved during encoding to still hold
+    /// afterwards. Conservation cannot be combined with the
+    /// [`output_quantization`][Self::output_quantization].
+    #[must_use]
+    pub const fn with_conservation(mut self, conservation: EBCCConservation) -> Self {
+        self.conservation = Some(conservation);
+        self
+    }
+
+    /// Clamp the decoded values to the inclusive physical value range from
+    /// `min` to `max`, e.g. `Some(0.0)` and `None` for non-negative
+    /// precipitation, which is recorded with the compressed data.
+    ///
+    /// The data must lie within the range, such that clamping only moves the
+    /// decoded values closer to it and the error bound still holds. The value
+    /// range cannot be combined with the
+    /// [`conservation`][Self::conservation] of the mean.
+    #[must_use]
+    pub const fn with_value_range(mut self, min: Option<f32>, max: Option<f32>) -> Self {
+        self.value_range = Some(EBCCValueRange::new(min, max));
+        self
+    }
+
+    /// Apply the custom [`EBCCStage`][crate::EBCCStage] that is registered
+    /// under the `id` to the data before encoding, which is recorded with
+    /// the compressed data.
+    ///
+    /// The stage must be registered with
+    /// [`ebcc_register_stage`][crate::ebcc_register_stage] when encoding and
+    /// when decoding. The stage cannot be combined with [`roi`][Self::roi]
+    /// weights.
+    #[must_use]
+    pub fn with_stage(mut self, id: impl Into<String>) -> Self {
+        self.stage = Some(id.into());
+        self
+    }
+
+    /// Code the residual of the base layer with the
+    /// [`ResidualCoder`][crate::ResidualCoder] with the `id`, instead of the
+    /// residual stage that is built into the EBCC C library, which is
+    /// recorded with the compressed data.
+    ///
+    /// The built-in [`EbccResidualStage`][crate::EbccResidualStage],
+    /// [`QuantizedResiduals`][crate::QuantizedResiduals], and
+    /// [`SparseCorrections`][crate::SparseCorrections] coders are always
+    /// available, while custom coders must be registered with
+    /// [`ebcc_register_residual_coder`][crate::ebcc_register_residual_coder]
+    /// when encoding and when decoding. A residual coder requires an
+    /// absolute or relative error bound and the [`EBCCBaseMode::Jpeg2000`],
+    /// and cannot be combined with [`roi`][Self::roi] weights. The base
+    /// layer is selected with [`with_base_codec`][Self::with_base_codec].
+    #[must_use]
+    pub fn with_residual_coder(mut self, id: impl Into<String>) -> Self {
+        self.residual_coder = Some(id.into());
+        self
+    }
+
+    /// Approximate the data with the `base_codec` before its residual is
+    /// coded, e.g. with the [`EBCCBaseCodec::Identity`] to skip the
+    /// `JPEG2000` stage.
+    ///
+    /// Without a [`residual_coder`][Self::residual_coder], a base codec
+    /// other than `JPEG2000` codes the residual with the
+    /// [`EbccResidualStage`][crate::EbccResidualStage], such that the
+    /// built-in residual stage can be benchmarked with and without the
+    /// `JPEG2000` base layer. It has the same requirements as a residual
+    /// coder.
+    #[must_use]
+    pub const fn with_base_codec(mut self, base_codec: EBCCBaseCodec) -> Self {
+        self.base_codec = base_codec;
+        self
+    }
+
+    /// Guard against compressed data that exceeds the raw size times the
+    /// guard's maximum ratio, either by failing or by storing the data
+    /// losslessly.
+    ///
+    /// The guard applies to [`ebcc_encode`][crate::ebcc_encode] and all
+    /// encoders built on top of it, but neither to chunked compression nor to
+    /// the [`EBCCBaseMode::Stored`].
+    #[must_use]
+    pub const fn with_expansion_guard(mut self, expansion_guard: EBCCExpansionGuard) -> Self {
+        self.expansion_guard = Some(expansion_guard);
+        self
+    }
+
+    /// Weigh the error bound of every pixel with the region-of-interest
+    /// `roi`, whose shape must match the `(height, width)` of the frames.
+    ///
+    /// Values whose reconstruction violates their weighted error bound are
+    /// stored verbatim, so the region of interest should be small compared
+    /// to the frames. The `roi` requires an absolute or relative error bound.
+    #[must_use]
+    pub fn with_roi(mut self, roi: EBCCRoi) -> Self {
+        self.roi = Some(roi);
+        self
+    }
+
+    /// Apply the invertible `transform` to the data before encoding, e.g. a
+    /// logarithmic one for data that spans orders of magnitude.
+    ///
+    /// The absolute or relative error bound still applies to the original
+    /// data and is mapped through the `transform`. The `transform` can
+    /// neither be combined with the [`EBCCBaseMode::Stored`] nor with
+    /// [`roi`][Self::roi] weights.
+    #[must_use]
+    pub const fn with_transform(mut self, transform: EBCCTransform) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// A guaranteed upper bound on the size of the output of
+    /// [`ebcc_encode`][crate::ebcc_encode] for data of the given
+    /// `(frames, height, width)` shape with this configuration.
+    ///
+    /// The bound allows pre-allocating output buffers, e.g. for
+    /// [`ebcc_encode_into_slice`][crate::ebcc_encode_into_slice], without a
+    /// trial encode. Since the EBCC C library does not bound the size of its
+    /// output, the bound is only tight for the [`EBCCBaseMode::Stored`] or
+    /// with an [`expansion_guard`][Self::expansion_guard], and is otherwise
+    /// the maximum output size of the [`limits`][Self::limits] plus the
+    /// [`EBCCHeader`][crate::EBCCHeader]. The postprocessing of the
+    /// [`quantile_sketch`][Self::quantile_sketch], the
+    /// [`value_range`][Self::value_range], the
+    /// [`output_quantization`][Self::output_quantization], and the
+    /// [`conservation`][Self::conservation] adds its fixed overhead.
+    ///
+    /// Returns [`None`] if the output size is not bounded, i.e. if neither
+    /// the configuration nor the limits bound it, or if the bound overflows a
+    /// `usize`.
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(feature = "encode", doc = "```rust")]
+    #[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+    /// use ebcc::{ebcc_encode, EBCCConfig, EBCCExpansionGuard};
+    /// use ndarray::Array;
+    ///
+    /// # fn main() -> ebcc::EBCCResult<()> {
+    /// let config = EBCCConfig::max_absolute_error_bounded(0.1)
+    ///     .with_expansion_guard(EBCCExpansionGuard::store_raw(1.0));
+    ///
+    /// let data = Array::from_elem((2, 32, 32), 1.0_f32);
+    /// let compressed = ebcc_encode(data.view(), &config)?;
+    /// let bound = config.max_compressed_size(data.dim());
+    /// assert!(bound.is_some_and(|bound| compressed.len() <= bound));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_compressed_size(&self, shape: (usize, usize, usize)) -> Option<usize> {
+        // the limits only bound the output if their maximum can be reached
+        let limit = Some(self.limits.max_output_bytes).filter(|limit| *limit < usize::MAX);
+
+        let payload = match (self.max_payload_size(shape), limit) {
+            (Some(payload), Some(limit)) => payload.min(limit),
+            (Some(bound), None) | (None, Some(bound)) => bound,
+            (None, None) => return None,
+        };
+
+        payload.checked_add(EBCCHeader::encoded_len_with(self.checksum_algorithm))
+    }
+
+    /// Upper bound on the size of the EBCC payload, without the
+    /// [`EBCCHeader`], or [`None`] if the size is not bounded.
+    fn max_payload_size(&self, shape: (usize, usize, usize)) -> Option<usize> {
+        let (frames, height, width) = shape;
+        let elements = frames.checked_mul(height)?.checked_mul(width)?;
+
+        // the postprocessing wraps the payload without the postprocessing
+        let (overhead, inner) = if self.quantile_sketch {
+            let sketches = frames.checked_mul(SKETCH_QUANTILES * size_of::<f32>())?;
+            let inner = Self {
+                quantile_sketch: false,
+                ..self.clone()
+            };
+            (EBCC_SKETCH_HEADER_LEN.checked_add(sketches)?, inner)
+        } else if self.value_range.is_some() {
+            let inner = Self {
+                value_range: None,
+                ..self.clone()
+            };
+            (EBCC_CLAMP_HEADER_LEN, inner)
+        } else if self.output_quantization.is_some() {
+            let inner = Self {
+                output_quantization: None,
+                ..self.clone()
+            };
+            (EBCC_QUANTIZE_HEADER_LEN, inner)
+        } else if let Some(conservation) = self.conservation {
+            let sums = match conservation {
+                EBCCConservation::Global => 1,
+                EBCCConservation::PerFrame => frames,
+            };
+            let inner = Self {
+                conservation: None,
+                ..self.clone()
+            };
+            let overhead = sums.checked_mul(size_of::<f64>())?;
+            (EBCC_CONSERVE_HEADER_LEN.checked_add(overhead)?, inner)
+        } else {
+            if self.base_mode == EBCCBaseMode::Stored && self.stage.is_none() {
+                return stored_size_bound(elements, self.stored_compression);
+            }
+
+            // all other payloads, including the stored-raw fallback, are
+            //  limited by the expansion guard
+            let EBCCExpansionGuard { max_ratio, .. } = self.expansion_guard?;
+            #[expect(
+                clippy::cast_possible_truncation,
+                clippy::cast_precision_loss,
+                clippy::cast_sign_loss
+            )]
+            let guarded = ((elements as f64) * (size_of::<f32>() as f64) * f64::from(max_ratio))
+                .floor() as usize;
+            return Some(guarded);
+        };
+
+        overhead.checked_add(inner.max_payload_size(shape)?)
+    }
+
+    /// A stable 64-bit fingerprint of the parameters that determine the
+    /// compressed bitstream, i.e. the [`base_cr`][Self::base_cr], the
+    /// [`base_mode`][Self::base_mode], the
+    /// [`residual_compression_type`][Self::residual_compression_type], the
+    /// [`roi`][Self::roi] weights, the [`transform`][Self::transform], the
+    /// [`quantile_sketch`][Self::quantile_sketch] flag, the
+    /// [`output_quantization`][Self::output_quantization] step, the
+    /// [`conservation`][Self::conservation] mode, the
+    /// [`value_range`][Self::value_range], the custom
+    /// [`stage`][Self::stage] ID, the
+    /// [`residual_coder`][Self::residual_coder] ID, the
+    /// [`base_codec`][Self::base_codec], and the
+    /// [`stored_compression`][Self::stored_compression] of stored data.
+    ///
+    /// The [`check_finite`][Self::check_finite] and
+    /// [`checksum_decompressed`][Self::checksum_decompressed] flags, the
+    /// [`checksum_algorithm`][Self::checksum_algorithm], and the
+    /// [`limits`][Self::limits] do not change the compressed bitstream and
+    /// are therefore not part of the fingerprint.
+    ///
+    /// The [`expansion_guard`][Self::expansion_guard] is not part of the
+    /// fingerprint either, even though the
+    /// [`StoreRaw`][EBCCExpansionFallback::StoreRaw] fallback replaces the
+    /// bitstream of incompressible data with stored data. It only decides
+    /// whether the compressed bitstream is kept, and the header of the stored
+    /// data still records the fingerprint of the configuration that failed to
+    /// compress it, such that data compressed with the same settings but
+    /// different guards shares the fingerprint.
+    ///
+    /// Every optional parameter is hashed behind its own tag, and the
+    /// variable-length ones behind their length as well, such that
+    /// configurations that differ in any of them have different fingerprints.
+    ///
+    /// The fingerprint is stored in the header of every
+    /// [`ebcc_encode`][crate::ebcc_encode] payload, of EBCC frame streams, and
+    /// of [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] data. Data of
+    /// a header, stream, or tiled data version before 3 was compressed before
+    /// the fingerprint tagged its parameters and records no comparable
+    /// fingerprint.
+    #[must_use]
+    pub fn fingerprint(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let residual_type: u8 = match self.residual_compression_type {
+            EBCCResidualType::Jpeg2000Only => 0,
+            EBCCResidualType::AbsoluteError(_) => 1,
+            EBCCResidualType::RelativeError(_) => 2,
+        };
+
+        // 64-bit FNV-1a, which is stable across platforms and releases
+        std::iter::once(residual_type)
+            .chain(self.base_cr.to_bits().to_le_bytes())
+            .chain(
+                self.residual_compression_type
+                    .as_error()
+                    .to_bits()
+                    .to_le_bytes(),
+            )
+            .chain(self.fingerprint_options().into_iter().flatten().flatten())
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// The encoded optional parameters of the
+    /// [`fingerprint`][Self::fingerprint].
+    ///
+    /// Every parameter is encoded behind its own tag byte, and variable-length
+    /// parameters additionally behind their length, such that the encodings
+    /// of different configurations never coincide. Unset parameters and the
+    /// defaults of the base mode, base codec, and stored compression are not
+    /// encoded.
+    fn fingerprint_options(&self) -> [Option<Vec<u8>>; 11] {
+        fn tagged(tag: u8, bytes: &[u8]) -> Vec<u8> {
+            std::iter::once(tag).chain(bytes.iter().copied()).collect()
+        }
+
+        fn framed(tag: u8, bytes: &[u8]) -> Vec<u8> {
+            std::iter::once(tag)
+                .chain((bytes.len() as u64).to_le_bytes())
+                .chain(bytes.iter().copied())
+                .collect()
+        }
+
+        let base_mode = match self.base_mode {
+            EBCCBaseMode::Jpeg2000 => None,
+            EBCCBaseMode::None => Some(tagged(b'M', &[1])),
+            EBCCBaseMode::Stored => Some(tagged(b'M', &[2])),
+            EBCCBaseMode::Layered => Some(tagged(b'M', &[3])),
+        };
+        let base_codec = match self.base_codec {
+            EBCCBaseCodec::Jpeg2000 => None,
+            EBCCBaseCodec::Identity => Some(tagged(b'B', b"I")),
+        };
+        // the stored compression only changes the bitstream of stored data
+        let stored_compression = match (self.base_mode, self.stored_compression) {
+            (EBCCBaseMode::Stored, EBCCStoredCompression::None) => Some(tagged(b'Z', &[0])),
+            (EBCCBaseMode::Stored, EBCCStoredCompression::Zstd) => Some(tagged(b'Z', &[1])),
+            _ => None,
+        };
+        let roi = self.roi.as_ref().map(|roi| {
+            let weights = roi.weights();
+            let [height, width] = [weights.nrows(), weights.ncols()].map(|dim| dim as u64);
+            let bytes = height
+                .to_le_bytes()
+                .into_iter()
+                .chain(width.to_le_bytes())
+                .chain(
+                    weights
+                        .into_iter()
+                        .flat_map(|weight| weight.to_bits().to_le_bytes()),
+                )
+                .collect::<Vec<_>>();
+            framed(b'W', &bytes)
+        });
+        let transform = self.transform.map(|transform| {
+            let (kind, scale, offset) = match transform {
+                EBCCTransform::Linear { scale, offset } => (0_u8, scale, offset),
+                EBCCTransform::Log1p => (1_u8, 0.0, 0.0),
+                EBCCTransform::SignedLog => (2_u8, 0.0, 0.0),
+            };
+            let bytes = std::iter::once(kind)
+                .chain(scale.to_bits().to_le_bytes())
+                .chain(offset.to_bits().to_le_bytes())
+                .collect::<Vec<_>>();
+            tagged(b'T', &bytes)
+        });
+        let quantile_sketch = self.quantile_sketch.then(|| tagged(b'Q', &[]));
+        let output_quantization = self
+            .output_quantization
+            .map(|step| tagged(b'O', &step.to_bits().to_le_bytes()));
+        let conservation = self.conservation.map(|conservation| match conservation {
+            EBCCConservation::Global => tagged(b'C', &[0]),
+            EBCCConservation::PerFrame => tagged(b'C', &[1]),
+        });
+        let value_range = self.value_range.map(|range| {
+            let bytes = [range.min, range.max]
+                .into_iter()
+                .flat_map(|bound| {
+                    std::iter::once(u8::from(bound.is_some()))
+                        .chain(bound.unwrap_or(0.0).to_bits().to_le_bytes())
+                })
+                .collect::<Vec<_>>();
+            tagged(b'V', &bytes)
+        });
+        let stage = self.stage.as_ref().map(|id| framed(b'S', id.as_bytes()));
+        let residual_coder = self
+            .residual_coder
+            .as_ref()
+            .map(|id| framed(b'R', id.as_bytes()));
+
+        [
+            base_mode,
+            stored_compression,
+            roi,
+            transform,
+            quantile_sketch,
+            output_quantization,
+            conservation,
+            value_range,
+            stage,
+            residual_coder,
+            base_codec,
+        ]
+    }
+
+    /// Check that this configuration has the `fingerprint` that is recorded
+    /// in the compressed data, if any.
+    pub(crate) fn check_fingerprint(&self, fingerprint: Option<u64>) -> EBCCResult<()> {
+        match fingerprint {
+            Some(fingerprint) if fingerprint != self.fingerprint() => {
+                Err(EBCCError::InvalidConfig(String::from(
+                    "The source configuration does not match the configuration fingerprint of \
+                     the compressed data",
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Validate the configuration parameters.
     ///
     /// # Errors
     ///
-    /// - [`EBCCError::InvalidConfig`] if `base_cr` is non-positive
-    /// - [`EBCCError::InvalidConfig`] if the absolute or relative error bound
-    ///   is non-positive
+    /// - [`EBCCError::NonPositiveBaseCR`] if `base_cr` is non-positive
+    /// - [`EBCCError::NonPositiveErrorBound`] if the absolute or relative error
+    ///   bound is non-positive
+    /// - [`EBCCError::InvalidConfig`] if the [`EBCCBaseMode::None`] or the
+    ///   [`EBCCBaseMode::Layered`] is used without an absolute or relative
+    ///   error bound, or if the maximum ratio
+    ///   of the [`expansion_guard`][Self::expansion_guard] is not finite and
+    ///   positive
+    /// - [`EBCCError::InvalidConfig`] if the [`roi`][Self::roi] weights are
+    ///   empty or not finite and positive, or are used without an absolute or
+    ///   relative error bound
+    /// - [`EBCCError::InvalidConfig`] if the parameters of the
+    ///   [`transform`][Self::transform] are not finite, or if it is combined
+    ///   with the [`EBCCBaseMode::Stored`] or with [`roi`][Self::roi] weights
+    /// - [`EBCCError::InvalidConfig`] if the
+    ///   [`output_quantization`][Self::output_quantization] step is not finite
+    ///   and positive, is not smaller than twice the absolute error bound, or
+    ///   is combined with the [`EBCCBaseMode::Stored`] or with the
+    ///   [`conservation`][Self::conservation] of the mean
+    /// - [`EBCCError::InvalidConfig`] if the [`value_range`][Self::value_range]
+    ///   has no bounds, a NaN bound, or a minimum above its maximum, or is
+    ///   combined with the [`conservation`][Self::conservation] of the mean
+    /// - [`EBCCError::InvalidConfig`] if the custom [`stage`][Self::stage] ID
+    ///   is empty or longer than `u16::MAX` bytes, or is combined with
+    ///   [`roi`][Self::roi] weights
+    /// - [`EBCCError::InvalidConfig`] if the
+    ///   [`residual_coder`][Self::residual_coder] ID is empty or longer than
+    ///   `u16::MAX` bytes, or if it or a [`base_codec`][Self::base_codec]
+    ///   other than `JPEG2000` is used without an absolute or relative error
+    ///   bound, with a [`base_mode`][Self::base_mode] other than
+    ///   [`EBCCBaseMode::Jpeg2000`], or with [`roi`][Self::roi] weights
     pub fn validate(&self) -> EBCCResult<()> {
-        // Check compression ratio
-        if self.base_cr <= 0.0 {
-            return Err(EBCCError::InvalidConfig(String::from(
-                "Base compression ratio must be positive",
-            )));
-        }
+        validate_base_cr(self.base_cr)?;
 
         // Check residual-specific parameters
         match self.residual_compression_type {
             EBCCResidualType::AbsoluteError(error) | EBCCResidualType::RelativeError(error) => {
-                if error <= 0.0 {
-                    return Err(EBCCError::InvalidConfig(String::from(
-                        "Error bound must be positive",
-                    )));
+                validate_error_bound(error)?;
+            }
+            EBCCResidualType::Jpeg2000Only => match self.base_mode {
+                EBCCBaseMode::None => return Err(residual_only_requires_error_bound()),
+                EBCCBaseMode::Layered => return Err(layered_requires_error_bound()),
+                EBCCBaseMode::Jpeg2000 | EBCCBaseMode::Stored => (),
+            },
+        }
+
+        if let Some(EBCCExpansionGuard { max_ratio, .. }) = self.expansion_guard {
+            if !(max_ratio.is_finite() && max_ratio > 0.0) {
+                return Err(EBCCError::InvalidConfig(format!(
+                    "Expansion guard ratio must be finite and positive, got {max_ratio}"
+                )));
+            }
+        }
+
+        if let Some(roi) = &self.roi {
+            roi.validate()?;
+            if self.residual_compression_type == EBCCResidualType::Jpeg2000Only {
+                return Err(roi_requires_error_bound());
+            }
+        }
+
+        if let Some(transform) = self.transform {
+            transform.validate()?;
+            if self.base_mode == EBCCBaseMode::Stored || self.roi.is_some() {
+                return Err(EBCCError::InvalidConfig(String::from(
+                    "Transforms cannot be combined with stored data or ROI weights",
+                )));
+            }
+        }
+
+        if let Some(step) = self.output_quantization {
+            if !(step.is_finite() && step > 0.0) {
+                return Err(EBCCError::InvalidConfig(format!(
+                    "Output quantization step must be finite and positive, got {step}"
+                )));
+            }
+            if self.base_mode == EBCCBaseMode::Stored || self.conservation.is_some() {
+                return Err(EBCCError::InvalidConfig(String::from(
+                    "Output quantization cannot be combined with stored data or conservation",
+                )));
+            }
+            if let EBCCResidualType::AbsoluteError(error) = self.residual_compression_type {
+                if step >= error * 2.0 {
+                    return Err(quantization_step_too_large(step));
                 }
             }
-            EBCCResidualType::Jpeg2000Only => {
-                // No additional validation needed
+        }
+
+        if let Some(range) = self.value_range {
+            range.validate()?;
+            if self.conservation.is_some() {
+                return Err(EBCCError::InvalidConfig(String::from(
+                    "Value ranges cannot be combined with conservation",
+                )));
+            }
+        }
+
+        if let Some(id) = &self.stage {
+            validate_stage_id(id)?;
+            if self.roi.is_some() {
+                return Err(EBCCError::InvalidConfig(String::from(
+                    "Custom stages cannot be combined with ROI weights",
+                )));
+            }
+        }
+
+        if let Some(id) = &self.residual_coder {
+            validate_residual_coder_id(id)?;
+        }
+        if self.residual_coder.is_some() || self.base_codec != EBCCBaseCodec::Jpeg2000 {
+            if self.residual_compression_type == EBCCResidualType::Jpeg2000Only {
+                return Err(residual_coder_requires_error_bound());
+            }
+            if self.base_mode != EBCCBaseMode::Jpeg2000 || self.roi.is_some() {
+                return Err(EBCCError::InvalidConfig(String::from(
+                    "Residual coders and base codecs require the JPEG2000 base mode and cannot be \
+                     combined with ROI weights",
+                )));
             }
         }
 
         Ok(())
     }
 }
+
+/// Override of an optional field of an [`EBCCConfig`] by an
+/// [`EBCCConfigOverride`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EBCCOverride<T> {
+    /// Inherit the value, if any, of the parent configuration
+    #[default]
+    Inherit,
+    /// Set the value, regardless of the parent configuration
+    Set(T),
+    /// Clear the value, regardless of the parent configuration
+    Clear,
+}
+
+impl<T: Clone> EBCCOverride<T> {
+    /// Apply this override on top of the `parent` value.
+    #[must_use]
+    pub fn inherit(&self, parent: Option<&T>) -> Option<T> {
+        match self {
+            Self::Inherit => parent.cloned(),
+            Self::Set(value) => Some(value.clone()),
+            Self::Clear => None,
+        }
+    }
+}
+
+/// Partial EBCC configuration whose unspecified fields are inherited from a
+/// parent [`EBCCConfig`].
+///
+/// Optional fields of the parent configuration, e.g. its
+/// [`transform`][EBCCConfig::transform], can be inherited, set, or cleared
+/// with an [`EBCCOverride`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EBCCConfigOverride {
+    /// Base compression ratio for JPEG2000 layer
+    pub base_cr: Option<f32>,
+
+    /// Base layer
+    pub base_mode: Option<EBCCBaseMode>,
+
+    /// Type of residual compression to apply
+    pub residual_compression_type: Option<EBCCResidualType>,
+
+    /// Whether the input data is checked for non-finite values before
+    /// compression
+    pub check_finite: Option<bool>,
+
+    /// Limits on the number and size of frames that are encoded
+    pub limits: Option<EBCCLimits>,
+
+    /// Whether a checksum of the decompressed data is stored in the
+    /// [`EBCCHeader`][crate::EBCCHeader] and verified when decoding
+    pub checksum_decompressed: Option<bool>,
+
+    /// Algorithm of the checksum of the compressed payload
+    pub checksum_algorithm: Option<EBCCChecksumAlgorithm>,
+
+    /// Guard against compressed data that is larger than the raw data
+    pub expansion_guard: EBCCOverride<EBCCExpansionGuard>,
+
+    /// Per-pixel weights of the error bound
+    pub roi: EBCCOverride<EBCCRoi>,
+
+    /// Lossless compression of stored data
+    pub stored_compression: Option<EBCCStoredCompression>,
+
+    /// Invertible transform that is applied to the data before encoding
+    pub transform: EBCCOverride<EBCCTransform>,
+
+    /// Whether a quantile sketch of every frame is stored
+    pub quantile_sketch: Option<bool>,
+
+    /// Grid step to which the decoded values are snapped
+    pub output_quantization: EBCCOverride<f32>,
+
+    /// Mean that is conserved by the reconstruction
+    pub conservation: EBCCOverride<EBCCConservation>,
+
+    /// Physical value range to which the decoded values are clamped
+    pub value_range: EBCCOverride<EBCCValueRange>,
+
+    /// ID of a registered custom stage that is applied before encoding
+    pub stage: EBCCOverride<String>,
+
+    /// ID of the residual coder of the base layer
+    pub residual_coder: EBCCOverride<String>,
+
+    /// Codec of the base layer under a residual coder
+    pub base_codec: Option<EBCCBaseCodec>,
+
+    /// Whether the encoders run single-threaded
+    pub deterministic: Option<bool>,
+}
+
+impl EBCCConfigOverride {
+    /// Create a new configuration override that inherits all fields.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            base_cr: None,
+            base_mode: None,
+            residual_compression_type: None,
+            check_finite: None,
+            limits: None,
+            checksum_decompressed: None,
+            checksum_algorithm: None,
+            expansion_guard: EBCCOverride::Inherit,
+            roi: EBCCOverride::Inherit,
+            stored_compression: None,
+            transform: EBCCOverride::Inherit,
+            quantile_sketch: None,
+            output_quantization: EBCCOverride::Inherit,
+            conservation: EBCCOverride::Inherit,
+            value_range: EBCCOverride::Inherit,
+            stage: EBCCOverride::Inherit,
+            residual_coder: EBCCOverride::Inherit,
+            base_codec: None,
+            deterministic: None,
+        }
+    }
+
+    /// Override the JPEG2000 layer base compression ratio.
+    #[must_use]
+    pub const fn with_base_cr(mut self, base_cr: f32) -> Self {
+        self.base_cr = Some(base_cr);
+        self
+    }
+
+    /// Override the base layer of EBCC compression.
+    #[must_use]
+    pub const fn with_base_mode(mut self, base_mode: EBCCBaseMode) -> Self {
+        self.base_mode = Some(base_mode);
+        self
+    }
+
+    /// Override the type of residual compression.
+    #[must_use]
+    pub const fn with_residual_compression_type(
+        mut self,
+        residual_compression_type: EBCCResidualType,
+    ) -> Self {
+        self.residual_compression_type = Some(residual_compression_type);
+        self
+    }
+
+    /// Override whether the input data is checked for non-finite values
+    /// before compression.
+    #[must_use]
+    pub const fn with_check_finite(mut self, check_finite: bool) -> Self {
+        self.check_finite = Some(check_finite);
+        self
+    }
+
+    /// Override the limits on the number and size of frames that are
+    /// encoded.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: EBCCLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Override whether a checksum of the decompressed data is stored.
+    #[must_use]
+    pub const fn with_checksum_decompressed(mut self, checksum_decompressed: bool) -> Self {
+        self.checksum_decompressed = Some(checksum_decompressed);
+        self
+    }
+
+    /// Override the algorithm of the checksum of the compressed payload.
+    #[must_use]
+    pub const fn with_checksum_algorithm(mut self, algorithm: EBCCChecksumAlgorithm) -> Self {
+        self.checksum_algorithm = Some(algorithm);
+        self
+    }
+
+    /// Override the guard against compressed data that is larger than the
+    /// raw data.
+    #[must_use]
+    pub const fn with_expansion_guard(mut self, expansion_guard: EBCCExpansionGuard) -> Self {
+        self.expansion_guard = EBCCOverride::Set(expansion_guard);
+        self
+    }
+
+    /// Disable the guard against compressed data that is larger than the raw
+    /// data, even if the parent configuration has one.
+    #[must_use]
+    pub const fn without_expansion_guard(mut self) -> Self {
+        self.expansion_guard = EBCCOverride::Clear;
+        self
+    }
+
+    /// Override the region-of-interest weights of the error bound.
+    #[must_use]
+    pub fn with_roi(mut self, roi: EBCCRoi) -> Self {
+        self.roi = EBCCOverride::Set(roi);
+        self
+    }
+
+    /// Remove the region-of-interest weights of the error bound, even if the
+    /// parent configuration has them.
+    #[must_use]
+    pub fn without_roi(mut self) -> Self {
+        self.roi = EBCCOverride::Clear;
+        self
+    }
+
+    /// Override the lossless compression of stored data.
+    #[must_use]
+    pub const fn with_stored_compression(
+        mut self,
+        stored_compression: EBCCStoredCompression,
+    ) -> Self {
+        self.stored_compression = Some(stored_compression);
+        self
+    }
+
+    /// Override the transform that is applied to the data before encoding.
+    #[must_use]
+    pub const fn with_transform(mut self, transform: EBCCTransform) -> Self {
+        self.transform = EBCCOverride::Set(transform);
+        self
+    }
+
+    /// Remove the transform that is applied to the data before encoding,
+    /// even if the parent configuration has one.
+    #[must_use]
+    pub const fn without_transform(mut self) -> Self {
+        self.transform = EBCCOverride::Clear;
+        self
+    }
+
+    /// Override whether a quantile sketch of every frame is stored.
+    #[must_use]
+    pub const fn with_quantile_sketch(mut self, quantile_sketch: bool) -> Self {
+        self.quantile_sketch = Some(quantile_sketch);
+        self
+    }
+
+    /// Override the grid step to which the decoded values are snapped.
+    #[must_use]
+    pub const fn with_output_quantization(mut self, step: f32) -> Self {
+        self.output_quantization = EBCCOverride::Set(step);
+        self
+    }
+
+    /// Stop snapping the decoded values to a grid, even if the parent
+    /// configuration does.
+    #[must_use]
+    pub const fn without_output_quantization(mut self) -> Self {
+        self.output_quantization = EBCCOverride::Clear;
+        self
+    }
+
+    /// Override the mean that is conserved by the reconstruction.
+    #[must_use]
+    pub const fn with_conservation(mut self, conservation: EBCCConservation) -> Self {
+        self.conservation = EBCCOverride::Set(conservation);
+        self
+    }
+
+    /// Stop conserving a mean in the reconstruction, even if the parent
+    /// configuration does.
+    #[must_use]
+    pub const fn without_conservation(mut self) -> Self {
+        self.conservation = EBCCOverride::Clear;
+        self
+    }
+
+    /// Override the physical value range to which the decoded values are
+    /// clamped.
+    #[must_use]
+    pub const fn with_value_range(mut self, min: Option<f32>, max: Option<f32>) -> Self {
+        self.value_range = EBCCOverride::Set(EBCCValueRange::new(min, max));
+        self
+    }
+
+    /// Stop clamping the decoded values to a value range, even if the parent
+    /// configuration does.
+    #[must_use]
+    pub const fn without_value_range(mut self) -> Self {
+        self.value_range = EBCCOverride::Clear;
+        self
+    }
+
+    /// Override the ID of the custom stage that is applied before encoding.
+    #[must_use]
+    pub fn with_stage(mut self, id: impl Into<String>) -> Self {
+        self.stage = EBCCOverride::Set(id.into());
+        self
+    }
+
+    /// Remove the custom stage that is applied before encoding, even if the
+    /// parent configuration has one.
+    #[must_use]
+    pub fn without_stage(mut self) -> Self {
+        self.stage = EBCCOverride::Clear;
+        self
+    }
+
+    /// Override the ID of the residual coder of the base layer.
+    #[must_use]
+    pub fn with_residual_coder(mut self, id: impl Into<String>) -> Self {
+        self.residual_coder = EBCCOverride::Set(id.into());
+        self
+    }
+
+    /// Remove the residual coder of the base layer, even if the parent
+    /// configuration has one.
+    #[must_use]
+    pub fn without_residual_coder(mut self) -> Self {
+        self.residual_coder = EBCCOverride::Clear;
+        self
+    }
+
+    /// Override the codec of the base layer under a residual coder.
+    #[must_use]
+    pub const fn with_base_codec(mut self, base_codec: EBCCBaseCodec) -> Self {
+        self.base_codec = Some(base_codec);
+        self
+    }
+
+    /// Override whether the encoders run single-threaded.
+    #[must_use]
+    pub const fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = Some(deterministic);
+        self
+    }
+
+    /// Apply this override on top of the `parent` configuration, inheriting
+    /// all fields that it does not specify.
+    #[must_use]
+    pub fn inherit(&self, parent: &EBCCConfig) -> EBCCConfig {
+        EBCCConfig {
+            base_cr: self.base_cr.unwrap_or(parent.base_cr),
+            base_mode: self.base_mode.unwrap_or(parent.base_mode),
+            residual_compression_type: self
+                .residual_compression_type
+                .unwrap_or(parent.residual_compression_type),
+            check_finite: self.check_finite.unwrap_or(parent.check_finite),
+            limits: self.limits.unwrap_or(parent.limits),
+            checksum_decompressed: self
+                .checksum_decompressed
+                .unwrap_or(parent.checksum_decompressed),
+            checksum_algorithm: self.checksum_algorithm.unwrap_or(parent.checksum_algorithm),
+            expansion_guard: self
+                .expansion_guard
+                .inherit(parent.expansion_guard.as_ref()),
+            roi: self.roi.inherit(parent.roi.as_ref()),
+            stored_compression: self.stored_compression.unwrap_or(parent.stored_compression),
+            transform: self.transform.inherit(parent.transform.as_ref()),
+            quantile_sketch: self.quantile_sketch.unwrap_or(parent.quantile_sketch),
+            output_quantization: self
+                .output_quantization
+                .inherit(parent.output_quantization.as_ref()),
+            conservation: self.conservation.inherit(parent.conservation.as_ref()),
+            value_range: self.value_range.inherit(parent.value_range.as_ref()),
+            stage: self.stage.inherit(parent.stage.as_ref()),
+            residual_coder: self.residual_coder.inherit(parent.residual_coder.as_ref()),
+            base_codec: self.base_codec.unwrap_or(parent.base_codec),
+            deterministic: self.deterministic.unwrap_or(parent.deterministic),
+        }
+    }
+}
@@ -0,0 +1,522 @@
+//! Binary serialization of EBCC configurations.
+//!
+//! Unlike the [`fingerprint`][EBCCConfig::fingerprint], which only hashes
+//! the parameters that determine the compressed bitstream, the serialized
+//! configuration records every field, such that a configuration can be
+//! stored next to the data, e.g. in a reproduction bundle, and be restored
+//! exactly.
+//!
+//! # Format
+//!
+//! All integers are stored in little-endian byte order. Optional values
+//! start with a `u8` that is `1` if the value is present and `0` otherwise.
+//!
+//! - header: [`EBCC_CONFIG_MAGIC`] and the format version as `u32`
+//! - the [`base_cr`][EBCCConfig::base_cr] as `f32`, the
+//!   [`base_mode`][EBCCConfig::base_mode] as `u8`, and the
+//!   [`residual_compression_type`][EBCCConfig::residual_compression_type] as
+//!   a `u8` kind and an `f32` error bound
+//! - the boolean options as `u8` flags and the
+//!   [`checksum_algorithm`][EBCCConfig::checksum_algorithm] code as `u32`
+//! - the [`limits`][EBCCConfig::limits], with sizes as `u64`s, the optional
+//!   timeout as `u64` seconds and `u32` nanoseconds, and the optional
+//!   maximum number of threads as `u64`
+//! - the optional [`expansion_guard`][EBCCConfig::expansion_guard] as an
+//!   `f32` ratio and a `u8` fallback
+//! - the optional [`roi`][EBCCConfig::roi] weights as their `u64` height and
+//!   width followed by the `f32` weights in standard order
+//! - the [`stored_compression`][EBCCConfig::stored_compression] as `u8`,
+//!   the optional [`transform`][EBCCConfig::transform] as a `u8` kind and
+//!   two `f32` parameters, the optional
+//!   [`output_quantization`][EBCCConfig::output_quantization] step as `f32`,
+//!   the optional [`conservation`][EBCCConfig::conservation] as `u8`, and
+//!   the optional [`value_range`][EBCCConfig::value_range] bounds as `f32`s
+//! - the optional [`stage`][EBCCConfig::stage] and
+//!   [`residual_coder`][EBCCConfig::residual_coder] IDs as their `u64`
+//!   length followed by their UTF-8 bytes
+//! - since version 2, the [`base_codec`][EBCCConfig::base_codec] as `u8`
+
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use ndarray::Array2;
+
+use crate::checksum::EBCCChecksumAlgorithm;
+use crate::clamp::EBCCValueRange;
+use crate::config::{
+    EBCCBaseCodec, EBCCBaseMode, EBCCConfig, EBCCExpansionFallback, EBCCExpansionGuard,
+    EBCCResidualType, EBCCStoredCompression,
+};
+use crate::conserve::EBCCConservation;
+use crate::error::{EBCCError, EBCCResult};
+use crate::limits::EBCCLimits;
+use crate::reader::Format;
+use crate::roi::EBCCRoi;
+use crate::size::u64_to_usize;
+use crate::transform::EBCCTransform;
+
+/// Magic bytes at the start of every serialized EBCC configuration.
+pub const EBCC_CONFIG_MAGIC: &[u8; 8] = b"EBCCCONF";
+
+/// Version of the serialized EBCC configuration format.
+const EBCC_CONFIG_VERSION: u32 = 2;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("Serialized EBCC configuration");
+
+const FLAG_CHECK_FINITE: u8 = 1;
+const FLAG_CHECKSUM_DECOMPRESSED: u8 = 2;
+const FLAG_QUANTILE_SKETCH: u8 = 4;
+const FLAG_DETERMINISTIC: u8 = 8;
+
+impl EBCCConfig {
+    /// Serialize every field of the configuration into a self-describing
+    /// binary format, which [`EBCCConfig::from_bytes`] restores.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ebcc::EBCCConfig;
+    ///
+    /// # fn main() -> ebcc::EBCCResult<()> {
+    /// let config = EBCCConfig::max_absolute_error_bounded(0.1).with_quantile_sketch();
+    /// assert_eq!(EBCCConfig::from_bytes(&config.to_bytes())?, config);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::from(EBCC_CONFIG_MAGIC.as_slice());
+        bytes.extend_from_slice(&EBCC_CONFIG_VERSION.to_le_bytes());
+
+        bytes.extend_from_slice(&self.base_cr.to_le_bytes());
+        bytes.push(match self.base_mode {
+            EBCCBaseMode::Jpeg2000 => 0,
+            EBCCBaseMode::None => 1,
+            EBCCBaseMode::Stored => 2,
+            EBCCBaseMode::Layered => 3,
+        });
+        bytes.push(match self.residual_compression_type {
+            EBCCResidualType::Jpeg2000Only => 0,
+            EBCCResidualType::AbsoluteError(_) => 1,
+            EBCCResidualType::RelativeError(_) => 2,
+        });
+        bytes.extend_from_slice(&self.residual_compression_type.as_error().to_le_bytes());
+
+        let flags = [
+            (self.check_finite, FLAG_CHECK_FINITE),
+            (self.checksum_decompressed, FLAG_CHECKSUM_DECOMPRESSED),
+            (self.quantile_sketch, FLAG_QUANTILE_SKETCH),
+            (self.deterministic, FLAG_DETERMINISTIC),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, flag)| enabled.then_some(flag))
+        .fold(0, |flags, flag| flags | flag);
+        bytes.push(flags);
+        bytes.extend_from_slice(&self.checksum_algorithm.code().to_le_bytes());
+
+        write_limits(&mut bytes, &self.limits);
+
+        write_option(&mut bytes, self.expansion_guard, |bytes, guard| {
+            bytes.extend_from_slice(&guard.max_ratio.to_le_bytes());
+            bytes.push(match guard.fallback {
+                EBCCExpansionFallback::Error => 0,
+                EBCCExpansionFallback::StoreRaw => 1,
+            });
+        });
+
+        write_option(&mut bytes, self.roi.as_ref(), |bytes, roi| {
+            let weights = roi.weights();
+            bytes.extend_from_slice(&(weights.nrows() as u64).to_le_bytes());
+            bytes.extend_from_slice(&(weights.ncols() as u64).to_le_bytes());
+            bytes.extend(weights.iter().flat_map(|weight| weight.to_le_bytes()));
+        });
+
+        bytes.push(match self.stored_compression {
+            EBCCStoredCompression::None => 0,
+            EBCCStoredCompression::Zstd => 1,
+        });
+
+        write_option(&mut bytes, self.transform, |bytes, transform| {
+            let (kind, scale, offset) = match transform {
+                EBCCTransform::Linear { scale, offset } => (0_u8, scale, offset),
+                EBCCTransform::Log1p => (1_u8, 0.0, 0.0),
+                EBCCTransform::SignedLog => (2_u8, 0.0, 0.0),
+            };
+            bytes.push(kind);
+            bytes.extend_from_slice(&scale.to_le_bytes());
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        });
+
+        write_option(&mut bytes, self.output_quantization, |bytes, step| {
+            bytes.extend_from_slice(&step.to_le_bytes());
+        });
+
+        write_option(&mut bytes, self.conservation, |bytes, conservation| {
+            bytes.push(match conservation {
+                EBCCConservation::Global => 0,
+                EBCCConservation::PerFrame => 1,
+            });
+        });
+
+        write_option(&mut bytes, self.value_range, |bytes, range| {
+            write_option(bytes, range.min, |bytes, min| {
+                bytes.extend_from_slice(&min.to_le_bytes());
+            });
+            write_option(bytes, range.max, |bytes, max| {
+                bytes.extend_from_slice(&max.to_le_bytes());
+            });
+        });
+
+        write_option(&mut bytes, self.stage.as_deref(), write_str);
+        write_option(&mut bytes, self.residual_coder.as_deref(), write_str);
+
+        bytes.push(match self.base_codec {
+            EBCCBaseCodec::Jpeg2000 => 0,
+            EBCCBaseCodec::Identity => 1,
+        });
+
+        bytes
+    }
+
+    /// Restore a configuration that was serialized with
+    /// [`EBCCConfig::to_bytes`].
+    ///
+    /// The restored configuration is not
+    /// [validated][EBCCConfig::validate], such that an invalid configuration
+    /// that was serialized, e.g. the one of a failed call, is restored as it
+    /// was.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the `bytes` are not a serialized
+    ///   configuration, are truncated or corrupted, or have trailing bytes
+    /// - [`EBCCError::DecompressionError`] if the checksum algorithm is not
+    ///   supported by this build
+    pub fn from_bytes(bytes: &[u8]) -> EBCCResult<Self> {
+        let Some(mut reader) = bytes.strip_prefix(EBCC_CONFIG_MAGIC.as_slice()) else {
+            return Err(EBCCError::InvalidInput(String::from(
+                "Serialized EBCC configuration does not start with the expected magic bytes",
+            )));
+        };
+
+        let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+        if !(1..=EBCC_CONFIG_VERSION).contains(&version) {
+            return Err(EBCCError::InvalidInput(format!(
+                "Unsupported serialized EBCC configuration version: {version}"
+            )));
+        }
+
+        let base_cr = read_f32(&mut reader)?;
+        let base_mode = match read_u8(&mut reader)? {
+            0 => EBCCBaseMode::Jpeg2000,
+            1 => EBCCBaseMode::None,
+            2 => EBCCBaseMode::Stored,
+            3 => EBCCBaseMode::Layered,
+            _ => return Err(FORMAT.corrupted()),
+        };
+        let residual_kind = read_u8(&mut reader)?;
+        let error = read_f32(&mut reader)?;
+        let residual_compression_type = match residual_kind {
+            0 => EBCCResidualType::Jpeg2000Only,
+            1 => EBCCResidualType::AbsoluteError(error),
+            2 => EBCCResidualType::RelativeError(error),
+            _ => return Err(FORMAT.corrupted()),
+        };
+
+        let flags = read_u8(&mut reader)?;
+        if flags
+            & !(FLAG_CHECK_FINITE
+                | FLAG_CHECKSUM_DECOMPRESSED
+                | FLAG_QUANTILE_SKETCH
+                | FLAG_DETERMINISTIC)
+            != 0
+        {
+            return Err(FORMAT.corrupted());
+        }
+        let checksum_algorithm =
+            EBCCChecksumAlgorithm::from_code(u32::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+
+        let limits = read_limits(&mut reader)?;
+
+        let expansion_guard = read_option(&mut reader, read_expansion_guard)?;
+        let roi = read_option(&mut reader, read_roi)?;
+
+        let stored_compression = match read_u8(&mut reader)? {
+            0 => EBCCStoredCompression::None,
+            1 => EBCCStoredCompression::Zstd,
+            _ => return Err(FORMAT.corrupted()),
+        };
+
+        let transform = read_option(&mut reader, read_transform)?;
+
+        let output_quantization = read_option(&mut reader, read_f32)?;
+
+        let conservation = read_option(&mut reader, |reader| match read_u8(reader)? {
+            0 => Ok(EBCCConservation::Global),
+            1 => Ok(EBCCConservation::PerFrame),
+            _ => Err(FORMAT.corrupted()),
+        })?;
+
+        let value_range = read_option(&mut reader, |reader| {
+            let min = read_option(reader, read_f32)?;
+            let max = read_option(reader, read_f32)?;
+            Ok(EBCCValueRange::new(min, max))
+        })?;
+
+        let stage = read_option(&mut reader, read_string)?;
+        let residual_coder = read_option(&mut reader, read_string)?;
+
+        // version 1 configurations always use the JPEG2000 base codec
+        let base_codec = match version {
+            1 => EBCCBaseCodec::Jpeg2000,
+            _ => match read_u8(&mut reader)? {
+                0 => EBCCBaseCodec::Jpeg2000,
+                1 => EBCCBaseCodec::Identity,
+                _ => return Err(FORMAT.corrupted()),
+            },
+        };
+
+        if !reader.is_empty() {
+            return Err(EBCCError::InvalidInput(String::from(
+                "Serialized EBCC configuration has trailing bytes",
+            )));
+        }
+
+        Ok(Self {
+            base_cr,
+            base_mode,
+            residual_compression_type,
+            check_finite: flags & FLAG_CHECK_FINITE != 0,
+            limits,
+            checksum_decompressed: flags & FLAG_CHECKSUM_DECOMPRESSED != 0,
+            checksum_algorithm,
+            expansion_guard,
+            roi,
+            stored_compression,
+            transform,
+            quantile_sketch: flags & FLAG_QUANTILE_SKETCH != 0,
+            output_quantization,
+            conservation,
+            value_range,
+            stage,
+            residual_coder,
+            base_codec,
+            deterministic: flags & FLAG_DETERMINISTIC != 0,
+        })
+    }
+}
+
+fn read_expansion_guard(reader: &mut &[u8]) -> EBCCResult<EBCCExpansionGuard> {
+    let max_ratio = read_f32(reader)?;
+    let fallback = match read_u8(reader)? {
+        0 => EBCCExpansionFallback::Error,
+        1 => EBCCExpansionFallback::StoreRaw,
+        _ => return Err(FORMAT.corrupted()),
+    };
+
+    Ok(EBCCExpansionGuard {
+        max_ratio,
+        fallback,
+    })
+}
+
+fn read_roi(reader: &mut &[u8]) -> EBCCResult<EBCCRoi> {
+    let height = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?;
+    let width = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?;
+
+    // check the length before allocating the weights
+    let len = height
+        .checked_mul(width)
+        .filter(|&len| len <= reader.len() / size_of::<f32>())
+        .ok_or_else(|| FORMAT.truncated())?;
+    let weights = (0..len)
+        .map(|_| read_f32(reader))
+        .collect::<EBCCResult<Vec<_>>>()?;
+    let weights =
+        Array2::from_shape_vec((height, width), weights).map_err(|_| FORMAT.corrupted())?;
+
+    Ok(EBCCRoi::new(weights))
+}
+
+fn read_transform(reader: &mut &[u8]) -> EBCCResult<EBCCTransform> {
+    let kind = read_u8(reader)?;
+    let scale = read_f32(reader)?;
+    let offset = read_f32(reader)?;
+
+    match kind {
+        0 => Ok(EBCCTransform::Linear { scale, offset }),
+        1 => Ok(EBCCTransform::Log1p),
+        2 => Ok(EBCCTransform::SignedLog),
+        _ => Err(FORMAT.corrupted()),
+    }
+}
+
+fn write_limits(bytes: &mut Vec<u8>, limits: &EBCCLimits) {
+    bytes.extend_from_slice(&(limits.max_frames as u64).to_le_bytes());
+    bytes.extend_from_slice(&(limits.max_frame_elements as u64).to_le_bytes());
+    write_option(
+        bytes,
+        limits.expected_frame_shape,
+        |bytes, (height, width)| {
+            bytes.extend_from_slice(&(height as u64).to_le_bytes());
+            bytes.extend_from_slice(&(width as u64).to_le_bytes());
+        },
+    );
+    bytes.extend_from_slice(&(limits.max_input_bytes as u64).to_le_bytes());
+    bytes.extend_from_slice(&(limits.max_output_bytes as u64).to_le_bytes());
+    write_option(bytes, limits.timeout, |bytes, timeout| {
+        bytes.extend_from_slice(&timeout.as_secs().to_le_bytes());
+        bytes.extend_from_slice(&timeout.subsec_nanos().to_le_bytes());
+    });
+    write_option(bytes, limits.max_threads, |bytes, threads| {
+        bytes.extend_from_slice(&(threads.get() as u64).to_le_bytes());
+    });
+}
+
+fn read_limits(reader: &mut &[u8]) -> EBCCResult<EBCCLimits> {
+    Ok(EBCCLimits {
+        max_frames: read_limit(reader)?,
+        max_frame_elements: read_limit(reader)?,
+        expected_frame_shape: read_option(reader, |reader| {
+            Ok((
+                u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?,
+                u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?,
+            ))
+        })?,
+        max_input_bytes: read_limit(reader)?,
+        max_output_bytes: read_limit(reader)?,
+        timeout: read_option(reader, |reader| {
+            let secs = u64::from_le_bytes(FORMAT.read_array(reader)?);
+            let nanos = u32::from_le_bytes(FORMAT.read_array(reader)?);
+            if nanos >= 1_000_000_000 {
+                return Err(FORMAT.corrupted());
+            }
+            Ok(Duration::new(secs, nanos))
+        })?,
+        max_threads: read_option(reader, |reader| {
+            NonZeroUsize::new(read_limit(reader)?).ok_or_else(|| FORMAT.corrupted())
+        })?,
+    })
+}
+
+/// Read a size limit, which saturates on targets where it does not fit into
+/// a `usize`, e.g. the unlimited [`usize::MAX`] of a 64-bit target on a
+/// 32-bit target.
+fn read_limit(reader: &mut &[u8]) -> EBCCResult<usize> {
+    let limit = u64::from_le_bytes(FORMAT.read_array(reader)?);
+    Ok(usize::try_from(limit).unwrap_or(usize::MAX))
+}
+
+fn write_option<T>(bytes: &mut Vec<u8>, value: Option<T>, write: impl FnOnce(&mut Vec<u8>, T)) {
+    match value {
+        None => bytes.push(0),
+        Some(value) => {
+            bytes.push(1);
+            write(bytes, value);
+        }
+    }
+}
+
+fn read_option<T>(
+    reader: &mut &[u8],
+    read: impl FnOnce(&mut &[u8]) -> EBCCResult<T>,
+) -> EBCCResult<Option<T>> {
+    match read_u8(reader)? {
+        0 => Ok(None),
+        1 => read(reader).map(Some),
+        _ => Err(FORMAT.corrupted()),
+    }
+}
+
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+fn read_string(reader: &mut &[u8]) -> EBCCResult<String> {
+    let len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?;
+    let Some((value, rest)) = reader.split_at_checked(len) else {
+        return Err(FORMAT.truncated());
+    };
+    *reader = rest;
+
+    String::from_utf8(value.to_vec()).map_err(|_| FORMAT.corrupted())
+}
+
+fn read_u8(reader: &mut &[u8]) -> EBCCResult<u8> {
+    FORMAT.read_array(reader).map(u8::from_le_bytes)
+}
+
+fn read_f32(reader: &mut &[u8]) -> EBCCResult<f32> {
+    FORMAT.read_array(reader).map(f32::from_le_bytes)
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+
+    #[test]
+    fn test_config_bytes_roundtrip() -> EBCCResult<()> {
+        let limits = EBCCLimits::unlimited()
+            .with_max_frames(12)
+            .with_max_output_bytes(1 << 20)
+            .with_expected_frame_shape((32, 48))
+            .with_timeout(Duration::from_millis(1500))
+            .with_max_threads(NonZeroUsize::MIN.saturating_add(2));
+
+        let configs = [
+            EBCCConfig::new(),
+            EBCCConfig::jpeg2000_only(20.0).with_deterministic(true),
+            EBCCConfig::relative_error_bounded(0.01)
+                .with_base_mode(EBCCBaseMode::Layered)
+                .with_checksum_algorithm(EBCCChecksumAlgorithm::Xxh3)
+                .with_limits(limits)
+                .with_expansion_guard(EBCCExpansionGuard::store_raw(1.5))
+                .with_roi(EBCCRoi::new(Array::from_shape_fn((4, 6), |(y, x)| {
+                    if (y + x) % 2 == 0 {
+                        0.5
+                    } else {
+                        2.0
+                    }
+                })))
+                .with_stored_compression(EBCCStoredCompression::Zstd)
+                .with_transform(EBCCTransform::Linear {
+                    scale: 2.0,
+                    offset: -273.15,
+                })
+                .with_quantile_sketch()
+                .with_output_quantization(0.5)
+                .with_conservation(EBCCConservation::PerFrame)
+                .with_value_range(Some(0.0), None)
+                .with_stage("scale")
+                .with_residual_coder("ebcc.sparse")
+                .with_base_codec(EBCCBaseCodec::Identity)
+                .skip_finite_check()
+                .with_decompressed_checksum(),
+        ];
+
+        for config in configs {
+            let bytes = config.to_bytes();
+            assert_eq!(EBCCConfig::from_bytes(&bytes)?, config);
+
+            for len in 0..bytes.len() {
+                assert!(matches!(
+                    EBCCConfig::from_bytes(bytes.get(..len).unwrap_or_default()),
+                    Err(EBCCError::InvalidInput(_))
+                ));
+            }
+
+            let mut trailing = bytes;
+            trailing.push(0);
+            assert!(matches!(
+                EBCCConfig::from_bytes(&trailing),
+                Err(EBCCError::InvalidInput(_))
+            ));
+        }
+
+        Ok(())
+    }
+}
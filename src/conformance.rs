@@ -0,0 +1,257 @@
+//! Conformance harness that checks the codec against documented envelopes.
+//!
+//! The harness compresses a fixed set of deterministic reference fields from
+//! the [`testdata`] module and checks that the achieved
+//! compression ratio and reconstruction errors stay within the documented
+//! envelopes. It can be run on the target hardware and toolchain to qualify a
+//! deployment:
+//!
+//! ```rust,no_run
+//! # fn main() -> ebcc::EBCCResult<()> {
+//! let report = ebcc::conformance::run()?;
+//! println!("{report}");
+//! assert!(report.passed());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use ndarray::Array;
+
+use crate::codec::{ebcc_decode_into, ebcc_encode, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::EBCCResult;
+use crate::testdata;
+use crate::verify::{data_range, pointwise_error_bound, ABSOLUTE_ERROR_TOLERANCE};
+
+/// Reference case with its documented envelope.
+#[derive(Debug, Clone)]
+pub struct ConformanceCase {
+    /// Unique name of the case
+    pub name: &'static str,
+    /// Shape of the reference field
+    pub shape: (usize, usize, usize),
+    /// Generator for the reference field
+    pub field: fn((usize, usize, usize)) -> Array<f32, EbccDim>,
+    /// Configuration that is used to compress the reference field
+    pub config: EBCCConfig,
+    /// Minimum compression ratio that must be achieved
+    pub min_compression_ratio: f64,
+    /// Maximum absolute error, as a fraction of the data range, that is
+    /// allowed for configurations without a pointwise error bound
+    pub max_range_relative_error: f32,
+}
+
+/// Measured metrics of one [`ConformanceCase`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformanceResult {
+    /// Name of the case
+    pub name: &'static str,
+    /// Achieved compression ratio
+    pub compression_ratio: f64,
+    /// Maximum absolute reconstruction error
+    pub max_abs_error: f32,
+    /// Root mean square reconstruction error
+    pub rmse: f64,
+    /// Envelope violations, empty if the case passed
+    pub violations: Vec<String>,
+}
+
+impl ConformanceResult {
+    /// Returns `true` if the case stayed within its envelope.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Report of a conformance run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformanceReport {
+    /// Results of all cases, in the order in which they were run
+    pub results: Vec<ConformanceResult>,
+}
+
+impl ConformanceReport {
+    /// Returns `true` if all cases stayed within their envelopes.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(ConformanceResult::passed)
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            fmt,
+            "{:<32} {:>8} {:>12} {:>12}  status",
+            "case", "ratio", "max error", "rmse"
+        )?;
+
+        for result in &self.results {
+            writeln!(
+                fmt,
+                "{:<32} {:>8.2} {:>12.6} {:>12.6}  {}",
+                result.name,
+                result.compression_ratio,
+                result.max_abs_error,
+                result.rmse,
+                if result.passed() { "ok" } else { "FAILED" },
+            )?;
+
+            for violation in &result.violations {
+                writeln!(fmt, "  - {violation}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The reference cases with their documented envelopes.
+///
+/// - pointwise absolute error bounds must hold up to an absolute tolerance of
+///   [`ABSOLUTE_ERROR_TOLERANCE`]
+/// - range-relative error bounds must hold up to a relative tolerance of
+///   [`RELATIVE_ERROR_TOLERANCE`][crate::verify::RELATIVE_ERROR_TOLERANCE]
+/// - JPEG2000-only compression must stay within the listed fraction of the
+///   data range
+#[must_use]
+pub fn reference_cases() -> Vec<ConformanceCase> {
+    vec![
+        ConformanceCase {
+            name: "temperature/jpeg2000-only",
+            shape: (1, 721, 1440),
+            field: testdata::temperature,
+            config: EBCCConfig::jpeg2000_only(10.0),
+            min_compression_ratio: 5.0,
+            max_range_relative_error: 0.1,
+        },
+        ConformanceCase {
+            name: "temperature/max-error-0.1",
+            shape: (1, 721, 1440),
+            field: testdata::temperature,
+            config: EBCCConfig::max_absolute_error_bounded(0.1).with_base_cr(20.0),
+            min_compression_ratio: 5.0,
+            max_range_relative_error: 1.0,
+        },
+        ConformanceCase {
+            name: "temperature/relative-error-0.001",
+            shape: (4, 128, 256),
+            field: testdata::temperature,
+            config: EBCCConfig::relative_error_bounded(0.001).with_base_cr(20.0),
+            min_compression_ratio: 1.0,
+            max_range_relative_error: 1.0,
+        },
+        ConformanceCase {
+            name: "fronts/max-error-0.5",
+            shape: (2, 64, 128),
+            field: testdata::fronts,
+            config: EBCCConfig::max_absolute_error_bounded(0.5).with_base_cr(15.0),
+            min_compression_ratio: 1.0,
+            max_range_relative_error: 1.0,
+        },
+        ConformanceCase {
+            name: "constant/default",
+            shape: (1, 32, 32),
+            field: |shape| testdata::constant(shape, 42.0),
+            config: EBCCConfig::new(),
+            min_compression_ratio: 2.0,
+            max_range_relative_error: 0.0,
+        },
+    ]
+}
+
+/// Run all [`reference_cases`] and report their metrics.
+///
+/// # Errors
+///
+/// Returns an error if compressing or decompressing any reference field fails.
+pub fn run() -> EBCCResult<ConformanceReport> {
+    let results = reference_cases()
+        .iter()
+        .map(run_case)
+        .collect::<EBCCResult<_>>()?;
+
+    Ok(ConformanceReport { results })
+}
+
+/// Run a single [`ConformanceCase`] and check it against its envelope.
+///
+/// # Errors
+///
+/// Returns an error if compressing or decompressing the reference field fails.
+#[expect(clippy::cast_precision_loss)]
+pub fn run_case(case: &ConformanceCase) -> EBCCResult<ConformanceResult> {
+    let data = (case.field)(case.shape);
+
+    let compressed = ebcc_encode(data.view(), &case.config)?;
+    let mut decompressed = Array::zeros(data.dim());
+    ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+    let original_size = data.len() * std::mem::size_of::<f32>();
+    let compression_ratio = original_size as f64 / compressed.len() as f64;
+
+    let (mut max_abs_error, mut squared_error) = (0.0_f32, 0.0_f64);
+    for (&original, &decoded) in data.iter().zip(decompressed.iter()) {
+        let error = (original - decoded).abs();
+        max_abs_error = max_abs_error.max(error);
+        squared_error += f64::from(error).powi(2);
+    }
+    let rmse = (squared_error / data.len().max(1) as f64).sqrt();
+
+    let data_range = data_range(data.view());
+
+    let mut violations = Vec::new();
+
+    if compression_ratio < case.min_compression_ratio {
+        violations.push(format!(
+            "compression ratio {compression_ratio:.2} is below {:.2}",
+            case.min_compression_ratio
+        ));
+    }
+
+    let error_bound = pointwise_error_bound(&case.config, data_range).unwrap_or_else(|| {
+        data_range.mul_add(case.max_range_relative_error, ABSOLUTE_ERROR_TOLERANCE)
+    });
+    if max_abs_error > error_bound {
+        violations.push(format!(
+            "max error {max_abs_error} exceeds the bound {error_bound}"
+        ));
+    }
+
+    Ok(ConformanceResult {
+        name: case.name,
+        compression_ratio,
+        max_abs_error,
+        rmse,
+        violations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_cases_have_unique_names() {
+        let cases = reference_cases();
+
+        for (i, case) in cases.iter().enumerate() {
+            assert!(cases
+                .iter()
+                .skip(i + 1)
+                .all(|other| other.name != case.name));
+        }
+    }
+
+    #[test]
+    fn test_conformance() -> EBCCResult<()> {
+        let report = run()?;
+
+        assert!(report.passed(), "{report}");
+
+        Ok(())
+    }
+}
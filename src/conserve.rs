@@ -0,0 +1,312 @@
+//! Conservation of the mean of the reconstructed data.
+//!
+//! Mass- or energy-conserving downstream models require that the mean, and
+//! thereby the integral, of a field is not changed by lossy compression. With
+//! an [`EBCCConservation`] mode, the sum of the original data, either of all
+//! frames or of every frame, is recorded in the payload. After decoding, the
+//! reconstruction is shifted by the difference of the means, and the
+//! remaining `f32` rounding error of the sum is corrected by moving values by
+//! a single ulp towards the recorded sum, such that the means agree up to the
+//! rounding of the `f64` sum.
+//!
+//! Since the shift is at most the error bound of the inner payload, the
+//! absolute or relative error bound is halved during encoding, such that it
+//! still holds after the shift.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_CONSERVE_MAGIC`], the format version as `u32`, the
+//!   [`EBCCConservation`] mode as `u8` (`0` for global and `1` for per-frame),
+//!   and the number of frames, the frame height, and the frame width as
+//!   `u64`s
+//! - the sums of the original data as `f64`s, one for all frames or one for
+//!   each frame
+//! - the inner EBCC payload
+
+use ndarray::{ArrayView, Axis};
+
+use crate::codec::{ebcc_decode_nested, ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::verify::data_range;
+
+/// Magic bytes at the start of every mean-conserving EBCC payload.
+pub const EBCC_CONSERVE_MAGIC: &[u8; 8] = b"EBCCCONS";
+
+/// Version of the mean-conserving EBCC payload format.
+const EBCC_CONSERVE_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC conservation data");
+
+/// Length of the mean-conserving payload header
+pub const EBCC_CONSERVE_HEADER_LEN: usize = 8 + 4 + 1 + 3 * 8;
+
+const CONSERVE_GLOBAL: u8 = 0;
+const CONSERVE_PER_FRAME: u8 = 1;
+
+/// Mean that is conserved by the reconstruction, see
+/// [`EBCCConfig::with_conservation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EBCCConservation {
+    /// The mean of all frames is conserved
+    Global,
+    /// The mean of every frame, and thereby of all frames, is conserved
+    PerFrame,
+}
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_CONSERVE_MAGIC`].
+pub fn is_conserving(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_CONSERVE_MAGIC)
+}
+
+/// Encode a 3D data array into a mean-conserving payload with the
+/// [`EBCCConfig::conservation`] mode of the `config`, which must be set.
+pub fn conserve_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let Some(conservation) = config.conservation else {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "Conservation requires a conservation mode",
+        )));
+    };
+
+    let error = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => Some(error),
+        EBCCResidualType::RelativeError(error) => Some(data_range(data) * error),
+        EBCCResidualType::Jpeg2000Only => None,
+    };
+    let residual_compression_type = match error {
+        // the shift is bounded by the inner error bound, and the values are
+        //  rounded to f32 and moved by an ulp, by at most an ulp of the
+        //  largest value each
+        Some(error) => {
+            let max_abs = data.iter().fold(0.0_f32, |max, x| max.max(x.abs()));
+            let inner_error = (max_abs * f32::EPSILON).mul_add(-4.0, error * 0.5);
+            if inner_error <= 0.0 {
+                return Err(EBCCError::InvalidConfig(format!(
+                    "Error bound {error} is too tight for conserving the mean of the data"
+                )));
+            }
+            EBCCResidualType::AbsoluteError(inner_error)
+        }
+        None => EBCCResidualType::Jpeg2000Only,
+    };
+
+    let sums = match conservation {
+        EBCCConservation::Global => vec![sum(data.iter())],
+        EBCCConservation::PerFrame => data
+            .axis_iter(Axis(0))
+            .map(|frame| sum(frame.iter()))
+            .collect(),
+    };
+
+    let inner_config = EBCCConfig {
+        residual_compression_type,
+        conservation: None,
+        ..config.clone()
+    };
+    let inner = ebcc_encode_c_buffer_with_scratch(data, &inner_config, scratch)?;
+    let inner = inner.as_slice();
+
+    let mut compressed_data =
+        Vec::with_capacity(EBCC_CONSERVE_HEADER_LEN + sums.len() * 8 + inner.len());
+    compressed_data.extend_from_slice(EBCC_CONSERVE_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_CONSERVE_VERSION.to_le_bytes());
+    compressed_data.push(match conservation {
+        EBCCConservation::Global => CONSERVE_GLOBAL,
+        EBCCConservation::PerFrame => CONSERVE_PER_FRAME,
+    });
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    for sum in sums {
+        compressed_data.extend_from_slice(&sum.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(inner);
+
+    Ok(compressed_data)
+}
+
+/// Decode a mean-conserving payload of the expected `shape`, which may be
+/// modified during decoding, into the flattened 3D data array.
+pub fn conserve_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_CONSERVE_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_CONSERVE_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC conservation version: {version}").into(),
+        ));
+    }
+
+    let [conservation] = FORMAT.read_array(&mut reader)?;
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+    let elements = data_len(expected_shape.into())?;
+
+    let [frames, _, _] = expected_shape;
+    let (groups, group_len) = match conservation {
+        CONSERVE_GLOBAL => (1, elements),
+        CONSERVE_PER_FRAME => (frames, elements / frames.max(1)),
+        conservation => {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC conservation mode: {conservation}").into(),
+            ))
+        }
+    };
+
+    let mut sums = Vec::with_capacity(groups);
+    for _ in 0..groups {
+        sums.push(f64::from_le_bytes(FORMAT.read_array(&mut reader)?));
+    }
+
+    let header_len = compressed_data.len() - reader.len();
+    let Some(inner) = compressed_data.get_mut(header_len..) else {
+        return Err(FORMAT.truncated());
+    };
+
+    let mut decompressed_data = ebcc_decode_nested(inner, expected_shape.into(), depth + 1)?
+        .as_slice()
+        .to_vec();
+
+    for (values, target) in decompressed_data
+        .chunks_exact_mut(group_len.max(1))
+        .zip(sums)
+    {
+        conserve(values, target);
+    }
+
+    Ok(decompressed_data)
+}
+
+/// Shift the `values` such that their sum matches the `target` sum, and
+/// then move single values by one ulp towards the `target` to correct the
+/// rounding error of the shift.
+fn conserve(values: &mut [f32], target: f64) {
+    if values.is_empty() || !target.is_finite() {
+        return;
+    }
+
+    #[expect(clippy::cast_precision_loss)]
+    let shift = (target - sum(values.iter())) / (values.len() as f64);
+    for value in values.iter_mut() {
+        #[expect(clippy::cast_possible_truncation)]
+        let shifted = (f64::from(*value) + shift) as f32;
+        *value = shifted;
+    }
+
+    let mut remaining = target - sum(values.iter());
+    for value in values.iter_mut() {
+        if remaining == 0.0 {
+            break;
+        }
+
+        let moved = next_toward(*value, remaining > 0.0);
+        let delta = f64::from(moved) - f64::from(*value);
+        // only move values if it brings the sum closer to the target
+        if moved.is_finite() && delta.abs() < remaining.abs() * 2.0 {
+            *value = moved;
+            remaining -= delta;
+        }
+    }
+}
+
+/// Sum the `values` in order in `f64`.
+fn sum<'a>(values: impl Iterator<Item = &'a f32>) -> f64 {
+    values.fold(0.0, |sum, &x| sum + f64::from(x))
+}
+
+/// The next `f32` after `x` towards positive infinity if `up`, or towards
+/// negative infinity otherwise.
+fn next_toward(x: f32, up: bool) -> f32 {
+    if x == 0.0 {
+        let smallest = f32::from_bits(1);
+        return if up { smallest } else { -smallest };
+    }
+
+    let bits = x.to_bits();
+    if (x > 0.0) == up {
+        f32::from_bits(bits + 1)
+    } else {
+        f32::from_bits(bits - 1)
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_conservation() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+
+        for conservation in [EBCCConservation::Global, EBCCConservation::PerFrame] {
+            for config in [
+                EBCCConfig::max_absolute_error_bounded(0.5),
+                EBCCConfig::relative_error_bounded(0.01),
+                EBCCConfig::jpeg2000_only(100.0),
+            ] {
+                let config = config.with_conservation(conservation);
+
+                let compressed = ebcc_encode(data.view(), &config)?;
+                let mut decompressed = Array::zeros(data.dim());
+                ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+                if config.residual_compression_type != EBCCResidualType::Jpeg2000Only {
+                    check_error_bound(data.view(), decompressed.view(), &config)?;
+                }
+
+                // the means agree up to the rounding of a few ulps
+                let frames = match conservation {
+                    EBCCConservation::Global => vec![(data.view(), decompressed.view())],
+                    EBCCConservation::PerFrame => data
+                        .axis_chunks_iter(Axis(0), 1)
+                        .zip(decompressed.axis_chunks_iter(Axis(0), 1))
+                        .collect(),
+                };
+                for (original, reconstructed) in frames {
+                    let difference = sum(original.iter()) - sum(reconstructed.iter());
+                    assert!(difference.abs() < 1e-3, "{conservation:?}: {difference}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_toward() {
+        for x in [0.0_f32, 1.0, -1.0, 280.0, -1e-30] {
+            assert!(next_toward(x, true) > x);
+            assert!(next_toward(x, false) < x);
+        }
+    }
+}
@@ -0,0 +1,3181 @@
+//! Editable EBCC frame containers with a checksummed frame index.
+//!
+//! Unlike an EBCC frame stream, which is written once and decoded
+//! front-to-back, a container stores every frame as its own
+//! [`ebcc_encode`] record and ends with an index of all
+//! records. Frames can therefore be decoded individually and replaced without
+//! recompressing the other frames.
+//!
+//! The container format consists of
+//! - a header with the [`EBCC_CONTAINER_MAGIC`] bytes, the
+//!   [`EBCC_CONTAINER_VERSION`] as `u32`, and the frame height and width as
+//!   `u64`
+//! - the frame records, each an [`ebcc_encode`] payload
+//!   of a single frame, which may be shared by several index entries when
+//!   bitwise identical consecutive frames are stored only once
+//! - the frame index, with the offset and length as `u64` and the CRC-32
+//!   checksum as `u32` of each frame's record, regardless of the
+//!   [`checksum_algorithm`][EBCCConfig::checksum_algorithm] of the
+//!   configuration, or an all-zero tombstone entry for each deleted frame
+//! - the access manifest, which is empty if no frame has an access tag, and
+//!   otherwise holds the number of tagged frames as `u64` and, for each
+//!   tagged frame, its index and the length of its UTF-8 tag as `u64`
+//!   followed by the tag
+//! - the metadata, which is empty if the container has no metadata, and
+//!   otherwise holds the number of entries as `u64` and, for each entry, the
+//!   length of its UTF-8 key as `u64` followed by the key and the length of
+//!   its value as `u64` followed by the value; containers with metadata
+//!   always store the number of tagged frames of the access manifest, even if
+//!   it is zero
+//! - a footer with the offset of the frame index and the number of frames as
+//!   `u64`, and the CRC-32 checksum of the frame index, access manifest, and
+//!   metadata as `u32`
+//!
+//! All integers are stored in little-endian byte order. When a container is
+//! edited, new records and a new index are appended to the end of the
+//! container, such that the previous contents are never overwritten
+//! (copy-forward). Only the last index is used. Deleted frames keep their
+//! index, such that the indices of the other frames remain stable, but are
+//! skipped when the whole container is decoded. The orphaned records and
+//! indices, and the tombstones of deleted frames, can be dropped with
+//! [`compact`].
+//!
+//! # Examples
+//!
+#![cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#![cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+//! use std::io::Cursor;
+//!
+//! use ebcc::container::{EbccContainer, EbccContainerWriter};
+//! use ebcc::EBCCConfig;
+//! use ndarray::Array;
+//!
+//! # fn main() -> ebcc::EBCCResult<()> {
+//! let config = EBCCConfig::max_absolute_error_bounded(0.1);
+//!
+//! let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (32, 32))?;
+//! for t in 0..4 {
+//!     writer.push_frame(Array::from_elem((32, 32), t as f32).view())?;
+//! }
+//! let bytes = writer.finish()?;
+//!
+//! // correct the third frame without recompressing the others
+//! let mut container = EbccContainer::open(Cursor::new(bytes))?;
+//! container.replace_frame(2, Array::from_elem((32, 32), 42.0).view(), &config)?;
+//!
+//! let mut frame = Array::zeros((32, 32));
+//! container.decode_frame_into(2, frame.view_mut())?;
+//! assert!(frame.iter().all(|x| (x - 42.0).abs() <= 0.1));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The frame records of archived containers can be checked for corruption
+//! without decoding them with [`verify_integrity`]. Containers of
+//! consecutive time ranges can be merged with [`concat()`], and a range of
+//! frames can be extracted with [`split`], both without re-encoding.
+//!
+//! Archives with many variables, each stored in its own container, can
+//! describe their settings with an [`EBCCContainerConfig`], which inherits a
+//! default [`EBCCConfig`] into per-variable and per-frame overrides.
+//!
+//! Frames can be encoded by multiple threads at once with
+//! [`EbccContainerWriter::into_concurrent`], which hands out
+//! [`EbccFrameProducer`]s and commits the encoded frames on one thread.
+//!
+//! Archives that mix public and restricted data, e.g. embargoed levels or
+//! fields, can tag frames with access labels, e.g. `"restricted"`, with
+//! [`EbccContainerWriter::set_access_tag`]. The tags are stored in the
+//! container's access manifest. Readers install an access hook with
+//! [`EbccContainer::set_access_hook`], which is asked before any tagged frame
+//! is decoded and can, e.g., check the reader's permissions or ask for
+//! confirmation. [`EbccContainer::accessible_frames`] filters the frames that
+//! the hook grants access to.
+//!
+//! Writers can describe the layout of the frames, e.g. how many vertical
+//! levels are interleaved with the time steps, with small key-value
+//! metadata entries, see [`EbccContainerWriter::set_metadata`], which
+//! readers look up with [`EbccContainer::metadata`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+#[cfg(feature = "encode")]
+use std::sync::{mpsc, Arc};
+
+#[cfg(feature = "decode")]
+use ndarray::{Array, ArrayViewMut, ArrayViewMut2, Slice};
+use ndarray::{ArrayView, ArrayView2, Axis};
+
+use crate::cbuffer::CBuffer;
+#[cfg(feature = "decode")]
+use crate::codec::copy_decompressed;
+use crate::codec::{decompressed_view, ebcc_decode_c_buffer_mut, ebcc_encode, EbccDim};
+use crate::config::{EBCCConfig, EBCCConfigOverride};
+use crate::error::{EBCCChecksummedData, EBCCError, EBCCResult};
+use crate::header::{header_payload_mut, verify_decompressed_checksum, EBCCHeader};
+use crate::limits::EBCCLimits;
+use crate::params::validate_regular_ebcc_shape;
+use crate::reader::Format;
+use crate::size::{u64_to_usize, usize_to_u64};
+#[cfg(feature = "decode")]
+use crate::time::{EBCCDateTime, EBCCTimeAxis};
+
+/// Magic bytes at the start of every EBCC container.
+pub const EBCC_CONTAINER_MAGIC: &[u8; 8] = b"EBCCCONT";
+
+/// Version of the EBCC container format.
+///
+/// Containers of version 1, which have no access manifest, of version 2,
+/// which have no metadata, and of version 3, whose frame records are EBCC C
+/// library payloads without an [`EBCCHeader`], can still be read.
+pub const EBCC_CONTAINER_VERSION: u32 = 4;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC container");
+
+/// Name of the frame index format in the errors of its readers
+const INDEX_FORMAT: Format = Format("EBCC container frame index");
+
+/// Name of the access manifest format in the errors of its readers
+const MANIFEST_FORMAT: Format = Format("EBCC container access manifest");
+
+/// Maximum length of an access tag, in bytes
+const MAX_ACCESS_TAG_LEN: usize = 255;
+
+/// Maximum length of a metadata key, in bytes
+const MAX_METADATA_KEY_LEN: usize = 255;
+
+/// Maximum length of all encoded metadata entries, in bytes
+const MAX_METADATA_LEN: u64 = 1 << 16;
+
+const HEADER_LEN: u64 = 8 + 4 + 8 + 8;
+const INDEX_ENTRY_LEN: u64 = 8 + 8 + 4;
+const FOOTER_LEN: u64 = 8 + 8 + 4;
+
+/// Location and checksum of one frame record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameEntry {
+    offset: u64,
+    len: u64,
+    checksum: u32,
+}
+
+impl FrameEntry {
+    /// Index entry of a deleted frame, which has no record
+    const TOMBSTONE: Self = Self {
+        offset: 0,
+        len: 0,
+        checksum: 0,
+    };
+
+    fn is_tombstone(&self) -> bool {
+        *self == Self::TOMBSTONE
+    }
+}
+
+/// Seekable storage that can be hinted to fetch byte ranges ahead of time.
+///
+/// Storage with a high access latency, e.g. a reader that issues ranged
+/// requests to a remote object store, can implement
+/// [`prefetch`][Self::prefetch] to start fetching the hinted ranges in the
+/// background, such that the latency overlaps with decoding. The hints are
+/// given by [`EbccContainer::prefetch_frames`].
+pub trait Prefetch: Read + Seek {
+    /// Hint that the byte `ranges`, which are sorted and do not overlap, will
+    /// be read soon.
+    ///
+    /// Prefetching is only a hint, so the default implementation does
+    /// nothing.
+    ///
+    /// # Errors
+    ///
+    /// Implementations may return an error if issuing the prefetch fails.
+    #[expect(unused_variables)]
+    fn prefetch(&mut self, ranges: &[Range<u64>]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: AsRef<[u8]>> Prefetch for Cursor<T> {}
+
+impl Prefetch for File {}
+
+impl<T: Prefetch + ?Sized> Prefetch for &mut T {
+    fn prefetch(&mut self, ranges: &[Range<u64>]) -> io::Result<()> {
+        (**self).prefetch(ranges)
+    }
+}
+
+impl<T: Prefetch + ?Sized> Prefetch for Box<T> {
+    fn prefetch(&mut self, ranges: &[Range<u64>]) -> io::Result<()> {
+        (**self).prefetch(ranges)
+    }
+}
+
+/// Result of [`verify_integrity`] for one EBCC container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// Number of frames in the container, including deleted frames
+    pub frames: usize,
+    /// Number of deleted frames, which have no record to verify
+    pub deleted_frames: usize,
+    /// Number of record bytes whose checksums were verified
+    pub verified_bytes: u64,
+    /// Indices of the frames whose records are truncated or whose checksums
+    /// do not match, in ascending order
+    pub corrupted_frames: Vec<usize>,
+}
+
+impl IntegrityReport {
+    /// Check if the records of all frames that have not been deleted are
+    /// intact.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.corrupted_frames.is_empty()
+    }
+}
+
+/// Hierarchical configuration of the EBCC containers of an archive.
+///
+/// Every frame is encoded with the `default` configuration, on top of which
+/// the [`EBCCConfigOverride`] of its variable and then the override of the
+/// frame within its variable are applied. Fields that an override does not
+/// specify are inherited, so only the settings that differ from the default
+/// have to be spelled out.
+///
+/// # Examples
+///
+/// ```rust
+/// use ebcc::container::EBCCContainerConfig;
+/// use ebcc::{EBCCConfig, EBCCConfigOverride, EBCCResidualType};
+///
+/// let config = EBCCContainerConfig::new(EBCCConfig::max_absolute_error_bounded(0.1))
+///     .with_variable("precipitation", EBCCConfigOverride::new().with_base_cr(10.0))
+///     .with_frame(
+///         "precipitation",
+///         0,
+///         EBCCConfigOverride::new()
+///             .with_residual_compression_type(EBCCResidualType::AbsoluteError(0.01)),
+///     );
+///
+/// let frame = config.resolve("precipitation", 0);
+/// assert_eq!(frame.base_cr, 10.0);
+/// assert_eq!(frame.residual_compression_type, EBCCResidualType::AbsoluteError(0.01));
+/// assert_eq!(config.resolve("temperature", 0), EBCCConfig::max_absolute_error_bounded(0.1));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct EBCCContainerConfig {
+    default: EBCCConfig,
+    variables: BTreeMap<String, VariableConfig>,
+}
+
+/// Overrides of one variable of an [`EBCCContainerConfig`]
+#[derive(Debug, Clone, Default, PartialEq)]
+struct VariableConfig {
+    config: EBCCConfigOverride,
+    frames: BTreeMap<usize, EBCCConfigOverride>,
+}
+
+impl EBCCContainerConfig {
+    /// Create a new hierarchical configuration that encodes all variables and
+    /// frames with the `default` configuration.
+    #[must_use]
+    pub const fn new(default: EBCCConfig) -> Self {
+        Self {
+            default,
+            variables: BTreeMap::new(),
+        }
+    }
+
+    /// Override the configuration of all frames of the `variable`.
+    #[must_use]
+    pub fn with_variable(
+        mut self,
+        variable: impl Into<String>,
+        config: EBCCConfigOverride,
+    ) -> Self {
+        self.variables.entry(variable.into()).or_default().config = config;
+        self
+    }
+
+    /// Override the configuration of the `frame` of the `variable`, on top of
+    /// the variable's override.
+    #[must_use]
+    pub fn with_frame(
+        mut self,
+        variable: impl Into<String>,
+        frame: usize,
+        config: EBCCConfigOverride,
+    ) -> Self {
+        self.variables
+            .entry(variable.into())
+            .or_default()
+            .frames
+            .insert(frame, config);
+        self
+    }
+
+    /// The default configuration, which all overrides inherit from.
+    #[must_use]
+    pub const fn default_config(&self) -> &EBCCConfig {
+        &self.default
+    }
+
+    /// Resolve the configuration of all frames of the `variable` that do not
+    /// have their own override.
+    #[must_use]
+    pub fn resolve_variable(&self, variable: &str) -> EBCCConfig {
+        self.variables.get(variable).map_or_else(
+            || self.default.clone(),
+            |overrides| overrides.config.inherit(&self.default),
+        )
+    }
+
+    /// Resolve the configuration of the `frame` of the `variable`.
+    #[must_use]
+    pub fn resolve(&self, variable: &str, frame: usize) -> EBCCConfig {
+        let config = self.resolve_variable(variable);
+
+        if let Some(overrides) = self
+            .variables
+            .get(variable)
+            .and_then(|overrides| overrides.frames.get(&frame))
+        {
+            return overrides.inherit(&config);
+        }
+
+        config
+    }
+
+    /// Validate the default configuration and every resolved override.
+    ///
+    /// # Errors
+    ///
+    /// - all errors that [`EBCCConfig::validate`] can return, for the first
+    ///   invalid configuration
+    pub fn validate(&self) -> EBCCResult<()> {
+        self.default.validate()?;
+
+        for overrides in self.variables.values() {
+            let config = overrides.config.inherit(&self.default);
+            config.validate()?;
+
+            for frame in overrides.frames.values() {
+                frame.inherit(&config).validate()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Encode a 3D data array into an EBCC container, where each frame along the
+/// leading axis is encoded with its own configuration from `configs`.
+///
+/// This allows different frames, e.g. analysis and forecast steps, to use
+/// different error bounds. Since every frame record is decoded on its own,
+/// the per-frame bounds are recorded in the container and the result can be
+/// decoded with a single call to [`ebcc_decode_into`][crate::ebcc_decode_into],
+/// without the `configs`.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyDimension`] if the `data` has any zero-size dimension
+/// - [`EBCCError::InvalidConfig`] if the number of `configs` does not match
+///   the number of frames
+/// - all errors that [`EbccContainerWriter::new`] and
+///   [`EbccContainerWriter::push_frame_with_config`] can return, where the
+///   number of frames is limited by the first configuration
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_into, ebcc_encode_per_frame, testdata, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = testdata::temperature((2, 32, 32));
+/// let configs = [
+///     // analysis step
+///     EBCCConfig::max_absolute_error_bounded(0.01),
+///     // forecast step
+///     EBCCConfig::max_absolute_error_bounded(0.1),
+/// ];
+///
+/// let compressed = ebcc_encode_per_frame(data.view(), &configs)?;
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_into(&compressed, decompressed.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "encode")]
+pub fn ebcc_encode_per_frame(
+    data: ArrayView<f32, EbccDim>,
+    configs: &[EBCCConfig],
+) -> EBCCResult<Vec<u8>> {
+    let (frames, height, width) = data.dim();
+    if frames == 0 {
+        return Err(EBCCError::EmptyDimension);
+    }
+    let ([first, ..], true) = (configs, configs.len() == frames) else {
+        return Err(EBCCError::InvalidConfig(format!(
+            "Expected one configuration for each of the {frames} frames but got {}",
+            configs.len(),
+        )));
+    };
+
+    let mut writer = EbccContainerWriter::new(Vec::new(), first.clone(), (height, width))?;
+    for (frame, config) in data.outer_iter().zip(configs) {
+        writer.push_frame_with_config(frame, config)?;
+    }
+    writer.finish()
+}
+
+/// Writer that encodes 2D frames into a new EBCC container.
+///
+/// Each pushed frame is encoded and written immediately, only the small
+/// frame index is kept in memory. [`finish`][Self::finish] writes the frame
+/// index and footer.
+#[cfg(feature = "encode")]
+pub struct EbccContainerWriter<W: Write> {
+    writer: W,
+    config: EBCCConfig,
+    frame_overrides: BTreeMap<usize, EBCCConfigOverride>,
+    frame_shape: (usize, usize),
+    offset: u64,
+    index: Vec<FrameEntry>,
+    previous_frame: Vec<f32>,
+    previous_config: Option<EBCCConfig>,
+    repeated_frames: Vec<usize>,
+    tags: BTreeMap<usize, String>,
+    metadata: BTreeMap<String, Vec<u8>>,
+}
+
+#[cfg(feature = "encode")]
+impl<W: Write> EbccContainerWriter<W> {
+    /// Create a new container writer for frames of shape `(height, width)`
+    /// that are encoded with the given `config`.
+    ///
+    /// The container header is written to `writer` immediately.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::EmptyDimension`] or [`EBCCError::UnsupportedShape`] if
+    ///   `frame_shape` has any zero-size dimension or its EBCC internal image
+    ///   dimensions are outside the supported range
+    /// - [`EBCCError::InvalidConfig`] if [`config.validate`][EBCCConfig::validate]
+    ///   fails
+    /// - [`EBCCError::FrameTooLarge`] if `frame_shape` exceeds the
+    ///   [`config.limits`][EBCCConfig::limits]
+    /// - [`EBCCError::Io`] if writing the container header fails
+    pub fn new(mut writer: W, config: EBCCConfig, frame_shape: (usize, usize)) -> EBCCResult<Self> {
+        validate_frame_shape(frame_shape)?;
+        config.validate()?;
+        config.limits.check_frame_shape(frame_shape)?;
+
+        write_header(&mut writer, frame_shape)?;
+
+        Ok(Self {
+            writer,
+            config,
+            frame_overrides: BTreeMap::new(),
+            frame_shape,
+            offset: HEADER_LEN,
+            index: Vec::new(),
+            previous_frame: Vec::new(),
+            previous_config: None,
+            repeated_frames: Vec::new(),
+            tags: BTreeMap::new(),
+            metadata: BTreeMap::new(),
+        })
+    }
+
+    /// Create a new container writer for frames of shape `(height, width)`
+    /// of the `variable`, whose configurations are resolved from the
+    /// hierarchical `config`.
+    ///
+    /// The container header is written to `writer` immediately.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidConfig`] if the configuration of the `variable`
+    ///   or of any of its frames is invalid
+    /// - all errors that [`new`][Self::new] can return
+    pub fn with_config(
+        writer: W,
+        config: &EBCCContainerConfig,
+        variable: &str,
+        frame_shape: (usize, usize),
+    ) -> EBCCResult<Self> {
+        let variable_config = config.resolve_variable(variable);
+        let frame_overrides = config
+            .variables
+            .get(variable)
+            .map(|overrides| overrides.frames.clone())
+            .unwrap_or_default();
+        for frame in frame_overrides.values() {
+            frame.inherit(&variable_config).validate()?;
+        }
+
+        Ok(Self {
+            frame_overrides,
+            ..Self::new(writer, variable_config, frame_shape)?
+        })
+    }
+
+    /// Encode one `(height, width)` frame and append it to the container.
+    ///
+    /// The frame is encoded with the writer's configuration, or with its own
+    /// override if the writer was created [`with_config`][Self::with_config].
+    /// A frame that is bitwise identical to the previous frame and uses the
+    /// same configuration is not encoded again but references the previous
+    /// frame's record, see [`repeated_frames`][Self::repeated_frames].
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameShapeMismatch`] if the `frame` does not have the
+    ///   container's frame shape
+    /// - [`EBCCError::TooManyFrames`] if the container would exceed the maximum
+    ///   number of frames of the [`config.limits`][EBCCConfig::limits]
+    /// - all errors that [`ebcc_encode`] can return
+    /// - [`EBCCError::Io`] if writing the frame record fails
+    pub fn push_frame(&mut self, frame: ArrayView2<f32>) -> EBCCResult<()> {
+        let frame_config = self
+            .frame_overrides
+            .get(&self.index.len())
+            .map(|overrides| overrides.inherit(&self.config));
+
+        self.append_frame(frame, frame_config.as_ref())
+    }
+
+    /// Encode one `(height, width)` frame with its own `config` and append
+    /// it to the container.
+    ///
+    /// The number of frames is still limited by the writer's
+    /// [`config.limits`][EBCCConfig::limits].
+    ///
+    /// # Errors
+    ///
+    /// - all errors that [`push_frame`][Self::push_frame] can return
+    pub fn push_frame_with_config(
+        &mut self,
+        frame: ArrayView2<f32>,
+        config: &EBCCConfig,
+    ) -> EBCCResult<()> {
+        self.append_frame(frame, Some(config))
+    }
+
+    /// Encode the `frame` with the `config`, or with the writer's
+    /// configuration if [`None`], and append it to the container
+    fn append_frame(
+        &mut self,
+        frame: ArrayView2<f32>,
+        config: Option<&EBCCConfig>,
+    ) -> EBCCResult<()> {
+        check_frame_shape(self.frame_shape, frame.dim())?;
+        self.config
+            .limits
+            .check_frames(self.index.len().saturating_add(1))?;
+
+        let config = config.unwrap_or(&self.config);
+
+        if let Some(&previous) = self.index.last() {
+            if self.previous_config.as_ref() == Some(config)
+                && frame.len() == self.previous_frame.len()
+                && frame
+                    .iter()
+                    .zip(&self.previous_frame)
+                    .all(|(a, b)| a.to_bits() == b.to_bits())
+            {
+                self.repeated_frames.push(self.index.len());
+                self.index.push(previous);
+                return Ok(());
+            }
+        }
+
+        let entry = write_record(&mut self.writer, self.offset, frame, config)?;
+        self.offset = entry.offset + entry.len;
+        self.index.push(entry);
+
+        self.previous_config = Some(config.clone());
+        self.previous_frame.clear();
+        self.previous_frame.extend(frame.iter().copied());
+
+        Ok(())
+    }
+
+    /// The indices of the frames that were bitwise identical to their
+    /// preceding frame, in ascending order.
+    ///
+    /// Repeated frames share the record of the preceding frame, and often
+    /// point to an issue upstream, e.g. a level that was duplicated by
+    /// interpolation.
+    #[must_use]
+    pub fn repeated_frames(&self) -> &[usize] {
+        &self.repeated_frames
+    }
+
+    /// Tag the frame with index `frame` with the access `tag`, e.g.
+    /// `"restricted"`, which replaces any previous tag of the frame.
+    ///
+    /// The frame may also be pushed after it has been tagged. Readers are
+    /// asked for access before they decode a tagged frame, see
+    /// [`EbccContainer::set_access_hook`].
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the `tag` is empty or longer than 255
+    ///   bytes
+    pub fn set_access_tag(&mut self, frame: usize, tag: impl Into<String>) -> EBCCResult<()> {
+        let tag = tag.into();
+        validate_access_tag(&tag)?;
+
+        self.tags.insert(frame, tag);
+
+        Ok(())
+    }
+
+    /// Store the metadata `value` under the `key`, which replaces any previous
+    /// value of the `key`.
+    ///
+    /// Metadata describes the container as a whole, e.g. the number of
+    /// vertical levels that are interleaved with the time steps, and can be
+    /// read with [`EbccContainer::metadata`].
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the `key` is empty or longer than 255
+    ///   bytes, or if all metadata entries together would exceed 64 KiB
+    pub fn set_metadata(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<Vec<u8>>,
+    ) -> EBCCResult<()> {
+        let key = key.into();
+        validate_metadata_key(&key)?;
+
+        let mut metadata = self.metadata.clone();
+        metadata.insert(key, value.into());
+        if metadata_len(&metadata) > MAX_METADATA_LEN {
+            return Err(EBCCError::InvalidInput(format!(
+                "EBCC container metadata must not exceed {MAX_METADATA_LEN} bytes",
+            )));
+        }
+        self.metadata = metadata;
+
+        Ok(())
+    }
+
+    /// Write the frame index, the access manifest, the metadata, and the
+    /// footer, and return the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] if a frame was tagged but never
+    ///   pushed
+    /// - [`EBCCError::Io`] if writing the frame index or footer fails
+    pub fn finish(mut self) -> EBCCResult<W> {
+        if let Some((&frame, _)) = self.tags.range(self.index.len()..).next() {
+            return Err(EBCCError::FrameOutOfBounds {
+                frame,
+                frames: self.index.len(),
+            });
+        }
+
+        write_index(
+            &mut self.writer,
+            &self.index,
+            &self.tags,
+            &self.metadata,
+            self.offset,
+        )?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(feature = "encode")]
+impl<W: Write> EbccContainerWriter<W> {
+    /// Split the writer into a committer and a producer such that the frames
+    /// up to a total of `frames` can be encoded concurrently.
+    ///
+    /// The [`EbccFrameProducer`] can be cloned and sent to other threads,
+    /// which encode frames in any order and submit them to the
+    /// [`EbccContainerCommitter`]. Its [`commit`][EbccContainerCommitter::commit]
+    /// writes the submitted frames as they arrive, and the frame index once
+    /// all producers have been dropped. Frames that have already been pushed
+    /// to this writer keep their indices.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::TooManyFrames`] if `frames` exceeds the maximum number
+    ///   of frames of the [`config.limits`][EBCCConfig::limits]
+    /// - [`EBCCError::InvalidInput`] if more than `frames` frames have
+    ///   already been pushed
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+    #[cfg_attr(
+        not(all(feature = "encode", feature = "decode")),
+        doc = "```rust,ignore"
+    )]
+    /// use ebcc::container::{EbccContainer, EbccContainerWriter};
+    /// use ebcc::EBCCConfig;
+    /// use ndarray::Array;
+    ///
+    /// # fn main() -> ebcc::EBCCResult<()> {
+    /// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+    /// let writer = EbccContainerWriter::new(Vec::new(), config, (32, 32))?;
+    /// let (committer, producer) = writer.into_concurrent(4)?;
+    ///
+    /// let (bytes, report) = std::thread::scope(|scope| {
+    ///     for t in 0..4 {
+    ///         let producer = producer.clone();
+    ///         scope.spawn(move || {
+    ///             producer.push_frame(t, Array::from_elem((32, 32), t as f32).view())
+    ///         });
+    ///     }
+    ///     // the index is written once all producers have been dropped
+    ///     drop(producer);
+    ///
+    ///     committer.commit()
+    /// })?;
+    /// assert!(report.is_complete());
+    ///
+    /// let container = EbccContainer::open(std::io::Cursor::new(bytes))?;
+    /// assert_eq!(container.live_frames(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_concurrent(
+        mut self,
+        frames: usize,
+    ) -> EBCCResult<(EbccContainerCommitter<W>, EbccFrameProducer)> {
+        self.config.limits.check_frames(frames)?;
+        if self.index.len() > frames {
+            return Err(EBCCError::InvalidInput(format!(
+                "{} frames have already been pushed, more than the {frames} frames of the container",
+                self.index.len(),
+            )));
+        }
+
+        // frames that are never submitted remain tombstones
+        self.index.resize(frames, FrameEntry::TOMBSTONE);
+        let (sender, receiver) = mpsc::channel();
+
+        let producer = EbccFrameProducer {
+            config: self.config.clone(),
+            frame_overrides: Arc::new(std::mem::take(&mut self.frame_overrides)),
+            frame_shape: self.frame_shape,
+            frames,
+            sender,
+        };
+
+        Ok((
+            EbccContainerCommitter {
+                writer: self,
+                receiver,
+            },
+            producer,
+        ))
+    }
+}
+
+/// Handle to encode frames concurrently and submit them to an
+/// [`EbccContainerCommitter`].
+///
+/// Created by [`EbccContainerWriter::into_concurrent`]. The producer can be
+/// cloned and sent to other threads.
+#[cfg(feature = "encode")]
+#[derive(Debug, Clone)]
+pub struct EbccFrameProducer {
+    config: EBCCConfig,
+    frame_overrides: Arc<BTreeMap<usize, EBCCConfigOverride>>,
+    frame_shape: (usize, usize),
+    frames: usize,
+    sender: mpsc::Sender<EncodedFrame>,
+}
+
+/// Frame that was encoded by an [`EbccFrameProducer`]
+#[cfg(feature = "encode")]
+#[derive(Debug)]
+struct EncodedFrame {
+    frame: usize,
+    record: Vec<u8>,
+}
+
+#[cfg(feature = "encode")]
+impl EbccFrameProducer {
+    /// Encode the `(height, width)` `data` of the frame with index `frame`
+    /// and submit it to the committer.
+    ///
+    /// If encoding fails, nothing is submitted and the frame is reported as
+    /// missing when the container is committed.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] if `frame` is out of bounds
+    /// - [`EBCCError::FrameShapeMismatch`] if the `data` does not have the
+    ///   container's frame shape
+    /// - all errors that [`ebcc_encode`] can return
+    /// - [`EBCCError::Io`] if the committer has been dropped
+    pub fn push_frame(&self, frame: usize, data: ArrayView2<f32>) -> EBCCResult<()> {
+        if frame >= self.frames {
+            return Err(EBCCError::FrameOutOfBounds {
+                frame,
+                frames: self.frames,
+            });
+        }
+        check_frame_shape(self.frame_shape, data.dim())?;
+
+        let frame_config = self
+            .frame_overrides
+            .get(&frame)
+            .map(|overrides| overrides.inherit(&self.config));
+        let record = ebcc_encode(
+            data.insert_axis(Axis(0)),
+            frame_config.as_ref().unwrap_or(&self.config),
+        )?;
+
+        self.sender
+            .send(EncodedFrame { frame, record })
+            .map_err(|_| {
+                EBCCError::Io(io::Error::new(
+                    ErrorKind::BrokenPipe,
+                    "the EBCC container committer has been dropped",
+                ))
+            })
+    }
+}
+
+/// Single consumer that writes the frames submitted by
+/// [`EbccFrameProducer`]s into an EBCC container.
+///
+/// Created by [`EbccContainerWriter::into_concurrent`].
+#[cfg(feature = "encode")]
+pub struct EbccContainerCommitter<W: Write> {
+    writer: EbccContainerWriter<W>,
+    receiver: mpsc::Receiver<EncodedFrame>,
+}
+
+/// Result of [`EbccContainerCommitter::commit`].
+#[cfg(feature = "encode")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitReport {
+    /// Number of frames in the container, including missing frames
+    pub frames: usize,
+    /// Indices of the frames that were never submitted, e.g. because their
+    /// producer failed, in ascending order
+    ///
+    /// Missing frames are stored as deleted frames.
+    pub missing_frames: Vec<usize>,
+    /// Indices of the frames that were submitted more than once, in the order
+    /// of submission, whose repeated submissions were discarded
+    pub duplicate_frames: Vec<usize>,
+}
+
+#[cfg(feature = "encode")]
+impl CommitReport {
+    /// Check if every frame was submitted exactly once.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.missing_frames.is_empty() && self.duplicate_frames.is_empty()
+    }
+}
+
+#[cfg(feature = "encode")]
+impl<W: Write> EbccContainerCommitter<W> {
+    /// Write the submitted frames as they arrive until all
+    /// [`EbccFrameProducer`]s have been dropped, then write the frame index
+    /// and footer, and return the underlying writer.
+    ///
+    /// The frame index is always consistent: frames that were never
+    /// submitted are stored as deleted frames, and only the first submission
+    /// of each frame is kept. Both are listed in the returned
+    /// [`CommitReport`].
+    ///
+    /// <div class="warning">
+    ///
+    /// **Warning:** This method blocks until all producers have been dropped,
+    /// including the one returned by
+    /// [`EbccContainerWriter::into_concurrent`].
+    ///
+    /// </div>
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::Io`] if writing a frame record, the frame index, or the
+    ///   footer fails
+    pub fn commit(self) -> EBCCResult<(W, CommitReport)> {
+        let Self {
+            mut writer,
+            receiver,
+        } = self;
+
+        // frames that were pushed before the writer was split are complete
+        let mut submitted = writer
+            .index
+            .iter()
+            .map(|entry| !entry.is_tombstone())
+            .collect::<Vec<_>>();
+        let mut duplicate_frames = Vec::new();
+
+        for EncodedFrame { frame, record } in receiver {
+            let (Some(entry), Some(submitted)) =
+                (writer.index.get_mut(frame), submitted.get_mut(frame))
+            else {
+                continue;
+            };
+            if *submitted {
+                duplicate_frames.push(frame);
+                continue;
+            }
+
+            writer.writer.write_all(&record)?;
+            *entry = FrameEntry {
+                offset: writer.offset,
+                len: usize_to_u64(record.len())?,
+                checksum: crc32fast::hash(&record),
+            };
+            writer.offset += entry.len;
+            *submitted = true;
+        }
+
+        let missing_frames = submitted
+            .iter()
+            .enumerate()
+            .filter(|(_, submitted)| !**submitted)
+            .map(|(frame, _)| frame)
+            .collect();
+
+        let report = CommitReport {
+            frames: writer.index.len(),
+            missing_frames,
+            duplicate_frames,
+        };
+
+        Ok((writer.finish()?, report))
+    }
+}
+
+/// Random-access reader and editor of an EBCC container.
+///
+/// The container is read from, and edited in, any seekable `inner` storage,
+/// e.g. a [`File`] or a [`Cursor<Vec<u8>>`][Cursor].
+pub struct EbccContainer<F> {
+    inner: F,
+    version: u32,
+    frame_shape: (usize, usize),
+    index: Vec<FrameEntry>,
+    tags: BTreeMap<usize, String>,
+    metadata: BTreeMap<String, Vec<u8>>,
+    access_hook: Option<AccessHook>,
+    end: u64,
+    payload: Vec<u8>,
+}
+
+/// Hook that decides whether a tagged frame may be decoded
+type AccessHook = Box<dyn FnMut(usize, &str) -> bool + Send + Sync>;
+
+impl<F: Read + Seek> EbccContainer<F> {
+    /// Open an existing EBCC container and read its frame index.
+    ///
+    /// The number of frames and the frame shape are checked against the
+    /// default [`EBCCLimits`] before the frame index is read.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the container header or footer is
+    ///   missing, truncated, or malformed
+    /// - [`EBCCError::DecompressionError`] if the container version is not
+    ///   supported
+    /// - [`EBCCError::TooManyFrames`] or [`EBCCError::FrameTooLarge`] if the
+    ///   container exceeds the default [`EBCCLimits`]
+    /// - [`EBCCError::InvalidInput`] if the frame index is corrupted
+    /// - [`EBCCError::Io`] if reading from `inner` fails
+    pub fn open(mut inner: F) -> EBCCResult<Self> {
+        let limits = EBCCLimits::default();
+
+        inner.seek(SeekFrom::Start(0))?;
+        let magic: [u8; 8] = read_array(&mut inner)?;
+        if &magic != EBCC_CONTAINER_MAGIC {
+            return Err(EBCCError::InvalidInput(String::from(
+                "Missing EBCC container header",
+            )));
+        }
+        let version = u32::from_le_bytes(read_array(&mut inner)?);
+        if !(1..=EBCC_CONTAINER_VERSION).contains(&version) {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC container version: {version}").into(),
+            ));
+        }
+        let frame_shape = (
+            u64_to_usize(u64::from_le_bytes(read_array(&mut inner)?))?,
+            u64_to_usize(u64::from_le_bytes(read_array(&mut inner)?))?,
+        );
+        validate_frame_shape(frame_shape)?;
+        limits.check_frame_shape(frame_shape)?;
+
+        let end = inner.seek(SeekFrom::End(0))?;
+        if end < HEADER_LEN + FOOTER_LEN {
+            return Err(FORMAT.truncated());
+        }
+        inner.seek(SeekFrom::Start(end - FOOTER_LEN))?;
+        let index_offset = u64::from_le_bytes(read_array(&mut inner)?);
+        let frames = u64::from_le_bytes(read_array(&mut inner)?);
+        let index_checksum = u32::from_le_bytes(read_array(&mut inner)?);
+
+        limits.check_frames(u64_to_usize(frames)?)?;
+        let index_len = frames.saturating_mul(INDEX_ENTRY_LEN);
+        let manifest_end = end - FOOTER_LEN;
+        if index_offset < HEADER_LEN
+            || index_offset
+                .checked_add(index_len)
+                .is_none_or(|index_end| index_end > manifest_end)
+        {
+            return Err(INDEX_FORMAT.corrupted());
+        }
+
+        let manifest_len = manifest_end - index_offset - index_len;
+        if manifest_len > max_manifest_len(version, frames)? {
+            return Err(MANIFEST_FORMAT.corrupted());
+        }
+
+        inner.seek(SeekFrom::Start(index_offset))?;
+        let mut index_bytes = Vec::new();
+        (&mut inner)
+            .take(index_len + manifest_len)
+            .read_to_end(&mut index_bytes)?;
+        if usize_to_u64(index_bytes.len())? != index_len + manifest_len
+            || crc32fast::hash(&index_bytes) != index_checksum
+        {
+            return Err(INDEX_FORMAT.corrupted());
+        }
+
+        let (mut index_bytes, manifest_bytes) = index_bytes
+            .split_at_checked(u64_to_usize(index_len)?)
+            .ok_or_else(|| FORMAT.truncated())?;
+        let mut index = Vec::with_capacity(u64_to_usize(frames)?);
+        for _ in 0..frames {
+            let entry = FrameEntry {
+                offset: u64::from_le_bytes(read_array(&mut index_bytes)?),
+                len: u64::from_le_bytes(read_array(&mut index_bytes)?),
+                checksum: u32::from_le_bytes(read_array(&mut index_bytes)?),
+            };
+            if entry.is_tombstone() {
+                index.push(entry);
+                continue;
+            }
+            if entry.len == 0
+                || entry.offset < HEADER_LEN
+                || entry
+                    .offset
+                    .checked_add(entry.len)
+                    .is_none_or(|end| end > index_offset)
+            {
+                return Err(INDEX_FORMAT.corrupted());
+            }
+            index.push(entry);
+        }
+
+        let mut manifest_bytes = manifest_bytes;
+        let tags = read_access_manifest(&mut manifest_bytes, index.len())?;
+        let metadata = read_metadata(manifest_bytes, version)?;
+
+        Ok(Self {
+            inner,
+            version,
+            frame_shape,
+            index,
+            tags,
+            metadata,
+            access_hook: None,
+            end,
+            payload: Vec::new(),
+        })
+    }
+
+    /// The `(height, width)` shape of each frame.
+    #[must_use]
+    pub const fn frame_shape(&self) -> (usize, usize) {
+        self.frame_shape
+    }
+
+    /// The number of frames in the container, including deleted frames.
+    #[must_use]
+    pub fn frames(&self) -> usize {
+        self.index.len()
+    }
+
+    /// The number of frames in the container that have not been deleted.
+    #[must_use]
+    pub fn live_frames(&self) -> usize {
+        self.index
+            .iter()
+            .filter(|entry| !entry.is_tombstone())
+            .count()
+    }
+
+    /// The indices of the frames that share the record of an earlier frame,
+    /// in ascending order.
+    ///
+    /// The [`EbccContainerWriter`] stores bitwise identical consecutive
+    /// frames only once, see [`EbccContainerWriter::repeated_frames`].
+    #[must_use]
+    pub fn repeated_frames(&self) -> Vec<usize> {
+        let mut records = BTreeSet::new();
+
+        self.index
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.is_tombstone() && !records.insert(entry.offset))
+            .map(|(frame, _)| frame)
+            .collect()
+    }
+
+    /// Check if the frame with index `frame` has been deleted.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] if `frame` is out of bounds
+    pub fn is_deleted(&self, frame: usize) -> EBCCResult<bool> {
+        Ok(self.entry(frame)?.is_tombstone())
+    }
+
+    /// The access tag of the frame with index `frame`, or [`None`] if it is
+    /// not tagged.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] if `frame` is out of bounds
+    pub fn access_tag(&self, frame: usize) -> EBCCResult<Option<&str>> {
+        self.entry(frame)?;
+
+        Ok(self.tags.get(&frame).map(String::as_str))
+    }
+
+    /// The access tags of all tagged frames, by frame index.
+    #[must_use]
+    pub const fn access_tags(&self) -> &BTreeMap<usize, String> {
+        &self.tags
+    }
+
+    /// The metadata entries of the container, by key, see
+    /// [`EbccContainerWriter::set_metadata`].
+    #[must_use]
+    pub const fn metadata(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.metadata
+    }
+
+    /// Install the access `hook`, which is asked, with the frame index and
+    /// its access tag, before any tagged frame is decoded.
+    ///
+    /// If the `hook` returns `false`, decoding the frame fails with
+    /// [`EBCCError::AccessDenied`]. The `hook` can, e.g., check the tag
+    /// against the permissions of the reader, or ask for confirmation before
+    /// embargoed data is decoded. Untagged frames are always decoded. Without
+    /// a hook, all frames are decoded.
+    ///
+    /// Compaction and integrity verification copy or check the frame records
+    /// without decoding them and are therefore not restricted.
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+    #[cfg_attr(
+        not(all(feature = "encode", feature = "decode")),
+        doc = "```rust,ignore"
+    )]
+    /// use std::io::Cursor;
+    ///
+    /// use ebcc::container::{EbccContainer, EbccContainerWriter};
+    /// use ebcc::{EBCCConfig, EBCCError};
+    /// use ndarray::Array;
+    ///
+    /// # fn main() -> ebcc::EBCCResult<()> {
+    /// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+    /// let mut writer = EbccContainerWriter::new(Vec::new(), config, (32, 32))?;
+    /// for t in 0..3 {
+    ///     writer.push_frame(Array::from_elem((32, 32), t as f32).view())?;
+    /// }
+    /// writer.set_access_tag(1, "restricted")?;
+    /// let bytes = writer.finish()?;
+    ///
+    /// let mut container = EbccContainer::open(Cursor::new(bytes))?;
+    /// container.set_access_hook(|_frame, tag| tag != "restricted");
+    /// assert_eq!(container.accessible_frames(), [0, 2]);
+    ///
+    /// let mut frame = Array::zeros((32, 32));
+    /// assert!(matches!(
+    ///     container.decode_frame_into(1, frame.view_mut()),
+    ///     Err(EBCCError::AccessDenied { frame: 1, .. })
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_access_hook(
+        &mut self,
+        hook: impl FnMut(usize, &str) -> bool + Send + Sync + 'static,
+    ) {
+        self.access_hook = Some(Box::new(hook));
+    }
+
+    /// The indices of the frames that have not been deleted and can be
+    /// decoded, i.e. that are not tagged or whose access the
+    /// [access hook][Self::set_access_hook] grants, in ascending order.
+    pub fn accessible_frames(&mut self) -> Vec<usize> {
+        (0..self.frames())
+            .filter(|&frame| {
+                self.index
+                    .get(frame)
+                    .is_some_and(|entry| !entry.is_tombstone())
+                    && self.check_access(frame).is_ok()
+            })
+            .collect()
+    }
+
+    /// Decode the frame with index `frame` into a 2D data array.
+    ///
+    /// The checksum of the frame's record is verified before decoding.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] or [`EBCCError::FrameDeleted`] if
+    ///   `frame` is out of bounds or has been deleted
+    /// - [`EBCCError::FrameShapeMismatch`] if the `decompressed_data` does not
+    ///   have the container's frame shape
+    /// - [`EBCCError::InvalidInput`] or [`EBCCError::ChecksumMismatch`] if the
+    ///   frame's record is truncated or its checksum does not match
+    /// - [`EBCCError::DecompressionError`] if decompression with EBCC fails
+    /// - [`EBCCError::Io`] if reading from `inner` fails
+    #[cfg(feature = "decode")]
+    pub fn decode_frame_into(
+        &mut self,
+        frame: usize,
+        decompressed_data: ArrayViewMut2<f32>,
+    ) -> EBCCResult<()> {
+        check_frame_shape(self.frame_shape, decompressed_data.dim())?;
+
+        let decompressed_buffer = self.decode_frame_c_buffer(frame)?;
+        copy_decompressed(
+            decompressed_data.insert_axis(Axis(0)),
+            decompressed_view(self.frame_shape_3d(), &decompressed_buffer)?,
+        );
+
+        Ok(())
+    }
+
+    /// Decode all frames that have not been deleted into a 3D data array.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::ShapeMismatch`] if the `decompressed_data` does not have
+    ///   the shape `(live_frames, height, width)` of the container
+    /// - all errors that [`decode_frame_into`][Self::decode_frame_into] can
+    ///   return
+    #[cfg(feature = "decode")]
+    pub fn decode_into(
+        &mut self,
+        mut decompressed_data: ArrayViewMut<f32, EbccDim>,
+    ) -> EBCCResult<()> {
+        self.visit_frames(decompressed_data.dim(), |frame, decompressed| {
+            copy_decompressed(
+                decompressed_data.slice_axis_mut(Axis(0), Slice::from(frame..frame + 1)),
+                decompressed,
+            );
+            Ok(())
+        })
+    }
+
+    /// Decode the frames whose times on the `time_axis` are within
+    /// `start..end` into a new 3D data array.
+    ///
+    /// The frame indices are resolved from the times of the `time_axis`,
+    /// such that callers can request data by timestamps. Since containers do
+    /// not store time coordinates, the caller must supply the `time_axis`,
+    /// which only supports fixed time steps, see [`EBCCTimeAxis`].
+    ///
+    /// # Errors
+    ///
+    /// - all errors that [`EBCCTimeAxis::frame_range`] can return when
+    ///   resolving the frames of this container
+    /// - [`EBCCError::FrameDeleted`] if a time step within `start..end` is
+    ///   missing, i.e. its frame has been deleted
+    /// - all errors that [`decode_frame_into`][Self::decode_frame_into] can
+    ///   return
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+    #[cfg_attr(
+        not(all(feature = "encode", feature = "decode")),
+        doc = "```rust,ignore"
+    )]
+    /// use std::io::Cursor;
+    /// use std::time::Duration;
+    ///
+    /// use ebcc::container::{EbccContainer, EbccContainerWriter};
+    /// use ebcc::{EBCCCalendar, EBCCConfig, EBCCDateTime, EBCCTimeAxis};
+    /// use ndarray::Array;
+    ///
+    /// # fn main() -> ebcc::EBCCResult<()> {
+    /// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+    /// let mut writer = EbccContainerWriter::new(Vec::new(), config, (32, 32))?;
+    /// for t in 0..8 {
+    ///     writer.push_frame(Array::from_elem((32, 32), t as f32).view())?;
+    /// }
+    /// let mut container = EbccContainer::open(Cursor::new(writer.finish()?))?;
+    ///
+    /// // six-hourly frames from 2000-01-01
+    /// let time_axis = EBCCTimeAxis::regular(
+    ///     EBCCCalendar::Standard,
+    ///     EBCCDateTime::date(2000, 1, 1),
+    ///     Duration::from_secs(6 * 3600),
+    /// )?;
+    ///
+    /// let day = container.decode_time_range(
+    ///     &time_axis,
+    ///     EBCCDateTime::date(2000, 1, 2),
+    ///     EBCCDateTime::date(2000, 1, 3),
+    /// )?;
+    /// assert_eq!(day.dim(), (4, 32, 32));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "decode")]
+    pub fn decode_time_range(
+        &mut self,
+        time_axis: &EBCCTimeAxis,
+        start: EBCCDateTime,
+        end: EBCCDateTime,
+    ) -> EBCCResult<Array<f32, EbccDim>> {
+        let frames = time_axis.frame_range(start, end, self.frames())?;
+
+        // missing time steps are reported before anything is decoded
+        for frame in frames.clone() {
+            if self.is_deleted(frame)? {
+                return Err(EBCCError::FrameDeleted { frame });
+            }
+        }
+
+        let (height, width) = self.frame_shape;
+        let mut decompressed_data = Array::zeros((frames.len(), height, width));
+        for (frame, decompressed) in frames.zip(decompressed_data.outer_iter_mut()) {
+            self.decode_frame_into(frame, decompressed)?;
+        }
+
+        Ok(decompressed_data)
+    }
+
+    /// Consume the container and return the underlying storage.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// The number of bytes that are no longer referenced by the frame index,
+    /// e.g. the records of replaced or deleted frames and previous frame
+    /// indices and access manifests.
+    ///
+    /// These bytes can be reclaimed with [`compact_into`][Self::compact_into].
+    #[must_use]
+    pub fn orphaned_bytes(&self) -> u64 {
+        // records that are shared by repeated frames are only counted once
+        let mut records = self
+            .index
+            .iter()
+            .map(|entry| (entry.offset, entry.len))
+            .collect::<Vec<_>>();
+        records.sort_unstable();
+        records.dedup();
+
+        let live_bytes = records
+            .iter()
+            .fold(HEADER_LEN + FOOTER_LEN, |live, (_, len)| live + len)
+            + self.index.iter().map(|_| INDEX_ENTRY_LEN).sum::<u64>()
+            + access_manifest_len(&self.tags, &self.metadata);
+
+        self.end.saturating_sub(live_bytes)
+    }
+
+    /// Write a compacted copy of the container to the `writer`, and return
+    /// the `writer`.
+    ///
+    /// The compacted container only contains the records that are referenced
+    /// by the current frame index, in frame order, and a rebuilt frame index.
+    /// The records are copied verbatim, i.e. without re-encoding, after their
+    /// checksums have been verified, and records that are shared by
+    /// [repeated frames][Self::repeated_frames] remain shared. Deleted frames
+    /// are dropped, such that the compacted container has
+    /// [`live_frames`][Self::live_frames] frames, which are renumbered
+    /// consecutively and keep their access tags. The metadata is kept as
+    /// well.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] or [`EBCCError::ChecksumMismatch`] if any
+    ///   frame's record is truncated or its checksum does not match
+    /// - [`EBCCError::Io`] if reading from `inner` or writing to the `writer`
+    ///   fails
+    pub fn compact_into<W: Write>(&mut self, mut writer: W) -> EBCCResult<W> {
+        let offset = write_header(&mut writer, self.frame_shape)?;
+
+        let mut copy = RecordCopy {
+            offset,
+            index: Vec::with_capacity(self.live_frames()),
+            tags: BTreeMap::new(),
+            metadata: self.metadata.clone(),
+        };
+        self.copy_records_into(&mut writer, 0..self.frames(), &mut copy, false)?;
+
+        write_index(
+            &mut writer,
+            &copy.index,
+            &copy.tags,
+            &copy.metadata,
+            copy.offset,
+        )?;
+        writer.flush()?;
+
+        Ok(writer)
+    }
+
+    /// Verify the checksums of all frame records without decoding them.
+    ///
+    /// The records are read in storage order, in small chunks, such that
+    /// verification is I/O bound and needs only constant memory. Unlike
+    /// decoding, corrupted records do not stop the verification but are
+    /// reported in the [`IntegrityReport`].
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::Io`] if reading from `inner` fails
+    pub fn verify_integrity(&mut self) -> EBCCResult<IntegrityReport> {
+        let mut records = self
+            .index
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, entry)| !entry.is_tombstone())
+            .collect::<Vec<_>>();
+        records.sort_unstable_by_key(|(_, entry)| entry.offset);
+
+        let mut report = IntegrityReport {
+            frames: self.frames(),
+            deleted_frames: self.frames() - records.len(),
+            verified_bytes: 0,
+            corrupted_frames: Vec::new(),
+        };
+
+        for (frame, entry) in records {
+            self.inner.seek(SeekFrom::Start(entry.offset))?;
+
+            let mut hasher = Crc32Writer::new(io::sink());
+            let len = io::copy(&mut (&mut self.inner).take(entry.len), &mut hasher)?;
+
+            report.verified_bytes += len;
+            if len != entry.len || hasher.finalize() != entry.checksum {
+                report.corrupted_frames.push(frame);
+            }
+        }
+
+        report.corrupted_frames.sort_unstable();
+
+        Ok(report)
+    }
+
+    /// Decode all frames that have not been deleted of a container with the
+    /// given `shape`, and pass each frame and its position among the live
+    /// frames to `visit`.
+    pub(crate) fn visit_frames(
+        &mut self,
+        shape: (usize, usize, usize),
+        mut visit: impl FnMut(usize, ArrayView<f32, EbccDim>) -> EBCCResult<()>,
+    ) -> EBCCResult<()> {
+        let (height, width) = self.frame_shape;
+        let live_frames = self.live_frames();
+        if shape != (live_frames, height, width) {
+            return Err(EBCCError::ShapeMismatch {
+                expected: [live_frames, height, width],
+                actual: shape.into(),
+            });
+        }
+
+        let mut position = 0;
+        for frame in 0..self.frames() {
+            if self.is_deleted(frame)? {
+                continue;
+            }
+
+            let decompressed_buffer = self.decode_frame_c_buffer(frame)?;
+            visit(
+                position,
+                decompressed_view(self.frame_shape_3d(), &decompressed_buffer)?,
+            )?;
+            position += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Read the [fingerprint][EBCCConfig::fingerprint] of the configuration of
+    /// every frame that has not been deleted from the header of its record,
+    /// or [`None`] for records without a header, which are written by
+    /// containers before version 4, or with a header before version 3.
+    pub(crate) fn frame_config_fingerprints(&mut self) -> EBCCResult<Vec<Option<u64>>> {
+        let mut fingerprints = Vec::with_capacity(self.live_frames());
+        for frame in 0..self.frames() {
+            if self.is_deleted(frame)? {
+                continue;
+            }
+
+            self.read_record(frame)?;
+            let header = EBCCHeader::parse(&self.payload)?;
+            fingerprints.push(header.and_then(|header| header.recorded_config_fingerprint()));
+        }
+
+        Ok(fingerprints)
+    }
+
+    fn decode_frame_c_buffer(&mut self, frame: usize) -> EBCCResult<CBuffer<f32>> {
+        self.check_access(frame)?;
+        self.read_record(frame)?;
+
+        // records of containers before version 4 have no header
+        let shape = self.frame_shape_3d();
+        let (payload, decompressed_checksum) = header_payload_mut(&mut self.payload, shape)?;
+        let decompressed_buffer = ebcc_decode_c_buffer_mut(payload, shape)?;
+        verify_decompressed_checksum(decompressed_checksum, decompressed_buffer.as_slice())?;
+
+        Ok(decompressed_buffer)
+    }
+
+    /// Read the record of the `frame` into the payload buffer and verify its
+    /// checksum
+    fn read_record(&mut self, frame: usize) -> EBCCResult<FrameEntry> {
+        let entry = self.entry(frame)?;
+        if entry.is_tombstone() {
+            return Err(EBCCError::FrameDeleted { frame });
+        }
+
+        self.inner.seek(SeekFrom::Start(entry.offset))?;
+        self.payload.clear();
+        (&mut self.inner)
+            .take(entry.len)
+            .read_to_end(&mut self.payload)?;
+
+        if usize_to_u64(self.payload.len())? != entry.len {
+            return Err(FORMAT.truncated());
+        }
+        if crc32fast::hash(&self.payload) != entry.checksum {
+            return Err(EBCCError::ChecksumMismatch {
+                data: EBCCChecksummedData::ContainerFrame { frame },
+            });
+        }
+
+        Ok(entry)
+    }
+
+    /// Ask the access hook whether the `frame` may be decoded if it is tagged
+    fn check_access(&mut self, frame: usize) -> EBCCResult<()> {
+        let (Some(tag), Some(hook)) = (self.tags.get(&frame), self.access_hook.as_mut()) else {
+            return Ok(());
+        };
+
+        if hook(frame, tag) {
+            Ok(())
+        } else {
+            Err(EBCCError::AccessDenied {
+                frame,
+                tag: tag.clone(),
+            })
+        }
+    }
+
+    /// Copy the records of the `frames` verbatim to the `writer`, after their
+    /// checksums have been verified, and append their index entries and
+    /// access tags to the `copy`
+    ///
+    /// Records that are shared by repeated frames remain shared. Deleted
+    /// frames are dropped, or kept as tombstones if `keep_deleted` is set.
+    fn copy_records_into(
+        &mut self,
+        writer: &mut impl Write,
+        frames: Range<usize>,
+        copy: &mut RecordCopy,
+        keep_deleted: bool,
+    ) -> EBCCResult<()> {
+        let mut records = BTreeMap::new();
+        for frame in frames {
+            if self.is_deleted(frame)? {
+                if keep_deleted {
+                    copy.index.push(FrameEntry::TOMBSTONE);
+                }
+                continue;
+            }
+
+            if let Some(tag) = self.tags.get(&frame) {
+                copy.tags.insert(copy.index.len(), tag.clone());
+            }
+
+            // repeated frames keep sharing their record
+            if let Some(&copied) = records.get(&self.entry(frame)?.offset) {
+                copy.index.push(copied);
+                continue;
+            }
+
+            let entry = self.read_record(frame)?;
+            writer.write_all(&self.payload)?;
+
+            let copied = FrameEntry {
+                offset: copy.offset,
+                ..entry
+            };
+            copy.index.push(copied);
+            records.insert(entry.offset, copied);
+            copy.offset += entry.len;
+        }
+
+        Ok(())
+    }
+
+    fn entry(&self, frame: usize) -> EBCCResult<FrameEntry> {
+        self.index
+            .get(frame)
+            .copied()
+            .ok_or_else(|| EBCCError::FrameOutOfBounds {
+                frame,
+                frames: self.frames(),
+            })
+    }
+
+    const fn frame_shape_3d(&self) -> (usize, usize, usize) {
+        (1, self.frame_shape.0, self.frame_shape.1)
+    }
+}
+
+impl<F: Prefetch> EbccContainer<F> {
+    /// Hint the underlying storage that the given `frames` will be decoded
+    /// soon, e.g. the next frames of a sequential animation playback.
+    ///
+    /// The records of the `frames` that have not been deleted are passed to
+    /// [`Prefetch::prefetch`] as sorted byte ranges, where adjacent records
+    /// are merged into one range.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] if `frames` is out of bounds
+    /// - [`EBCCError::Io`] if issuing the prefetch fails
+    pub fn prefetch_frames(&mut self, frames: Range<usize>) -> EBCCResult<()> {
+        let Some(entries) = self.index.get(frames.clone()) else {
+            return Err(EBCCError::FrameOutOfBounds {
+                frame: frames.end.saturating_sub(1).max(frames.start),
+                frames: self.frames(),
+            });
+        };
+
+        let mut records = entries
+            .iter()
+            .filter(|entry| !entry.is_tombstone())
+            .map(|entry| entry.offset..entry.offset + entry.len)
+            .collect::<Vec<_>>();
+        records.sort_unstable_by_key(|record| record.start);
+
+        let mut ranges: Vec<Range<u64>> = Vec::with_capacity(records.len());
+        for record in records {
+            match ranges.last_mut() {
+                Some(range) if range.end >= record.start => range.end = range.end.max(record.end),
+                _ => ranges.push(record),
+            }
+        }
+
+        if !ranges.is_empty() {
+            self.inner.prefetch(&ranges)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: Read + Write + Seek> EbccContainer<F> {
+    /// Replace the frame with index `frame` by re-encoding only the `data` of
+    /// that frame with the given `config`.
+    ///
+    /// The new record and an updated frame index are appended to the end of
+    /// the container, such that the previous contents are never overwritten.
+    /// The space of the replaced record is not reclaimed. Replacing a deleted
+    /// frame restores it.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] if `frame` is out of bounds
+    /// - [`EBCCError::FrameShapeMismatch`] if the `data` does not have the
+    ///   container's frame shape
+    /// - all errors that [`ebcc_encode`] can return
+    /// - [`EBCCError::Io`] if writing to `inner` fails
+    #[cfg(feature = "encode")]
+    pub fn replace_frame(
+        &mut self,
+        frame: usize,
+        data: ArrayView2<f32>,
+        config: &EBCCConfig,
+    ) -> EBCCResult<()> {
+        self.entry(frame)?;
+        check_frame_shape(self.frame_shape, data.dim())?;
+
+        self.inner.seek(SeekFrom::Start(self.end))?;
+        let entry = write_record(&mut self.inner, self.end, data, config)?;
+
+        self.update_index(frame, entry, entry.offset + entry.len)
+    }
+
+    /// Logically delete the frame with index `frame` by replacing its index
+    /// entry with a tombstone.
+    ///
+    /// Only an updated frame index is appended to the end of the container.
+    /// Deleted frames keep their index, such that the indices of the other
+    /// frames remain stable, but are skipped by
+    /// [`decode_into`][Self::decode_into]. The space of the deleted record is
+    /// reclaimed by [`compact_into`][Self::compact_into]. Deleting a frame
+    /// that has already been deleted does nothing.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] if `frame` is out of bounds
+    /// - [`EBCCError::Io`] if writing to `inner` fails
+    pub fn delete_frame(&mut self, frame: usize) -> EBCCResult<()> {
+        if self.is_deleted(frame)? {
+            return Ok(());
+        }
+
+        self.inner.seek(SeekFrom::Start(self.end))?;
+        self.update_index(frame, FrameEntry::TOMBSTONE, self.end)
+    }
+
+    /// Tag the frame with index `frame` with the access `tag`, or remove its
+    /// tag if [`None`].
+    ///
+    /// Only an updated frame index and access manifest are appended to the
+    /// end of the container. Since the header is not rewritten, containers of
+    /// version 1, which have no access manifest, cannot be tagged and must
+    /// first be upgraded with [`compact_into`][Self::compact_into].
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameOutOfBounds`] if `frame` is out of bounds
+    /// - [`EBCCError::InvalidInput`] if the `tag` is empty or longer than 255
+    ///   bytes, or if the container has version 1
+    /// - [`EBCCError::Io`] if writing to `inner` fails
+    pub fn set_access_tag(&mut self, frame: usize, tag: Option<&str>) -> EBCCResult<()> {
+        self.entry(frame)?;
+
+        let mut tags = self.tags.clone();
+        if let Some(tag) = tag {
+            if self.version < 2 {
+                return Err(EBCCError::InvalidInput(String::from(
+                    "EBCC containers of version 1 cannot be tagged, compact them first",
+                )));
+            }
+            validate_access_tag(tag)?;
+            tags.insert(frame, String::from(tag));
+        } else {
+            tags.remove(&frame);
+        }
+
+        self.inner.seek(SeekFrom::Start(self.end))?;
+        let end = write_index(
+            &mut self.inner,
+            &self.index,
+            &tags,
+            &self.metadata,
+            self.end,
+        )?;
+        self.inner.flush()?;
+
+        self.tags = tags;
+        self.end = end;
+
+        Ok(())
+    }
+
+    /// Append a new frame index at `index_offset`, in which the entry of the
+    /// `frame` is replaced by the new `entry`
+    fn update_index(
+        &mut self,
+        frame: usize,
+        entry: FrameEntry,
+        index_offset: u64,
+    ) -> EBCCResult<()> {
+        let mut index = self.index.clone();
+        if let Some(old_entry) = index.get_mut(frame) {
+            *old_entry = entry;
+        }
+
+        let end = write_index(
+            &mut self.inner,
+            &index,
+            &self.tags,
+            &self.metadata,
+            index_offset,
+        )?;
+        self.inner.flush()?;
+
+        self.index = index;
+        self.end = end;
+
+        Ok(())
+    }
+}
+
+/// Compact the EBCC container read from `container` into the `writer`,
+/// dropping all orphaned records and rebuilding the frame index.
+///
+/// Edited containers grow with every edit, since the replaced records are
+/// never overwritten. Compacting long-lived containers from time to time keeps
+/// them from growing without bound. See
+/// [`EbccContainer::compact_into`] for details.
+///
+/// # Errors
+///
+/// - all errors that [`EbccContainer::open`] and
+///   [`EbccContainer::compact_into`] can return
+pub fn compact<R: Read + Seek, W: Write>(container: R, writer: W) -> EBCCResult<W> {
+    EbccContainer::open(container)?.compact_into(writer)
+}
+
+/// Concatenate the EBCC `containers`, which cover consecutive time ranges,
+/// into one container that is written to the `writer`, and return the
+/// `writer`.
+///
+/// The frames of all containers follow each other in the given order. Their
+/// records are copied verbatim, i.e. without re-encoding, after their
+/// checksums have been verified. Deleted frames are kept as deleted frames,
+/// such that the frame indices of the concatenated container stay aligned
+/// with the time steps of the inputs, and access tags are carried over. All
+/// containers must have the same metadata, which is carried over as well.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if `containers` is empty, or if the
+///   containers have different metadata
+/// - [`EBCCError::FrameShapeMismatch`] if the containers have different frame
+///   shapes
+/// - [`EBCCError::TooManyFrames`] if the concatenated container would exceed
+///   the default [`EBCCLimits`], such that it could not be opened
+/// - all errors that [`EbccContainer::open`] and
+///   [`EbccContainer::compact_into`] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use std::io::Cursor;
+///
+/// use ebcc::container::{self, EbccContainer, EbccContainerWriter};
+/// use ebcc::EBCCConfig;
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let mut months = Vec::new();
+/// for month in 0..3 {
+///     let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (32, 32))?;
+///     for day in 0..30 {
+///         writer.push_frame(Array::from_elem((32, 32), (month * 30 + day) as f32).view())?;
+///     }
+///     months.push(Cursor::new(writer.finish()?));
+/// }
+///
+/// let quarter = container::concat(months, Vec::new())?;
+/// let february = container::split(Cursor::new(quarter.as_slice()), 30..60, Vec::new())?;
+///
+/// assert_eq!(EbccContainer::open(Cursor::new(quarter))?.frames(), 90);
+/// assert_eq!(EbccContainer::open(Cursor::new(february))?.frames(), 30);
+/// # Ok(())
+/// # }
+/// ```
+pub fn concat<R: Read + Seek, W: Write>(
+    containers: impl IntoIterator<Item = R>,
+    mut writer: W,
+) -> EBCCResult<W> {
+    let mut containers = containers.into_iter();
+    let Some(first) = containers.next() else {
+        return Err(EBCCError::InvalidInput(String::from(
+            "At least one EBCC container is required for concatenation",
+        )));
+    };
+
+    let mut container = EbccContainer::open(first)?;
+    let frame_shape = container.frame_shape;
+
+    let mut copy = RecordCopy {
+        offset: write_header(&mut writer, frame_shape)?,
+        index: Vec::new(),
+        tags: BTreeMap::new(),
+        metadata: container.metadata.clone(),
+    };
+    loop {
+        let frames = 0..container.frames();
+        container.copy_records_into(&mut writer, frames, &mut copy, true)?;
+
+        let Some(next) = containers.next() else {
+            break;
+        };
+        container = EbccContainer::open(next)?;
+        check_frame_shape(frame_shape, container.frame_shape)?;
+        if container.metadata != copy.metadata {
+            return Err(EBCCError::InvalidInput(String::from(
+                "EBCC containers with different metadata cannot be concatenated",
+            )));
+        }
+    }
+    EBCCLimits::default().check_frames(copy.index.len())?;
+
+    write_index(
+        &mut writer,
+        &copy.index,
+        &copy.tags,
+        &copy.metadata,
+        copy.offset,
+    )?;
+    writer.flush()?;
+
+    Ok(writer)
+}
+
+/// Extract the `frames` of the EBCC `container` into a new container that is
+/// written to the `writer`, and return the `writer`.
+///
+/// The records of the `frames` are copied verbatim, i.e. without
+/// re-encoding, after their checksums have been verified. The extracted
+/// frames are renumbered from zero. Deleted frames are kept as deleted
+/// frames, such that the extracted container covers the whole time range,
+/// and access tags and the metadata are carried over.
+///
+/// # Errors
+///
+/// - all errors that [`EbccContainer::open`] can return
+/// - [`EBCCError::FrameOutOfBounds`] if `frames` is out of bounds
+/// - all errors that [`EbccContainer::compact_into`] can return
+pub fn split<R: Read + Seek, W: Write>(
+    container: R,
+    frames: Range<usize>,
+    mut writer: W,
+) -> EBCCResult<W> {
+    let mut container = EbccContainer::open(container)?;
+    if container.index.get(frames.clone()).is_none() {
+        return Err(EBCCError::FrameOutOfBounds {
+            frame: frames.end.saturating_sub(1).max(frames.start),
+            frames: container.frames(),
+        });
+    }
+
+    let mut copy = RecordCopy {
+        offset: write_header(&mut writer, container.frame_shape)?,
+        index: Vec::with_capacity(frames.len()),
+        tags: BTreeMap::new(),
+        metadata: container.metadata.clone(),
+    };
+    container.copy_records_into(&mut writer, frames, &mut copy, true)?;
+
+    write_index(
+        &mut writer,
+        &copy.index,
+        &copy.tags,
+        &copy.metadata,
+        copy.offset,
+    )?;
+    writer.flush()?;
+
+    Ok(writer)
+}
+
+/// Verify the framing and the checksums of the EBCC container read from
+/// `container` without decoding any frames.
+///
+/// See [`EbccContainer::verify_integrity`] for details.
+///
+/// # Errors
+///
+/// - all errors that [`EbccContainer::open`] can return, e.g. if the
+///   container's header, footer, or frame index is corrupted
+/// - [`EBCCError::Io`] if reading from `container` fails
+pub fn verify_integrity<R: Read + Seek>(container: R) -> EBCCResult<IntegrityReport> {
+    EbccContainer::open(container)?.verify_integrity()
+}
+
+/// Check if the `compressed_data` starts with the EBCC container magic bytes.
+#[must_use]
+pub fn is_ebcc_container(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_CONTAINER_MAGIC)
+}
+
+/// Frame index, access tags, and metadata of records that are copied into a
+/// new container, whose records end at `offset`
+struct RecordCopy {
+    offset: u64,
+    index: Vec<FrameEntry>,
+    tags: BTreeMap<usize, String>,
+    metadata: BTreeMap<String, Vec<u8>>,
+}
+
+/// Write the container header and return its length
+fn write_header(writer: &mut impl Write, (height, width): (usize, usize)) -> EBCCResult<u64> {
+    writer.write_all(EBCC_CONTAINER_MAGIC)?;
+    writer.write_all(&EBCC_CONTAINER_VERSION.to_le_bytes())?;
+    writer.write_all(&usize_to_u64(height)?.to_le_bytes())?;
+    writer.write_all(&usize_to_u64(width)?.to_le_bytes())?;
+
+    Ok(HEADER_LEN)
+}
+
+fn write_record(
+    writer: &mut impl Write,
+    offset: u64,
+    frame: ArrayView2<f32>,
+    config: &EBCCConfig,
+) -> EBCCResult<FrameEntry> {
+    let record = ebcc_encode(frame.insert_axis(Axis(0)), config)?;
+
+    writer.write_all(&record)?;
+
+    Ok(FrameEntry {
+        offset,
+        len: usize_to_u64(record.len())?,
+        checksum: crc32fast::hash(&record),
+    })
+}
+
+/// Write the frame `index`, the access manifest of the `tags`, and the
+/// `metadata` at `index_offset` and return the end offset
+///
+/// The index is streamed into the `writer` while its checksum is computed,
+/// such that it is never assembled in memory.
+fn write_index(
+    writer: &mut impl Write,
+    index: &[FrameEntry],
+    tags: &BTreeMap<usize, String>,
+    metadata: &BTreeMap<String, Vec<u8>>,
+    index_offset: u64,
+) -> EBCCResult<u64> {
+    let mut index_writer = Crc32Writer::new(&mut *writer);
+    for entry in index {
+        index_writer.write_all(&entry.offset.to_le_bytes())?;
+        index_writer.write_all(&entry.len.to_le_bytes())?;
+        index_writer.write_all(&entry.checksum.to_le_bytes())?;
+    }
+
+    if !tags.is_empty() || !metadata.is_empty() {
+        index_writer.write_all(&usize_to_u64(tags.len())?.to_le_bytes())?;
+        for (&frame, tag) in tags {
+            index_writer.write_all(&usize_to_u64(frame)?.to_le_bytes())?;
+            index_writer.write_all(&usize_to_u64(tag.len())?.to_le_bytes())?;
+            index_writer.write_all(tag.as_bytes())?;
+        }
+    }
+
+    if !metadata.is_empty() {
+        index_writer.write_all(&usize_to_u64(metadata.len())?.to_le_bytes())?;
+        for (key, value) in metadata {
+            index_writer.write_all(&usize_to_u64(key.len())?.to_le_bytes())?;
+            index_writer.write_all(key.as_bytes())?;
+            index_writer.write_all(&usize_to_u64(value.len())?.to_le_bytes())?;
+            index_writer.write_all(value)?;
+        }
+    }
+
+    let checksum = index_writer.finalize();
+    writer.write_all(&index_offset.to_le_bytes())?;
+    writer.write_all(&usize_to_u64(index.len())?.to_le_bytes())?;
+    writer.write_all(&checksum.to_le_bytes())?;
+
+    let index_len = usize_to_u64(index.len())?.saturating_mul(INDEX_ENTRY_LEN);
+    Ok(index_offset + index_len + access_manifest_len(tags, metadata) + FOOTER_LEN)
+}
+
+/// Length of the access manifest of the `tags` and of the `metadata`, in
+/// bytes
+fn access_manifest_len(
+    tags: &BTreeMap<usize, String>,
+    metadata: &BTreeMap<String, Vec<u8>>,
+) -> u64 {
+    if tags.is_empty() && metadata.is_empty() {
+        return 0;
+    }
+
+    tags.values()
+        .map(|tag| 8 + 8 + tag.len() as u64)
+        .fold(8, u64::saturating_add)
+        .saturating_add(metadata_len(metadata))
+}
+
+/// Length of the encoded `metadata`, in bytes
+fn metadata_len(metadata: &BTreeMap<String, Vec<u8>>) -> u64 {
+    if metadata.is_empty() {
+        return 0;
+    }
+
+    metadata
+        .iter()
+        .map(|(key, value)| 8 + key.len() as u64 + 8 + value.len() as u64)
+        .fold(8, u64::saturating_add)
+}
+
+/// Maximum length of the access manifest and metadata of a container of the
+/// `version` with `frames` frames, in bytes
+fn max_manifest_len(version: u32, frames: u64) -> EBCCResult<u64> {
+    // only containers since version 2 have an access manifest, which holds at
+    //  most one tag per frame, and only containers since version 3 have metadata
+    let max_tags_len = 8 + frames.saturating_mul(8 + 8 + usize_to_u64(MAX_ACCESS_TAG_LEN)?);
+
+    Ok(match version {
+        1 => 0,
+        2 => max_tags_len,
+        _ => max_tags_len.saturating_add(MAX_METADATA_LEN),
+    })
+}
+
+/// Parse the access manifest of a container with `frames` frames from the
+/// front of the `manifest_bytes` and advance them
+fn read_access_manifest(
+    manifest_bytes: &mut &[u8],
+    frames: usize,
+) -> EBCCResult<BTreeMap<usize, String>> {
+    let mut tags = BTreeMap::new();
+    if manifest_bytes.is_empty() {
+        return Ok(tags);
+    }
+
+    let tagged_frames = u64::from_le_bytes(read_array(manifest_bytes)?);
+    for _ in 0..tagged_frames {
+        let frame = u64_to_usize(u64::from_le_bytes(read_array(manifest_bytes)?))?;
+        let len = u64_to_usize(u64::from_le_bytes(read_array(manifest_bytes)?))?;
+        let Some((tag, rest)) = manifest_bytes.split_at_checked(len) else {
+            return Err(MANIFEST_FORMAT.corrupted());
+        };
+        *manifest_bytes = rest;
+
+        let Ok(tag) = std::str::from_utf8(tag) else {
+            return Err(MANIFEST_FORMAT.corrupted());
+        };
+        if frame >= frames
+            || validate_access_tag(tag).is_err()
+            || tags.insert(frame, String::from(tag)).is_some()
+        {
+            return Err(MANIFEST_FORMAT.corrupted());
+        }
+    }
+
+    Ok(tags)
+}
+
+/// Parse the metadata of a container of the `version`, which fills the
+/// remaining `manifest_bytes` after the access manifest
+fn read_metadata(mut manifest_bytes: &[u8], version: u32) -> EBCCResult<BTreeMap<String, Vec<u8>>> {
+    let mut metadata = BTreeMap::new();
+
+    // only version 3 containers have metadata after the access manifest
+    if version >= 3 && !manifest_bytes.is_empty() {
+        let entries = u64::from_le_bytes(read_array(&mut manifest_bytes)?);
+        for _ in 0..entries {
+            let len = u64_to_usize(u64::from_le_bytes(read_array(&mut manifest_bytes)?))?;
+            let Some((key, rest)) = manifest_bytes.split_at_checked(len) else {
+                return Err(MANIFEST_FORMAT.corrupted());
+            };
+            manifest_bytes = rest;
+
+            let len = u64_to_usize(u64::from_le_bytes(read_array(&mut manifest_bytes)?))?;
+            let Some((value, rest)) = manifest_bytes.split_at_checked(len) else {
+                return Err(MANIFEST_FORMAT.corrupted());
+            };
+            manifest_bytes = rest;
+
+            let Ok(key) = std::str::from_utf8(key) else {
+                return Err(MANIFEST_FORMAT.corrupted());
+            };
+            if validate_metadata_key(key).is_err()
+                || metadata.insert(String::from(key), value.to_vec()).is_some()
+            {
+                return Err(MANIFEST_FORMAT.corrupted());
+            }
+        }
+    }
+
+    if !manifest_bytes.is_empty() {
+        return Err(MANIFEST_FORMAT.corrupted());
+    }
+
+    Ok(metadata)
+}
+
+fn validate_metadata_key(key: &str) -> EBCCResult<()> {
+    if key.is_empty() || key.len() > MAX_METADATA_KEY_LEN {
+        return Err(EBCCError::InvalidInput(format!(
+            "Metadata keys must have between 1 and {MAX_METADATA_KEY_LEN} bytes, got {} bytes",
+            key.len(),
+        )));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn validate_access_tag(tag: &str) -> EBCCResult<()> {
+    if tag.is_empty() || tag.len() > MAX_ACCESS_TAG_LEN {
+        return Err(EBCCError::InvalidInput(format!(
+            "Access tags must have between 1 and {MAX_ACCESS_TAG_LEN} bytes, got {} bytes",
+            tag.len(),
+        )));
+    }
+
+    Ok(())
+}
+
+/// Writer that computes the CRC-32 checksum of the bytes that it writes
+/// into the `inner` writer
+struct Crc32Writer<W: Write> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> Crc32Writer<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    fn finalize(self) -> u32 {
+        self.hasher.finalize()
+    }
+}
+
+impl<W: Write> Write for Crc32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.hasher.update(buf.get(..len).unwrap_or_default());
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn validate_frame_shape((height, width): (usize, usize)) -> EBCCResult<()> {
+    if height == 0 || width == 0 {
+        return Err(EBCCError::EmptyDimension);
+    }
+
+    validate_regular_ebcc_shape((1, height, width))
+}
+
+fn check_frame_shape(expected: (usize, usize), found: (usize, usize)) -> EBCCResult<()> {
+    if expected != found {
+        return Err(EBCCError::FrameShapeMismatch {
+            expected: expected.into(),
+            actual: found.into(),
+        });
+    }
+
+    Ok(())
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> EBCCResult<[u8; N]> {
+    let mut array = [0; N];
+    match reader.read_exact(&mut array) {
+        Ok(()) => Ok(array),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Err(FORMAT.truncated()),
+        Err(err) => Err(EBCCError::Io(err)),
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+#[expect(clippy::indexing_slicing, clippy::cast_possible_truncation)]
+mod tests {
+    use std::io::Cursor;
+
+    use ndarray::s;
+
+    use super::*;
+    use crate::time::EBCCCalendar;
+    use crate::verify::check_error_bound;
+    use crate::{
+        ebcc_decode_into, ebcc_encode, testdata, EBCCBaseMode, EBCCConservation,
+        EBCCExpansionGuard, EBCCLimits, EBCCResidualType, EBCCRoi, EBCCStoredCompression,
+        EBCCTransform, EBCCValueRange,
+    };
+
+    fn write_container(data: &Array<f32, EbccDim>, config: &EBCCConfig) -> EBCCResult<Vec<u8>> {
+        let (_, height, width) = data.dim();
+        let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (height, width))?;
+        for frame in data.outer_iter() {
+            writer.push_frame(frame)?;
+        }
+        writer.finish()
+    }
+
+    #[test]
+    fn test_container_roundtrip() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let bytes = write_container(&data, &config)?;
+
+        let mut container = EbccContainer::open(Cursor::new(bytes.as_slice()))?;
+        assert_eq!(container.frames(), 3);
+        assert_eq!(container.frame_shape(), (32, 48));
+
+        let mut decompressed = Array::zeros(data.dim());
+        container.decode_into(decompressed.view_mut())?;
+
+        // each frame is encoded on its own
+        for (t, frame) in data.outer_iter().enumerate() {
+            let compressed = ebcc_encode(frame.insert_axis(Axis(0)), &config)?;
+            let mut expected = Array::zeros((1, 32, 48));
+            ebcc_decode_into(&compressed, expected.view_mut())?;
+            assert_eq!(
+                decompressed.index_axis(Axis(0), t),
+                expected.index_axis(Axis(0), 0)
+            );
+        }
+
+        // containers can also be decoded directly
+        let mut direct = Array::zeros(data.dim());
+        ebcc_decode_into(&bytes, direct.view_mut())?;
+        assert_eq!(direct, decompressed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_per_frame() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let configs = [0.5, 0.05, 0.005].map(EBCCConfig::max_absolute_error_bounded);
+
+        let compressed = ebcc_encode_per_frame(data.view(), &configs)?;
+        assert!(is_ebcc_container(&compressed));
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        for (t, config) in configs.iter().enumerate() {
+            let frame = Slice::from(t..=t);
+            check_error_bound(
+                data.slice_axis(Axis(0), frame),
+                decompressed.slice_axis(Axis(0), frame),
+                config,
+            )?;
+        }
+
+        assert!(matches!(
+            ebcc_encode_per_frame(data.view(), &configs[..2]),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            ebcc_encode_per_frame(Array::zeros((0, 32, 48)).view(), &[]),
+            Err(EBCCError::EmptyDimension)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mixed_lossy_and_stored_frames() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let lossy = EBCCConfig::max_absolute_error_bounded(0.1);
+        let stored = EBCCConfig::new()
+            .with_base_mode(EBCCBaseMode::Stored)
+            .with_stored_compression(EBCCStoredCompression::Zstd);
+
+        let compressed =
+            ebcc_encode_per_frame(data.view(), &[lossy.clone(), stored, lossy.clone()])?;
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        check_error_bound(data.view(), decompressed.view(), &lossy)?;
+        assert_eq!(
+            data.index_axis(Axis(0), 1),
+            decompressed.index_axis(Axis(0), 1)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_frames() -> EBCCResult<()> {
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let data = testdata::temperature((2, 32, 48));
+        let frames = [0, 0, 0, 1, 0];
+
+        let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (32, 48))?;
+        for frame in frames {
+            writer.push_frame(data.index_axis(Axis(0), frame))?;
+        }
+        assert_eq!(writer.repeated_frames(), &[1, 2]);
+        let bytes = writer.finish()?;
+
+        let mut container = EbccContainer::open(Cursor::new(bytes))?;
+        assert_eq!(container.repeated_frames(), vec![1, 2]);
+        assert_eq!(container.orphaned_bytes(), 0);
+        assert!(container.verify_integrity()?.is_ok());
+
+        for (i, frame) in frames.into_iter().enumerate() {
+            let mut decompressed = Array::zeros((32, 48));
+            container.decode_frame_into(i, decompressed.view_mut())?;
+            check_error_bound(
+                data.index_axis(Axis(0), frame).insert_axis(Axis(0)),
+                decompressed.view().insert_axis(Axis(0)),
+                &config,
+            )?;
+        }
+
+        // replacing a repeated frame orphans no shared record
+        container.replace_frame(1, data.index_axis(Axis(0), 1), &config)?;
+        assert_eq!(container.repeated_frames(), vec![2]);
+
+        let compacted = EbccContainer::open(Cursor::new(container.compact_into(Vec::new())?))?;
+        assert_eq!(compacted.repeated_frames(), vec![2]);
+        assert_eq!(compacted.orphaned_bytes(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hierarchical_config() -> EBCCResult<()> {
+        let config = EBCCContainerConfig::new(EBCCConfig::max_absolute_error_bounded(0.1))
+            .with_variable("t2m", EBCCConfigOverride::new().with_base_cr(20.0))
+            .with_frame(
+                "t2m",
+                1,
+                EBCCConfigOverride::new()
+                    .with_residual_compression_type(EBCCResidualType::AbsoluteError(0.01)),
+            );
+        config.validate()?;
+
+        assert_eq!(
+            config.resolve("tp", 1),
+            EBCCConfig::max_absolute_error_bounded(0.1)
+        );
+        assert_eq!(
+            config.resolve("t2m", 0),
+            EBCCConfig::max_absolute_error_bounded(0.1).with_base_cr(20.0)
+        );
+        assert_eq!(
+            config.resolve("t2m", 1),
+            EBCCConfig::max_absolute_error_bounded(0.01).with_base_cr(20.0)
+        );
+
+        let data = testdata::temperature((3, 32, 48));
+        let mut writer = EbccContainerWriter::with_config(Vec::new(), &config, "t2m", (32, 48))?;
+        for frame in data.outer_iter() {
+            writer.push_frame(frame)?;
+        }
+        let bytes = writer.finish()?;
+
+        let mut container = EbccContainer::open(Cursor::new(bytes.as_slice()))?;
+        for (t, frame) in data.outer_iter().enumerate() {
+            let compressed = ebcc_encode(frame.insert_axis(Axis(0)), &config.resolve("t2m", t))?;
+            let mut expected = Array::zeros((1, 32, 48));
+            ebcc_decode_into(&compressed, expected.view_mut())?;
+
+            let mut decompressed = Array::zeros((32, 48));
+            container.decode_frame_into(t, decompressed.view_mut())?;
+            assert_eq!(decompressed, expected.index_axis(Axis(0), 0));
+        }
+
+        // invalid overrides are rejected when the writer is created
+        let invalid = config.with_frame("t2m", 2, EBCCConfigOverride::new().with_base_cr(-1.0));
+        assert!(invalid.validate().is_err());
+        assert!(EbccContainerWriter::with_config(Vec::new(), &invalid, "t2m", (32, 48)).is_err());
+        assert!(EbccContainerWriter::with_config(Vec::new(), &invalid, "tp", (32, 48)).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_override() {
+        let parent = EBCCConfig::max_absolute_error_bounded(0.1)
+            .with_expansion_guard(EBCCExpansionGuard::new(2.0))
+            .with_roi(EBCCRoi::new(Array::from_elem((32, 48), 2.0)))
+            .with_transform(EBCCTransform::Log1p)
+            .with_output_quantization(0.5)
+            .with_conservation(EBCCConservation::Global)
+            .with_value_range(Some(0.0), None)
+            .with_stage("parent-stage")
+            .with_residual_coder("parent-coder");
+
+        // an empty override inherits everything
+        assert_eq!(EBCCConfigOverride::new().inherit(&parent), parent);
+
+        // knobs without an optional value can be overridden
+        let limits = EBCCLimits::new().with_max_frames(3);
+        let config = EBCCConfigOverride::new()
+            .with_limits(limits)
+            .with_deterministic(true)
+            .inherit(&parent);
+        assert_eq!(config.limits, limits);
+        assert!(config.deterministic);
+
+        // optional knobs can be set and cleared
+        let guard = EBCCExpansionGuard::store_raw(1.0);
+        let config = EBCCConfigOverride::new().with_expansion_guard(guard);
+        assert_eq!(config.inherit(&parent).expansion_guard, Some(guard));
+        let config = EBCCConfigOverride::new().without_expansion_guard();
+        assert_eq!(config.inherit(&parent).expansion_guard, None);
+
+        let weights = EBCCRoi::new(Array::from_elem((32, 48), 0.5));
+        let config = EBCCConfigOverride::new().with_roi(weights.clone());
+        assert_eq!(config.inherit(&parent).roi, Some(weights));
+        let config = EBCCConfigOverride::new().without_roi();
+        assert_eq!(config.inherit(&parent).roi, None);
+
+        let config = EBCCConfigOverride::new().with_transform(EBCCTransform::SignedLog);
+        assert_eq!(
+            config.inherit(&parent).transform,
+            Some(EBCCTransform::SignedLog)
+        );
+        let config = EBCCConfigOverride::new().without_transform();
+        assert_eq!(config.inherit(&parent).transform, None);
+
+        let config = EBCCConfigOverride::new().with_output_quantization(0.25);
+        assert_eq!(config.inherit(&parent).output_quantization, Some(0.25));
+        let config = EBCCConfigOverride::new().without_output_quantization();
+        assert_eq!(config.inherit(&parent).output_quantization, None);
+
+        let config = EBCCConfigOverride::new().with_conservation(EBCCConservation::PerFrame);
+        assert_eq!(
+            config.inherit(&parent).conservation,
+            Some(EBCCConservation::PerFrame)
+        );
+        let config = EBCCConfigOverride::new().without_conservation();
+        assert_eq!(config.inherit(&parent).conservation, None);
+
+        let config = EBCCConfigOverride::new().with_value_range(None, Some(1.0));
+        assert_eq!(
+            config.inherit(&parent).value_range,
+            Some(EBCCValueRange::new(None, Some(1.0)))
+        );
+        let config = EBCCConfigOverride::new().without_value_range();
+        assert_eq!(config.inherit(&parent).value_range, None);
+
+        let config = EBCCConfigOverride::new().with_stage("stage");
+        assert_eq!(config.inherit(&parent).stage.as_deref(), Some("stage"));
+        let config = EBCCConfigOverride::new().without_stage();
+        assert_eq!(config.inherit(&parent).stage, None);
+
+        let config = EBCCConfigOverride::new().with_residual_coder("coder");
+        assert_eq!(
+            config.inherit(&parent).residual_coder.as_deref(),
+            Some("coder")
+        );
+        let config = EBCCConfigOverride::new().without_residual_coder();
+        assert_eq!(config.inherit(&parent).residual_coder, None);
+
+        // clearing a knob leaves the others inherited
+        let config = EBCCConfigOverride::new().without_roi().inherit(&parent);
+        assert_eq!(
+            config,
+            EBCCConfig {
+                roi: None,
+                ..parent
+            }
+        );
+    }
+
+    #[test]
+    fn test_concurrent_writer() -> EBCCResult<()> {
+        let data = testdata::temperature((6, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let expected = write_container(&data, &config)?;
+
+        let mut writer = EbccContainerWriter::new(Vec::new(), config, (32, 48))?;
+        writer.push_frame(data.index_axis(Axis(0), 0))?;
+        let (committer, producer) = writer.into_concurrent(6)?;
+
+        let (bytes, report) = std::thread::scope(|scope| {
+            // frames are submitted out of order by several producers
+            for frames in [[5, 3], [1, 4]] {
+                let producer = producer.clone();
+                let data = &data;
+                scope.spawn(move || {
+                    for t in frames {
+                        producer.push_frame(t, data.index_axis(Axis(0), t))?;
+                    }
+                    Ok::<(), EBCCError>(())
+                });
+            }
+
+            // frame 2 fails to encode, and frame 0 has already been pushed
+            let mut invalid = data.index_axis(Axis(0), 2).to_owned();
+            invalid.fill(f32::NAN);
+            assert!(producer.push_frame(2, invalid.view()).is_err());
+            producer.push_frame(0, data.index_axis(Axis(0), 0))?;
+            assert!(matches!(
+                producer.push_frame(6, data.index_axis(Axis(0), 0)),
+                Err(EBCCError::FrameOutOfBounds {
+                    frame: 6,
+                    frames: 6
+                })
+            ));
+            drop(producer);
+
+            committer.commit()
+        })?;
+
+        assert_eq!(
+            report,
+            CommitReport {
+                frames: 6,
+                missing_frames: vec![2],
+                duplicate_frames: vec![0],
+            }
+        );
+        assert!(!report.is_complete());
+
+        let mut container = EbccContainer::open(Cursor::new(bytes))?;
+        let mut expected = EbccContainer::open(Cursor::new(expected))?;
+        assert!(verify_integrity(Cursor::new(container.inner.get_ref()))?.is_ok());
+        assert!(container.is_deleted(2)?);
+        for t in [0, 1, 3, 4, 5] {
+            let mut decompressed = Array::zeros((32, 48));
+            container.decode_frame_into(t, decompressed.view_mut())?;
+            let mut frame = Array::zeros((32, 48));
+            expected.decode_frame_into(t, frame.view_mut())?;
+            assert_eq!(decompressed, frame);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_frame() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let bytes = write_container(&data, &config)?;
+
+        let mut before = Array::zeros(data.dim());
+        ebcc_decode_into(&bytes, before.view_mut())?;
+
+        let mut container = EbccContainer::open(Cursor::new(bytes.clone()))?;
+        let replacement = testdata::fronts((1, 32, 48));
+        container.replace_frame(1, replacement.index_axis(Axis(0), 0), &config)?;
+        assert!(container
+            .replace_frame(3, replacement.index_axis(Axis(0), 0), &config)
+            .is_err());
+
+        let edited = container.into_inner().into_inner();
+        // the previous contents are copied forward
+        assert_eq!(&edited[..bytes.len()], bytes.as_slice());
+
+        let mut after = Array::zeros(data.dim());
+        ebcc_decode_into(&edited, after.view_mut())?;
+
+        assert_eq!(after.index_axis(Axis(0), 0), before.index_axis(Axis(0), 0));
+        assert_eq!(after.index_axis(Axis(0), 2), before.index_axis(Axis(0), 2));
+        assert!(after
+            .index_axis(Axis(0), 1)
+            .iter()
+            .zip(replacement.iter())
+            .all(|(a, b)| (a - b).abs() <= 0.1 + 1e-6));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let bytes = write_container(&data, &config)?;
+
+        let container = EbccContainer::open(Cursor::new(bytes.as_slice()))?;
+        assert_eq!(container.orphaned_bytes(), 0);
+
+        let mut container = EbccContainer::open(Cursor::new(bytes.clone()))?;
+        for _ in 0..3 {
+            container.replace_frame(1, data.index_axis(Axis(0), 0), &config)?;
+        }
+        assert!(container.orphaned_bytes() > 0);
+
+        let compacted = container.compact_into(Vec::new())?;
+        let edited = container.into_inner().into_inner();
+        assert_eq!(
+            compact(Cursor::new(edited.as_slice()), Vec::new())?,
+            compacted
+        );
+        assert!(compacted.len() < edited.len());
+
+        let container = EbccContainer::open(Cursor::new(compacted.as_slice()))?;
+        assert_eq!(container.orphaned_bytes(), 0);
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&edited, expected.view_mut())?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compacted, decompressed.view_mut())?;
+        assert_eq!(decompressed, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concat_split() -> EBCCResult<()> {
+        let data = testdata::temperature((5, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let first = write_container(&data.slice(s![..2, .., ..]).to_owned(), &config)?;
+
+        let mut second = EbccContainer::open(Cursor::new(write_container(
+            &data.slice(s![2.., .., ..]).to_owned(),
+            &config,
+        )?))?;
+        second.delete_frame(1)?;
+        second.set_access_tag(2, Some("restricted"))?;
+        let second = second.into_inner().into_inner();
+
+        let concatenated = concat(
+            [
+                Cursor::new(first.as_slice()),
+                Cursor::new(second.as_slice()),
+            ],
+            Vec::new(),
+        )?;
+        let mut container = EbccContainer::open(Cursor::new(concatenated.as_slice()))?;
+        assert_eq!(container.frames(), 5);
+        assert_eq!(container.orphaned_bytes(), 0);
+        assert!(container.is_deleted(3)?);
+        assert_eq!(container.access_tag(4)?, Some("restricted"));
+
+        let mut expected = Array::zeros((32, 48));
+        let mut decompressed = Array::zeros((32, 48));
+        for frame in [0, 1, 2, 4] {
+            container.decode_frame_into(frame, decompressed.view_mut())?;
+            let (source, index) = if frame < 2 {
+                (&first, frame)
+            } else {
+                (&second, frame - 2)
+            };
+            EbccContainer::open(Cursor::new(source.as_slice()))?
+                .decode_frame_into(index, expected.view_mut())?;
+            assert_eq!(decompressed, expected);
+        }
+
+        let split_off = split(Cursor::new(concatenated.as_slice()), 1..4, Vec::new())?;
+        let mut container = EbccContainer::open(Cursor::new(split_off.as_slice()))?;
+        assert_eq!(container.frames(), 3);
+        assert_eq!(container.orphaned_bytes(), 0);
+        assert!(container.is_deleted(2)?);
+        assert_eq!(container.access_tag(2)?, None);
+        container.decode_frame_into(0, decompressed.view_mut())?;
+        EbccContainer::open(Cursor::new(first.as_slice()))?
+            .decode_frame_into(1, expected.view_mut())?;
+        assert_eq!(decompressed, expected);
+
+        assert!(split(Cursor::new(concatenated.as_slice()), 4..6, Vec::new()).is_err());
+        assert!(concat(Vec::<Cursor<&[u8]>>::new(), Vec::new()).is_err());
+        let other_shape = write_container(&testdata::temperature((1, 32, 32)), &config)?;
+        assert!(concat(
+            [
+                Cursor::new(first.as_slice()),
+                Cursor::new(other_shape.as_slice())
+            ],
+            Vec::new(),
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_frame() -> EBCCResult<()> {
+        let data = testdata::temperature((4, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let bytes = write_container(&data, &config)?;
+
+        let mut before = Array::zeros(data.dim());
+        ebcc_decode_into(&bytes, before.view_mut())?;
+
+        let mut container = EbccContainer::open(Cursor::new(bytes))?;
+        container.delete_frame(1)?;
+        container.delete_frame(1)?;
+        container.delete_frame(3)?;
+        assert!(container.delete_frame(4).is_err());
+
+        assert_eq!(container.frames(), 4);
+        assert_eq!(container.live_frames(), 2);
+        assert!(container.is_deleted(1)?);
+        assert!(!container.is_deleted(2)?);
+
+        // the indices of the other frames remain stable
+        let mut frame = Array::zeros((32, 48));
+        assert!(container.decode_frame_into(1, frame.view_mut()).is_err());
+        container.decode_frame_into(2, frame.view_mut())?;
+        assert_eq!(frame, before.index_axis(Axis(0), 2));
+
+        // deleted frames are skipped on decode
+        let edited = container.into_inner().into_inner();
+        let mut after = Array::zeros((2, 32, 48));
+        ebcc_decode_into(&edited, after.view_mut())?;
+        assert_eq!(after.index_axis(Axis(0), 0), before.index_axis(Axis(0), 0));
+        assert_eq!(after.index_axis(Axis(0), 1), before.index_axis(Axis(0), 2));
+        assert!(ebcc_decode_into(&edited, before.view_mut()).is_err());
+
+        // and dropped by compaction
+        let mut container = EbccContainer::open(Cursor::new(edited.as_slice()))?;
+        assert!(container.orphaned_bytes() > 0);
+        let compacted = container.compact_into(Vec::new())?;
+        let container = EbccContainer::open(Cursor::new(compacted.as_slice()))?;
+        assert_eq!(container.frames(), 2);
+        assert_eq!(container.orphaned_bytes(), 0);
+
+        let mut decompressed = Array::zeros((2, 32, 48));
+        ebcc_decode_into(&compacted, decompressed.view_mut())?;
+        assert_eq!(decompressed, after);
+
+        // a deleted frame is restored by replacing it
+        let mut container = EbccContainer::open(Cursor::new(edited))?;
+        container.replace_frame(3, data.index_axis(Axis(0), 3), &config)?;
+        assert_eq!(container.live_frames(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_time_range() -> EBCCResult<()> {
+        let data = testdata::temperature((6, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let bytes = write_container(&data, &config)?;
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&bytes, expected.view_mut())?;
+
+        // daily frames in a 360-day calendar
+        let time_axis = EBCCTimeAxis::regular(
+            EBCCCalendar::Day360,
+            EBCCDateTime::date(1999, 2, 28),
+            std::time::Duration::from_secs(86400),
+        )?;
+
+        let mut container = EbccContainer::open(Cursor::new(bytes))?;
+        let decompressed = container.decode_time_range(
+            &time_axis,
+            EBCCDateTime::date(1999, 2, 30),
+            EBCCDateTime::date(1999, 3, 3),
+        )?;
+        assert_eq!(decompressed, expected.slice(s![2..5, .., ..]));
+
+        // a missing time step is reported as a deleted frame
+        container.delete_frame(3)?;
+        assert!(matches!(
+            container.decode_time_range(
+                &time_axis,
+                EBCCDateTime::date(1999, 2, 30),
+                EBCCDateTime::date(1999, 3, 3),
+            ),
+            Err(EBCCError::FrameDeleted { frame: 3 })
+        ));
+        assert!(matches!(
+            container.decode_time_range(
+                &time_axis,
+                EBCCDateTime::date(1999, 3, 1),
+                EBCCDateTime::date(1999, 3, 10),
+            ),
+            Err(EBCCError::FrameOutOfBounds {
+                frame: 11,
+                frames: 6
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_access_tags() -> EBCCResult<()> {
+        let data = testdata::temperature((4, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (32, 48))?;
+        writer.set_access_tag(1, "restricted")?;
+        writer.set_access_tag(3, "embargoed")?;
+        assert!(writer.set_access_tag(0, "").is_err());
+        for frame in data.outer_iter() {
+            writer.push_frame(frame)?;
+        }
+        let bytes = writer.finish()?;
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&write_container(&data, &config)?, expected.view_mut())?;
+
+        // without a hook, all frames are decoded
+        let mut container = EbccContainer::open(Cursor::new(bytes.clone()))?;
+        assert_eq!(container.access_tag(0)?, None);
+        assert_eq!(container.access_tag(1)?, Some("restricted"));
+        assert!(container.access_tag(4).is_err());
+        assert_eq!(container.orphaned_bytes(), 0);
+        let mut decompressed = Array::zeros(data.dim());
+        container.decode_into(decompressed.view_mut())?;
+        assert_eq!(decompressed, expected);
+
+        // the hook is asked before tagged frames are decoded
+        let asked = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_asked = Arc::clone(&asked);
+        container.set_access_hook(move |frame, tag| {
+            if let Ok(mut asked) = hook_asked.lock() {
+                asked.push(frame);
+            }
+            tag != "restricted"
+        });
+        assert_eq!(container.accessible_frames(), [0, 2, 3]);
+        let mut frame = Array::zeros((32, 48));
+        assert!(matches!(
+            container.decode_frame_into(1, frame.view_mut()),
+            Err(EBCCError::AccessDenied { frame: 1, ref tag }) if tag == "restricted"
+        ));
+        container.decode_frame_into(3, frame.view_mut())?;
+        assert_eq!(frame, expected.index_axis(Axis(0), 3));
+        assert!(container.decode_into(decompressed.view_mut()).is_err());
+        assert_eq!(
+            asked.lock().map(|asked| asked.clone()).ok(),
+            Some(vec![1, 3, 1, 3, 1])
+        );
+
+        // tags can be edited, and are kept and renumbered by compaction
+        container.set_access_tag(1, None)?;
+        container.set_access_tag(0, Some("restricted"))?;
+        container.delete_frame(2)?;
+        assert_eq!(container.accessible_frames(), [1, 3]);
+        let compacted = container.compact_into(Vec::new())?;
+        let container = EbccContainer::open(Cursor::new(compacted))?;
+        assert_eq!(
+            container
+                .access_tags()
+                .clone()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [
+                (0, String::from("restricted")),
+                (2, String::from("embargoed"))
+            ]
+        );
+
+        // version 1 containers without an access manifest can still be read
+        let mut v1 = write_container(&data, &config)?;
+        v1[8..12].copy_from_slice(&1_u32.to_le_bytes());
+        let mut container = EbccContainer::open(Cursor::new(v1))?;
+        assert_eq!(container.access_tags().len(), 0);
+        // but only tagged after they have been upgraded by compaction
+        assert!(matches!(
+            container.set_access_tag(0, Some("restricted")),
+            Err(EBCCError::InvalidInput(_))
+        ));
+        container.set_access_tag(0, None)?;
+        let mut upgraded = EbccContainer::open(Cursor::new(container.compact_into(Vec::new())?))?;
+        upgraded.set_access_tag(0, Some("restricted"))?;
+        upgraded.inner.set_position(0);
+        assert_eq!(
+            EbccContainer::open(upgraded.inner)?.access_tag(0)?,
+            Some("restricted")
+        );
+        let mut v1 = bytes.clone();
+        v1[8..12].copy_from_slice(&1_u32.to_le_bytes());
+        assert!(EbccContainer::open(Cursor::new(v1)).is_err());
+
+        // corrupted manifests are detected
+        let mut corrupted = bytes;
+        let tag_byte = corrupted.len() - FOOTER_LEN as usize - 1;
+        corrupted[tag_byte] ^= 0xFF;
+        assert!(EbccContainer::open(Cursor::new(corrupted)).is_err());
+
+        // tagged frames must be pushed
+        let mut writer = EbccContainerWriter::new(Vec::new(), config, (32, 48))?;
+        writer.set_access_tag(0, "restricted")?;
+        assert!(matches!(
+            writer.finish(),
+            Err(EBCCError::FrameOutOfBounds {
+                frame: 0,
+                frames: 0
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata() -> EBCCResult<()> {
+        let data = testdata::temperature((4, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (32, 48))?;
+        writer.set_metadata("levels", vec![2])?;
+        writer.set_metadata("units", "K")?;
+        assert!(writer.set_metadata("", Vec::new()).is_err());
+        assert!(writer
+            .set_metadata(
+                "large",
+                vec![0; usize::try_from(MAX_METADATA_LEN).unwrap_or_default()]
+            )
+            .is_err());
+        for frame in data.outer_iter() {
+            writer.push_frame(frame)?;
+        }
+        let bytes = writer.finish()?;
+
+        let expected = BTreeMap::from([
+            (String::from("levels"), vec![2]),
+            (String::from("units"), b"K".to_vec()),
+        ]);
+        let mut container = EbccContainer::open(Cursor::new(bytes.clone()))?;
+        assert_eq!(container.metadata(), &expected);
+        assert_eq!(container.access_tags().len(), 0);
+        assert_eq!(container.orphaned_bytes(), 0);
+
+        // the metadata is kept by edits, compaction, and splitting
+        container.set_access_tag(1, Some("restricted"))?;
+        container.delete_frame(2)?;
+        let compacted = container.compact_into(Vec::new())?;
+        assert_eq!(
+            EbccContainer::open(Cursor::new(compacted))?.metadata(),
+            &expected
+        );
+        let part = split(Cursor::new(bytes.as_slice()), 1..3, Vec::new())?;
+        assert_eq!(
+            EbccContainer::open(Cursor::new(part))?.metadata(),
+            &expected
+        );
+
+        // only containers with the same metadata can be concatenated
+        let merged = concat([Cursor::new(&bytes), Cursor::new(&bytes)], Vec::new())?;
+        assert_eq!(
+            EbccContainer::open(Cursor::new(merged))?.metadata(),
+            &expected
+        );
+        let plain = write_container(&data, &config)?;
+        assert!(matches!(
+            concat([Cursor::new(&bytes), Cursor::new(&plain)], Vec::new()),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        // version 2 containers have no metadata
+        let mut v2 = bytes;
+        v2[8..12].copy_from_slice(&2_u32.to_le_bytes());
+        assert!(EbccContainer::open(Cursor::new(v2)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefetch_frames() -> EBCCResult<()> {
+        struct Recorder<'a> {
+            inner: Cursor<&'a [u8]>,
+            prefetched: Vec<Vec<Range<u64>>>,
+        }
+
+        impl Read for Recorder<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.inner.read(buf)
+            }
+        }
+
+        impl Seek for Recorder<'_> {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.inner.seek(pos)
+            }
+        }
+
+        impl Prefetch for Recorder<'_> {
+            fn prefetch(&mut self, ranges: &[Range<u64>]) -> io::Result<()> {
+                self.prefetched.push(ranges.to_vec());
+                Ok(())
+            }
+        }
+
+        let data = testdata::temperature((4, 32, 32));
+        let config = EBCCConfig::new();
+        let bytes = write_container(&data, &config)?;
+
+        let mut container = EbccContainer::open(Cursor::new(bytes))?;
+        container.replace_frame(2, data.index_axis(Axis(0), 2), &config)?;
+        let bytes = container.into_inner().into_inner();
+
+        let mut container = EbccContainer::open(Recorder {
+            inner: Cursor::new(bytes.as_slice()),
+            prefetched: Vec::new(),
+        })?;
+        container.prefetch_frames(0..4)?;
+        container.prefetch_frames(4..4)?;
+        assert!(container.prefetch_frames(3..5).is_err());
+
+        let index = container.index.clone();
+        let recorder = container.into_inner();
+        // frames 0 and 1 are adjacent, while frame 2 has been moved to the end
+        assert_eq!(
+            recorder.prefetched,
+            [[
+                index[0].offset..index[1].offset + index[1].len,
+                index[3].offset..index[3].offset + index[3].len,
+                index[2].offset..index[2].offset + index[2].len,
+            ]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_integrity() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 32));
+        let bytes = write_container(&data, &EBCCConfig::new())?;
+
+        let report = verify_integrity(Cursor::new(bytes.as_slice()))?;
+        assert!(report.is_ok());
+        assert_eq!(report.frames, 3);
+        assert_eq!(report.deleted_frames, 0);
+
+        let mut container = EbccContainer::open(Cursor::new(bytes))?;
+        container.delete_frame(2)?;
+        let mut corrupted = container.into_inner().into_inner();
+        corrupted[HEADER_LEN as usize + 1] ^= 0xFF;
+
+        let report = verify_integrity(Cursor::new(corrupted.as_slice()))?;
+        assert!(!report.is_ok());
+        assert_eq!(report.corrupted_frames, [0]);
+        assert_eq!(report.deleted_frames, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_container_detects_corruption() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 32));
+        let bytes = write_container(&data, &EBCCConfig::new())?;
+
+        let mut corrupted = bytes.clone();
+        corrupted[HEADER_LEN as usize + 1] ^= 0xFF;
+        let mut container = EbccContainer::open(Cursor::new(corrupted))?;
+        let mut frame = Array::zeros((32, 32));
+        assert!(container.decode_frame_into(0, frame.view_mut()).is_err());
+        assert!(container.decode_frame_into(1, frame.view_mut()).is_ok());
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(EbccContainer::open(Cursor::new(truncated)).is_err());
+
+        let mut corrupted_index = bytes.clone();
+        let index_byte = corrupted_index.len() - FOOTER_LEN as usize - 1;
+        corrupted_index[index_byte] ^= 0xFF;
+        assert!(EbccContainer::open(Cursor::new(corrupted_index)).is_err());
+
+        Ok(())
+    }
+}
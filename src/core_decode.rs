@@ -0,0 +1,150 @@
+//! Reduced decode-only API for `no_std` targets and builds without `ndarray`.
+
+use alloc::{string::String, vec::Vec};
+use core::ptr;
+
+pub use ebcc_sys::EBCC_NDIMS;
+#[cfg(feature = "ndarray")]
+use ndarray::{ArrayView, ArrayViewMut, Dim, Ix};
+
+use crate::cbuffer::CBuffer;
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::{header_payload_mut, verify_decompressed_checksum};
+use crate::size::{check_slice_len, data_len};
+use crate::sync::{c_call_failure, with_ebcc_call};
+
+/// EBCC data dimension.
+#[cfg(feature = "ndarray")]
+pub type EbccDim = Dim<[Ix; EBCC_NDIMS]>;
+
+/// Common prefix of the magic bytes of all EBCC formats that wrap the EBCC C
+/// library payload, e.g. frame streams and containers
+const EBCC_FORMAT_MAGIC_PREFIX: &[u8; 4] = b"EBCC";
+
+/// Decode a single EBCC payload into a 3D data array.
+///
+/// This is the reduced decode function of `no_std` builds. The
+/// `compressed_data` must be a single payload of the EBCC C library, with or
+/// without an [`EBCCHeader`][crate::EBCCHeader]. The header's shape and
+/// checksums are validated against the `decompressed_data` like with the
+/// `std` feature. All other EBCC formats, e.g. frame streams and containers,
+/// require the `std` and `ndarray` features.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::ShapeTooLarge`] if the size of the `decompressed_data`
+///   does not fit into the address space
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` is in another EBCC
+///   format, or if its header is invalid
+/// - [`EBCCError::ShapeMismatch`] or [`EBCCError::SwappedDimensions`] if the
+///   header's shape differs from the `decompressed_data`
+/// - [`EBCCError::ChecksumMismatch`] if a checksum does not match
+/// - [`EBCCError::DecompressionError`] if decompression with EBCC fails
+/// - [`EBCCError::SizeMismatch`] if the decompressed data does not fit into
+///   `decompressed_data`
+#[cfg(feature = "ndarray")]
+pub fn ebcc_decode_into(
+    compressed_data: &[u8],
+    mut decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    let shape = decompressed_data.dim();
+
+    decode_with(compressed_data, shape, |decompressed_buffer| {
+        let decompressed_view =
+            ArrayView::from_shape(shape, decompressed_buffer).map_err(|_| {
+                EBCCError::SizeMismatch {
+                    expected: shape.into(),
+                    actual: decompressed_buffer.len(),
+                }
+            })?;
+        decompressed_data.assign(&decompressed_view);
+        Ok(())
+    })
+}
+
+/// Decode a single EBCC payload into a flat slice of data with the
+/// `(frames, height, width)` `shape` in standard (row-major) order.
+///
+/// This is the reduced decode function of `no_std` builds and of builds
+/// without the `ndarray` feature. Like `ebcc_decode_into`, it only decodes
+/// single payloads of the EBCC C library, with or without an
+/// [`EBCCHeader`][crate::EBCCHeader].
+///
+/// # Errors
+///
+/// - [`EBCCError::SliceLenMismatch`] if the `decompressed_data` does not
+///   have the number of elements of the `shape`
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::ShapeTooLarge`] if the size of the `shape` does not fit
+///   into the address space
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` is in another EBCC
+///   format, or if its header is invalid
+/// - [`EBCCError::ShapeMismatch`] or [`EBCCError::SwappedDimensions`] if the
+///   header's shape differs from the `shape`
+/// - [`EBCCError::ChecksumMismatch`] if a checksum does not match
+/// - [`EBCCError::DecompressionError`] if decompression with EBCC fails
+/// - [`EBCCError::SizeMismatch`] if the decompressed data does not have the
+///   `shape`
+pub fn ebcc_decode_into_slice(
+    compressed_data: &[u8],
+    decompressed_data: &mut [f32],
+    shape: (usize, usize, usize),
+) -> EBCCResult<()> {
+    check_slice_len(decompressed_data.len(), shape)?;
+
+    decode_with(compressed_data, shape, |decompressed_buffer| {
+        if decompressed_buffer.len() != decompressed_data.len() {
+            return Err(EBCCError::SizeMismatch {
+                expected: shape.into(),
+                actual: decompressed_buffer.len(),
+            });
+        }
+        decompressed_data.copy_from_slice(decompressed_buffer);
+        Ok(())
+    })
+}
+
+/// Decode a single EBCC payload of the `shape` and pass the decompressed
+/// buffer to `f`.
+fn decode_with(
+    compressed_data: &[u8],
+    shape: (usize, usize, usize),
+    f: impl FnOnce(&[f32]) -> EBCCResult<()>,
+) -> EBCCResult<()> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    data_len(shape)?;
+
+    let mut compressed_data = Vec::from(compressed_data); // C function may modify the input
+    let (payload, checksum) = header_payload_mut(&mut compressed_data, shape)?;
+
+    if payload.starts_with(EBCC_FORMAT_MAGIC_PREFIX) {
+        return Err(EBCCError::InvalidInput(String::from(
+            "Only single EBCC payloads can be decoded without the std and ndarray features",
+        )));
+    }
+
+    let mut out_buffer: *mut f32 = ptr::null_mut();
+    #[expect(unsafe_code)]
+    let decompressed_size = with_ebcc_call(|| unsafe {
+        ebcc_sys::ebcc_decode(payload.as_mut_ptr(), payload.len(), &raw mut out_buffer)
+    });
+
+    #[expect(unsafe_code)]
+    // Safety: out_buffer was allocated by EBCC and has decompressed_size elements
+    let decompressed_buffer = unsafe { CBuffer::new(out_buffer, decompressed_size) };
+    let Some(decompressed_buffer) = decompressed_buffer else {
+        return Err(EBCCError::DecompressionError(c_call_failure(
+            String::from("ebcc_decode returned no decompressed data"),
+            out_buffer.is_null(),
+        )));
+    };
+    let decompressed_buffer = decompressed_buffer.as_slice();
+
+    verify_decompressed_checksum(checksum, decompressed_buffer)?;
+
+    f(decompressed_buffer)
+}
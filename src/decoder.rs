@@ -0,0 +1,436 @@
+//! Reusable EBCC decoder context.
+
+#[cfg(feature = "decode")]
+use std::io::Cursor;
+
+use ndarray::ArrayViewMut;
+
+use crate::adaptive::is_ebcc_tiled;
+use crate::capture::{capture_call, CaptureInput};
+#[cfg(not(feature = "decode"))]
+use crate::codec::decode_unsupported;
+use crate::codec::{ebcc_decode_frames_into_mut, EbccDim};
+use crate::container::is_ebcc_container;
+#[cfg(feature = "decode")]
+use crate::container::EbccContainer;
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+use crate::layered::decode_approximation_into;
+use crate::limits::{EBCCDecodeOptions, EBCCLimits};
+use crate::sketch::ebcc_inspect;
+use crate::stream::{ebcc_decode_stream_body_with_scratch, is_ebcc_stream, EBCC_STREAM_MAGIC};
+
+/// Reusable decoder for decompressing many arrays into caller-owned buffers.
+///
+/// Each call to [`ebcc_decode_into`][crate::ebcc_decode_into] allocates a
+/// staging copy of the compressed data, which EBCC may modify. An
+/// [`EbccDecoder`] instead owns this buffer and reuses it across calls.
+///
+/// EBCC always decompresses into a buffer that it allocates itself, from
+/// which the data is copied once into the output array. When the output
+/// array is contiguous in standard (row-major) layout, this copy is a single
+/// `memcpy` and no further intermediate array is created.
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_encode, EBCCConfig, EbccDecoder};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+/// let mut decoder = EbccDecoder::new();
+/// let mut decompressed = Array::zeros((1, 32, 32));
+///
+/// for i in 0..4 {
+///     let chunk = Array::from_elem((1, 32, 32), i as f32);
+///     let compressed = ebcc_encode(chunk.view(), &config)?;
+///
+///     decoder.decode_into(&compressed, decompressed.view_mut())?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EbccDecoder {
+    limits: EBCCLimits,
+    compressed: Vec<u8>,
+}
+
+impl EbccDecoder {
+    /// Create a new decoder with the default [`EBCCLimits`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            limits: EBCCLimits::new(),
+            compressed: Vec::new(),
+        }
+    }
+
+    /// Change the limits on the number and size of frames, and on the size of
+    /// the input and output, that are decoded.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: EBCCLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The limits on the number and size of frames that are decoded.
+    #[must_use]
+    pub const fn limits(&self) -> &EBCCLimits {
+        &self.limits
+    }
+
+    /// Decode into a 3D data array using EBCC decompression.
+    ///
+    /// The `compressed_data` may have been produced by
+    /// [`ebcc_encode`][crate::ebcc_encode] or by an
+    /// [`EbccStreamEncoder`][crate::EbccStreamEncoder].
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::TooManyFrames`] or [`EBCCError::FrameTooLarge`] if the
+    ///   shape of the `decompressed_data` exceeds the decoder's
+    ///   [`limits`][Self::limits]
+    /// - [`EBCCError::InputTooLarge`] or [`EBCCError::OutputTooLarge`] if the
+    ///   size of the `compressed_data` or of the `decompressed_data` exceeds
+    ///   the decoder's [`limits`][Self::limits]
+    /// - all other errors that [`ebcc_decode_into`][crate::ebcc_decode_into]
+    ///   can return
+    pub fn decode_into(
+        &mut self,
+        compressed_data: &[u8],
+        decompressed_data: ArrayViewMut<f32, EbccDim>,
+    ) -> EBCCResult<()> {
+        let shape = decompressed_data.dim();
+        capture_call(CaptureInput::decode(compressed_data, shape), || {
+            if compressed_data.is_empty() {
+                return Err(EBCCError::EmptyInput);
+            }
+
+            self.limits.check_shape(decompressed_data.dim())?;
+            self.limits.check_input_bytes(compressed_data.len())?;
+            self.limits.check_output_bytes(
+                decompressed_data
+                    .len()
+                    .saturating_mul(std::mem::size_of::<f32>()),
+            )?;
+
+            if let Some(mut stream_body) =
+                compressed_data.strip_prefix(EBCC_STREAM_MAGIC.as_slice())
+            {
+                return ebcc_decode_stream_body_with_scratch(
+                    &mut stream_body,
+                    &mut self.compressed,
+                    decompressed_data,
+                );
+            }
+
+            if is_ebcc_container(compressed_data) {
+                #[cfg(feature = "decode")]
+                return EbccContainer::open(Cursor::new(compressed_data))?
+                    .decode_into(decompressed_data);
+                #[cfg(not(feature = "decode"))]
+                return Err(decode_unsupported());
+            }
+
+            // C function may modify the input
+            self.compressed.clear();
+            self.compressed.extend_from_slice(compressed_data);
+
+            ebcc_decode_frames_into_mut(&mut self.compressed, decompressed_data)
+        })
+    }
+
+    /// Release the memory held by the reusable buffer.
+    pub fn shrink_to_fit(&mut self) {
+        self.compressed = Vec::new();
+    }
+}
+
+/// Decode compressed data from an untrusted source into a 3D data array,
+/// bounding the resources that decoding may use.
+///
+/// Before anything is decoded, the shape of the `decompressed_data` is
+/// checked against the [`options`][EBCCDecodeOptions]. All formats that are
+/// decoded validate their declared shape against the `decompressed_data`
+/// before calling into the EBCC C library, so that corrupted or malicious
+/// data cannot make decoding allocate more than the output or loop over more
+/// frames than it has. If
+/// [`strict_header`][EBCCDecodeOptions::strict_header] is set, legacy
+/// headerless payloads, whose shape cannot be validated, are rejected.
+///
+/// <div class="warning">
+///
+/// **Warning:** The EBCC C library allocates its output according to the
+/// shape that is stored inside each EBCC payload, which this crate cannot
+/// parse. The [`EBCCHeader`][crate::EBCCHeader] checksum detects accidental
+/// corruption of this shape, but not a deliberately crafted payload.
+///
+/// </div>
+///
+/// # Errors
+///
+/// - [`EBCCError::TooManyFrames`] or [`EBCCError::OutputTooLarge`] if the
+///   shape of the `decompressed_data` exceeds the `options`
+/// - [`EBCCError::InvalidInput`] if
+///   [`strict_header`][EBCCDecodeOptions::strict_header] is set and the
+///   `compressed_data` is a legacy headerless payload
+/// - [`EBCCError::ConfigMismatch`] if an
+///   [expected config][EBCCDecodeOptions::with_expected_config] is set and the
+///   `compressed_data`, or any frame of a container, was encoded with a
+///   different configuration, or [`EBCCError::InvalidInput`] if it, or any
+///   frame of a container, records no config fingerprint
+/// - all other errors that [`ebcc_decode_into`][crate::ebcc_decode_into], or
+///   [`ebcc_decode_approximation_into`][crate::ebcc_decode_approximation_into] if
+///   [`approximation_only`][EBCCDecodeOptions::approximation_only] is set, can
+///   return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_with_options, ebcc_encode, EBCCConfig, EBCCDecodeOptions};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_elem((1, 32, 32), 1.0_f32);
+/// let compressed = ebcc_encode(data.view(), &EBCCConfig::new())?;
+///
+/// let options = EBCCDecodeOptions::new().with_max_output_bytes(1 << 20);
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_with_options(&compressed, decompressed.view_mut(), &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_with_options(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+    options: &EBCCDecodeOptions,
+) -> EBCCResult<()> {
+    let shape = decompressed_data.dim();
+    capture_call(CaptureInput::decode(compressed_data, shape), || {
+        decode_with_options(compressed_data, decompressed_data, options)
+    })
+}
+
+/// Decode compressed data like [`ebcc_decode_with_options`], without
+/// capturing a failed call.
+fn decode_with_options(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+    options: &EBCCDecodeOptions,
+) -> EBCCResult<()> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    options.check_shape(decompressed_data.dim())?;
+
+    if options.strict_header
+        && EBCCHeader::parse(compressed_data)?.is_none()
+        && !is_ebcc_stream(compressed_data)
+        && !is_ebcc_container(compressed_data)
+        && !is_ebcc_tiled(compressed_data)
+    {
+        return Err(EBCCError::InvalidInput(String::from(
+            "Compressed data has no self-describing header",
+        )));
+    }
+
+    if let Some(expected) = options.expected_config_fingerprint {
+        let inspection = ebcc_inspect(compressed_data)?;
+
+        // every frame of a container must match, and all other data records
+        //  a single fingerprint
+        let fingerprints = if is_ebcc_container(compressed_data) {
+            inspection.frame_config_fingerprints
+        } else {
+            vec![inspection.config_fingerprint]
+        };
+
+        for fingerprint in fingerprints {
+            match fingerprint {
+                Some(actual) if actual == expected => (),
+                Some(actual) => return Err(EBCCError::ConfigMismatch { expected, actual }),
+                None => {
+                    return Err(EBCCError::InvalidInput(String::from(
+                        "Compressed data records no config fingerprint",
+                    )))
+                }
+            }
+        }
+    }
+
+    if options.approximation_only {
+        return decode_approximation_into(compressed_data, decompressed_data, options);
+    }
+
+    // the output size is bounded by the options instead
+    let mut limits = EBCCLimits::unlimited().with_max_frames(options.max_frames);
+    limits.expected_frame_shape = options.expected_frame_shape;
+
+    EbccDecoder::new()
+        .with_limits(limits)
+        .decode_into(compressed_data, decompressed_data)
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use ndarray::{Array, ShapeBuilder};
+
+    use super::*;
+    use crate::codec::ebcc_encode_c_buffer;
+    use crate::{
+        ebcc_decode_into, ebcc_encode, ebcc_encode_per_frame, testdata, EBCCConfig,
+        EbccStreamEncoder,
+    };
+
+    #[test]
+    fn test_decoder_matches_ebcc_decode_into() -> EBCCResult<()> {
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let mut decoder = EbccDecoder::new();
+
+        for frames in [1, 3, 2] {
+            let data = testdata::temperature((frames, 32, 48));
+
+            let mut encoder =
+                EbccStreamEncoder::new(Vec::new(), config.clone(), (32, 48), NonZeroUsize::MIN)?;
+            for frame in data.outer_iter() {
+                encoder.push_frame(frame)?;
+            }
+
+            for compressed in [ebcc_encode(data.view(), &config)?, encoder.finish()?] {
+                let mut expected = Array::zeros(data.dim());
+                ebcc_decode_into(&compressed, expected.view_mut())?;
+
+                // both contiguous and Fortran-order outputs are supported
+                let mut contiguous = Array::zeros(data.dim());
+                decoder.decode_into(&compressed, contiguous.view_mut())?;
+                assert_eq!(contiguous, expected);
+
+                let mut fortran = Array::zeros(data.dim().f());
+                decoder.decode_into(&compressed, fortran.view_mut())?;
+                assert_eq!(fortran, expected);
+            }
+        }
+
+        assert!(decoder
+            .decode_into(&[], Array::zeros((1, 32, 48)).view_mut())
+            .is_err());
+
+        let data = testdata::temperature((3, 32, 48));
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let mut decoder = EbccDecoder::new().with_limits(EBCCLimits::new().with_max_frames(2));
+        assert!(decoder
+            .decode_into(&compressed, Array::zeros(data.dim()).view_mut())
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_with_options() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let mut decompressed = Array::zeros(data.dim());
+
+        let options = EBCCDecodeOptions::new();
+        ebcc_decode_with_options(&compressed, decompressed.view_mut(), &options)?;
+
+        assert!(matches!(
+            ebcc_decode_with_options(
+                &compressed,
+                decompressed.view_mut(),
+                &options.with_max_output_bytes(3 * 32 * 48 * 4 - 1)
+            ),
+            Err(EBCCError::OutputTooLarge {
+                bytes: 18432,
+                limit: 18431
+            })
+        ));
+        assert!(matches!(
+            ebcc_decode_with_options(
+                &compressed,
+                decompressed.view_mut(),
+                &options.with_max_frames(2)
+            ),
+            Err(EBCCError::TooManyFrames {
+                frames: 3,
+                limit: 2
+            })
+        ));
+
+        // legacy headerless payloads are only decoded without a strict header
+        let legacy = ebcc_encode_c_buffer(data.view(), &config)?;
+        assert!(
+            ebcc_decode_with_options(legacy.as_slice(), decompressed.view_mut(), &options).is_err()
+        );
+        ebcc_decode_with_options(
+            legacy.as_slice(),
+            decompressed.view_mut(),
+            &options.with_strict_header(false),
+        )?;
+
+        // chunks that were encoded with stale settings are detected
+        ebcc_decode_with_options(
+            &compressed,
+            decompressed.view_mut(),
+            &options.with_expected_config(&config),
+        )?;
+        let stale = EBCCConfig::max_absolute_error_bounded(0.2);
+        assert!(matches!(
+            ebcc_decode_with_options(
+                &compressed,
+                decompressed.view_mut(),
+                &options.with_expected_config(&stale)
+            ),
+            Err(EBCCError::ConfigMismatch { expected, actual })
+                if expected == stale.fingerprint() && actual == config.fingerprint()
+        ));
+        assert!(ebcc_decode_with_options(
+            legacy.as_slice(),
+            decompressed.view_mut(),
+            &options
+                .with_strict_header(false)
+                .with_expected_config(&config),
+        )
+        .is_err());
+
+        // every frame of a container must match the expected config
+        let uniform = ebcc_encode_per_frame(
+            data.view(),
+            &[config.clone(), config.clone(), config.clone()],
+        )?;
+        ebcc_decode_with_options(
+            &uniform,
+            decompressed.view_mut(),
+            &options.with_expected_config(&config),
+        )?;
+        let mixed = ebcc_encode_per_frame(
+            data.view(),
+            &[config.clone(), stale.clone(), config.clone()],
+        )?;
+        assert!(matches!(
+            ebcc_decode_with_options(
+                &mixed,
+                decompressed.view_mut(),
+                &options.with_expected_config(&config)
+            ),
+            Err(EBCCError::ConfigMismatch { expected, actual })
+                if expected == config.fingerprint() && actual == stale.fingerprint()
+        ));
+
+        Ok(())
+    }
+}
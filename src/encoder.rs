@@ -0,0 +1,158 @@
+//! Buffer-reusing EBCC encoding helper.
+
+use ndarray::ArrayView;
+
+use crate::accounting::{record_alloc, record_copy};
+use crate::codec::{ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::EBCCResult;
+use crate::header::{write_header, EBCCHeader};
+
+/// Helper that reuses its staging and output buffers when compressing many
+/// same-shaped arrays with the same configuration.
+///
+/// Each call to [`ebcc_encode`][crate::ebcc_encode] allocates a staging copy
+/// of the input data, which EBCC may modify, and a fresh output [`Vec<u8>`].
+/// An [`EbccBufferedEncoder`] instead owns these two buffers and reuses them
+/// across calls, so that encoding thousands of same-shaped chunks does not
+/// churn through full-array allocations. It still copies the data into and
+/// out of them, and every call sets up the `JPEG2000` and zstd state of the
+/// EBCC C library from scratch, just like [`ebcc_encode`][crate::ebcc_encode].
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_into, EBCCConfig, EbccBufferedEncoder};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let mut encoder = EbccBufferedEncoder::new(EBCCConfig::max_absolute_error_bounded(0.1))?;
+///
+/// for i in 0..4 {
+///     let chunk = Array::from_elem((1, 32, 32), i as f32);
+///     let compressed = encoder.encode(chunk.view())?;
+///
+///     let mut decompressed = Array::zeros(chunk.dim());
+///     ebcc_decode_into(compressed, decompressed.view_mut())?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EbccBufferedEncoder {
+    config: EBCCConfig,
+    input: Vec<f32>,
+    output: Vec<u8>,
+}
+
+impl EbccBufferedEncoder {
+    /// Create a new encoder with the given `config`.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidConfig`][crate::EBCCError::InvalidConfig] if
+    ///   [`config.validate`][EBCCConfig::validate] fails
+    pub fn new(config: EBCCConfig) -> EBCCResult<Self> {
+        config.validate()?;
+
+        Ok(Self {
+            config,
+            input: Vec::new(),
+            output: Vec::new(),
+        })
+    }
+
+    /// The configuration that this encoder uses.
+    #[must_use]
+    pub const fn config(&self) -> &EBCCConfig {
+        &self.config
+    }
+
+    /// Encode a 3D data array using EBCC compression.
+    ///
+    /// The returned compressed bytes borrow the encoder's output buffer and
+    /// are identical to the output of [`ebcc_encode`][crate::ebcc_encode].
+    ///
+    /// # Errors
+    ///
+    /// - all errors that [`ebcc_encode`][crate::ebcc_encode] can return
+    pub fn encode(&mut self, data: ArrayView<f32, EbccDim>) -> EBCCResult<&[u8]> {
+        let payload = ebcc_encode_c_buffer_with_scratch(data, &self.config, &mut self.input)?;
+        let payload = payload.as_slice();
+
+        self.output.clear();
+        let capacity = self.output.capacity();
+        self.output
+            .reserve(EBCCHeader::encoded_len_with(self.config.checksum_algorithm) + payload.len());
+        // only a growing output buffer is a new allocation
+        record_alloc(self.output.capacity().saturating_sub(capacity));
+        write_header(&mut self.output, data, &self.config, payload)?;
+        self.output.extend_from_slice(payload);
+        record_copy();
+
+        Ok(&self.output)
+    }
+
+    /// Release the memory held by the reusable buffers.
+    pub fn shrink_to_fit(&mut self) {
+        self.input = Vec::new();
+        self.output = Vec::new();
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::{ebcc_encode, ebcc_measure_allocations, testdata};
+
+    #[test]
+    fn test_encoder_matches_ebcc_encode() -> EBCCResult<()> {
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let mut encoder = EbccBufferedEncoder::new(config.clone())?;
+
+        for frames in [1, 3, 2] {
+            let data = testdata::temperature((frames, 32, 48));
+            assert_eq!(
+                encoder.encode(data.view())?,
+                ebcc_encode(data.view(), &config)?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encoder_measures_copies() -> EBCCResult<()> {
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let mut encoder = EbccBufferedEncoder::new(config.clone())?;
+        let data = testdata::temperature((2, 32, 48));
+
+        // the encoder copies the data like ebcc_encode, once on each side of
+        //  the C library
+        let (compressed, stats) = ebcc_measure_allocations(|| ebcc_encode(data.view(), &config));
+        let compressed = compressed?;
+        assert_eq!(stats.copies, 2);
+        let (result, encoder_stats) =
+            ebcc_measure_allocations(|| encoder.encode(data.view()).map(<[u8]>::len));
+        assert_eq!(result?, compressed.len());
+        assert_eq!(encoder_stats, stats);
+
+        // the reused output buffer is no new allocation, but still a copy
+        let (result, reused_stats) =
+            ebcc_measure_allocations(|| encoder.encode(data.view()).map(<[u8]>::len));
+        assert_eq!(result?, compressed.len());
+        assert_eq!(reused_stats.copies, 2);
+        assert!(reused_stats.peak_bytes <= encoder_stats.peak_bytes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encoder_rejects_invalid_config() {
+        assert!(EbccBufferedEncoder::new(EBCCConfig::new().with_base_cr(-1.0)).is_err());
+    }
+}
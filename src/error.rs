@@ -1,12 +1,21 @@
 //! Error types for EBCC operations.
 
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
 use thiserror::Error;
 
 /// Result type for EBCC operations.
 pub type EBCCResult<T> = Result<T, EBCCError>;
 
 /// Errors that can occur during EBCC compression/decompression.
+///
+/// Common errors are reported by typed variants, whose fields describe the
+/// failure without allocating and can be matched on. Their message is only
+/// formatted when the error is displayed. Rarer errors, e.g. malformed
+/// compressed data, carry a message instead.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum EBCCError {
     #[error("Invalid input data: {0}")]
     /// Invalid input data
@@ -18,9 +27,513 @@ pub enum EBCCError {
 
     #[error("Compression failed: {0}")]
     /// Compression failed
-    CompressionError(String),
+    CompressionError(EBCCFailure),
 
     #[error("Decompression failed: {0}")]
     /// Decompression failed
-    DecompressionError(String),
+    DecompressionError(EBCCFailure),
+
+    #[cfg(feature = "std")]
+    #[error("I/O error: {0}")]
+    /// Reading or writing compressed data failed
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid input data: Compressed data is empty")]
+    /// The compressed data is empty
+    EmptyInput,
+
+    #[error("Invalid input data: All dimensions must be > 0")]
+    /// The data has a zero-size dimension
+    EmptyDimension,
+
+    #[error("Invalid input data: EBCC requires tile dimensions of at least {} and internal image dimensions at most {}, got shape {depth}x{height}x{width}", ebcc_sys::EBCC_MIN_INTERNAL_IMAGE_DIM, ebcc_sys::EBCC_MAX_INTERNAL_IMAGE_DIM)]
+    /// The data shape is outside the range supported by EBCC
+    UnsupportedShape {
+        /// Number of frames
+        depth: usize,
+        /// Height of each frame
+        height: usize,
+        /// Width of each frame
+        width: usize,
+    },
+
+    #[error("Invalid input data: Non-finite value {value} at index {index:?}")]
+    /// The data contains a non-finite (infinite or NaN) value
+    NonFinite {
+        /// `(frame, y, x)` index of the first non-finite value
+        index: [usize; 3],
+        /// The non-finite value
+        value: f32,
+    },
+
+    #[error("Invalid input data: Compressed data has shape {expected:?} but output array has shape {actual:?}")]
+    /// The shape of the compressed data does not match the output array
+    ShapeMismatch {
+        /// Shape of the compressed data
+        expected: [usize; 3],
+        /// Shape of the output array
+        actual: [usize; 3],
+    },
+
+    #[error("Invalid input data: Frame should be of shape {expected:?} but has shape {actual:?}")]
+    /// A frame does not have the expected `[height, width]` shape
+    FrameShapeMismatch {
+        /// Expected frame shape
+        expected: [usize; 2],
+        /// Actual frame shape
+        actual: [usize; 2],
+    },
+
+    #[error("Invalid input data: Frames should be of shape {expected:?} but have the transposed shape {actual:?}, the height and width are likely swapped")]
+    /// The `[height, width]` frame shape is the transposition of the expected
+    /// frame shape, e.g. `1440x721` instead of `721x1440`
+    SwappedDimensions {
+        /// Expected frame shape
+        expected: [usize; 2],
+        /// Actual, transposed, frame shape
+        actual: [usize; 2],
+    },
+
+    #[error("Invalid input data: Decompressed data should be of shape {expected:?} but decompressed to {actual} elements")]
+    /// EBCC decompressed a different number of elements than expected
+    SizeMismatch {
+        /// Expected shape of the decompressed data
+        expected: [usize; 3],
+        /// Number of decompressed elements
+        actual: usize,
+    },
+
+    #[error("Invalid input data: Slice of {len} elements does not have the shape {shape:?}")]
+    /// A flat slice of data does not have the number of elements of its
+    /// `[frames, height, width]` shape
+    SliceLenMismatch {
+        /// Shape of the data
+        shape: [usize; 3],
+        /// Number of elements of the slice
+        len: usize,
+    },
+
+    #[error(
+        "Invalid input data: Size {value} does not fit into the {}-bit usize of this target",
+        usize::BITS
+    )]
+    /// A size that is stored as `u64` does not fit into a `usize`, e.g. on a
+    /// 32-bit target
+    UsizeOverflow {
+        /// Stored size
+        value: u64,
+    },
+
+    #[error("Invalid input data: Data of shape {shape:?} does not fit into the {}-bit address space of this target", usize::BITS)]
+    /// The size of the data, in bytes, exceeds [`isize::MAX`], e.g. on a
+    /// 32-bit target
+    ShapeTooLarge {
+        /// Shape of the data
+        shape: [usize; 3],
+    },
+
+    #[error("Invalid input data: {frames} frames exceed the limit of {limit} frames")]
+    /// The number of frames exceeds the [`EBCCLimits`][crate::EBCCLimits]
+    TooManyFrames {
+        /// Number of frames
+        frames: usize,
+        /// Maximum number of frames
+        limit: usize,
+    },
+
+    #[error("Invalid input data: Frames of shape [{height}, {width}] exceed the limit of {limit} elements per frame")]
+    /// The frame size exceeds the [`EBCCLimits`][crate::EBCCLimits]
+    FrameTooLarge {
+        /// Height of each frame
+        height: usize,
+        /// Width of each frame
+        width: usize,
+        /// Maximum number of elements per frame
+        limit: usize,
+    },
+
+    #[error("Invalid input data: Input of {bytes} bytes exceeds the limit of {limit} bytes")]
+    /// The size of the input exceeds the [`EBCCLimits`][crate::EBCCLimits]
+    InputTooLarge {
+        /// Size of the input, in bytes
+        bytes: usize,
+        /// Maximum size of the input, in bytes
+        limit: usize,
+    },
+
+    #[error("Invalid input data: Output of {bytes} bytes exceeds the limit of {limit} bytes")]
+    /// The size of the output, e.g. of the decompressed data, exceeds the
+    /// [`EBCCLimits`][crate::EBCCLimits] or the
+    /// [`EBCCDecodeOptions`][crate::EBCCDecodeOptions]
+    OutputTooLarge {
+        /// Size of the output, in bytes
+        bytes: usize,
+        /// Maximum size of the output, in bytes
+        limit: usize,
+    },
+
+    #[error(
+        "Invalid input data: Output buffer of {available} bytes is too small for {required} bytes"
+    )]
+    /// A caller-provided output buffer is too small, see
+    /// [`ebcc_encode_into_slice`][crate::ebcc_encode_into_slice]
+    BufferTooSmall {
+        /// Required size of the output buffer, in bytes
+        required: usize,
+        /// Size of the provided output buffer, in bytes
+        available: usize,
+    },
+
+    #[error("Timed out: The job did not finish within {limit:?}")]
+    /// A service job did not finish within the timeout of its
+    /// [`EBCCLimits`][crate::EBCCLimits]
+    TimedOut {
+        /// Maximum time of the job
+        limit: core::time::Duration,
+    },
+
+    #[error("Invalid input data: Frame {frame} is out of bounds for an EBCC container with {frames} frames")]
+    /// A container frame index is out of bounds
+    FrameOutOfBounds {
+        /// Frame index
+        frame: usize,
+        /// Number of frames in the container
+        frames: usize,
+    },
+
+    #[error("Invalid input data: Frame {frame} of the EBCC container has been deleted")]
+    /// A container frame has been deleted
+    FrameDeleted {
+        /// Frame index
+        frame: usize,
+    },
+
+    #[error("Access denied: Frame {frame} is tagged {tag:?}")]
+    /// Decoding a container frame with an access tag was denied by the
+    /// container's access hook, see
+    /// [`EbccContainer::set_access_hook`][crate::container::EbccContainer::set_access_hook]
+    AccessDenied {
+        /// Frame index
+        frame: usize,
+        /// Access tag of the frame
+        tag: String,
+    },
+
+    #[error("Invalid input data: {data} is corrupted: checksum mismatch")]
+    /// The checksum of compressed or decompressed data does not match
+    ChecksumMismatch {
+        /// The data whose checksum does not match
+        data: EBCCChecksummedData,
+    },
+
+    #[error("Invalid input data: compressed with the config fingerprint {actual:#018x} instead of the expected {expected:#018x}")]
+    /// The compressed data was encoded with a different configuration than
+    /// expected, see
+    /// [`EBCCDecodeOptions::with_expected_config`][crate::EBCCDecodeOptions::with_expected_config]
+    ConfigMismatch {
+        /// Fingerprint of the expected configuration
+        expected: u64,
+        /// Fingerprint that is recorded in the compressed data
+        actual: u64,
+    },
+
+    #[error(
+        "Decompression failed: Error {error} at index {index:?} exceeds the error bound {bound}"
+    )]
+    /// The decompressed data violates the requested error bound, see
+    /// [`verify::check_error_bound`][crate::verify::check_error_bound]
+    ErrorBoundViolated {
+        /// `(frame, y, x)` index of the first value that violates the bound
+        index: [usize; 3],
+        /// Absolute error of the value
+        error: f32,
+        /// Error bound, including its tolerance
+        bound: f32,
+    },
+
+    #[error("Compression failed: The compressed size of {compressed_bytes} bytes exceeds {max_ratio}x the raw size of {raw_bytes} bytes")]
+    /// The compressed data would expand beyond the maximum ratio of the
+    /// [`EBCCExpansionGuard`][crate::EBCCExpansionGuard]
+    ExpansionTooLarge {
+        /// Size of the compressed data in bytes
+        compressed_bytes: usize,
+        /// Size of the raw `f32` data in bytes
+        raw_bytes: usize,
+        /// Maximum ratio of compressed to raw size
+        max_ratio: f32,
+    },
+
+    #[error("Invalid configuration: Base compression ratio must be positive, got {base_cr}")]
+    /// The base compression ratio is non-positive
+    NonPositiveBaseCR {
+        /// Base compression ratio
+        base_cr: f32,
+    },
+
+    #[error("Invalid configuration: Error bound must be positive, got {error}")]
+    /// The absolute or relative error bound is non-positive
+    NonPositiveErrorBound {
+        /// Error bound
+        error: f32,
+    },
+}
+
+/// Error that the compressed data has the `expected` shape but the output
+/// array has the `actual` shape, which reports swapped frame dimensions with
+/// [`EBCCError::SwappedDimensions`].
+pub const fn shape_mismatch(expected: [usize; 3], actual: [usize; 3]) -> EBCCError {
+    let [expected_frames, expected_height, expected_width] = expected;
+    let [actual_frames, actual_height, actual_width] = actual;
+
+    if expected_frames == actual_frames
+        && expected_height == actual_width
+        && expected_width == actual_height
+        && expected_height != expected_width
+    {
+        return EBCCError::SwappedDimensions {
+            expected: [expected_height, expected_width],
+            actual: [actual_height, actual_width],
+        };
+    }
+
+    EBCCError::ShapeMismatch { expected, actual }
+}
+
+/// Data whose checksum is verified while decoding, see
+/// [`EBCCError::ChecksumMismatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EBCCChecksummedData {
+    /// The EBCC payload after an [`EBCCHeader`][crate::EBCCHeader], or one
+    /// tile of [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] data
+    CompressedPayload,
+    /// The decompressed data of an EBCC payload, whose checksum is stored in
+    /// its [`EBCCHeader`][crate::EBCCHeader]
+    DecompressedData,
+    /// One frame of an EBCC [`container`][crate::container]
+    ContainerFrame {
+        /// Frame index
+        frame: usize,
+    },
+}
+
+impl fmt::Display for EBCCChecksummedData {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CompressedPayload => fmt.write_str("EBCC payload"),
+            Self::DecompressedData => fmt.write_str("EBCC decompressed data"),
+            Self::ContainerFrame { frame } => write!(fmt, "EBCC container frame {frame}"),
+        }
+    }
+}
+
+/// Description of a failed compression or decompression, see
+/// [`EBCCError::CompressionError`] and [`EBCCError::DecompressionError`].
+///
+/// Besides its message, a failure carries its typed [`EBCCFailureCause`] and
+/// the error messages that the C libraries reported during the failed call,
+/// if any were captured, see [`ebcc_sys::ERROR_HOOK`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EBCCFailure {
+    message: String,
+    cause: EBCCFailureCause,
+    c_errors: Vec<String>,
+}
+
+impl EBCCFailure {
+    /// Create a failure with the given `message` and `cause`, which did not
+    /// capture any C error messages.
+    #[must_use]
+    pub fn new(message: impl Into<String>, cause: EBCCFailureCause) -> Self {
+        Self {
+            message: message.into(),
+            cause,
+            c_errors: Vec::new(),
+        }
+    }
+
+    /// Attach the `c_errors` messages that the C libraries reported, in the
+    /// order in which they were reported.
+    #[must_use]
+    pub fn with_c_errors(mut self, c_errors: Vec<String>) -> Self {
+        self.c_errors = c_errors;
+        self
+    }
+
+    /// The message that describes the failure, without the C error messages.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The typed cause of the failure.
+    #[must_use]
+    pub const fn cause(&self) -> EBCCFailureCause {
+        self.cause
+    }
+
+    /// The error messages that the C libraries reported during the failed
+    /// call, in the order in which they were reported.
+    ///
+    /// `OpenJPEG` usually reports the root cause first and a generic failure
+    /// last.
+    #[must_use]
+    pub fn c_errors(&self) -> &[String] {
+        &self.c_errors
+    }
+}
+
+impl From<String> for EBCCFailure {
+    fn from(message: String) -> Self {
+        Self::new(message, EBCCFailureCause::Other)
+    }
+}
+
+impl From<&str> for EBCCFailure {
+    fn from(message: &str) -> Self {
+        Self::new(message, EBCCFailureCause::Other)
+    }
+}
+
+impl fmt::Display for EBCCFailure {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.message)?;
+
+        for (i, c_error) in self.c_errors.iter().enumerate() {
+            fmt.write_str(if i == 0 { ", reporting: " } else { "; " })?;
+            fmt.write_str(c_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Typed cause of an [`EBCCFailure`], which callers can branch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EBCCFailureCause {
+    /// The Rust bindings detected the failure, e.g. an unsupported format
+    /// version, which the message describes
+    Other,
+    /// An EBCC C function returned no output buffer
+    NoOutput,
+    /// An EBCC C function returned an empty output buffer
+    EmptyOutput,
+    /// A zstd function returned an error
+    Zstd {
+        /// The `ZSTD_ErrorCode` of the error, e.g.
+        /// [`ebcc_sys::zstd::ZSTD_error_corruption_detected`]
+        code: u32,
+    },
+}
+
+/// Category of an [`EBCCError`], which callers can branch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EBCCErrorKind {
+    /// The input data, compressed data, or output array is invalid
+    InvalidInput,
+    /// The configuration is invalid
+    InvalidConfig,
+    /// The EBCC C library failed to compress valid input data
+    Compression,
+    /// The EBCC C library failed to decompress the compressed data
+    Decompression,
+    /// Reading or writing compressed data failed
+    Io,
+    /// A job did not finish within its timeout
+    TimedOut,
+    /// Access to tagged data was denied
+    AccessDenied,
+    /// The C libraries ran out of memory
+    OutOfMemory,
+}
+
+impl EBCCError {
+    /// The category of this error.
+    ///
+    /// Compression and decompression errors are categorized by their typed
+    /// [`EBCCFailureCause`]. Zstd errors that report an allocation failure
+    /// are categorized as [`EBCCErrorKind::OutOfMemory`], and zstd errors that
+    /// report corrupted or truncated compressed data as
+    /// [`EBCCErrorKind::InvalidInput`]. The EBCC C library only signals its
+    /// failures by returning no or an empty output buffer, so its failures
+    /// are categorized as compression or decompression errors.
+    #[must_use]
+    pub const fn kind(&self) -> EBCCErrorKind {
+        match self {
+            Self::InvalidInput(_)
+            | Self::EmptyInput
+            | Self::EmptyDimension
+            | Self::UnsupportedShape { .. }
+            | Self::NonFinite { .. }
+            | Self::ShapeMismatch { .. }
+            | Self::FrameShapeMismatch { .. }
+            | Self::SwappedDimensions { .. }
+            | Self::SizeMismatch { .. }
+            | Self::SliceLenMismatch { .. }
+            | Self::UsizeOverflow { .. }
+            | Self::ShapeTooLarge { .. }
+            | Self::TooManyFrames { .. }
+            | Self::FrameTooLarge { .. }
+            | Self::InputTooLarge { .. }
+            | Self::OutputTooLarge { .. }
+            | Self::BufferTooSmall { .. }
+            | Self::FrameOutOfBounds { .. }
+            | Self::FrameDeleted { .. }
+            | Self::ChecksumMismatch { .. }
+            | Self::ConfigMismatch { .. } => EBCCErrorKind::InvalidInput,
+            Self::InvalidConfig(_)
+            | Self::NonPositiveBaseCR { .. }
+            | Self::NonPositiveErrorBound { .. } => EBCCErrorKind::InvalidConfig,
+            Self::CompressionError(failure) | Self::DecompressionError(failure)
+                if is_zstd_out_of_memory(failure.cause) =>
+            {
+                EBCCErrorKind::OutOfMemory
+            }
+            Self::DecompressionError(failure) if is_zstd_corrupted_data(failure.cause) => {
+                EBCCErrorKind::InvalidInput
+            }
+            Self::CompressionError(_) | Self::ExpansionTooLarge { .. } => {
+                EBCCErrorKind::Compression
+            }
+            Self::DecompressionError(_) | Self::ErrorBoundViolated { .. } => {
+                EBCCErrorKind::Decompression
+            }
+            #[cfg(feature = "std")]
+            Self::Io(_) => EBCCErrorKind::Io,
+            Self::TimedOut { .. } => EBCCErrorKind::TimedOut,
+            Self::AccessDenied { .. } => EBCCErrorKind::AccessDenied,
+        }
+    }
+}
+
+/// Whether the `cause` is a zstd allocation failure.
+const fn is_zstd_out_of_memory(cause: EBCCFailureCause) -> bool {
+    matches!(
+        cause,
+        EBCCFailureCause::Zstd {
+            code: ebcc_sys::zstd::ZSTD_error_memory_allocation
+        }
+    )
+}
+
+/// Whether the `cause` is a zstd error that reports corrupted or truncated
+/// compressed data.
+const fn is_zstd_corrupted_data(cause: EBCCFailureCause) -> bool {
+    use ebcc_sys::zstd;
+
+    matches!(
+        cause,
+        EBCCFailureCause::Zstd {
+            code: zstd::ZSTD_error_prefix_unknown
+                | zstd::ZSTD_error_version_unsupported
+                | zstd::ZSTD_error_frameParameter_unsupported
+                | zstd::ZSTD_error_frameParameter_windowTooLarge
+                | zstd::ZSTD_error_corruption_detected
+                | zstd::ZSTD_error_checksum_wrong
+                | zstd::ZSTD_error_literals_headerWrong
+                | zstd::ZSTD_error_srcSize_wrong
+        }
+    )
 }
@@ -0,0 +1,127 @@
+//! Fast pre-scan for non-finite (infinite or NaN) values.
+
+use ndarray::{ArrayBase, Data, Dimension, Ix3};
+
+use crate::error::{EBCCError, EBCCResult};
+
+/// Exponent bits of an `f32`, which are all set iff the value is non-finite
+const F32_EXPONENT_MASK: u32 = 0x7f80_0000;
+
+/// Number of values that are checked together without branching
+const SCAN_CHUNK_LEN: usize = 256;
+
+/// Minimum number of values for which the scan is parallelized
+#[cfg(feature = "rayon")]
+const PARALLEL_SCAN_MIN_LEN: usize = 1 << 20;
+
+/// Find the first non-finite (infinite or NaN) value in the `data`.
+///
+/// Contiguous data is scanned in fixed-size chunks with a branch-free
+/// exponent-mask test that the compiler can auto-vectorize, and, with the
+/// `rayon` feature, large arrays are scanned in parallel. Only if the data
+/// contains a non-finite value is it searched again for the exact index to
+/// report.
+pub fn find_non_finite<S: Data<Elem = f32>, D: Dimension>(
+    data: &ArrayBase<S, D>,
+) -> Option<(D::Pattern, f32)> {
+    let all_finite = data
+        .as_slice_memory_order()
+        .map_or_else(|| data.iter().all(|x| x.is_finite()), all_finite_slice);
+
+    if all_finite {
+        return None;
+    }
+
+    data.indexed_iter()
+        .find(|(_, value)| !value.is_finite())
+        .map(|(i, &value)| (i, value))
+}
+
+/// Check that the `data` only contains finite values.
+///
+/// # Errors
+///
+/// - [`EBCCError::NonFinite`] if the `data` contains any non-finite (infinite
+///   or NaN) values
+pub fn validate_only_finite_data<S: Data<Elem = f32>>(data: &ArrayBase<S, Ix3>) -> EBCCResult<()> {
+    match find_non_finite(data) {
+        None => Ok(()),
+        Some((index, value)) => Err(EBCCError::NonFinite {
+            index: index.into(),
+            value,
+        }),
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn all_finite_slice(data: &[f32]) -> bool {
+    use rayon::prelude::{ParallelIterator, ParallelSlice};
+
+    if data.len() < PARALLEL_SCAN_MIN_LEN {
+        return all_finite_chunks(data);
+    }
+
+    data.par_chunks(PARALLEL_SCAN_MIN_LEN / 4)
+        .all(all_finite_chunks)
+}
+
+#[cfg(not(feature = "rayon"))]
+fn all_finite_slice(data: &[f32]) -> bool {
+    all_finite_chunks(data)
+}
+
+fn all_finite_chunks(data: &[f32]) -> bool {
+    data.chunks(SCAN_CHUNK_LEN).all(|chunk| {
+        // no early exit inside the chunk so that the loop is vectorized
+        !chunk
+            .iter()
+            .fold(false, |non_finite, x| non_finite | is_non_finite(*x))
+    })
+}
+
+fn is_non_finite(x: f32) -> bool {
+    (x.to_bits() & F32_EXPONENT_MASK) == F32_EXPONENT_MASK
+}
+
+#[cfg(test)]
+#[expect(clippy::indexing_slicing)]
+mod tests {
+    use ndarray::{s, Array};
+
+    use super::*;
+
+    #[test]
+    fn test_is_non_finite_matches_std() {
+        for x in [
+            0.0,
+            -0.0,
+            1.0,
+            f32::MIN_POSITIVE,
+            f32::MIN_POSITIVE / 2.0,
+            f32::MAX,
+            f32::MIN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NAN,
+            -f32::NAN,
+        ] {
+            assert_eq!(is_non_finite(x), !x.is_finite(), "{x}");
+        }
+    }
+
+    #[test]
+    fn test_validate_only_finite_data() {
+        let mut data = Array::from_elem((3, 33, 47), 1.0_f32);
+        assert!(validate_only_finite_data(&data).is_ok());
+
+        data[(2, 32, 46)] = f32::NAN;
+        assert_eq!(find_non_finite(&data).map(|(i, _)| i), Some((2, 32, 46)));
+
+        // non-contiguous views are checked as well
+        assert!(validate_only_finite_data(&data.slice(s![.., ..;2, ..;2])).is_err());
+        assert!(validate_only_finite_data(&data.slice(s![.., ..32, ..])).is_ok());
+
+        data[(2, 32, 46)] = f32::NEG_INFINITY;
+        assert!(validate_only_finite_data(&data.t()).is_err());
+    }
+}
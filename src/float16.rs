@@ -0,0 +1,89 @@
+//! Decoding into half-precision `f16` and `bf16` arrays.
+
+use half::{bf16, f16};
+use ndarray::ArrayViewMut;
+
+use crate::codec::{ebcc_decode_map_into, EbccDim};
+use crate::error::EBCCResult;
+
+/// Decode into a 3D `f16` data array using EBCC decompression.
+///
+/// The decoded data is converted to `f16` while it is copied into the
+/// `decompressed_data`, e.g. for a preview in a browser or on a GPU. EBCC
+/// still decodes into an `f32` buffer first, but EBCC frame streams are
+/// decoded and converted segment by segment, such that only one segment is
+/// held as `f32` at a time. A single [`ebcc_encode`][crate::ebcc_encode]
+/// payload is decoded into one `f32` buffer of its full size. Values that are
+/// out of the `f16` range become infinite.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_decode_into`][crate::ebcc_decode_into] can return
+///
+/// # Examples
+///
+/// ```rust
+/// use ebcc::{ebcc_decode_into_f16, ebcc_encode, EBCCConfig};
+/// use half::f16;
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((1, 32, 32), |(_, y, x)| (y + x) as f32);
+/// let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.01))?;
+///
+/// let mut preview = Array::from_elem(data.dim(), f16::ZERO);
+/// ebcc_decode_into_f16(&compressed, preview.view_mut())?;
+/// assert!(data.iter().zip(&preview).all(|(a, b)| (a - b.to_f32()).abs() <= 0.1));
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_into_f16(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f16, EbccDim>,
+) -> EBCCResult<()> {
+    ebcc_decode_map_into(compressed_data, decompressed_data, f16::from_f32)
+}
+
+/// Decode into a 3D `bf16` data array using EBCC decompression.
+///
+/// Like [`ebcc_decode_into_f16`], the decoded data is converted while it is
+/// copied out. `bf16` keeps the range of `f32` at a lower precision.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_decode_into`][crate::ebcc_decode_into] can return
+pub fn ebcc_decode_into_bf16(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<bf16, EbccDim>,
+) -> EBCCResult<()> {
+    ebcc_decode_map_into(compressed_data, decompressed_data, bf16::from_f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, testdata, EBCCConfig};
+
+    #[test]
+    fn test_decode_half() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        for compressed in testdata::encode_single_and_stream(data.view(), &config)? {
+            let mut expected = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, expected.view_mut())?;
+
+            let mut preview = Array::from_elem(data.dim(), f16::ZERO);
+            ebcc_decode_into_f16(&compressed, preview.view_mut())?;
+            assert_eq!(preview, expected.mapv(f16::from_f32));
+
+            let mut preview = Array::from_elem(data.dim(), bf16::ZERO);
+            ebcc_decode_into_bf16(&compressed, preview.view_mut())?;
+            assert_eq!(preview, expected.mapv(bf16::from_f32));
+        }
+
+        Ok(())
+    }
+}
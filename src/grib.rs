@@ -0,0 +1,405 @@
+//! GRIB2 sections that carry EBCC compressed fields.
+//!
+//! A GRIB2 message stores the packing method of a field in its data
+//! representation section (section 5), whose template describes how the
+//! packed values in the data section (section 7) are decoded. This module
+//! wraps a single-frame [`ebcc_encode`] payload into both sections, using
+//! the local-use data representation template
+//! [`EBCC_GRIB_TEMPLATE_NUMBER`], such that downstream GRIB2 tooling can
+//! experiment with EBCC as a packing method.
+//! The other sections of the message, e.g. the grid definition, are left to
+//! that tooling.
+//!
+//! The data representation section has the following octets, all integers
+//! and floats in big-endian byte order as in GRIB2:
+//!
+//! | octets | content                                                      |
+//! |--------|--------------------------------------------------------------|
+//! | 1-4    | length of the section, [`EBCC_GRIB_DATA_REPRESENTATION_LEN`] |
+//! | 5      | number of the section, 5                                     |
+//! | 6-9    | number of data points, `height * width`                      |
+//! | 10-11  | data representation template number                          |
+//! | 12-15  | height (number of rows) of the field                         |
+//! | 16-19  | width (number of columns) of the field                       |
+//! | 20     | type of the original field values, 0 (floating point)        |
+//! | 21     | residual type, 0 (none), 1 (absolute), 2 (relative)          |
+//! | 22-25  | error bound, as IEEE 754 `f32`                               |
+//! | 26-29  | base compression ratio, as IEEE 754 `f32`                    |
+//! | 30-37  | [`EBCCConfig::fingerprint`] of the configuration             |
+//!
+//! The data section consists of its length in octets 1-4, its number 7 in
+//! octet 5, and the [`ebcc_encode`] payload, including its
+//! [`EBCCHeader`], from octet 6.
+
+use ndarray::{Array2, ArrayView2, Axis};
+
+use crate::codec::{ebcc_decode_into, ebcc_encode};
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+use crate::reader::Format;
+
+/// Number of the local-use GRIB2 data representation template of EBCC
+/// compressed fields, from the range 50000-65534 that GRIB2 reserves for
+/// local use
+pub const EBCC_GRIB_TEMPLATE_NUMBER: u16 = 50_000;
+
+/// Length of the data representation section, in octets
+pub const EBCC_GRIB_DATA_REPRESENTATION_LEN: usize = 37;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC GRIB2 section");
+
+/// Length of the data section before the payload, in octets
+const DATA_SECTION_HEADER_LEN: usize = 5;
+
+/// Code table 5.1 value of floating point original field values
+const FLOATING_POINT_VALUES: u8 = 0;
+
+/// Metadata of an EBCC compressed field in the GRIB2 data representation
+/// section.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EBCCGribDataRepresentation {
+    /// Height (number of rows) of the field
+    pub height: u32,
+    /// Width (number of columns) of the field
+    pub width: u32,
+    /// Residual compression type and error bound of the configuration
+    pub residual_compression_type: EBCCResidualType,
+    /// Base compression ratio of the configuration
+    pub base_cr: f32,
+    /// [`EBCCConfig::fingerprint`] of the configuration
+    pub config_fingerprint: u64,
+}
+
+impl EBCCGribDataRepresentation {
+    /// Number of data points of the field, `height * width`
+    #[must_use]
+    pub const fn data_points(&self) -> u64 {
+        (self.height as u64) * (self.width as u64)
+    }
+
+    /// Encode the data representation section.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the number of data points does not
+    ///   fit into the four octets of the section
+    pub fn to_section(&self) -> EBCCResult<[u8; EBCC_GRIB_DATA_REPRESENTATION_LEN]> {
+        let data_points = u32::try_from(self.data_points()).map_err(|_| {
+            EBCCError::InvalidInput(format!(
+                "GRIB2 fields have at most {} data points, got {}",
+                u32::MAX,
+                self.data_points(),
+            ))
+        })?;
+        let (residual_type, error) = match self.residual_compression_type {
+            EBCCResidualType::Jpeg2000Only => (0, 0.0),
+            EBCCResidualType::AbsoluteError(error) => (1, error),
+            EBCCResidualType::RelativeError(error) => (2, error),
+        };
+
+        let mut section = [0; EBCC_GRIB_DATA_REPRESENTATION_LEN];
+        let mut writer = section.as_mut_slice();
+        for field in [
+            #[expect(clippy::cast_possible_truncation)]
+            (EBCC_GRIB_DATA_REPRESENTATION_LEN as u32)
+                .to_be_bytes()
+                .as_slice(),
+            &[5],
+            &data_points.to_be_bytes(),
+            &EBCC_GRIB_TEMPLATE_NUMBER.to_be_bytes(),
+            &self.height.to_be_bytes(),
+            &self.width.to_be_bytes(),
+            &[FLOATING_POINT_VALUES, residual_type],
+            &error.to_be_bytes(),
+            &self.base_cr.to_be_bytes(),
+            &self.config_fingerprint.to_be_bytes(),
+        ] {
+            let (head, rest) = writer.split_at_mut(field.len());
+            head.copy_from_slice(field);
+            writer = rest;
+        }
+
+        Ok(section)
+    }
+
+    /// Parse the data representation `section`.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the `section` is truncated, is not a
+    ///   data representation section, or its number of data points does not
+    ///   match its height and width
+    /// - [`EBCCError::DecompressionError`] if the `section` does not use the
+    ///   EBCC [`EBCC_GRIB_TEMPLATE_NUMBER`], or its original field values or residual
+    ///   type are not supported
+    pub fn parse(section: &[u8]) -> EBCCResult<Self> {
+        let mut reader = section;
+
+        let len = u32::from_be_bytes(FORMAT.read_array(&mut reader)?);
+        let [number] = FORMAT.read_array(&mut reader)?;
+        if number != 5 || usize::try_from(len).ok() != Some(section.len()) {
+            return Err(EBCCError::InvalidInput(format!(
+                "GRIB2 section {number} of {len} octets is not a data representation section of {} octets",
+                section.len(),
+            )));
+        }
+
+        let data_points = u32::from_be_bytes(FORMAT.read_array(&mut reader)?);
+        let template = u16::from_be_bytes(FORMAT.read_array(&mut reader)?);
+        if template != EBCC_GRIB_TEMPLATE_NUMBER {
+            return Err(EBCCError::DecompressionError(format!(
+                "GRIB2 data representation template 5.{template} is not the EBCC template 5.{EBCC_GRIB_TEMPLATE_NUMBER}",
+            ).into()));
+        }
+
+        let height = u32::from_be_bytes(FORMAT.read_array(&mut reader)?);
+        let width = u32::from_be_bytes(FORMAT.read_array(&mut reader)?);
+        let [values, residual_type] = FORMAT.read_array(&mut reader)?;
+        let error = f32::from_be_bytes(FORMAT.read_array(&mut reader)?);
+        let base_cr = f32::from_be_bytes(FORMAT.read_array(&mut reader)?);
+        let config_fingerprint = u64::from_be_bytes(FORMAT.read_array(&mut reader)?);
+
+        if values != FLOATING_POINT_VALUES {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported GRIB2 type of original field values: {values}").into(),
+            ));
+        }
+        let residual_compression_type = match residual_type {
+            0 => EBCCResidualType::Jpeg2000Only,
+            1 => EBCCResidualType::AbsoluteError(error),
+            2 => EBCCResidualType::RelativeError(error),
+            residual_type => {
+                return Err(EBCCError::DecompressionError(
+                    format!("Unsupported EBCC GRIB2 residual type: {residual_type}").into(),
+                ))
+            }
+        };
+
+        let representation = Self {
+            height,
+            width,
+            residual_compression_type,
+            base_cr,
+            config_fingerprint,
+        };
+        if representation.data_points() != u64::from(data_points) {
+            return Err(EBCCError::InvalidInput(format!(
+                "GRIB2 field of shape [{height}, {width}] does not have {data_points} data points",
+            )));
+        }
+
+        Ok(representation)
+    }
+}
+
+/// Data representation (5) and data (7) sections of a GRIB2 message that
+/// carry an EBCC compressed field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EBCCGribSections {
+    /// Encoded data representation section, see [`EBCCGribDataRepresentation`]
+    pub data_representation: [u8; EBCC_GRIB_DATA_REPRESENTATION_LEN],
+    /// Encoded data section, which contains the [`ebcc_encode`] payload
+    pub data: Vec<u8>,
+}
+
+/// Encode a 2D field with the `config` into GRIB2 sections.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_encode`] and [`ebcc_grib_wrap`] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::grib::{ebcc_grib_decode, ebcc_grib_encode};
+/// use ebcc::EBCCConfig;
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let field = Array::from_shape_fn((32, 48), |(y, x)| (y + x) as f32);
+///
+/// let config = EBCCConfig::max_absolute_error_bounded(0.01);
+/// let sections = ebcc_grib_encode(field.view(), &config)?;
+/// let decoded = ebcc_grib_decode(&sections.data_representation, &sections.data)?;
+/// assert_eq!(decoded.dim(), field.dim());
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_grib_encode(
+    field: ArrayView2<f32>,
+    config: &EBCCConfig,
+) -> EBCCResult<EBCCGribSections> {
+    ebcc_grib_wrap(&ebcc_encode(field.insert_axis(Axis(0)), config)?, config)
+}
+
+/// Wrap an [`ebcc_encode`] payload of a single frame, which was compressed
+/// with the `config`, into GRIB2 sections.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` does not start
+///   with an [`EBCCHeader`], e.g. because it is a legacy headerless payload,
+///   has more than one frame, was not compressed with the `config`, or if
+///   its frame or the data section are too large for GRIB2
+/// - [`EBCCError::DecompressionError`] if the header version, data type, or
+///   flags are not supported
+pub fn ebcc_grib_wrap(compressed_data: &[u8], config: &EBCCConfig) -> EBCCResult<EBCCGribSections> {
+    let Some(header) = EBCCHeader::parse(compressed_data)? else {
+        return Err(EBCCError::InvalidInput(String::from(
+            "EBCC GRIB2 sections require a payload that starts with an EBCC header",
+        )));
+    };
+
+    let [frames, height, width] = header.shape;
+    if frames != 1 {
+        return Err(EBCCError::InvalidInput(format!(
+            "EBCC GRIB2 sections carry a single field, got {frames} frames",
+        )));
+    }
+    if header.config_fingerprint != config.fingerprint() {
+        return Err(EBCCError::InvalidInput(String::from(
+            "EBCC payload was not compressed with the configuration of the GRIB2 sections",
+        )));
+    }
+
+    let too_large = |what: &str| {
+        EBCCError::InvalidInput(format!("EBCC {what} is too large for a GRIB2 section"))
+    };
+    let representation = EBCCGribDataRepresentation {
+        height: u32::try_from(height).map_err(|_| too_large("field"))?,
+        width: u32::try_from(width).map_err(|_| too_large("field"))?,
+        residual_compression_type: config.residual_compression_type,
+        base_cr: config.base_cr,
+        config_fingerprint: header.config_fingerprint,
+    };
+
+    let data_len = DATA_SECTION_HEADER_LEN + compressed_data.len();
+    let mut data = Vec::with_capacity(data_len);
+    data.extend_from_slice(
+        &u32::try_from(data_len)
+            .map_err(|_| too_large("payload"))?
+            .to_be_bytes(),
+    );
+    data.push(7);
+    data.extend_from_slice(compressed_data);
+
+    Ok(EBCCGribSections {
+        data_representation: representation.to_section()?,
+        data,
+    })
+}
+
+/// Unwrap the [`ebcc_encode`] payload from GRIB2 sections, together with
+/// the parsed data representation section.
+///
+/// # Errors
+///
+/// - all errors that [`EBCCGribDataRepresentation::parse`] can return
+/// - [`EBCCError::InvalidInput`] if the `data` is truncated or is not a
+///   data section
+pub fn ebcc_grib_unwrap<'a>(
+    data_representation: &[u8],
+    data: &'a [u8],
+) -> EBCCResult<(EBCCGribDataRepresentation, &'a [u8])> {
+    let representation = EBCCGribDataRepresentation::parse(data_representation)?;
+
+    let mut reader = data;
+    let len = u32::from_be_bytes(FORMAT.read_array(&mut reader)?);
+    let [number] = FORMAT.read_array(&mut reader)?;
+    if number != 7 || usize::try_from(len).ok() != Some(data.len()) {
+        return Err(EBCCError::InvalidInput(format!(
+            "GRIB2 section {number} of {len} octets is not a data section of {} octets",
+            data.len(),
+        )));
+    }
+
+    Ok((representation, reader))
+}
+
+/// Decode the 2D field from GRIB2 sections.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_grib_unwrap`] can return
+/// - all errors that [`ebcc_decode_into`] can return, e.g.
+///   [`EBCCError::ShapeMismatch`] if the payload does not have the shape of
+///   the data representation section
+pub fn ebcc_grib_decode(data_representation: &[u8], data: &[u8]) -> EBCCResult<Array2<f32>> {
+    let (representation, compressed_data) = ebcc_grib_unwrap(data_representation, data)?;
+
+    let too_large = || EBCCError::InvalidInput(String::from("GRIB2 field is too large"));
+    let mut field = Array2::zeros((
+        usize::try_from(representation.height).map_err(|_| too_large())?,
+        usize::try_from(representation.width).map_err(|_| too_large())?,
+    ));
+    ebcc_decode_into(compressed_data, field.view_mut().insert_axis(Axis(0)))?;
+
+    Ok(field)
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::s;
+
+    use super::*;
+    use crate::{testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_grib_sections() -> EBCCResult<()> {
+        let data = testdata::temperature((1, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let sections = ebcc_grib_encode(data.index_axis(Axis(0), 0), &config)?;
+        assert_eq!(
+            sections.data_representation.get(9..11),
+            Some(EBCC_GRIB_TEMPLATE_NUMBER.to_be_bytes().as_slice())
+        );
+
+        let (representation, compressed) =
+            ebcc_grib_unwrap(&sections.data_representation, &sections.data)?;
+        assert_eq!(
+            representation,
+            EBCCGribDataRepresentation {
+                height: 32,
+                width: 48,
+                residual_compression_type: EBCCResidualType::AbsoluteError(0.1),
+                base_cr: config.base_cr,
+                config_fingerprint: config.fingerprint(),
+            }
+        );
+        assert_eq!(representation.data_points(), 32 * 48);
+        assert_eq!(compressed, ebcc_encode(data.view(), &config)?);
+
+        let field = ebcc_grib_decode(&sections.data_representation, &sections.data)?;
+        check_error_bound(data.view(), field.view().insert_axis(Axis(0)), &config)?;
+
+        // mismatching sections are rejected
+        assert!(ebcc_grib_wrap(compressed, &EBCCConfig::max_absolute_error_bounded(0.2)).is_err());
+        assert!(ebcc_grib_wrap(
+            &ebcc_encode(testdata::temperature((2, 32, 48)).view(), &config)?,
+            &config
+        )
+        .is_err());
+        assert!(ebcc_grib_unwrap(&sections.data, &sections.data_representation).is_err());
+        assert!(ebcc_grib_decode(
+            &sections.data_representation,
+            sections.data.get(..20).unwrap_or_default()
+        )
+        .is_err());
+
+        let mut other_template = sections.data_representation;
+        other_template[10] ^= 1;
+        assert!(EBCCGribDataRepresentation::parse(&other_template).is_err());
+
+        let transposed = ebcc_grib_encode(data.slice(s![0, .., ..]).t(), &config)?;
+        assert!(ebcc_grib_decode(&transposed.data_representation, &sections.data).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,641 @@
+//! Self-describing header of [`ebcc_encode`][crate::ebcc_encode] payloads.
+
+use alloc::format;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+use std::io::Write;
+
+#[cfg(all(feature = "std", feature = "ndarray"))]
+use ndarray::ArrayView;
+
+use crate::checksum::{EBCCChecksum, EBCCChecksumAlgorithm};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+use crate::codec::{ebcc_decode_c_buffer_mut, EbccDim};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+use crate::config::EBCCConfig;
+use crate::error::{shape_mismatch, EBCCChecksummedData, EBCCError, EBCCResult};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+use crate::layout::is_fortran_order;
+use crate::reader::Format;
+use crate::size::{u64_to_usize, usize_to_u64};
+
+/// Magic bytes at the start of every [`ebcc_encode`][crate::ebcc_encode]
+/// payload.
+pub const EBCC_HEADER_MAGIC: &[u8; 8] = b"EBCCDATA";
+
+/// Version of the [`ebcc_encode`][crate::ebcc_encode] payload header.
+///
+/// Headers of version 1, whose payload checksum is always CRC-32 and whose
+/// data is always in standard order, can still be parsed. Headers before
+/// version 3 record an older [`EBCCConfig::fingerprint`][crate::EBCCConfig::fingerprint],
+/// which is not reported by
+/// [`recorded_config_fingerprint`][EBCCHeader::recorded_config_fingerprint].
+pub const EBCC_HEADER_VERSION: u32 = 3;
+
+/// First header version whose configuration fingerprint tags every optional
+/// configuration parameter
+const TAGGED_FINGERPRINT_VERSION: u32 = 3;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC header");
+
+/// Header flag that the decompressed checksum is present
+const FLAG_DECOMPRESSED_CHECKSUM: u32 = 1 << 0;
+/// Header flag that the compressed data was in Fortran order
+const FLAG_FORTRAN_ORDER: u32 = 1 << 1;
+/// Header flag that no configuration fingerprint is recorded
+const FLAG_NO_CONFIG_FINGERPRINT: u32 = 1 << 2;
+/// Shift of the [`EBCCChecksumAlgorithm`] code in the header flags
+const CHECKSUM_ALGORITHM_SHIFT: u32 = 8;
+/// Mask of the [`EBCCChecksumAlgorithm`] code in the header flags
+const CHECKSUM_ALGORITHM_MASK: u32 = 0xFF << CHECKSUM_ALGORITHM_SHIFT;
+
+/// Element data type of EBCC compressed data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EBCCDataType {
+    /// 32-bit IEEE 754 floating point
+    F32,
+}
+
+impl EBCCDataType {
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    const fn code(self) -> u32 {
+        match self {
+            Self::F32 => 1,
+        }
+    }
+
+    const fn from_code(code: u32) -> Option<Self> {
+        match code {
+            1 => Some(Self::F32),
+            _ => None,
+        }
+    }
+}
+
+/// Header that describes an [`ebcc_encode`][crate::ebcc_encode] payload.
+///
+/// The header consists of the [`EBCC_HEADER_MAGIC`] bytes, the
+/// [`version`][Self::version] and the [`dtype`][Self::dtype] as `u32`, the
+/// [`shape`][Self::shape], the [`config_fingerprint`][Self::config_fingerprint]
+/// and the [`payload_len`][Self::payload_len] as `u64`, the first four bytes
+/// of the [`checksum`][Self::checksum] digest of the payload, a `u32` of
+/// flags, whose bit 0 marks the presence of the
+/// [`decompressed_checksum`][Self::decompressed_checksum], bit 1 the
+/// [`fortran_order`][Self::fortran_order], bit 2 the absence of the
+/// [`config_fingerprint`][Self::config_fingerprint], see
+/// [`has_config_fingerprint`][Self::has_config_fingerprint], and bits 8-15 the code of the
+/// [`EBCCChecksumAlgorithm`], the optional decompressed checksum as `u32`,
+/// and the remaining bytes of digests that are longer than four bytes, all
+/// in little-endian byte order. It is directly followed by the payload of
+/// the EBCC C library.
+///
+/// Payloads that were produced before the header was introduced do not start
+/// with the magic bytes and are still decoded, without any validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct EBCCHeader {
+    /// Version of the header format
+    pub version: u32,
+    /// Element data type
+    pub dtype: EBCCDataType,
+    /// Shape `[frames, height, width]` of the compressed data
+    pub shape: [usize; 3],
+    /// [`EBCCConfig::fingerprint`] of the configuration that was used for
+    /// compression
+    pub config_fingerprint: u64,
+    /// Length of the payload that follows the header, in bytes
+    pub payload_len: u64,
+    /// Checksum of the payload, by default with
+    /// [`EBCCChecksumAlgorithm::Crc32`]
+    pub checksum: EBCCChecksum,
+    /// CRC-32 checksum of the little-endian bytes of the decompressed data,
+    /// see [`EBCCConfig::with_decompressed_checksum`]
+    pub decompressed_checksum: Option<u32>,
+    /// Whether the compressed data was in Fortran (column-major) order,
+    /// such that it can be decoded into the same layout with
+    /// [`EBCCOutputLayout::AsEncoded`][crate::EBCCOutputLayout::AsEncoded]
+    ///
+    /// The payload itself always stores the data in standard (row-major)
+    /// order.
+    pub fortran_order: bool,
+    /// Whether the [`config_fingerprint`][Self::config_fingerprint] is
+    /// recorded, which is not the case for data that was truncated with
+    /// [`ebcc_truncate`][crate::ebcc_truncate] without its configuration
+    pub has_config_fingerprint: bool,
+}
+
+impl EBCCHeader {
+    /// Length of the encoded header with a four-byte checksum digest, e.g.
+    /// the default CRC-32, in bytes
+    pub const LEN: usize = 8 + 4 + 4 + 3 * 8 + 8 + 8 + 4 + 4 + 4;
+
+    /// Length of this encoded header, in bytes, after which the payload
+    /// starts
+    #[must_use]
+    pub const fn encoded_len(&self) -> usize {
+        Self::encoded_len_with(self.checksum.algorithm())
+    }
+
+    /// Length of an encoded header whose payload checksum uses the
+    /// `algorithm`, in bytes
+    #[must_use]
+    pub const fn encoded_len_with(algorithm: EBCCChecksumAlgorithm) -> usize {
+        Self::LEN - 4 + algorithm.digest_len()
+    }
+
+    /// The [`config_fingerprint`][Self::config_fingerprint], unless it is
+    /// not [recorded][Self::has_config_fingerprint] or the header predates
+    /// version 3, whose fingerprints did not tag the optional configuration
+    /// parameters and can thus coincide for different configurations.
+    #[must_use]
+    pub const fn recorded_config_fingerprint(&self) -> Option<u64> {
+        if !self.has_config_fingerprint || self.version < TAGGED_FINGERPRINT_VERSION {
+            return None;
+        }
+        Some(self.config_fingerprint)
+    }
+
+    /// Parse the header at the start of the `compressed_data`.
+    ///
+    /// Returns `None` if the `compressed_data` does not start with the
+    /// [`EBCC_HEADER_MAGIC`] bytes, e.g. because it is a legacy headerless
+    /// payload, an EBCC frame stream, or an EBCC container.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the header is truncated
+    /// - [`EBCCError::DecompressionError`] if the header version, data type,
+    ///   or flags are not supported
+    pub fn parse(compressed_data: &[u8]) -> EBCCResult<Option<Self>> {
+        let Some(mut header) = compressed_data.strip_prefix(EBCC_HEADER_MAGIC.as_slice()) else {
+            return Ok(None);
+        };
+
+        let version = u32::from_le_bytes(FORMAT.read_array(&mut header)?);
+        if !(1..=EBCC_HEADER_VERSION).contains(&version) {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC header version: {version}").into(),
+            ));
+        }
+
+        let dtype = u32::from_le_bytes(FORMAT.read_array(&mut header)?);
+        let Some(dtype) = EBCCDataType::from_code(dtype) else {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC data type: {dtype}").into(),
+            ));
+        };
+
+        let mut shape = [0; 3];
+        for dim in &mut shape {
+            let value = u64::from_le_bytes(FORMAT.read_array(&mut header)?);
+            *dim = u64_to_usize(value)?;
+        }
+
+        let config_fingerprint = u64::from_le_bytes(FORMAT.read_array(&mut header)?);
+        let payload_len = u64::from_le_bytes(FORMAT.read_array(&mut header)?);
+        let checksum_prefix: [u8; 4] = FORMAT.read_array(&mut header)?;
+
+        let flags = u32::from_le_bytes(FORMAT.read_array(&mut header)?);
+        // the Fortran order and the checksum algorithm, and thus the header
+        //  length, are only recorded since version 2, and a missing
+        //  fingerprint since version 3
+        let supported_flags = match version {
+            1 => FLAG_DECOMPRESSED_CHECKSUM,
+            2 => FLAG_DECOMPRESSED_CHECKSUM | FLAG_FORTRAN_ORDER | CHECKSUM_ALGORITHM_MASK,
+            _ => {
+                FLAG_DECOMPRESSED_CHECKSUM
+                    | FLAG_FORTRAN_ORDER
+                    | CHECKSUM_ALGORITHM_MASK
+                    | FLAG_NO_CONFIG_FINGERPRINT
+            }
+        };
+        if flags & !supported_flags != 0 {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC header flags: {flags:#x}").into(),
+            ));
+        }
+        let decompressed_checksum = u32::from_le_bytes(FORMAT.read_array(&mut header)?);
+        let decompressed_checksum =
+            (flags & FLAG_DECOMPRESSED_CHECKSUM != 0).then_some(decompressed_checksum);
+        let fortran_order = flags & FLAG_FORTRAN_ORDER != 0;
+        let has_config_fingerprint = flags & FLAG_NO_CONFIG_FINGERPRINT == 0;
+
+        let algorithm = EBCCChecksumAlgorithm::from_code(
+            (flags & CHECKSUM_ALGORITHM_MASK) >> CHECKSUM_ALGORITHM_SHIFT,
+        )?;
+        let Some(checksum_suffix) = header.get(..algorithm.digest_len() - 4) else {
+            return Err(FORMAT.truncated());
+        };
+        let checksum = EBCCChecksum::from_digest(
+            algorithm,
+            &[checksum_prefix.as_slice(), checksum_suffix].concat(),
+        )?;
+
+        Ok(Some(Self {
+            version,
+            dtype,
+            shape,
+            config_fingerprint,
+            payload_len,
+            checksum,
+            decompressed_checksum,
+            fortran_order,
+            has_config_fingerprint,
+        }))
+    }
+}
+
+/// Write the header for the EBCC C library `payload` of the `data` that was
+/// compressed with the `config`, and return its length.
+///
+/// If the `config` asks for a checksum of the decompressed data, a copy of
+/// the `payload` is decoded to compute it.
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub fn write_header(
+    writer: &mut impl Write,
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    payload: &[u8],
+) -> EBCCResult<usize> {
+    let shape = data.dim();
+
+    let decompressed_checksum = if config.checksum_decompressed {
+        // C function may modify the input
+        let mut payload = Vec::from(payload);
+        Some(decompressed_checksum(
+            ebcc_decode_c_buffer_mut(&mut payload, shape)?.as_slice(),
+        ))
+    } else {
+        None
+    };
+
+    write_parsed_header(
+        writer,
+        &EBCCHeader {
+            version: EBCC_HEADER_VERSION,
+            dtype: EBCCDataType::F32,
+            shape: shape.into(),
+            config_fingerprint: config.fingerprint(),
+            payload_len: usize_to_u64(payload.len())?,
+            checksum: config.checksum_algorithm.checksum(payload),
+            decompressed_checksum,
+            fortran_order: is_fortran_order(data),
+            has_config_fingerprint: true,
+        },
+    )
+}
+
+/// Write an already known `header`, e.g. one that was [parsed][EBCCHeader::parse]
+/// and updated for a modified payload, and return its length.
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub fn write_parsed_header(writer: &mut impl Write, header: &EBCCHeader) -> EBCCResult<usize> {
+    writer.write_all(EBCC_HEADER_MAGIC)?;
+    writer.write_all(&header.version.to_le_bytes())?;
+    writer.write_all(&header.dtype.code().to_le_bytes())?;
+    for dim in header.shape {
+        writer.write_all(&usize_to_u64(dim)?.to_le_bytes())?;
+    }
+    writer.write_all(&header.config_fingerprint.to_le_bytes())?;
+    writer.write_all(&header.payload_len.to_le_bytes())?;
+    let (checksum_prefix, checksum_suffix) = header
+        .checksum
+        .digest()
+        .split_at_checked(4)
+        .unwrap_or_default();
+    writer.write_all(checksum_prefix)?;
+    let mut flags = header.checksum.algorithm().code() << CHECKSUM_ALGORITHM_SHIFT;
+    if header.decompressed_checksum.is_some() {
+        flags |= FLAG_DECOMPRESSED_CHECKSUM;
+    }
+    if header.fortran_order {
+        flags |= FLAG_FORTRAN_ORDER;
+    }
+    if !header.has_config_fingerprint {
+        flags |= FLAG_NO_CONFIG_FINGERPRINT;
+    }
+    writer.write_all(&flags.to_le_bytes())?;
+    writer.write_all(&header.decompressed_checksum.unwrap_or(0).to_le_bytes())?;
+    writer.write_all(checksum_suffix)?;
+
+    Ok(header.encoded_len())
+}
+
+/// Validate the header, if any, of the `compressed_data` against the output
+/// `shape` and return the EBCC C library payload and the expected checksum of
+/// the decompressed data, if any.
+///
+/// Legacy headerless payloads are returned unchanged.
+pub fn header_payload_mut(
+    compressed_data: &mut [u8],
+    shape: (usize, usize, usize),
+) -> EBCCResult<(&mut [u8], Option<u32>)> {
+    let Some(header) = EBCCHeader::parse(compressed_data)? else {
+        return Ok((compressed_data, None));
+    };
+
+    let output_shape = <[usize; 3]>::from(shape);
+    if header.shape != output_shape {
+        return Err(shape_mismatch(header.shape, output_shape));
+    }
+
+    let payload = compressed_data
+        .get_mut(header.encoded_len()..)
+        .unwrap_or_default();
+    verify_payload(&header, payload)?;
+
+    Ok((payload, header.decompressed_checksum))
+}
+
+/// Validate the header, if any, of the `compressed_data` and return it and
+/// the EBCC C library payload, without checking the shape.
+///
+/// Legacy headerless payloads are returned unchanged.
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub fn header_payload(compressed_data: &[u8]) -> EBCCResult<(Option<EBCCHeader>, &[u8])> {
+    let Some(header) = EBCCHeader::parse(compressed_data)? else {
+        return Ok((None, compressed_data));
+    };
+
+    let payload = compressed_data
+        .get(header.encoded_len()..)
+        .unwrap_or_default();
+    verify_payload(&header, payload)?;
+
+    Ok((Some(header), payload))
+}
+
+/// Verify the length and checksum of the `payload` against its `header`.
+fn verify_payload(header: &EBCCHeader, payload: &[u8]) -> EBCCResult<()> {
+    if usize_to_u64(payload.len())? != header.payload_len {
+        return Err(EBCCError::InvalidInput(format!(
+            "EBCC payload should be {} bytes long but is {} bytes long",
+            header.payload_len,
+            payload.len(),
+        )));
+    }
+    if header.checksum.algorithm().checksum(payload) != header.checksum {
+        return Err(EBCCError::ChecksumMismatch {
+            data: EBCCChecksummedData::CompressedPayload,
+        });
+    }
+
+    Ok(())
+}
+
+/// Verify the `decompressed_data` against the `expected` checksum, if any.
+pub fn verify_decompressed_checksum(
+    expected: Option<u32>,
+    decompressed_data: &[f32],
+) -> EBCCResult<()> {
+    match expected {
+        Some(expected) if decompressed_checksum(decompressed_data) != expected => {
+            Err(EBCCError::ChecksumMismatch {
+                data: EBCCChecksummedData::DecompressedData,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// CRC-32 checksum of the little-endian bytes of the `decompressed_data`
+fn decompressed_checksum(decompressed_data: &[f32]) -> u32 {
+    const CHUNK: usize = 1024;
+
+    let mut hasher = crc32fast::Hasher::new();
+    let mut bytes = [0_u8; CHUNK * 4];
+
+    for values in decompressed_data.chunks(CHUNK) {
+        for (bytes, value) in bytes.chunks_exact_mut(4).zip(values) {
+            bytes.copy_from_slice(&value.to_le_bytes());
+        }
+        hasher.update(bytes.get(..values.len() * 4).unwrap_or_default());
+    }
+
+    hasher.finalize()
+}
+
+#[cfg(all(
+    test,
+    feature = "std",
+    feature = "ndarray",
+    feature = "encode",
+    feature = "decode"
+))]
+#[expect(clippy::indexing_slicing)]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::codec::ebcc_encode_c_buffer;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata};
+
+    #[test]
+    fn test_header() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let compressed = ebcc_encode(data.view(), &config)?;
+
+        let header = EBCCHeader::parse(&compressed)?;
+        assert_eq!(
+            header,
+            Some(EBCCHeader {
+                version: EBCC_HEADER_VERSION,
+                dtype: EBCCDataType::F32,
+                shape: [2, 32, 48],
+                config_fingerprint: config.fingerprint(),
+                payload_len: (compressed.len() - EBCCHeader::LEN) as u64,
+                checksum: EBCCChecksumAlgorithm::Crc32.checksum(&compressed[EBCCHeader::LEN..]),
+                decompressed_checksum: None,
+                fortran_order: false,
+                has_config_fingerprint: true,
+            })
+        );
+
+        // a header without a configuration fingerprint reports none
+        let header = header.map(|header| EBCCHeader {
+            has_config_fingerprint: false,
+            ..header
+        });
+        let mut encoded = Vec::new();
+        if let Some(header) = &header {
+            write_parsed_header(&mut encoded, header)?;
+        }
+        let parsed = EBCCHeader::parse(&encoded)?;
+        assert_eq!(parsed, header);
+        assert_eq!(
+            parsed.and_then(|header| header.recorded_config_fingerprint()),
+            None
+        );
+
+        let mut decompressed = Array::zeros((2, 32, 48));
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        // the shape is validated before decoding, and swapped frame
+        //  dimensions are reported as such
+        let mut transposed = Array::zeros((2, 48, 32));
+        assert!(matches!(
+            ebcc_decode_into(&compressed, transposed.view_mut()),
+            Err(EBCCError::SwappedDimensions {
+                expected: [32, 48],
+                actual: [48, 32],
+            })
+        ));
+        let mut wrong = Array::zeros((1, 32, 48));
+        assert!(matches!(
+            ebcc_decode_into(&compressed, wrong.view_mut()),
+            Err(EBCCError::ShapeMismatch {
+                expected: [2, 32, 48],
+                actual: [1, 32, 48],
+            })
+        ));
+
+        // corrupted and truncated payloads are detected
+        let mut corrupted = compressed.clone();
+        corrupted[EBCCHeader::LEN + 1] ^= 0xFF;
+        assert!(matches!(
+            ebcc_decode_into(&corrupted, decompressed.view_mut()),
+            Err(EBCCError::ChecksumMismatch {
+                data: EBCCChecksummedData::CompressedPayload,
+            })
+        ));
+        assert!(
+            ebcc_decode_into(&compressed[..compressed.len() - 1], decompressed.view_mut()).is_err()
+        );
+        assert!(EBCCHeader::parse(&compressed[..EBCCHeader::LEN - 1]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompressed_checksum() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1).with_decompressed_checksum();
+        let compressed = ebcc_encode(data.view(), &config)?;
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        let header = EBCCHeader::parse(&compressed)?;
+        assert_eq!(
+            header.and_then(|header| header.decompressed_checksum),
+            Some(decompressed_checksum(
+                decompressed.as_slice().unwrap_or_default()
+            ))
+        );
+
+        // the fingerprint does not depend on the checksum flag
+        assert_eq!(
+            header.map(|header| header.config_fingerprint),
+            Some(EBCCConfig::max_absolute_error_bounded(0.1).fingerprint())
+        );
+
+        // a wrong decompressed checksum is detected after decoding
+        let mut corrupted = compressed;
+        corrupted[EBCCHeader::LEN - 1] ^= 0xFF;
+        assert!(matches!(
+            ebcc_decode_into(&corrupted, decompressed.view_mut()),
+            Err(EBCCError::ChecksumMismatch {
+                data: EBCCChecksummedData::DecompressedData,
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_algorithms() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&ebcc_encode(data.view(), &config)?, expected.view_mut())?;
+
+        for algorithm in [
+            EBCCChecksumAlgorithm::Crc32c,
+            EBCCChecksumAlgorithm::Xxh3,
+            #[cfg(feature = "blake3")]
+            EBCCChecksumAlgorithm::Blake3,
+        ] {
+            let config = config.clone().with_checksum_algorithm(algorithm);
+            let compressed = ebcc_encode(data.view(), &config)?;
+
+            // the algorithm is recorded in the header, which grows to fit
+            //  longer digests
+            let Some(header) = EBCCHeader::parse(&compressed)? else {
+                return Err(EBCCError::InvalidInput(String::from("missing header")));
+            };
+            assert_eq!(
+                header.encoded_len(),
+                EBCCHeader::encoded_len_with(algorithm)
+            );
+            assert_eq!(
+                header.checksum,
+                algorithm.checksum(&compressed[header.encoded_len()..])
+            );
+            assert_eq!(
+                compressed.len() as u64,
+                header.encoded_len() as u64 + header.payload_len
+            );
+
+            let mut decompressed = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, decompressed.view_mut())?;
+            assert_eq!(decompressed, expected);
+
+            let mut corrupted = compressed.clone();
+            corrupted[header.encoded_len() + 1] ^= 0xFF;
+            assert!(matches!(
+                ebcc_decode_into(&corrupted, decompressed.view_mut()),
+                Err(EBCCError::ChecksumMismatch {
+                    data: EBCCChecksummedData::CompressedPayload,
+                })
+            ));
+            assert!(EBCCHeader::parse(&compressed[..header.encoded_len() - 1]).is_err());
+
+            // version 1 headers always have a CRC-32 checksum
+            let mut v1 = compressed.clone();
+            v1[EBCC_HEADER_MAGIC.len()..EBCC_HEADER_MAGIC.len() + 4]
+                .copy_from_slice(&1_u32.to_le_bytes());
+            assert!(matches!(
+                EBCCHeader::parse(&v1),
+                Err(EBCCError::DecompressionError(_))
+            ));
+        }
+
+        let mut v1 = ebcc_encode(data.view(), &config)?;
+        v1[EBCC_HEADER_MAGIC.len()..EBCC_HEADER_MAGIC.len() + 4]
+            .copy_from_slice(&1_u32.to_le_bytes());
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&v1, decompressed.view_mut())?;
+        assert_eq!(decompressed, expected);
+
+        // unknown algorithms are rejected
+        let mut compressed = ebcc_encode(data.view(), &config)?;
+        compressed[EBCCHeader::LEN - 7] = 0xFF;
+        assert!(matches!(
+            EBCCHeader::parse(&compressed),
+            Err(EBCCError::DecompressionError(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_headerless_payload() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let legacy = ebcc_encode_c_buffer(data.view(), &config)?;
+        assert_eq!(&compressed[EBCCHeader::LEN..], legacy.as_slice());
+        assert_eq!(EBCCHeader::parse(legacy.as_slice())?, None);
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, expected.view_mut())?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(legacy.as_slice(), decompressed.view_mut())?;
+        assert_eq!(decompressed, expected);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+//! Heartbeats during long-running encode and decode jobs.
+
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::sync::ebcc_calls;
+
+/// Shortest interval between two heartbeats
+const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Liveness report of a job that runs with [`ebcc_with_heartbeat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EBCCHeartbeat {
+    /// Number of this heartbeat, starting at one
+    pub beat: u64,
+    /// Time since the job started
+    pub elapsed: Duration,
+    /// Number of calls into the EBCC C library that have finished in this
+    /// process, which increases while a job with many calls makes progress
+    pub native_calls: u64,
+    /// Whether a call into the EBCC C library is currently running
+    pub in_native_call: bool,
+}
+
+/// Run the `job`, e.g. an encode or decode of a huge array, and call the
+/// `heartbeat` callback at least every `interval` until it has finished.
+///
+/// Jobs under external supervisors, e.g. Slurm or Kubernetes liveness probes,
+/// can forward the heartbeats to distinguish a slow but alive job from a hung
+/// one. The heartbeats are called from a separate thread, and keep coming
+/// while the job is inside the EBCC C library, which cannot report its
+/// progress. Every [`EBCCHeartbeat`] therefore reports whether a call into
+/// the C library is running and how many calls have finished, such that
+/// supervisors can, e.g., flag a single C call that runs for much longer than
+/// expected.
+///
+/// The `interval` is at least one millisecond. No heartbeat is sent if the
+/// `job` finishes within the first `interval`.
+///
+/// # Examples
+///
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+/// use std::time::Duration;
+///
+/// use ebcc::{ebcc_encode, ebcc_with_heartbeat, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_elem((1, 32, 32), 1.0_f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let compressed = ebcc_with_heartbeat(
+///     Duration::from_secs(10),
+///     |heartbeat| eprintln!("still encoding after {:?}", heartbeat.elapsed),
+///     || ebcc_encode(data.view(), &config),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_with_heartbeat<T>(
+    interval: Duration,
+    mut heartbeat: impl FnMut(&EBCCHeartbeat) + Send,
+    job: impl FnOnce() -> T,
+) -> T {
+    let interval = interval.max(MIN_HEARTBEAT_INTERVAL);
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        // the job finishing, or unwinding, drops the sender and stops the
+        //  heartbeats
+        let (done, finished) = mpsc::channel::<()>();
+
+        scope.spawn(move || {
+            let mut beat = 0;
+            while finished.recv_timeout(interval) == Err(RecvTimeoutError::Timeout) {
+                beat += 1;
+                let (native_calls, in_native_call) = ebcc_calls();
+                heartbeat(&EBCCHeartbeat {
+                    beat,
+                    elapsed: start.elapsed(),
+                    native_calls,
+                    in_native_call,
+                });
+            }
+        });
+
+        let result = job();
+        drop(done);
+        result
+    })
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::{ebcc_encode, testdata, EBCCConfig, EBCCResult};
+
+    #[test]
+    fn test_heartbeat() -> EBCCResult<()> {
+        let mut beats = Vec::new();
+        let result = ebcc_with_heartbeat(
+            Duration::from_millis(5),
+            |heartbeat| beats.push(*heartbeat),
+            || {
+                thread::sleep(Duration::from_millis(50));
+                42
+            },
+        );
+        assert_eq!(result, 42);
+        assert!(!beats.is_empty());
+        assert!(beats
+            .iter()
+            .zip(1..)
+            .all(|(heartbeat, beat)| heartbeat.beat == beat));
+        assert!(beats
+            .windows(2)
+            .all(|pair| matches!(pair, [a, b] if a.elapsed < b.elapsed)));
+
+        let data = testdata::temperature((2, 32, 48));
+        let (before, _) = ebcc_calls();
+        ebcc_with_heartbeat(
+            Duration::from_secs(60),
+            |_| (),
+            || ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.1)),
+        )?;
+        assert!(ebcc_calls().0 > before);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+//! Temporal upsampling by interpolating between decoded key frames.
+
+use std::num::NonZeroUsize;
+
+use ndarray::{Array, ArrayViewMut, Axis, Zip};
+
+use crate::codec::{ebcc_decode_into, EbccDim};
+use crate::error::{EBCCError, EBCCResult};
+
+/// Method used to synthesize frames between two stored key frames.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TemporalInterpolation {
+    /// Linear interpolation between the two neighbouring key frames
+    Linear,
+    /// Interpolating wavelet prediction from the four neighbouring key frames
+    ///
+    /// This uses the cubic Deslauriers-Dubuc predictor, which is smoother than
+    /// linear interpolation. At the first and last key frame interval, it
+    /// falls back to linear interpolation.
+    DeslauriersDubuc,
+}
+
+/// Origin of a frame returned by [`ebcc_decode_interpolated_into`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FrameOrigin {
+    /// The frame is the decoded key frame with the given index
+    Stored {
+        /// Index of the key frame in the compressed data
+        key_frame: usize,
+    },
+    /// The frame was synthesized by interpolation and was never stored
+    Synthesized {
+        /// Index of the key frame before the synthesized frame
+        after_key_frame: usize,
+        /// Fractional position in `(0, 1)` between `after_key_frame` and the
+        /// next key frame
+        position: f32,
+    },
+}
+
+impl FrameOrigin {
+    /// Returns `true` if the frame was synthesized by interpolation.
+    #[must_use]
+    pub const fn is_synthesized(&self) -> bool {
+        matches!(self, Self::Synthesized { .. })
+    }
+}
+
+/// Decode compressed key frames and temporally upsample them by inserting
+/// interpolated frames.
+///
+/// The `compressed_data` must contain `K` frames of shape `(height, width)`,
+/// which are decoded as key frames. Between every pair of consecutive key
+/// frames, `upsampling - 1` frames are synthesized using the interpolation
+/// `method`. Therefore, `decompressed_data` must have the shape
+/// `((K - 1) * upsampling + 1, height, width)`.
+///
+/// <div class="warning">
+///
+/// **Warning:** Synthesized frames are not covered by any error bound, since
+/// they do not correspond to any stored data. Use the returned
+/// [`FrameOrigin`]s to distinguish between stored and synthesized frames.
+///
+/// </div>
+///
+/// # Returns
+///
+/// The [`FrameOrigin`] of each frame in `decompressed_data`.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the number of frames in
+///   `decompressed_data` is not of the form `(K - 1) * upsampling + 1`
+/// - all errors that [`ebcc_decode_into`] can return when decoding the `K`
+///   key frames
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use std::num::NonZeroUsize;
+///
+/// use ebcc::{ebcc_decode_interpolated_into, ebcc_encode, EBCCConfig, TemporalInterpolation};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// // two key frames
+/// let data = Array::from_shape_fn((2, 32, 32), |(t, _, _)| t as f32);
+/// let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.01))?;
+///
+/// // upsample by a factor of 4
+/// let mut decompressed = Array::zeros((5, 32, 32));
+/// let origins = ebcc_decode_interpolated_into(
+///     &compressed,
+///     NonZeroUsize::new(4).unwrap(),
+///     TemporalInterpolation::Linear,
+///     decompressed.view_mut(),
+/// )?;
+///
+/// assert!(!origins[0].is_synthesized());
+/// assert!(origins[1].is_synthesized());
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_interpolated_into(
+    compressed_data: &[u8],
+    upsampling: NonZeroUsize,
+    method: TemporalInterpolation,
+    mut decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<Vec<FrameOrigin>> {
+    let (frames, height, width) = decompressed_data.dim();
+    let upsampling = upsampling.get();
+
+    let Some(key_frames) = frames
+        .checked_sub(1)
+        .filter(|frames| frames % upsampling == 0)
+        .map(|frames| frames / upsampling + 1)
+    else {
+        return Err(EBCCError::InvalidInput(format!(
+            "Upsampling by {upsampling} requires (K - 1) * {upsampling} + 1 output frames, got {frames}",
+        )));
+    };
+
+    let mut key_data = Array::zeros((key_frames, height, width));
+    ebcc_decode_into(compressed_data, key_data.view_mut())?;
+
+    let mut origins = Vec::with_capacity(frames);
+
+    for (i, mut frame) in decompressed_data.axis_iter_mut(Axis(0)).enumerate() {
+        let (key_frame, offset) = (i / upsampling, i % upsampling);
+
+        if offset == 0 {
+            frame.assign(&key_data.index_axis(Axis(0), key_frame));
+            origins.push(FrameOrigin::Stored { key_frame });
+            continue;
+        }
+
+        #[expect(clippy::cast_precision_loss)]
+        let position = offset as f32 / upsampling as f32;
+
+        let before = key_data.index_axis(Axis(0), key_frame);
+        let after = key_data.index_axis(Axis(0), key_frame + 1);
+
+        match (
+            method,
+            key_frame.checked_sub(1),
+            Some(key_frame + 2).filter(|next| *next < key_frames),
+        ) {
+            (TemporalInterpolation::DeslauriersDubuc, Some(previous), Some(next)) => {
+                let [w0, w1, w2, w3] = cubic_weights(position);
+                let previous = key_data.index_axis(Axis(0), previous);
+                let next = key_data.index_axis(Axis(0), next);
+
+                Zip::from(&mut frame)
+                    .and(&previous)
+                    .and(&before)
+                    .and(&after)
+                    .and(&next)
+                    .for_each(|x, &a, &b, &c, &d| {
+                        *x = w0.mul_add(a, w1.mul_add(b, w2.mul_add(c, w3 * d)));
+                    });
+            }
+            _ => {
+                Zip::from(&mut frame)
+                    .and(&before)
+                    .and(&after)
+                    .for_each(|x, &b, &c| *x = (c - b).mul_add(position, b));
+            }
+        }
+
+        origins.push(FrameOrigin::Synthesized {
+            after_key_frame: key_frame,
+            position,
+        });
+    }
+
+    Ok(origins)
+}
+
+/// Cubic Lagrange weights for the key frames at `-1, 0, 1, 2` when
+/// interpolating at position `t` in `(0, 1)`.
+fn cubic_weights(t: f32) -> [f32; 4] {
+    [
+        -t * (t - 1.0) * (t - 2.0) / 6.0,
+        (t + 1.0) * (t - 1.0) * (t - 2.0) / 2.0,
+        -(t + 1.0) * t * (t - 2.0) / 2.0,
+        (t + 1.0) * t * (t - 1.0) / 6.0,
+    ]
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+#[expect(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::{ebcc_encode, EBCCConfig};
+
+    #[test]
+    fn test_cubic_weights_midpoint() {
+        let weights = cubic_weights(0.5);
+
+        for (weight, expected) in weights.iter().zip([-1.0, 9.0, 9.0, -1.0]) {
+            assert!((weight - expected / 16.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_interpolated_decode() -> EBCCResult<()> {
+        let data = Array::from_shape_fn((4, 32, 32), |(t, _y, _x)| (t * 10) as f32);
+        let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.01))?;
+
+        for method in [
+            TemporalInterpolation::Linear,
+            TemporalInterpolation::DeslauriersDubuc,
+        ] {
+            let mut decompressed = Array::zeros((10, 32, 32));
+            let origins = ebcc_decode_interpolated_into(
+                &compressed,
+                NonZeroUsize::new(3).unwrap(),
+                method,
+                decompressed.view_mut(),
+            )?;
+
+            assert_eq!(origins.len(), 10);
+            assert_eq!(origins.iter().filter(|o| o.is_synthesized()).count(), 6);
+
+            // the data is linear in time, so both methods reproduce it
+            for (t, frame) in decompressed.axis_iter(Axis(0)).enumerate() {
+                let expected = t as f32 * 10.0 / 3.0;
+                assert!(frame.iter().all(|x| (x - expected).abs() < 0.05));
+            }
+        }
+
+        let mut wrong_frames = Array::zeros((9, 32, 32));
+        assert!(ebcc_decode_interpolated_into(
+            &compressed,
+            NonZeroUsize::new(3).unwrap(),
+            TemporalInterpolation::Linear,
+            wrong_frames.view_mut(),
+        )
+        .is_err());
+
+        // without upsampling, all frames are stored key frames
+        let mut decompressed = Array::zeros(data.dim());
+        let origins = ebcc_decode_interpolated_into(
+            &compressed,
+            NonZeroUsize::MIN,
+            TemporalInterpolation::Linear,
+            decompressed.view_mut(),
+        )?;
+        assert_eq!(origins[3], FrameOrigin::Stored { key_frame: 3 });
+
+        Ok(())
+    }
+}
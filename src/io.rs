@@ -0,0 +1,190 @@
+//! [`std::io`]-based EBCC compression and decompression.
+
+#[cfg(feature = "encode")]
+use std::io::Write;
+#[cfg(feature = "decode")]
+use std::io::{Cursor, Read};
+
+#[cfg(feature = "encode")]
+use ndarray::ArrayView;
+#[cfg(feature = "decode")]
+use ndarray::ArrayViewMut;
+
+#[cfg(feature = "decode")]
+use crate::codec::ebcc_decode_frames_into_mut;
+#[cfg(feature = "encode")]
+use crate::codec::ebcc_encode_c_buffer;
+use crate::codec::EbccDim;
+#[cfg(feature = "encode")]
+use crate::config::EBCCConfig;
+#[cfg(feature = "decode")]
+use crate::container::{is_ebcc_container, EbccContainer};
+#[cfg(feature = "decode")]
+use crate::error::EBCCError;
+use crate::error::EBCCResult;
+#[cfg(feature = "encode")]
+use crate::header::write_header;
+#[cfg(feature = "decode")]
+use crate::limits::EBCCLimits;
+#[cfg(feature = "decode")]
+use crate::size::usize_to_u64;
+#[cfg(feature = "decode")]
+use crate::stream::{ebcc_decode_stream_body_from_reader, EBCC_STREAM_MAGIC};
+
+/// Encode a 3D data array using EBCC compression and write the compressed
+/// bytes to the `writer`.
+///
+/// The compressed bytes are written directly from the buffer produced by
+/// EBCC, without first copying them into a [`Vec<u8>`]. The written bytes are
+/// identical to the output of [`ebcc_encode`][crate::ebcc_encode].
+///
+/// # Returns
+///
+/// The number of compressed bytes written.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_encode`][crate::ebcc_encode] can return
+/// - [`EBCCError::Io`] if writing to the `writer` fails
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_from_reader, ebcc_encode_to_writer, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_vec((1, 32, 32), vec![1.0f32; 32 * 32]).unwrap();
+///
+/// let mut compressed = Vec::new();
+/// ebcc_encode_to_writer(data.view(), &EBCCConfig::new(), &mut compressed)?;
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_from_reader(&mut compressed.as_slice(), decompressed.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "encode")]
+pub fn ebcc_encode_to_writer(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    writer: &mut impl Write,
+) -> EBCCResult<usize> {
+    let payload = ebcc_encode_c_buffer(data, config)?;
+    let payload = payload.as_slice();
+
+    let header_len = write_header(writer, data, config, payload)?;
+    writer.write_all(payload)?;
+
+    Ok(header_len + payload.len())
+}
+
+/// Decode EBCC compressed bytes read from the `reader` into a 3D data array.
+///
+/// The `reader` may provide either a single
+/// [`ebcc_encode`][crate::ebcc_encode] payload, an EBCC frame stream
+/// produced by an [`EbccStreamEncoder`][crate::EbccStreamEncoder], or an
+/// EBCC [`container`][crate::container]. Frame streams are decoded
+/// segment-by-segment such that only one segment is held in memory at a
+/// time, whereas a single payload or container is read into memory in full.
+///
+/// The `reader` is read until the end of the stream or payload.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_decode_into`][crate::ebcc_decode_into] can return
+/// - [`EBCCError::Io`] if reading from the `reader` fails
+/// - [`EBCCError::InputTooLarge`] if a single payload or container exceeds
+///   the default [`EBCCLimits::max_input_bytes`], in which case at most one
+///   byte beyond the limit is read
+#[cfg(feature = "decode")]
+pub fn ebcc_decode_from_reader(
+    reader: &mut impl Read,
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    // check the limits before anything is read from the reader
+    let limits = EBCCLimits::default();
+    limits.check_shape(decompressed_data.dim())?;
+
+    let mut compressed_data = Vec::with_capacity(EBCC_STREAM_MAGIC.len());
+    reader
+        .take(EBCC_STREAM_MAGIC.len() as u64)
+        .read_to_end(&mut compressed_data)?;
+
+    if compressed_data == EBCC_STREAM_MAGIC {
+        return ebcc_decode_stream_body_from_reader(reader, decompressed_data);
+    }
+
+    // read at most one byte beyond the limit to detect oversized inputs
+    reader
+        .take(usize_to_u64(limits.max_input_bytes)?.saturating_add(1))
+        .read_to_end(&mut compressed_data)?;
+    limits.check_input_bytes(compressed_data.len())?;
+
+    if is_ebcc_container(&compressed_data) {
+        return EbccContainer::open(Cursor::new(compressed_data))?.decode_into(decompressed_data);
+    }
+
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    ebcc_decode_frames_into_mut(&mut compressed_data, decompressed_data)
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use ndarray::{Array, Axis};
+
+    use super::*;
+    use crate::{ebcc_encode, testdata, verify::check_error_bound, EbccStreamEncoder};
+
+    #[test]
+    fn test_writer_matches_vec() -> EBCCResult<()> {
+        let data = testdata::temperature((1, 32, 64));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let mut written = Vec::new();
+        let len = ebcc_encode_to_writer(data.view(), &config, &mut written)?;
+
+        assert_eq!(len, written.len());
+        assert_eq!(written, ebcc_encode(data.view(), &config)?);
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_from_reader(&mut written.as_slice(), decompressed.view_mut())?;
+        check_error_bound(data.view(), decompressed.view(), &config)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_stream_from_reader() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 32));
+
+        let mut encoder = EbccStreamEncoder::new(
+            Vec::new(),
+            EBCCConfig::max_absolute_error_bounded(0.1),
+            (32, 32),
+            NonZeroUsize::new(2).unwrap(),
+        )?;
+        for frame in data.axis_iter(Axis(0)) {
+            encoder.push_frame(frame)?;
+        }
+        let compressed = encoder.finish()?;
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_from_reader(&mut compressed.as_slice(), decompressed.view_mut())?;
+
+        let truncated = compressed.get(..compressed.len() - 4).unwrap();
+        assert!(ebcc_decode_from_reader(&mut &*truncated, decompressed.view_mut()).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1129 @@
+//! Layered EBCC compression with a separately coded residual layer.
+//!
+//! The data is first encoded with a `JPEG2000`-only base layer by the EBCC C
+//! library. The residual between the data and the decoded base layer is then
+//! quantized linearly in Rust such that the reconstruction stays within the
+//! error bound, using the same codes as the residual-only compression. Since
+//! the two layers are stored separately, the base layer can be decoded on its
+//! own, see [`ebcc_decode_approximation_into`], and the residual layer can be
+//! requantized to fit into a smaller byte budget, see [`ebcc_truncate`].
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_LAYERED_MAGIC`], the format version as `u32`, the number
+//!   of frames, the frame height, and the frame width as `u64`s, the absolute
+//!   error bound as `f32`, and the length of the base layer and the number of
+//!   verbatim values as `u64`s
+//! - the base layer, a `JPEG2000`-only EBCC payload
+//! - the verbatim `f32` values
+//! - the codes: `0` for a verbatim value, `1` followed by the length of a run
+//!   of zero quantization codes, or `2 + zigzag(q)` for a non-zero
+//!   quantization code `q` of the residual
+
+use ndarray::{Array, ArrayView, ArrayViewMut};
+
+use crate::adaptive::is_ebcc_tiled;
+use crate::cbuffer::CBuffer;
+use crate::codec::{
+    copy_decompressed, decompressed_view, ebcc_decode_c_buffer_mut, ebcc_decode_nested,
+    ebcc_encode_c_buffer_with_scratch, EbccDim,
+};
+use crate::coder::{is_residual_coded, residual_coded_decode};
+use crate::config::{EBCCBaseMode, EBCCConfig, EBCCResidualType};
+use crate::container::is_ebcc_container;
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::{
+    header_payload, header_payload_mut, write_parsed_header, EBCCHeader, EBCC_HEADER_VERSION,
+};
+use crate::limits::EBCCDecodeOptions;
+use crate::reader::Format;
+use crate::residual::{
+    flush_zero_run, quantize, reconstruct, unzigzag, write_varint, zigzag, CODE_OFFSET,
+    CODE_VERBATIM, CODE_ZERO_RUN,
+};
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::stream::is_ebcc_stream;
+use crate::verify::data_range;
+
+/// Magic bytes at the start of every layered EBCC payload.
+pub const EBCC_LAYERED_MAGIC: &[u8; 8] = b"EBCCLAYR";
+
+/// Version of the layered EBCC payload format.
+const EBCC_LAYERED_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC layered data");
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_LAYERED_MAGIC`].
+pub fn is_layered(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_LAYERED_MAGIC)
+}
+
+/// Encode a 3D data array into a layered payload with the base compression
+/// ratio and the error bound of the `config`.
+pub fn layered_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let error_bound = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => error,
+        EBCCResidualType::RelativeError(error) => data_range(data) * error,
+        EBCCResidualType::Jpeg2000Only => return Err(layered_requires_error_bound()),
+    };
+
+    let base_config = EBCCConfig {
+        residual_compression_type: EBCCResidualType::Jpeg2000Only,
+        base_mode: EBCCBaseMode::Jpeg2000,
+        expansion_guard: None,
+        ..config.clone()
+    };
+    let base = ebcc_encode_c_buffer_with_scratch(data, &base_config, scratch)?;
+
+    let mut base_copy = base.as_slice().to_vec();
+    let approximation = ebcc_decode_c_buffer_mut(&mut base_copy, data.dim())?;
+
+    let mut verbatim = Vec::new();
+    let mut codes = Vec::new();
+    let mut zero_run = 0_u64;
+
+    for (value, prediction) in data.iter().zip(approximation.as_slice()) {
+        match quantize(*value, *prediction, error_bound) {
+            Some(0) => zero_run += 1,
+            Some(code) => {
+                flush_zero_run(&mut codes, &mut zero_run);
+                write_varint(&mut codes, zigzag(code) + CODE_OFFSET);
+            }
+            None => {
+                flush_zero_run(&mut codes, &mut zero_run);
+                write_varint(&mut codes, CODE_VERBATIM);
+                verbatim.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+    flush_zero_run(&mut codes, &mut zero_run);
+
+    let header = LayeredHeader {
+        shape: data.dim().into(),
+        error_bound,
+        base_len: base.as_slice().len(),
+        verbatim_len: verbatim.len(),
+    };
+
+    header.write_payload(base.as_slice(), &verbatim, &codes)
+}
+
+/// Residual correction of one value of the `JPEG2000` base layer, see
+/// [`EBCCResidualLayer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EBCCResidualCorrection {
+    /// The base layer approximation is corrected by the `residual`, which is
+    /// the quantization `code` times twice the error bound
+    Quantized {
+        /// C-order index of the corrected value
+        index: usize,
+        /// Quantization code of the residual
+        code: i64,
+        /// Residual that is added to the base layer approximation
+        residual: f32,
+    },
+    /// The value is stored verbatim and replaces the base layer
+    /// approximation, e.g. because its quantized residual would violate the
+    /// error bound
+    Verbatim {
+        /// C-order index of the replaced value
+        index: usize,
+        /// Value that replaces the base layer approximation
+        value: f32,
+    },
+}
+
+impl EBCCResidualCorrection {
+    /// C-order index of the corrected value.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        match self {
+            Self::Quantized { index, .. } | Self::Verbatim { index, .. } => *index,
+        }
+    }
+}
+
+/// Residual layer of EBCC compressed data, which corrects the `JPEG2000`
+/// base layer such that the error bound holds, see
+/// [`ebcc_extract_residuals`].
+///
+/// Only the values whose base layer approximation is corrected are listed,
+/// all other values are within the error bound of their approximation.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct EBCCResidualLayer {
+    /// Shape `[frames, height, width]` of the compressed data
+    pub shape: [usize; 3],
+    /// Absolute error bound of the residual layer
+    pub error_bound: f32,
+    /// Sparse corrections of the base layer, in ascending C-order of their
+    /// indices
+    pub corrections: Vec<EBCCResidualCorrection>,
+}
+
+impl EBCCResidualLayer {
+    /// Convert the sparse corrections into a dense 3D array of the
+    /// residuals.
+    ///
+    /// Values without a correction have a zero residual. Since the residual
+    /// of a verbatim value cannot be known without the base layer, verbatim
+    /// values are NaN.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::TooManyFrames`], [`EBCCError::OutputTooLarge`], or
+    ///   [`EBCCError::ShapeTooLarge`] if the dense array of the residual
+    ///   layer's [`shape`][Self::shape] exceeds the default
+    ///   [`EBCCDecodeOptions`] limits
+    pub fn to_dense(&self) -> EBCCResult<Array<f32, EbccDim>> {
+        EBCCDecodeOptions::new().check_shape(self.shape.into())?;
+
+        let mut residuals = Array::zeros(self.shape);
+        // residuals is contiguous and in standard layout
+        let Some(dense) = residuals.as_slice_mut() else {
+            return Ok(residuals);
+        };
+
+        for correction in &self.corrections {
+            let (index, residual) = match *correction {
+                EBCCResidualCorrection::Quantized {
+                    index, residual, ..
+                } => (index, residual),
+                EBCCResidualCorrection::Verbatim { index, .. } => (index, f32::NAN),
+            };
+            if let Some(dense) = dense.get_mut(index) {
+                *dense = residual;
+            }
+        }
+
+        Ok(residuals)
+    }
+}
+
+/// Header of a layered payload
+struct LayeredHeader {
+    shape: [usize; 3],
+    error_bound: f32,
+    base_len: usize,
+    verbatim_len: usize,
+}
+
+impl LayeredHeader {
+    /// Read the header of a layered payload and return it and its length.
+    fn read(compressed_data: &[u8]) -> EBCCResult<(Self, usize)> {
+        let Some(mut reader) = compressed_data.strip_prefix(EBCC_LAYERED_MAGIC.as_slice()) else {
+            return Err(FORMAT.corrupted());
+        };
+
+        let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+        if version != EBCC_LAYERED_VERSION {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC layered version: {version}").into(),
+            ));
+        }
+
+        let mut shape = [0; 3];
+        for dim in &mut shape {
+            *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+        }
+
+        let error_bound = f32::from_bits(u32::from_le_bytes(FORMAT.read_array(&mut reader)?));
+        let base_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+        let verbatim_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?
+            .checked_mul(4)
+            .ok_or_else(|| FORMAT.corrupted())?;
+
+        let header = Self {
+            shape,
+            error_bound,
+            base_len,
+            verbatim_len,
+        };
+
+        Ok((header, compressed_data.len() - reader.len()))
+    }
+
+    /// Length of the encoded header, in bytes
+    const LEN: usize = EBCC_LAYERED_MAGIC.len() + 4 + 3 * 8 + 4 + 2 * 8;
+
+    /// Write a layered payload with this header, the `base` layer, and the
+    /// residual layer of `verbatim` values and `codes`.
+    fn write_payload(&self, base: &[u8], verbatim: &[u8], codes: &[u8]) -> EBCCResult<Vec<u8>> {
+        let mut compressed_data =
+            Vec::with_capacity(Self::LEN + base.len() + verbatim.len() + codes.len());
+        compressed_data.extend_from_slice(EBCC_LAYERED_MAGIC);
+        compressed_data.extend_from_slice(&EBCC_LAYERED_VERSION.to_le_bytes());
+        for dim in self.shape {
+            compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+        }
+        compressed_data.extend_from_slice(&self.error_bound.to_bits().to_le_bytes());
+        compressed_data.extend_from_slice(&usize_to_u64(self.base_len)?.to_le_bytes());
+        compressed_data.extend_from_slice(&usize_to_u64(self.verbatim_len / 4)?.to_le_bytes());
+        compressed_data.extend_from_slice(base);
+        compressed_data.extend_from_slice(verbatim);
+        compressed_data.extend_from_slice(codes);
+
+        Ok(compressed_data)
+    }
+
+    /// Pass all corrections of the `residual` layer to `correct`, in
+    /// ascending C-order of their indices.
+    fn for_each_correction(
+        &self,
+        residual: &[u8],
+        mut correct: impl FnMut(EBCCResidualCorrection) -> EBCCResult<()>,
+    ) -> EBCCResult<()> {
+        let Some((verbatim, mut codes)) = residual.split_at_checked(self.verbatim_len) else {
+            return Err(FORMAT.truncated());
+        };
+        let mut verbatim = verbatim
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap_or_default()));
+
+        let elements = data_len(self.shape.into())?;
+        let mut index = 0;
+
+        while index < elements {
+            match FORMAT.read_varint(&mut codes)? {
+                CODE_VERBATIM => {
+                    let value = verbatim.next().ok_or_else(|| FORMAT.truncated())?;
+                    correct(EBCCResidualCorrection::Verbatim { index, value })?;
+                    index += 1;
+                }
+                CODE_ZERO_RUN => {
+                    // values in a run of zero codes keep their approximation
+                    let zero_run = u64_to_usize(FORMAT.read_varint(&mut codes)?)?;
+                    index = index
+                        .checked_add(zero_run)
+                        .filter(|end| zero_run > 0 && *end <= elements)
+                        .ok_or_else(|| FORMAT.corrupted())?;
+                }
+                code => {
+                    let code = unzigzag(code - CODE_OFFSET);
+                    let residual = reconstruct(0.0, code, self.error_bound);
+                    correct(EBCCResidualCorrection::Quantized {
+                        index,
+                        code,
+                        residual,
+                    })?;
+                    index += 1;
+                }
+            }
+        }
+
+        if !codes.is_empty() || verbatim.next().is_some() {
+            return Err(FORMAT.corrupted());
+        }
+
+        Ok(())
+    }
+}
+
+/// Decode a layered payload of the expected `shape`, which may be modified
+/// during decoding, into the flattened 3D data array.
+///
+/// If `approximation_only` is set, only the base layer is decoded and the
+/// residual layer is skipped.
+pub fn layered_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+    approximation_only: bool,
+) -> EBCCResult<Vec<f32>> {
+    let (header, header_len) = LayeredHeader::read(compressed_data)?;
+
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if header.shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: header.shape,
+            actual: expected_shape,
+        });
+    }
+    let elements = data_len(expected_shape.into())?;
+
+    let Some((base, residual)) = compressed_data
+        .get_mut(header_len..)
+        .and_then(|payload| payload.split_at_mut_checked(header.base_len))
+    else {
+        return Err(FORMAT.truncated());
+    };
+
+    let mut decompressed_data = ebcc_decode_nested(base, expected_shape.into(), depth + 1)?
+        .as_slice()
+        .to_vec();
+    if decompressed_data.len() != elements {
+        return Err(FORMAT.corrupted());
+    }
+
+    if approximation_only {
+        return Ok(decompressed_data);
+    }
+
+    header.for_each_correction(residual, |correction| {
+        let decompressed = decompressed_data
+            .get_mut(correction.index())
+            .ok_or_else(|| FORMAT.corrupted())?;
+        *decompressed = match correction {
+            EBCCResidualCorrection::Quantized { code, .. } => {
+                reconstruct(*decompressed, code, header.error_bound)
+            }
+            EBCCResidualCorrection::Verbatim { value, .. } => value,
+        };
+        Ok(())
+    })?;
+
+    Ok(decompressed_data)
+}
+
+/// Decode only the `JPEG2000` base layer of EBCC compressed data into a 3D
+/// data array, skipping the residual correction.
+///
+/// The decoded approximation is smooth and faster to decode than the full
+/// data, but it does not satisfy the error bound. It is useful for previews,
+/// and for analyzing how much the residual layer contributes to a field.
+///
+/// Only data that was compressed with the [`EBCCBaseMode::Layered`] or with
+/// a [residual coder](crate::EBCCConfig::with_residual_coder) stores its
+/// residual layer separately from the base layer. The `JPEG2000` payloads of
+/// all other base modes mix the residual correction into the EBCC C library
+/// payload, and EBCC frame streams, containers, and tiled data must be
+/// decoded frame by frame. The checksum of the decompressed data, which
+/// covers the full data, is not verified.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::TooManyFrames`], [`EBCCError::OutputTooLarge`], or
+///   [`EBCCError::ShapeTooLarge`] if the shape of the `decompressed_data`
+///   exceeds the default [`EBCCDecodeOptions`]
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` has no separate
+///   residual layer, or if it is truncated or corrupted
+/// - [`EBCCError::ShapeMismatch`] if the shape of the `compressed_data` does
+///   not match the `decompressed_data`
+/// - [`EBCCError::ChecksumMismatch`] if the checksum of the payload does not
+///   match its [`EBCCHeader`][crate::EBCCHeader]
+/// - [`EBCCError::DecompressionError`] if decompression with EBCC fails
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_approximation_into, ebcc_encode, EBCCBaseMode, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((1, 32, 32), |(_, y, x)| (y * x) as f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1).with_base_mode(EBCCBaseMode::Layered);
+///
+/// let compressed = ebcc_encode(data.view(), &config)?;
+///
+/// let mut approximation = Array::zeros(data.dim());
+/// ebcc_decode_approximation_into(&compressed, approximation.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_approximation_into(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    decode_approximation_into(
+        compressed_data,
+        decompressed_data,
+        &EBCCDecodeOptions::new(),
+    )
+}
+
+/// Decode the base layer like [`ebcc_decode_approximation_into`], within the
+/// limits of the decode `options`.
+pub fn decode_approximation_into(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+    options: &EBCCDecodeOptions,
+) -> EBCCResult<()> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    let shape = decompressed_data.dim();
+    options.check_shape(shape)?;
+
+    if is_ebcc_stream(compressed_data)
+        || is_ebcc_container(compressed_data)
+        || is_ebcc_tiled(compressed_data)
+    {
+        return Err(EBCCError::InvalidInput(String::from(
+            "Approximations of EBCC frame streams, containers, and tiled data are not supported, \
+             decode their frames individually",
+        )));
+    }
+
+    let mut compressed_data = Vec::from(compressed_data); // C function may modify the input
+
+    // the decompressed checksum covers the residual layer, which is skipped
+    let (payload, _) = header_payload_mut(&mut compressed_data, shape)?;
+    let approximation = if is_layered(payload) {
+        layered_decode(payload, shape, 0, true)?
+    } else if is_residual_coded(payload) {
+        residual_coded_decode(payload, shape, 0, true)?
+    } else {
+        return Err(EBCCError::InvalidInput(String::from(
+            "EBCC data has no separate residual layer, it must be compressed with the layered \
+             base mode or a residual coder",
+        )));
+    };
+
+    let approximation = CBuffer::from_vec(approximation);
+    copy_decompressed(decompressed_data, decompressed_view(shape, &approximation)?);
+
+    Ok(())
+}
+
+/// Extract the residual layer of EBCC compressed data, without decoding the
+/// `JPEG2000` base layer.
+///
+/// The residual layer lists the corrections of all values that the base
+/// layer fails to approximate within the error bound, which shows the
+/// structure of what `JPEG2000` does not capture, e.g. across an archive.
+/// Only data that was compressed with the [`EBCCBaseMode::Layered`] stores
+/// its residual layer separately from the base layer.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` was not compressed
+///   with the [`EBCCBaseMode::Layered`], or if it is truncated or corrupted
+/// - [`EBCCError::ChecksumMismatch`] if the checksum of the payload does not
+///   match its [`EBCCHeader`][crate::EBCCHeader]
+/// - [`EBCCError::DecompressionError`] if the layered payload version is not
+///   supported
+/// - [`EBCCError::TooManyFrames`], [`EBCCError::OutputTooLarge`], or
+///   [`EBCCError::ShapeTooLarge`] if the shape stored in the payload exceeds
+///   the default [`EBCCDecodeOptions`] limits
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_encode, ebcc_extract_residuals, EBCCBaseMode, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((1, 32, 32), |(_, y, x)| (y * x) as f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1).with_base_mode(EBCCBaseMode::Layered);
+///
+/// let compressed = ebcc_encode(data.view(), &config)?;
+///
+/// let residuals = ebcc_extract_residuals(&compressed)?;
+/// println!("{} of {} values are corrected", residuals.corrections.len(), data.len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_extract_residuals(compressed_data: &[u8]) -> EBCCResult<EBCCResidualLayer> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    let (_, payload) = header_payload(compressed_data)?;
+    if !is_layered(payload) {
+        return Err(not_layered());
+    }
+
+    let (header, header_len) = LayeredHeader::read(payload)?;
+    EBCCDecodeOptions::new().check_shape(header.shape.into())?;
+    let Some(residual) = payload
+        .get(header_len..)
+        .and_then(|payload| payload.get(header.base_len..))
+    else {
+        return Err(FORMAT.truncated());
+    };
+
+    let mut corrections = Vec::new();
+    header.for_each_correction(residual, |correction| {
+        corrections.push(correction);
+        Ok(())
+    })?;
+
+    Ok(EBCCResidualLayer {
+        shape: header.shape,
+        error_bound: header.error_bound,
+        corrections,
+    })
+}
+
+/// Truncate EBCC compressed data to a lower quality that fits into a budget
+/// of `budget_bytes`, e.g. to adapt the delivery of an archive to the
+/// available bandwidth.
+///
+/// Only data that was compressed with the [`EBCCBaseMode::Layered`] can be
+/// truncated, since its residual layer is coded in Rust. The `JPEG2000` base
+/// layer itself cannot be truncated, since the EBCC C library does not
+/// expose the quality layers of its codestream. All other data is rejected,
+/// even if it already fits into the budget.
+///
+/// Data that already fits into the budget is returned unchanged. Otherwise,
+/// the residual layer is requantized with doubled error bounds, one quality
+/// level at a time, until the truncated data fits. Each level bounds the
+/// error of the truncated data by the original error bound plus the
+/// [error bound][EBCCResidualLayer::error_bound] of its residual layer. If
+/// even the coarsest level does not fit, the residual layer is dropped, such
+/// that only the `JPEG2000` base layer, without any error bound, remains.
+///
+/// The truncated data keeps the [`EBCCHeader`][crate::EBCCHeader], if any,
+/// of the `compressed_data`, without a checksum of the decompressed data, and
+/// is decoded like any other EBCC compressed data. Since the configuration
+/// with the error bound that the truncated data keeps is not known, it
+/// records no [`config_fingerprint`][crate::EBCCHeader::config_fingerprint].
+/// Use [`ebcc_truncate_with_config`] to check the configuration of the
+/// `compressed_data` and record the fingerprint of the truncated data.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` was not compressed
+///   with the [`EBCCBaseMode::Layered`], e.g. if it is a container, tiled, or
+///   a stream, or if it is truncated or corrupted
+/// - [`EBCCError::BufferTooSmall`] with the size of the base layer if even
+///   the base layer does not fit into the budget
+/// - [`EBCCError::ChecksumMismatch`] if the checksum of the payload does not
+///   match its [`EBCCHeader`][crate::EBCCHeader]
+/// - [`EBCCError::DecompressionError`] if the header or layered payload
+///   version is not supported
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_into, ebcc_encode, ebcc_truncate, testdata, EBCCBaseMode, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = testdata::temperature((1, 64, 64));
+/// let config = EBCCConfig::max_absolute_error_bounded(0.001).with_base_mode(EBCCBaseMode::Layered);
+///
+/// let compressed = ebcc_encode(data.view(), &config)?;
+/// let preview = ebcc_truncate(&compressed, compressed.len() / 2)?;
+/// assert!(preview.len() <= compressed.len() / 2);
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_into(&preview, decompressed.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_truncate(compressed_data: &[u8], budget_bytes: usize) -> EBCCResult<Vec<u8>> {
+    truncate(compressed_data, None, budget_bytes)
+}
+
+/// Truncate EBCC compressed data like [`ebcc_truncate`], after checking that
+/// it was compressed with the `config`.
+///
+/// The truncated data records the
+/// [`config_fingerprint`][crate::EBCCHeader::config_fingerprint] of the
+/// `config` with the error bound that it keeps, i.e. with the error bound
+/// scaled by `1 + 2^level`, or with [`EBCCResidualType::Jpeg2000Only`] if the
+/// residual layer was dropped.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidConfig`] if the `config` does not match the
+///   configuration fingerprint of the `compressed_data`
+/// - all errors that [`ebcc_truncate`] can return
+pub fn ebcc_truncate_with_config(
+    compressed_data: &[u8],
+    config: &EBCCConfig,
+    budget_bytes: usize,
+) -> EBCCResult<Vec<u8>> {
+    truncate(compressed_data, Some(config), budget_bytes)
+}
+
+/// Truncate EBCC compressed data like [`ebcc_truncate`], after checking that
+/// it was compressed with the `config`, if any, whose truncated fingerprint
+/// is then recorded.
+fn truncate(
+    compressed_data: &[u8],
+    config: Option<&EBCCConfig>,
+    budget_bytes: usize,
+) -> EBCCResult<Vec<u8>> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    let (header, payload) = header_payload(compressed_data)?;
+    if !is_layered(payload) {
+        return Err(not_layered());
+    }
+    if let Some(config) = config {
+        config.check_fingerprint(header.and_then(|header| header.recorded_config_fingerprint()))?;
+    }
+
+    if compressed_data.len() <= budget_bytes {
+        return Ok(compressed_data.to_vec());
+    }
+
+    let payload_budget =
+        budget_bytes.saturating_sub(header.map_or(0, |header| header.encoded_len()));
+
+    let (layered, layered_len) = LayeredHeader::read(payload)?;
+    let Some((base, residual)) = payload
+        .get(layered_len..)
+        .and_then(|payload| payload.split_at_checked(layered.base_len))
+    else {
+        return Err(FORMAT.truncated());
+    };
+
+    let mut corrections = Vec::new();
+    layered.for_each_correction(residual, |correction| {
+        corrections.push(correction);
+        Ok(())
+    })?;
+
+    let elements = data_len(layered.shape.into())?;
+    let max_code = corrections
+        .iter()
+        .map(|correction| match *correction {
+            EBCCResidualCorrection::Quantized { code, .. } => code.abs(),
+            EBCCResidualCorrection::Verbatim { .. } => 0,
+        })
+        .max()
+        .unwrap_or(0);
+    let base_only = LayeredHeader::LEN + base.len() + residual_layer(&[], elements, 0).1.len();
+
+    let mut truncated_payload = None;
+    for level in 1..i64::BITS - 1 {
+        let (verbatim, codes) = residual_layer(&corrections, elements, level);
+        if LayeredHeader::LEN + base.len() + verbatim.len() + codes.len() <= payload_budget {
+            let header = LayeredHeader {
+                error_bound: layered.error_bound * level_scale(level),
+                verbatim_len: verbatim.len(),
+                ..layered
+            };
+            truncated_payload = Some((header.write_payload(base, &verbatim, &codes)?, Some(level)));
+            break;
+        }
+
+        // coarser levels would only keep the same verbatim values
+        if requantize(max_code, level) == 0 {
+            break;
+        }
+    }
+
+    let (truncated_payload, level) = match truncated_payload {
+        Some(truncated_payload) => truncated_payload,
+        None if base_only <= payload_budget => {
+            let header = LayeredHeader {
+                error_bound: f32::INFINITY,
+                verbatim_len: 0,
+                ..layered
+            };
+            (
+                header.write_payload(base, &[], &residual_layer(&[], elements, 0).1)?,
+                None,
+            )
+        }
+        None => {
+            return Err(EBCCError::BufferTooSmall {
+                required: base_only + header.map_or(0, |header| header.encoded_len()),
+                available: budget_bytes,
+            })
+        }
+    };
+
+    let Some(header) = header else {
+        return Ok(truncated_payload);
+    };
+
+    let mut truncated_data = Vec::with_capacity(header.encoded_len() + truncated_payload.len());
+    write_parsed_header(
+        &mut truncated_data,
+        &EBCCHeader {
+            version: EBCC_HEADER_VERSION,
+            config_fingerprint: config
+                .map_or(0, |config| truncated_config(config, level).fingerprint()),
+            has_config_fingerprint: config.is_some(),
+            payload_len: usize_to_u64(truncated_payload.len())?,
+            checksum: header.checksum.algorithm().checksum(&truncated_payload),
+            decompressed_checksum: None,
+            ..header
+        },
+    )?;
+    truncated_data.extend_from_slice(&truncated_payload);
+
+    Ok(truncated_data)
+}
+
+/// The factor `2^level` by which the error bound of a residual layer that is
+/// requantized at the `level` grows.
+fn level_scale(level: u32) -> f32 {
+    #[expect(clippy::cast_possible_wrap)]
+    2.0_f32.powi(level as i32)
+}
+
+/// The `config` with the error bound that data, which was compressed with
+/// it, keeps once its residual layer is requantized at the `level`, or
+/// dropped if there is no level.
+fn truncated_config(config: &EBCCConfig, level: Option<u32>) -> EBCCConfig {
+    // the truncated residual adds its error bound to the original one
+    let residual_compression_type = match (config.residual_compression_type, level) {
+        (EBCCResidualType::AbsoluteError(error), Some(level)) => {
+            EBCCResidualType::AbsoluteError(error * (1.0 + level_scale(level)))
+        }
+        (EBCCResidualType::RelativeError(error), Some(level)) => {
+            EBCCResidualType::RelativeError(error * (1.0 + level_scale(level)))
+        }
+        (_, _) => EBCCResidualType::Jpeg2000Only,
+    };
+
+    EBCCConfig {
+        residual_compression_type,
+        ..config.clone()
+    }
+}
+
+/// Encode the verbatim values and the codes of a residual layer of
+/// `elements` values with the `corrections`, whose quantization codes are
+/// requantized with a `2^level` times larger error bound.
+fn residual_layer(
+    corrections: &[EBCCResidualCorrection],
+    elements: usize,
+    level: u32,
+) -> (Vec<u8>, Vec<u8>) {
+    let mut verbatim = Vec::new();
+    let mut codes = Vec::new();
+    let mut zero_run = 0_u64;
+    let mut next_index = 0;
+
+    for correction in corrections {
+        zero_run += (correction.index() - next_index) as u64;
+        next_index = correction.index() + 1;
+
+        match *correction {
+            EBCCResidualCorrection::Quantized { code, .. } => match requantize(code, level) {
+                0 => zero_run += 1,
+                code => {
+                    flush_zero_run(&mut codes, &mut zero_run);
+                    write_varint(&mut codes, zigzag(code) + CODE_OFFSET);
+                }
+            },
+            EBCCResidualCorrection::Verbatim { value, .. } => {
+                flush_zero_run(&mut codes, &mut zero_run);
+                write_varint(&mut codes, CODE_VERBATIM);
+                verbatim.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+    zero_run += elements.saturating_sub(next_index) as u64;
+    flush_zero_run(&mut codes, &mut zero_run);
+
+    (verbatim, codes)
+}
+
+/// Requantize a quantization `code` with a `2^level` times larger error
+/// bound, rounding half away from zero.
+const fn requantize(code: i64, level: u32) -> i64 {
+    let factor = 1_i64 << level;
+    (code.abs() + factor / 2) / factor * code.signum()
+}
+
+/// Error that the layered encoding requires an absolute or relative error
+/// bound.
+pub fn layered_requires_error_bound() -> EBCCError {
+    EBCCError::InvalidConfig(String::from(
+        "The layered base mode requires an absolute or relative error bound",
+    ))
+}
+
+fn not_layered() -> EBCCError {
+    EBCCError::InvalidInput(String::from(
+        "EBCC data has no separate residual layer, it must be compressed with the layered base \
+         mode",
+    ))
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::*;
+    use crate::{
+        ebcc_decode_into, ebcc_decode_with_options, ebcc_encode, ebcc_inspect, testdata,
+        EbccStreamEncoder, QuantizedResiduals,
+    };
+
+    #[test]
+    fn test_layered_roundtrip() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config =
+            EBCCConfig::max_absolute_error_bounded(0.05).with_base_mode(EBCCBaseMode::Layered);
+        let compressed = ebcc_encode(data.view(), &config)?;
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        assert!(decompressed
+            .iter()
+            .zip(data.iter())
+            .all(|(a, b)| (a - b).abs() <= 0.05));
+
+        // the approximation is the decoded JPEG2000-only base layer
+        let base_config = EBCCConfig::jpeg2000_only(config.base_cr);
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(
+            &ebcc_encode(data.view(), &base_config)?,
+            expected.view_mut(),
+        )?;
+        let mut approximation = Array::zeros(data.dim());
+        ebcc_decode_approximation_into(&compressed, approximation.view_mut())?;
+        assert_eq!(approximation, expected);
+
+        let options = EBCCDecodeOptions::new().with_approximation_only(true);
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_with_options(&compressed, decompressed.view_mut(), &options)?;
+        assert_eq!(decompressed, expected);
+
+        // other payloads have no separate residual layer
+        let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.05))?;
+        assert!(matches!(
+            ebcc_decode_approximation_into(&compressed, approximation.view_mut()),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        assert!(matches!(
+            ebcc_encode(
+                data.view(),
+                &EBCCConfig::jpeg2000_only(10.0).with_base_mode(EBCCBaseMode::Layered)
+            ),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_approximation_formats() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+
+        // residual-coded payloads decode their base layer
+        let config = EBCCConfig::max_absolute_error_bounded(0.05)
+            .with_residual_coder(QuantizedResiduals::ID);
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let mut approximation = Array::zeros(data.dim());
+        ebcc_decode_approximation_into(&compressed, approximation.view_mut())?;
+
+        // the options limits apply to approximations
+        let options = EBCCDecodeOptions::new()
+            .with_approximation_only(true)
+            .with_max_output_bytes(data.len());
+        assert!(matches!(
+            ebcc_decode_with_options(&compressed, approximation.view_mut(), &options),
+            Err(EBCCError::OutputTooLarge { .. })
+        ));
+
+        // frame streams must be decoded frame by frame
+        let mut encoder = EbccStreamEncoder::new(
+            Vec::new(),
+            EBCCConfig::max_absolute_error_bounded(0.05).with_base_mode(EBCCBaseMode::Layered),
+            (32, 48),
+            NonZeroUsize::MIN,
+        )?;
+        for frame in data.outer_iter() {
+            encoder.push_frame(frame)?;
+        }
+        let compressed = encoder.finish()?;
+        assert!(matches!(
+            ebcc_decode_approximation_into(&compressed, approximation.view_mut()),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_residuals() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 32));
+        let config =
+            EBCCConfig::max_absolute_error_bounded(0.001).with_base_mode(EBCCBaseMode::Layered);
+        let compressed = ebcc_encode(data.view(), &config)?;
+
+        let residuals = ebcc_extract_residuals(&compressed)?;
+        assert_eq!(residuals.shape, [2, 32, 32]);
+        assert_eq!(residuals.error_bound.to_bits(), 0.001_f32.to_bits());
+        assert!(!residuals.corrections.is_empty());
+        assert!(residuals
+            .corrections
+            .is_sorted_by_key(EBCCResidualCorrection::index));
+
+        // the base layer plus the residuals reconstructs the decoded data
+        let mut approximation = Array::zeros(data.dim());
+        ebcc_decode_approximation_into(&compressed, approximation.view_mut())?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        let dense = residuals.to_dense()?;
+        for ((residual, approximation), decompressed) in
+            dense.iter().zip(&approximation).zip(&decompressed)
+        {
+            if residual.is_nan() {
+                continue;
+            }
+            assert!((approximation + residual - decompressed).abs() <= 1e-4);
+        }
+
+        assert!(matches!(
+            ebcc_extract_residuals(&ebcc_encode(data.view(), &EBCCConfig::new())?),
+            Err(EBCCError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            ebcc_extract_residuals(&[]),
+            Err(EBCCError::EmptyInput)
+        ));
+
+        // crafted shapes are rejected before anything is allocated
+        let mut crafted = Vec::from(EBCC_LAYERED_MAGIC.as_slice());
+        crafted.extend_from_slice(&EBCC_LAYERED_VERSION.to_le_bytes());
+        for dim in [1_u64 << 20, 1 << 20, 1 << 20] {
+            crafted.extend_from_slice(&dim.to_le_bytes());
+        }
+        crafted.extend_from_slice(&0.1_f32.to_le_bytes());
+        crafted.extend_from_slice(&[0; 2 * 8]);
+        assert!(matches!(
+            ebcc_extract_residuals(&crafted),
+            Err(EBCCError::TooManyFrames { .. } | EBCCError::OutputTooLarge { .. })
+        ));
+        let crafted = EBCCResidualLayer {
+            shape: [1, 1 << 20, 1 << 20],
+            ..residuals
+        };
+        assert!(matches!(
+            crafted.to_dense(),
+            Err(EBCCError::OutputTooLarge { .. } | EBCCError::FrameTooLarge { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 64, 64));
+        let config =
+            EBCCConfig::max_absolute_error_bounded(0.001).with_base_mode(EBCCBaseMode::Layered);
+        let compressed = ebcc_encode(data.view(), &config)?;
+
+        assert_eq!(ebcc_truncate(&compressed, compressed.len())?, compressed);
+
+        let mut approximation = Array::zeros(data.dim());
+        ebcc_decode_approximation_into(&compressed, approximation.view_mut())?;
+        let Err(EBCCError::BufferTooSmall {
+            required: base_only,
+            ..
+        }) = ebcc_truncate(&compressed, 0)
+        else {
+            return Err(EBCCError::InvalidInput(String::from(
+                "truncated below the base layer",
+            )));
+        };
+        assert!(base_only < compressed.len());
+
+        // smaller budgets give coarser, but still decodable, data
+        let mut previous_error = 0.0;
+        for budget in [compressed.len() - 1, compressed.len() * 3 / 4, base_only] {
+            let truncated = ebcc_truncate_with_config(&compressed, &config, budget)?;
+            assert!(truncated.len() <= budget);
+
+            // without the configuration, no fingerprint is recorded
+            let unchecked = ebcc_truncate(&compressed, budget)?;
+            assert_eq!(unchecked.len(), truncated.len());
+            assert_eq!(ebcc_inspect(&unchecked)?.config_fingerprint, None);
+
+            let mut decompressed = Array::zeros(data.dim());
+            ebcc_decode_into(&truncated, decompressed.view_mut())?;
+            let error = decompressed
+                .iter()
+                .zip(data.iter())
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f32::max);
+            assert!(error >= previous_error);
+            previous_error = error;
+
+            // the fingerprint records the error bound that is kept
+            let residuals = ebcc_extract_residuals(&truncated)?;
+            let fingerprint = ebcc_inspect(&truncated)?.config_fingerprint;
+            if residuals.error_bound.is_finite() {
+                assert!(error <= 0.001 + residuals.error_bound + 1e-4);
+                let kept = EBCCConfig {
+                    residual_compression_type: EBCCResidualType::AbsoluteError(
+                        0.001 * (1.0 + residuals.error_bound / 0.001),
+                    ),
+                    ..config.clone()
+                };
+                assert_eq!(fingerprint, Some(kept.fingerprint()));
+            } else {
+                assert_eq!(decompressed, approximation);
+                let kept = EBCCConfig {
+                    residual_compression_type: EBCCResidualType::Jpeg2000Only,
+                    ..config.clone()
+                };
+                assert_eq!(fingerprint, Some(kept.fingerprint()));
+            }
+        }
+
+        // the configuration must match the compressed data
+        assert!(matches!(
+            ebcc_truncate_with_config(&compressed, &EBCCConfig::new(), compressed.len() - 1),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        // other payloads cannot be truncated, even if they fit
+        let config = EBCCConfig::max_absolute_error_bounded(0.001);
+        let compressed = ebcc_encode(data.view(), &config)?;
+        assert!(matches!(
+            ebcc_truncate(&compressed, compressed.len()),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_layered_corrupted() -> EBCCResult<()> {
+        let data = testdata::precipitation((1, 32, 32), 3);
+        let config = EBCCConfig::relative_error_bounded(1e-3).with_base_mode(EBCCBaseMode::Layered);
+        let mut compressed = layered_encode(data.view(), &config, &mut Vec::new())?;
+
+        let decompressed = layered_decode(&mut compressed.clone(), data.dim(), 0, false)?;
+        assert_eq!(decompressed.len(), data.len());
+
+        // truncated payloads are rejected
+        let mut truncated = compressed
+            .get(..compressed.len() - 1)
+            .unwrap_or_default()
+            .to_vec();
+        assert!(layered_decode(&mut truncated, data.dim(), 0, false).is_err());
+
+        // the declared shape must match before anything is allocated
+        assert!(matches!(
+            layered_decode(&mut compressed, (1, 32, 64), 0, true),
+            Err(EBCCError::ShapeMismatch {
+                expected: [1, 32, 32],
+                actual: [1, 32, 64],
+            })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+//! Decoding into a caller-selected memory layout.
+
+use ndarray::{s, Array, ArrayView, Axis, ShapeBuilder};
+
+use crate::codec::{copy_decompressed, ebcc_decode_visit, EbccDim};
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+
+/// Memory layout of the array returned by [`ebcc_decode_with_layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EBCCOutputLayout {
+    /// Standard (row-major, C) layout with shape `(frames, height, width)`
+    #[default]
+    Standard,
+    /// Fortran (column-major) layout with shape `(frames, height, width)`
+    Fortran,
+    /// Standard (row-major, C) layout whose axes are permuted such that axis
+    /// `i` of the output is axis `axes[i]` of the `(frames, height, width)`
+    /// data, as with [`ArrayBase::permuted_axes`][ndarray::ArrayBase::permuted_axes]
+    Permuted([usize; 3]),
+    /// Fortran layout if the compressed data was in Fortran order when it
+    /// was encoded, as recorded in its [`EBCCHeader`], and standard layout
+    /// otherwise
+    AsEncoded,
+}
+
+/// Decode EBCC compressed data of the given `shape` into a new array with the
+/// requested memory `layout`.
+///
+/// The decoded data is written into the requested layout directly while it is
+/// copied out of the buffer that EBCC decompressed into, so that no second
+/// transpose pass is needed, e.g. to feed Fortran or GPU consumers.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the [`EBCCOutputLayout::Permuted`] axes
+///   are not a permutation of `[0, 1, 2]`
+/// - all errors that [`ebcc_decode_into`][crate::ebcc_decode_into] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_with_layout, ebcc_encode, EBCCConfig, EBCCOutputLayout};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((2, 32, 48), |(t, y, x)| (t + y + x) as f32);
+/// let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.01))?;
+///
+/// // (width, height, frames) in row-major order
+/// let transposed = ebcc_decode_with_layout(
+///     &compressed,
+///     data.dim(),
+///     EBCCOutputLayout::Permuted([2, 1, 0]),
+/// )?;
+/// assert_eq!(transposed.dim(), (48, 32, 2));
+/// assert!(transposed.is_standard_layout());
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_with_layout(
+    compressed_data: &[u8],
+    shape: (usize, usize, usize),
+    layout: EBCCOutputLayout,
+) -> EBCCResult<Array<f32, EbccDim>> {
+    let layout = match layout {
+        EBCCOutputLayout::AsEncoded => match EBCCHeader::parse(compressed_data)? {
+            Some(header) if header.fortran_order => EBCCOutputLayout::Fortran,
+            _ => EBCCOutputLayout::Standard,
+        },
+        layout => layout,
+    };
+
+    let (mut decompressed_data, inverse_axes) = match layout {
+        EBCCOutputLayout::Standard | EBCCOutputLayout::AsEncoded => {
+            (Array::zeros(shape), [0, 1, 2])
+        }
+        EBCCOutputLayout::Fortran => (Array::zeros(shape.f()), [0, 1, 2]),
+        EBCCOutputLayout::Permuted(axes) => {
+            let mut inverse_axes = [usize::MAX; 3];
+            for (i, axis) in axes.into_iter().enumerate() {
+                match inverse_axes.get_mut(axis) {
+                    Some(inverse) if *inverse == usize::MAX => *inverse = i,
+                    _ => {
+                        return Err(EBCCError::InvalidInput(format!(
+                            "Output axes {axes:?} are not a permutation of [0, 1, 2]"
+                        )))
+                    }
+                }
+            }
+
+            let dims: [usize; 3] = shape.into();
+            let permuted_shape = axes.map(|axis| dims.get(axis).copied().unwrap_or_default());
+
+            (Array::zeros(permuted_shape), inverse_axes)
+        }
+    };
+
+    // view the output in the (frames, height, width) order of the data
+    let mut output = decompressed_data.view_mut().permuted_axes(inverse_axes);
+
+    ebcc_decode_visit(compressed_data, shape, |first_frame, frames| {
+        copy_decompressed(
+            output.slice_mut(s![
+                first_frame..first_frame + frames.len_of(Axis(0)),
+                ..,
+                ..
+            ]),
+            frames,
+        );
+        Ok(())
+    })?;
+
+    Ok(decompressed_data)
+}
+
+/// Returns `true` if the `data` is in Fortran (column-major) order and not
+/// also in standard (row-major) order.
+pub fn is_fortran_order(data: ArrayView<f32, EbccDim>) -> bool {
+    !data.is_standard_layout() && data.t().is_standard_layout()
+}
+
+/// Copy the `data` in standard (row-major) order into the `copy`.
+///
+/// Fortran-ordered data, e.g. read from Fortran models or netCDF, is
+/// transposed in cache-friendly blocks instead of being gathered element by
+/// element.
+pub fn copy_standard_order(data: ArrayView<f32, EbccDim>, copy: &mut Vec<f32>) {
+    const BLOCK: usize = 64;
+
+    copy.clear();
+
+    if let Some(data) = data.as_slice() {
+        copy.extend_from_slice(data);
+        return;
+    }
+
+    if !is_fortran_order(data) {
+        copy.extend(data.iter().copied());
+        return;
+    }
+
+    let (frames, height, width) = data.dim();
+    copy.resize(data.len(), 0.0);
+
+    // each (frames, width) plane is transposed in square blocks of frames
+    //  and columns, such that both the source cache lines, which run along
+    //  the frames, and the copy's cache lines, which run along the columns,
+    //  are reused within a block
+    for (y, plane) in data.axis_iter(Axis(1)).enumerate() {
+        for t in (0..frames).step_by(BLOCK) {
+            let block_frames = t..(t + BLOCK).min(frames);
+            for x in (0..width).step_by(BLOCK) {
+                let columns = x..(x + BLOCK).min(width);
+                for (t, values) in block_frames.clone().zip(
+                    plane
+                        .slice(s![block_frames.clone(), columns.clone()])
+                        .outer_iter(),
+                ) {
+                    let start = (t * height + y) * width;
+                    if let Some(row) = copy.get_mut(start + columns.start..start + columns.end) {
+                        for (copy, value) in row.iter_mut().zip(values) {
+                            *copy = *value;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::header::EBCC_HEADER_MAGIC;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, EBCCConfig};
+
+    #[test]
+    fn test_decode_with_layout() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.1))?;
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, expected.view_mut())?;
+
+        let standard =
+            ebcc_decode_with_layout(&compressed, data.dim(), EBCCOutputLayout::Standard)?;
+        assert!(standard.is_standard_layout());
+        assert_eq!(standard, expected);
+
+        let fortran = ebcc_decode_with_layout(&compressed, data.dim(), EBCCOutputLayout::Fortran)?;
+        assert!(fortran.t().is_standard_layout());
+        assert_eq!(fortran, expected);
+
+        let permuted = ebcc_decode_with_layout(
+            &compressed,
+            data.dim(),
+            EBCCOutputLayout::Permuted([1, 2, 0]),
+        )?;
+        assert!(permuted.is_standard_layout());
+        assert_eq!(permuted, expected.view().permuted_axes([1, 2, 0]));
+
+        assert!(ebcc_decode_with_layout(
+            &compressed,
+            data.dim(),
+            EBCCOutputLayout::Permuted([0, 0, 1])
+        )
+        .is_err());
+        assert!(ebcc_decode_with_layout(
+            &compressed,
+            data.dim(),
+            EBCCOutputLayout::Permuted([0, 1, 3])
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fortran_order_input() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 40, 150));
+        let mut fortran = Array::zeros(data.dim().f());
+        fortran.assign(&data);
+        assert!(is_fortran_order(fortran.view()));
+        assert!(!is_fortran_order(data.view()));
+
+        let mut copy = Vec::new();
+        copy_standard_order(fortran.view(), &mut copy);
+        assert_eq!(copy.as_slice(), data.as_slice().unwrap_or_default());
+
+        // the transpose is blocked along both the frames and the columns
+        let long = testdata::temperature((70, 3, 130));
+        let mut long_fortran = Array::zeros(long.dim().f());
+        long_fortran.assign(&long);
+        copy_standard_order(long_fortran.view(), &mut copy);
+        assert_eq!(copy.as_slice(), long.as_slice().unwrap_or_default());
+
+        // the payload is independent of the input order, only the header
+        //  records the Fortran order
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let compressed = ebcc_encode(fortran.view(), &config)?;
+        let standard = ebcc_encode(data.view(), &config)?;
+        assert_eq!(
+            compressed.get(EBCCHeader::LEN..),
+            standard.get(EBCCHeader::LEN..)
+        );
+        assert!(EBCCHeader::parse(&compressed)?.is_some_and(|header| header.fortran_order));
+        assert!(EBCCHeader::parse(&standard)?.is_some_and(|header| !header.fortran_order));
+
+        // version 1 headers cannot record the Fortran order
+        let mut v1 = compressed.clone();
+        if let Some(version) = v1.get_mut(EBCC_HEADER_MAGIC.len()..EBCC_HEADER_MAGIC.len() + 4) {
+            version.copy_from_slice(&1_u32.to_le_bytes());
+        }
+        assert!(matches!(
+            EBCCHeader::parse(&v1),
+            Err(EBCCError::DecompressionError(_))
+        ));
+
+        let decompressed =
+            ebcc_decode_with_layout(&compressed, data.dim(), EBCCOutputLayout::AsEncoded)?;
+        assert!(decompressed.t().is_standard_layout());
+        let expected = ebcc_decode_with_layout(&standard, data.dim(), EBCCOutputLayout::AsEncoded)?;
+        assert!(expected.is_standard_layout());
+        assert_eq!(decompressed, expected);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+//! Compression of 4D data with vertical levels and level-dependent error
+//! bounds.
+//!
+//! Atmospheric data often comes as `(time, level, lat, lon)` arrays, whose
+//! levels tolerate different errors, e.g. stratospheric levels larger errors
+//! than surface levels. [`ebcc_encode_levels`] encodes every `(lat, lon)`
+//! frame into one EBCC [`container`][crate::container], with the absolute
+//! error bound of its level. The frames are stored in `(time, level)` order,
+//! such that the frame of time `t` and level `l` has the index
+//! `t * levels + l`. The number of levels and their error bounds are stored
+//! in the container's [metadata][crate::container::EbccContainer::metadata],
+//! such that readers do not need to know them in advance.
+
+use std::io::{Cursor, Read, Seek};
+
+use ndarray::{ArrayView4, ArrayViewMut3, ArrayViewMut4};
+
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::container::{EbccContainer, EbccContainerWriter};
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::size::{u64_to_usize, usize_to_u64};
+
+/// Key of the container metadata with the number of levels as `u64` and the
+/// absolute error bound of every level as `f32`
+const LEVELS_METADATA_KEY: &str = "ebcc.levels";
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC levels metadata");
+
+/// Encode 4D `(time, level, lat, lon)` data into an EBCC container, with a
+/// different absolute error bound for each vertical level.
+///
+/// Each frame is encoded with the `config`, whose
+/// [`residual_compression_type`][EBCCConfig::residual_compression_type] is
+/// replaced by the absolute error bound of the frame's level from the
+/// `bounds`. The container can also be decoded with
+/// [`ebcc_decode_into`][crate::ebcc_decode_into] into an array of shape
+/// `(time * level, lat, lon)`. The error bounds can be read back with
+/// [`ebcc_level_bounds`].
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyDimension`] if the `data` has no time steps or levels
+/// - [`EBCCError::InvalidConfig`] if there is not exactly one error bound per
+///   level
+/// - all errors that [`EbccContainerWriter`] can return, e.g.
+///   [`EBCCError::NonPositiveErrorBound`] if any of the `bounds` is not
+///   positive
+///
+/// # Examples
+///
+/// ```rust
+/// use ebcc::{ebcc_decode_level_into, ebcc_encode_levels, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((2, 3, 32, 32), |(t, l, y, x)| (t + l + y + x) as f32);
+/// // surface, tropospheric, and stratospheric levels
+/// let bounds = [0.01, 0.1, 1.0];
+///
+/// let compressed = ebcc_encode_levels(data.view(), &bounds, &EBCCConfig::new())?;
+///
+/// let mut stratosphere = Array::zeros((2, 32, 32));
+/// ebcc_decode_level_into(&compressed, 2, stratosphere.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_levels(
+    data: ArrayView4<f32>,
+    bounds: &[f32],
+    config: &EBCCConfig,
+) -> EBCCResult<Vec<u8>> {
+    let (times, levels, height, width) = data.dim();
+    if times == 0 || levels == 0 {
+        return Err(EBCCError::EmptyDimension);
+    }
+    if bounds.len() != levels {
+        return Err(EBCCError::InvalidConfig(format!(
+            "Expected one error bound for each of the {levels} levels but got {}",
+            bounds.len(),
+        )));
+    }
+
+    let configs = bounds
+        .iter()
+        .map(|bound| EBCCConfig {
+            residual_compression_type: EBCCResidualType::AbsoluteError(*bound),
+            ..config.clone()
+        })
+        .collect::<Vec<_>>();
+
+    let mut metadata = Vec::with_capacity(8 + bounds.len() * 4);
+    metadata.extend_from_slice(&usize_to_u64(levels)?.to_le_bytes());
+    for bound in bounds {
+        metadata.extend_from_slice(&bound.to_le_bytes());
+    }
+
+    let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (height, width))?;
+    writer.set_metadata(LEVELS_METADATA_KEY, metadata)?;
+    for time in data.outer_iter() {
+        for (frame, config) in time.outer_iter().zip(&configs) {
+            writer.push_frame_with_config(frame, config)?;
+        }
+    }
+    writer.finish()
+}
+
+/// Decode an EBCC container from [`ebcc_encode_levels`] into a 4D
+/// `(time, level, lat, lon)` data array.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the container has no levels metadata, or
+///   a different number of levels than the `decompressed_data`
+/// - [`EBCCError::ShapeMismatch`] if the container does not have the
+///   `(time, lat, lon)` shape of the `decompressed_data` for every level
+/// - all errors that
+///   [`EbccContainer::decode_frame_into`][crate::container::EbccContainer::decode_frame_into]
+///   can return
+pub fn ebcc_decode_levels_into(
+    compressed_data: &[u8],
+    mut decompressed_data: ArrayViewMut4<f32>,
+) -> EBCCResult<()> {
+    let (times, levels, height, width) = decompressed_data.dim();
+    let (mut container, bounds) = open_levels(compressed_data, (times, height, width))?;
+    if levels != bounds.len() {
+        return Err(EBCCError::InvalidInput(format!(
+            "Expected {} levels but got {levels} levels",
+            bounds.len(),
+        )));
+    }
+
+    for (time, mut frames) in decompressed_data.outer_iter_mut().enumerate() {
+        for (level, frame) in frames.outer_iter_mut().enumerate() {
+            container.decode_frame_into(time * levels + level, frame)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decode a single vertical `level` of an EBCC container from
+/// [`ebcc_encode_levels`] into a 3D `(time, lat, lon)` data array.
+///
+/// Only the frames of the `level` are decoded.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the container has no levels metadata, or
+///   if the `level` is not below its number of levels
+/// - [`EBCCError::ShapeMismatch`] if the container does not have the
+///   `(time, lat, lon)` shape of the `decompressed_data` for every level
+/// - all errors that
+///   [`EbccContainer::decode_frame_into`][crate::container::EbccContainer::decode_frame_into]
+///   can return
+pub fn ebcc_decode_level_into(
+    compressed_data: &[u8],
+    level: usize,
+    mut decompressed_data: ArrayViewMut3<f32>,
+) -> EBCCResult<()> {
+    let (mut container, bounds) = open_levels(compressed_data, decompressed_data.dim())?;
+
+    let levels = bounds.len();
+    if level >= levels {
+        return Err(EBCCError::InvalidInput(format!(
+            "Level {level} is out of bounds for data with {levels} levels"
+        )));
+    }
+
+    for (time, frame) in decompressed_data.outer_iter_mut().enumerate() {
+        container.decode_frame_into(time * levels + level, frame)?;
+    }
+
+    Ok(())
+}
+
+/// Read the absolute error bound of every vertical level of an EBCC
+/// container from [`ebcc_encode_levels`].
+///
+/// The number of levels is the length of the returned bounds.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the container has no levels metadata or
+///   its levels metadata is corrupted
+/// - all errors that
+///   [`EbccContainer::open`][crate::container::EbccContainer::open] can
+///   return
+pub fn ebcc_level_bounds(compressed_data: &[u8]) -> EBCCResult<Vec<f32>> {
+    let container = EbccContainer::open(Cursor::new(compressed_data))?;
+
+    read_level_bounds(&container)
+}
+
+/// Open the EBCC container of 4D data whose levels each have the
+/// `(time, lat, lon)` `shape`, read the error bounds of its levels, and check
+/// that it has one frame per time step and level.
+fn open_levels(
+    compressed_data: &[u8],
+    shape: (usize, usize, usize),
+) -> EBCCResult<(LevelsContainer<'_>, Vec<f32>)> {
+    let container = EbccContainer::open(Cursor::new(compressed_data))?;
+    let bounds = read_level_bounds(&container)?;
+
+    let (frame_height, frame_width) = container.frame_shape();
+    let expected = [
+        container.frames().div_ceil(bounds.len()),
+        frame_height,
+        frame_width,
+    ];
+    let actual = <[usize; 3]>::from(shape);
+    if expected != actual || container.frames() % bounds.len() != 0 {
+        return Err(EBCCError::ShapeMismatch { expected, actual });
+    }
+
+    Ok((container, bounds))
+}
+
+/// EBCC container of 4D data that is read from a byte slice
+type LevelsContainer<'a> = EbccContainer<Cursor<&'a [u8]>>;
+
+/// Read the error bounds of the levels from the metadata of the `container`
+fn read_level_bounds<F: Read + Seek>(container: &EbccContainer<F>) -> EBCCResult<Vec<f32>> {
+    let Some(mut metadata) = container
+        .metadata()
+        .get(LEVELS_METADATA_KEY)
+        .map(Vec::as_slice)
+    else {
+        return Err(EBCCError::InvalidInput(String::from(
+            "EBCC container has no levels metadata, it must be encoded with ebcc_encode_levels",
+        )));
+    };
+
+    let levels = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut metadata)?))?;
+    if levels == 0 || metadata.len() != levels.saturating_mul(4) {
+        return Err(FORMAT.corrupted());
+    }
+
+    let mut bounds = Vec::with_capacity(levels);
+    for _ in 0..levels {
+        bounds.push(f32::from_le_bytes(FORMAT.read_array(&mut metadata)?));
+    }
+
+    Ok(bounds)
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::{s, Array, Array4};
+
+    use super::*;
+    use crate::{ebcc_decode_into, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_levels() -> EBCCResult<()> {
+        let data = testdata::temperature((6, 32, 48));
+        let data: Array4<f32> = data
+            .into_shape_with_order((2, 3, 32, 48))
+            .map_err(|err| EBCCError::InvalidInput(err.to_string()))?;
+        let bounds = [0.01, 0.1, 1.0];
+
+        let compressed = ebcc_encode_levels(data.view(), &bounds, &EBCCConfig::new())?;
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_levels_into(&compressed, decompressed.view_mut())?;
+
+        for (level, bound) in bounds.iter().enumerate() {
+            let mut level_data = Array::zeros((2, 32, 48));
+            ebcc_decode_level_into(&compressed, level, level_data.view_mut())?;
+            assert_eq!(level_data, decompressed.slice(s![.., level, .., ..]));
+
+            check_error_bound(
+                data.slice(s![.., level, .., ..]),
+                level_data.view(),
+                &EBCCConfig::max_absolute_error_bounded(*bound),
+            )?;
+        }
+
+        // the frames are stored in (time, level) order
+        let mut frames = Array::zeros((6, 32, 48));
+        ebcc_decode_into(&compressed, frames.view_mut())?;
+        assert_eq!(
+            frames.into_shape_with_order((2, 3, 32, 48)).ok(),
+            Some(decompressed)
+        );
+
+        assert!(matches!(
+            ebcc_encode_levels(
+                data.view(),
+                bounds.get(..2).unwrap_or_default(),
+                &EBCCConfig::new()
+            ),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+        assert_eq!(ebcc_level_bounds(&compressed)?, bounds);
+        assert!(matches!(
+            ebcc_decode_level_into(&compressed, 3, Array::zeros((2, 32, 48)).view_mut()),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        // a wrong factorization of the frames into time steps and levels is
+        //  rejected, even though it has the same number of frames
+        assert!(matches!(
+            ebcc_decode_level_into(&compressed, 0, Array::zeros((3, 32, 48)).view_mut()),
+            Err(EBCCError::ShapeMismatch {
+                expected: [2, 32, 48],
+                actual: [3, 32, 48],
+            })
+        ));
+        assert!(matches!(
+            ebcc_decode_levels_into(&compressed, Array::zeros((3, 2, 32, 48)).view_mut()),
+            Err(EBCCError::ShapeMismatch { .. })
+        ));
+        assert!(matches!(
+            ebcc_decode_levels_into(&compressed, Array::zeros((2, 2, 32, 48)).view_mut()),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        // plain containers have no levels metadata
+        let mut writer = EbccContainerWriter::new(Vec::new(), EBCCConfig::new(), (32, 48))?;
+        for time in data.outer_iter() {
+            for frame in time.outer_iter() {
+                writer.push_frame(frame)?;
+            }
+        }
+        assert!(matches!(
+            ebcc_level_bounds(&writer.finish()?),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -18,15 +18,481 @@
 //!
 //! High-level bindigs to the [EBCC] compressor.
 //!
+//! # Thread safety
+//!
+//! All functions of this crate can be called from many threads at once, and
+//! calls into EBCC's encode and decode functions run in parallel. Every call
+//! creates its own `OpenJPEG` codecs and zstd contexts, so the only
+//! process-global state that the C libraries read is the `OPJ_NUM_THREADS`
+//! environment variable, which must not be modified while EBCC may be
+//! called on other threads.
+//!
+//! All public types are [`Send`] and [`Sync`] if their type parameters, e.g.
+//! the writer of an [`EbccStreamEncoder`], are.
+//!
+//! # Tracing
+//!
+//! With the `tracing` feature, encoding and decoding are instrumented with
+//! debug-level [`tracing`](https://docs.rs/tracing) spans, e.g. for input
+//! validation and the EBCC C call, and with debug-level events that report
+//! the achieved compression ratio.
+//!
+//! # Capturing failures
+//!
+//! Within [`ebcc_with_capture`], every failed encode or decode call records
+//! an [`EBCCCaptureBundle`] with its shape, configuration, and input hash,
+//! and optionally writes its raw input to a file, such that intermittent
+//! failures can be [replayed][EBCCCaptureBundle::replay] offline.
+//!
+//! # Bytemuck
+//!
+//! With the `bytemuck` feature, the fixed-layout headers of the stream
+//! format, [`EbccStreamHeader`] and [`EbccStreamSegmentHeader`], implement
+//! [`bytemuck::Pod`](https://docs.rs/bytemuck/latest/bytemuck/trait.Pod.html)
+//! and can be cast from and to bytes without copying.
+//!
+//! # Memory mapping
+//!
+//! With the `mmap` feature, [`ebcc_decode_mmap`] decodes compressed files
+//! straight from a memory mapping, without first reading them into memory.
+//!
+//! # Checksums
+//!
+//! The payload checksum in the [`EBCCHeader`] uses CRC-32 by default, or
+//! the [`EBCCChecksumAlgorithm`] that is selected with
+//! [`EBCCConfig::with_checksum_algorithm`], e.g. to meet the fixity policy
+//! of an archive. CRC-32C and XXH3 are always available, the BLAKE3
+//! cryptographic hash requires the `blake3` feature.
+//!
+//! # `ndarray` versions
+//!
+//! The public API uses the array views of `ndarray` 0.16. With the
+//! `ndarray015` or the `ndarray017` feature, the 3D views of `ndarray` 0.15
+//! or 0.17 implement [`IntoEbccView`] and [`IntoEbccViewMut`], which convert
+//! them without copying, such that downstream crates can upgrade `ndarray`
+//! independently of this crate. Both features enable the `ndarray` feature.
+//!
+//! # Arrow and Parquet
+//!
+//! With the `arrow` feature, [`ebcc_to_arrow_struct`] stores compressed
+//! chunks as the rows of an Arrow `StructArray` with the
+//! [`ebcc_arrow_schema`], next to their serialized configuration, shape,
+//! config fingerprint, checksum, and [`EBCCEncodeStats`], e.g. to write them
+//! into Parquet files. The chunks are read back, without copying them, with
+//! [`ebcc_from_arrow_struct`], which checks the metadata columns against the
+//! header of each chunk.
+//!
+//! # Manifests
+//!
+//! [`ebcc_encode_with_stats`] returns the shape, configuration, checksum,
+//! compression ratio, and error of a compressed chunk, which an
+//! [`EbccManifest`] collects into an index of a whole dataset. With the
+//! `serde` feature, the manifest, and the configuration enums it records,
+//! can be serialized.
+//!
+//! # Half precision
+//!
+//! With the `half` feature, [`ebcc_decode_into_f16`] and
+//! [`ebcc_decode_into_bf16`] decode into arrays of the `half` crate's `f16`
+//! and `bf16` types, converting the data while it is copied out.
+//!
+//! # `nalgebra`
+//!
+//! With the `nalgebra` feature, [`ebcc_encode_matrix`] and
+//! [`ebcc_decode_matrix`] compress a `nalgebra` [`DMatrix`][nalgebra::DMatrix]
+//! as a single frame directly from and into its column-major storage,
+//! without converting through `ndarray` on the caller side.
+//!
+//! # netCDF
+//!
+//! With the `netcdf` feature, [`ebcc_compress_variable`] compresses a whole
+//! variable of a netCDF file into an EBCC frame stream, reading it chunk by
+//! chunk along its time axis with [`ebcc_variable_chunks`]. The feature
+//! links against the system netCDF and HDF5 libraries.
+//!
+//! # GRIB2
+//!
+//! The [`grib`] module wraps single-frame EBCC payloads into GRIB2 data
+//! representation and data sections of a local-use template, such that
+//! GRIB2 tooling can experiment with EBCC as a packing method.
+//!
+//! # `no_std`
+//!
+//! Without the default `std` feature, the crate is `no_std` but requires
+//! `alloc`, e.g. to run the decoder inside a WASI plugin sandbox. It then
+//! only offers a reduced API, see below.
+//!
+//! # Slices and `ndarray`
+//!
+//! The data arrays of the public API are `ndarray` views.
+//! [`ebcc_encode_slice`][crate::ebcc_encode_slice] and
+//! [`ebcc_decode_into_slice`] instead take flat slices in standard
+//! (row-major) order together with their shape. With `std` and `decode`,
+//! the unsafe [`ebcc_decode_into_raw`][crate::ebcc_decode_into_raw] decodes
+//! into a raw pointer, e.g. into a mapped GPU staging buffer.
+//!
+//! The full API is built on `ndarray` views throughout and requires both the
+//! default `std` and `ndarray` features. Without either of them, the crate
+//! only offers a reduced API that does not depend on `ndarray` unless its
+//! feature is enabled:
+//! - the [`raw`] module encodes, with the `encode` feature, and decodes, with
+//!   the `decode` feature, flat slices with the EBCC C library
+//! - with the `decode` feature, [`ebcc_decode_into_slice`] decodes single
+//!   [`ebcc_encode`][crate::ebcc_encode] or [`raw::encode`] payloads, with or
+//!   without an [`EBCCHeader`], into a flat slice, and, with the `ndarray`
+//!   feature but without `std`, [`ebcc_decode_into`] into a 3D data array
+//!
+//! All other formats and functions, including the high-level encoders and
+//! the [`std::io`] adapters, require both the `std` and the `ndarray`
+//! feature.
+//!
+//! # Encode and decode features
+//!
+//! The default features enable `std`, `ndarray`, and both the `encode` and
+//! the `decode` feature. A decode-only build, e.g. for a viewer that only
+//! decompresses, disables the default features and only enables the
+//! `decode` feature, optionally together with `std` and `ndarray`. It then
+//! does not link the JPEG2000 encoder and the zstd compressor of the EBCC C
+//! library, which shrinks, e.g., WASM binaries substantially. An
+//! encode-only build, e.g. for a producer that never reads its output back,
+//! likewise only enables the `encode` feature.
+//!
+//! With only one of the two features, the codec, stream, container, and
+//! [`std::io`] adapter APIs only export their encoding or decoding half.
+//! The remaining functions of the full API are still available, but fail
+//! with an [`EBCCError::CompressionError`] or an
+//! [`EBCCError::DecompressionError`] when they reach the missing half of
+//! the EBCC C library. The multi-level, transcoding, and conformance runner
+//! APIs require both features.
+//!
+//! # System libraries
+//!
+//! With the `system-libs` feature, or the `EBCC_SYS_USE_SYSTEM_LIBS=1`
+//! environment variable at build time, the EBCC C library is linked against
+//! the system `libopenjp2` and `libzstd`, found with `pkg-config`, instead
+//! of the vendored copies. [`version`] queries the versions of the
+//! `OpenJPEG` and zstd libraries that are linked at runtime, next to the
+//! EBCC version that the bindings were generated for.
+//!
+//! # Allocator
+//!
+//! The EBCC C library allocates its working memory and the buffers that it
+//! returns with `malloc`. With the `rust-alloc` feature, the vendored EBCC,
+//! `OpenJPEG`, and zstd libraries instead allocate through the Rust global
+//! allocator, such that a custom `#[global_allocator]`, e.g. jemalloc with
+//! strict accounting, sees all memory and no buffer is ever freed by a
+//! different allocator than the one that allocated it.
+//!
+//! # Threads
+//!
+//! `OpenJPEG` encodes with as many threads as the `OPJ_NUM_THREADS`
+//! environment variable asks for. A
+//! [deterministic][EBCCConfig::with_deterministic] encode limits the vendored
+//! `OpenJPEG` library to a single thread without modifying the environment.
+//! With the `single-threaded` feature, the vendored `OpenJPEG` and zstd
+//! libraries are built without thread support altogether.
+//!
 //! [EBCC]: https://github.com/spcl/EBCC
 
+#![cfg_attr(not(feature = "std"), no_std)]
+// without both the encode and the decode feature, parts of the crate are
+//  only reachable from the missing half of the API
+#![cfg_attr(not(all(feature = "encode", feature = "decode")), allow(dead_code))]
+
+extern crate alloc;
+
+// without std, the ndarray views are only used by the decode feature
+#[cfg(all(feature = "ndarray", not(any(feature = "std", feature = "decode"))))]
+use ndarray as _;
+
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod accounting;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod adaptive;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod auto;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod batch;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod capabilities;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod capture;
+#[cfg(any(feature = "encode", feature = "decode"))]
+mod cbuffer;
+mod checksum;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod clamp;
+#[cfg(all(feature = "std", feature = "ndarray"))]
 mod codec;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod coder;
+#[cfg(all(feature = "ndarray", any(feature = "std", feature = "decode")))]
+mod compat;
+#[cfg(all(feature = "std", feature = "ndarray"))]
 mod config;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod config_bytes;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod conserve;
+#[cfg(all(not(all(feature = "std", feature = "ndarray")), feature = "decode"))]
+mod core_decode;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod decoder;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod encoder;
 mod error;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod finite;
+#[cfg(feature = "half")]
+mod float16;
+mod header;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod heartbeat;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod interpolate;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod io;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod layered;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod layout;
+#[cfg(all(
+    feature = "std",
+    feature = "ndarray",
+    feature = "encode",
+    feature = "decode"
+))]
+mod levels;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod limits;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod manifest;
+#[cfg(feature = "nalgebra")]
+mod matrix;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod multivar;
+#[cfg(feature = "netcdf")]
+mod nc;
+#[cfg(feature = "async")]
+mod offload;
+#[cfg(any(feature = "encode", all(feature = "std", feature = "ndarray")))]
+mod params;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod quantize;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod rate;
+mod reader;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod reduce;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod residual;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod roi;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod service;
+mod size;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod sketch;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod ssim;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod stage;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod stored;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod stream;
+#[cfg(any(feature = "encode", feature = "decode"))]
+mod sync;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod time;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod trace;
+#[cfg(all(
+    feature = "std",
+    feature = "ndarray",
+    feature = "encode",
+    feature = "decode"
+))]
+mod transcode;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod transform;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+mod units;
+mod version;
 
+#[cfg(feature = "conformance")]
+pub mod conformance;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub mod container;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub mod grib;
+#[cfg(any(feature = "encode", feature = "decode"))]
+pub mod raw;
+#[cfg(all(
+    feature = "std",
+    feature = "ndarray",
+    feature = "encode",
+    feature = "decode"
+))]
+pub mod runner;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub mod testdata;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub mod verify;
+
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use accounting::{
+    ebcc_measure_allocations, ebcc_measure_resources, EBCCAllocationStats, EBCCResourceUsage,
+};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use adaptive::{
+    ebcc_adaptive_base_crs, ebcc_encode_adaptive, EBCC_TILED_MAGIC, EBCC_TILED_VERSION,
+};
+#[cfg(feature = "arrow")]
+pub use arrow::{ebcc_arrow_schema, ebcc_from_arrow_struct, ebcc_to_arrow_struct};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use auto::EBCCTarget;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use batch::{ebcc_encode_batch, ebcc_encode_batch_parallel, EbccBatchEncoder};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use capabilities::{capabilities, EBCCCapabilities};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use capture::{
+    ebcc_with_capture, EBCCCaptureBundle, EBCCCaptureContext, EBCCCaptureOperation,
+    EBCCCaptureOptions, EBCC_CAPTURE_MAGIC,
+};
+pub use checksum::{EBCCChecksum, EBCCChecksumAlgorithm};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use clamp::{EBCCValueRange, EBCC_CLAMP_MAGIC};
+#[cfg(all(feature = "std", feature = "ndarray", feature = "decode"))]
 pub use codec::{
-    ebcc_decode_chunking_into, ebcc_decode_into, ebcc_encode, ebcc_encode_chunking,
-    ebcc_encode_chunking_compat, EBCCChunkShape, EBCCCompatChunkShape, EbccDim, EBCC_NDIMS,
+    ebcc_decode_chunking_into, ebcc_decode_into, ebcc_decode_into_raw, ebcc_decode_into_slice,
+    ebcc_decode_mut_into,
+};
+#[cfg(all(feature = "std", feature = "ndarray", feature = "encode"))]
+pub use codec::{
+    ebcc_encode, ebcc_encode_chunking, ebcc_encode_chunking_compat, ebcc_encode_into,
+    ebcc_encode_into_slice, ebcc_encode_mut, ebcc_encode_slice, EBCCChunkShape,
+    EBCCCompatChunkShape,
+};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use codec::{EbccDim, EBCC_NDIMS};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use coder::{
+    ebcc_register_residual_coder, ebcc_registered_residual_coders, ebcc_unregister_residual_coder,
+    EbccResidualStage, QuantizedResiduals, ResidualCoder, SparseCorrections, EBCC_CODER_MAGIC,
+};
+#[cfg(all(feature = "ndarray", any(feature = "std", feature = "decode")))]
+pub use compat::{IntoEbccView, IntoEbccViewMut};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use config::{
+    EBCCBaseCodec, EBCCBaseMode, EBCCConfig, EBCCConfigOverride, EBCCExpansionFallback,
+    EBCCExpansionGuard, EBCCOverride, EBCCResidualType, EBCCStoredCompression,
 };
-pub use config::{EBCCConfig, EBCCResidualType};
-pub use error::{EBCCError, EBCCResult};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use config_bytes::EBCC_CONFIG_MAGIC;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use conserve::{EBCCConservation, EBCC_CONSERVE_MAGIC};
+#[cfg(all(feature = "std", feature = "ndarray", feature = "encode"))]
+pub use container::ebcc_encode_per_frame;
+#[cfg(all(not(feature = "std"), feature = "ndarray", feature = "decode"))]
+pub use core_decode::{ebcc_decode_into, EbccDim};
+#[cfg(all(not(all(feature = "std", feature = "ndarray")), feature = "decode"))]
+pub use core_decode::{ebcc_decode_into_slice, EBCC_NDIMS};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use decoder::{ebcc_decode_with_options, EbccDecoder};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use encoder::EbccBufferedEncoder;
+pub use error::{
+    EBCCChecksummedData, EBCCError, EBCCErrorKind, EBCCFailure, EBCCFailureCause, EBCCResult,
+};
+#[cfg(feature = "half")]
+pub use float16::{ebcc_decode_into_bf16, ebcc_decode_into_f16};
+pub use header::{EBCCDataType, EBCCHeader, EBCC_HEADER_MAGIC, EBCC_HEADER_VERSION};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use heartbeat::{ebcc_with_heartbeat, EBCCHeartbeat};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use interpolate::{ebcc_decode_interpolated_into, FrameOrigin, TemporalInterpolation};
+#[cfg(all(feature = "std", feature = "ndarray", feature = "decode"))]
+pub use io::ebcc_decode_from_reader;
+#[cfg(all(feature = "std", feature = "ndarray", feature = "encode"))]
+pub use io::ebcc_encode_to_writer;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use layered::{
+    ebcc_decode_approximation_into, ebcc_extract_residuals, ebcc_truncate,
+    ebcc_truncate_with_config, EBCCResidualCorrection, EBCCResidualLayer, EBCC_LAYERED_MAGIC,
+};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use layout::{ebcc_decode_with_layout, EBCCOutputLayout};
+#[cfg(all(
+    feature = "std",
+    feature = "ndarray",
+    feature = "encode",
+    feature = "decode"
+))]
+pub use levels::{
+    ebcc_decode_level_into, ebcc_decode_levels_into, ebcc_encode_levels, ebcc_level_bounds,
+};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use limits::{EBCCDecodeOptions, EBCCLimits};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use manifest::{ebcc_encode_with_stats, EBCCEncodeStats, EbccManifest};
+#[cfg(feature = "nalgebra")]
+pub use matrix::{ebcc_decode_matrix, ebcc_encode_matrix};
+#[cfg(feature = "mmap")]
+pub use mmap::ebcc_decode_mmap;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use multivar::{
+    ebcc_decode_multivar, ebcc_decode_multivar_with_access, ebcc_encode_multivar,
+    EBCCMultiVarConfig, EBCC_MULTIVAR_MAGIC, EBCC_MULTIVAR_VERSION,
+};
+#[cfg(feature = "netcdf")]
+pub use nc::{ebcc_compress_variable, ebcc_variable_chunks, EbccVariableChunks};
+#[cfg(feature = "async")]
+pub use offload::{ebcc_decode_async, ebcc_encode_async, EbccOffloadPool};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use quantize::EBCC_QUANTIZE_MAGIC;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use rate::ebcc_encode_with_ratio;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use reduce::{ebcc_decode_reduce, Reduction};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use roi::EBCCRoi;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use service::{EbccCompressionService, EbccJob};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use sketch::{ebcc_inspect, EBCCInspection, EBCCQuantileSketch, EBCC_SKETCH_MAGIC};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use ssim::{ebcc_encode_ssim_bounded, ebcc_ssim};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use stage::{
+    ebcc_register_stage, ebcc_registered_stages, ebcc_unregister_stage, EBCCStage, EBCC_STAGE_MAGIC,
+};
+#[cfg(all(feature = "std", feature = "ndarray", feature = "encode"))]
+pub use stream::EbccStreamEncoder;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use stream::{
+    EbccStreamHeader, EbccStreamSegmentHeader, EBCC_STREAM_MAGIC, EBCC_STREAM_VERSION,
+};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use time::{EBCCCalendar, EBCCDateTime, EBCCTimeAxis};
+#[cfg(all(
+    feature = "std",
+    feature = "ndarray",
+    feature = "encode",
+    feature = "decode"
+))]
+pub use transcode::{ebcc_transcode, ebcc_transcode_allow_legacy};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use transform::{EBCCTransform, EBCC_TRANSFORM_MAGIC};
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub use units::{ebcc_decode_converted_into, EBCCDecodeMetadata, EBCCUnitConversion};
+pub use version::{version, EBCCVersion};
+
+// criterion is only used by the benchmarks and proptest by the property tests
+#[cfg(test)]
+use ::{criterion as _, proptest as _};
@@ -0,0 +1,507 @@
+//! Safety limits on the size of the data that is encoded or decoded.
+
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::size::data_len;
+
+const DEFAULT_MAX_FRAMES: usize = 1 << 20;
+const DEFAULT_MAX_FRAME_ELEMENTS: usize = 1 << 30;
+// 4 GiB, which is further limited by the address space on 32-bit targets
+const DEFAULT_MAX_OUTPUT_BYTES: usize = u32::MAX as usize;
+
+/// Limits on the number and size of frames that are encoded or decoded.
+///
+/// Archives from third parties may claim to contain enormous numbers of
+/// frames or enormous frames. Checking the claimed shape against these limits
+/// before encoding or decoding protects services from unbounded loops and
+/// allocations.
+///
+/// The [default](Self::new) limits allow up to `2^20` frames (more than a
+/// century of hourly data) of up to `2^30` elements each. Encoding uses the
+/// [`EBCCConfig::limits`][crate::EBCCConfig::limits], while decoding uses the
+/// default limits unless they are changed with
+/// [`EbccDecoder::with_limits`][crate::EbccDecoder::with_limits].
+///
+/// The limits can also pin the expected `(height, width)` frame shape of the
+/// grid, which catches the classic mistake of passing a transposed
+/// `1440x721` field for a `721x1440` grid with a descriptive
+/// [`EBCCError::SwappedDimensions`] error.
+///
+/// Multi-tenant services can further bound the size of the input and output
+/// bytes, the time, and the number of threads of every call, and enforce
+/// different quotas per tenant with different limits, e.g. per
+/// [`EbccCompressionService`][crate::EbccCompressionService] profile. These
+/// quotas are unlimited by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EBCCLimits {
+    /// Maximum number of frames
+    pub max_frames: usize,
+    /// Maximum number of elements, i.e. `height * width`, per frame
+    pub max_frame_elements: usize,
+    /// Optional expected `(height, width)` shape of every frame
+    pub expected_frame_shape: Option<(usize, usize)>,
+    /// Maximum size of the input, i.e. of the data that is encoded or of the
+    /// compressed data that is decoded, in bytes
+    pub max_input_bytes: usize,
+    /// Maximum size of the output, i.e. of the compressed data or of the
+    /// decompressed data, in bytes
+    pub max_output_bytes: usize,
+    /// Optional maximum time that a job of an
+    /// [`EbccCompressionService`][crate::EbccCompressionService] may take
+    /// from its submission until its result
+    ///
+    /// Since the EBCC C library cannot be interrupted, a job that exceeds its
+    /// timeout while it is running still finishes in the background, but its
+    /// result is discarded.
+    pub timeout: Option<Duration>,
+    /// Optional maximum number of threads that parallel encoding, or the jobs
+    /// of the same profile in an
+    /// [`EbccCompressionService`][crate::EbccCompressionService], may use at a
+    /// time
+    pub max_threads: Option<NonZeroUsize>,
+}
+
+impl Default for EBCCLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EBCCLimits {
+    /// Create the default limits.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_frames: DEFAULT_MAX_FRAMES,
+            max_frame_elements: DEFAULT_MAX_FRAME_ELEMENTS,
+            expected_frame_shape: None,
+            max_input_bytes: usize::MAX,
+            max_output_bytes: usize::MAX,
+            timeout: None,
+            max_threads: None,
+        }
+    }
+
+    /// Create limits that accept any number and size of frames.
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            max_frames: usize::MAX,
+            max_frame_elements: usize::MAX,
+            expected_frame_shape: None,
+            max_input_bytes: usize::MAX,
+            max_output_bytes: usize::MAX,
+            timeout: None,
+            max_threads: None,
+        }
+    }
+
+    /// Change the maximum number of frames.
+    #[must_use]
+    pub const fn with_max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+
+    /// Change the maximum number of elements per frame.
+    #[must_use]
+    pub const fn with_max_frame_elements(mut self, max_frame_elements: usize) -> Self {
+        self.max_frame_elements = max_frame_elements;
+        self
+    }
+
+    /// Change the expected `(height, width)` shape of every frame, e.g.
+    /// `(721, 1440)` for a global quarter-degree grid.
+    #[must_use]
+    pub const fn with_expected_frame_shape(mut self, frame_shape: (usize, usize)) -> Self {
+        self.expected_frame_shape = Some(frame_shape);
+        self
+    }
+
+    /// Change the maximum size of the input, in bytes.
+    #[must_use]
+    pub const fn with_max_input_bytes(mut self, max_input_bytes: usize) -> Self {
+        self.max_input_bytes = max_input_bytes;
+        self
+    }
+
+    /// Change the maximum size of the output, in bytes.
+    #[must_use]
+    pub const fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Change the maximum time of a service job.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Change the maximum number of threads.
+    #[must_use]
+    pub const fn with_max_threads(mut self, max_threads: NonZeroUsize) -> Self {
+        self.max_threads = Some(max_threads);
+        self
+    }
+
+    /// Check that data of the given `(frames, height, width)` shape is within
+    /// the limits.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::TooManyFrames`] if the number of `frames` exceeds
+    ///   [`max_frames`][Self::max_frames]
+    /// - [`EBCCError::FrameTooLarge`] if `height * width` exceeds
+    ///   [`max_frame_elements`][Self::max_frame_elements]
+    /// - [`EBCCError::SwappedDimensions`] or [`EBCCError::FrameShapeMismatch`]
+    ///   if the frame shape is the transposed or a different shape than the
+    ///   [`expected_frame_shape`][Self::expected_frame_shape]
+    /// - [`EBCCError::ShapeTooLarge`] if the size of the data does not fit
+    ///   into the address space, e.g. on a 32-bit target
+    pub fn check_shape(&self, shape: (usize, usize, usize)) -> EBCCResult<()> {
+        let (frames, height, width) = shape;
+        self.check_frames(frames)?;
+        self.check_frame_shape((height, width))?;
+        data_len(shape)?;
+        Ok(())
+    }
+
+    /// Check that the number of `frames` is within the limits.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::TooManyFrames`] if the number of `frames` exceeds
+    ///   [`max_frames`][Self::max_frames]
+    pub const fn check_frames(&self, frames: usize) -> EBCCResult<()> {
+        if frames > self.max_frames {
+            return Err(EBCCError::TooManyFrames {
+                frames,
+                limit: self.max_frames,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check that a frame of the given `(height, width)` shape is within the
+    /// limits.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameTooLarge`] if `height * width` exceeds
+    ///   [`max_frame_elements`][Self::max_frame_elements]
+    /// - [`EBCCError::SwappedDimensions`] or [`EBCCError::FrameShapeMismatch`]
+    ///   if the frame shape is the transposed or a different shape than the
+    ///   [`expected_frame_shape`][Self::expected_frame_shape]
+    pub fn check_frame_shape(&self, (height, width): (usize, usize)) -> EBCCResult<()> {
+        if let Some(expected) = self.expected_frame_shape {
+            check_expected_frame_shape(expected, (height, width))?;
+        }
+
+        match height.checked_mul(width) {
+            Some(elements) if elements <= self.max_frame_elements => Ok(()),
+            _ => Err(EBCCError::FrameTooLarge {
+                height,
+                width,
+                limit: self.max_frame_elements,
+            }),
+        }
+    }
+
+    /// Check that an input of the given number of `bytes` is within the
+    /// limits.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InputTooLarge`] if the `bytes` exceed
+    ///   [`max_input_bytes`][Self::max_input_bytes]
+    pub const fn check_input_bytes(&self, bytes: usize) -> EBCCResult<()> {
+        if bytes > self.max_input_bytes {
+            return Err(EBCCError::InputTooLarge {
+                bytes,
+                limit: self.max_input_bytes,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check that an output of the given number of `bytes` is within the
+    /// limits.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::OutputTooLarge`] if the `bytes` exceed
+    ///   [`max_output_bytes`][Self::max_output_bytes]
+    pub const fn check_output_bytes(&self, bytes: usize) -> EBCCResult<()> {
+        if bytes > self.max_output_bytes {
+            return Err(EBCCError::OutputTooLarge {
+                bytes,
+                limit: self.max_output_bytes,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The number of threads that may be used if `requested` threads are
+    /// requested, which is capped at [`max_threads`][Self::max_threads].
+    #[must_use]
+    pub fn threads(&self, requested: NonZeroUsize) -> NonZeroUsize {
+        self.max_threads
+            .map_or(requested, |max_threads| requested.min(max_threads))
+    }
+}
+
+/// Options for decoding compressed data from untrusted sources with
+/// [`ebcc_decode_with_options`][crate::ebcc_decode_with_options].
+///
+/// Malicious or corrupted compressed data may claim an enormous shape in
+/// order to make the decoder allocate tens of gigabytes or loop for a long
+/// time. With these options, the size of the output and the number of frames
+/// are bounded before anything is decoded, and the compressed data can be
+/// required to describe itself with a header that is validated against the
+/// output before the EBCC C library is called.
+///
+/// The [default](Self::new) options allow up to 4 GiB of output and up to
+/// `2^20` frames, and require a self-describing header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EBCCDecodeOptions {
+    /// Maximum size of the decompressed data, in bytes
+    pub max_output_bytes: usize,
+    /// Maximum number of decompressed frames
+    pub max_frames: usize,
+    /// Whether compressed data without a self-describing header is rejected
+    ///
+    /// Single [`ebcc_encode`][crate::ebcc_encode] payloads must then start
+    /// with an [`EBCCHeader`][crate::EBCCHeader], whose shape and checksum
+    /// are validated before decoding. Legacy headerless payloads are
+    /// rejected. EBCC frame streams, containers, and
+    /// [adaptive](crate::ebcc_encode_adaptive) data carry their own headers
+    /// and are accepted.
+    pub strict_header: bool,
+    /// Optional expected `(height, width)` shape of every frame, see
+    /// [`EBCCLimits::expected_frame_shape`]
+    pub expected_frame_shape: Option<(usize, usize)>,
+    /// Whether only the `JPEG2000` base layer is decoded, skipping the
+    /// residual correction, see
+    /// [`ebcc_decode_approximation_into`][crate::ebcc_decode_approximation_into]
+    pub approximation_only: bool,
+    /// Optional [fingerprint][EBCCConfig::fingerprint] of the configuration
+    /// that the compressed data must have been encoded with, see
+    /// [`with_expected_config`][Self::with_expected_config]
+    pub expected_config_fingerprint: Option<u64>,
+}
+
+impl Default for EBCCDecodeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EBCCDecodeOptions {
+    /// Create the default, strict, decode options.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            max_frames: DEFAULT_MAX_FRAMES,
+            strict_header: true,
+            expected_frame_shape: None,
+            approximation_only: false,
+            expected_config_fingerprint: None,
+        }
+    }
+
+    /// Change the maximum size of the decompressed data, in bytes.
+    #[must_use]
+    pub const fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Change the maximum number of decompressed frames.
+    #[must_use]
+    pub const fn with_max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+
+    /// Change whether compressed data without a self-describing header is
+    /// rejected.
+    #[must_use]
+    pub const fn with_strict_header(mut self, strict_header: bool) -> Self {
+        self.strict_header = strict_header;
+        self
+    }
+
+    /// Change the expected `(height, width)` shape of every frame, which
+    /// detects swapped dimensions of the output.
+    #[must_use]
+    pub const fn with_expected_frame_shape(mut self, frame_shape: (usize, usize)) -> Self {
+        self.expected_frame_shape = Some(frame_shape);
+        self
+    }
+
+    /// Change whether only the `JPEG2000` base layer is decoded, which is
+    /// faster but does not satisfy the error bound, e.g. for previews.
+    #[must_use]
+    pub const fn with_approximation_only(mut self, approximation_only: bool) -> Self {
+        self.approximation_only = approximation_only;
+        self
+    }
+
+    /// Require that the compressed data was encoded with a configuration that
+    /// has the same [fingerprint][EBCCConfig::fingerprint] as the `config`,
+    /// such that pipelines detect chunks that were encoded with stale
+    /// settings after a configuration change.
+    ///
+    /// [`ebcc_encode`][crate::ebcc_encode] payloads with an
+    /// [`EBCCHeader`][crate::EBCCHeader], EBCC frame streams,
+    /// [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] data, and the
+    /// frames of [EBCC containers][crate::container] record the fingerprint,
+    /// see [`ebcc_inspect`][crate::ebcc_inspect]. Every frame of a container
+    /// must match, and data that records no fingerprint, e.g. legacy
+    /// headerless payloads or containers before version 4, is rejected.
+    #[must_use]
+    pub fn with_expected_config(mut self, config: &EBCCConfig) -> Self {
+        self.expected_config_fingerprint = Some(config.fingerprint());
+        self
+    }
+
+    /// Check that decompressed data of the given `(frames, height, width)`
+    /// shape is within the options' limits.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::TooManyFrames`] if the number of `frames` exceeds
+    ///   [`max_frames`][Self::max_frames]
+    /// - [`EBCCError::OutputTooLarge`] if the size of the decompressed data
+    ///   exceeds [`max_output_bytes`][Self::max_output_bytes]
+    /// - [`EBCCError::ShapeTooLarge`] if the size of the decompressed data
+    ///   does not fit into the address space, e.g. on a 32-bit target
+    /// - [`EBCCError::SwappedDimensions`] or [`EBCCError::FrameShapeMismatch`]
+    ///   if the frame shape is the transposed or a different shape than the
+    ///   [`expected_frame_shape`][Self::expected_frame_shape]
+    pub fn check_shape(&self, shape: (usize, usize, usize)) -> EBCCResult<()> {
+        let (frames, height, width) = shape;
+        if let Some(expected) = self.expected_frame_shape {
+            check_expected_frame_shape(expected, (height, width))?;
+        }
+        EBCCLimits::unlimited()
+            .with_max_frames(self.max_frames)
+            .check_frames(frames)?;
+
+        let bytes = frames
+            .checked_mul(height)
+            .and_then(|elements| elements.checked_mul(width))
+            .and_then(|elements| elements.checked_mul(std::mem::size_of::<f32>()))
+            .unwrap_or(usize::MAX);
+        if bytes > self.max_output_bytes {
+            return Err(EBCCError::OutputTooLarge {
+                bytes,
+                limit: self.max_output_bytes,
+            });
+        }
+
+        data_len(shape)?;
+        Ok(())
+    }
+}
+
+const fn check_expected_frame_shape(
+    (expected_height, expected_width): (usize, usize),
+    (height, width): (usize, usize),
+) -> EBCCResult<()> {
+    if height == expected_height && width == expected_width {
+        return Ok(());
+    }
+
+    let (expected, actual) = ([expected_height, expected_width], [height, width]);
+    if height == expected_width && width == expected_height {
+        return Err(EBCCError::SwappedDimensions { expected, actual });
+    }
+
+    Err(EBCCError::FrameShapeMismatch { expected, actual })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let limits = EBCCLimits::new();
+
+        // a year of hourly ERA5 frames does not fit into a 32-bit address space
+        assert_eq!(
+            limits.check_shape((24 * 365, 721, 1440)).is_ok(),
+            usize::BITS > 32
+        );
+        assert!(limits.check_shape((DEFAULT_MAX_FRAMES + 1, 1, 1)).is_err());
+        assert!(limits.check_shape((1, 1 << 16, 1 << 16)).is_err());
+        assert!(limits.check_shape((1, usize::MAX, 2)).is_err());
+
+        let limits = limits.with_max_frames(4).with_max_frame_elements(32 * 32);
+        assert!(limits.check_shape((4, 32, 32)).is_ok());
+        assert!(limits.check_shape((5, 32, 32)).is_err());
+        assert!(limits.check_shape((4, 32, 33)).is_err());
+
+        assert!(EBCCLimits::unlimited()
+            .check_shape((1 << 10, 1 << 10, 1 << 8))
+            .is_ok());
+        assert!(matches!(
+            EBCCLimits::unlimited().check_shape((usize::MAX, usize::MAX, 1)),
+            Err(EBCCError::ShapeTooLarge { .. })
+        ));
+
+        let limits = EBCCLimits::new().with_expected_frame_shape((721, 1440));
+        assert!(limits.check_shape((2, 721, 1440)).is_ok());
+        assert!(matches!(
+            limits.check_shape((2, 1440, 721)),
+            Err(EBCCError::SwappedDimensions {
+                expected: [721, 1440],
+                actual: [1440, 721],
+            })
+        ));
+        assert!(matches!(
+            EBCCDecodeOptions::new()
+                .with_expected_frame_shape((721, 1440))
+                .check_shape((2, 720, 1440)),
+            Err(EBCCError::FrameShapeMismatch { .. })
+        ));
+
+        let limits = EBCCLimits::new()
+            .with_max_input_bytes(1024)
+            .with_max_output_bytes(512);
+        assert!(limits.check_input_bytes(1024).is_ok());
+        assert!(matches!(
+            limits.check_input_bytes(1025),
+            Err(EBCCError::InputTooLarge {
+                bytes: 1025,
+                limit: 1024,
+            })
+        ));
+        assert!(limits.check_output_bytes(512).is_ok());
+        assert!(matches!(
+            limits.check_output_bytes(513),
+            Err(EBCCError::OutputTooLarge {
+                bytes: 513,
+                limit: 512,
+            })
+        ));
+
+        let (two, four) = (
+            NonZeroUsize::MIN.saturating_add(1),
+            NonZeroUsize::MIN.saturating_add(3),
+        );
+        assert_eq!(EBCCLimits::new().threads(four), four);
+        assert_eq!(EBCCLimits::new().with_max_threads(two).threads(four), two);
+        assert_eq!(EBCCLimits::new().with_max_threads(four).threads(two), two);
+    }
+}
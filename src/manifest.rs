@@ -0,0 +1,401 @@
+//! Dataset-level manifests of compressed chunks.
+
+use std::collections::BTreeMap;
+
+use ndarray::{Array, ArrayView, Zip};
+
+use crate::checksum::EBCCChecksumAlgorithm;
+use crate::codec::{ebcc_decode_into, ebcc_encode, EbccDim};
+use crate::config::{EBCCBaseMode, EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+use crate::size::usize_to_u64;
+
+/// Statistics of one compressed chunk, which are returned by
+/// [`ebcc_encode_with_stats`] and collected in an [`EbccManifest`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EBCCEncodeStats {
+    /// Shape `[frames, height, width]` of the chunk
+    pub shape: [usize; 3],
+    /// [`EBCCConfig::fingerprint`] of the configuration
+    pub config_fingerprint: u64,
+    /// Base compression ratio of the configuration
+    pub base_cr: f32,
+    /// Base layer of the configuration
+    pub base_mode: EBCCBaseMode,
+    /// Residual compression type and error bound of the configuration
+    pub residual_compression_type: EBCCResidualType,
+    /// Algorithm of the [`checksum`][Self::checksum]
+    pub checksum_algorithm: EBCCChecksumAlgorithm,
+    /// Digest of the payload checksum in the [`EBCCHeader`] of the chunk,
+    /// which covers the payload but not the header itself
+    pub checksum: Vec<u8>,
+    /// Length of the compressed chunk, in bytes
+    pub compressed_len: u64,
+    /// Achieved compression ratio, i.e. the length of the raw `f32` data
+    /// divided by the [`compressed_len`][Self::compressed_len]
+    pub compression_ratio: f64,
+    /// Maximum absolute error of the decoded data
+    pub max_abs_error: f32,
+    /// Root mean square error of the decoded data
+    pub rmse: f64,
+    /// Minimum finite value of the data
+    pub min: f32,
+    /// Maximum finite value of the data
+    pub max: f32,
+    /// Mean of the finite values of the data
+    pub mean: f64,
+}
+
+/// Encode a 3D data array with EBCC, like [`ebcc_encode`], and return the
+/// [`EBCCEncodeStats`] of the compressed chunk next to it.
+///
+/// The compressed data is decoded again to measure its error. Only the
+/// values that are finite in both the data and its decoded reconstruction
+/// contribute to the error statistics.
+///
+/// The minimum, maximum, and mean summarise the finite values of the data,
+/// e.g. to prune chunks by their value range without decoding them. If the
+/// data has no finite values, the minimum is positive and the maximum
+/// negative infinity, and the mean is zero.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_encode`] and [`ebcc_decode_into`] can return
+///
+/// # Examples
+///
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+/// use ebcc::{ebcc_encode_with_stats, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((2, 32, 32), |(t, y, x)| (t + y + x) as f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let (compressed, stats) = ebcc_encode_with_stats(data.view(), &config)?;
+/// assert_eq!(stats.compressed_len, compressed.len() as u64);
+/// assert!(stats.max_abs_error <= 0.1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_with_stats(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+) -> EBCCResult<(Vec<u8>, EBCCEncodeStats)> {
+    let compressed = ebcc_encode(data, config)?;
+    let Some(header) = EBCCHeader::parse(&compressed)? else {
+        return Err(EBCCError::InvalidInput(String::from(
+            "EBCC compressed data is missing its header",
+        )));
+    };
+
+    let mut decompressed = Array::zeros(data.dim());
+    ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+    let (max_abs_error, sum_squares, count) = Zip::from(&data).and(&decompressed).fold(
+        (0.0_f32, 0.0_f64, 0_usize),
+        |(max, sum, count), &x, &y| {
+            if x.is_finite() && y.is_finite() {
+                let error = f64::from(x) - f64::from(y);
+                (max.max((x - y).abs()), error.mul_add(error, sum), count + 1)
+            } else {
+                (max, sum, count)
+            }
+        },
+    );
+    #[expect(clippy::cast_precision_loss)]
+    let rmse = if count == 0 {
+        0.0
+    } else {
+        (sum_squares / count as f64).sqrt()
+    };
+
+    let (min, max, mean) = finite_summary(data);
+
+    let raw_len = usize_to_u64(data.len())?.saturating_mul(4);
+    let compressed_len = usize_to_u64(compressed.len())?;
+    #[expect(clippy::cast_precision_loss)]
+    let compression_ratio = raw_len as f64 / compressed_len as f64;
+
+    let stats = EBCCEncodeStats {
+        shape: data.dim().into(),
+        config_fingerprint: config.fingerprint(),
+        base_cr: config.base_cr,
+        base_mode: config.base_mode,
+        residual_compression_type: config.residual_compression_type,
+        checksum_algorithm: header.checksum.algorithm(),
+        checksum: Vec::from(header.checksum.digest()),
+        compressed_len,
+        compression_ratio,
+        max_abs_error,
+        rmse,
+        min,
+        max,
+        mean,
+    };
+
+    Ok((compressed, stats))
+}
+
+/// The minimum, maximum, and mean of the finite values of the `data`.
+fn finite_summary(data: ArrayView<f32, EbccDim>) -> (f32, f32, f64) {
+    let (min, max, sum, count) = data.iter().copied().filter(|x| x.is_finite()).fold(
+        (f32::INFINITY, f32::NEG_INFINITY, 0.0_f64, 0_usize),
+        |(min, max, sum, count), x| (min.min(x), max.max(x), sum + f64::from(x), count + 1),
+    );
+
+    #[expect(clippy::cast_precision_loss)]
+    let mean = if count == 0 { 0.0 } else { sum / count as f64 };
+
+    (min, max, mean)
+}
+
+/// Manifest of the compressed chunks of a dataset, which records the
+/// [`EBCCEncodeStats`] of every chunk by its name.
+///
+/// Large archives can keep a manifest next to their chunks as an index of
+/// what was compressed with which settings. With the `serde` feature, the
+/// manifest can be serialized, e.g. into JSON.
+///
+/// # Examples
+///
+#[cfg_attr(feature = "encode", doc = "```rust")]
+#[cfg_attr(not(feature = "encode"), doc = "```rust,ignore")]
+/// use ebcc::{ebcc_encode_with_stats, EBCCConfig, EbccManifest};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1);
+///
+/// let mut manifest = EbccManifest::new();
+/// for month in 1..=3 {
+///     let data = Array::from_elem((4, 32, 32), month as f32);
+///     let (_compressed, stats) = ebcc_encode_with_stats(data.view(), &config)?;
+///     manifest.insert(format!("t2m/2000-{month:02}"), stats)?;
+/// }
+///
+/// assert_eq!(manifest.chunks_with_config(&config).count(), 3);
+/// assert!(manifest.max_abs_error() <= 0.1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EbccManifest {
+    chunks: BTreeMap<String, EBCCEncodeStats>,
+}
+
+impl EbccManifest {
+    /// Create an empty manifest.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            chunks: BTreeMap::new(),
+        }
+    }
+
+    /// Record the `stats` of the chunk with the `name`.
+    ///
+    /// Recording the same statistics of a chunk again has no effect.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if a chunk with the same `name` but
+    ///   different statistics has already been recorded
+    pub fn insert(&mut self, name: impl Into<String>, stats: EBCCEncodeStats) -> EBCCResult<()> {
+        let name = name.into();
+
+        match self.chunks.get(&name) {
+            Some(recorded) if *recorded != stats => Err(EBCCError::InvalidInput(format!(
+                "EBCC manifest already records different statistics for the chunk `{name}`",
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                self.chunks.insert(name, stats);
+                Ok(())
+            }
+        }
+    }
+
+    /// Merge the chunks of the `other` manifest, e.g. of another part of the
+    /// dataset, into this manifest.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if both manifests record different
+    ///   statistics for a chunk of the same name, in which case this manifest
+    ///   is left unchanged
+    pub fn merge(&mut self, other: Self) -> EBCCResult<()> {
+        if let Some((name, _)) = other.chunks.iter().find(|(name, stats)| {
+            self.chunks
+                .get(*name)
+                .is_some_and(|recorded| recorded != *stats)
+        }) {
+            return Err(EBCCError::InvalidInput(format!(
+                "EBCC manifests record different statistics for the chunk `{name}`",
+            )));
+        }
+
+        self.chunks.extend(other.chunks);
+
+        Ok(())
+    }
+
+    /// The number of recorded chunks
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns `true` if no chunks are recorded
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// The statistics of the chunk with the `name`, if it is recorded
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&EBCCEncodeStats> {
+        self.chunks.get(name)
+    }
+
+    /// Iterate over the names and statistics of all chunks, sorted by name.
+    pub fn chunks(&self) -> impl Iterator<Item = (&str, &EBCCEncodeStats)> {
+        self.chunks
+            .iter()
+            .map(|(name, stats)| (name.as_str(), stats))
+    }
+
+    /// Iterate over the chunks whose names start with the `prefix`, e.g. all
+    /// chunks of one variable.
+    pub fn chunks_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a EBCCEncodeStats)> {
+        self.chunks()
+            .skip_while(move |(name, _)| *name < prefix)
+            .take_while(move |(name, _)| name.starts_with(prefix))
+    }
+
+    /// Iterate over the chunks that were compressed with a configuration
+    /// that has the same [fingerprint][EBCCConfig::fingerprint] as the
+    /// `config`, e.g. to find the chunks that an outdated policy produced.
+    pub fn chunks_with_config<'a>(
+        &'a self,
+        config: &EBCCConfig,
+    ) -> impl Iterator<Item = (&'a str, &'a EBCCEncodeStats)> {
+        let fingerprint = config.fingerprint();
+        self.chunks()
+            .filter(move |(_, stats)| stats.config_fingerprint == fingerprint)
+    }
+
+    /// Total compressed length of all chunks, in bytes
+    #[must_use]
+    pub fn compressed_len(&self) -> u64 {
+        self.chunks
+            .values()
+            .map(|stats| stats.compressed_len)
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Total compression ratio of all chunks, or NaN if no chunks are
+    /// recorded
+    #[must_use]
+    #[expect(clippy::cast_precision_loss)]
+    pub fn compression_ratio(&self) -> f64 {
+        let raw_len = self
+            .chunks
+            .values()
+            .map(|stats| stats.compression_ratio * stats.compressed_len as f64)
+            .sum::<f64>();
+
+        raw_len / self.compressed_len() as f64
+    }
+
+    /// Maximum absolute error over all chunks
+    #[must_use]
+    pub fn max_abs_error(&self) -> f32 {
+        self.chunks
+            .values()
+            .fold(0.0, |max, stats| max.max(stats.max_abs_error))
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::testdata;
+
+    #[test]
+    fn test_manifest() -> EBCCResult<()> {
+        let data = testdata::temperature((4, 32, 48));
+        let loose = EBCCConfig::max_absolute_error_bounded(0.5);
+        let tight = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let (compressed, stats) = ebcc_encode_with_stats(data.view(), &tight)?;
+        assert_eq!(stats.shape, [4, 32, 48]);
+        assert_eq!(stats.compressed_len, compressed.len() as u64);
+        assert!(stats.max_abs_error <= 0.1);
+        assert!(stats.rmse <= f64::from(stats.max_abs_error));
+        assert!(stats.min <= stats.max);
+        assert!(f64::from(stats.min) <= stats.mean && stats.mean <= f64::from(stats.max));
+        // the checksum covers the payload, like the header checksum
+        let payload = compressed.get(EBCCHeader::LEN..).unwrap_or_default();
+        assert_eq!(
+            stats.checksum,
+            EBCCChecksumAlgorithm::Crc32.checksum(payload).digest()
+        );
+
+        let mut manifest = EbccManifest::new();
+        manifest.insert("t2m/0", stats.clone())?;
+        manifest.insert("t2m/0", stats.clone())?;
+        manifest.insert("t2m/1", ebcc_encode_with_stats(data.view(), &loose)?.1)?;
+
+        let mut other = EbccManifest::new();
+        other.insert("sst/0", ebcc_encode_with_stats(data.view(), &loose)?.1)?;
+        other.insert("t2m/0", stats.clone())?;
+        manifest.merge(other)?;
+
+        assert_eq!(manifest.len(), 3);
+        assert_eq!(manifest.get("t2m/0"), Some(&stats));
+        assert_eq!(
+            manifest
+                .chunks_with_prefix("t2m/")
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["t2m/0", "t2m/1"]
+        );
+        assert_eq!(
+            manifest
+                .chunks_with_config(&loose)
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["sst/0", "t2m/1"]
+        );
+        assert!(manifest.max_abs_error() <= 0.5);
+        assert!(manifest.compression_ratio() > 1.0);
+
+        // conflicting statistics are rejected without changing the manifest
+        let mut conflicting = EbccManifest::new();
+        conflicting.insert("sst/1", stats.clone())?;
+        conflicting.insert("t2m/1", stats.clone())?;
+        assert!(manifest.merge(conflicting).is_err());
+        assert!(manifest.insert("t2m/1", stats).is_err());
+        assert_eq!(manifest.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finite_summary() {
+        let data =
+            Array::from_shape_vec((1, 1, 4), vec![1.0, f32::NAN, 3.0, 5.0]).unwrap_or_default();
+        assert_eq!(finite_summary(data.view()), (1.0, 5.0, 3.0));
+
+        let nan = data.slice(ndarray::s![.., .., 1..2]);
+        assert_eq!(finite_summary(nan), (f32::INFINITY, f32::NEG_INFINITY, 0.0));
+    }
+}
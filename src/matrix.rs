@@ -0,0 +1,117 @@
+//! Encoding and decoding of `nalgebra` matrices.
+//!
+//! A [`DMatrix`] is a single 2D frame whose rows are the height and whose
+//! columns are the width of the data. Since `nalgebra` stores matrices in
+//! column-major order, the matrix storage is viewed as a Fortran-ordered
+//! `(1, nrows, ncols)` array without copying, which the codec transposes in
+//! blocks and records in the [`EBCCHeader`][crate::EBCCHeader].
+
+use nalgebra::DMatrix;
+use ndarray::{ArrayView, ArrayViewMut, ShapeBuilder};
+
+use crate::codec::{ebcc_decode_into, ebcc_encode, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+
+/// Encode a `nalgebra` matrix as a single frame using EBCC compression.
+///
+/// The rows of the `matrix` are the height and its columns the width of the
+/// frame, such that the compressed data can also be decoded with
+/// [`ebcc_decode_into`] into an array of shape `(1, nrows, ncols)`.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_encode`] can return
+///
+/// # Examples
+///
+/// ```rust
+/// use ebcc::{ebcc_decode_matrix, ebcc_encode_matrix, EBCCConfig};
+/// use nalgebra::DMatrix;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let matrix = DMatrix::from_fn(32, 48, |y, x| (y + x) as f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.01);
+///
+/// let compressed = ebcc_encode_matrix(&matrix, &config)?;
+/// let decompressed = ebcc_decode_matrix(&compressed, 32, 48)?;
+/// assert!((decompressed - matrix).amax() <= 0.01);
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_matrix(matrix: &DMatrix<f32>, config: &EBCCConfig) -> EBCCResult<Vec<u8>> {
+    ebcc_encode(matrix_view(matrix)?, config)
+}
+
+/// Decode EBCC compressed data of a single `nrows x ncols` frame into a new
+/// `nalgebra` matrix.
+///
+/// The compressed data does not need to come from [`ebcc_encode_matrix`],
+/// any single frame of shape `(1, nrows, ncols)` is decoded.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_decode_into`] can return
+pub fn ebcc_decode_matrix(
+    compressed_data: &[u8],
+    nrows: usize,
+    ncols: usize,
+) -> EBCCResult<DMatrix<f32>> {
+    let mut matrix = DMatrix::zeros(nrows, ncols);
+    ebcc_decode_into(compressed_data, matrix_view_mut(&mut matrix)?)?;
+    Ok(matrix)
+}
+
+/// View the column-major storage of the `matrix` as a Fortran-ordered
+/// `(1, nrows, ncols)` array.
+fn matrix_view(matrix: &DMatrix<f32>) -> EBCCResult<ArrayView<'_, f32, EbccDim>> {
+    let shape = (1, matrix.nrows(), matrix.ncols());
+    ArrayView::from_shape(shape.f(), matrix.as_slice()).map_err(|_| matrix_len_mismatch(shape))
+}
+
+/// View the column-major storage of the `matrix` as a mutable
+/// Fortran-ordered `(1, nrows, ncols)` array.
+fn matrix_view_mut(matrix: &mut DMatrix<f32>) -> EBCCResult<ArrayViewMut<'_, f32, EbccDim>> {
+    let shape = (1, matrix.nrows(), matrix.ncols());
+    ArrayViewMut::from_shape(shape.f(), matrix.as_mut_slice())
+        .map_err(|_| matrix_len_mismatch(shape))
+}
+
+fn matrix_len_mismatch(shape: (usize, usize, usize)) -> EBCCError {
+    EBCCError::SliceLenMismatch {
+        shape: shape.into(),
+        len: shape.1.saturating_mul(shape.2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_matrix_roundtrip() -> EBCCResult<()> {
+        let data = testdata::temperature((1, 32, 48));
+        let matrix = DMatrix::from_row_iterator(32, 48, data.iter().copied());
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let compressed = ebcc_encode_matrix(&matrix, &config)?;
+        let decompressed = ebcc_decode_matrix(&compressed, 32, 48)?;
+
+        // the column-major matrix is decoded like a row-major array
+        let mut array = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, array.view_mut())?;
+        check_error_bound(data.view(), array.view(), &config)?;
+        assert_eq!(Some(decompressed.transpose().as_slice()), array.as_slice());
+
+        // row-major arrays decode into matrices as well
+        let compressed = ebcc_encode(data.view(), &config)?;
+        assert_eq!(ebcc_decode_matrix(&compressed, 32, 48)?, decompressed);
+
+        assert!(ebcc_decode_matrix(&compressed, 48, 32).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Memory-mapped decoding of EBCC compressed files.
+
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::MmapOptions;
+use ndarray::ArrayViewMut;
+
+use crate::codec::{ebcc_decode_mut_into, EbccDim};
+use crate::error::{EBCCError, EBCCResult};
+
+/// Decode the EBCC compressed file at `path` into a 3D data array by
+/// memory-mapping it.
+///
+/// The file is not read into a [`Vec<u8>`] first, and only the pages that are
+/// touched while decoding are loaded from disk. Since the EBCC C library
+/// does not guarantee that its input is left unmodified, the file is mapped
+/// copy-on-write and decoded with [`ebcc_decode_mut_into`], such that any
+/// modification only copies the affected pages in memory and is never
+/// written back to the file. Decoding a multi-GB chunk file therefore does
+/// not allocate a second buffer of the same size.
+///
+/// The file may contain any data that
+/// [`ebcc_decode_into`][crate::ebcc_decode_into] accepts.
+///
+/// # Errors
+///
+/// - [`EBCCError::Io`] if opening or mapping the file fails
+/// - all errors that [`ebcc_decode_into`][crate::ebcc_decode_into] can return
+///
+/// # Safety
+///
+/// The file must not be modified, e.g. truncated, by this or another process
+/// while it is being decoded, since the memory mapping would otherwise
+/// change underneath the decoder, which is undefined behaviour.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ebcc::ebcc_decode_mmap;
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let mut decompressed = Array::zeros((24, 721, 1440));
+/// // Safety: the chunk file is not modified while it is decoded
+/// unsafe { ebcc_decode_mmap("chunk.ebcc".as_ref(), decompressed.view_mut())? };
+/// # Ok(())
+/// # }
+/// ```
+#[expect(unsafe_code)]
+pub unsafe fn ebcc_decode_mmap(
+    path: &Path,
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    let file = File::open(path)?;
+
+    if file.metadata()?.len() == 0 {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    // Safety: the caller guarantees that the file is not modified while it
+    //         is mapped, and the copy-on-write mapping is never written back
+    let mut compressed_data = unsafe { MmapOptions::new().map_copy(&file)? };
+
+    ebcc_decode_mut_into(&mut compressed_data, decompressed_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, EBCCConfig};
+
+    #[test]
+    fn test_decode_mmap() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.1))?;
+
+        let path = std::env::temp_dir().join(format!("ebcc-mmap-{}.ebcc", std::process::id()));
+        fs::write(&path, &compressed)?;
+
+        let mut expected = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, expected.view_mut())?;
+
+        let mut decompressed = Array::zeros(data.dim());
+        #[expect(unsafe_code)]
+        // Safety: the file is only modified after decoding
+        let result = unsafe { ebcc_decode_mmap(&path, decompressed.view_mut()) };
+
+        // the file is left unmodified
+        let written = fs::read(&path);
+        fs::remove_file(&path)?;
+
+        result?;
+        assert_eq!(written?, compressed);
+        assert!(decompressed
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| a.to_bits() == b.to_bits()));
+
+        Ok(())
+    }
+}
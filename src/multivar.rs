@@ -0,0 +1,734 @@
+//! Correlated compression of several aligned variables.
+//!
+//! Variables such as the `u` and `v` wind components, or the temperature at
+//! adjacent levels, share much of their structure. With
+//! [`ebcc_encode_multivar`], a variable can be predicted from an earlier
+//! reference variable by a linear fit, `x ~ a * y + b`, over the decoded
+//! reference `y`, so that only the, usually much smaller, prediction residual
+//! has to be compressed. Since the prediction uses the decoded reference,
+//! the decoder reproduces it exactly and the error bound of every variable
+//! holds for the variable itself.
+//!
+//! # Format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_MULTIVAR_MAGIC`], the format version as `u32`, the number
+//!   of variables, the number of frames, the frame height, and the frame width
+//!   as `u64`s
+//! - for each variable: the index of its reference variable as `u64`, or
+//!   `u64::MAX` if it is not predicted, the fitted slope `a` and intercept `b`
+//!   as `f64`s, the length of its UTF-8 access tag as `u64`, which is zero if
+//!   the variable is not tagged, followed by the tag, and the length of its
+//!   payload as `u64`, followed by the [`ebcc_encode`] payload of the
+//!   variable or of its prediction residual
+//!
+//! Payloads of version 1, whose variables have no access tags, can still be
+//! decoded.
+//!
+//! # Access tags
+//!
+//! Payloads that mix public and restricted variables can tag variables with
+//! access labels, e.g. `"restricted"`, with
+//! [`EBCCMultiVarConfig::with_access_tag`], like the frames of a
+//! [container][crate::container]. [`ebcc_decode_multivar_with_access`] asks an
+//! access hook before any tagged variable is decoded.
+
+use ndarray::{Array, ArrayView, Zip};
+
+use crate::codec::{ebcc_decode_into, ebcc_encode, EbccDim};
+use crate::config::{EBCCBaseMode, EBCCConfig, EBCCResidualType};
+use crate::container::validate_access_tag;
+use crate::error::{EBCCError, EBCCResult};
+use crate::finite::validate_only_finite_data;
+use crate::limits::EBCCDecodeOptions;
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::verify::data_range;
+
+/// Magic bytes at the start of every multi-variable EBCC payload.
+pub const EBCC_MULTIVAR_MAGIC: &[u8; 8] = b"EBCCMVAR";
+
+/// Version of the multi-variable EBCC payload format.
+///
+/// Payloads of version 1, which have no access tags, can still be decoded.
+pub const EBCC_MULTIVAR_VERSION: u32 = 2;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC multi-variable data");
+
+const NO_REFERENCE: u64 = u64::MAX;
+
+/// Configuration of [`ebcc_encode_multivar`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EBCCMultiVarConfig {
+    /// Configuration of each variable
+    pub configs: Vec<EBCCConfig>,
+    /// Optional index of the earlier reference variable from which each
+    /// variable is predicted
+    pub references: Vec<Option<usize>>,
+    /// Optional access tag of each variable, see
+    /// [`ebcc_decode_multivar_with_access`]
+    pub access_tags: Vec<Option<String>>,
+}
+
+impl EBCCMultiVarConfig {
+    /// Create a new configuration that encodes each of the `variables` with
+    /// the `config`, without any prediction.
+    #[must_use]
+    pub fn new(config: EBCCConfig, variables: usize) -> Self {
+        Self {
+            configs: vec![config; variables],
+            references: vec![None; variables],
+            access_tags: vec![None; variables],
+        }
+    }
+
+    /// Change the configuration of the `variable`.
+    ///
+    /// Variables outside the configuration are rejected by
+    /// [`ebcc_encode_multivar`].
+    #[must_use]
+    pub fn with_config(mut self, variable: usize, config: EBCCConfig) -> Self {
+        if let Some(old) = self.configs.get_mut(variable) {
+            *old = config;
+        }
+        self
+    }
+
+    /// Predict the `variable` from the earlier `reference` variable.
+    ///
+    /// Variables outside the configuration are rejected by
+    /// [`ebcc_encode_multivar`].
+    #[must_use]
+    pub fn with_reference(mut self, variable: usize, reference: usize) -> Self {
+        if let Some(old) = self.references.get_mut(variable) {
+            *old = Some(reference);
+        }
+        self
+    }
+
+    /// Tag the `variable` with the access `tag`, e.g. `"restricted"`.
+    ///
+    /// Variables outside the configuration, and tags that are empty or longer
+    /// than 255 bytes, are rejected by [`ebcc_encode_multivar`].
+    #[must_use]
+    pub fn with_access_tag(mut self, variable: usize, tag: impl Into<String>) -> Self {
+        if let Some(old) = self.access_tags.get_mut(variable) {
+            *old = Some(tag.into());
+        }
+        self
+    }
+
+    /// Validate the configuration for the given number of `variables`.
+    fn validate(&self, variables: usize) -> EBCCResult<()> {
+        if self.configs.len() != variables
+            || self.references.len() != variables
+            || self.access_tags.len() != variables
+        {
+            return Err(EBCCError::InvalidConfig(format!(
+                "Multi-variable configuration for {} variables cannot encode {variables} variables",
+                self.configs.len(),
+            )));
+        }
+
+        for (variable, reference) in self.references.iter().enumerate() {
+            if let Some(reference) = reference {
+                if *reference >= variable {
+                    return Err(EBCCError::InvalidConfig(format!(
+                        "Variable {variable} can only be predicted from an earlier variable, not from {reference}",
+                    )));
+                }
+            }
+        }
+
+        self.access_tags
+            .iter()
+            .flatten()
+            .try_for_each(|tag| validate_access_tag(tag))?;
+
+        self.configs.iter().try_for_each(EBCCConfig::validate)
+    }
+}
+
+/// Encode several aligned 3D `variables` of the same shape together, where
+/// each variable can be predicted from an earlier reference variable.
+///
+/// The prediction residual of a variable with an absolute error bound is
+/// compressed with a slightly tightened absolute error bound, which covers
+/// the rounding of the prediction. A relative error bound is converted into
+/// the absolute error bound that it implies for the variable. Variables with
+/// the [`EBCCBaseMode::Stored`], or whose error bound is too tight to cover
+/// the rounding, are not predicted.
+///
+/// The variables are decoded with [`ebcc_decode_multivar`].
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyDimension`] if there are no `variables`
+/// - [`EBCCError::ShapeMismatch`] if the `variables` have different shapes
+/// - [`EBCCError::InvalidConfig`] if the `config` does not have one
+///   configuration per variable, if a variable is predicted from itself or a
+///   later variable, or if any configuration is invalid
+/// - [`EBCCError::InvalidInput`] if an access tag is empty or longer than 255
+///   bytes
+/// - all errors that [`ebcc_encode`] and [`ebcc_decode_into`] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_multivar, ebcc_encode_multivar, testdata, EBCCConfig, EBCCMultiVarConfig};
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let u = testdata::temperature((2, 32, 64));
+/// let v = u.mapv(|x| 0.5 * x + 1.0);
+///
+/// let config = EBCCMultiVarConfig::new(EBCCConfig::max_absolute_error_bounded(0.1), 2)
+///     .with_reference(1, 0);
+/// let compressed = ebcc_encode_multivar(&[u.view(), v.view()], &config)?;
+///
+/// let decompressed = ebcc_decode_multivar(&compressed)?;
+/// assert_eq!(decompressed.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_multivar(
+    variables: &[ArrayView<f32, EbccDim>],
+    config: &EBCCMultiVarConfig,
+) -> EBCCResult<Vec<u8>> {
+    let Some(first) = variables.first() else {
+        return Err(EBCCError::EmptyDimension);
+    };
+    let shape = first.dim();
+    if let Some(other) = variables.iter().find(|variable| variable.dim() != shape) {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape.into(),
+            actual: other.dim().into(),
+        });
+    }
+    config.validate(variables.len())?;
+
+    let mut compressed_data = Vec::new();
+    compressed_data.extend_from_slice(EBCC_MULTIVAR_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_MULTIVAR_VERSION.to_le_bytes());
+    compressed_data.extend_from_slice(&usize_to_u64(variables.len())?.to_le_bytes());
+    for dim in <[usize; 3]>::from(shape) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+
+    let is_reference = (0..variables.len())
+        .map(|variable| config.references.contains(&Some(variable)))
+        .collect::<Vec<_>>();
+
+    let mut decoded: Vec<Array<f32, EbccDim>> = Vec::with_capacity(variables.len());
+    for (((variable, config), reference), tag) in variables
+        .iter()
+        .zip(&config.configs)
+        .zip(&config.references)
+        .zip(&config.access_tags)
+    {
+        if config.check_finite {
+            validate_only_finite_data(variable)?;
+        }
+
+        let prediction = reference.and_then(|reference| {
+            let reference = decoded.get(reference)?;
+            predicted_config(*variable, config)
+                .map(|config| (reference, fit(*variable, reference.view()), config))
+        });
+
+        let (reference, (slope, intercept), payload, mut decompressed) =
+            if let Some((reference_data, (slope, intercept), residual_config)) = prediction {
+                let mut residual = Array::zeros(shape);
+                Zip::from(&mut residual)
+                    .and(variable)
+                    .and(reference_data)
+                    .for_each(|r, &x, &y| *r = residual_of(x, y, slope, intercept));
+
+                let payload = ebcc_encode(residual.view(), &residual_config)?;
+                ebcc_decode_into(&payload, residual.view_mut())?;
+                Zip::from(&mut residual)
+                    .and(reference_data)
+                    .for_each(|r, &y| *r = reconstruct(*r, y, slope, intercept));
+
+                (*reference, (slope, intercept), payload, residual)
+            } else {
+                let payload = ebcc_encode(*variable, config)?;
+                let mut decompressed = Array::zeros(shape);
+                ebcc_decode_into(&payload, decompressed.view_mut())?;
+                (None, (0.0, 0.0), payload, decompressed)
+            };
+
+        compressed_data.extend_from_slice(
+            &reference
+                .map_or(Ok(NO_REFERENCE), usize_to_u64)?
+                .to_le_bytes(),
+        );
+        compressed_data.extend_from_slice(&slope.to_le_bytes());
+        compressed_data.extend_from_slice(&intercept.to_le_bytes());
+        let tag = tag.as_deref().unwrap_or_default();
+        compressed_data.extend_from_slice(&usize_to_u64(tag.len())?.to_le_bytes());
+        compressed_data.extend_from_slice(tag.as_bytes());
+        compressed_data.extend_from_slice(&usize_to_u64(payload.len())?.to_le_bytes());
+        compressed_data.extend_from_slice(&payload);
+
+        // only references need to be kept decoded
+        if !is_reference.get(decoded.len()).copied().unwrap_or_default() {
+            decompressed = Array::zeros((0, 0, 0));
+        }
+        decoded.push(decompressed);
+    }
+
+    Ok(compressed_data)
+}
+
+/// Decode all variables of a multi-variable payload that was encoded with
+/// [`ebcc_encode_multivar`].
+///
+/// The shape and the total size of the variables are checked against the
+/// default [`EBCCDecodeOptions`] before anything is allocated. All variables
+/// are decoded, including those with an access tag, see
+/// [`ebcc_decode_multivar_with_access`].
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the `compressed_data` is not a
+///   multi-variable payload or is truncated or corrupted
+/// - [`EBCCError::DecompressionError`] if the payload version is not
+///   supported
+/// - [`EBCCError::TooManyFrames`] or [`EBCCError::OutputTooLarge`] if the
+///   shape or the total size of the variables exceeds the default
+///   [`EBCCDecodeOptions`]
+/// - all errors that [`ebcc_decode_into`] can return
+pub fn ebcc_decode_multivar(compressed_data: &[u8]) -> EBCCResult<Vec<Array<f32, EbccDim>>> {
+    decode_multivar(compressed_data, |_, _| true)?
+        .into_iter()
+        .map(|decoded| decoded.ok_or_else(|| FORMAT.corrupted()))
+        .collect()
+}
+
+/// Decode the variables of a multi-variable payload that was encoded with
+/// [`ebcc_encode_multivar`], for which the `access_hook` grants access.
+///
+/// The `access_hook` is called with the index and the access tag of every
+/// tagged variable before anything is decoded, and can, e.g., check the tag
+/// against the permissions of the reader. Untagged variables are always
+/// decoded. Variables to which access is denied are returned as [`None`].
+/// They are only decoded internally if a granted variable is predicted from
+/// them, but are never returned.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_decode_multivar`] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{
+///     ebcc_decode_multivar_with_access, ebcc_encode_multivar, testdata, EBCCConfig,
+///     EBCCMultiVarConfig,
+/// };
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let t2m = testdata::temperature((1, 32, 32));
+/// let precip = t2m.mapv(|x| x - 273.15);
+///
+/// let config = EBCCMultiVarConfig::new(EBCCConfig::max_absolute_error_bounded(0.1), 2)
+///     .with_access_tag(1, "embargoed");
+/// let compressed = ebcc_encode_multivar(&[t2m.view(), precip.view()], &config)?;
+///
+/// let decompressed = ebcc_decode_multivar_with_access(&compressed, |_, tag| tag != "embargoed")?;
+/// assert!(decompressed[0].is_some());
+/// assert!(decompressed[1].is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_multivar_with_access(
+    compressed_data: &[u8],
+    access_hook: impl FnMut(usize, &str) -> bool,
+) -> EBCCResult<Vec<Option<Array<f32, EbccDim>>>> {
+    decode_multivar(compressed_data, access_hook)
+}
+
+/// One variable of a multi-variable payload
+struct VariableEntry<'a> {
+    reference: Option<usize>,
+    slope: f64,
+    intercept: f64,
+    tag: Option<&'a str>,
+    payload: &'a [u8],
+}
+
+fn decode_multivar(
+    compressed_data: &[u8],
+    mut access_hook: impl FnMut(usize, &str) -> bool,
+) -> EBCCResult<Vec<Option<Array<f32, EbccDim>>>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_MULTIVAR_MAGIC.as_slice()) else {
+        return Err(EBCCError::InvalidInput(String::from(
+            "Compressed data is not a multi-variable EBCC payload",
+        )));
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if !(1..=EBCC_MULTIVAR_VERSION).contains(&version) {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC multi-variable version: {version}").into(),
+        ));
+    }
+
+    let variables = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    let shape = shape.into();
+
+    // the untrusted shape and number of variables are checked against the
+    //  decode limits before anything is allocated, all variables are kept
+    let options = EBCCDecodeOptions::new();
+    options.check_shape(shape)?;
+    let bytes = data_len(shape)?
+        .saturating_mul(size_of::<f32>())
+        .saturating_mul(variables);
+    if bytes > options.max_output_bytes {
+        return Err(EBCCError::OutputTooLarge {
+            bytes,
+            limit: options.max_output_bytes,
+        });
+    }
+
+    // the number of variables is untrusted, so the entries grow as they are
+    //  read
+    let mut entries = Vec::new();
+    for variable in 0..variables {
+        let entry = read_entry(&mut reader, version)?;
+        if entry
+            .reference
+            .is_some_and(|reference| reference >= variable)
+        {
+            return Err(FORMAT.corrupted());
+        }
+        entries.push(entry);
+    }
+    if !reader.is_empty() {
+        return Err(FORMAT.corrupted());
+    }
+
+    // denied variables are only decoded if a granted variable is predicted
+    //  from them
+    let mut needed = entries
+        .iter()
+        .enumerate()
+        .map(|(variable, entry)| entry.tag.is_none_or(|tag| access_hook(variable, tag)))
+        .collect::<Vec<_>>();
+    let granted = needed.clone();
+    for (variable, entry) in entries.iter().enumerate().rev() {
+        if let (Some(true), Some(reference)) = (needed.get(variable).copied(), entry.reference) {
+            if let Some(needed) = needed.get_mut(reference) {
+                *needed = true;
+            }
+        }
+    }
+
+    let mut decoded: Vec<Option<Array<f32, EbccDim>>> = Vec::with_capacity(entries.len());
+    for (entry, needed) in entries.iter().zip(needed) {
+        if !needed {
+            decoded.push(None);
+            continue;
+        }
+
+        let mut decompressed = Array::zeros(shape);
+        ebcc_decode_into(entry.payload, decompressed.view_mut())?;
+
+        if let Some(reference) = entry.reference {
+            let Some(Some(reference)) = decoded.get(reference) else {
+                return Err(FORMAT.corrupted());
+            };
+            Zip::from(&mut decompressed)
+                .and(reference)
+                .for_each(|r, &y| *r = reconstruct(*r, y, entry.slope, entry.intercept));
+        }
+
+        decoded.push(Some(decompressed));
+    }
+
+    // variables that were only decoded as references are not returned
+    for (decoded, granted) in decoded.iter_mut().zip(granted) {
+        if !granted {
+            *decoded = None;
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Read the entry of one variable from a payload of the given `version`
+fn read_entry<'a>(reader: &mut &'a [u8], version: u32) -> EBCCResult<VariableEntry<'a>> {
+    let reference = match u64::from_le_bytes(FORMAT.read_array(reader)?) {
+        NO_REFERENCE => None,
+        reference => Some(u64_to_usize(reference).map_err(|_| FORMAT.corrupted())?),
+    };
+    let slope = f64::from_le_bytes(FORMAT.read_array(reader)?);
+    let intercept = f64::from_le_bytes(FORMAT.read_array(reader)?);
+
+    let tag = if version >= 2 {
+        let tag_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?;
+        let Some((tag, rest)) = reader.split_at_checked(tag_len) else {
+            return Err(FORMAT.truncated());
+        };
+        *reader = rest;
+
+        match std::str::from_utf8(tag) {
+            Ok("") => None,
+            Ok(tag) if validate_access_tag(tag).is_ok() => Some(tag),
+            _ => return Err(FORMAT.corrupted()),
+        }
+    } else {
+        None
+    };
+
+    let payload_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(reader)?))?;
+    let Some((payload, rest)) = reader.split_at_checked(payload_len) else {
+        return Err(FORMAT.truncated());
+    };
+    *reader = rest;
+
+    Ok(VariableEntry {
+        reference,
+        slope,
+        intercept,
+        tag,
+        payload,
+    })
+}
+
+/// Configuration with which the prediction residual of the `variable` is
+/// compressed, or [`None`] if the `variable` cannot be predicted.
+fn predicted_config(variable: ArrayView<f32, EbccDim>, config: &EBCCConfig) -> Option<EBCCConfig> {
+    if config.base_mode == EBCCBaseMode::Stored {
+        return None;
+    }
+
+    let error = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => error,
+        EBCCResidualType::RelativeError(error) => data_range(variable) * error,
+        EBCCResidualType::Jpeg2000Only => {
+            return Some(config.clone());
+        }
+    };
+
+    // the prediction and reconstruction are rounded to f32 twice, by at most
+    //  half an ulp of the largest value, which the error bound must cover
+    let max_abs = variable.iter().fold(0.0_f32, |max, x| max.max(x.abs()));
+    let error = (max_abs * f32::EPSILON).mul_add(-2.0, error);
+
+    (error > 0.0).then(|| EBCCConfig {
+        residual_compression_type: EBCCResidualType::AbsoluteError(error),
+        roi: None,
+        ..config.clone()
+    })
+}
+
+/// Least-squares fit of `x ~ slope * y + intercept`.
+fn fit(x: ArrayView<f32, EbccDim>, y: ArrayView<f32, EbccDim>) -> (f64, f64) {
+    #[expect(clippy::cast_precision_loss)]
+    let n = x.len() as f64;
+
+    let (mut sum_x, mut sum_y, mut sum_products, mut sum_squares) = (0.0, 0.0, 0.0, 0.0);
+    Zip::from(&x).and(&y).for_each(|&x, &y| {
+        let (x, y) = (f64::from(x), f64::from(y));
+        sum_x += x;
+        sum_y += y;
+        sum_products += x * y;
+        sum_squares += y * y;
+    });
+
+    let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+    let variance = mean_y.mul_add(-mean_y, sum_squares / n);
+    let covariance = mean_x.mul_add(-mean_y, sum_products / n);
+
+    let slope = if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    };
+    let intercept = slope.mul_add(-mean_y, mean_x);
+
+    if slope.is_finite() && intercept.is_finite() {
+        (slope, intercept)
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+#[expect(clippy::cast_possible_truncation)]
+fn residual_of(x: f32, y: f32, slope: f64, intercept: f64) -> f32 {
+    (f64::from(x) - slope.mul_add(f64::from(y), intercept)) as f32
+}
+
+#[expect(clippy::cast_possible_truncation)]
+fn reconstruct(residual: f32, y: f32, slope: f64, intercept: f64) -> f32 {
+    (slope.mul_add(f64::from(y), intercept) + f64::from(residual)) as f32
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::testdata;
+    use crate::verify::check_error_bound;
+
+    #[test]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_multivar_roundtrip() -> EBCCResult<()> {
+        let t850 = testdata::temperature((2, 32, 64));
+        let mut t700 = t850.mapv(|x| 0.9_f32.mul_add(x, -15.0));
+        for ((_, y, x), t) in t700.indexed_iter_mut() {
+            *t = (((x + y) % 3) as f32).mul_add(0.01, *t);
+        }
+        let variables = [t850.view(), t700.view()];
+
+        let config = EBCCMultiVarConfig::new(EBCCConfig::max_absolute_error_bounded(0.05), 2)
+            .with_config(0, EBCCConfig::relative_error_bounded(0.001))
+            .with_reference(1, 0);
+
+        let compressed = ebcc_encode_multivar(&variables, &config)?;
+        let decompressed = ebcc_decode_multivar(&compressed)?;
+        assert_eq!(decompressed.len(), 2);
+
+        for ((variable, decompressed), config) in
+            variables.iter().zip(&decompressed).zip(&config.configs)
+        {
+            check_error_bound(*variable, decompressed.view(), config)?;
+        }
+
+        // the predicted variable compresses better than on its own
+        let independent = ebcc_encode_multivar(
+            &variables,
+            &EBCCMultiVarConfig {
+                references: vec![None; 2],
+                ..config
+            },
+        )?;
+        assert!(compressed.len() <= independent.len());
+
+        assert!(
+            ebcc_decode_multivar(compressed.split_last().map_or(&[], |(_, rest)| rest)).is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multivar_access_tags() -> EBCCResult<()> {
+        let t850 = testdata::temperature((1, 32, 32));
+        let t700 = t850.mapv(|x| 0.9_f32.mul_add(x, -15.0));
+        let t500 = t850.mapv(|x| 0.8_f32.mul_add(x, -30.0));
+        let variables = [t850.view(), t700.view(), t500.view()];
+
+        let config = EBCCMultiVarConfig::new(EBCCConfig::max_absolute_error_bounded(0.05), 3)
+            .with_reference(1, 0)
+            .with_reference(2, 1)
+            .with_access_tag(0, "restricted")
+            .with_access_tag(2, "embargoed");
+        let compressed = ebcc_encode_multivar(&variables, &config)?;
+        let all = ebcc_decode_multivar(&compressed)?;
+
+        // the hook is asked for every tagged variable
+        let mut asked = Vec::new();
+        let decompressed = ebcc_decode_multivar_with_access(&compressed, |variable, tag| {
+            asked.push((variable, String::from(tag)));
+            false
+        })?;
+        assert_eq!(
+            asked,
+            [
+                (0, String::from("restricted")),
+                (2, String::from("embargoed"))
+            ]
+        );
+
+        // the denied reference is decoded to reconstruct the granted variable,
+        //  but is not returned
+        assert!(decompressed.first().is_some_and(Option::is_none));
+        assert_eq!(decompressed.get(1).and_then(Option::as_ref), all.get(1));
+        assert!(decompressed.get(2).is_some_and(Option::is_none));
+
+        let decompressed = ebcc_decode_multivar_with_access(&compressed, |_, _| true)?;
+        assert!(decompressed
+            .iter()
+            .map(Option::as_ref)
+            .eq(all.iter().map(Some)));
+
+        assert!(matches!(
+            ebcc_encode_multivar(&variables, &config.with_access_tag(1, "")),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        // version 1 payloads without access tags can still be decoded
+        let compressed = ebcc_encode_multivar(
+            &[t850.view()],
+            &EBCCMultiVarConfig::new(EBCCConfig::max_absolute_error_bounded(0.05), 1),
+        )?;
+        let tag_len = EBCC_MULTIVAR_MAGIC.len() + 4 + 4 * 8 + 3 * 8;
+        let mut v1 = Vec::from(compressed.get(..tag_len).unwrap_or_default());
+        v1.extend_from_slice(compressed.get(tag_len + 8..).unwrap_or_default());
+        if let Some(version) = v1.get_mut(EBCC_MULTIVAR_MAGIC.len()..EBCC_MULTIVAR_MAGIC.len() + 4)
+        {
+            version.copy_from_slice(&1_u32.to_le_bytes());
+        }
+        assert_eq!(
+            ebcc_decode_multivar(&v1)?,
+            ebcc_decode_multivar(&compressed)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_multivar() {
+        let data = testdata::temperature((1, 32, 32));
+        let config = EBCCMultiVarConfig::new(EBCCConfig::max_absolute_error_bounded(0.1), 2);
+
+        assert!(matches!(
+            ebcc_encode_multivar(&[], &config),
+            Err(EBCCError::EmptyDimension)
+        ));
+        assert!(matches!(
+            ebcc_encode_multivar(&[data.view()], &config),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            ebcc_encode_multivar(
+                &[data.view(), data.view()],
+                &config.clone().with_reference(0, 1)
+            ),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            ebcc_encode_multivar(&[data.view(), Array::zeros((1, 32, 64)).view()], &config),
+            Err(EBCCError::ShapeMismatch { .. })
+        ));
+
+        // crafted headers are rejected before anything is allocated
+        for (variables, shape) in [(1_u64, [1_u64, 1 << 20, 1 << 20]), (1 << 40, [1, 32, 32])] {
+            let mut compressed = Vec::from(EBCC_MULTIVAR_MAGIC.as_slice());
+            compressed.extend_from_slice(&EBCC_MULTIVAR_VERSION.to_le_bytes());
+            compressed.extend_from_slice(&variables.to_le_bytes());
+            for dim in shape {
+                compressed.extend_from_slice(&dim.to_le_bytes());
+            }
+            assert!(matches!(
+                ebcc_decode_multivar(&compressed),
+                Err(EBCCError::OutputTooLarge { .. } | EBCCError::FrameTooLarge { .. })
+            ));
+        }
+    }
+}
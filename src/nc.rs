@@ -0,0 +1,448 @@
+//! Compression of whole `netCDF` variables.
+//!
+//! The last two dimensions of a variable are the height and width of its
+//! frames, and its first dimension is the time axis, along which the
+//! variable is read chunk by chunk. All other dimensions, e.g. vertical
+//! levels, are flattened into the frames of each time step.
+//!
+//! Packed variables are unpacked with their `scale_factor` and `add_offset`
+//! attributes, and values that equal their `_FillValue` attribute are read
+//! as NaN, following the CF conventions.
+
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+
+use ndarray::Array;
+
+use crate::codec::EbccDim;
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::size::data_len;
+use crate::stream::EbccStreamEncoder;
+
+/// Iterator over the chunks of a `netCDF` variable along its time axis,
+/// which is created by [`ebcc_variable_chunks`].
+///
+/// Every item is the range of time steps of the chunk together with its
+/// `(frames, height, width)` data, where every time step contributes the
+/// product of the sizes of all dimensions between the time axis and the
+/// last two dimensions as frames.
+pub struct EbccVariableChunks<'f> {
+    variable: netcdf::Variable<'f>,
+    dims: Vec<usize>,
+    chunk_steps: usize,
+    next_step: usize,
+    packing: Packing,
+}
+
+/// CF packing and missing value attributes of a `netCDF` variable
+struct Packing {
+    scale_factor: f64,
+    add_offset: f64,
+    fill_value: Option<f32>,
+}
+
+impl Packing {
+    fn read(variable: &netcdf::Variable) -> EBCCResult<Self> {
+        Ok(Self {
+            scale_factor: numeric_attribute(variable, "scale_factor")?.unwrap_or(1.0),
+            add_offset: numeric_attribute(variable, "add_offset")?.unwrap_or(0.0),
+            #[expect(clippy::cast_possible_truncation)]
+            fill_value: numeric_attribute(variable, "_FillValue")?.map(|fill| fill as f32),
+        })
+    }
+
+    #[expect(clippy::float_cmp)]
+    fn is_packed(&self) -> bool {
+        self.scale_factor != 1.0 || self.add_offset != 0.0
+    }
+
+    /// Unpack the raw `data` of the variable in place
+    fn unpack(&self, data: &mut [f32]) {
+        let is_packed = self.is_packed();
+        if !is_packed && self.fill_value.is_none() {
+            return;
+        }
+
+        for value in data {
+            if self
+                .fill_value
+                .is_some_and(|fill| fill.to_bits() == value.to_bits())
+            {
+                *value = f32::NAN;
+            } else if is_packed {
+                #[expect(clippy::cast_possible_truncation)]
+                let unpacked = f64::from(*value).mul_add(self.scale_factor, self.add_offset) as f32;
+                *value = unpacked;
+            }
+        }
+    }
+}
+
+impl EbccVariableChunks<'_> {
+    /// Number of time steps of the variable, which is one for variables
+    /// with two dimensions
+    #[must_use]
+    pub fn steps(&self) -> usize {
+        match self.dims.as_slice() {
+            [steps, _, _, ..] => *steps,
+            _ => 1,
+        }
+    }
+
+    /// Number of time steps per chunk, which is the chunk size of the time
+    /// axis in the `netCDF` file, or one for contiguous variables
+    #[must_use]
+    pub const fn chunk_steps(&self) -> usize {
+        self.chunk_steps
+    }
+
+    /// Shape `(height, width)` of every frame
+    #[must_use]
+    pub fn frame_shape(&self) -> (usize, usize) {
+        match self.dims.as_slice() {
+            [.., height, width] => (*height, *width),
+            _ => (0, 0),
+        }
+    }
+
+    /// Number of frames per time step
+    #[must_use]
+    pub fn frames_per_step(&self) -> usize {
+        self.dims
+            .get(1..self.dims.len().saturating_sub(2))
+            .unwrap_or_default()
+            .iter()
+            .product()
+    }
+
+    fn read_chunk(&self, steps: Range<usize>) -> EBCCResult<Array<f32, EbccDim>> {
+        let (height, width) = self.frame_shape();
+        let shape = (
+            steps.len().saturating_mul(self.frames_per_step()),
+            height,
+            width,
+        );
+
+        let mut data = vec![0.0; data_len(shape)?];
+        let extents = self
+            .dims
+            .iter()
+            .enumerate()
+            .map(|(axis, len)| {
+                if axis == 0 && self.dims.len() > 2 {
+                    steps.clone()
+                } else {
+                    0..*len
+                }
+            })
+            .collect::<Vec<_>>();
+        self.variable
+            .get_values_into(&mut data, extents)
+            .map_err(|err| netcdf_error(&self.variable.name(), &err))?;
+        self.packing.unpack(&mut data);
+
+        Array::from_shape_vec(shape, data).map_err(|_| EBCCError::ShapeTooLarge {
+            shape: shape.into(),
+        })
+    }
+}
+
+impl Iterator for EbccVariableChunks<'_> {
+    type Item = EBCCResult<(Range<usize>, Array<f32, EbccDim>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let steps = self.steps();
+        if self.next_step >= steps {
+            return None;
+        }
+
+        let chunk = self.next_step..(self.next_step + self.chunk_steps).min(steps);
+        self.next_step = chunk.end;
+
+        Some(self.read_chunk(chunk.clone()).map(|data| (chunk, data)))
+    }
+}
+
+/// Iterate over the chunks of the `netCDF` `variable` in the `file` along its
+/// time axis, honouring the chunking of the time axis in the file.
+///
+/// Variables with two dimensions are read as a single frame, variables with
+/// more dimensions are read in chunks of their first, time, dimension. The
+/// values are converted into `f32` by the `netCDF` library, unpacked with
+/// the `scale_factor` and `add_offset` attributes of the variable, if any,
+/// and values that equal its `_FillValue` attribute are read as NaN.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the `variable` does not exist in the
+///   `file`, has fewer than two dimensions, or if its `scale_factor`,
+///   `add_offset`, or `_FillValue` attribute is not a single number
+pub fn ebcc_variable_chunks<'f>(
+    file: &'f netcdf::File,
+    variable: &str,
+) -> EBCCResult<EbccVariableChunks<'f>> {
+    let Some(var) = file.variable(variable) else {
+        return Err(EBCCError::InvalidInput(format!(
+            "netCDF variable `{variable}` does not exist"
+        )));
+    };
+
+    let dims = var
+        .dimensions()
+        .iter()
+        .map(netcdf::Dimension::len)
+        .collect::<Vec<_>>();
+    if dims.len() < 2 {
+        return Err(EBCCError::InvalidInput(format!(
+            "netCDF variable `{variable}` must have at least two dimensions, got {}",
+            dims.len(),
+        )));
+    }
+
+    // contiguous variables and netCDF-3 files, which have no chunking, are
+    //  read one time step at a time
+    let chunk_steps = if dims.len() > 2 {
+        var.chunking()
+            .ok()
+            .flatten()
+            .and_then(|chunking| chunking.first().copied())
+            .unwrap_or(1)
+            .max(1)
+    } else {
+        1
+    };
+
+    let packing = Packing::read(&var)?;
+
+    Ok(EbccVariableChunks {
+        variable: var,
+        dims,
+        chunk_steps,
+        next_step: 0,
+        packing,
+    })
+}
+
+/// Compress the whole `netCDF` `variable` in the `file` with the `config`
+/// into an EBCC frame stream that is written to the `sink`, and return the
+/// `sink`.
+///
+/// The variable is read chunk by chunk with [`ebcc_variable_chunks`], such
+/// that only one chunk is in memory at a time, and every chunk is encoded
+/// into its own stream segment. The stream decodes with
+/// [`ebcc_decode_into`][crate::ebcc_decode_into] into an array of shape
+/// `(frames, height, width)`, where the frames of all time steps follow each
+/// other.
+///
+/// # Errors
+///
+/// - all errors that [`ebcc_variable_chunks`] can return
+/// - [`EBCCError::InvalidInput`] if reading the variable fails
+/// - all errors that [`EbccStreamEncoder::new`],
+///   [`EbccStreamEncoder::push_frame`], and [`EbccStreamEncoder::finish`]
+///   can return, e.g. [`EBCCError::NonFinite`] for missing values that are
+///   stored as NaN or as the `_FillValue` of the variable
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+///
+/// use ebcc::{ebcc_compress_variable, EBCCConfig};
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let file = netcdf::open("era5.nc")
+///     .map_err(|err| ebcc::EBCCError::InvalidInput(err.to_string()))?;
+/// let sink = BufWriter::new(File::create("era5-t2m.ebcc")?);
+///
+/// ebcc_compress_variable(&file, "t2m", &EBCCConfig::max_absolute_error_bounded(0.01), sink)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_compress_variable<W: Write>(
+    file: &netcdf::File,
+    variable: &str,
+    config: &EBCCConfig,
+    sink: W,
+) -> EBCCResult<W> {
+    let chunks = ebcc_variable_chunks(file, variable)?;
+
+    let segment_frames = chunks
+        .chunk_steps()
+        .saturating_mul(chunks.frames_per_step());
+    let mut encoder = EbccStreamEncoder::new(
+        sink,
+        config.clone(),
+        chunks.frame_shape(),
+        NonZeroUsize::new(segment_frames).unwrap_or(NonZeroUsize::MIN),
+    )?;
+
+    for chunk in chunks {
+        let (_, data) = chunk?;
+        for frame in data.outer_iter() {
+            encoder.push_frame(frame)?;
+        }
+    }
+
+    encoder.finish()
+}
+
+/// Read the numeric attribute `name` of the `variable`, if it exists
+fn numeric_attribute(variable: &netcdf::Variable, name: &str) -> EBCCResult<Option<f64>> {
+    use netcdf::AttributeValue;
+
+    let Some(attribute) = variable.attribute(name) else {
+        return Ok(None);
+    };
+
+    let value = match attribute
+        .value()
+        .map_err(|err| netcdf_error(&variable.name(), &err))?
+    {
+        AttributeValue::Uchar(value) => f64::from(value),
+        AttributeValue::Schar(value) => f64::from(value),
+        AttributeValue::Ushort(value) => f64::from(value),
+        AttributeValue::Short(value) => f64::from(value),
+        AttributeValue::Uint(value) => f64::from(value),
+        AttributeValue::Int(value) => f64::from(value),
+        AttributeValue::Float(value) => f64::from(value),
+        AttributeValue::Double(value) => value,
+        AttributeValue::Floats(values) if values.len() == 1 => {
+            values.first().copied().map_or(f64::NAN, f64::from)
+        }
+        AttributeValue::Doubles(values) if values.len() == 1 => {
+            values.first().copied().unwrap_or(f64::NAN)
+        }
+        _ => {
+            return Err(EBCCError::InvalidInput(format!(
+                "netCDF attribute `{name}` of variable `{}` must be a single number",
+                variable.name(),
+            )))
+        }
+    };
+
+    Ok(Some(value))
+}
+
+fn netcdf_error(variable: &str, err: &netcdf::Error) -> EBCCError {
+    EBCCError::InvalidInput(format!(
+        "Reading the netCDF variable `{variable}` failed: {err}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ebcc_decode_into, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_compress_variable() -> EBCCResult<()> {
+        let path = std::env::temp_dir().join(format!("ebcc-netcdf-test-{}.nc", std::process::id()));
+        let data = testdata::temperature((5, 32, 48));
+
+        {
+            let mut file = netcdf::create(&path).map_err(|err| netcdf_error("", &err))?;
+            for (name, len) in [("time", 5), ("lat", 32), ("lon", 48)] {
+                file.add_dimension(name, len)
+                    .map_err(|err| netcdf_error(name, &err))?;
+            }
+            let mut var = file
+                .add_variable::<f32>("t2m", &["time", "lat", "lon"])
+                .map_err(|err| netcdf_error("t2m", &err))?;
+            var.set_chunking(&[2, 32, 48])
+                .map_err(|err| netcdf_error("t2m", &err))?;
+            var.put_values(data.as_slice().unwrap_or_default(), ..)
+                .map_err(|err| netcdf_error("t2m", &err))?;
+        }
+
+        let file = netcdf::open(&path).map_err(|err| netcdf_error("", &err))?;
+
+        let chunks = ebcc_variable_chunks(&file, "t2m")?;
+        assert_eq!(chunks.chunk_steps(), 2);
+        assert_eq!(
+            chunks
+                .map(|chunk| chunk.map(|(steps, _)| steps))
+                .collect::<EBCCResult<Vec<_>>>()?,
+            [0..2, 2..4, 4..5]
+        );
+
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let compressed = ebcc_compress_variable(&file, "t2m", &config, Vec::new())?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        check_error_bound(data.view(), decompressed.view(), &config)?;
+
+        assert!(ebcc_variable_chunks(&file, "missing").is_err());
+
+        drop(file);
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_variable() -> EBCCResult<()> {
+        let path =
+            std::env::temp_dir().join(format!("ebcc-netcdf-packed-test-{}.nc", std::process::id()));
+        let data = testdata::temperature((2, 32, 32));
+        let (scale_factor, add_offset) = (0.01_f32, 273.15_f32);
+        #[expect(clippy::cast_possible_truncation)]
+        let mut packed = data
+            .iter()
+            .map(|value| ((value - add_offset) / scale_factor).round() as i16)
+            .collect::<Vec<_>>();
+        if let Some(missing) = packed.first_mut() {
+            *missing = i16::MIN;
+        }
+
+        {
+            let mut file = netcdf::create(&path).map_err(|err| netcdf_error("", &err))?;
+            for (name, len) in [("time", 2), ("lat", 32), ("lon", 32)] {
+                file.add_dimension(name, len)
+                    .map_err(|err| netcdf_error(name, &err))?;
+            }
+            let mut var = file
+                .add_variable::<i16>("t2m", &["time", "lat", "lon"])
+                .map_err(|err| netcdf_error("t2m", &err))?;
+            var.put_attribute("scale_factor", scale_factor)
+                .map_err(|err| netcdf_error("t2m", &err))?;
+            var.put_attribute("add_offset", add_offset)
+                .map_err(|err| netcdf_error("t2m", &err))?;
+            var.put_attribute("_FillValue", i16::MIN)
+                .map_err(|err| netcdf_error("t2m", &err))?;
+            var.put_values(&packed, ..)
+                .map_err(|err| netcdf_error("t2m", &err))?;
+        }
+
+        let file = netcdf::open(&path).map_err(|err| netcdf_error("", &err))?;
+
+        let chunks = ebcc_variable_chunks(&file, "t2m")?
+            .map(|chunk| chunk.map(|(_, data)| data))
+            .collect::<EBCCResult<Vec<_>>>()?;
+        let unpacked = chunks.iter().flatten().copied().collect::<Vec<_>>();
+        assert_eq!(unpacked.len(), data.len());
+        assert!(unpacked.first().is_some_and(|missing| missing.is_nan()));
+        for (unpacked, value) in unpacked.iter().zip(&data).skip(1) {
+            assert!((unpacked - value).abs() <= scale_factor);
+        }
+
+        // the fill value is a missing value, which EBCC cannot compress
+        assert!(matches!(
+            ebcc_compress_variable(
+                &file,
+                "t2m",
+                &EBCCConfig::max_absolute_error_bounded(0.1),
+                Vec::new()
+            ),
+            Err(EBCCError::NonFinite { .. })
+        ));
+
+        drop(file);
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+}
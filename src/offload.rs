@@ -0,0 +1,226 @@
+//! Asynchronous EBCC compression and decompression on a blocking pool.
+
+use std::{
+    num::NonZeroUsize,
+    panic,
+    sync::{Arc, OnceLock},
+    thread,
+};
+
+use ndarray::{Array, ArrayView};
+use tokio::{sync::Semaphore, task};
+
+use crate::capture::propagate_capture;
+use crate::codec::{ebcc_decode_into, ebcc_encode, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+
+/// Pool that offloads CPU-bound EBCC work from an async runtime.
+///
+/// Encode and decode jobs are run on Tokio's blocking thread pool so that
+/// they do not stall the async executor. At most `max_in_flight` jobs run at
+/// the same time. Further submissions wait asynchronously until a slot
+/// becomes free, which provides backpressure to the submitting tasks.
+///
+/// # Cancellation
+///
+/// All methods are cancellation-safe: dropping a returned future before the
+/// job has started releases its queue slot immediately. Once a job has
+/// started, it runs to completion in the background and its result is
+/// discarded, while its slot stays occupied until the job finishes. Hence,
+/// cancelled jobs still count towards the `max_in_flight` limit for as long as
+/// they use a CPU.
+///
+/// All methods must be called from within a Tokio runtime.
+#[derive(Clone, Debug)]
+pub struct EbccOffloadPool {
+    slots: Arc<Semaphore>,
+}
+
+impl EbccOffloadPool {
+    /// Create a new offload pool that runs at most `max_in_flight` jobs at
+    /// the same time.
+    #[must_use]
+    pub fn new(max_in_flight: NonZeroUsize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(max_in_flight.get())),
+        }
+    }
+
+    /// Encode a 3D data array using EBCC compression on the blocking pool.
+    ///
+    /// See [`ebcc_encode`] for details.
+    ///
+    /// # Errors
+    ///
+    /// - all errors that [`ebcc_encode`] can return
+    /// - [`EBCCError::CompressionError`] if the job was cancelled because the
+    ///   runtime is shutting down
+    pub async fn encode(
+        &self,
+        data: Array<f32, EbccDim>,
+        config: EBCCConfig,
+    ) -> EBCCResult<Vec<u8>> {
+        self.spawn(move || ebcc_encode(data.view(), &config))
+            .await
+            .unwrap_or_else(|()| {
+                Err(EBCCError::CompressionError(
+                    "EBCC encode job was cancelled".into(),
+                ))
+            })
+    }
+
+    /// Decode EBCC compressed data into a new 3D data array of the given
+    /// `shape` on the blocking pool.
+    ///
+    /// See [`ebcc_decode_into`] for details.
+    ///
+    /// # Errors
+    ///
+    /// - all errors that [`ebcc_decode_into`] can return
+    /// - [`EBCCError::DecompressionError`] if the job was cancelled because
+    ///   the runtime is shutting down
+    pub async fn decode(
+        &self,
+        compressed_data: Vec<u8>,
+        shape: (usize, usize, usize),
+    ) -> EBCCResult<Array<f32, EbccDim>> {
+        self.spawn(move || {
+            let mut decompressed_data = Array::zeros(shape);
+            ebcc_decode_into(&compressed_data, decompressed_data.view_mut())?;
+            Ok(decompressed_data)
+        })
+        .await
+        .unwrap_or_else(|()| {
+            Err(EBCCError::DecompressionError(
+                "EBCC decode job was cancelled".into(),
+            ))
+        })
+    }
+
+    async fn spawn<T: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, ()> {
+        // the semaphore is never closed
+        let slot = Arc::clone(&self.slots)
+            .acquire_owned()
+            .await
+            .map_err(|_| ())?;
+
+        let handle = task::spawn_blocking(propagate_capture(move || {
+            // keep the slot occupied until the job has finished, even if the
+            //  awaiting future has been dropped
+            let _slot = slot;
+            job()
+        }));
+
+        match handle.await {
+            Ok(result) => Ok(result),
+            Err(err) => match err.try_into_panic() {
+                Ok(payload) => panic::resume_unwind(payload),
+                Err(_cancelled) => Err(()),
+            },
+        }
+    }
+}
+
+impl Default for EbccOffloadPool {
+    /// Create a new offload pool that runs at most as many jobs in parallel
+    /// as there are available CPUs.
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().unwrap_or(NonZeroUsize::MIN))
+    }
+}
+
+/// Encode a 3D data array using EBCC compression without blocking the async
+/// runtime.
+///
+/// The job is run on a process-wide default [`EbccOffloadPool`]. Use an
+/// explicit [`EbccOffloadPool`] to control the number of concurrent jobs.
+///
+/// This function must be called from within a Tokio runtime.
+///
+/// # Errors
+///
+/// - all errors that [`EbccOffloadPool::encode`] can return
+///
+/// # Examples
+///
+/// ```rust
+/// use ebcc::{ebcc_decode_async, ebcc_encode_async, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// # let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// # runtime.block_on(async {
+/// let data = Array::from_shape_vec((1, 32, 32), vec![1.0f32; 32 * 32]).unwrap();
+///
+/// let compressed = ebcc_encode_async(data.view(), &EBCCConfig::new()).await?;
+/// let decompressed = ebcc_decode_async(compressed, data.dim()).await?;
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+pub async fn ebcc_encode_async(
+    data: ArrayView<'_, f32, EbccDim>,
+    config: &EBCCConfig,
+) -> EBCCResult<Vec<u8>> {
+    default_pool().encode(data.to_owned(), config.clone()).await
+}
+
+/// Decode EBCC compressed data into a new 3D data array of the given `shape`
+/// without blocking the async runtime.
+///
+/// The job is run on a process-wide default [`EbccOffloadPool`]. Use an
+/// explicit [`EbccOffloadPool`] to control the number of concurrent jobs.
+///
+/// This function must be called from within a Tokio runtime.
+///
+/// # Errors
+///
+/// - all errors that [`EbccOffloadPool::decode`] can return
+pub async fn ebcc_decode_async(
+    compressed_data: Vec<u8>,
+    shape: (usize, usize, usize),
+) -> EBCCResult<Array<f32, EbccDim>> {
+    default_pool().decode(compressed_data, shape).await
+}
+
+fn default_pool() -> &'static EbccOffloadPool {
+    static DEFAULT_POOL: OnceLock<EbccOffloadPool> = OnceLock::new();
+
+    DEFAULT_POOL.get_or_init(EbccOffloadPool::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::runtime::Builder;
+
+    use super::*;
+    use crate::testdata;
+
+    #[test]
+    fn test_offload_roundtrip() -> EBCCResult<()> {
+        let runtime = Builder::new_current_thread().build()?;
+
+        runtime.block_on(async {
+            let pool = EbccOffloadPool::new(NonZeroUsize::MIN);
+            let data = testdata::temperature((2, 32, 32));
+            let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+            let a = task::spawn({
+                let (pool, data, config) = (pool.clone(), data.clone(), config.clone());
+                async move { pool.encode(data, config).await }
+            });
+            let b = pool.encode(data.clone(), config.clone()).await?;
+            assert_eq!(a.await.map_err(std::io::Error::other)??, b);
+
+            let compressed = ebcc_encode_async(data.view(), &config).await?;
+            let decompressed = pool.decode(compressed, data.dim()).await?;
+            assert_eq!(decompressed.dim(), data.dim());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,75 @@
+//! Checks of the parameters that are passed to the EBCC C library, which are
+//! shared by [`EBCCConfig::validate`][crate::EBCCConfig::validate] and the
+//! [`raw`][crate::raw] API.
+
+use alloc::{format, string::String};
+
+use ebcc_sys::{EBCC_MAX_INTERNAL_IMAGE_DIM, EBCC_MIN_INTERNAL_IMAGE_DIM, EBCC_NDIMS};
+
+use crate::error::{EBCCError, EBCCResult};
+use crate::size::data_len;
+
+/// Check that the JPEG2000 base compression ratio is positive.
+pub fn validate_base_cr(base_cr: f32) -> EBCCResult<()> {
+    if base_cr <= 0.0 {
+        return Err(EBCCError::NonPositiveBaseCR { base_cr });
+    }
+
+    Ok(())
+}
+
+/// Check that the error bound of the residual layer is positive.
+pub fn validate_error_bound(error: f32) -> EBCCResult<()> {
+    if error <= 0.0 {
+        return Err(EBCCError::NonPositiveErrorBound { error });
+    }
+
+    Ok(())
+}
+
+/// Check that EBCC supports data of the `(depth, height, width)` shape, whose
+/// internal image must be within the supported dimensions.
+pub fn validate_regular_ebcc_shape(
+    (depth, height, width): (usize, usize, usize),
+) -> EBCCResult<()> {
+    // EBCC flattens all dimensions except the last into one internal image height.
+    let Some(image_height) = depth.checked_mul(height) else {
+        return Err(EBCCError::InvalidInput(String::from("Dimension overflow")));
+    };
+
+    if height < EBCC_MIN_INTERNAL_IMAGE_DIM
+        || !(EBCC_MIN_INTERNAL_IMAGE_DIM..=EBCC_MAX_INTERNAL_IMAGE_DIM).contains(&image_height)
+        || !(EBCC_MIN_INTERNAL_IMAGE_DIM..=EBCC_MAX_INTERNAL_IMAGE_DIM).contains(&width)
+    {
+        return Err(EBCCError::UnsupportedShape {
+            depth,
+            height,
+            width,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that EBCC supports chunk tiles of the `chunk_shape`, whose internal
+/// images must be within the supported dimensions.
+pub fn validate_ebcc_chunk_shape(chunk_shape: [usize; EBCC_NDIMS]) -> EBCCResult<()> {
+    let [chunk_depth, chunk_height, chunk_width] = chunk_shape;
+    let Some(image_height) = chunk_depth.checked_mul(chunk_height) else {
+        return Err(EBCCError::InvalidInput(String::from(
+            "Chunk dimension overflow",
+        )));
+    };
+    if chunk_height < EBCC_MIN_INTERNAL_IMAGE_DIM
+        || !(EBCC_MIN_INTERNAL_IMAGE_DIM..=EBCC_MAX_INTERNAL_IMAGE_DIM).contains(&image_height)
+        || !(EBCC_MIN_INTERNAL_IMAGE_DIM..=EBCC_MAX_INTERNAL_IMAGE_DIM).contains(&chunk_width)
+    {
+        return Err(EBCCError::InvalidInput(format!(
+            "EBCC requires chunk tile dimensions of at least {EBCC_MIN_INTERNAL_IMAGE_DIM} and internal image dimensions at most {EBCC_MAX_INTERNAL_IMAGE_DIM}, got {chunk_depth}x{chunk_height}x{chunk_width}",
+        )));
+    }
+
+    data_len((chunk_depth, chunk_height, chunk_width))?;
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+//! Postprocessing that snaps the decoded values to a fixed quantization grid.
+//!
+//! Some archives require that the decoded values lie on a fixed grid, e.g.
+//! multiples of `0.01 K`, such that they are reproducible independent of the
+//! codec. The grid step is recorded in the payload, see
+//! [`EBCCConfig::with_output_quantization`], and every decoded value is
+//! rounded to the nearest multiple of the step. The absolute or relative
+//! error bound is tightened by half a step during encoding, such that it
+//! still holds after snapping.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_QUANTIZE_MAGIC`], the format version as `u32`, the grid
+//!   step as `f32`, and the number of frames, the frame height, and the frame
+//!   width as `u64`s
+//! - the inner EBCC payload
+
+use ndarray::ArrayView;
+
+use crate::codec::{ebcc_decode_nested, ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::verify::data_range;
+
+/// Magic bytes at the start of every grid-quantized EBCC payload.
+pub const EBCC_QUANTIZE_MAGIC: &[u8; 8] = b"EBCCQGRD";
+
+/// Version of the grid-quantized EBCC payload format.
+const EBCC_QUANTIZE_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC output quantization data");
+
+/// Length of the grid-quantized payload header
+pub const EBCC_QUANTIZE_HEADER_LEN: usize = 8 + 4 + 4 + 3 * 8;
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_QUANTIZE_MAGIC`].
+pub fn is_quantized(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_QUANTIZE_MAGIC)
+}
+
+/// Encode a 3D data array into a grid-quantized payload with the
+/// [`EBCCConfig::output_quantization`] of the `config`, which must be set.
+pub fn quantize_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let Some(step) = config.output_quantization else {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "Output quantization requires a grid step",
+        )));
+    };
+
+    let error = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => Some(error),
+        EBCCResidualType::RelativeError(error) => Some(data_range(data) * error),
+        EBCCResidualType::Jpeg2000Only => None,
+    };
+    let residual_compression_type = match error {
+        // snapping moves every value by at most half a step, and the values
+        //  are rounded to f32 by at most half an ulp of the largest value
+        Some(error) => {
+            let max_abs = data.iter().fold(0.0_f32, |max, x| max.max(x.abs()));
+            let error = (max_abs * f32::EPSILON).mul_add(-2.0, step.mul_add(-0.5, error));
+            if error <= 0.0 {
+                return Err(quantization_step_too_large(step));
+            }
+            EBCCResidualType::AbsoluteError(error)
+        }
+        None => EBCCResidualType::Jpeg2000Only,
+    };
+
+    let inner_config = EBCCConfig {
+        residual_compression_type,
+        output_quantization: None,
+        ..config.clone()
+    };
+    let inner = ebcc_encode_c_buffer_with_scratch(data, &inner_config, scratch)?;
+    let inner = inner.as_slice();
+
+    let mut compressed_data = Vec::with_capacity(EBCC_QUANTIZE_HEADER_LEN + inner.len());
+    compressed_data.extend_from_slice(EBCC_QUANTIZE_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_QUANTIZE_VERSION.to_le_bytes());
+    compressed_data.extend_from_slice(&step.to_le_bytes());
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(inner);
+
+    Ok(compressed_data)
+}
+
+/// Decode a grid-quantized payload of the expected `shape`, which may be
+/// modified during decoding, into the flattened 3D data array.
+pub fn quantize_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_QUANTIZE_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_QUANTIZE_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC output quantization version: {version}").into(),
+        ));
+    }
+
+    let step = f32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if !(step.is_finite() && step > 0.0) {
+        return Err(FORMAT.corrupted());
+    }
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+    data_len(expected_shape.into())?;
+
+    let Some(inner) = compressed_data.get_mut(EBCC_QUANTIZE_HEADER_LEN..) else {
+        return Err(FORMAT.truncated());
+    };
+
+    Ok(ebcc_decode_nested(inner, expected_shape.into(), depth + 1)?
+        .as_slice()
+        .iter()
+        .map(|&x| snap(x, step))
+        .collect())
+}
+
+/// Round `x` to the nearest multiple of the grid `step`.
+#[expect(clippy::cast_possible_truncation)]
+fn snap(x: f32, step: f32) -> f32 {
+    let step = f64::from(step);
+    ((f64::from(x) / step).round() * step) as f32
+}
+
+/// Error that the output quantization `step` is too large for the error
+/// bound.
+pub fn quantization_step_too_large(step: f32) -> EBCCError {
+    EBCCError::InvalidConfig(format!(
+        "Output quantization step {step} must be smaller than twice the error bound"
+    ))
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_output_quantization() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+
+        for config in [
+            EBCCConfig::max_absolute_error_bounded(0.1),
+            EBCCConfig::relative_error_bounded(0.01),
+        ] {
+            let config = config.with_output_quantization(0.01);
+
+            let compressed = ebcc_encode(data.view(), &config)?;
+            let mut decompressed = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+            check_error_bound(data.view(), decompressed.view(), &config)?;
+            assert!(decompressed
+                .iter()
+                .all(|&x| x.to_bits() == snap(x, 0.01).to_bits()));
+        }
+
+        for step in [0.0, -1.0, f32::NAN, 0.2] {
+            assert!(matches!(
+                ebcc_encode(
+                    data.view(),
+                    &EBCCConfig::max_absolute_error_bounded(0.1).with_output_quantization(step)
+                ),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+
+        Ok(())
+    }
+}
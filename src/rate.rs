@@ -0,0 +1,195 @@
+//! Rate control that targets a total compression ratio.
+
+use ndarray::ArrayView;
+
+use crate::auto::sample_frames;
+use crate::codec::{ebcc_encode, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::finite::validate_only_finite_data;
+use crate::params::validate_regular_ebcc_shape;
+
+/// Base compression ratios at which the rate curve of the sample is estimated
+const RATE_BASE_CRS: [f64; 10] = [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+
+/// Maximum number of encodings of the full data
+const RATE_REFINE_STEPS: usize = 4;
+
+/// Encode a 3D data array with EBCC, such that its total compression ratio
+/// is closest to the `target_ratio`.
+///
+/// Only the base compression ratio is searched, while the absolute
+/// `error_floor` bound holds for every value. The search runs in two passes.
+/// First, the rate curve, i.e. the total compression ratio as a function of
+/// the base compression ratio, is estimated on up to four evenly spaced
+/// frames of the `data`, and interpolated in log space to estimate the base
+/// compression ratio that reaches the `target_ratio`. Second, the full
+/// `data` is encoded with the estimate and refined by bisection with at most
+/// four encodings, and the encoding whose total compression ratio is closest
+/// to the `target_ratio` is returned.
+///
+/// Since the `error_floor` always holds, targets that require a larger error
+/// cannot be reached, and the encoding with the closest ratio is returned
+/// instead. The returned data is an [`ebcc_encode`] payload.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidConfig`] if the `target_ratio` is not finite and at
+///   least one, or the `error_floor` is not finite and positive
+/// - [`EBCCError::EmptyDimension`], [`EBCCError::UnsupportedShape`], and
+///   [`EBCCError::NonFinite`] if the `data` cannot be encoded
+/// - all errors that [`ebcc_encode`] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_into, ebcc_encode_with_ratio};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((2, 32, 32), |(t, y, x)| (t + y + x) as f32);
+/// let compressed = ebcc_encode_with_ratio(data.view(), 10.0, 0.5)?;
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_into(&compressed, decompressed.view_mut())?;
+/// assert!(data.iter().zip(&decompressed).all(|(a, b)| (a - b).abs() <= 0.5));
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_encode_with_ratio(
+    data: ArrayView<f32, EbccDim>,
+    target_ratio: f32,
+    error_floor: f32,
+) -> EBCCResult<Vec<u8>> {
+    if !(target_ratio.is_finite() && target_ratio >= 1.0) {
+        return Err(EBCCError::InvalidConfig(format!(
+            "Target compression ratio must be finite and at least 1, got {target_ratio}"
+        )));
+    }
+    if !(error_floor.is_finite() && error_floor > 0.0) {
+        return Err(EBCCError::InvalidConfig(format!(
+            "Error floor must be finite and positive, got {error_floor}"
+        )));
+    }
+
+    if data.is_empty() {
+        return Err(EBCCError::EmptyDimension);
+    }
+    validate_regular_ebcc_shape(data.dim())?;
+    validate_only_finite_data(&data)?;
+    // the data has already been checked for non-finite values
+    let config = EBCCConfig::max_absolute_error_bounded(error_floor).skip_finite_check();
+
+    #[expect(clippy::cast_precision_loss)]
+    let encode = |data: ArrayView<f32, EbccDim>, ln_base_cr: f64| {
+        #[expect(clippy::cast_possible_truncation)]
+        let compressed = ebcc_encode(data, &config.clone().with_base_cr(ln_base_cr.exp() as f32))?;
+        let ln_ratio = ((data.len() * std::mem::size_of::<f32>()) as f64).ln()
+            - (compressed.len() as f64).ln();
+        EBCCResult::Ok((compressed, ln_ratio))
+    };
+    let target = f64::from(target_ratio).ln();
+
+    // first pass: estimate the rate curve on a sample of the data
+    let sample = sample_frames(data);
+    let curve = RATE_BASE_CRS
+        .iter()
+        .map(|base_cr| Ok((base_cr.ln(), encode(sample.view(), base_cr.ln())?.1)))
+        .collect::<EBCCResult<Vec<_>>>()?;
+
+    let reached = curve.iter().position(|&(_, ln_ratio)| ln_ratio >= target);
+    let (lower, upper) = reached.map_or_else(
+        || (curve.last(), None),
+        |i| (i.checked_sub(1).and_then(|i| curve.get(i)), curve.get(i)),
+    );
+    let (mut lo, mut hi, mut mid) = match (lower, upper) {
+        (Some(&(lo, lo_ratio)), Some(&(hi, hi_ratio))) => {
+            let t = if hi_ratio > lo_ratio {
+                ((target - lo_ratio) / (hi_ratio - lo_ratio)).clamp(0.0, 1.0)
+            } else {
+                0.5
+            };
+            (lo, hi, t.mul_add(hi - lo, lo))
+        }
+        (Some(&(x, _)), None) | (None, Some(&(x, _))) => (x, x, x),
+        (None, None) => {
+            return Err(EBCCError::CompressionError(
+                "No rate curve was estimated".into(),
+            ))
+        }
+    };
+
+    // second pass: refine the estimate on the full data
+    let mut best: Option<(f64, Vec<u8>)> = None;
+    for _ in 0..RATE_REFINE_STEPS {
+        let (compressed, ln_ratio) = encode(data, mid)?;
+        let distance = (ln_ratio - target).abs();
+        if best
+            .as_ref()
+            .is_none_or(|(best_distance, _)| distance < *best_distance)
+        {
+            best = Some((distance, compressed));
+        }
+
+        if ln_ratio < target {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+        if (hi - lo).abs() < f64::EPSILON {
+            break;
+        }
+        mid = (lo + hi) / 2.0;
+    }
+
+    best.map(|(_, compressed)| compressed)
+        .ok_or_else(|| EBCCError::CompressionError("No encoding was produced".into()))
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_encode_with_ratio() -> EBCCResult<()> {
+        let data = testdata::temperature((6, 32, 48));
+
+        for target_ratio in [2.0, 10.0, 1000.0] {
+            let compressed = ebcc_encode_with_ratio(data.view(), target_ratio, 0.5)?;
+
+            let mut decompressed = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, decompressed.view_mut())?;
+            check_error_bound(
+                data.view(),
+                decompressed.view(),
+                &EBCCConfig::max_absolute_error_bounded(0.5),
+            )?;
+        }
+
+        for (target_ratio, error_floor) in [
+            (0.5, 0.5),
+            (0.0, 0.5),
+            (-10.0, 0.5),
+            (f32::NAN, 0.5),
+            (f32::INFINITY, 0.5),
+            (10.0, 0.0),
+            (10.0, -0.5),
+            (10.0, f32::NAN),
+            (10.0, f32::INFINITY),
+        ] {
+            assert!(matches!(
+                ebcc_encode_with_ratio(data.view(), target_ratio, error_floor),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+
+        Ok(())
+    }
+}
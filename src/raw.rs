@@ -0,0 +1,506 @@
+//! Low-level safe wrappers that mirror the EBCC C API.
+//!
+//! The functions in this module call the EBCC C library directly and return
+//! its exact output, without any of the Rust-side headers, checksums, or
+//! validation of the high-level API. They are meant for users who need the
+//! reference behaviour of EBCC, e.g. to interoperate with other EBCC
+//! bindings, while still avoiding hand-written unsafe code.
+//!
+//! The configuration is checked like an `EBCCConfig` before it is passed to
+//! EBCC unchanged:
+//! the length of the data must match the [`RawConfig::dims`], which must be
+//! supported by EBCC, the [`RawConfig::base_cr`] and, unless there is no
+//! residual compression, the [`RawConfig::error`] must be positive, and the
+//! [`RawConfig::chunk_dims`] of chunked encoding must be supported as well.
+//!
+//! Calls into EBCC can run in parallel, like in the rest of this crate.
+//!
+//! This module does not depend on `ndarray` and is also available without
+//! the `std` and `ndarray` features, with the encode functions requiring the
+//! `encode` feature and the decode functions requiring the `decode` feature.
+//! It is thus the encoder of builds that do not want the `ndarray`
+//! dependency.
+
+use alloc::format;
+use core::ptr;
+
+use ebcc_sys::EBCC_NDIMS;
+#[cfg(feature = "encode")]
+use ebcc_sys::{codec_config_t, residual_t};
+
+pub use crate::cbuffer::CBuffer;
+#[cfg(all(feature = "std", feature = "ndarray"))]
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+#[cfg(feature = "encode")]
+use crate::params::{
+    validate_base_cr, validate_ebcc_chunk_shape, validate_error_bound, validate_regular_ebcc_shape,
+};
+use crate::sync::{c_call_failure, with_ebcc_call};
+
+/// Residual compression type of the EBCC C API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawResidualType {
+    /// No residual compression - base JPEG2000 only
+    None,
+    /// Sparsification of the residual
+    SparsificationFactor,
+    /// Absolute maximum error bound
+    MaxError,
+    /// Range-relative error bound
+    RelativeError,
+    /// Quantile-based error bound
+    Quantile,
+}
+
+impl RawResidualType {
+    #[cfg(feature = "encode")]
+    const fn as_residual(self) -> residual_t::Type {
+        match self {
+            Self::None => residual_t::NONE,
+            Self::SparsificationFactor => residual_t::SPARSIFICATION_FACTOR,
+            Self::MaxError => residual_t::MAX_ERROR,
+            Self::RelativeError => residual_t::RELATIVE_ERROR,
+            Self::Quantile => residual_t::QUANTILE,
+        }
+    }
+}
+
+/// Configuration of the EBCC C API, which mirrors its `codec_config_t`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawConfig {
+    /// Shape of the 3D data array
+    pub dims: [usize; EBCC_NDIMS],
+    /// Compression ratio of the JPEG2000 base layer
+    pub base_cr: f32,
+    /// Residual compression type
+    pub residual_compression_type: RawResidualType,
+    /// Compression ratio of the residual layer, which is unused by EBCC
+    pub residual_cr: f32,
+    /// Error bound of the residual layer
+    pub error: f32,
+    /// Shape of the chunks for chunked encoding
+    pub chunk_dims: [usize; EBCC_NDIMS],
+}
+
+impl RawConfig {
+    /// Create the raw configuration for data of the given `dims` that
+    /// corresponds to the high-level `config`.
+    ///
+    /// The [`chunk_dims`][Self::chunk_dims] are zero and must be set with
+    /// [`with_chunk_dims`][Self::with_chunk_dims] before chunked encoding.
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    #[must_use]
+    pub const fn new(dims: [usize; EBCC_NDIMS], config: &EBCCConfig) -> Self {
+        let residual_compression_type = match config.residual_compression_type {
+            EBCCResidualType::Jpeg2000Only => RawResidualType::None,
+            EBCCResidualType::AbsoluteError(_) => RawResidualType::MaxError,
+            EBCCResidualType::RelativeError(_) => RawResidualType::RelativeError,
+        };
+
+        Self {
+            dims,
+            base_cr: config.base_cr,
+            residual_compression_type,
+            residual_cr: 1.0,
+            error: config.residual_compression_type.as_error(),
+            chunk_dims: [0; EBCC_NDIMS],
+        }
+    }
+
+    /// Set the shape of the chunks for chunked encoding.
+    #[must_use]
+    pub const fn with_chunk_dims(mut self, chunk_dims: [usize; EBCC_NDIMS]) -> Self {
+        self.chunk_dims = chunk_dims;
+        self
+    }
+
+    #[cfg(feature = "encode")]
+    const fn as_ffi(&self) -> codec_config_t {
+        codec_config_t {
+            dims: self.dims,
+            base_cr: self.base_cr,
+            residual_compression_type: self.residual_compression_type.as_residual(),
+            residual_cr: self.residual_cr,
+            error: self.error,
+            chunk_dims: self.chunk_dims,
+        }
+    }
+}
+
+/// Encode the `data` with EBCC's `ebcc_encode`.
+///
+/// EBCC may modify the `data` during encoding.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyDimension`] if any of the `config.dims` is zero
+/// - [`EBCCError::SizeMismatch`] if the length of the `data` does not match
+///   the `config.dims`
+/// - [`EBCCError::UnsupportedShape`] if EBCC does not support the
+///   `config.dims`
+/// - [`EBCCError::NonPositiveBaseCR`] or [`EBCCError::NonPositiveErrorBound`]
+///   if the `config.base_cr` or the `config.error` is not positive
+/// - [`EBCCError::CompressionError`] if compression with EBCC fails
+#[cfg(feature = "encode")]
+pub fn encode(data: &mut [f32], config: &RawConfig) -> EBCCResult<CBuffer<u8>> {
+    validate_data(data, config)?;
+
+    let mut ffi_config = config.as_ffi();
+    let mut out_buffer: *mut u8 = ptr::null_mut();
+    #[expect(unsafe_code)]
+    // Safety: data has the length of the config dims
+    let compressed_size = with_ebcc_call(|| unsafe {
+        ebcc_sys::ebcc_encode(data.as_mut_ptr(), &raw mut ffi_config, &raw mut out_buffer)
+    });
+
+    compressed_buffer("ebcc_encode", out_buffer, compressed_size)
+}
+
+/// Encode the `data` with EBCC's `ebcc_encode_chunking`.
+///
+/// EBCC may modify the `data` during encoding.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyDimension`] if any of the `config.dims` or
+///   `config.chunk_dims` is zero
+/// - [`EBCCError::SizeMismatch`] if the length of the `data` does not match
+///   the `config.dims`
+/// - [`EBCCError::UnsupportedShape`] if EBCC does not support the
+///   `config.dims`
+/// - [`EBCCError::InvalidInput`] if EBCC does not support the
+///   `config.chunk_dims`
+/// - [`EBCCError::NonPositiveBaseCR`] or [`EBCCError::NonPositiveErrorBound`]
+///   if the `config.base_cr` or the `config.error` is not positive
+/// - [`EBCCError::CompressionError`] if compression with EBCC fails
+#[cfg(feature = "encode")]
+pub fn encode_chunking(data: &mut [f32], config: &RawConfig) -> EBCCResult<CBuffer<u8>> {
+    validate_data(data, config)?;
+    validate_chunk_dims(config)?;
+
+    let mut ffi_config = config.as_ffi();
+    let mut out_buffer: *mut u8 = ptr::null_mut();
+    #[expect(unsafe_code)]
+    // Safety: data has the length of the config dims, chunk dims are non-zero
+    let compressed_size = with_ebcc_call(|| unsafe {
+        ebcc_sys::ebcc_encode_chunking(data.as_mut_ptr(), &raw mut ffi_config, &raw mut out_buffer)
+    });
+
+    compressed_buffer("ebcc_encode_chunking", out_buffer, compressed_size)
+}
+
+/// Encode the `data` with EBCC's `ebcc_encode_chunking_compat`.
+///
+/// Zero `config.chunk_dims` let EBCC choose the chunk shape automatically.
+/// EBCC may modify the `data` during encoding.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyDimension`] if any of the `config.dims` is zero
+/// - [`EBCCError::SizeMismatch`] if the length of the `data` does not match
+///   the `config.dims`
+/// - [`EBCCError::UnsupportedShape`] if EBCC does not support the
+///   `config.dims`
+/// - [`EBCCError::InvalidInput`] if EBCC does not support the non-zero
+///   `config.chunk_dims`
+/// - [`EBCCError::NonPositiveBaseCR`] or [`EBCCError::NonPositiveErrorBound`]
+///   if the `config.base_cr` or the `config.error` is not positive
+/// - [`EBCCError::CompressionError`] if compression with EBCC fails
+#[cfg(feature = "encode")]
+pub fn encode_chunking_compat(data: &mut [f32], config: &RawConfig) -> EBCCResult<CBuffer<u8>> {
+    validate_data(data, config)?;
+    // zero chunk dims let EBCC choose the chunk shape
+    if config.chunk_dims != [0; EBCC_NDIMS] {
+        validate_chunk_dims(config)?;
+    }
+
+    let mut ffi_config = config.as_ffi();
+    let mut out_buffer: *mut u8 = ptr::null_mut();
+    #[expect(unsafe_code)]
+    // Safety: data has the length of the config dims
+    let compressed_size = with_ebcc_call(|| unsafe {
+        ebcc_sys::ebcc_encode_chunking_compat(
+            data.as_mut_ptr(),
+            &raw mut ffi_config,
+            &raw mut out_buffer,
+        )
+    });
+
+    compressed_buffer("ebcc_encode_chunking_compat", out_buffer, compressed_size)
+}
+
+/// Decode the output of [`encode`] with EBCC's `ebcc_decode`.
+///
+/// EBCC may modify the `compressed_data` during decoding.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::DecompressionError`] if decompression with EBCC fails
+#[cfg(feature = "decode")]
+pub fn decode(compressed_data: &mut [u8]) -> EBCCResult<CBuffer<f32>> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    let mut out_buffer: *mut f32 = ptr::null_mut();
+    #[expect(unsafe_code)]
+    // Safety: compressed_data is valid for its length
+    let decompressed_size = with_ebcc_call(|| unsafe {
+        ebcc_sys::ebcc_decode(
+            compressed_data.as_mut_ptr(),
+            compressed_data.len(),
+            &raw mut out_buffer,
+        )
+    });
+
+    decompressed_buffer("ebcc_decode", out_buffer, decompressed_size)
+}
+
+/// Decode the output of [`encode_chunking`] or [`encode_chunking_compat`]
+/// with EBCC's `ebcc_decode_chunking`.
+///
+/// EBCC may modify the `compressed_data` during decoding.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyInput`] if the `compressed_data` is empty
+/// - [`EBCCError::DecompressionError`] if decompression with EBCC fails
+#[cfg(feature = "decode")]
+pub fn decode_chunking(compressed_data: &mut [u8]) -> EBCCResult<CBuffer<f32>> {
+    if compressed_data.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    let mut out_buffer: *mut f32 = ptr::null_mut();
+    #[expect(unsafe_code)]
+    // Safety: compressed_data is valid for its length
+    let decompressed_size = with_ebcc_call(|| unsafe {
+        ebcc_sys::ebcc_decode_chunking(
+            compressed_data.as_mut_ptr(),
+            compressed_data.len(),
+            &raw mut out_buffer,
+        )
+    });
+
+    decompressed_buffer("ebcc_decode_chunking", out_buffer, decompressed_size)
+}
+
+#[cfg(feature = "encode")]
+fn validate_data(data: &[f32], config: &RawConfig) -> EBCCResult<()> {
+    if config.dims.contains(&0) {
+        return Err(EBCCError::EmptyDimension);
+    }
+
+    let expected = config
+        .dims
+        .iter()
+        .try_fold(1_usize, |len, &dim| len.checked_mul(dim));
+    if expected != Some(data.len()) {
+        return Err(EBCCError::SizeMismatch {
+            expected: config.dims,
+            actual: data.len(),
+        });
+    }
+
+    validate_regular_ebcc_shape(config.dims.into())?;
+
+    validate_base_cr(config.base_cr)?;
+    if config.residual_compression_type != RawResidualType::None {
+        validate_error_bound(config.error)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "encode")]
+fn validate_chunk_dims(config: &RawConfig) -> EBCCResult<()> {
+    if config.chunk_dims.contains(&0) {
+        return Err(EBCCError::EmptyDimension);
+    }
+
+    validate_ebcc_chunk_shape(config.chunk_dims)
+}
+
+#[cfg(feature = "encode")]
+fn compressed_buffer(
+    function: &str,
+    out_buffer: *mut u8,
+    compressed_size: usize,
+) -> EBCCResult<CBuffer<u8>> {
+    #[expect(unsafe_code)]
+    // Safety: out_buffer was allocated by EBCC and has compressed_size elements
+    (unsafe { CBuffer::new(out_buffer, compressed_size) }).ok_or_else(|| {
+        EBCCError::CompressionError(c_call_failure(
+            format!("EBCC {function} returned no output"),
+            out_buffer.is_null(),
+        ))
+    })
+}
+
+#[cfg(feature = "decode")]
+fn decompressed_buffer(
+    function: &str,
+    out_buffer: *mut f32,
+    decompressed_size: usize,
+) -> EBCCResult<CBuffer<f32>> {
+    #[expect(unsafe_code)]
+    // Safety: out_buffer was allocated by EBCC and has decompressed_size elements
+    (unsafe { CBuffer::new(out_buffer, decompressed_size) }).ok_or_else(|| {
+        EBCCError::DecompressionError(c_call_failure(
+            format!("EBCC {function} returned no output"),
+            out_buffer.is_null(),
+        ))
+    })
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use alloc::vec;
+
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    use ndarray::Array;
+
+    use super::*;
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    use crate::codec::ebcc_encode;
+
+    #[test]
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_raw_roundtrip() -> EBCCResult<()> {
+        let shape = (2, 32, 48);
+        let data = Array::from_shape_fn(shape, |(t, y, x)| (t + y + x) as f32 / 10.0);
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let raw_config = RawConfig::new(shape.into(), &config);
+
+        let mut input = data.iter().copied().collect::<Vec<_>>();
+        let compressed = encode(&mut input, &raw_config)?;
+
+        // the high-level API wraps the exact same payload in a header
+        assert!(ebcc_encode(data.view(), &config)?.ends_with(compressed.as_slice()));
+
+        let decompressed = decode(&mut compressed.as_slice().to_vec())?;
+        assert_eq!(decompressed.as_slice().len(), data.len());
+        assert!(data
+            .iter()
+            .zip(decompressed.as_slice())
+            .all(|(x, y)| (x - y).abs() <= 0.1 + 1e-6));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_slice_roundtrip() -> EBCCResult<()> {
+        let data = vec![1.0_f32; 32 * 32];
+        let config = RawConfig {
+            dims: [1, 32, 32],
+            base_cr: 10.0,
+            residual_compression_type: RawResidualType::MaxError,
+            residual_cr: 1.0,
+            error: 0.1,
+            chunk_dims: [0; EBCC_NDIMS],
+        };
+
+        // raw payloads are decoded without ndarray, like headerless payloads
+        let compressed = encode(&mut data.clone(), &config)?;
+        let mut decompressed = vec![0.0; data.len()];
+        crate::ebcc_decode_into_slice(compressed.as_slice(), &mut decompressed, (1, 32, 32))?;
+        assert!(data
+            .iter()
+            .zip(&decompressed)
+            .all(|(x, y)| (x - y).abs() <= 0.1 + 1e-6));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    fn test_raw_rejects_unsafe_inputs() {
+        let config = RawConfig::new([1, 32, 32], &EBCCConfig::new());
+
+        assert!(matches!(
+            encode(&mut [0.0; 32], &config),
+            Err(EBCCError::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            encode(
+                &mut [],
+                &RawConfig {
+                    dims: [0, 32, 32],
+                    ..config
+                }
+            ),
+            Err(EBCCError::EmptyDimension)
+        ));
+        assert!(matches!(
+            encode_chunking(&mut [0.0; 32 * 32], &config),
+            Err(EBCCError::EmptyDimension)
+        ));
+        assert!(matches!(decode(&mut []), Err(EBCCError::EmptyInput)));
+    }
+
+    #[test]
+    fn test_raw_rejects_invalid_configs() {
+        let config = RawConfig {
+            dims: [1, 32, 32],
+            base_cr: 10.0,
+            residual_compression_type: RawResidualType::MaxError,
+            residual_cr: 1.0,
+            error: 0.1,
+            chunk_dims: [1, 32, 32],
+        };
+
+        assert!(matches!(
+            encode(
+                &mut [0.0; 32 * 32],
+                &RawConfig {
+                    base_cr: 0.0,
+                    ..config
+                }
+            ),
+            Err(EBCCError::NonPositiveBaseCR { .. })
+        ));
+        assert!(matches!(
+            encode(
+                &mut [0.0; 32 * 32],
+                &RawConfig {
+                    error: -1.0,
+                    ..config
+                }
+            ),
+            Err(EBCCError::NonPositiveErrorBound { .. })
+        ));
+        assert!(matches!(
+            encode(
+                &mut [0.0; 32 * 4],
+                &RawConfig {
+                    dims: [1, 4, 32],
+                    ..config
+                }
+            ),
+            Err(EBCCError::UnsupportedShape { .. })
+        ));
+        assert!(matches!(
+            encode_chunking(
+                &mut [0.0; 32 * 32],
+                &RawConfig {
+                    chunk_dims: [1, 4, 32],
+                    ..config
+                }
+            ),
+            Err(EBCCError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            encode_chunking_compat(
+                &mut [0.0; 32 * 32],
+                &RawConfig {
+                    chunk_dims: [1, 32, 4],
+                    ..config
+                }
+            ),
+            Err(EBCCError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+//! Shared readers for the binary EBCC formats.
+
+use alloc::format;
+
+use crate::error::{EBCCError, EBCCResult};
+
+/// Binary EBCC format, named e.g. `"EBCC layered data"`, whose readers
+/// describe truncated or corrupted input with the name of the format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format(pub &'static str);
+
+impl Format {
+    /// The error for input that ends before the format is complete.
+    #[must_use]
+    pub fn truncated(self) -> EBCCError {
+        EBCCError::InvalidInput(format!("{} is truncated", self.0))
+    }
+
+    /// The error for input that does not follow the format.
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    #[must_use]
+    pub fn corrupted(self) -> EBCCError {
+        EBCCError::InvalidInput(format!("{} is corrupted", self.0))
+    }
+
+    /// Read `N` bytes from the front of the `reader` and advance it.
+    pub fn read_array<const N: usize>(self, reader: &mut &[u8]) -> EBCCResult<[u8; N]> {
+        let Some((bytes, rest)) = reader.split_first_chunk() else {
+            return Err(self.truncated());
+        };
+
+        *reader = rest;
+
+        Ok(*bytes)
+    }
+
+    /// Read a LEB128 varint from the front of the `reader` and advance it.
+    #[cfg(all(feature = "std", feature = "ndarray"))]
+    pub fn read_varint(self, reader: &mut &[u8]) -> EBCCResult<u64> {
+        let mut value = 0_u64;
+
+        for shift in (0..64).step_by(7) {
+            let Some((byte, rest)) = reader.split_first() else {
+                return Err(self.truncated());
+            };
+            *reader = rest;
+
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(self.corrupted())
+    }
+}
@@ -0,0 +1,205 @@
+//! Reductions that are computed while decoding.
+
+use ndarray::{Array, ArrayView, Axis, Ix2, Slice, Zip};
+
+use crate::codec::{ebcc_decode_visit, EbccDim};
+use crate::error::{EBCCError, EBCCResult};
+
+/// Reduction that is computed by [`ebcc_decode_reduce`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Reduction {
+    /// Arithmetic mean, accumulated in `f64`
+    Mean,
+    /// Minimum value
+    Min,
+    /// Maximum value
+    Max,
+}
+
+/// Decode EBCC compressed data of the given `shape` and reduce it along the
+/// `axis`, without materializing the full decoded 3D data array.
+///
+/// The reduction is computed while the data is decoded. For EBCC frame
+/// streams produced by an [`EbccStreamEncoder`][crate::EbccStreamEncoder],
+/// only one segment of decoded frames is held in memory at a time, so that
+/// e.g. a monthly mean can be computed from an hourly archive at the memory
+/// cost of a single segment. A single [`ebcc_encode`][crate::ebcc_encode]
+/// payload is decoded at once, but is never copied into a separate array.
+///
+/// Reducing along `Axis(0)` returns an array of shape `(height, width)`,
+/// along `Axis(1)` an array of shape `(frames, width)`, and along `Axis(2)`
+/// an array of shape `(frames, height)`.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the `axis` is not one of the three axes
+/// - [`EBCCError::InvalidInput`] if the reduced `axis` has length zero
+/// - all errors that [`ebcc_decode_into`][crate::ebcc_decode_into] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_reduce, ebcc_encode, EBCCConfig, Reduction};
+/// use ndarray::{Array, Axis};
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((4, 32, 32), |(t, _, _)| t as f32);
+/// let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.01))?;
+///
+/// let mean = ebcc_decode_reduce(&compressed, data.dim(), Reduction::Mean, Axis(0))?;
+/// assert_eq!(mean.dim(), (32, 32));
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_reduce(
+    compressed_data: &[u8],
+    shape: (usize, usize, usize),
+    reduction: Reduction,
+    axis: Axis,
+) -> EBCCResult<Array<f32, Ix2>> {
+    let (frames, height, width) = shape;
+
+    let (reduced_shape, reduced_len) = match axis {
+        Axis(0) => ((height, width), frames),
+        Axis(1) => ((frames, width), height),
+        Axis(2) => ((frames, height), width),
+        Axis(axis) => {
+            return Err(EBCCError::InvalidInput(format!(
+                "Cannot reduce along axis {axis} of 3D data"
+            )))
+        }
+    };
+
+    if reduced_len == 0 {
+        return Err(EBCCError::InvalidInput(format!(
+            "Cannot reduce along the zero-length axis {} of data with shape {:?}",
+            axis.index(),
+            <[usize; 3]>::from(shape),
+        )));
+    }
+
+    let init = match reduction {
+        Reduction::Mean => 0.0,
+        Reduction::Min => f64::INFINITY,
+        Reduction::Max => f64::NEG_INFINITY,
+    };
+    let mut accumulator = Array::from_elem(reduced_shape, init);
+
+    ebcc_decode_visit(compressed_data, shape, |first_frame, frames| {
+        accumulate(&mut accumulator, first_frame, frames, reduction, axis);
+        Ok(())
+    })?;
+
+    if reduction == Reduction::Mean {
+        #[expect(clippy::cast_precision_loss)]
+        accumulator.mapv_inplace(|sum| sum / reduced_len as f64);
+    }
+
+    #[expect(clippy::cast_possible_truncation)]
+    Ok(accumulator.mapv(|x| x as f32))
+}
+
+fn accumulate(
+    accumulator: &mut Array<f64, Ix2>,
+    first_frame: usize,
+    frames: ArrayView<f32, EbccDim>,
+    reduction: Reduction,
+    axis: Axis,
+) {
+    let combine: fn(&mut f64, f32) = match reduction {
+        Reduction::Mean => |acc, x| *acc += f64::from(x),
+        Reduction::Min => |acc, x| *acc = acc.min(f64::from(x)),
+        Reduction::Max => |acc, x| *acc = acc.max(f64::from(x)),
+    };
+
+    if axis == Axis(0) {
+        for frame in frames.outer_iter() {
+            Zip::from(&mut *accumulator)
+                .and(&frame)
+                .for_each(|acc, &x| combine(acc, x));
+        }
+        return;
+    }
+
+    // the frame axis is kept, so each frame is reduced into its own row, and
+    // each lane along the reduced axis into one element of that row
+    let lane_axis = Axis(axis.index() - 1);
+    for (mut row, frame) in accumulator
+        .slice_axis_mut(
+            Axis(0),
+            Slice::from(first_frame..first_frame + frames.len_of(Axis(0))),
+        )
+        .outer_iter_mut()
+        .zip(frames.outer_iter())
+    {
+        for (acc, lane) in row.iter_mut().zip(frame.lanes(lane_axis)) {
+            for &x in lane {
+                combine(acc, x);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::*;
+    use crate::{ebcc_encode, testdata, EBCCConfig, EbccStreamEncoder};
+
+    fn reference(data: &Array<f32, EbccDim>, reduction: Reduction, axis: Axis) -> Array<f32, Ix2> {
+        data.map_axis(axis, |lane| match reduction {
+            Reduction::Mean => lane.mean().unwrap_or(f32::NAN),
+            Reduction::Min => lane.fold(f32::INFINITY, |a, &b| a.min(b)),
+            Reduction::Max => lane.fold(f32::NEG_INFINITY, |a, &b| a.max(b)),
+        })
+    }
+
+    #[test]
+    fn test_reduce_matches_full_decode() -> EBCCResult<()> {
+        let data = testdata::temperature((5, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let mut encoder = EbccStreamEncoder::new(
+            Vec::new(),
+            config.clone(),
+            (32, 48),
+            NonZeroUsize::MIN.saturating_add(1),
+        )?;
+        for frame in data.outer_iter() {
+            encoder.push_frame(frame)?;
+        }
+
+        for compressed in [ebcc_encode(data.view(), &config)?, encoder.finish()?] {
+            let mut decompressed = Array::zeros(data.dim());
+            crate::ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+            for reduction in [Reduction::Mean, Reduction::Min, Reduction::Max] {
+                for axis in [Axis(0), Axis(1), Axis(2)] {
+                    let reduced = ebcc_decode_reduce(&compressed, data.dim(), reduction, axis)?;
+                    let expected = reference(&decompressed, reduction, axis);
+
+                    assert_eq!(reduced.dim(), expected.dim());
+                    assert!(reduced
+                        .iter()
+                        .zip(expected.iter())
+                        .all(|(a, b)| (a - b).abs() < 1e-3));
+                }
+            }
+        }
+
+        assert!(ebcc_decode_reduce(
+            &ebcc_encode(data.view(), &config)?,
+            data.dim(),
+            Reduction::Mean,
+            Axis(3)
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
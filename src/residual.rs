@@ -0,0 +1,361 @@
+//! Residual-only EBCC compression without the `JPEG2000` base layer.
+//!
+//! Each frame is predicted with a 2D Lorenzo predictor over the already
+//! reconstructed values, and the prediction residual is quantized linearly
+//! such that the reconstruction stays within the error bound. Runs of zero
+//! quantization codes are run-length encoded and all codes are stored as
+//! LEB128 varints. Values whose quantized reconstruction would violate the
+//! error bound, e.g. because the prediction overflows, are stored verbatim.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_RESIDUAL_MAGIC`], the format version as `u32`, the
+//!   number of frames, the frame height, and the frame width as `u64`s, the
+//!   absolute error bound as `f32`, and the number of verbatim values as `u64`
+//! - the verbatim `f32` values
+//! - the codes: `0` for a verbatim value, `1` followed by the length of a run
+//!   of zero quantization codes, or `2 + zigzag(q)` for a non-zero
+//!   quantization code `q`
+
+use ndarray::{ArrayView, Axis};
+
+use crate::codec::EbccDim;
+use crate::config::EBCCResidualType;
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+
+/// Magic bytes at the start of every residual-only EBCC payload.
+pub const EBCC_RESIDUAL_MAGIC: &[u8; 8] = b"EBCCRESD";
+
+/// Version of the residual-only EBCC payload format.
+const EBCC_RESIDUAL_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC residual-only data");
+
+pub const CODE_VERBATIM: u64 = 0;
+pub const CODE_ZERO_RUN: u64 = 1;
+pub const CODE_OFFSET: u64 = 2;
+
+/// Quantization codes are limited such that they are exactly representable
+const MAX_QUANTIZATION_CODE: f64 = 4_503_599_627_370_496.0;
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_RESIDUAL_MAGIC`].
+pub fn is_residual_only(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_RESIDUAL_MAGIC)
+}
+
+/// Encode a 3D data array into a residual-only payload with the error bound
+/// of the `residual` compression type.
+pub fn residual_only_encode(
+    data: ArrayView<f32, EbccDim>,
+    residual: EBCCResidualType,
+) -> EBCCResult<Vec<u8>> {
+    let error_bound = match residual {
+        EBCCResidualType::AbsoluteError(error) => error,
+        EBCCResidualType::RelativeError(error) => {
+            let (min, max) = data
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| {
+                    (min.min(*x), max.max(*x))
+                });
+            #[expect(clippy::cast_possible_truncation)]
+            let error_bound = (f64::from(error) * (f64::from(max) - f64::from(min))) as f32;
+            error_bound
+        }
+        EBCCResidualType::Jpeg2000Only => return Err(residual_only_requires_error_bound()),
+    };
+
+    let (frames, height, width) = data.dim();
+
+    let mut verbatim = Vec::new();
+    let mut codes = Vec::new();
+    let mut zero_run = 0_u64;
+
+    let mut previous_row = vec![0.0_f32; width];
+    let mut row = vec![0.0_f32; width];
+
+    for frame in data.axis_iter(Axis(0)) {
+        previous_row.fill(0.0);
+
+        for data_row in frame.rows() {
+            let (mut left, mut up_left) = (0.0, 0.0);
+
+            for ((value, up), reconstructed) in data_row.iter().zip(&previous_row).zip(&mut row) {
+                let prediction = left + up - up_left;
+
+                *reconstructed = match quantize(*value, prediction, error_bound) {
+                    Some(0) => {
+                        zero_run += 1;
+                        prediction
+                    }
+                    Some(code) => {
+                        flush_zero_run(&mut codes, &mut zero_run);
+                        write_varint(&mut codes, zigzag(code) + CODE_OFFSET);
+                        reconstruct(prediction, code, error_bound)
+                    }
+                    None => {
+                        flush_zero_run(&mut codes, &mut zero_run);
+                        write_varint(&mut codes, CODE_VERBATIM);
+                        verbatim.extend_from_slice(&value.to_le_bytes());
+                        *value
+                    }
+                };
+
+                (left, up_left) = (*reconstructed, *up);
+            }
+
+            std::mem::swap(&mut previous_row, &mut row);
+        }
+    }
+    flush_zero_run(&mut codes, &mut zero_run);
+
+    let mut compressed_data = Vec::with_capacity(8 + 4 + 4 * 8 + 4 + verbatim.len() + codes.len());
+    compressed_data.extend_from_slice(EBCC_RESIDUAL_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_RESIDUAL_VERSION.to_le_bytes());
+    for dim in [frames, height, width] {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(&error_bound.to_bits().to_le_bytes());
+    compressed_data.extend_from_slice(&usize_to_u64(verbatim.len() / 4)?.to_le_bytes());
+    compressed_data.extend_from_slice(&verbatim);
+    compressed_data.extend_from_slice(&codes);
+
+    Ok(compressed_data)
+}
+
+/// Decode a residual-only payload of the expected `shape` into the flattened
+/// 3D data array.
+pub fn residual_only_decode(
+    compressed_data: &[u8],
+    expected_shape: (usize, usize, usize),
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_RESIDUAL_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_RESIDUAL_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC residual-only version: {version}").into(),
+        ));
+    }
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+    let [frames, height, width] = shape;
+
+    let error_bound = f32::from_bits(u32::from_le_bytes(FORMAT.read_array(&mut reader)?));
+
+    let verbatim_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?
+        .checked_mul(4)
+        .ok_or_else(|| FORMAT.corrupted())?;
+    let Some((verbatim, mut codes)) = reader.split_at_checked(verbatim_len) else {
+        return Err(FORMAT.truncated());
+    };
+    let mut verbatim = verbatim
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap_or_default()));
+
+    let elements = data_len((frames, height, width))?;
+    let mut decompressed_data = Vec::with_capacity(elements);
+    let mut zero_run = 0_u64;
+
+    let mut previous_row = vec![0.0_f32; width];
+    let mut row = vec![0.0_f32; width];
+
+    for _ in 0..frames {
+        previous_row.fill(0.0);
+
+        for _ in 0..height {
+            let (mut left, mut up_left) = (0.0, 0.0);
+
+            for (up, reconstructed) in previous_row.iter().zip(&mut row) {
+                let prediction = left + up - up_left;
+
+                if zero_run == 0 {
+                    match FORMAT.read_varint(&mut codes)? {
+                        CODE_VERBATIM => {
+                            *reconstructed = verbatim.next().ok_or_else(|| FORMAT.truncated())?;
+                        }
+                        CODE_ZERO_RUN => {
+                            zero_run = FORMAT.read_varint(&mut codes)?;
+                            if zero_run == 0 {
+                                return Err(FORMAT.corrupted());
+                            }
+                        }
+                        code => {
+                            let code = unzigzag(code - CODE_OFFSET);
+                            *reconstructed = reconstruct(prediction, code, error_bound);
+                        }
+                    }
+                }
+                if zero_run > 0 {
+                    zero_run -= 1;
+                    *reconstructed = prediction;
+                }
+
+                (left, up_left) = (*reconstructed, *up);
+            }
+
+            decompressed_data.extend_from_slice(&row);
+            std::mem::swap(&mut previous_row, &mut row);
+        }
+    }
+
+    if zero_run > 0 || !codes.is_empty() || verbatim.next().is_some() {
+        return Err(FORMAT.corrupted());
+    }
+
+    Ok(decompressed_data)
+}
+
+/// Quantize the residual of the `value` and its `prediction`, or return
+/// [`None`] if the reconstruction would violate the `error_bound`
+#[expect(clippy::cast_possible_truncation, clippy::float_cmp)]
+pub fn quantize(value: f32, prediction: f32, error_bound: f32) -> Option<i64> {
+    if value == prediction {
+        return Some(0);
+    }
+
+    let code =
+        ((f64::from(value) - f64::from(prediction)) / (2.0 * f64::from(error_bound))).round();
+    // also rejects non-finite codes
+    if code.abs() < MAX_QUANTIZATION_CODE {
+        let code = code as i64;
+        let reconstructed = reconstruct(prediction, code, error_bound);
+
+        ((reconstructed - value).abs() <= error_bound).then_some(code)
+    } else {
+        None
+    }
+}
+
+#[expect(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+pub fn reconstruct(prediction: f32, code: i64, error_bound: f32) -> f32 {
+    (2.0 * f64::from(error_bound)).mul_add(code as f64, f64::from(prediction)) as f32
+}
+
+#[expect(clippy::cast_sign_loss)]
+pub const fn zigzag(code: i64) -> u64 {
+    ((code << 1) ^ (code >> 63)) as u64
+}
+
+#[expect(clippy::cast_possible_wrap)]
+pub const fn unzigzag(code: u64) -> i64 {
+    ((code >> 1) as i64) ^ -((code & 1) as i64)
+}
+
+pub fn flush_zero_run(codes: &mut Vec<u8>, zero_run: &mut u64) {
+    if *zero_run > 0 {
+        write_varint(codes, CODE_ZERO_RUN);
+        write_varint(codes, *zero_run);
+        *zero_run = 0;
+    }
+}
+
+pub fn write_varint(codes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        #[expect(clippy::cast_possible_truncation)]
+        codes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    #[expect(clippy::cast_possible_truncation)]
+    codes.push(value as u8);
+}
+
+pub fn residual_only_requires_error_bound() -> EBCCError {
+    EBCCError::InvalidConfig(String::from(
+        "The residual-only base mode requires an absolute or relative error bound",
+    ))
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, EBCCBaseMode, EBCCConfig};
+
+    #[test]
+    fn test_zigzag() {
+        for code in [0, 1, -1, 2, -2, i64::MAX, i64::MIN] {
+            assert_eq!(unzigzag(zigzag(code)), code);
+        }
+    }
+
+    #[test]
+    fn test_residual_only_roundtrip() -> EBCCResult<()> {
+        let data = testdata::precipitation((2, 32, 48), 7);
+        let range = data.fold(0.0_f32, |max, x| max.max(*x));
+
+        for (config, error_bound) in [
+            (EBCCConfig::max_absolute_error_bounded(0.01), 0.01),
+            (EBCCConfig::relative_error_bounded(1e-3), 1e-3 * range),
+        ] {
+            let config = config.with_base_mode(EBCCBaseMode::None);
+            let compressed = ebcc_encode(data.view(), &config)?;
+
+            let mut decompressed = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, decompressed.view_mut())?;
+            assert!(decompressed
+                .iter()
+                .zip(data.iter())
+                .all(|(a, b)| (a - b).abs() <= error_bound));
+        }
+
+        assert!(matches!(
+            ebcc_encode(
+                data.view(),
+                &EBCCConfig::jpeg2000_only(10.0).with_base_mode(EBCCBaseMode::None)
+            ),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_residual_only_extreme_values() -> EBCCResult<()> {
+        let mut data = testdata::constant((1, 32, 32), 0.0);
+        data.iter_mut().step_by(3).for_each(|x| *x = f32::MAX);
+        data.iter_mut().step_by(5).for_each(|x| *x = -f32::MAX);
+
+        let compressed = residual_only_encode(data.view(), EBCCResidualType::AbsoluteError(0.5))?;
+        let decompressed = residual_only_decode(&compressed, data.dim())?;
+        assert_eq!(decompressed.len(), data.len());
+        assert!(decompressed
+            .iter()
+            .zip(data.iter())
+            .all(|(a, b)| (a - b).abs() <= 0.5));
+
+        // truncated payloads are rejected
+        let truncated = compressed.get(..compressed.len() - 1).unwrap_or_default();
+        assert!(residual_only_decode(truncated, data.dim()).is_err());
+
+        // the declared shape must match before anything is allocated
+        assert!(matches!(
+            residual_only_decode(&compressed, (1, 32, 64)),
+            Err(EBCCError::ShapeMismatch {
+                expected: [1, 32, 32],
+                actual: [1, 32, 64],
+            })
+        ));
+
+        Ok(())
+    }
+}
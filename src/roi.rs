@@ -0,0 +1,355 @@
+//! Region-of-interest quality maps with selective residual correction.
+//!
+//! An [`EBCCRoi`] weighs the error bound of every pixel of a frame: pixels
+//! with a small weight, e.g. along coastlines or cyclone tracks, have a
+//! tighter error bound, while pixels with a large weight have a relaxed one.
+//! The data is first encoded with the most relaxed error bound, i.e. the
+//! error bound times the largest weight. All values whose reconstruction
+//! then violates their own, tighter, error bound are corrected by storing
+//! them verbatim.
+//!
+//! The EBCC C library does not expose the region-of-interest coding of
+//! `OpenJPEG`, so the base layer itself is not weighted.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_ROI_MAGIC`], the format version as `u32`, the number of
+//!   frames, the frame height, and the frame width as `u64`s, and the number
+//!   of corrected values and the length of the inner payload as `u64`s
+//! - the corrections: the C-order index of the value as `u64` and the value
+//!   as `f32`
+//! - the inner EBCC payload with the relaxed error bound
+
+use std::sync::Arc;
+
+use ndarray::{Array2, ArrayView, ArrayView2};
+
+use crate::codec::{
+    ebcc_decode_c_buffer_mut, ebcc_decode_nested, ebcc_encode_c_buffer_with_scratch, EbccDim,
+};
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::verify::data_range;
+
+/// Magic bytes at the start of every region-of-interest EBCC payload.
+pub const EBCC_ROI_MAGIC: &[u8; 8] = b"EBCCROIC";
+
+/// Version of the region-of-interest EBCC payload format.
+const EBCC_ROI_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC ROI data");
+
+/// Length of one correction, an index and a value
+const CORRECTION_LEN: usize = 8 + 4;
+
+/// Per-pixel weights of the error bound of every frame.
+///
+/// The absolute or relative error bound of the
+/// [`residual_compression_type`][EBCCConfig::residual_compression_type] is
+/// multiplied by the weight of each pixel, i.e. weights below one tighten and
+/// weights above one relax the error bound. The weights are shared by all
+/// frames and are cheap to clone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EBCCRoi {
+    weights: Arc<Array2<f32>>,
+}
+
+impl EBCCRoi {
+    /// Create a new region-of-interest map from the `(height, width)` error
+    /// bound `weights`, which must be finite and positive.
+    #[must_use]
+    pub fn new(weights: Array2<f32>) -> Self {
+        Self {
+            weights: Arc::new(weights),
+        }
+    }
+
+    /// The `(height, width)` error bound weights.
+    #[must_use]
+    pub fn weights(&self) -> ArrayView2<'_, f32> {
+        self.weights.view()
+    }
+
+    /// Validate that all weights are finite and positive.
+    pub(crate) fn validate(&self) -> EBCCResult<()> {
+        if self.weights.is_empty() {
+            return Err(EBCCError::InvalidConfig(String::from(
+                "ROI weights must not be empty",
+            )));
+        }
+
+        self.weights
+            .iter()
+            .find(|weight| !(weight.is_finite() && **weight > 0.0))
+            .map_or(Ok(()), |weight| {
+                Err(EBCCError::InvalidConfig(format!(
+                    "ROI weights must be finite and positive, got {weight}"
+                )))
+            })
+    }
+
+    /// The largest weight, which relaxes the error bound the most.
+    fn max_weight(&self) -> f32 {
+        self.weights.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_ROI_MAGIC`].
+pub fn is_roi(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_ROI_MAGIC)
+}
+
+/// Encode a 3D data array into a region-of-interest payload with the
+/// [`EBCCConfig::roi`] of the `config`, which must be set.
+pub fn roi_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let Some(roi) = &config.roi else {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "ROI encoding requires ROI weights",
+        )));
+    };
+
+    let (_, height, width) = data.dim();
+    if roi.weights.dim() != (height, width) {
+        return Err(EBCCError::InvalidConfig(format!(
+            "ROI weights of shape {:?} do not match frames of shape {:?}",
+            roi.weights.shape(),
+            [height, width],
+        )));
+    }
+
+    let (error_bound, residual_compression_type) = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => (
+            error,
+            EBCCResidualType::AbsoluteError(error * roi.max_weight()),
+        ),
+        EBCCResidualType::RelativeError(error) => (
+            data_range(data) * error,
+            EBCCResidualType::RelativeError(error * roi.max_weight()),
+        ),
+        EBCCResidualType::Jpeg2000Only => return Err(roi_requires_error_bound()),
+    };
+
+    let inner_config = EBCCConfig {
+        residual_compression_type,
+        roi: None,
+        expansion_guard: None,
+        ..config.clone()
+    };
+    let inner = ebcc_encode_c_buffer_with_scratch(data, &inner_config, scratch)?;
+
+    let mut inner_copy = inner.as_slice().to_vec();
+    let decoded = ebcc_decode_c_buffer_mut(&mut inner_copy, data.dim())?;
+
+    let mut corrections = Vec::new();
+    let weights = roi.weights.iter().cycle();
+    for (index, ((&x, &y), &weight)) in data.iter().zip(decoded.as_slice()).zip(weights).enumerate()
+    {
+        let error = (x - y).abs();
+        if error.is_nan() || error > error_bound * weight {
+            corrections.extend_from_slice(&usize_to_u64(index)?.to_le_bytes());
+            corrections.extend_from_slice(&x.to_le_bytes());
+        }
+    }
+
+    let inner = inner.as_slice();
+    let mut compressed_data =
+        Vec::with_capacity(EBCC_ROI_MAGIC.len() + 4 + 5 * 8 + corrections.len() + inner.len());
+    compressed_data.extend_from_slice(EBCC_ROI_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_ROI_VERSION.to_le_bytes());
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data
+        .extend_from_slice(&usize_to_u64(corrections.len() / CORRECTION_LEN)?.to_le_bytes());
+    compressed_data.extend_from_slice(&usize_to_u64(inner.len())?.to_le_bytes());
+    compressed_data.extend_from_slice(&corrections);
+    compressed_data.extend_from_slice(inner);
+
+    Ok(compressed_data)
+}
+
+/// Decode a region-of-interest payload of the expected `shape`, which may be
+/// modified during decoding, into the flattened 3D data array.
+pub fn roi_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_ROI_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_ROI_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC ROI version: {version}").into(),
+        ));
+    }
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+    let elements = data_len(expected_shape.into())?;
+
+    let corrections_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?
+        .checked_mul(CORRECTION_LEN)
+        .ok_or_else(|| FORMAT.corrupted())?;
+    let inner_len = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    if corrections_len.checked_add(inner_len) != Some(reader.len()) {
+        return Err(FORMAT.truncated());
+    }
+
+    let header_len = compressed_data.len() - reader.len();
+    let Some((corrections, inner)) = compressed_data
+        .get_mut(header_len..)
+        .and_then(|payload| payload.split_at_mut_checked(corrections_len))
+    else {
+        return Err(FORMAT.truncated());
+    };
+
+    let mut decompressed_data = ebcc_decode_nested(inner, expected_shape.into(), depth + 1)?
+        .as_slice()
+        .to_vec();
+
+    for correction in corrections.chunks_exact(CORRECTION_LEN) {
+        let mut correction: &[u8] = correction;
+        let index = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut correction)?))?;
+        let value = f32::from_le_bytes(FORMAT.read_array(&mut correction)?);
+
+        let Some(decompressed) = decompressed_data
+            .get_mut(index)
+            .filter(|_| index < elements)
+        else {
+            return Err(FORMAT.corrupted());
+        };
+        *decompressed = value;
+    }
+
+    Ok(decompressed_data)
+}
+
+/// Error that the region-of-interest encoding requires an absolute or
+/// relative error bound.
+pub fn roi_requires_error_bound() -> EBCCError {
+    EBCCError::InvalidConfig(String::from(
+        "ROI weights require an absolute or relative error bound",
+    ))
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::codec::{ebcc_decode_c_buffer_mut, MAX_NESTING_DEPTH};
+    use crate::header::header_payload;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_roi_roundtrip() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 64));
+
+        // the left half of every frame is the region of interest
+        let weights = Array::from_shape_fn((32, 64), |(_, x)| if x < 32 { 0.1 } else { 2.0 });
+        let config = EBCCConfig::max_absolute_error_bounded(0.5).with_roi(EBCCRoi::new(weights));
+
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        // the relaxed error bound holds everywhere ...
+        check_error_bound(
+            data.view(),
+            decompressed.view(),
+            &EBCCConfig::max_absolute_error_bounded(1.0),
+        )?;
+        // ... and the tightened one within the region of interest
+        for ((_, _, x), (&a, &b)) in data
+            .indexed_iter()
+            .map(|(index, _)| index)
+            .zip(data.iter().zip(decompressed.iter()))
+        {
+            if x < 32 {
+                assert!((a - b).abs() <= 0.05, "{a} vs {b}");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_roi() {
+        let data = testdata::temperature((1, 32, 32));
+
+        for (weights, config) in [
+            (
+                Array::ones((32, 16)),
+                EBCCConfig::max_absolute_error_bounded(0.1),
+            ),
+            (
+                Array::zeros((32, 32)),
+                EBCCConfig::max_absolute_error_bounded(0.1),
+            ),
+            (Array::ones((32, 32)), EBCCConfig::jpeg2000_only(10.0)),
+        ] {
+            assert!(matches!(
+                ebcc_encode(data.view(), &config.with_roi(EBCCRoi::new(weights))),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_nested_roi() -> EBCCResult<()> {
+        let data = testdata::temperature((1, 32, 32));
+        let compressed = ebcc_encode(data.view(), &EBCCConfig::max_absolute_error_bounded(0.1))?;
+        let mut payload = header_payload(&compressed)?.1.to_vec();
+
+        // wrap the payload into ROI payloads without corrections
+        let wrap = |payload: &mut Vec<u8>| -> EBCCResult<()> {
+            let mut wrapped = Vec::from(EBCC_ROI_MAGIC.as_slice());
+            wrapped.extend_from_slice(&EBCC_ROI_VERSION.to_le_bytes());
+            for dim in <[usize; 3]>::from(data.dim()) {
+                wrapped.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+            }
+            wrapped.extend_from_slice(&0_u64.to_le_bytes());
+            wrapped.extend_from_slice(&usize_to_u64(payload.len())?.to_le_bytes());
+            wrapped.append(payload);
+            *payload = wrapped;
+            Ok(())
+        };
+
+        for _ in 0..MAX_NESTING_DEPTH {
+            wrap(&mut payload)?;
+        }
+        ebcc_decode_c_buffer_mut(&mut payload.clone(), data.dim())?;
+
+        // crafted payloads that are nested too deeply are rejected
+        wrap(&mut payload)?;
+        assert!(matches!(
+            ebcc_decode_c_buffer_mut(&mut payload, data.dim()),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,468 @@
+//! Declarative compression jobs, e.g. for archive conversion campaigns.
+//!
+//! An [`EBCCJobSpec`] describes which input files are compressed, with which
+//! configuration each variable is compressed, how the compressed files are
+//! laid out, and how many inputs are compressed in parallel. [`run_job`]
+//! executes the job and reports its progress after every input, such that a
+//! campaign is a configuration instead of a bespoke script. The `ebcc run`
+//! subcommand of the `ebcc-cli` crate reads job specifications from TOML or
+//! JSON files.
+//!
+//! Every input is compressed into one file in the
+//! [`output_dir`][EBCCJobSpec::output_dir], which is named after the input
+//! file with the `.ebcc` extension.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use ebcc::container::EBCCContainerConfig;
+//! use ebcc::runner::{run_job, EBCCJobInput, EBCCJobLayout, EBCCJobSpec};
+//! use ebcc::{testdata, EBCCConfig};
+//!
+//! # fn main() -> ebcc::EBCCResult<()> {
+//! let output_dir = std::env::temp_dir().join(format!("ebcc-job-doc-{}", std::process::id()));
+//! let spec = EBCCJobSpec::new(
+//!     EBCCContainerConfig::new(EBCCConfig::max_absolute_error_bounded(0.1)),
+//!     &output_dir,
+//! )
+//! .with_input(EBCCJobInput::new("t2m-2024.f32", "temperature"))
+//! .with_input(EBCCJobInput::new("t2m-2025.f32", "temperature"))
+//! .with_layout(EBCCJobLayout::Container);
+//!
+//! let report = run_job(
+//!     &spec,
+//!     // e.g. read the input file
+//!     |_input| Ok(testdata::temperature((2, 32, 48))),
+//!     |progress| eprintln!("{}/{} done", progress.completed, progress.total),
+//! )?;
+//! assert_eq!(report.outputs.len(), 2);
+//! # std::fs::remove_dir_all(output_dir)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use ndarray::Array;
+
+use crate::capture::propagate_capture;
+use crate::codec::EbccDim;
+use crate::container::{EBCCContainerConfig, EbccContainerWriter};
+use crate::error::{EBCCError, EBCCResult};
+use crate::io::ebcc_encode_to_writer;
+
+/// Specification of a compression job, which is executed by [`run_job`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EBCCJobSpec {
+    /// Input files, which are compressed in this order
+    pub inputs: Vec<EBCCJobInput>,
+    /// Configuration of every variable and frame
+    pub config: EBCCContainerConfig,
+    /// Directory into which the compressed files are written
+    pub output_dir: PathBuf,
+    /// Layout of the compressed files
+    pub layout: EBCCJobLayout,
+    /// Maximum number of inputs that are compressed in parallel
+    pub threads: NonZeroUsize,
+}
+
+impl EBCCJobSpec {
+    /// Create a new job without any inputs that compresses with the `config`
+    /// into the `output_dir`, one input at a time and with the
+    /// [`EBCCJobLayout::Payload`] layout.
+    #[must_use]
+    pub fn new(config: EBCCContainerConfig, output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inputs: Vec::new(),
+            config,
+            output_dir: output_dir.into(),
+            layout: EBCCJobLayout::Payload,
+            threads: NonZeroUsize::MIN,
+        }
+    }
+
+    /// Add the `input` to the job.
+    #[must_use]
+    pub fn with_input(mut self, input: EBCCJobInput) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    /// Change the layout of the compressed files.
+    #[must_use]
+    pub const fn with_layout(mut self, layout: EBCCJobLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Compress up to `threads` inputs in parallel.
+    ///
+    /// Since every call into the EBCC C library is serialized, the threads
+    /// mostly overlap loading the inputs with writing the compressed files.
+    #[must_use]
+    pub const fn with_threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// The path of the compressed file of the `input`.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidConfig`] if the `input` path has no file name
+    pub fn output_path(&self, input: &EBCCJobInput) -> EBCCResult<PathBuf> {
+        let Some(stem) = input.path.file_stem() else {
+            return Err(EBCCError::InvalidConfig(format!(
+                "Job input {} has no file name",
+                input.path.display(),
+            )));
+        };
+
+        Ok(self.output_dir.join(stem).with_extension("ebcc"))
+    }
+
+    /// Validate the configuration and the output paths of the job.
+    ///
+    /// # Errors
+    ///
+    /// - all errors that [`EBCCContainerConfig::validate`] can return
+    /// - [`EBCCError::InvalidConfig`] if an input has no file name, or if
+    ///   two inputs would be compressed into the same output file
+    pub fn validate(&self) -> EBCCResult<()> {
+        self.config.validate()?;
+
+        let mut outputs = BTreeSet::new();
+        for input in &self.inputs {
+            let output = self.output_path(input)?;
+            if !outputs.insert(output) {
+                return Err(EBCCError::InvalidConfig(format!(
+                    "Job input {} would overwrite the output of another input",
+                    input.path.display(),
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Input file of an [`EBCCJobSpec`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EBCCJobInput {
+    /// Path of the input file
+    pub path: PathBuf,
+    /// Variable of the data, which selects its configuration from the
+    /// [`EBCCJobSpec::config`]
+    pub variable: String,
+    /// Shape `(frames, height, width)` of the data, for input formats that
+    /// do not record it, e.g. raw `f32` files
+    pub shape: Option<(usize, usize, usize)>,
+}
+
+impl EBCCJobInput {
+    /// Create a new input of the `variable` read from the file at `path`.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>, variable: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            variable: variable.into(),
+            shape: None,
+        }
+    }
+
+    /// Record the shape `(frames, height, width)` of the data.
+    #[must_use]
+    pub const fn with_shape(mut self, shape: (usize, usize, usize)) -> Self {
+        self.shape = Some(shape);
+        self
+    }
+}
+
+/// Layout of the compressed files of an [`EBCCJobSpec`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EBCCJobLayout {
+    /// One [`ebcc_encode`][crate::ebcc_encode] payload per input, which is
+    /// compressed with the configuration of its variable
+    #[default]
+    Payload,
+    /// One [`container`][crate::container] per input, whose frames are
+    /// compressed with the configuration of their variable and frame
+    Container,
+}
+
+/// Compressed file of one input of a job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EBCCJobOutput {
+    /// Path of the input file
+    pub input: PathBuf,
+    /// Path of the compressed file
+    pub output: PathBuf,
+    /// Variable of the data
+    pub variable: String,
+    /// Size of the uncompressed data, in bytes
+    pub original_bytes: usize,
+    /// Size of the compressed file, in bytes
+    pub compressed_bytes: u64,
+}
+
+/// Progress of a job, which is reported after every compressed input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EBCCJobProgress<'a> {
+    /// Number of inputs that have been compressed
+    pub completed: usize,
+    /// Total number of inputs
+    pub total: usize,
+    /// The input that has just been compressed
+    pub output: &'a EBCCJobOutput,
+}
+
+/// Result of [`run_job`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EBCCJobReport {
+    /// Compressed file of every input, in the order of the inputs
+    pub outputs: Vec<EBCCJobOutput>,
+}
+
+/// Execute the compression job of the `spec`.
+///
+/// Every input is read with `load`, e.g. from a `.npy` or raw `f32` file,
+/// compressed with the configuration of its variable, and written into the
+/// [`output_dir`][EBCCJobSpec::output_dir], which is created if needed.
+/// After every input, `progress` is called on the calling thread. Inputs are
+/// compressed on up to [`threads`][EBCCJobSpec::threads] threads, which are
+/// further limited by the [`limits`][crate::EBCCConfig::limits] of the
+/// default configuration.
+///
+/// The job stops at the first input that fails, while the inputs that are
+/// already being compressed are finished.
+///
+/// # Errors
+///
+/// - all errors that [`EBCCJobSpec::validate`] can return
+/// - all errors that `load` returns
+/// - all errors that [`ebcc_encode`][crate::ebcc_encode] or the
+///   [`EbccContainerWriter`] can return
+/// - [`EBCCError::Io`] if creating or writing an output file fails
+pub fn run_job(
+    spec: &EBCCJobSpec,
+    load: impl Fn(&EBCCJobInput) -> EBCCResult<Array<f32, EbccDim>> + Sync,
+    mut progress: impl FnMut(&EBCCJobProgress),
+) -> EBCCResult<EBCCJobReport> {
+    spec.validate()?;
+    fs::create_dir_all(&spec.output_dir)?;
+
+    let total = spec.inputs.len();
+    let threads = spec
+        .config
+        .default_config()
+        .limits
+        .threads(spec.threads)
+        .get()
+        .min(total);
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (load, next, failed) = (&load, &next, &failed);
+            scope.spawn(propagate_capture(move || {
+                while !failed.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(input) = spec.inputs.get(index) else {
+                        break;
+                    };
+                    if sender
+                        .send((index, compress_input(spec, input, load)))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }));
+        }
+        // the loop below ends once all workers have dropped their senders
+        drop(sender);
+
+        let mut outputs = Vec::with_capacity(total);
+        let mut error = None;
+        for (index, output) in receiver {
+            match output {
+                Ok(output) => {
+                    outputs.push((index, output));
+                    if let Some((_, output)) = outputs.last() {
+                        progress(&EBCCJobProgress {
+                            completed: outputs.len(),
+                            total,
+                            output,
+                        });
+                    }
+                }
+                Err(err) => {
+                    failed.store(true, Ordering::Relaxed);
+                    error.get_or_insert(err);
+                }
+            }
+        }
+
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        outputs.sort_unstable_by_key(|(index, _)| *index);
+        Ok(EBCCJobReport {
+            outputs: outputs.into_iter().map(|(_, output)| output).collect(),
+        })
+    })
+}
+
+/// Compress one `input` of the job `spec` into its output file
+fn compress_input(
+    spec: &EBCCJobSpec,
+    input: &EBCCJobInput,
+    load: impl Fn(&EBCCJobInput) -> EBCCResult<Array<f32, EbccDim>>,
+) -> EBCCResult<EBCCJobOutput> {
+    let data = load(input)?;
+    let output = spec.output_path(input)?;
+    let mut writer = BufWriter::new(File::create(&output)?);
+
+    match spec.layout {
+        EBCCJobLayout::Payload => {
+            let config = spec.config.resolve_variable(&input.variable);
+            ebcc_encode_to_writer(data.view(), &config, &mut writer)?;
+        }
+        EBCCJobLayout::Container => {
+            let (_, height, width) = data.dim();
+            let mut container = EbccContainerWriter::with_config(
+                writer,
+                &spec.config,
+                &input.variable,
+                (height, width),
+            )?;
+            for frame in data.outer_iter() {
+                container.push_frame(frame)?;
+            }
+            writer = container.finish()?;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+
+    Ok(EBCCJobOutput {
+        compressed_bytes: fs::metadata(&output)?.len(),
+        input: input.path.clone(),
+        output,
+        variable: input.variable.clone(),
+        original_bytes: data.len() * size_of::<f32>(),
+    })
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use crate::container::EbccContainer;
+    use crate::verify::check_error_bound;
+    use crate::{ebcc_decode_into, testdata, EBCCConfig, EBCCConfigOverride, EBCCResidualType};
+
+    use super::*;
+
+    #[test]
+    fn test_run_job() -> EBCCResult<()> {
+        let output_dir =
+            std::env::temp_dir().join(format!("ebcc-runner-test-{}", std::process::id()));
+
+        let config = EBCCContainerConfig::new(EBCCConfig::max_absolute_error_bounded(0.1))
+            .with_variable(
+                "precipitation",
+                EBCCConfigOverride::new()
+                    .with_residual_compression_type(EBCCResidualType::AbsoluteError(0.01)),
+            );
+        let inputs = ["a/t2m.f32", "b/tp.f32", "c/t2m-2.f32", "d/tp-2.f32"];
+        let mut spec =
+            EBCCJobSpec::new(config, &output_dir).with_threads(NonZeroUsize::MIN.saturating_add(2));
+        for (i, path) in inputs.iter().enumerate() {
+            let variable = if i % 2 == 0 {
+                "temperature"
+            } else {
+                "precipitation"
+            };
+            spec = spec.with_input(EBCCJobInput::new(*path, variable).with_shape((2, 32, 48)));
+        }
+        let load =
+            |input: &EBCCJobInput| Ok(testdata::temperature(input.shape.unwrap_or((1, 32, 48))));
+
+        for layout in [EBCCJobLayout::Payload, EBCCJobLayout::Container] {
+            let progressed = AtomicUsize::new(0);
+            let report = run_job(&spec.clone().with_layout(layout), load, |progress| {
+                assert_eq!(progress.total, inputs.len());
+                assert_eq!(
+                    progressed.fetch_add(1, Ordering::Relaxed) + 1,
+                    progress.completed
+                );
+            })?;
+            assert_eq!(progressed.into_inner(), inputs.len());
+
+            assert_eq!(report.outputs.len(), inputs.len());
+            for (output, input) in report.outputs.iter().zip(&spec.inputs) {
+                assert_eq!(output.input, input.path);
+                assert_eq!(output.output, spec.output_path(input)?);
+                assert_eq!(output.original_bytes, 2 * 32 * 48 * size_of::<f32>());
+                assert_eq!(output.compressed_bytes, fs::metadata(&output.output)?.len());
+
+                let compressed = fs::read(&output.output)?;
+                let mut decompressed = Array::zeros((2, 32, 48));
+                match layout {
+                    EBCCJobLayout::Payload => {
+                        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+                    }
+                    EBCCJobLayout::Container => {
+                        EbccContainer::open(std::io::Cursor::new(compressed))?
+                            .decode_into(decompressed.view_mut())?;
+                    }
+                }
+                check_error_bound(
+                    load(input)?.view(),
+                    decompressed.view(),
+                    &spec.config.resolve_variable(&input.variable),
+                )?;
+            }
+        }
+
+        // inputs must not overwrite each other's outputs
+        let duplicate = spec
+            .clone()
+            .with_input(EBCCJobInput::new("e/t2m.npy", "temperature"));
+        assert!(matches!(
+            run_job(&duplicate, load, |_| ()),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        // the first failing input fails the job
+        let failing = run_job(
+            &spec,
+            |input| match input.variable.as_str() {
+                "precipitation" => Err(EBCCError::EmptyInput),
+                _ => load(input),
+            },
+            |_| (),
+        );
+        assert!(matches!(failing, Err(EBCCError::EmptyInput)));
+
+        fs::remove_dir_all(output_dir)?;
+
+        Ok(())
+    }
+}
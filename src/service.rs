@@ -0,0 +1,721 @@
+//! Managed in-process EBCC compression service.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use ndarray::Array;
+
+use crate::capture::EBCCCaptureContext;
+use crate::codec::{ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::EBCCConfig;
+use crate::decoder::EbccDecoder;
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::write_header;
+use crate::limits::EBCCLimits;
+
+/// Job that is run by a worker with its workspace
+type Job = Box<dyn FnOnce(&mut Workspace) + Send>;
+
+/// Reusable buffers that every worker owns
+#[derive(Debug, Default)]
+struct Workspace {
+    scratch: Vec<f32>,
+    decoder: EbccDecoder,
+}
+
+/// Managed EBCC codec runtime with a thread pool, reusable workspaces, a
+/// cache of decoded data, and named configuration profiles.
+///
+/// Jobs are submitted with [`submit_encode`][Self::submit_encode] and
+/// [`submit_decode`][Self::submit_decode] and run on the service's worker
+/// threads. Every profile, all decode jobs, and all jobs without a profile
+/// are separate tenants, each with its own queue. The workers take the jobs
+/// of the tenants in turns, and the jobs of each tenant in submission order,
+/// such that a tenant with many queued jobs cannot starve the others. Every
+/// worker reuses its own staging buffers across jobs, like an
+/// [`EbccBufferedEncoder`][crate::EbccBufferedEncoder] and an
+/// [`EbccDecoder`]. The submission returns an [`EbccJob`] handle, which is
+/// backed by a channel and can be waited on from any thread.
+///
+/// Every profile can have its own [`EBCCLimits`], e.g. to enforce different
+/// quotas per tenant. The [`config.limits`][EBCCConfig::limits] of a profile
+/// bound the size of the input and output of its jobs, the time from the
+/// submission of each job until its result, and the number of its jobs that
+/// run at the same time. Jobs of a tenant that already runs its maximum
+/// number of jobs stay queued and do not occupy a worker. The
+/// [decode limits](Self::with_decode_limits) bound all decode jobs in the
+/// same way.
+///
+/// Dropping the service waits for all submitted jobs to finish.
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use std::num::NonZeroUsize;
+///
+/// use ebcc::{EBCCConfig, EbccCompressionService};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let service = EbccCompressionService::new(NonZeroUsize::MIN)
+///     .with_profile("t2m", EBCCConfig::max_absolute_error_bounded(0.1))?
+///     .with_decode_cache(16);
+///
+/// let data = Array::from_elem((1, 32, 32), 280.0_f32);
+/// let compressed = service.submit_encode(data, "t2m")?.wait()?;
+/// let decompressed = service.submit_decode(compressed, (1, 32, 32)).wait()?;
+/// assert!(decompressed.iter().all(|t| (t - 280.0).abs() <= 0.1));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct EbccCompressionService {
+    scheduler: Arc<Scheduler>,
+    workers: Vec<JoinHandle<()>>,
+    profiles: BTreeMap<String, Profile>,
+    decode_limits: EBCCLimits,
+    cache: Arc<Mutex<DecodeCache>>,
+}
+
+/// Configuration profile and the tenant that its jobs belong to
+#[derive(Debug)]
+struct Profile {
+    config: EBCCConfig,
+    tenant: TenantId,
+}
+
+/// Tenant of the jobs without a profile
+const UNPROFILED_TENANT: TenantId = 0;
+/// Tenant of all decode jobs
+const DECODE_TENANT: TenantId = 1;
+
+impl EbccCompressionService {
+    /// Create a new service with `threads` worker threads, no configuration
+    /// profiles, the default decode limits, and no decode cache.
+    #[must_use]
+    pub fn new(threads: NonZeroUsize) -> Self {
+        let scheduler = Arc::new(Scheduler::new());
+        scheduler.add_tenant(None);
+        scheduler.add_tenant(None);
+
+        let workers = (0..threads.get())
+            .map(|_| {
+                let scheduler = Arc::clone(&scheduler);
+                thread::spawn(move || {
+                    let mut workspace = Workspace::default();
+                    while let Some((tenant, job)) = scheduler.next() {
+                        // a panicking job drops its result sender, which its
+                        //  handle reports, and the worker keeps running
+                        let _ = panic::catch_unwind(AssertUnwindSafe(|| job(&mut workspace)));
+                        scheduler.finish(tenant);
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            scheduler,
+            workers,
+            profiles: BTreeMap::new(),
+            decode_limits: EBCCLimits::new(),
+            cache: Arc::new(Mutex::new(DecodeCache::new(0))),
+        }
+    }
+
+    /// Add the configuration profile `name` with the `config`, which replaces
+    /// any previous profile of the same name.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidConfig`] if [`config.validate`][EBCCConfig::validate]
+    ///   fails
+    pub fn with_profile(mut self, name: impl Into<String>, config: EBCCConfig) -> EBCCResult<Self> {
+        config.validate()?;
+        let tenant = self.scheduler.add_tenant(config.limits.max_threads);
+        self.profiles
+            .insert(name.into(), Profile { config, tenant });
+        Ok(self)
+    }
+
+    /// Change the limits of all decode jobs, which are checked before
+    /// anything is decoded.
+    #[must_use]
+    pub fn with_decode_limits(mut self, limits: EBCCLimits) -> Self {
+        self.scheduler
+            .set_max_threads(DECODE_TENANT, limits.max_threads);
+        self.decode_limits = limits;
+        self
+    }
+
+    /// Cache the decoded data of up to `entries` recently decoded payloads,
+    /// such that decoding the same payload again returns immediately.
+    #[must_use]
+    pub fn with_decode_cache(self, entries: usize) -> Self {
+        *self.cache.lock().unwrap_or_else(PoisonError::into_inner) = DecodeCache::new(entries);
+        self
+    }
+
+    /// The configuration profile `name`, if it exists.
+    #[must_use]
+    pub fn profile(&self, name: &str) -> Option<&EBCCConfig> {
+        self.profiles.get(name).map(|profile| &profile.config)
+    }
+
+    /// Submit a job that encodes the 3D `data` array with the configuration
+    /// profile `profile`.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidConfig`] if the `profile` does not exist
+    pub fn submit_encode(
+        &self,
+        data: Array<f32, EbccDim>,
+        profile: &str,
+    ) -> EBCCResult<EbccJob<Vec<u8>>> {
+        let Some(Profile { config, tenant }) = self.profiles.get(profile) else {
+            return Err(EBCCError::InvalidConfig(format!(
+                "Unknown configuration profile {profile:?}"
+            )));
+        };
+
+        Ok(self.submit_encode_for_tenant(data, config.clone(), *tenant))
+    }
+
+    /// Submit a job that encodes the 3D `data` array with the `config`.
+    ///
+    /// The job's result is identical to the output of
+    /// [`ebcc_encode`][crate::ebcc_encode]. Only the
+    /// [`max_threads`][EBCCLimits::max_threads] of the
+    /// [`config.limits`][EBCCConfig::limits] are ignored, since the job does
+    /// not belong to a profile. All jobs without a profile share one queue.
+    #[must_use]
+    pub fn submit_encode_with_config(
+        &self,
+        data: Array<f32, EbccDim>,
+        config: EBCCConfig,
+    ) -> EbccJob<Vec<u8>> {
+        self.submit_encode_for_tenant(data, config, UNPROFILED_TENANT)
+    }
+
+    fn submit_encode_for_tenant(
+        &self,
+        data: Array<f32, EbccDim>,
+        config: EBCCConfig,
+        tenant: TenantId,
+    ) -> EbccJob<Vec<u8>> {
+        let limits = config.limits;
+        self.submit(&limits, tenant, move |workspace| {
+            let payload =
+                ebcc_encode_c_buffer_with_scratch(data.view(), &config, &mut workspace.scratch)?;
+            let payload = payload.as_slice();
+
+            let mut compressed_data = Vec::new();
+            write_header(&mut compressed_data, data.view(), &config, payload)?;
+            compressed_data.extend_from_slice(payload);
+
+            Ok(compressed_data)
+        })
+    }
+
+    /// Submit a job that decodes the `compressed_data` into a new 3D data
+    /// array of the given `shape`.
+    ///
+    /// If the service has a [decode cache][Self::with_decode_cache] that
+    /// contains the `compressed_data`, the returned job is ready
+    /// immediately.
+    #[must_use]
+    pub fn submit_decode(
+        &self,
+        compressed_data: Vec<u8>,
+        shape: (usize, usize, usize),
+    ) -> EbccJob<Array<f32, EbccDim>> {
+        let cached = self
+            .cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&compressed_data, shape);
+        if let Some(decompressed_data) = cached {
+            let (sender, receiver) = mpsc::sync_channel(1);
+            let _ = sender.send(Ok(decompressed_data));
+            return EbccJob {
+                receiver,
+                deadline: None,
+            };
+        }
+
+        let cache = Arc::clone(&self.cache);
+        let limits = self.decode_limits;
+        self.submit(&limits, DECODE_TENANT, move |workspace| {
+            limits.check_shape(shape)?;
+            let mut decompressed_data = Array::zeros(shape);
+            let mut decoder = std::mem::take(&mut workspace.decoder).with_limits(limits);
+            let result = decoder.decode_into(&compressed_data, decompressed_data.view_mut());
+            workspace.decoder = decoder;
+            result?;
+
+            cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(compressed_data, &decompressed_data);
+
+            Ok(decompressed_data)
+        })
+    }
+
+    fn submit<T: Send + 'static>(
+        &self,
+        limits: &EBCCLimits,
+        tenant: TenantId,
+        job: impl FnOnce(&mut Workspace) -> EBCCResult<T> + Send + 'static,
+    ) -> EbccJob<T> {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let deadline = limits
+            .timeout
+            .and_then(|timeout| Some((Instant::now().checked_add(timeout)?, timeout)));
+        // the job is captured by the capture of the submitting thread
+        let capture = EBCCCaptureContext::current();
+
+        self.scheduler.push(
+            tenant,
+            Box::new(move |workspace: &mut Workspace| {
+                // jobs that have already timed out are not started
+                let result = match deadline {
+                    Some((deadline, limit)) if Instant::now() >= deadline => {
+                        Err(EBCCError::TimedOut { limit })
+                    }
+                    _ => match &capture {
+                        Some(capture) => capture.run(|| job(workspace)),
+                        None => job(workspace),
+                    },
+                };
+                let _ = sender.send(result);
+            }),
+        );
+
+        EbccJob { receiver, deadline }
+    }
+}
+
+impl Drop for EbccCompressionService {
+    fn drop(&mut self) {
+        // closing the scheduler stops the workers once all jobs have finished
+        self.scheduler.close();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Handle to the result of a job that was submitted to an
+/// [`EbccCompressionService`].
+#[derive(Debug)]
+pub struct EbccJob<T> {
+    receiver: mpsc::Receiver<EBCCResult<T>>,
+    deadline: Option<(Instant, Duration)>,
+}
+
+impl<T> EbccJob<T> {
+    /// Block until the job has finished and return its result.
+    ///
+    /// # Errors
+    ///
+    /// - all errors that the job can return
+    /// - [`EBCCError::CompressionError`] if the job failed without a result,
+    ///   e.g. because it panicked
+    /// - [`EBCCError::TimedOut`] if the job did not finish within the
+    ///   [`timeout`][EBCCLimits::timeout] of its limits
+    pub fn wait(self) -> EBCCResult<T> {
+        let Some((deadline, limit)) = self.deadline else {
+            return self.receiver.recv().unwrap_or_else(|_| Err(job_failed()));
+        };
+
+        match self
+            .receiver
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(EBCCError::TimedOut { limit }),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(job_failed()),
+        }
+    }
+
+    /// Return the result of the job if it has finished, or [`None`] if it is
+    /// still running.
+    ///
+    /// # Errors
+    ///
+    /// - all errors that [`wait`][Self::wait] can return
+    #[must_use]
+    pub fn try_wait(&self) -> Option<EBCCResult<T>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => match self.deadline {
+                Some((deadline, limit)) if Instant::now() >= deadline => {
+                    Some(Err(EBCCError::TimedOut { limit }))
+                }
+                _ => None,
+            },
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(job_failed())),
+        }
+    }
+}
+
+fn job_failed() -> EBCCError {
+    EBCCError::CompressionError("EBCC service job failed without a result".into())
+}
+
+/// Index of a tenant of a [`Scheduler`]
+type TenantId = usize;
+
+/// Fair queue of the jobs of all tenants, from which the workers take the
+/// jobs of the tenants in turns
+struct Scheduler {
+    state: Mutex<SchedulerState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    tenants: Vec<Tenant>,
+    next_tenant: TenantId,
+    closed: bool,
+}
+
+/// Queued jobs of one tenant, of which at most `max_threads` run at the same
+/// time
+#[derive(Default)]
+struct Tenant {
+    max_threads: Option<NonZeroUsize>,
+    running: usize,
+    jobs: VecDeque<Job>,
+}
+
+impl Tenant {
+    fn is_runnable(&self) -> bool {
+        !self.jobs.is_empty()
+            && self
+                .max_threads
+                .is_none_or(|max_threads| self.running < max_threads.get())
+    }
+}
+
+impl Scheduler {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(SchedulerState::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SchedulerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Add a new tenant, of whose jobs at most `max_threads` run at the same
+    /// time
+    fn add_tenant(&self, max_threads: Option<NonZeroUsize>) -> TenantId {
+        let mut state = self.lock();
+        state.tenants.push(Tenant {
+            max_threads,
+            ..Tenant::default()
+        });
+        state.tenants.len() - 1
+    }
+
+    fn set_max_threads(&self, tenant: TenantId, max_threads: Option<NonZeroUsize>) {
+        if let Some(tenant) = self.lock().tenants.get_mut(tenant) {
+            tenant.max_threads = max_threads;
+        }
+        self.changed.notify_all();
+    }
+
+    /// Queue the `job` of the `tenant`
+    ///
+    /// Jobs that are pushed after the scheduler has been closed are dropped,
+    /// together with their result sender, which their handle reports.
+    fn push(&self, tenant: TenantId, job: Job) {
+        let mut state = self.lock();
+        if state.closed {
+            return;
+        }
+        if let Some(tenant) = state.tenants.get_mut(tenant) {
+            tenant.jobs.push_back(job);
+        }
+        drop(state);
+        self.changed.notify_one();
+    }
+
+    /// Block until a job can run and take it, starting the search at the
+    /// tenant after the one whose job was taken last, or return [`None`]
+    /// once the scheduler is closed and all jobs have been taken
+    fn next(&self) -> Option<(TenantId, Job)> {
+        let mut state = self.lock();
+        loop {
+            let tenants = state.tenants.len();
+            let runnable = (0..tenants)
+                .map(|offset| (state.next_tenant + offset) % tenants)
+                .find(|&tenant| state.tenants.get(tenant).is_some_and(Tenant::is_runnable));
+
+            if let Some(index) = runnable {
+                state.next_tenant = index + 1;
+                if let Some(tenant) = state.tenants.get_mut(index) {
+                    if let Some(job) = tenant.jobs.pop_front() {
+                        tenant.running += 1;
+                        return Some((index, job));
+                    }
+                }
+            }
+
+            if state.closed && state.tenants.iter().all(|tenant| tenant.jobs.is_empty()) {
+                return None;
+            }
+
+            state = self
+                .changed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Release the thread of a finished job of the `tenant`
+    fn finish(&self, tenant: TenantId) {
+        if let Some(tenant) = self.lock().tenants.get_mut(tenant) {
+            tenant.running = tenant.running.saturating_sub(1);
+        }
+        self.changed.notify_all();
+    }
+
+    /// Stop accepting new jobs and wake all workers
+    fn close(&self) {
+        self.lock().closed = true;
+        self.changed.notify_all();
+    }
+}
+
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.lock();
+        fmt.debug_struct("Scheduler")
+            .field("tenants", &state.tenants.len())
+            .field(
+                "queued",
+                &state
+                    .tenants
+                    .iter()
+                    .map(|tenant| tenant.jobs.len())
+                    .sum::<usize>(),
+            )
+            .field("closed", &state.closed)
+            .finish()
+    }
+}
+
+/// Least-recently-used cache of decoded data
+#[derive(Debug)]
+struct DecodeCache {
+    capacity: usize,
+    entries: VecDeque<(Vec<u8>, Array<f32, EbccDim>)>,
+}
+
+impl DecodeCache {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::new(),
+        }
+    }
+
+    fn get(
+        &mut self,
+        compressed_data: &[u8],
+        shape: (usize, usize, usize),
+    ) -> Option<Array<f32, EbccDim>> {
+        let index = self.entries.iter().position(|(compressed, decompressed)| {
+            compressed.as_slice() == compressed_data && decompressed.dim() == shape
+        })?;
+
+        let entry = self.entries.remove(index)?;
+        let decompressed_data = entry.1.clone();
+        self.entries.push_back(entry);
+
+        Some(decompressed_data)
+    }
+
+    fn insert(&mut self, compressed_data: Vec<u8>, decompressed_data: &Array<f32, EbccDim>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries
+            .push_back((compressed_data, decompressed_data.clone()));
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::{ebcc_encode, testdata};
+
+    #[test]
+    fn test_compression_service() -> EBCCResult<()> {
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let service = EbccCompressionService::new(NonZeroUsize::MIN.saturating_add(1))
+            .with_profile("t2m", config.clone())?
+            .with_decode_cache(2);
+
+        let data = testdata::temperature((2, 32, 48));
+        let jobs = (0..4)
+            .map(|_| service.submit_encode(data.clone(), "t2m"))
+            .collect::<EBCCResult<Vec<_>>>()?;
+
+        let expected = ebcc_encode(data.view(), &config)?;
+        for job in jobs {
+            assert_eq!(job.wait()?, expected);
+        }
+
+        let decoded = service.submit_decode(expected.clone(), data.dim()).wait()?;
+        // the second decode is served from the cache
+        let cached = service.submit_decode(expected, data.dim());
+        assert!(matches!(cached.try_wait(), Some(Ok(cached)) if cached == decoded));
+
+        assert!(matches!(
+            service.submit_encode(data, "tp"),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+        assert!(service
+            .submit_decode(vec![0; 4], (2, 32, 48))
+            .wait()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_greedy_tenant() -> EBCCResult<()> {
+        let scheduler = Scheduler::new();
+        let greedy = scheduler.add_tenant(Some(NonZeroUsize::MIN));
+        let modest = scheduler.add_tenant(None);
+
+        let (sender, receiver) = mpsc::channel();
+        for (tenant, job) in [
+            (greedy, 0),
+            (greedy, 1),
+            (greedy, 2),
+            (modest, 3),
+            (modest, 4),
+        ] {
+            let sender = sender.clone();
+            scheduler.push(
+                tenant,
+                Box::new(move |_: &mut Workspace| {
+                    let _ = sender.send(job);
+                }),
+            );
+        }
+
+        // the capped greedy tenant does not hold back the other tenant
+        let run = |expected_tenant| {
+            let (tenant, job) = scheduler.next()?;
+            job(&mut Workspace::default());
+            (tenant == expected_tenant).then_some(tenant)
+        };
+        let first = run(greedy);
+        assert_eq!(first, Some(greedy));
+        assert_eq!(run(modest), Some(modest));
+        assert_eq!(run(modest), Some(modest));
+        if let Some(first) = first {
+            scheduler.finish(first);
+        }
+        assert_eq!(run(greedy), Some(greedy));
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), [0, 3, 4, 1]);
+
+        // a service whose workers are all busy with a greedy tenant's queue
+        //  still runs the jobs of another tenant
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let service = EbccCompressionService::new(NonZeroUsize::MIN.saturating_add(1))
+            .with_profile(
+                "greedy",
+                config
+                    .clone()
+                    .with_limits(EBCCLimits::new().with_max_threads(NonZeroUsize::MIN)),
+            )?
+            .with_profile("modest", config.clone())?;
+        let data = testdata::temperature((4, 64, 64));
+        let greedy_jobs = (0..16)
+            .map(|_| service.submit_encode(data.clone(), "greedy"))
+            .collect::<EBCCResult<Vec<_>>>()?;
+        let modest_job = service.submit_encode(data.clone(), "modest")?;
+
+        assert_eq!(modest_job.wait()?, ebcc_encode(data.view(), &config)?);
+        assert!(greedy_jobs
+            .last()
+            .is_some_and(|job| job.try_wait().is_none()));
+        for job in greedy_jobs {
+            job.wait()?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_limits() -> EBCCResult<()> {
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let service = EbccCompressionService::new(NonZeroUsize::MIN.saturating_add(3))
+            .with_profile(
+                "small",
+                config
+                    .clone()
+                    .with_limits(EBCCLimits::new().with_max_input_bytes(1024)),
+            )?
+            .with_profile(
+                "serial",
+                config
+                    .clone()
+                    .with_limits(EBCCLimits::new().with_max_threads(NonZeroUsize::MIN)),
+            )?
+            .with_profile(
+                "impatient",
+                config
+                    .clone()
+                    .with_limits(EBCCLimits::new().with_timeout(Duration::ZERO)),
+            )?
+            .with_decode_limits(EBCCLimits::new().with_max_output_bytes(1024));
+
+        let data = testdata::temperature((2, 32, 48));
+        assert!(matches!(
+            service.submit_encode(data.clone(), "small")?.wait(),
+            Err(EBCCError::InputTooLarge { .. })
+        ));
+        assert!(matches!(
+            service.submit_encode(data.clone(), "impatient")?.wait(),
+            Err(EBCCError::TimedOut { .. })
+        ));
+
+        let expected = ebcc_encode(data.view(), &config)?;
+        let jobs = (0..4)
+            .map(|_| service.submit_encode(data.clone(), "serial"))
+            .collect::<EBCCResult<Vec<_>>>()?;
+        for job in jobs {
+            assert_eq!(job.wait()?, expected);
+        }
+
+        assert!(matches!(
+            service.submit_decode(expected, data.dim()).wait(),
+            Err(EBCCError::OutputTooLarge { .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! Size conversions that are checked on all target pointer widths.
+//!
+//! The EBCC formats store sizes as `u64`, while the EBCC C library and Rust
+//! allocations use `size_t` and `usize`, which are only 32 bits wide on
+//! targets such as wasm32 or armv7. Sizes that do not fit are rejected
+//! instead of being truncated.
+
+use alloc::format;
+
+use crate::error::{EBCCError, EBCCResult};
+
+/// Convert a size that is stored as `u64` into a `usize`.
+pub fn u64_to_usize(value: u64) -> EBCCResult<usize> {
+    usize::try_from(value).map_err(|_| EBCCError::UsizeOverflow { value })
+}
+
+/// Convert a `usize` size into the `u64` that is stored.
+pub fn usize_to_u64(value: usize) -> EBCCResult<u64> {
+    u64::try_from(value)
+        .map_err(|_| EBCCError::InvalidInput(format!("Size {value} does not fit into u64")))
+}
+
+/// Number of elements of `f32` data of the given `shape`, which must fit
+/// into a single allocation, i.e. take up at most [`isize::MAX`] bytes.
+pub fn data_len(shape: (usize, usize, usize)) -> EBCCResult<usize> {
+    let (frames, height, width) = shape;
+
+    frames
+        .checked_mul(height)
+        .and_then(|elements| elements.checked_mul(width))
+        .filter(|&elements| elements <= isize::MAX.unsigned_abs() / size_of::<f32>())
+        .ok_or_else(|| EBCCError::ShapeTooLarge {
+            shape: shape.into(),
+        })
+}
+
+/// Check that a flat slice of `len` elements has the given `shape`, which
+/// must fit into a single allocation.
+pub fn check_slice_len(len: usize, shape: (usize, usize, usize)) -> EBCCResult<()> {
+    if data_len(shape)? != len {
+        return Err(EBCCError::SliceLenMismatch {
+            shape: shape.into(),
+            len,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_len_near_limit() {
+        let max_elements = isize::MAX.unsigned_abs() / size_of::<f32>();
+
+        assert_eq!(data_len((1, 1, max_elements)).ok(), Some(max_elements));
+        assert!(matches!(
+            data_len((1, 1, max_elements + 1)),
+            Err(EBCCError::ShapeTooLarge { .. })
+        ));
+        assert!(matches!(
+            data_len((2, 1, max_elements / 2 + 1)),
+            Err(EBCCError::ShapeTooLarge { .. })
+        ));
+        assert!(matches!(
+            data_len((usize::MAX, 2, 1)),
+            Err(EBCCError::ShapeTooLarge { .. })
+        ));
+
+        // the largest shape that EBCC supports overflows 32-bit targets
+        let largest = (
+            1,
+            ebcc_sys::EBCC_MAX_INTERNAL_IMAGE_DIM,
+            ebcc_sys::EBCC_MAX_INTERNAL_IMAGE_DIM,
+        );
+        assert_eq!(data_len(largest).is_ok(), usize::BITS > 32);
+    }
+
+    #[test]
+    fn test_u64_to_usize() {
+        assert_eq!(u64_to_usize(42).ok(), Some(42));
+        assert_eq!(u64_to_usize(1 << 32).is_ok(), usize::BITS > 32);
+        assert_eq!(u64_to_usize(u64::MAX).is_ok(), usize::BITS >= 64);
+
+        #[cfg(target_pointer_width = "32")]
+        assert!(matches!(
+            u64_to_usize(1 << 32),
+            Err(EBCCError::UsizeOverflow { value }) if value == 1 << 32
+        ));
+    }
+}
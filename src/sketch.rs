@@ -0,0 +1,492 @@
+//! Quantile sketches of every frame that are stored next to the compressed
+//! data.
+//!
+//! A quantile sketch records the percentiles of the original data of a frame
+//! at encode time, see [`EBCCConfig::with_quantile_sketch`]. The sketches
+//! can be read with [`ebcc_inspect`] without decoding anything, such that
+//! percentile queries, e.g. the 99th percentile of the wind speed over a
+//! month, can be approximated by [merging][EBCCQuantileSketch::merge] the
+//! sketches of all frames.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_SKETCH_MAGIC`], the format version as `u32`, the number
+//!   of frames, the frame height, the frame width, and the number of
+//!   quantiles per frame as `u64`s
+//! - the sketches: the quantiles of every frame as `f32`s
+//! - the inner EBCC payload
+
+use std::io::Cursor;
+
+use ndarray::{ArrayView, Axis};
+
+use crate::adaptive::{is_ebcc_tiled, tiled_config_fingerprint};
+use crate::cbuffer::CBuffer;
+use crate::codec::{ebcc_decode_nested, ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::EBCCConfig;
+use crate::container::{is_ebcc_container, EbccContainer};
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+use crate::limits::EBCCLimits;
+use crate::reader::Format;
+use crate::size::{u64_to_usize, usize_to_u64};
+use crate::stream::{is_ebcc_stream, read_stream_header, EBCC_STREAM_MAGIC};
+
+/// Magic bytes at the start of every quantile-sketched EBCC payload.
+pub const EBCC_SKETCH_MAGIC: &[u8; 8] = b"EBCCQSKT";
+
+/// Version of the quantile-sketched EBCC payload format.
+const EBCC_SKETCH_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC quantile sketch data");
+
+/// Number of quantiles per sketch, i.e. the percentiles `0..=100`
+pub const SKETCH_QUANTILES: usize = 101;
+
+/// Length of the quantile-sketched payload header
+pub const EBCC_SKETCH_HEADER_LEN: usize = 8 + 4 + 4 * 8;
+
+/// Percentiles of the original data of one frame, see
+/// [`EBCCConfig::with_quantile_sketch`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EBCCQuantileSketch {
+    quantiles: Vec<f32>,
+}
+
+impl EBCCQuantileSketch {
+    /// Compute the sketch of the `values`, which are sorted in place, or
+    /// [`None`] if there are no values.
+    fn from_values(values: &mut [f32]) -> Option<Self> {
+        values.sort_unstable_by(f32::total_cmp);
+
+        let quantiles = (0..SKETCH_QUANTILES)
+            .map(|i| {
+                #[expect(clippy::cast_precision_loss)]
+                let rank = (i as f64) / ((SKETCH_QUANTILES - 1) as f64);
+                interpolate(values, rank)
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self { quantiles })
+    }
+
+    /// The percentiles `0..=100` of the data, in ascending order.
+    #[must_use]
+    pub fn quantiles(&self) -> &[f32] {
+        &self.quantiles
+    }
+
+    /// Approximate the `q`-quantile of the data, with `q` in `[0, 1]`, by
+    /// interpolating between the recorded percentiles.
+    #[must_use]
+    pub fn quantile(&self, q: f64) -> f32 {
+        interpolate(&self.quantiles, q.clamp(0.0, 1.0)).unwrap_or(f32::NAN)
+    }
+
+    /// Merge the `sketches` of several frames of the same size into an
+    /// approximate sketch of all of their data, or [`None`] if there are no
+    /// sketches.
+    #[must_use]
+    pub fn merge<'a>(sketches: impl IntoIterator<Item = &'a Self>) -> Option<Self> {
+        let mut quantiles = sketches
+            .into_iter()
+            .flat_map(|sketch| sketch.quantiles.iter().copied())
+            .collect::<Vec<_>>();
+
+        Self::from_values(&mut quantiles)
+    }
+}
+
+/// Information about EBCC compressed data that is available without
+/// decoding it, see [`ebcc_inspect`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct EBCCInspection {
+    /// The header of the payload, or [`None`] for legacy headerless payloads
+    /// and EBCC frame streams
+    pub header: Option<EBCCHeader>,
+    /// The [fingerprint][EBCCConfig::fingerprint] of the configuration that
+    /// the data was compressed with, which is recorded in the header of
+    /// payloads, of EBCC frame streams, and of
+    /// [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] data, and which
+    /// is shared by all frames of an EBCC container, or [`None`] if it is not
+    /// recorded or the frames of a container differ
+    pub config_fingerprint: Option<u64>,
+    /// The [fingerprint][EBCCConfig::fingerprint] of the configuration of
+    /// every frame of an [EBCC container][crate::container] that has not been
+    /// deleted, or [`None`] for frames that do not record it, which is empty
+    /// for all other formats
+    pub frame_config_fingerprints: Vec<Option<u64>>,
+    /// The quantile sketch of every frame, which is empty unless the data
+    /// was compressed [`with_quantile_sketch`][EBCCConfig::with_quantile_sketch]
+    pub quantile_sketches: Vec<EBCCQuantileSketch>,
+}
+
+impl EBCCInspection {
+    /// Check whether the data was compressed with a configuration that has
+    /// the same [fingerprint][EBCCConfig::fingerprint] as the `config`, e.g.
+    /// to audit that all files of an archive follow a compression policy.
+    ///
+    /// The frames of an EBCC container only match if every frame was
+    /// compressed with a configuration of the same fingerprint.
+    ///
+    /// Returns [`None`] if no fingerprint is recorded, e.g. for legacy
+    /// headerless payloads, or for empty containers.
+    #[must_use]
+    pub fn matches_config(&self, config: &EBCCConfig) -> Option<bool> {
+        let fingerprint = config.fingerprint();
+
+        if self.frame_config_fingerprints.is_empty() {
+            return self
+                .config_fingerprint
+                .map(|recorded| recorded == fingerprint);
+        }
+
+        self.frame_config_fingerprints
+            .iter()
+            .copied()
+            .collect::<Option<Vec<_>>>()
+            .map(|recorded| recorded.iter().all(|recorded| *recorded == fingerprint))
+    }
+}
+
+/// Inspect an [`ebcc_encode`][crate::ebcc_encode] payload, an EBCC frame
+/// stream, an [EBCC container][crate::container], or
+/// [`ebcc_encode_adaptive`][crate::ebcc_encode_adaptive] data without
+/// decoding it.
+///
+/// The records of every frame of a container are read to find their
+/// configuration fingerprints.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the header or the quantile sketches are
+///   truncated or corrupted
+/// - [`EBCCError::ChecksumMismatch`] if the checksum of a container frame or
+///   of a tile of adaptive data does not match
+/// - [`EBCCError::DecompressionError`] if the header, stream, or quantile
+///   sketch version is not supported
+/// - [`EBCCError::ShapeTooLarge`] if the shape of the quantile sketches
+///   exceeds the default [`EBCCLimits`]
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_encode, ebcc_inspect, EBCCConfig, EBCCQuantileSketch};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((2, 32, 32), |(t, y, x)| (t * 1024 + y * 32 + x) as f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1).with_quantile_sketch();
+///
+/// let compressed = ebcc_encode(data.view(), &config)?;
+/// let inspection = ebcc_inspect(&compressed)?;
+///
+/// let p99 = EBCCQuantileSketch::merge(&inspection.quantile_sketches).map(|sketch| sketch.quantile(0.99));
+/// assert!(p99.is_some_and(|p99| (p99 - 2027.0).abs() < 10.0));
+/// assert_eq!(inspection.matches_config(&config), Some(true));
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_inspect(compressed_data: &[u8]) -> EBCCResult<EBCCInspection> {
+    if is_ebcc_stream(compressed_data) {
+        let mut stream = compressed_data
+            .get(EBCC_STREAM_MAGIC.len()..)
+            .unwrap_or_default();
+        return Ok(EBCCInspection {
+            header: None,
+            config_fingerprint: read_stream_header(&mut stream)?.config_fingerprint(),
+            frame_config_fingerprints: Vec::new(),
+            quantile_sketches: Vec::new(),
+        });
+    }
+
+    if is_ebcc_tiled(compressed_data) {
+        return Ok(EBCCInspection {
+            header: None,
+            config_fingerprint: tiled_config_fingerprint(compressed_data)?,
+            frame_config_fingerprints: Vec::new(),
+            quantile_sketches: Vec::new(),
+        });
+    }
+
+    if is_ebcc_container(compressed_data) {
+        let frame_config_fingerprints =
+            EbccContainer::open(Cursor::new(compressed_data))?.frame_config_fingerprints()?;
+        let config_fingerprint =
+            frame_config_fingerprints
+                .first()
+                .copied()
+                .flatten()
+                .filter(|first| {
+                    frame_config_fingerprints
+                        .iter()
+                        .all(|fingerprint| *fingerprint == Some(*first))
+                });
+
+        return Ok(EBCCInspection {
+            header: None,
+            config_fingerprint,
+            frame_config_fingerprints,
+            quantile_sketches: Vec::new(),
+        });
+    }
+
+    let header = EBCCHeader::parse(compressed_data)?;
+    let payload = header.map_or(compressed_data, |header| {
+        compressed_data
+            .get(header.encoded_len()..)
+            .unwrap_or_default()
+    });
+
+    let quantile_sketches = if is_sketched(payload) {
+        read_sketches(payload)?.0
+    } else {
+        Vec::new()
+    };
+
+    Ok(EBCCInspection {
+        header,
+        config_fingerprint: header.and_then(|header| header.recorded_config_fingerprint()),
+        frame_config_fingerprints: Vec::new(),
+        quantile_sketches,
+    })
+}
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_SKETCH_MAGIC`].
+pub fn is_sketched(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_SKETCH_MAGIC)
+}
+
+/// Encode a 3D data array into a quantile-sketched payload.
+pub fn sketch_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let mut sketches = Vec::with_capacity(data.len_of(Axis(0)) * SKETCH_QUANTILES * 4);
+    let mut values = Vec::new();
+    for frame in data.axis_iter(Axis(0)) {
+        values.clear();
+        values.extend(frame.iter().copied());
+        let Some(sketch) = EBCCQuantileSketch::from_values(&mut values) else {
+            return Err(EBCCError::EmptyDimension);
+        };
+        sketches.extend(sketch.quantiles.iter().flat_map(|q| q.to_le_bytes()));
+    }
+
+    let inner_config = EBCCConfig {
+        quantile_sketch: false,
+        ..config.clone()
+    };
+    let inner = ebcc_encode_c_buffer_with_scratch(data, &inner_config, scratch)?;
+    let inner = inner.as_slice();
+
+    let mut compressed_data =
+        Vec::with_capacity(EBCC_SKETCH_HEADER_LEN + sketches.len() + inner.len());
+    compressed_data.extend_from_slice(EBCC_SKETCH_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_SKETCH_VERSION.to_le_bytes());
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(&usize_to_u64(SKETCH_QUANTILES)?.to_le_bytes());
+    compressed_data.extend_from_slice(&sketches);
+    compressed_data.extend_from_slice(inner);
+
+    Ok(compressed_data)
+}
+
+/// Decode a quantile-sketched payload of the expected `shape`, which may be
+/// modified during decoding, into the flattened 3D data array.
+pub fn sketch_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+) -> EBCCResult<CBuffer<f32>> {
+    let (_, shape, header_len) = read_sketches(compressed_data)?;
+
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+
+    let Some(inner) = compressed_data.get_mut(header_len..) else {
+        return Err(FORMAT.truncated());
+    };
+
+    ebcc_decode_nested(inner, expected_shape.into(), depth + 1)
+}
+
+/// Read the sketches of a quantile-sketched payload, and return them with
+/// the shape of the data and the length of the header and sketches.
+fn read_sketches(
+    compressed_data: &[u8],
+) -> EBCCResult<(Vec<EBCCQuantileSketch>, [usize; 3], usize)> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_SKETCH_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_SKETCH_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC quantile sketch version: {version}").into(),
+        ));
+    }
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    EBCCLimits::new().check_shape(shape.into())?;
+
+    let quantiles = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    if quantiles != SKETCH_QUANTILES {
+        return Err(FORMAT.corrupted());
+    }
+
+    let [frames, _, _] = shape;
+    let sketches_len = frames * SKETCH_QUANTILES * 4;
+    let Some((sketches, _)) = reader.split_at_checked(sketches_len) else {
+        return Err(FORMAT.truncated());
+    };
+
+    let sketches = sketches
+        .chunks_exact(SKETCH_QUANTILES * 4)
+        .map(|sketch| EBCCQuantileSketch {
+            quantiles: sketch
+                .chunks_exact(4)
+                .map(|q| f32::from_le_bytes(q.try_into().unwrap_or_default()))
+                .collect(),
+        })
+        .collect();
+
+    Ok((sketches, shape, EBCC_SKETCH_HEADER_LEN + sketches_len))
+}
+
+/// Linearly interpolate the `rank`, in `[0, 1]`, within the sorted `values`.
+fn interpolate(values: &[f32], rank: f64) -> Option<f32> {
+    let (&last, _) = values.split_last()?;
+
+    #[expect(clippy::cast_precision_loss)]
+    let position = rank * ((values.len() - 1) as f64);
+    #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let lower = position.floor() as usize;
+
+    let below = values.get(lower).copied().unwrap_or(last);
+    let above = values.get(lower + 1).copied().unwrap_or(below);
+
+    #[expect(clippy::cast_possible_truncation)]
+    let fraction = (position - position.floor()) as f32;
+
+    Some((above - below).mul_add(fraction, below))
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::container::EbccContainerWriter;
+    use crate::{
+        ebcc_decode_into, ebcc_encode, ebcc_encode_adaptive, testdata, verify::check_error_bound,
+    };
+
+    #[test]
+    fn test_quantile_sketch() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1).with_quantile_sketch();
+
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        check_error_bound(data.view(), decompressed.view(), &config)?;
+
+        let inspection = ebcc_inspect(&compressed)?;
+        assert_eq!(inspection.quantile_sketches.len(), 3);
+
+        for (frame, sketch) in data.axis_iter(Axis(0)).zip(&inspection.quantile_sketches) {
+            let min = frame.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = frame.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            assert_eq!(sketch.quantile(0.0).to_bits(), min.to_bits());
+            assert_eq!(sketch.quantile(1.0).to_bits(), max.to_bits());
+            assert!(sketch.quantiles().windows(2).all(|q| q.first() <= q.last()));
+        }
+
+        let mut values = data.iter().copied().collect::<Vec<_>>();
+        let exact = EBCCQuantileSketch::from_values(&mut values).map(|sketch| sketch.quantile(0.9));
+        let merged = EBCCQuantileSketch::merge(&inspection.quantile_sketches)
+            .map(|sketch| sketch.quantile(0.9));
+        assert!(
+            matches!((exact, merged), (Some(exact), Some(merged)) if (exact - merged).abs() < 0.5)
+        );
+
+        // payloads without sketches are still inspected
+        let inspection = ebcc_inspect(&ebcc_encode(
+            data.view(),
+            &EBCCConfig::max_absolute_error_bounded(0.1),
+        )?)?;
+        assert!(inspection.header.is_some());
+        assert!(inspection.quantile_sketches.is_empty());
+        assert_eq!(
+            inspection.matches_config(&EBCCConfig::max_absolute_error_bounded(0.1)),
+            Some(true)
+        );
+        assert_eq!(
+            inspection.matches_config(&EBCCConfig::max_absolute_error_bounded(0.2)),
+            Some(false)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_container_and_tiled() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+        let other = EBCCConfig::max_absolute_error_bounded(0.2);
+
+        let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (32, 48))?;
+        for frame in data.outer_iter() {
+            writer.push_frame(frame)?;
+        }
+        let inspection = ebcc_inspect(&writer.finish()?)?;
+        assert_eq!(inspection.config_fingerprint, Some(config.fingerprint()));
+        assert_eq!(
+            inspection.frame_config_fingerprints,
+            [Some(config.fingerprint()); 3]
+        );
+        assert_eq!(inspection.matches_config(&config), Some(true));
+        assert_eq!(inspection.matches_config(&other), Some(false));
+
+        // the frames of a container may be compressed with different configs
+        let mut writer = EbccContainerWriter::new(Vec::new(), config.clone(), (32, 48))?;
+        for (frame, config) in data.outer_iter().zip([&config, &other, &config]) {
+            writer.push_frame_with_config(frame, config)?;
+        }
+        let inspection = ebcc_inspect(&writer.finish()?)?;
+        assert_eq!(inspection.config_fingerprint, None);
+        assert_eq!(inspection.matches_config(&config), Some(false));
+        assert_eq!(inspection.matches_config(&other), Some(false));
+
+        let tiled = ebcc_encode_adaptive(data.view(), &config, (32, 32))?;
+        let inspection = ebcc_inspect(&tiled)?;
+        assert_eq!(inspection.config_fingerprint, Some(config.fingerprint()));
+        assert!(inspection.frame_config_fingerprints.is_empty());
+        assert_eq!(inspection.matches_config(&config), Some(true));
+        assert_eq!(inspection.matches_config(&other), Some(false));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+//! Compression bounded by the structural similarity (SSIM) of the decoded
+//! data.
+
+use ndarray::{s, Array, ArrayView, ArrayView2};
+
+use crate::codec::{ebcc_decode_into, ebcc_encode, EbccDim};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::finite::validate_only_finite_data;
+use crate::params::validate_regular_ebcc_shape;
+
+/// Side length of the square windows over which the SSIM is computed
+const SSIM_WINDOW: usize = 8;
+
+/// Stride between neighbouring SSIM windows
+const SSIM_STRIDE: usize = 4;
+
+/// Smallest base compression ratio that [`ebcc_encode_ssim_bounded`] tries
+const MIN_SSIM_BASE_CR: f64 = 1.0;
+
+/// Number of bisection steps over the base compression ratio
+const SSIM_BISECTION_STEPS: usize = 12;
+
+/// Encode a 3D data array with EBCC, choosing the largest base compression
+/// ratio for which the decoded data still has a mean structural similarity
+/// (SSIM) of at least `min_ssim` with the original `data`.
+///
+/// Pointwise error bounds are a poor fit for archives that are only ever
+/// visualised, where perceptual fidelity matters more than the numeric error
+/// of each value. This function instead searches the base compression ratio
+/// between 1 and the configured [`base_cr`][EBCCConfig::base_cr], which acts
+/// as the upper bound, by encoding and decoding the `data` repeatedly. All
+/// other settings of the `config`, including its
+/// [`residual_compression_type`][EBCCConfig::residual_compression_type], are
+/// kept, so an error-bounded residual still holds on top of the SSIM bound.
+/// Combine the search with
+/// [`EBCCConfig::jpeg2000_only`] for a purely perceptual bound.
+///
+/// The SSIM is computed with [`ebcc_ssim`]. The compressed data is decoded
+/// with [`ebcc_decode_into`][crate::ebcc_decode_into].
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidConfig`] if `min_ssim` is not in `(0, 1]`
+/// - [`EBCCError::CompressionError`] if not even the smallest base
+///   compression ratio reaches `min_ssim`
+/// - all errors that [`ebcc_encode`][crate::ebcc_encode] and
+///   [`ebcc_decode_into`][crate::ebcc_decode_into] can return
+pub fn ebcc_encode_ssim_bounded(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    min_ssim: f64,
+) -> EBCCResult<Vec<u8>> {
+    if !(min_ssim > 0.0 && min_ssim <= 1.0) {
+        return Err(EBCCError::InvalidConfig(format!(
+            "Minimum SSIM {min_ssim} must be in (0, 1]",
+        )));
+    }
+
+    validate_regular_ebcc_shape(data.dim())?;
+    config.validate()?;
+    if config.check_finite {
+        validate_only_finite_data(&data)?;
+    }
+    // the data has already been checked for non-finite values
+    let config = config.clone().skip_finite_check();
+
+    let mut decompressed = Array::zeros(data.dim());
+    let mut encode = |base_cr: f64| -> EBCCResult<(Vec<u8>, f64)> {
+        #[expect(clippy::cast_possible_truncation)]
+        let compressed = ebcc_encode(data, &config.clone().with_base_cr(base_cr as f32))?;
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        let ssim = ebcc_ssim(data, decompressed.view())?;
+        Ok((compressed, ssim))
+    };
+
+    let max_base_cr = f64::from(config.base_cr).max(MIN_SSIM_BASE_CR);
+    let (compressed, ssim) = encode(max_base_cr)?;
+    if ssim >= min_ssim {
+        return Ok(compressed);
+    }
+
+    let (mut best, ssim) = encode(MIN_SSIM_BASE_CR)?;
+    if ssim < min_ssim {
+        return Err(EBCCError::CompressionError(format!(
+            "Minimum SSIM {min_ssim} cannot be reached, the smallest base compression ratio {MIN_SSIM_BASE_CR} only reaches {ssim}",
+        ).into()));
+    }
+
+    // bisect in log space since the SSIM changes with the order of magnitude
+    //  of the base compression ratio
+    let (mut lo, mut hi) = (MIN_SSIM_BASE_CR.ln(), max_base_cr.ln());
+    for _ in 0..SSIM_BISECTION_STEPS {
+        let mid = (lo + hi) / 2.0;
+        let (compressed, ssim) = encode(mid.exp())?;
+        if ssim >= min_ssim {
+            best = compressed;
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    Ok(best)
+}
+
+/// Compute the mean structural similarity (SSIM) between the `original` and
+/// the `decompressed` 3D data arrays.
+///
+/// The SSIM is computed for each frame over 8x8 windows with a stride of 4,
+/// using the value range of the `original` data as its dynamic range, and is
+/// averaged over all windows and frames. Frames that are smaller than a
+/// window are treated as a single window. An SSIM of 1 means that the data is
+/// structurally identical.
+///
+/// # Errors
+///
+/// - [`EBCCError::ShapeMismatch`] if the `original` and `decompressed` data
+///   have different shapes
+/// - [`EBCCError::EmptyDimension`] if the data is empty
+#[expect(clippy::cast_precision_loss)]
+pub fn ebcc_ssim(
+    original: ArrayView<f32, EbccDim>,
+    decompressed: ArrayView<f32, EbccDim>,
+) -> EBCCResult<f64> {
+    if original.dim() != decompressed.dim() {
+        return Err(EBCCError::ShapeMismatch {
+            expected: original.dim().into(),
+            actual: decompressed.dim().into(),
+        });
+    }
+    if original.is_empty() {
+        return Err(EBCCError::EmptyDimension);
+    }
+
+    let (min, max) = original
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &x| {
+            (min.min(x), max.max(x))
+        });
+    let range = f64::from(max) - f64::from(min);
+    // constant data still has a well-defined SSIM
+    let range = if range > 0.0 { range } else { 1.0 };
+    let c1 = (0.01 * range).powi(2);
+    let c2 = (0.03 * range).powi(2);
+
+    let (_, height, width) = original.dim();
+    let rows = window_starts(height);
+    let cols = window_starts(width);
+
+    let mut sum = 0.0;
+    let mut windows = 0_usize;
+    for (original, decompressed) in original.outer_iter().zip(decompressed.outer_iter()) {
+        for &y in &rows {
+            for &x in &cols {
+                let window = s![
+                    y..(y + SSIM_WINDOW).min(height),
+                    x..(x + SSIM_WINDOW).min(width)
+                ];
+                sum += window_ssim(original.slice(window), decompressed.slice(window), c1, c2);
+                windows += 1;
+            }
+        }
+    }
+
+    Ok(sum / windows as f64)
+}
+
+fn window_starts(len: usize) -> Vec<usize> {
+    if len <= SSIM_WINDOW {
+        return vec![0];
+    }
+
+    let mut starts = (0..=(len - SSIM_WINDOW))
+        .step_by(SSIM_STRIDE)
+        .collect::<Vec<_>>();
+    // the last window always reaches the edge
+    if starts.last() != Some(&(len - SSIM_WINDOW)) {
+        starts.push(len - SSIM_WINDOW);
+    }
+    starts
+}
+
+#[expect(clippy::cast_precision_loss)]
+fn window_ssim(a: ArrayView2<f32>, b: ArrayView2<f32>, c1: f64, c2: f64) -> f64 {
+    let n = a.len() as f64;
+
+    let mean_a = a.iter().map(|&x| f64::from(x)).sum::<f64>() / n;
+    let mean_b = b.iter().map(|&x| f64::from(x)).sum::<f64>() / n;
+
+    let (mut var_a, mut var_b, mut cov) = (0.0, 0.0, 0.0);
+    for (&x, &y) in a.iter().zip(b.iter()) {
+        let (dx, dy) = (f64::from(x) - mean_a, f64::from(y) - mean_b);
+        var_a = dx.mul_add(dx, var_a);
+        var_b = dy.mul_add(dy, var_b);
+        cov = dx.mul_add(dy, cov);
+    }
+    let (var_a, var_b, cov) = (var_a / n, var_b / n, cov / n);
+
+    ((2.0 * mean_a).mul_add(mean_b, c1) * 2.0_f64.mul_add(cov, c2))
+        / (mean_a.mul_add(mean_a, mean_b.mul_add(mean_b, c1)) * (var_a + var_b + c2))
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::testdata;
+
+    #[test]
+    fn test_window_starts() {
+        assert_eq!(window_starts(4), [0]);
+        assert_eq!(window_starts(8), [0]);
+        assert_eq!(window_starts(16), [0, 4, 8]);
+        assert_eq!(window_starts(18), [0, 4, 8, 10]);
+    }
+
+    #[test]
+    fn test_ssim() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        assert!((ebcc_ssim(data.view(), data.view())? - 1.0).abs() < 1e-12);
+
+        let mut noisy = data.clone();
+        noisy.zip_mut_with(&testdata::noise((2, 32, 48), 5.0, 7), |x, n| *x += n);
+        let mut noisier = data.clone();
+        noisier.zip_mut_with(&testdata::noise((2, 32, 48), 20.0, 7), |x, n| *x += n);
+
+        let ssim = ebcc_ssim(data.view(), noisy.view())?;
+        assert!(ssim < 1.0);
+        assert!(ebcc_ssim(data.view(), noisier.view())? < ssim);
+
+        assert!(matches!(
+            ebcc_ssim(data.view(), data.slice(s![.., .., ..32])),
+            Err(EBCCError::ShapeMismatch { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ssim_bounded_roundtrip() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 64, 64));
+        let config = EBCCConfig::jpeg2000_only(200.0);
+
+        let compressed = ebcc_encode_ssim_bounded(data.view(), &config, 0.95)?;
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        assert!(ebcc_ssim(data.view(), decompressed.view())? >= 0.95);
+
+        assert!(matches!(
+            ebcc_encode_ssim_bounded(data.view(), &config, 0.0),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            ebcc_encode_ssim_bounded(data.view(), &config, f64::NAN),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        Ok(())
+    }
+}
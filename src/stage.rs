@@ -0,0 +1,368 @@
+//! Runtime registry of custom preprocessing stages.
+//!
+//! Organisations often need transforms that are specific to their data, e.g.
+//! a climatology subtraction, which are not built into EBCC. A custom
+//! [`EBCCStage`] is registered under a string ID with
+//! [`ebcc_register_stage`], and referenced by that ID with
+//! [`EBCCConfig::with_stage`]. The stage is applied to the data before
+//! encoding, and its ID is recorded in the payload, such that every consumer
+//! that has registered a stage under the same ID can decode the data, also
+//! inside of containers and streams.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_STAGE_MAGIC`], the format version as `u32`, the length of
+//!   the stage ID as `u16`, the UTF-8 bytes of the stage ID, and the number
+//!   of frames, the frame height, and the frame width as `u64`s
+//! - the inner EBCC payload of the staged data
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use ndarray::{Array, ArrayView, ArrayViewMut};
+
+use crate::codec::{ebcc_decode_nested, ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::verify::data_range;
+
+/// Magic bytes at the start of every EBCC payload with a custom stage.
+pub const EBCC_STAGE_MAGIC: &[u8; 8] = b"EBCCSTGE";
+
+/// Version of the custom stage EBCC payload format.
+const EBCC_STAGE_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC stage data");
+
+/// Registered custom stages by their ID
+static STAGES: RwLock<BTreeMap<String, Arc<dyn EBCCStage>>> = RwLock::new(BTreeMap::new());
+
+/// Custom invertible preprocessing stage, which is registered with
+/// [`ebcc_register_stage`].
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use std::sync::Arc;
+///
+/// use ebcc::{
+///     ebcc_decode_into, ebcc_encode, ebcc_register_stage, EBCCConfig, EBCCResult, EBCCStage,
+///     EbccDim,
+/// };
+/// use ndarray::{Array, ArrayViewMut};
+///
+/// /// Subtracts a constant climatological mean
+/// struct Anomaly(f32);
+///
+/// impl EBCCStage for Anomaly {
+///     fn forward(&self, mut data: ArrayViewMut<f32, EbccDim>) -> EBCCResult<()> {
+///         data.mapv_inplace(|x| x - self.0);
+///         Ok(())
+///     }
+///
+///     fn inverse(&self, mut data: ArrayViewMut<f32, EbccDim>) -> EBCCResult<()> {
+///         data.mapv_inplace(|x| x + self.0);
+///         Ok(())
+///     }
+/// }
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// ebcc_register_stage("org.example.anomaly", Arc::new(Anomaly(285.0)));
+///
+/// let data = Array::from_elem((1, 32, 32), 290.0_f32);
+/// let config = EBCCConfig::max_absolute_error_bounded(0.1).with_stage("org.example.anomaly");
+/// let compressed = ebcc_encode(data.view(), &config)?;
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_into(&compressed, decompressed.view_mut())?;
+/// assert!(decompressed.iter().all(|x| (x - 290.0).abs() <= 0.1));
+/// # Ok(())
+/// # }
+/// ```
+pub trait EBCCStage: Send + Sync {
+    /// Apply the stage in-place to the data before it is encoded.
+    ///
+    /// # Errors
+    ///
+    /// - any error that prevents the stage from being applied to the `data`
+    fn forward(&self, data: ArrayViewMut<f32, EbccDim>) -> EBCCResult<()>;
+
+    /// Invert the stage in-place on the decoded data.
+    ///
+    /// # Errors
+    ///
+    /// - any error that prevents the stage from being inverted on the `data`
+    fn inverse(&self, data: ArrayViewMut<f32, EbccDim>) -> EBCCResult<()>;
+
+    /// Map an absolute `error` bound of the original `data` into an absolute
+    /// error bound of the staged data, such that the `error` still holds
+    /// after the [`inverse`][Self::inverse].
+    ///
+    /// The `error` already leaves a margin for rounding the stage and its
+    /// inverse to `f32`. By default, the `error` is kept, which is only
+    /// correct for stages whose inverse does not amplify errors, e.g. shifts
+    /// and permutations. Stages that are not invertible up to the `f32`
+    /// rounding must tighten the bound accordingly.
+    fn staged_error_bound(&self, data: ArrayView<f32, EbccDim>, error: f32) -> f32 {
+        let _ = data;
+        error
+    }
+}
+
+/// Register the custom `stage` under the `id`, replacing and returning any
+/// stage that was previously registered under the same `id`.
+///
+/// Stages are registered for the whole process, such that all encoders and
+/// decoders can use them.
+pub fn ebcc_register_stage(
+    id: impl Into<String>,
+    stage: Arc<dyn EBCCStage>,
+) -> Option<Arc<dyn EBCCStage>> {
+    STAGES
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(id.into(), stage)
+}
+
+/// Unregister and return the custom stage with the `id`, if it exists.
+pub fn ebcc_unregister_stage(id: &str) -> Option<Arc<dyn EBCCStage>> {
+    STAGES
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(id)
+}
+
+/// The IDs of all registered custom stages, in sorted order.
+#[must_use]
+pub fn ebcc_registered_stages() -> Vec<String> {
+    STAGES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .keys()
+        .cloned()
+        .collect()
+}
+
+fn registered_stage(id: &str) -> EBCCResult<Arc<dyn EBCCStage>> {
+    STAGES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(id)
+        .cloned()
+        .ok_or_else(|| EBCCError::InvalidConfig(format!("EBCC stage {id:?} is not registered")))
+}
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_STAGE_MAGIC`].
+pub fn is_staged(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_STAGE_MAGIC)
+}
+
+/// Check that the custom stage `id` can be recorded in a payload.
+pub fn validate_stage_id(id: &str) -> EBCCResult<()> {
+    if id.is_empty() || u16::try_from(id.len()).is_err() {
+        return Err(EBCCError::InvalidConfig(format!(
+            "EBCC stage ID must have between 1 and {} bytes, got {}",
+            u16::MAX,
+            id.len(),
+        )));
+    }
+
+    Ok(())
+}
+
+/// Encode a 3D data array into a payload with the custom
+/// [`EBCCConfig::stage`] of the `config`, which must be set and registered.
+pub fn stage_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let Some(id) = &config.stage else {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "Staged encoding requires a custom stage",
+        )));
+    };
+    let Ok(id_len) = u16::try_from(id.len()) else {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "EBCC stage ID is too long",
+        )));
+    };
+    let stage = registered_stage(id)?;
+
+    let error = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => Some(error),
+        EBCCResidualType::RelativeError(error) => Some(data_range(data) * error),
+        EBCCResidualType::Jpeg2000Only => None,
+    };
+    let residual_compression_type = match error {
+        Some(error) => {
+            // the stage and its inverse are both rounded to f32, by at most
+            //  half an ulp of the largest value, which the error bound must cover
+            let max_abs = data.iter().fold(0.0_f32, |max, x| max.max(x.abs()));
+            let rounded_error = (max_abs * f32::EPSILON).mul_add(-2.0, error);
+            let staged_error = stage.staged_error_bound(data, rounded_error);
+            if !(staged_error.is_finite() && staged_error > 0.0) {
+                return Err(EBCCError::InvalidConfig(format!(
+                    "Error bound {error} is too tight for the EBCC stage {id:?}"
+                )));
+            }
+            EBCCResidualType::AbsoluteError(staged_error)
+        }
+        None => EBCCResidualType::Jpeg2000Only,
+    };
+
+    let mut staged = data.to_owned();
+    stage.forward(staged.view_mut())?;
+
+    let inner_config = EBCCConfig {
+        residual_compression_type,
+        stage: None,
+        expansion_guard: None,
+        ..config.clone()
+    };
+    let inner = ebcc_encode_c_buffer_with_scratch(staged.view(), &inner_config, scratch)?;
+    let inner = inner.as_slice();
+
+    let mut compressed_data = Vec::with_capacity(8 + 4 + 2 + id.len() + 3 * 8 + inner.len());
+    compressed_data.extend_from_slice(EBCC_STAGE_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_STAGE_VERSION.to_le_bytes());
+    compressed_data.extend_from_slice(&id_len.to_le_bytes());
+    compressed_data.extend_from_slice(id.as_bytes());
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(inner);
+
+    Ok(compressed_data)
+}
+
+/// Decode a payload with a custom stage of the expected `shape`, which may
+/// be modified during decoding, into the flattened 3D data array.
+pub fn stage_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_STAGE_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_STAGE_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC stage version: {version}").into(),
+        ));
+    }
+
+    let id_len = usize::from(u16::from_le_bytes(FORMAT.read_array(&mut reader)?));
+    let Some((id, rest)) = reader.split_at_checked(id_len) else {
+        return Err(FORMAT.truncated());
+    };
+    reader = rest;
+    let Ok(id) = std::str::from_utf8(id) else {
+        return Err(FORMAT.corrupted());
+    };
+    let stage = registered_stage(id).map_err(|_| {
+        EBCCError::DecompressionError(
+            format!("EBCC stage {id:?} must be registered to decode the data").into(),
+        )
+    })?;
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+    data_len(expected_shape.into())?;
+
+    let header_len = compressed_data.len() - reader.len();
+    let Some(inner) = compressed_data.get_mut(header_len..) else {
+        return Err(FORMAT.truncated());
+    };
+
+    let decompressed_data = ebcc_decode_nested(inner, expected_shape.into(), depth + 1)?
+        .as_slice()
+        .to_vec();
+    let Ok(mut decompressed_data) = Array::from_shape_vec(expected_shape, decompressed_data) else {
+        return Err(FORMAT.corrupted());
+    };
+    stage.inverse(decompressed_data.view_mut())?;
+
+    Ok(decompressed_data.into_iter().collect())
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, verify::check_error_bound};
+
+    /// Flips the data upside down
+    struct Flip;
+
+    impl EBCCStage for Flip {
+        fn forward(&self, mut data: ArrayViewMut<f32, EbccDim>) -> EBCCResult<()> {
+            data.invert_axis(ndarray::Axis(1));
+            let flipped = data.to_owned();
+            data.invert_axis(ndarray::Axis(1));
+            data.assign(&flipped);
+            Ok(())
+        }
+
+        fn inverse(&self, data: ArrayViewMut<f32, EbccDim>) -> EBCCResult<()> {
+            self.forward(data)
+        }
+    }
+
+    #[test]
+    fn test_custom_stage() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1).with_stage("test.flip");
+
+        assert!(matches!(
+            ebcc_encode(data.view(), &config),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        ebcc_register_stage("test.flip", Arc::new(Flip));
+        assert!(ebcc_registered_stages().contains(&String::from("test.flip")));
+
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        check_error_bound(data.view(), decompressed.view(), &config)?;
+
+        // the bound must leave room for the f32 rounding of the stage
+        let max_abs = data.iter().fold(0.0_f32, |max, x| max.max(x.abs()));
+        let tight =
+            EBCCConfig::max_absolute_error_bounded(max_abs * f32::EPSILON).with_stage("test.flip");
+        assert!(matches!(
+            ebcc_encode(data.view(), &tight),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        assert!(ebcc_unregister_stage("test.flip").is_some());
+        assert!(matches!(
+            ebcc_decode_into(&compressed, decompressed.view_mut()),
+            Err(EBCCError::DecompressionError(_))
+        ));
+
+        Ok(())
+    }
+}
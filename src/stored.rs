@@ -0,0 +1,415 @@
+//! Stored-raw EBCC payloads that keep the data losslessly.
+//!
+//! Incompressible data, e.g. white noise with a tight error bound, can make
+//! EBCC produce payloads that are larger than the data itself. Such data is
+//! stored raw instead, see [`EBCCExpansionGuard`][crate::EBCCExpansionGuard].
+//! Data that must be kept losslessly can also be stored explicitly with the
+//! [`EBCCBaseMode::Stored`][crate::EBCCBaseMode::Stored].
+//!
+//! The stored data can optionally be compressed losslessly with the zstd
+//! library that EBCC is linked with. Its bytes are first shuffled into byte
+//! planes, i.e. all first bytes of the `f32` values are followed by all
+//! second bytes and so on, which groups the slowly varying sign and exponent
+//! bytes together.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_STORED_MAGIC`], the format version as `u32`, the
+//!   [`EBCCStoredCompression`] as `u8` (`0` for none and `1` for zstd), which
+//!   is missing in version 1, and the number of frames, the frame height, and
+//!   the frame width as `u64`s
+//! - either the `f32` values in C order, or the zstd-compressed byte planes
+//!   of the `f32` values in C order
+
+use std::ffi::c_int;
+use std::fmt;
+
+#[cfg(feature = "decode")]
+use ebcc_sys::zstd::ZSTD_decompress;
+#[cfg(feature = "encode")]
+use ebcc_sys::zstd::{ZSTD_compress, ZSTD_compressBound};
+use ebcc_sys::zstd::{ZSTD_getErrorCode, ZSTD_getErrorName, ZSTD_isError};
+use ndarray::ArrayView;
+
+#[cfg(not(feature = "decode"))]
+use crate::codec::decode_unsupported;
+#[cfg(not(feature = "encode"))]
+use crate::codec::encode_unsupported;
+use crate::codec::EbccDim;
+use crate::config::EBCCStoredCompression;
+use crate::error::{EBCCError, EBCCFailure, EBCCFailureCause, EBCCResult};
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::sync::with_ebcc_call;
+use crate::version::static_c_str;
+
+/// Magic bytes at the start of every stored-raw EBCC payload.
+pub const EBCC_STORED_MAGIC: &[u8; 8] = b"EBCCRAWD";
+
+/// Version of the stored-raw EBCC payload format.
+///
+/// Version 2 added the compression byte. Payloads of version 1, which always
+/// store the `f32` values uncompressed, can still be decoded.
+const EBCC_STORED_VERSION: u32 = 2;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC stored-raw data");
+
+/// Length of the stored-raw payload header
+pub const EBCC_STORED_HEADER_LEN: usize = 8 + 4 + 1 + 3 * 8;
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZSTD: u8 = 1;
+
+/// zstd compression level, which favours speed since the byte planes of
+/// floating-point data compress only moderately
+const ZSTD_LEVEL: c_int = 3;
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_STORED_MAGIC`].
+pub fn is_stored(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_STORED_MAGIC)
+}
+
+/// Upper bound on the size of a stored-raw payload of `elements` values with
+/// the lossless `compression`, or [`None`] if it does not fit into `usize`.
+pub fn stored_size_bound(elements: usize, compression: EBCCStoredCompression) -> Option<usize> {
+    let raw_bytes = elements.checked_mul(size_of::<f32>())?;
+
+    let body = match compression {
+        EBCCStoredCompression::None => raw_bytes,
+        EBCCStoredCompression::Zstd => zstd_compress_bound(raw_bytes)?,
+    };
+
+    body.checked_add(EBCC_STORED_HEADER_LEN)
+}
+
+/// Upper bound on the size of `bytes` bytes after zstd compression, or
+/// [`None`] if zstd cannot compress that many bytes.
+#[cfg(feature = "encode")]
+fn zstd_compress_bound(bytes: usize) -> Option<usize> {
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_compressBound only computes a size
+    let bound = unsafe { ZSTD_compressBound(bytes) };
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_isError only inspects the code
+    if unsafe { ZSTD_isError(bound) } != 0 {
+        return None;
+    }
+    Some(bound)
+}
+
+/// Upper bound on the size of `bytes` bytes after zstd compression, like
+/// the `ZSTD_COMPRESSBOUND` macro, without linking the zstd compressor.
+#[cfg(not(feature = "encode"))]
+fn zstd_compress_bound(bytes: usize) -> Option<usize> {
+    const SMALL_INPUT: usize = 128 << 10;
+
+    let margin = SMALL_INPUT.saturating_sub(bytes) >> 11;
+    bytes.checked_add(bytes >> 8)?.checked_add(margin)
+}
+
+/// Store a 3D data array raw in a stored-raw payload, which is optionally
+/// compressed losslessly.
+pub fn stored_encode(
+    data: ArrayView<f32, EbccDim>,
+    compression: EBCCStoredCompression,
+) -> EBCCResult<Vec<u8>> {
+    let mut compressed_data =
+        Vec::with_capacity(EBCC_STORED_HEADER_LEN + data.len() * size_of::<f32>());
+    compressed_data.extend_from_slice(EBCC_STORED_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_STORED_VERSION.to_le_bytes());
+    compressed_data.push(match compression {
+        EBCCStoredCompression::None => COMPRESSION_NONE,
+        EBCCStoredCompression::Zstd => COMPRESSION_ZSTD,
+    });
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+
+    match compression {
+        EBCCStoredCompression::None => {
+            compressed_data.extend(data.iter().flat_map(|x| x.to_le_bytes()));
+        }
+        EBCCStoredCompression::Zstd => {
+            let planes = (0..size_of::<f32>())
+                .flat_map(|plane| {
+                    data.iter()
+                        .map(move |x| x.to_le_bytes().get(plane).copied().unwrap_or_default())
+                })
+                .collect::<Vec<u8>>();
+            zstd_compress(&planes, &mut compressed_data)?;
+        }
+    }
+
+    Ok(compressed_data)
+}
+
+/// Decode a stored-raw payload of the expected `shape` into the flattened 3D
+/// data array.
+pub fn stored_decode(
+    compressed_data: &[u8],
+    expected_shape: (usize, usize, usize),
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_STORED_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if !(1..=EBCC_STORED_VERSION).contains(&version) {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC stored-raw version: {version}").into(),
+        ));
+    }
+
+    let [compression] = if version >= 2 {
+        FORMAT.read_array(&mut reader)?
+    } else {
+        [COMPRESSION_NONE]
+    };
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+
+    let elements = data_len(expected_shape.into())?;
+
+    match compression {
+        COMPRESSION_NONE => {
+            if reader.len() != elements * size_of::<f32>() {
+                return Err(FORMAT.truncated());
+            }
+
+            Ok(reader
+                .chunks_exact(size_of::<f32>())
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap_or_default()))
+                .collect())
+        }
+        COMPRESSION_ZSTD => {
+            let planes = zstd_decompress(reader, elements * size_of::<f32>())?;
+
+            Ok((0..elements)
+                .map(|i| {
+                    let mut bytes = [0; size_of::<f32>()];
+                    for (plane, byte) in bytes.iter_mut().enumerate() {
+                        *byte = planes
+                            .get(plane * elements + i)
+                            .copied()
+                            .unwrap_or_default();
+                    }
+                    f32::from_le_bytes(bytes)
+                })
+                .collect())
+        }
+        compression => Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC stored-raw compression: {compression}").into(),
+        )),
+    }
+}
+
+/// Compress the `bytes` with zstd and append them to the `compressed_data`.
+#[cfg(feature = "encode")]
+fn zstd_compress(bytes: &[u8], compressed_data: &mut Vec<u8>) -> EBCCResult<()> {
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_compressBound only computes a size
+    let bound = unsafe { ZSTD_compressBound(bytes.len()) };
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_isError only inspects the code
+    if unsafe { ZSTD_isError(bound) } != 0 {
+        return Err(EBCCError::CompressionError(zstd_failure(
+            format_args!("zstd cannot compress {} bytes", bytes.len()),
+            bound,
+        )));
+    }
+
+    compressed_data.reserve(bound);
+    let spare = compressed_data.spare_capacity_mut();
+
+    #[expect(unsafe_code)]
+    // Safety: spare is valid for writes of at least bound bytes and bytes is
+    //         valid for reads of bytes.len() bytes
+    let size = with_ebcc_call(|| unsafe {
+        ZSTD_compress(
+            spare.as_mut_ptr().cast(),
+            spare.len(),
+            bytes.as_ptr().cast(),
+            bytes.len(),
+            ZSTD_LEVEL,
+        )
+    });
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_isError only inspects the code
+    if unsafe { ZSTD_isError(size) } != 0 {
+        return Err(EBCCError::CompressionError(zstd_failure(
+            format_args!("zstd failed to compress {} bytes", bytes.len()),
+            size,
+        )));
+    }
+    if size > spare.len() {
+        return Err(EBCCError::CompressionError(
+            format!(
+                "zstd compressed {} bytes into {size} bytes, more than the {} bytes of the output",
+                bytes.len(),
+                spare.len(),
+            )
+            .into(),
+        ));
+    }
+
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_compress has initialized size bytes of the spare capacity
+    unsafe {
+        compressed_data.set_len(compressed_data.len() + size);
+    }
+
+    Ok(())
+}
+
+/// Compress the `bytes` like [`zstd_compress`] without the `encode` feature,
+/// which always fails.
+#[cfg(not(feature = "encode"))]
+fn zstd_compress(_bytes: &[u8], _compressed_data: &mut Vec<u8>) -> EBCCResult<()> {
+    Err(encode_unsupported())
+}
+
+/// Decompress the zstd-compressed `bytes`, which must decompress to exactly
+/// `len` bytes.
+#[cfg(feature = "decode")]
+fn zstd_decompress(bytes: &[u8], len: usize) -> EBCCResult<Vec<u8>> {
+    let mut decompressed = Vec::<u8>::with_capacity(len);
+
+    #[expect(unsafe_code)]
+    // Safety: decompressed is valid for writes of len bytes and bytes is valid
+    //         for reads of bytes.len() bytes
+    let size = with_ebcc_call(|| unsafe {
+        ZSTD_decompress(
+            decompressed.as_mut_ptr().cast(),
+            len,
+            bytes.as_ptr().cast(),
+            bytes.len(),
+        )
+    });
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_isError only inspects the code
+    if unsafe { ZSTD_isError(size) } != 0 {
+        return Err(EBCCError::DecompressionError(zstd_failure(
+            format_args!("EBCC stored-raw data is corrupted"),
+            size,
+        )));
+    }
+    if size != len {
+        return Err(FORMAT.corrupted());
+    }
+
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_decompress has initialized all len bytes
+    unsafe {
+        decompressed.set_len(len);
+    }
+
+    Ok(decompressed)
+}
+
+/// Decompress the `bytes` like [`zstd_decompress`] without the `decode`
+/// feature, which always fails.
+#[cfg(not(feature = "decode"))]
+fn zstd_decompress(_bytes: &[u8], _len: usize) -> EBCCResult<Vec<u8>> {
+    Err(decode_unsupported())
+}
+
+/// Describe the failure of a zstd function, which returned the error `code`,
+/// with the `message` and the name of the error, e.g.
+/// `"Data corruption detected"`.
+fn zstd_failure(message: fmt::Arguments, code: usize) -> EBCCFailure {
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_getErrorName returns a static null-terminated string for
+    //         every code
+    let name = unsafe { static_c_str(ZSTD_getErrorName(code)) };
+    #[expect(unsafe_code)]
+    // Safety: ZSTD_getErrorCode only inspects the code
+    let code = unsafe { ZSTD_getErrorCode(code) };
+
+    EBCCFailure::new(
+        format!("{message}: {name}"),
+        EBCCFailureCause::Zstd { code },
+    )
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::error::EBCCErrorKind;
+    use crate::testdata;
+
+    #[test]
+    fn test_stored_roundtrip() -> EBCCResult<()> {
+        let data = testdata::noise((2, 32, 48), 1.0, 42);
+
+        let compressed = stored_encode(data.view(), EBCCStoredCompression::None)?;
+        assert!(is_stored(&compressed));
+        assert_eq!(
+            compressed.len(),
+            EBCC_STORED_HEADER_LEN + data.len() * size_of::<f32>()
+        );
+
+        let decompressed = stored_decode(&compressed, data.dim())?;
+        assert!(data.iter().eq(decompressed.iter()));
+
+        assert!(matches!(
+            stored_decode(&compressed, (1, 32, 48)),
+            Err(EBCCError::ShapeMismatch { .. })
+        ));
+        assert!(stored_decode(
+            compressed.split_last().map_or(&[], |(_, rest)| rest),
+            data.dim()
+        )
+        .is_err());
+
+        // version 1 has no compression byte
+        let mut legacy = compressed;
+        legacy.splice(8..13, 1_u32.to_le_bytes());
+        let decompressed = stored_decode(&legacy, data.dim())?;
+        assert!(data.iter().eq(decompressed.iter()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stored_zstd_roundtrip() -> EBCCResult<()> {
+        let data = testdata::temperature((2, 32, 48));
+
+        let compressed = stored_encode(data.view(), EBCCStoredCompression::Zstd)?;
+        assert!(is_stored(&compressed));
+        assert!(compressed.len() < data.len() * size_of::<f32>());
+
+        let decompressed = stored_decode(&compressed, data.dim())?;
+        assert!(data.iter().eq(decompressed.iter()));
+
+        // the zstd error is reported with its typed cause
+        let err = stored_decode(
+            compressed.split_last().map_or(&[], |(_, rest)| rest),
+            data.dim(),
+        );
+        assert!(matches!(&err, Err(err) if err.kind() == EBCCErrorKind::InvalidInput));
+        assert!(matches!(
+            err,
+            Err(EBCCError::DecompressionError(failure))
+                if matches!(failure.cause(), EBCCFailureCause::Zstd { .. })
+                    && failure.message().strip_prefix("EBCC stored-raw data is corrupted: ")
+                        .is_some_and(|name| !name.is_empty())
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,834 @@
+//! Incremental (frame-by-frame) EBCC stream encoding.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    num::NonZeroUsize,
+};
+
+use ebcc_sys::{EBCC_MAX_INTERNAL_IMAGE_DIM, EBCC_NDIMS};
+use ndarray::{s, ArrayView, ArrayView2, ArrayViewMut, Axis};
+
+use crate::codec::{
+    copy_decompressed, decompressed_view, ebcc_decode_c_buffer_mut, ebcc_encode_c_buffer, EbccDim,
+};
+use crate::config::EBCCConfig;
+use crate::error::{EBCCError, EBCCResult};
+use crate::finite::find_non_finite;
+use crate::params::validate_regular_ebcc_shape;
+use crate::size::usize_to_u64;
+
+/// Magic bytes at the start of every EBCC frame stream.
+pub const EBCC_STREAM_MAGIC: &[u8; 8] = b"EBCCSTRM";
+
+/// Version of the EBCC frame stream format.
+pub const EBCC_STREAM_VERSION: u32 = 3;
+
+/// Fixed-layout header at the start of every EBCC frame stream.
+///
+/// Since version 2, the header ends with the
+/// [fingerprint][EBCCConfig::fingerprint] of the configuration that the
+/// stream was compressed with, which only tags every optional configuration
+/// parameter since version 3. Version 1 streams, whose header is eight bytes
+/// shorter, are still decoded.
+///
+/// All fields are stored as little-endian byte arrays, so the header has no
+/// padding and an alignment of one. With the `bytemuck` feature, the header
+/// implements `bytemuck::Pod` and can be cast from and to its
+/// [`LEN`][Self::LEN] bytes directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct EbccStreamHeader {
+    magic: [u8; 8],
+    version: [u8; 4],
+    height: [u8; 8],
+    width: [u8; 8],
+    config_fingerprint: [u8; 8],
+}
+
+impl EbccStreamHeader {
+    /// Length of the encoded header, in bytes
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Length of the encoded header of version 1 streams, which do not
+    /// record the configuration fingerprint, in bytes
+    pub const LEGACY_LEN: usize = Self::LEN - 8;
+
+    /// Create a header with the [`EBCC_STREAM_MAGIC`] and
+    /// [`EBCC_STREAM_VERSION`] for frames of shape `(height, width)` that
+    /// are compressed with a configuration with the `config_fingerprint`.
+    #[must_use]
+    pub const fn new(height: u64, width: u64, config_fingerprint: u64) -> Self {
+        Self {
+            magic: *EBCC_STREAM_MAGIC,
+            version: EBCC_STREAM_VERSION.to_le_bytes(),
+            height: height.to_le_bytes(),
+            width: width.to_le_bytes(),
+            config_fingerprint: config_fingerprint.to_le_bytes(),
+        }
+    }
+
+    /// Magic bytes, which are [`EBCC_STREAM_MAGIC`] in a valid stream
+    #[must_use]
+    pub const fn magic(&self) -> [u8; 8] {
+        self.magic
+    }
+
+    /// Version of the stream format
+    #[must_use]
+    pub const fn version(&self) -> u32 {
+        u32::from_le_bytes(self.version)
+    }
+
+    /// Height of every frame in the stream
+    #[must_use]
+    pub const fn height(&self) -> u64 {
+        u64::from_le_bytes(self.height)
+    }
+
+    /// Width of every frame in the stream
+    #[must_use]
+    pub const fn width(&self) -> u64 {
+        u64::from_le_bytes(self.width)
+    }
+
+    /// [Fingerprint][EBCCConfig::fingerprint] of the configuration that the
+    /// stream was compressed with, or [`None`] for streams before version 3,
+    /// which record no fingerprint or one that did not tag the optional
+    /// configuration parameters
+    #[must_use]
+    pub const fn config_fingerprint(&self) -> Option<u64> {
+        if self.version() < 3 {
+            return None;
+        }
+        Some(u64::from_le_bytes(self.config_fingerprint))
+    }
+
+    /// Encode the header into its little-endian byte representation.
+    #[cfg(feature = "bytemuck")]
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes.copy_from_slice(bytemuck::bytes_of(self));
+        bytes
+    }
+
+    /// Encode the header into its little-endian byte representation.
+    #[cfg(not(feature = "bytemuck"))]
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        let (magic, rest) = bytes.split_at_mut(8);
+        let (version, rest) = rest.split_at_mut(4);
+        let (height, rest) = rest.split_at_mut(8);
+        let (width, config_fingerprint) = rest.split_at_mut(8);
+        magic.copy_from_slice(&self.magic);
+        version.copy_from_slice(&self.version);
+        height.copy_from_slice(&self.height);
+        width.copy_from_slice(&self.width);
+        config_fingerprint.copy_from_slice(&self.config_fingerprint);
+        bytes
+    }
+
+    /// Decode the header from its little-endian byte representation.
+    ///
+    /// The magic bytes and version are not checked.
+    #[cfg(feature = "bytemuck")]
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Self {
+        bytemuck::pod_read_unaligned(bytes)
+    }
+
+    /// Decode the header from its little-endian byte representation.
+    ///
+    /// The magic bytes and version are not checked.
+    #[cfg(not(feature = "bytemuck"))]
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Self {
+        let (magic, rest) = bytes.split_at(8);
+        let (version, rest) = rest.split_at(4);
+        let (height, rest) = rest.split_at(8);
+        let (width, config_fingerprint) = rest.split_at(8);
+        Self {
+            magic: magic.try_into().unwrap_or_default(),
+            version: version.try_into().unwrap_or_default(),
+            height: height.try_into().unwrap_or_default(),
+            width: width.try_into().unwrap_or_default(),
+            config_fingerprint: config_fingerprint.try_into().unwrap_or_default(),
+        }
+    }
+}
+
+/// Fixed-layout header in front of each segment payload of an EBCC frame
+/// stream.
+///
+/// The stream trailer has the same layout: a zero [`frames`][Self::frames]
+/// end marker, followed by the total number of frames in the stream instead
+/// of the payload length.
+///
+/// All fields are stored as little-endian byte arrays, so the header has no
+/// padding and an alignment of one. With the `bytemuck` feature, the header
+/// implements `bytemuck::Pod` and can be cast from and to its
+/// [`LEN`][Self::LEN] bytes directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct EbccStreamSegmentHeader {
+    frames: [u8; 8],
+    payload_len: [u8; 8],
+}
+
+impl EbccStreamSegmentHeader {
+    /// Length of the encoded segment header, in bytes
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Create a header for a segment with `frames` frames, which must be
+    /// non-zero, and a payload of `payload_len` bytes.
+    #[must_use]
+    pub const fn new(frames: u64, payload_len: u64) -> Self {
+        Self {
+            frames: frames.to_le_bytes(),
+            payload_len: payload_len.to_le_bytes(),
+        }
+    }
+
+    /// Create the stream trailer for a stream with `total_frames` frames.
+    #[must_use]
+    pub const fn trailer(total_frames: u64) -> Self {
+        Self::new(0, total_frames)
+    }
+
+    /// Returns `true` if this is the stream trailer, i.e. if
+    /// [`frames`][Self::frames] is zero.
+    #[must_use]
+    pub const fn is_trailer(&self) -> bool {
+        self.frames() == 0
+    }
+
+    /// Number of frames in the segment, or zero for the stream trailer
+    #[must_use]
+    pub const fn frames(&self) -> u64 {
+        u64::from_le_bytes(self.frames)
+    }
+
+    /// Length of the segment payload, in bytes, or the total number of frames
+    /// in the stream for the stream trailer
+    #[must_use]
+    pub const fn payload_len(&self) -> u64 {
+        u64::from_le_bytes(self.payload_len)
+    }
+
+    /// Encode the segment header into its little-endian byte representation.
+    #[cfg(feature = "bytemuck")]
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes.copy_from_slice(bytemuck::bytes_of(self));
+        bytes
+    }
+
+    /// Encode the segment header into its little-endian byte representation.
+    #[cfg(not(feature = "bytemuck"))]
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        let (frames, payload_len) = bytes.split_at_mut(8);
+        frames.copy_from_slice(&self.frames);
+        payload_len.copy_from_slice(&self.payload_len);
+        bytes
+    }
+
+    /// Decode the segment header from its little-endian byte representation.
+    #[cfg(feature = "bytemuck")]
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Self {
+        bytemuck::pod_read_unaligned(bytes)
+    }
+
+    /// Decode the segment header from its little-endian byte representation.
+    #[cfg(not(feature = "bytemuck"))]
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Self {
+        let (frames, payload_len) = bytes.split_at(8);
+        Self {
+            frames: frames.try_into().unwrap_or_default(),
+            payload_len: payload_len.try_into().unwrap_or_default(),
+        }
+    }
+}
+
+/// Encoder that compresses 2D frames as they arrive.
+///
+/// Frames are buffered until `max_buffered_frames` frames have been pushed,
+/// at which point the buffered frames are compressed with
+/// [`ebcc_encode`][crate::ebcc_encode] into one segment and written to the
+/// output. [`finish`][Self::finish] flushes the remaining frames and
+/// terminates the stream.
+///
+/// The resulting stream can be decoded with
+/// [`ebcc_decode_into`][crate::ebcc_decode_into] into an array of shape
+/// `(frames, height, width)`.
+///
+/// <div class="warning">
+///
+/// **Warning:** Range-relative error bounds are calculated independently for
+/// each segment.
+///
+/// </div>
+///
+/// # Stream format
+///
+/// All integers are stored in little-endian byte order.
+///
+/// - header ([`EbccStreamHeader`]): [`EBCC_STREAM_MAGIC`],
+///   [`EBCC_STREAM_VERSION`] as `u32`, the frame height and width as `u64`s,
+///   and the [fingerprint][EBCCConfig::fingerprint] of the configuration as
+///   `u64`, which version 1 streams do not record, such that their
+///   [`config_fingerprint`][EbccStreamHeader::config_fingerprint] is [`None`]
+/// - zero or more segments: an [`EbccStreamSegmentHeader`] with the non-zero
+///   number of frames in the segment as `u64` and the payload length as
+///   `u64`, followed by the [`ebcc_encode`][crate::ebcc_encode] payload
+/// - trailer ([`EbccStreamSegmentHeader::trailer`]): a zero `u64` end marker
+///   and the total number of frames as `u64`
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use std::num::NonZeroUsize;
+///
+/// use ebcc::{ebcc_decode_into, EBCCConfig, EbccStreamEncoder};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let mut encoder = EbccStreamEncoder::new(
+///     Vec::new(), EBCCConfig::new(), (32, 32), NonZeroUsize::MIN,
+/// )?;
+///
+/// for t in 0..3 {
+///     let frame = Array::from_elem((32, 32), t as f32);
+///     encoder.push_frame(frame.view())?;
+/// }
+///
+/// let compressed = encoder.finish()?;
+///
+/// let mut decompressed = Array::zeros((3, 32, 32));
+/// ebcc_decode_into(&compressed, decompressed.view_mut())?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct EbccStreamEncoder<W: Write> {
+    writer: W,
+    config: EBCCConfig,
+    frame_shape: (usize, usize),
+    max_buffered_frames: usize,
+    buffer: Vec<f32>,
+    buffered_frames: usize,
+    total_frames: u64,
+}
+
+impl<W: Write> EbccStreamEncoder<W> {
+    /// Create a new stream encoder that writes to `writer`.
+    ///
+    /// `frame_shape` is the `(height, width)` shape that all pushed frames
+    /// must have. At most `max_buffered_frames` frames are buffered before
+    /// they are encoded. The number of buffered frames is further capped such
+    /// that a segment never exceeds EBCC's internal image dimension limit.
+    ///
+    /// The stream header is written to `writer` immediately.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::EmptyDimension`] or [`EBCCError::UnsupportedShape`] if
+    ///   `frame_shape` has any zero-size dimension or its EBCC internal image
+    ///   dimensions are outside the supported range
+    /// - [`EBCCError::InvalidConfig`] if [`config.validate`][EBCCConfig::validate]
+    ///   fails
+    /// - [`EBCCError::FrameTooLarge`] if `frame_shape` exceeds the
+    ///   [`config.limits`][EBCCConfig::limits]
+    /// - [`EBCCError::Io`] if writing the stream header fails
+    pub fn new(
+        mut writer: W,
+        config: EBCCConfig,
+        frame_shape: (usize, usize),
+        max_buffered_frames: NonZeroUsize,
+    ) -> EBCCResult<Self> {
+        let (height, width) = frame_shape;
+        if height == 0 || width == 0 {
+            return Err(EBCCError::EmptyDimension);
+        }
+        validate_regular_ebcc_shape((1, height, width))?;
+        config.validate()?;
+        config.limits.check_frame_shape(frame_shape)?;
+
+        let max_buffered_frames = max_buffered_frames
+            .get()
+            .min(EBCC_MAX_INTERNAL_IMAGE_DIM / height);
+
+        writer.write_all(
+            &EbccStreamHeader::new(
+                usize_to_u64(height)?,
+                usize_to_u64(width)?,
+                config.fingerprint(),
+            )
+            .to_bytes(),
+        )?;
+
+        Ok(Self {
+            writer,
+            config,
+            frame_shape,
+            max_buffered_frames,
+            buffer: Vec::new(),
+            buffered_frames: 0,
+            total_frames: 0,
+        })
+    }
+
+    /// Push one `(height, width)` frame into the stream.
+    ///
+    /// Once the buffer is full, the buffered frames are encoded and written.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::FrameShapeMismatch`] if the `frame` does not have the
+    ///   stream's frame shape
+    /// - [`EBCCError::TooManyFrames`] if the stream would exceed the maximum
+    ///   number of frames of the [`config.limits`][EBCCConfig::limits]
+    /// - [`EBCCError::NonFinite`] if the `frame` contains any non-finite
+    ///   (infinite or NaN) values, unless the check is skipped with
+    ///   [`EBCCConfig::skip_finite_check`]
+    /// - [`EBCCError::CompressionError`] if compression with EBCC fails
+    /// - [`EBCCError::Io`] if writing the segment fails
+    pub fn push_frame(&mut self, frame: ArrayView2<f32>) -> EBCCResult<()> {
+        if frame.dim() != self.frame_shape {
+            return Err(EBCCError::FrameShapeMismatch {
+                expected: self.frame_shape.into(),
+                actual: frame.dim().into(),
+            });
+        }
+
+        let frame_index = self.total_frames + usize_to_u64(self.buffered_frames)?;
+        self.config.limits.check_frames(
+            usize::try_from(frame_index)
+                .unwrap_or(usize::MAX)
+                .saturating_add(1),
+        )?;
+
+        if let Some(((y, x), value)) = self
+            .config
+            .check_finite
+            .then(|| find_non_finite(&frame))
+            .flatten()
+        {
+            return Err(EBCCError::NonFinite {
+                index: [usize::try_from(frame_index).unwrap_or(usize::MAX), y, x],
+                value,
+            });
+        }
+
+        self.buffer.extend(frame.iter().copied());
+        self.buffered_frames += 1;
+
+        if self.buffered_frames >= self.max_buffered_frames {
+            self.flush_segment()?;
+        }
+
+        Ok(())
+    }
+
+    /// Encode any remaining buffered frames, terminate the stream, and return
+    /// the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::CompressionError`] if compression with EBCC fails
+    /// - [`EBCCError::Io`] if writing to or flushing the writer fails
+    pub fn finish(mut self) -> EBCCResult<W> {
+        self.flush_segment()?;
+
+        self.writer
+            .write_all(&EbccStreamSegmentHeader::trailer(self.total_frames).to_bytes())?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn flush_segment(&mut self) -> EBCCResult<()> {
+        if self.buffered_frames == 0 {
+            return Ok(());
+        }
+
+        let (height, width) = self.frame_shape;
+        let Ok(segment) = ArrayView::from_shape(
+            (self.buffered_frames, height, width),
+            self.buffer.as_slice(),
+        ) else {
+            return Err(EBCCError::InvalidInput(String::from(
+                "Buffered frames do not match the frame shape",
+            )));
+        };
+
+        // the frames were already checked when they were pushed
+        let payload = ebcc_encode_c_buffer(segment, &self.config.clone().skip_finite_check())?;
+        let payload = payload.as_slice();
+
+        let frame_count = usize_to_u64(self.buffered_frames)?;
+        self.writer.write_all(
+            &EbccStreamSegmentHeader::new(frame_count, usize_to_u64(payload.len())?).to_bytes(),
+        )?;
+        self.writer.write_all(payload)?;
+
+        self.total_frames += frame_count;
+        self.buffer.clear();
+        self.buffered_frames = 0;
+
+        Ok(())
+    }
+}
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_STREAM_MAGIC`].
+pub fn is_ebcc_stream(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_STREAM_MAGIC)
+}
+
+/// Decode an EBCC frame stream into a 3D data array.
+pub fn ebcc_decode_stream_into(
+    mut compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    let mut magic = [0; EBCC_STREAM_MAGIC.len()];
+    read_exact(&mut compressed_data, &mut magic)?;
+    if &magic != EBCC_STREAM_MAGIC {
+        return Err(EBCCError::DecompressionError(
+            "Missing EBCC stream header".into(),
+        ));
+    }
+
+    ebcc_decode_stream_body_from_reader(&mut compressed_data, decompressed_data)
+}
+
+/// Decode the remainder of an EBCC frame stream, whose
+/// [`EBCC_STREAM_MAGIC`] has already been consumed from the `reader`, into a
+/// 3D data array.
+///
+/// Only one segment payload is held in memory at a time.
+pub fn ebcc_decode_stream_body_from_reader(
+    reader: &mut impl Read,
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    ebcc_decode_stream_body_with_scratch(reader, &mut Vec::new(), decompressed_data)
+}
+
+/// Like [`ebcc_decode_stream_body_from_reader`], but reads the segment
+/// payloads into the reusable `payload` buffer.
+pub fn ebcc_decode_stream_body_with_scratch(
+    reader: &mut impl Read,
+    payload: &mut Vec<u8>,
+    mut decompressed_data: ArrayViewMut<f32, EbccDim>,
+) -> EBCCResult<()> {
+    let shape = decompressed_data.dim();
+    ebcc_visit_stream_body_with_scratch(reader, shape, payload, |first_frame, frames| {
+        copy_decompressed(
+            decompressed_data.slice_mut(s![
+                first_frame..first_frame + frames.len_of(Axis(0)),
+                ..,
+                ..
+            ]),
+            frames,
+        );
+        Ok(())
+    })
+}
+
+/// Decode the remainder of an EBCC frame stream, whose
+/// [`EBCC_STREAM_MAGIC`] has already been consumed from the `reader`, and
+/// pass each decoded segment to `visit`.
+///
+/// `visit` is called with the index of the first frame in the segment and a
+/// view of the segment's decoded frames. Only one segment is held in memory
+/// at a time.
+pub fn ebcc_visit_stream_body(
+    reader: &mut impl Read,
+    shape: (usize, usize, usize),
+    visit: impl FnMut(usize, ArrayView<f32, EbccDim>) -> EBCCResult<()>,
+) -> EBCCResult<()> {
+    ebcc_visit_stream_body_with_scratch(reader, shape, &mut Vec::new(), visit)
+}
+
+/// Read the header of an EBCC frame stream whose [`EBCC_STREAM_MAGIC`] has
+/// already been consumed from the `reader`.
+pub fn read_stream_header(reader: &mut impl Read) -> EBCCResult<EbccStreamHeader> {
+    let mut version = [0; 4];
+    read_exact(reader, &mut version)?;
+
+    // version 1 headers end before the configuration fingerprint
+    let len = match u32::from_le_bytes(version) {
+        1 => EbccStreamHeader::LEGACY_LEN,
+        2 | EBCC_STREAM_VERSION => EbccStreamHeader::LEN,
+        version => {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC stream version: {version}").into(),
+            ))
+        }
+    };
+
+    let mut header = [0; EbccStreamHeader::LEN];
+    let (magic, rest) = header.split_at_mut(EBCC_STREAM_MAGIC.len());
+    magic.copy_from_slice(EBCC_STREAM_MAGIC);
+    let (version_bytes, rest) = rest.split_at_mut(version.len());
+    version_bytes.copy_from_slice(&version);
+    read_exact(
+        reader,
+        rest.get_mut(..len - EBCC_STREAM_MAGIC.len() - version.len())
+            .unwrap_or_default(),
+    )?;
+
+    Ok(EbccStreamHeader::from_bytes(&header))
+}
+
+/// Like [`ebcc_visit_stream_body`], but reads the segment payloads into the
+/// reusable `payload` buffer.
+pub fn ebcc_visit_stream_body_with_scratch(
+    reader: &mut impl Read,
+    shape: (usize, usize, usize),
+    payload: &mut Vec<u8>,
+    mut visit: impl FnMut(usize, ArrayView<f32, EbccDim>) -> EBCCResult<()>,
+) -> EBCCResult<()> {
+    let header = read_stream_header(reader)?;
+
+    let (height, width) = (header.height(), header.width());
+    let output_dims: [usize; EBCC_NDIMS] = shape.into();
+    let (frames, output_height, output_width) = shape;
+    if (usize_to_u64(output_height)?, usize_to_u64(output_width)?) != (height, width) {
+        return Err(EBCCError::InvalidInput(format!(
+            "EBCC stream has frames of shape [{height}, {width}] but output array has shape {output_dims:?}",
+        )));
+    }
+
+    let mut decoded_frames = 0_usize;
+    let trailer = loop {
+        let mut segment = [0; EbccStreamSegmentHeader::LEN];
+        read_exact(reader, &mut segment)?;
+        let segment = EbccStreamSegmentHeader::from_bytes(&segment);
+        if segment.is_trailer() {
+            break segment;
+        }
+
+        let (frame_count, payload_len) = (segment.frames(), segment.payload_len());
+
+        let Some(segment_end) = usize::try_from(frame_count)
+            .ok()
+            .and_then(|frame_count| decoded_frames.checked_add(frame_count))
+            .filter(|segment_end| *segment_end <= frames)
+        else {
+            return Err(EBCCError::InvalidInput(format!(
+                "EBCC stream has more frames than the output array with shape {output_dims:?}",
+            )));
+        };
+
+        payload.clear();
+        // read incrementally instead of trusting the payload length up-front
+        reader.take(payload_len).read_to_end(payload)?;
+        if usize_to_u64(payload.len())? != payload_len {
+            return Err(EBCCError::InvalidInput(String::from(
+                "EBCC stream segment is truncated",
+            )));
+        }
+
+        let segment_shape = (segment_end - decoded_frames, output_height, output_width);
+        let decompressed_buffer = ebcc_decode_c_buffer_mut(payload, segment_shape)?;
+        visit(
+            decoded_frames,
+            decompressed_view(segment_shape, &decompressed_buffer)?,
+        )?;
+        decoded_frames = segment_end;
+    };
+
+    let total_frames = trailer.payload_len();
+    if total_frames != usize_to_u64(decoded_frames)? || decoded_frames != frames {
+        return Err(EBCCError::InvalidInput(format!(
+            "EBCC stream has {total_frames} frames but output array has shape {output_dims:?}",
+        )));
+    }
+
+    Ok(())
+}
+
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> EBCCResult<()> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Err(EBCCError::InvalidInput(
+            String::from("EBCC stream is truncated"),
+        )),
+        Err(err) => Err(EBCCError::Io(err)),
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::codec::ebcc_decode_into;
+    use crate::limits::EBCCLimits;
+
+    #[test]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_stream_roundtrip() -> EBCCResult<()> {
+        let data = Array::from_shape_fn((5, 32, 32), |(t, y, x)| (t + y + x) as f32);
+
+        let mut encoder = EbccStreamEncoder::new(
+            Vec::new(),
+            EBCCConfig::max_absolute_error_bounded(0.1),
+            (32, 32),
+            NonZeroUsize::new(2).unwrap(),
+        )?;
+        for frame in data.axis_iter(Axis(0)) {
+            encoder.push_frame(frame)?;
+        }
+        let compressed = encoder.finish()?;
+        assert!(is_ebcc_stream(&compressed));
+
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        let mut wrong_frames = Array::zeros((4, 32, 32));
+        assert!(ebcc_decode_into(&compressed, wrong_frames.view_mut()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[expect(clippy::cast_precision_loss)]
+    fn test_stream_config_fingerprint() -> EBCCResult<()> {
+        let data = Array::from_shape_fn((3, 32, 32), |(t, y, x)| (t * y + x) as f32);
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let mut encoder =
+            EbccStreamEncoder::new(Vec::new(), config.clone(), (32, 32), NonZeroUsize::MIN)?;
+        for frame in data.axis_iter(Axis(0)) {
+            encoder.push_frame(frame)?;
+        }
+        let compressed = encoder.finish()?;
+
+        let inspection = crate::ebcc_inspect(&compressed)?;
+        assert_eq!(inspection.config_fingerprint, Some(config.fingerprint()));
+        assert_eq!(inspection.matches_config(&config), Some(true));
+        assert_eq!(
+            inspection.matches_config(&EBCCConfig::max_absolute_error_bounded(0.2)),
+            Some(false)
+        );
+
+        // version 1 streams without a fingerprint are still decoded
+        let mut legacy = compressed.clone();
+        legacy.drain(EbccStreamHeader::LEGACY_LEN..EbccStreamHeader::LEN);
+        legacy
+            .get_mut(EBCC_STREAM_MAGIC.len()..EBCC_STREAM_MAGIC.len() + 4)
+            .unwrap()
+            .copy_from_slice(&1_u32.to_le_bytes());
+
+        let inspection = crate::ebcc_inspect(&legacy)?;
+        assert_eq!(inspection.config_fingerprint, None);
+        assert_eq!(inspection.matches_config(&config), None);
+
+        let mut decompressed = Array::zeros(data.dim());
+        let mut legacy_decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+        ebcc_decode_into(&legacy, legacy_decompressed.view_mut())?;
+        assert_eq!(decompressed, legacy_decompressed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_headers() -> EBCCResult<()> {
+        let compressed =
+            EbccStreamEncoder::new(Vec::new(), EBCCConfig::new(), (32, 48), NonZeroUsize::MIN)?
+                .finish()?;
+
+        let header = EbccStreamHeader::new(32, 48, EBCCConfig::new().fingerprint());
+        assert_eq!(header.magic(), *EBCC_STREAM_MAGIC);
+        assert_eq!(header.version(), EBCC_STREAM_VERSION);
+        assert_eq!((header.height(), header.width()), (32, 48));
+        assert_eq!(
+            header.config_fingerprint(),
+            Some(EBCCConfig::new().fingerprint())
+        );
+        assert_eq!(EbccStreamHeader::from_bytes(&header.to_bytes()), header);
+
+        let trailer = EbccStreamSegmentHeader::trailer(0);
+        assert!(trailer.is_trailer());
+        assert!(!EbccStreamSegmentHeader::new(1, 42).is_trailer());
+        assert_eq!(
+            EbccStreamSegmentHeader::from_bytes(&trailer.to_bytes()),
+            trailer
+        );
+
+        let mut expected = header.to_bytes().to_vec();
+        expected.extend_from_slice(&trailer.to_bytes());
+        assert_eq!(compressed, expected);
+
+        #[cfg(feature = "bytemuck")]
+        {
+            assert_eq!(bytemuck::bytes_of(&header), header.to_bytes());
+            assert_eq!(
+                bytemuck::pod_read_unaligned::<EbccStreamHeader>(&header.to_bytes()),
+                header
+            );
+            assert_eq!(bytemuck::bytes_of(&trailer), trailer.to_bytes());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_rejects_wrong_frame_shape() -> EBCCResult<()> {
+        let mut encoder =
+            EbccStreamEncoder::new(Vec::new(), EBCCConfig::new(), (32, 32), NonZeroUsize::MIN)?;
+
+        let frame = Array::<f32, _>::zeros((32, 33));
+        assert!(encoder.push_frame(frame.view()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_enforces_limits() -> EBCCResult<()> {
+        let config = EBCCConfig::new().with_limits(
+            EBCCLimits::new()
+                .with_max_frames(2)
+                .with_max_frame_elements(32 * 32),
+        );
+
+        assert!(
+            EbccStreamEncoder::new(Vec::new(), config.clone(), (32, 64), NonZeroUsize::MIN)
+                .is_err()
+        );
+
+        let mut encoder = EbccStreamEncoder::new(Vec::new(), config, (32, 32), NonZeroUsize::MIN)?;
+        let frame = Array::<f32, _>::zeros((32, 32));
+        encoder.push_frame(frame.view())?;
+        encoder.push_frame(frame.view())?;
+        assert!(encoder.push_frame(frame.view()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_stream() -> EBCCResult<()> {
+        let encoder =
+            EbccStreamEncoder::new(Vec::new(), EBCCConfig::new(), (32, 32), NonZeroUsize::MIN)?;
+        let compressed = encoder.finish()?;
+
+        let mut decompressed = Array::<f32, _>::zeros((0, 32, 32));
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        Ok(())
+    }
+}
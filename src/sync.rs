@@ -0,0 +1,195 @@
+//! Bookkeeping and thread safety of calls into the EBCC C library.
+
+use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::cell::RefCell;
+#[cfg(feature = "std")]
+use core::ffi::CStr;
+use core::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "std")]
+use std::sync::Once;
+
+#[cfg(all(feature = "std", feature = "ndarray"))]
+use crate::error::{EBCCError, EBCCResult};
+use crate::error::{EBCCFailure, EBCCFailureCause};
+
+/// Number of EBCC encode and decode calls that have started
+static EBCC_CALLS_STARTED: AtomicU64 = AtomicU64::new(0);
+
+/// Number of EBCC encode and decode calls that have finished
+static EBCC_CALLS_FINISHED: AtomicU64 = AtomicU64::new(0);
+
+/// Maximum number of error messages that are kept per EBCC call, such that a
+/// C library that reports many warnings cannot grow them without bound
+#[cfg(feature = "std")]
+const MAX_C_ERRORS: usize = 16;
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Error messages that the C libraries reported on this thread during the
+    /// current EBCC call, in the order in which they were reported
+    static C_ERRORS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Observe the error messages of the C libraries, see
+/// [`ebcc_sys::set_error_observer`].
+#[cfg(feature = "std")]
+fn observe_c_errors() {
+    static OBSERVE: Once = Once::new();
+
+    OBSERVE.call_once(|| ebcc_sys::set_error_observer(record_c_error));
+}
+
+/// Record the error `message` that the C libraries reported on this thread.
+///
+/// The first [`MAX_C_ERRORS`] messages of a call are kept, since `OpenJPEG`
+/// usually reports the root cause first and a generic failure last. The
+/// observer is called from within the C libraries and must not unwind, so
+/// the message is dropped if the thread-local is inaccessible.
+#[cfg(feature = "std")]
+fn record_c_error(message: &CStr) {
+    let message = message.to_string_lossy();
+    let message = message.trim();
+    if message.is_empty() {
+        return;
+    }
+
+    let _ = C_ERRORS.try_with(|errors| {
+        if let Ok(mut errors) = errors.try_borrow_mut() {
+            if errors.len() < MAX_C_ERRORS {
+                errors.push(String::from(message));
+            }
+        }
+    });
+}
+
+/// Take the error messages that the C libraries reported on this thread
+/// during the last EBCC call, in the order in which they were reported.
+///
+/// Messages are only captured with the [`ebcc_sys::ERROR_HOOK`], and only if
+/// they are reported on the calling thread, i.e. not by the worker threads of
+/// a multi-threaded `OpenJPEG` encode.
+#[cfg(feature = "std")]
+pub fn take_c_errors() -> Vec<String> {
+    C_ERRORS
+        .try_with(|errors| {
+            errors
+                .try_borrow_mut()
+                .map(|mut errors| core::mem::take(&mut *errors))
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
+
+/// Describe the failure of an EBCC C call with the `message` and, with the
+/// `std` feature, the error messages that the C libraries reported during the
+/// call.
+///
+/// The EBCC C library only signals a failure by returning no output buffer,
+/// if `out_is_null`, or an empty output buffer.
+pub fn c_call_failure(message: String, out_is_null: bool) -> EBCCFailure {
+    let cause = if out_is_null {
+        EBCCFailureCause::NoOutput
+    } else {
+        EBCCFailureCause::EmptyOutput
+    };
+
+    let failure = EBCCFailure::new(message, cause);
+    #[cfg(feature = "std")]
+    let failure = failure.with_c_errors(take_c_errors());
+
+    failure
+}
+
+/// Run `f`, which calls an EBCC encode or decode function, and count the
+/// call.
+///
+/// With the `std` feature, the error messages that the C libraries report
+/// during the call can afterwards be taken with [`take_c_errors`].
+///
+/// EBCC calls are not serialized. Every call creates its own `OpenJPEG`
+/// codecs and streams and its own zstd contexts, and only reads and writes
+/// the buffers that it is given, such that calls on different buffers can
+/// run in parallel. The allocator hooks of the `rust-alloc` feature are
+/// thread-safe as well. The only process-global state that the C libraries
+/// read is the `OPJ_NUM_THREADS` environment variable, which, like any
+/// environment variable, must not be modified while other threads may read
+/// it, and which is instead overridden by
+/// [`ebcc_sys::with_single_openjpeg_thread`] for deterministic encodes.
+pub fn with_ebcc_call<T>(f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "std")]
+    {
+        observe_c_errors();
+        let _ = take_c_errors();
+    }
+
+    EBCC_CALLS_STARTED.fetch_add(1, Ordering::Relaxed);
+    let result = f();
+    EBCC_CALLS_FINISHED.fetch_add(1, Ordering::Relaxed);
+
+    result
+}
+
+/// Run `f`, which calls an EBCC encode function, like [`with_ebcc_call`],
+/// with a single `OpenJPEG` thread if the encode is `deterministic`.
+#[cfg(all(feature = "std", feature = "ndarray", feature = "encode"))]
+pub fn with_ebcc_encode_call<T>(deterministic: bool, f: impl FnOnce() -> T) -> T {
+    if deterministic {
+        ebcc_sys::with_single_openjpeg_thread(|| with_ebcc_call(f))
+    } else {
+        with_ebcc_call(f)
+    }
+}
+
+/// Environment variable from which `OpenJPEG` reads its default number of
+/// threads when it creates a codec
+#[cfg(all(feature = "std", feature = "ndarray"))]
+const OPJ_NUM_THREADS: &str = "OPJ_NUM_THREADS";
+
+/// Check that an EBCC encode can run `deterministic`ally, i.e. that the
+/// `OpenJPEG` library either has no thread support, can be limited to a single
+/// thread with [`with_ebcc_encode_call`], or is asked to use at most one
+/// thread.
+///
+/// The vendored `OpenJPEG` library is limited to a single thread through
+/// [`ebcc_sys::with_single_openjpeg_thread`]. Prebuilt and system libraries
+/// only read the environment, which cannot be changed soundly while other
+/// threads may read it, so the encode is instead rejected if they may use
+/// more than one thread.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidConfig`] if `deterministic` is set and a prebuilt or
+///   system `OpenJPEG` library may use more than one thread
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub fn check_deterministic_encode(deterministic: bool) -> EBCCResult<()> {
+    if !deterministic || !ebcc_sys::OPENJPEG_THREADS || ebcc_sys::OPENJPEG_THREADS_HOOK {
+        return Ok(());
+    }
+
+    match std::env::var(OPJ_NUM_THREADS) {
+        Err(std::env::VarError::NotPresent) => Ok(()),
+        Ok(threads) if matches!(threads.trim(), "" | "0" | "1") => Ok(()),
+        Ok(threads) => Err(EBCCError::InvalidConfig(format!(
+            "Deterministic encoding requires a single OpenJPEG thread but the prebuilt or \
+             system OpenJPEG library is asked for {OPJ_NUM_THREADS}={threads}, unset it, use \
+             the vendored library, or enable the single-threaded feature"
+        ))),
+        Err(std::env::VarError::NotUnicode(_)) => Err(EBCCError::InvalidConfig(format!(
+            "Deterministic encoding requires a single OpenJPEG thread but the prebuilt or \
+             system OpenJPEG library is asked for more with {OPJ_NUM_THREADS}, unset it, use \
+             the vendored library, or enable the single-threaded feature"
+        ))),
+    }
+}
+
+/// The number of EBCC encode and decode calls that have finished, and
+/// whether any call is currently running.
+#[cfg(all(feature = "std", feature = "ndarray"))]
+pub fn ebcc_calls() -> (u64, bool) {
+    let finished = EBCC_CALLS_FINISHED.load(Ordering::Relaxed);
+    let started = EBCC_CALLS_STARTED.load(Ordering::Relaxed);
+    (finished, started > finished)
+}
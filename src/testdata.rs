@@ -0,0 +1,201 @@
+//! Synthetic climate-like fields for tests, benchmarks, and examples.
+//!
+//! All generators are deterministic: calling them with the same arguments
+//! always produces the same data. Generators with a `seed` parameter use a
+//! small built-in pseudo-random number generator so that no additional
+//! dependencies are required.
+
+use ndarray::Array;
+
+use crate::codec::EbccDim;
+
+/// Smooth temperature-like field in Kelvin.
+///
+/// The field is warmest at the equator, varies smoothly with longitude, and
+/// drifts slowly between frames, similar to an ERA5 2m temperature field on a
+/// regular latitude-longitude grid.
+#[must_use]
+#[expect(clippy::cast_precision_loss, clippy::suboptimal_flops)]
+pub fn temperature((frames, height, width): (usize, usize, usize)) -> Array<f32, EbccDim> {
+    Array::from_shape_fn((frames, height, width), |(t, i, j)| {
+        let (lat, lon) = lat_lon(i, j, height, width);
+        let drift = (t as f32 * 0.1).sin();
+
+        273.15
+            + 30.0 * (1.0 - lat.abs() / 90.0)
+            + 5.0 * (lon / 180.0).sin()
+            + 2.0 * (lat / 90.0 * 4.0).sin()
+            + drift
+    })
+}
+
+/// Noisy, non-negative precipitation-like field in mm/h.
+///
+/// Most grid points are exactly zero, while rain cells produce sparse,
+/// heavy-tailed positive values.
+#[must_use]
+#[expect(clippy::cast_precision_loss, clippy::suboptimal_flops)]
+pub fn precipitation(
+    (frames, height, width): (usize, usize, usize),
+    seed: u64,
+) -> Array<f32, EbccDim> {
+    let mut rng = SplitMix64::new(seed);
+
+    Array::from_shape_fn((frames, height, width), |(t, i, j)| {
+        let (lat, lon) = lat_lon(i, j, height, width);
+        let cells = (lat / 7.0 + t as f32 * 0.3).sin() * (lon / 11.0 - t as f32 * 0.2).cos();
+        let noise = rng.next_f32();
+
+        if cells + noise * 0.5 > 0.6 {
+            // exponential tail for the rain intensity
+            -(1.0 - rng.next_f32()).ln() * 2.0 * cells.max(0.1)
+        } else {
+            0.0
+        }
+    })
+}
+
+/// Uniform white noise in `[-amplitude, amplitude)`.
+#[must_use]
+pub fn noise(shape: (usize, usize, usize), amplitude: f32, seed: u64) -> Array<f32, EbccDim> {
+    let mut rng = SplitMix64::new(seed);
+
+    Array::from_shape_simple_fn(shape, || (rng.next_f32().mul_add(2.0, -1.0)) * amplitude)
+}
+
+/// Field with sharp frontal discontinuities that move between frames.
+///
+/// The two air masses are smooth on their own, but are separated by a sloped
+/// front across which the values jump by about 15 units.
+#[must_use]
+#[expect(clippy::cast_precision_loss, clippy::suboptimal_flops)]
+pub fn fronts((frames, height, width): (usize, usize, usize)) -> Array<f32, EbccDim> {
+    Array::from_shape_fn((frames, height, width), |(t, i, j)| {
+        let (y, x) = (i as f32, j as f32);
+        let position = x * 0.5 + (y / 9.0).sin() * 5.0 + t as f32 * 3.0;
+        let base = 285.0 + (y / 23.0).cos() * 2.0 + (x / 31.0).sin() * 2.0;
+
+        if y > position % (height.max(1) as f32) {
+            base - 15.0
+        } else {
+            base
+        }
+    })
+}
+
+/// Field where every value is `value`.
+#[must_use]
+pub fn constant(shape: (usize, usize, usize), value: f32) -> Array<f32, EbccDim> {
+    Array::from_elem(shape, value)
+}
+
+/// Temperature-like field where "land" points are replaced by `fill_value`.
+///
+/// This resembles sea-surface temperature products, where masked points are
+/// often stored using a large sentinel fill value.
+#[must_use]
+#[expect(clippy::suboptimal_flops)]
+pub fn masked(
+    (frames, height, width): (usize, usize, usize),
+    fill_value: f32,
+) -> Array<f32, EbccDim> {
+    let mut data = temperature((frames, height, width));
+
+    for ((_t, i, j), value) in data.indexed_iter_mut() {
+        let (lat, lon) = lat_lon(i, j, height, width);
+        let land = (lat / 17.0).sin() + (lon / 29.0).cos() * 0.8;
+
+        if land > 0.9 {
+            *value = fill_value;
+        }
+    }
+
+    data
+}
+
+/// Compress the `data` both as a single [`ebcc_encode`][crate::ebcc_encode]
+/// payload and as an EBCC frame stream with segments of two frames, for tests
+/// that check that both formats decode alike.
+#[cfg(all(test, feature = "encode"))]
+pub(crate) fn encode_single_and_stream(
+    data: ndarray::ArrayView<f32, EbccDim>,
+    config: &crate::EBCCConfig,
+) -> crate::EBCCResult<[Vec<u8>; 2]> {
+    let (_, height, width) = data.dim();
+    let mut stream = crate::EbccStreamEncoder::new(
+        Vec::new(),
+        config.clone(),
+        (height, width),
+        std::num::NonZeroUsize::MIN.saturating_add(1),
+    )?;
+    for frame in data.outer_iter() {
+        stream.push_frame(frame)?;
+    }
+
+    Ok([crate::ebcc_encode(data, config)?, stream.finish()?])
+}
+
+#[expect(clippy::cast_precision_loss, clippy::suboptimal_flops)]
+fn lat_lon(i: usize, j: usize, height: usize, width: usize) -> (f32, f32) {
+    let lat = -90.0 + (i as f32 / height.max(1) as f32) * 180.0;
+    let lon = -180.0 + (j as f32 / width.max(1) as f32) * 360.0;
+    (lat, lon)
+}
+
+/// `SplitMix64` pseudo-random number generator
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in `[0, 1)`
+    #[expect(clippy::cast_precision_loss)]
+    fn next_f32(&mut self) -> f32 {
+        // use the upper 24 bits, which fit exactly into the f32 mantissa
+        (self.next_u64() >> 40) as f32 / (1_u32 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generators_are_deterministic_and_finite() {
+        let shape = (2, 32, 48);
+
+        for (a, b) in [
+            (temperature(shape), temperature(shape)),
+            (precipitation(shape, 42), precipitation(shape, 42)),
+            (noise(shape, 1.0, 42), noise(shape, 1.0, 42)),
+            (fronts(shape), fronts(shape)),
+            (constant(shape, 1.5), constant(shape, 1.5)),
+            (masked(shape, 1e20), masked(shape, 1e20)),
+        ] {
+            assert_eq!(a.dim(), shape);
+            assert_eq!(a, b);
+            assert!(a.iter().all(|x| x.is_finite()));
+        }
+    }
+
+    #[test]
+    fn test_precipitation_is_non_negative_and_sparse() {
+        let data = precipitation((1, 64, 64), 7);
+
+        assert!(data.iter().all(|&x| x >= 0.0));
+        assert!(data.iter().any(|&x| x == 0.0));
+        assert!(data.iter().any(|&x| x > 0.0));
+    }
+}
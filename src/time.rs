@@ -0,0 +1,378 @@
+//! Time coordinates of frames, to resolve timestamps into frame indices.
+
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::error::{EBCCError, EBCCResult};
+
+/// Calendar of the time coordinates of an [`EBCCTimeAxis`], following the
+/// calendars of the CF conventions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EBCCCalendar {
+    /// Proleptic Gregorian calendar, as used by most observational data
+    #[default]
+    Standard,
+    /// Calendar without leap years (`noleap` or `365_day`), as used by many
+    /// climate models
+    NoLeap,
+    /// Calendar in which every year is a leap year (`all_leap` or `366_day`)
+    AllLeap,
+    /// Calendar in which every month has 30 days (`360_day`)
+    Day360,
+}
+
+impl EBCCCalendar {
+    /// Number of days in the `month` (1-12) of the `year`.
+    #[must_use]
+    pub const fn days_in_month(self, year: i32, month: u8) -> u8 {
+        let leap = match self {
+            Self::Standard => year % 4 == 0 && (year % 100 != 0 || year % 400 == 0),
+            Self::NoLeap => false,
+            Self::AllLeap => true,
+            Self::Day360 => return 30,
+        };
+
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => 0,
+        }
+    }
+
+    /// Number of days from 0000-01-01 to the `date` in this calendar.
+    fn days_since_epoch(self, date: EBCCDateTime) -> i64 {
+        let year = i64::from(date.year);
+        let day = i64::from(date.day) - 1;
+
+        let days_before_month = (1..date.month)
+            .map(|month| i64::from(self.days_in_month(date.year, month)))
+            .sum::<i64>();
+
+        let days_before_year = match self {
+            // the leap days of all years before this one
+            Self::Standard => {
+                let before = year - 1;
+                year * 365 + before.div_euclid(4) - before.div_euclid(100)
+                    + before.div_euclid(400)
+                    + 1
+            }
+            Self::NoLeap => year * 365,
+            Self::AllLeap => year * 366,
+            Self::Day360 => year * 360,
+        };
+
+        days_before_year + days_before_month + day
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::NoLeap => "noleap",
+            Self::AllLeap => "all_leap",
+            Self::Day360 => "360_day",
+        }
+    }
+}
+
+/// Calendar date and time of day, in whole seconds, of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EBCCDateTime {
+    /// Year, which may be negative
+    pub year: i32,
+    /// Month, from 1 to 12
+    pub month: u8,
+    /// Day of the month, from 1
+    pub day: u8,
+    /// Hour, from 0 to 23
+    pub hour: u8,
+    /// Minute, from 0 to 59
+    pub minute: u8,
+    /// Second, from 0 to 59
+    pub second: u8,
+}
+
+impl EBCCDateTime {
+    /// Midnight at the start of the given date.
+    #[must_use]
+    pub const fn date(year: i32, month: u8, day: u8) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        }
+    }
+
+    /// The same date at the given time of day.
+    #[must_use]
+    pub const fn with_time(self, hour: u8, minute: u8, second: u8) -> Self {
+        Self {
+            hour,
+            minute,
+            second,
+            ..self
+        }
+    }
+
+    /// Number of seconds since 0000-01-01T00:00:00 in the `calendar`.
+    fn seconds_since_epoch(self, calendar: EBCCCalendar) -> EBCCResult<i64> {
+        if self.month == 0
+            || self.month > 12
+            || self.day == 0
+            || self.day > calendar.days_in_month(self.year, self.month)
+            || self.hour >= 24
+            || self.minute >= 60
+            || self.second >= 60
+        {
+            return Err(EBCCError::InvalidInput(format!(
+                "{self} does not exist in the {} calendar",
+                calendar.name(),
+            )));
+        }
+
+        let seconds_of_day =
+            i64::from(self.hour) * 3600 + i64::from(self.minute) * 60 + i64::from(self.second);
+
+        Ok(calendar.days_since_epoch(self) * 86400 + seconds_of_day)
+    }
+}
+
+impl fmt::Display for EBCCDateTime {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Regular time axis of the frames of an archive, whose frame `i` is at
+/// `origin + i * step` in the `calendar`.
+///
+/// EBCC archives do not store time coordinates, so the time axis must be
+/// supplied by the caller, e.g. from the time variable of the netCDF file
+/// that the frames were read from. Only fixed steps of whole seconds are
+/// supported, i.e. calendar-dependent steps such as months or years in the
+/// standard calendar cannot be expressed.
+///
+/// Missing time steps are expected to be stored as deleted frames, such
+/// that the frame indices stay aligned with the time axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EBCCTimeAxis {
+    calendar: EBCCCalendar,
+    origin: EBCCDateTime,
+    origin_seconds: i64,
+    step_seconds: i64,
+}
+
+impl EBCCTimeAxis {
+    /// Create a regular time axis whose first frame is at the `origin` and
+    /// whose frames are `step` apart in the `calendar`.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if the `origin` does not exist in the
+    ///   `calendar`, or if the `step` is not a positive whole number of
+    ///   seconds
+    pub fn regular(
+        calendar: EBCCCalendar,
+        origin: EBCCDateTime,
+        step: Duration,
+    ) -> EBCCResult<Self> {
+        let step_seconds = i64::try_from(step.as_secs())
+            .ok()
+            .filter(|seconds| *seconds > 0 && step.subsec_nanos() == 0)
+            .ok_or_else(|| {
+                EBCCError::InvalidInput(format!(
+                    "The time step must be a positive whole number of seconds, got {step:?}"
+                ))
+            })?;
+
+        Ok(Self {
+            calendar,
+            origin,
+            origin_seconds: origin.seconds_since_epoch(calendar)?,
+            step_seconds,
+        })
+    }
+
+    /// The calendar of the time axis
+    #[must_use]
+    pub const fn calendar(&self) -> EBCCCalendar {
+        self.calendar
+    }
+
+    /// The time of the first frame
+    #[must_use]
+    pub const fn origin(&self) -> EBCCDateTime {
+        self.origin
+    }
+
+    /// Resolve the indices of the frames whose times are within
+    /// `start..end` and which exist among the first `frames` frames.
+    ///
+    /// # Errors
+    ///
+    /// - [`EBCCError::InvalidInput`] if `start` or `end` does not exist in
+    ///   the calendar, if `start` is before the origin of the time axis, or if
+    ///   no frame is within `start..end`
+    /// - [`EBCCError::FrameOutOfBounds`] if `start..end` extends beyond the
+    ///   last of the `frames` frames
+    pub fn frame_range(
+        &self,
+        start: EBCCDateTime,
+        end: EBCCDateTime,
+        frames: usize,
+    ) -> EBCCResult<Range<usize>> {
+        let start_seconds = start.seconds_since_epoch(self.calendar)? - self.origin_seconds;
+        let end_seconds = end.seconds_since_epoch(self.calendar)? - self.origin_seconds;
+
+        if start_seconds < 0 {
+            return Err(EBCCError::InvalidInput(format!(
+                "The start time {start} is before the first frame at {}",
+                self.origin,
+            )));
+        }
+
+        // the first and one past the last frame at or after start and end
+        let first = start_seconds.div_euclid(self.step_seconds)
+            + i64::from(start_seconds.rem_euclid(self.step_seconds) != 0);
+        let last = end_seconds.div_euclid(self.step_seconds)
+            + i64::from(end_seconds.rem_euclid(self.step_seconds) != 0);
+
+        let (Ok(first), Ok(last)) = (usize::try_from(first), usize::try_from(last)) else {
+            return Err(no_frames(start, end));
+        };
+        if first >= last {
+            return Err(no_frames(start, end));
+        }
+        if last > frames {
+            return Err(EBCCError::FrameOutOfBounds {
+                frame: last - 1,
+                frames,
+            });
+        }
+
+        Ok(first..last)
+    }
+}
+
+fn no_frames(start: EBCCDateTime, end: EBCCDateTime) -> EBCCError {
+    EBCCError::InvalidInput(format!("No frame is between {start} and {end}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calendars() -> EBCCResult<()> {
+        let date = EBCCDateTime::date;
+
+        for (calendar, year_days) in [
+            (EBCCCalendar::NoLeap, 365),
+            (EBCCCalendar::AllLeap, 366),
+            (EBCCCalendar::Day360, 360),
+        ] {
+            assert_eq!(
+                date(2001, 1, 1).seconds_since_epoch(calendar)?
+                    - date(2000, 1, 1).seconds_since_epoch(calendar)?,
+                year_days * 86400
+            );
+        }
+
+        // 1970-01-01 is 719528 days after 0000-01-01 in the proleptic
+        //  Gregorian calendar
+        assert_eq!(
+            date(1970, 1, 1).seconds_since_epoch(EBCCCalendar::Standard)?,
+            719_528 * 86400
+        );
+        assert_eq!(
+            date(2000, 3, 1).seconds_since_epoch(EBCCCalendar::Standard)?
+                - date(2000, 2, 28).seconds_since_epoch(EBCCCalendar::Standard)?,
+            2 * 86400
+        );
+        assert_eq!(
+            date(1900, 3, 1).seconds_since_epoch(EBCCCalendar::Standard)?
+                - date(1900, 2, 28).seconds_since_epoch(EBCCCalendar::Standard)?,
+            86400
+        );
+
+        assert!(date(2023, 2, 29)
+            .seconds_since_epoch(EBCCCalendar::NoLeap)
+            .is_err());
+        assert!(date(2023, 2, 30)
+            .seconds_since_epoch(EBCCCalendar::Day360)
+            .is_ok());
+        assert!(date(2024, 2, 30)
+            .seconds_since_epoch(EBCCCalendar::Standard)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_range() -> EBCCResult<()> {
+        let axis = EBCCTimeAxis::regular(
+            EBCCCalendar::NoLeap,
+            EBCCDateTime::date(2023, 2, 27),
+            Duration::from_secs(6 * 3600),
+        )?;
+
+        let range = axis.frame_range(
+            EBCCDateTime::date(2023, 2, 28),
+            EBCCDateTime::date(2023, 3, 1).with_time(1, 0, 0),
+            40,
+        )?;
+        assert_eq!(range, 4..9);
+
+        // times between steps select the next step
+        let range = axis.frame_range(
+            EBCCDateTime::date(2023, 2, 27).with_time(3, 0, 0),
+            EBCCDateTime::date(2023, 2, 27).with_time(12, 0, 0),
+            40,
+        )?;
+        assert_eq!(range, 1..2);
+
+        assert!(matches!(
+            axis.frame_range(
+                EBCCDateTime::date(2023, 3, 1),
+                EBCCDateTime::date(2023, 3, 31),
+                40
+            ),
+            Err(EBCCError::FrameOutOfBounds {
+                frame: 127,
+                frames: 40
+            })
+        ));
+        assert!(axis
+            .frame_range(
+                EBCCDateTime::date(2023, 2, 26),
+                EBCCDateTime::date(2023, 2, 28),
+                40
+            )
+            .is_err());
+        assert!(axis
+            .frame_range(
+                EBCCDateTime::date(2023, 2, 27).with_time(1, 0, 0),
+                EBCCDateTime::date(2023, 2, 27).with_time(2, 0, 0),
+                40
+            )
+            .is_err());
+
+        assert!(EBCCTimeAxis::regular(
+            EBCCCalendar::Standard,
+            EBCCDateTime::date(2023, 1, 1),
+            Duration::from_millis(500),
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+//! Optional `tracing` instrumentation of encoding and decoding.
+//!
+//! With the `tracing` feature, encoding and decoding are instrumented with
+//! debug-level spans for input validation, the input copy, and the EBCC C
+//! call, and with debug-level events that report the achieved compression
+//! ratio. Fallbacks, e.g. retrying a failed encode with tiles, are reported
+//! as warn-level events. The EBCC C library performs the base-layer
+//! `JPEG2000` encoding, the residual coding, and the zstd entropy coding
+//! within one call, so these stages share a single span. Without the
+//! `tracing` feature, the instrumentation compiles to nothing.
+
+/// Enter a debug-level span until the end of the current scope.
+macro_rules! debug_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+/// Emit a debug-level event.
+macro_rules! debug_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($($arg)*);
+    };
+}
+
+/// Emit a warn-level event.
+macro_rules! warn_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::warn!($($arg)*);
+    };
+}
+
+pub(crate) use debug_event;
+pub(crate) use debug_span;
+pub(crate) use warn_event;
+
+/// Compression ratio of `elements` `f32` values that are compressed into
+/// `compressed_bytes` bytes
+#[cfg(feature = "tracing")]
+#[expect(clippy::cast_precision_loss)]
+pub fn compression_ratio(elements: usize, compressed_bytes: usize) -> f64 {
+    (elements as f64 * std::mem::size_of::<f32>() as f64) / compressed_bytes as f64
+}
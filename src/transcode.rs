@@ -0,0 +1,424 @@
+//! Re-encoding of compressed data with a new configuration.
+
+use std::io::Cursor;
+use std::num::NonZeroUsize;
+
+use ndarray::{Array, Array2, ArrayView, Axis};
+
+use crate::codec::{ebcc_decode_into, ebcc_encode, EbccDim};
+use crate::config::{EBCCBaseMode, EBCCConfig, EBCCResidualType};
+use crate::container::{is_ebcc_container, EbccContainer, EbccContainerWriter};
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+use crate::limits::EBCCDecodeOptions;
+use crate::size::u64_to_usize;
+use crate::stream::{
+    ebcc_visit_stream_body, is_ebcc_stream, read_stream_header, EbccStreamEncoder,
+    EbccStreamSegmentHeader, EBCC_STREAM_MAGIC,
+};
+use crate::verify::data_range;
+
+/// Re-encode the `compressed` data, which was compressed with the
+/// `source_config`, with the `new_config`, e.g. to recompress an archive with
+/// a looser error bound.
+///
+/// The data is decoded and re-encoded piece by piece, such that only a
+/// bounded part of it is in memory at a time:
+/// - EBCC frame streams are transcoded segment by segment into a stream with
+///   segments of the same size
+/// - EBCC containers are transcoded frame by frame into a container, which
+///   drops deleted frames like [`compact`][crate::container::compact] and
+///   keeps the access tags of the other frames
+/// - [`ebcc_encode`] payloads, which must start with an [`EBCCHeader`] that
+///   records their shape, are compressed as a whole and are therefore
+///   decoded and transcoded as a whole into a payload, such that they are
+///   bounded only by the 4 GiB output limit of the default
+///   [`EBCCDecodeOptions`]
+///
+/// The compressed data does not record its error bound, only the
+/// [fingerprint][EBCCConfig::fingerprint] of its configuration, so the
+/// `source_config` is checked against the fingerprint in the header of
+/// payloads and streams, and in the header of every frame of containers.
+/// The frames of containers before version 4 record no fingerprint, so they
+/// are refused unless the caller vouches for the `source_config` with
+/// [`ebcc_transcode_allow_legacy`].
+///
+/// Transcoding cannot restore the precision that was lost when the data was
+/// first compressed, so the error bound of the `new_config` applies to the
+/// decoded data, not to the original data. A `new_config` whose error bound
+/// is tighter than the one of the `source_config`, which the transcoded data
+/// could not meet, is refused, such that the transcoded data is within
+/// twice the new error bound of the original data. An error bound that is
+/// below the `f32` resolution of the decoded data, i.e. half an ulp of its
+/// largest value, cannot be supported either and is refused.
+///
+/// # Errors
+///
+/// - [`EBCCError::EmptyInput`] if `compressed` is empty
+/// - [`EBCCError::InvalidInput`] if `compressed` is a legacy headerless
+///   payload, whose shape is unknown
+/// - [`EBCCError::InvalidConfig`] if the `source_config` does not match the
+///   recorded fingerprint, or if the error bound of the `new_config` is
+///   tighter than the one of the `source_config`, of a different kind, or
+///   below the `f32` resolution of the decoded data
+/// - [`EBCCError::InvalidInput`] if `compressed` is a container before
+///   version 4, whose frames record no fingerprint
+/// - [`EBCCError::TooManyFrames`] or [`EBCCError::OutputTooLarge`] if the
+///   shape of a payload exceeds the default [`EBCCDecodeOptions`]
+/// - all errors that [`ebcc_decode_into`] and [`ebcc_encode`],
+///   [`EbccStreamEncoder`], or [`EbccContainerWriter`] can return
+///
+/// # Examples
+///
+/// ```rust
+/// use ebcc::{ebcc_decode_into, ebcc_encode, ebcc_transcode, EBCCConfig};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let data = Array::from_shape_fn((2, 32, 32), |(t, y, x)| (t + y + x) as f32);
+/// let tight = EBCCConfig::max_absolute_error_bounded(0.1);
+/// let compressed = ebcc_encode(data.view(), &tight)?;
+///
+/// let loose = EBCCConfig::max_absolute_error_bounded(0.5);
+/// let loosened = ebcc_transcode(&compressed, &tight, &loose)?;
+///
+/// let mut decompressed = Array::zeros(data.dim());
+/// ebcc_decode_into(&loosened, decompressed.view_mut())?;
+/// assert!(data.iter().zip(&decompressed).all(|(a, b)| (a - b).abs() <= 0.1 + 0.5));
+///
+/// // the archive cannot be tightened
+/// assert!(ebcc_transcode(&loosened, &loose, &tight).is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_transcode(
+    compressed: &[u8],
+    source_config: &EBCCConfig,
+    new_config: &EBCCConfig,
+) -> EBCCResult<Vec<u8>> {
+    transcode(compressed, source_config, new_config, false)
+}
+
+/// Re-encode the `compressed` data like [`ebcc_transcode`], but also accept
+/// containers before version 4.
+///
+/// The frames of such containers record no
+/// [fingerprint][EBCCConfig::fingerprint] against which the `source_config`
+/// could be checked, so the caller is responsible for it: if it has a tighter error bound than the one that the frames were actually
+/// compressed with, the transcoded data may exceed the error bound of the
+/// `new_config`.
+///
+/// # Errors
+///
+/// All errors that [`ebcc_transcode`] can return, except for the refusal of
+/// containers before version 4.
+pub fn ebcc_transcode_allow_legacy(
+    compressed: &[u8],
+    source_config: &EBCCConfig,
+    new_config: &EBCCConfig,
+) -> EBCCResult<Vec<u8>> {
+    transcode(compressed, source_config, new_config, true)
+}
+
+fn transcode(
+    compressed: &[u8],
+    source_config: &EBCCConfig,
+    new_config: &EBCCConfig,
+    allow_legacy: bool,
+) -> EBCCResult<Vec<u8>> {
+    if compressed.is_empty() {
+        return Err(EBCCError::EmptyInput);
+    }
+
+    check_not_tighter(source_config, new_config)?;
+
+    if is_ebcc_stream(compressed) {
+        return transcode_stream(compressed, source_config, new_config);
+    }
+
+    if is_ebcc_container(compressed) {
+        return transcode_container(compressed, source_config, new_config, allow_legacy);
+    }
+
+    let Some(header) = EBCCHeader::parse(compressed)? else {
+        return Err(EBCCError::InvalidInput(String::from(
+            "Transcoding requires an EBCC header with the shape of the data",
+        )));
+    };
+    source_config.check_fingerprint(header.recorded_config_fingerprint())?;
+
+    // the untrusted shape is checked before anything is allocated
+    let shape = <(usize, usize, usize)>::from(header.shape);
+    EBCCDecodeOptions::new().check_shape(shape)?;
+
+    let mut decompressed = Array::zeros(shape);
+    ebcc_decode_into(compressed, decompressed.view_mut())?;
+    check_supported_error_bound(decompressed.view(), new_config)?;
+
+    ebcc_encode(decompressed.view(), new_config)
+}
+
+fn transcode_stream(
+    compressed: &[u8],
+    source_config: &EBCCConfig,
+    new_config: &EBCCConfig,
+) -> EBCCResult<Vec<u8>> {
+    let mut reader = compressed
+        .get(EBCC_STREAM_MAGIC.len()..)
+        .unwrap_or_default();
+    // the header is read again when the stream body is visited
+    let header = read_stream_header(&mut { reader })?;
+    source_config.check_fingerprint(header.config_fingerprint())?;
+    let frame_shape = (
+        u64_to_usize(header.height())?,
+        u64_to_usize(header.width())?,
+    );
+
+    // the trailer at the end of the stream records its number of frames
+    let trailer = compressed
+        .last_chunk::<{ EbccStreamSegmentHeader::LEN }>()
+        .map(EbccStreamSegmentHeader::from_bytes)
+        .filter(EbccStreamSegmentHeader::is_trailer)
+        .ok_or_else(|| EBCCError::InvalidInput(String::from("EBCC stream is truncated")))?;
+    let frames = u64_to_usize(trailer.payload_len())?;
+
+    let mut encoder = None;
+    ebcc_visit_stream_body(
+        &mut reader,
+        (frames, frame_shape.0, frame_shape.1),
+        |_, segment| {
+            check_supported_error_bound(segment, new_config)?;
+
+            // the first segment determines the segment size of the new stream
+            let encoder = match &mut encoder {
+                Some(encoder) => encoder,
+                None => encoder.insert(EbccStreamEncoder::new(
+                    Vec::new(),
+                    new_config.clone(),
+                    frame_shape,
+                    NonZeroUsize::new(segment.len_of(Axis(0))).unwrap_or(NonZeroUsize::MIN),
+                )?),
+            };
+            segment
+                .outer_iter()
+                .try_for_each(|frame| encoder.push_frame(frame))
+        },
+    )?;
+
+    match encoder {
+        Some(encoder) => encoder.finish(),
+        None => EbccStreamEncoder::new(
+            Vec::new(),
+            new_config.clone(),
+            frame_shape,
+            NonZeroUsize::MIN,
+        )?
+        .finish(),
+    }
+}
+
+fn transcode_container(
+    compressed: &[u8],
+    source_config: &EBCCConfig,
+    new_config: &EBCCConfig,
+    allow_legacy: bool,
+) -> EBCCResult<Vec<u8>> {
+    let mut container = EbccContainer::open(Cursor::new(compressed))?;
+    let frame_shape = container.frame_shape();
+
+    // every frame may have been compressed with a different configuration
+    for fingerprint in container.frame_config_fingerprints()? {
+        if fingerprint.is_none() && !allow_legacy {
+            return Err(EBCCError::InvalidInput(String::from(
+                "EBCC containers before version 4, and frames with an EBCC header before version \
+                 3, record no configuration fingerprint to check the source configuration against",
+            )));
+        }
+        source_config.check_fingerprint(fingerprint)?;
+    }
+
+    let mut writer = EbccContainerWriter::new(Vec::new(), new_config.clone(), frame_shape)?;
+    let mut frame_data = Array2::zeros(frame_shape);
+    let mut transcoded_frames = 0;
+    for frame in 0..container.frames() {
+        if container.is_deleted(frame)? {
+            continue;
+        }
+
+        container.decode_frame_into(frame, frame_data.view_mut())?;
+        check_supported_error_bound(frame_data.view().insert_axis(Axis(0)), new_config)?;
+        writer.push_frame(frame_data.view())?;
+
+        if let Some(tag) = container.access_tag(frame)? {
+            writer.set_access_tag(transcoded_frames, tag)?;
+        }
+        transcoded_frames += 1;
+    }
+
+    writer.finish()
+}
+
+/// Check that the error bound of the `new_config` is not tighter than the
+/// one of the `source_config`, with which the data was compressed.
+fn check_not_tighter(source_config: &EBCCConfig, new_config: &EBCCConfig) -> EBCCResult<()> {
+    // lossless data supports any error bound
+    if source_config.base_mode == EBCCBaseMode::Stored {
+        return Ok(());
+    }
+
+    match (
+        source_config.residual_compression_type,
+        new_config.residual_compression_type,
+    ) {
+        (_, EBCCResidualType::Jpeg2000Only) => Ok(()),
+        (EBCCResidualType::AbsoluteError(source), EBCCResidualType::AbsoluteError(new))
+        | (EBCCResidualType::RelativeError(source), EBCCResidualType::RelativeError(new))
+            if new >= source =>
+        {
+            Ok(())
+        }
+        (source, new) => Err(EBCCError::InvalidConfig(format!(
+            "Cannot transcode data that was compressed with {source:?} to the tighter or \
+             incomparable {new:?}",
+        ))),
+    }
+}
+
+/// Check that the error bound of the `config` is not below the `f32`
+/// resolution of the decoded `data`, which transcoding cannot support.
+fn check_supported_error_bound(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+) -> EBCCResult<()> {
+    let error = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => error,
+        // constant data is reproduced exactly under any relative bound
+        EBCCResidualType::RelativeError(error) => match data_range(data) {
+            range if range > 0.0 => range * error,
+            _ => return Ok(()),
+        },
+        EBCCResidualType::Jpeg2000Only => return Ok(()),
+    };
+
+    let max_abs = data
+        .iter()
+        .filter(|x| x.is_finite())
+        .fold(0.0_f32, |max, x| max.max(x.abs()));
+    let resolution = max_abs * f32::EPSILON * 0.5;
+    if error < resolution {
+        return Err(EBCCError::InvalidConfig(format!(
+            "Cannot transcode to an error bound of {error}, which is below the f32 resolution {resolution} of the decoded data",
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::{ebcc_encode_per_frame, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_transcode() -> EBCCResult<()> {
+        let data = testdata::temperature((4, 32, 48));
+        let tight = EBCCConfig::max_absolute_error_bounded(0.05);
+        let loose = EBCCConfig::max_absolute_error_bounded(0.5);
+
+        let mut stream = EbccStreamEncoder::new(
+            Vec::new(),
+            tight.clone(),
+            (32, 48),
+            NonZeroUsize::new(3).unwrap_or(NonZeroUsize::MIN),
+        )?;
+        let mut container = EbccContainerWriter::new(Vec::new(), tight.clone(), (32, 48))?;
+        for frame in data.outer_iter() {
+            stream.push_frame(frame)?;
+            container.push_frame(frame)?;
+        }
+        container.set_access_tag(3, "restricted")?;
+        let container = container.finish()?;
+
+        for compressed in [
+            ebcc_encode(data.view(), &tight)?,
+            stream.finish()?,
+            container.clone(),
+        ] {
+            let transcoded = ebcc_transcode(&compressed, &tight, &loose)?;
+            assert_eq!(is_ebcc_stream(&transcoded), is_ebcc_stream(&compressed));
+            assert_eq!(
+                is_ebcc_container(&transcoded),
+                is_ebcc_container(&compressed)
+            );
+
+            let mut decoded = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, decoded.view_mut())?;
+            let mut decompressed = Array::zeros(data.dim());
+            ebcc_decode_into(&transcoded, decompressed.view_mut())?;
+            check_error_bound(decoded.view(), decompressed.view(), &loose)?;
+            check_error_bound(
+                data.view(),
+                decompressed.view(),
+                &EBCCConfig::max_absolute_error_bounded(0.05 + 0.5),
+            )?;
+
+            // tighter bounds than the source bound are refused
+            assert!(matches!(
+                ebcc_transcode(&transcoded, &loose, &tight),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+
+        let transcoded = ebcc_transcode(&container, &tight, &loose)?;
+        let transcoded = EbccContainer::open(Cursor::new(transcoded))?;
+        assert_eq!(transcoded.access_tag(3)?, Some("restricted"));
+
+        let compressed = ebcc_encode(data.view(), &tight)?;
+        // the source configuration must match the recorded fingerprint
+        assert!(matches!(
+            ebcc_transcode(&compressed, &tight.clone().with_base_cr(20.0), &loose),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+        // of every frame of a container
+        let mixed = ebcc_encode_per_frame(
+            data.view(),
+            &[
+                tight.clone(),
+                tight.clone(),
+                tight.clone(),
+                tight.clone().with_base_cr(20.0),
+            ],
+        )?;
+        assert!(matches!(
+            ebcc_transcode(&mixed, &tight, &loose),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+        // bounds of a different kind cannot be compared
+        assert!(matches!(
+            ebcc_transcode(
+                &compressed,
+                &tight,
+                &EBCCConfig::relative_error_bounded(0.1)
+            ),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        // bounds below the f32 resolution of the data are refused
+        let lossless = EBCCConfig {
+            base_mode: EBCCBaseMode::Stored,
+            ..EBCCConfig::new()
+        };
+        let stored = ebcc_encode(data.view(), &lossless)?;
+        ebcc_transcode(&stored, &lossless, &tight)?;
+        assert!(matches!(
+            ebcc_transcode(
+                &stored,
+                &lossless,
+                &EBCCConfig::max_absolute_error_bounded(1e-9)
+            ),
+            Err(EBCCError::InvalidConfig(_))
+        ));
+
+        Ok(())
+    }
+}
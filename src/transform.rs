@@ -0,0 +1,345 @@
+//! Invertible pre-transforms that are applied to the data before encoding.
+//!
+//! Fields that span orders of magnitude, e.g. precipitation or specific
+//! humidity, compress poorly in linear space. An [`EBCCTransform`] maps the
+//! data into a space in which it compresses better, e.g. a logarithmic one,
+//! and is inverted when decoding. The absolute or relative error bound of the
+//! original data is mapped through the transform into an absolute error
+//! bound of the transformed data, such that it still holds for the original
+//! data after the inverse transform.
+//!
+//! # Payload format
+//!
+//! All integers are stored in little-endian byte order.
+//!
+//! - header: [`EBCC_TRANSFORM_MAGIC`], the format version as `u32`, the
+//!   transform as `u8` (`0` for linear, `1` for log1p, and `2` for signed
+//!   log), the scale and offset of a linear transform as `f32`s (zero
+//!   otherwise), and the number of frames, the frame height, and the frame
+//!   width as `u64`s
+//! - the inner EBCC payload of the transformed data
+
+use ndarray::ArrayView;
+
+use crate::codec::{ebcc_decode_nested, ebcc_encode_c_buffer_with_scratch, EbccDim};
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+use crate::reader::Format;
+use crate::size::{data_len, u64_to_usize, usize_to_u64};
+use crate::verify::data_range;
+
+/// Magic bytes at the start of every transformed EBCC payload.
+pub const EBCC_TRANSFORM_MAGIC: &[u8; 8] = b"EBCCTRFM";
+
+/// Version of the transformed EBCC payload format.
+const EBCC_TRANSFORM_VERSION: u32 = 1;
+
+/// Name of the format in the errors of its readers
+const FORMAT: Format = Format("EBCC transform data");
+
+/// Length of the transformed payload header
+const EBCC_TRANSFORM_HEADER_LEN: usize = 8 + 4 + 1 + 2 * 4 + 3 * 8;
+
+const TRANSFORM_LINEAR: u8 = 0;
+const TRANSFORM_LOG1P: u8 = 1;
+const TRANSFORM_SIGNED_LOG: u8 = 2;
+
+/// Invertible transform that is applied to the data before encoding, see
+/// [`EBCCConfig::transform`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EBCCTransform {
+    /// `y = x * scale + offset`, e.g. for unit conversions, with a finite
+    /// non-zero `scale` and a finite `offset`
+    Linear {
+        /// Factor that the data is multiplied with
+        scale: f32,
+        /// Offset that is added to the scaled data
+        offset: f32,
+    },
+    /// `y = ln(1 + x)`, for non-negative data such as precipitation, which
+    /// must be greater than `-1`
+    Log1p,
+    /// `y = sign(x) * ln(1 + |x|)`, for data of both signs that spans orders
+    /// of magnitude
+    SignedLog,
+}
+
+impl EBCCTransform {
+    /// Validate that the parameters of a linear transform are finite and that
+    /// its scale is non-zero.
+    pub(crate) fn validate(self) -> EBCCResult<()> {
+        match self {
+            Self::Linear { scale, offset } => {
+                if !(scale.is_finite() && scale != 0.0 && offset.is_finite()) {
+                    return Err(EBCCError::InvalidConfig(format!(
+                        "Linear transform requires a finite non-zero scale and a finite offset, got scale {scale} and offset {offset}"
+                    )));
+                }
+                Ok(())
+            }
+            Self::Log1p | Self::SignedLog => Ok(()),
+        }
+    }
+
+    #[expect(clippy::cast_possible_truncation)]
+    fn forward(self, x: f32) -> f32 {
+        let x = f64::from(x);
+        (match self {
+            Self::Linear { scale, offset } => x.mul_add(f64::from(scale), f64::from(offset)),
+            Self::Log1p => x.ln_1p(),
+            Self::SignedLog => x.signum() * x.abs().ln_1p(),
+        }) as f32
+    }
+
+    #[expect(clippy::cast_possible_truncation)]
+    fn inverse(self, y: f32) -> f32 {
+        let y = f64::from(y);
+        (match self {
+            Self::Linear { scale, offset } => (y - f64::from(offset)) / f64::from(scale),
+            Self::Log1p => y.exp_m1(),
+            Self::SignedLog => y.signum() * y.abs().exp_m1(),
+        }) as f32
+    }
+
+    /// Map the absolute `error` bound of the original `data` to an absolute
+    /// error bound of the transformed data, or [`None`] if no positive bound
+    /// remains.
+    #[expect(clippy::cast_possible_truncation)]
+    fn transformed_error_bound(self, data: ArrayView<f32, EbccDim>, error: f32) -> Option<f32> {
+        // the transform and its inverse are both rounded to f32, by at most
+        //  half an ulp of the largest value, which the error bound must cover
+        let max_abs = data.iter().fold(0.0_f32, |max, x| max.max(x.abs()));
+        let error = f64::from((max_abs * f32::EPSILON).mul_add(-2.0, error));
+
+        let transformed_error = match self {
+            Self::Linear { scale, .. } => error * f64::from(scale).abs(),
+            // |exp_m1(y + d) - exp_m1(y)| = (1 + x) * exp_m1(d) grows with x,
+            //  so the bound must hold for the largest value, and crossing
+            //  zero with the signed log only shrinks the error
+            Self::Log1p | Self::SignedLog => {
+                let max = match self {
+                    Self::Log1p => data.iter().fold(0.0_f32, |max, &x| max.max(x)),
+                    _ => max_abs,
+                };
+                (error / (1.0 + f64::from(max))).ln_1p()
+            }
+        };
+
+        let max_transformed = data
+            .iter()
+            .fold(0.0_f32, |max, &x| max.max(self.forward(x).abs()));
+        let transformed_error =
+            (max_transformed * f32::EPSILON).mul_add(-2.0, transformed_error as f32);
+
+        (transformed_error > 0.0).then_some(transformed_error)
+    }
+}
+
+/// Returns `true` if the `compressed_data` starts with the
+/// [`EBCC_TRANSFORM_MAGIC`].
+pub fn is_transformed(compressed_data: &[u8]) -> bool {
+    compressed_data.starts_with(EBCC_TRANSFORM_MAGIC)
+}
+
+/// Encode a 3D data array into a transformed payload with the
+/// [`EBCCConfig::transform`] of the `config`, which must be set.
+pub fn transform_encode(
+    data: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+    scratch: &mut Vec<f32>,
+) -> EBCCResult<Vec<u8>> {
+    let Some(transform) = config.transform else {
+        return Err(EBCCError::InvalidConfig(String::from(
+            "Transformed encoding requires a transform",
+        )));
+    };
+
+    if transform == EBCCTransform::Log1p {
+        if let Some(x) = data.iter().find(|x| **x <= -1.0) {
+            return Err(EBCCError::InvalidInput(format!(
+                "Log1p transform requires values greater than -1, got {x}"
+            )));
+        }
+    }
+
+    let error = match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => Some(error),
+        EBCCResidualType::RelativeError(error) => Some(data_range(data) * error),
+        EBCCResidualType::Jpeg2000Only => None,
+    };
+    let residual_compression_type = match error {
+        Some(error) => EBCCResidualType::AbsoluteError(
+            transform
+                .transformed_error_bound(data, error)
+                .ok_or_else(|| {
+                    EBCCError::InvalidConfig(format!(
+                        "Error bound {error} is too tight for the {transform:?} transform of the data"
+                    ))
+                })?,
+        ),
+        None => EBCCResidualType::Jpeg2000Only,
+    };
+
+    let transformed = data.mapv(|x| transform.forward(x));
+
+    let inner_config = EBCCConfig {
+        residual_compression_type,
+        transform: None,
+        expansion_guard: None,
+        ..config.clone()
+    };
+    let inner = ebcc_encode_c_buffer_with_scratch(transformed.view(), &inner_config, scratch)?;
+    let inner = inner.as_slice();
+
+    let (kind, scale, offset) = match transform {
+        EBCCTransform::Linear { scale, offset } => (TRANSFORM_LINEAR, scale, offset),
+        EBCCTransform::Log1p => (TRANSFORM_LOG1P, 0.0, 0.0),
+        EBCCTransform::SignedLog => (TRANSFORM_SIGNED_LOG, 0.0, 0.0),
+    };
+
+    let mut compressed_data = Vec::with_capacity(EBCC_TRANSFORM_HEADER_LEN + inner.len());
+    compressed_data.extend_from_slice(EBCC_TRANSFORM_MAGIC);
+    compressed_data.extend_from_slice(&EBCC_TRANSFORM_VERSION.to_le_bytes());
+    compressed_data.push(kind);
+    compressed_data.extend_from_slice(&scale.to_le_bytes());
+    compressed_data.extend_from_slice(&offset.to_le_bytes());
+    for dim in <[usize; 3]>::from(data.dim()) {
+        compressed_data.extend_from_slice(&usize_to_u64(dim)?.to_le_bytes());
+    }
+    compressed_data.extend_from_slice(inner);
+
+    Ok(compressed_data)
+}
+
+/// Decode a transformed payload of the expected `shape`, which may be
+/// modified during decoding, into the flattened 3D data array.
+pub fn transform_decode(
+    compressed_data: &mut [u8],
+    expected_shape: (usize, usize, usize),
+    depth: usize,
+) -> EBCCResult<Vec<f32>> {
+    let Some(mut reader) = compressed_data.strip_prefix(EBCC_TRANSFORM_MAGIC.as_slice()) else {
+        return Err(FORMAT.corrupted());
+    };
+
+    let version = u32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    if version != EBCC_TRANSFORM_VERSION {
+        return Err(EBCCError::DecompressionError(
+            format!("Unsupported EBCC transform version: {version}").into(),
+        ));
+    }
+
+    let [kind] = FORMAT.read_array(&mut reader)?;
+    let scale = f32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    let offset = f32::from_le_bytes(FORMAT.read_array(&mut reader)?);
+    let transform = match kind {
+        TRANSFORM_LINEAR => EBCCTransform::Linear { scale, offset },
+        TRANSFORM_LOG1P => EBCCTransform::Log1p,
+        TRANSFORM_SIGNED_LOG => EBCCTransform::SignedLog,
+        kind => {
+            return Err(EBCCError::DecompressionError(
+                format!("Unsupported EBCC transform: {kind}").into(),
+            ))
+        }
+    };
+    transform.validate().map_err(|_| FORMAT.corrupted())?;
+
+    let mut shape = [0; 3];
+    for dim in &mut shape {
+        *dim = u64_to_usize(u64::from_le_bytes(FORMAT.read_array(&mut reader)?))?;
+    }
+    // the declared shape is validated before anything is allocated
+    let expected_shape = <[usize; 3]>::from(expected_shape);
+    if shape != expected_shape {
+        return Err(EBCCError::ShapeMismatch {
+            expected: shape,
+            actual: expected_shape,
+        });
+    }
+    data_len(expected_shape.into())?;
+
+    let Some(inner) = compressed_data.get_mut(EBCC_TRANSFORM_HEADER_LEN..) else {
+        return Err(FORMAT.truncated());
+    };
+
+    Ok(ebcc_decode_nested(inner, expected_shape.into(), depth + 1)?
+        .as_slice()
+        .iter()
+        .map(|&y| transform.inverse(y))
+        .collect())
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, ebcc_encode, testdata, verify::check_error_bound};
+
+    #[test]
+    fn test_transform_roundtrip() -> EBCCResult<()> {
+        let temperature = testdata::temperature((2, 32, 48));
+        // precipitation-like data that spans several orders of magnitude
+        let precipitation = temperature.mapv(|t| ((t - 280.0) / 4.0).exp());
+        let anomaly = temperature.mapv(|t| ((t - 285.0) / 10.0).powi(3));
+
+        for (data, transform) in [
+            (
+                &temperature,
+                EBCCTransform::Linear {
+                    scale: 1.8,
+                    offset: -459.67,
+                },
+            ),
+            (&precipitation, EBCCTransform::Log1p),
+            (&anomaly, EBCCTransform::SignedLog),
+        ] {
+            for config in [
+                EBCCConfig::max_absolute_error_bounded(0.01),
+                EBCCConfig::relative_error_bounded(0.001),
+            ] {
+                let config = config.with_transform(transform);
+
+                let compressed = ebcc_encode(data.view(), &config)?;
+                let mut decompressed = Array::zeros(data.dim());
+                ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+                check_error_bound(data.view(), decompressed.view(), &config)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_transform() {
+        let data = testdata::temperature((1, 32, 32));
+
+        for transform in [
+            EBCCTransform::Linear {
+                scale: 0.0,
+                offset: 0.0,
+            },
+            EBCCTransform::Linear {
+                scale: 1.0,
+                offset: f32::NAN,
+            },
+        ] {
+            assert!(matches!(
+                ebcc_encode(
+                    data.view(),
+                    &EBCCConfig::max_absolute_error_bounded(0.1).with_transform(transform)
+                ),
+                Err(EBCCError::InvalidConfig(_))
+            ));
+        }
+
+        assert!(matches!(
+            ebcc_encode(
+                (data - 300.0).view(),
+                &EBCCConfig::max_absolute_error_bounded(0.1).with_transform(EBCCTransform::Log1p)
+            ),
+            Err(EBCCError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+//! Unit conversions that are applied while decoding.
+
+use ndarray::ArrayViewMut;
+
+use crate::codec::{ebcc_decode_map_into, EbccDim};
+use crate::error::{EBCCError, EBCCResult};
+use crate::header::EBCCHeader;
+
+/// Affine unit conversion `y = x * scale + offset` that is applied to the
+/// decoded data by [`ebcc_decode_converted_into`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EBCCUnitConversion {
+    /// Factor that the decoded data is multiplied with
+    pub scale: f64,
+    /// Offset that is added to the scaled data
+    pub offset: f64,
+}
+
+impl EBCCUnitConversion {
+    /// The identity conversion, which keeps the units of the data
+    pub const IDENTITY: Self = Self::new(1.0, 0.0);
+    /// Conversion from Kelvin to degrees Celsius
+    pub const KELVIN_TO_CELSIUS: Self = Self::new(1.0, -273.15);
+    /// Conversion from Pascal to hectopascal
+    pub const PASCAL_TO_HECTOPASCAL: Self = Self::new(0.01, 0.0);
+
+    /// Create a new conversion `y = x * scale + offset`.
+    #[must_use]
+    pub const fn new(scale: f64, offset: f64) -> Self {
+        Self { scale, offset }
+    }
+
+    /// Convert an absolute error bound of the data into the converted units,
+    /// which only depends on the [`scale`][Self::scale].
+    #[must_use]
+    #[expect(clippy::cast_possible_truncation)]
+    pub fn convert_error_bound(self, error: f32) -> f32 {
+        (f64::from(error) * self.scale.abs()) as f32
+    }
+
+    #[expect(clippy::cast_possible_truncation)]
+    fn apply(self, x: f32) -> f32 {
+        f64::from(x).mul_add(self.scale, self.offset) as f32
+    }
+}
+
+/// Metadata of the data decoded by [`ebcc_decode_converted_into`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct EBCCDecodeMetadata {
+    /// The header of a single [`ebcc_encode`][crate::ebcc_encode] payload,
+    /// or [`None`] for other formats and legacy headerless payloads
+    pub header: Option<EBCCHeader>,
+    /// The unit conversion that was applied to the decoded data
+    pub conversion: EBCCUnitConversion,
+}
+
+/// Decode into a 3D data array using EBCC decompression, and convert the
+/// decoded data into different units.
+///
+/// The `conversion` is applied while the decoded data is copied into the
+/// `decompressed_data`, such that no second pass over the data is needed. It
+/// is recorded in the returned metadata, e.g. to label the output.
+///
+/// # Errors
+///
+/// - [`EBCCError::InvalidInput`] if the scale or offset of the `conversion`
+///   are not finite
+/// - all errors that [`ebcc_decode_into`][crate::ebcc_decode_into] can return
+///
+/// # Examples
+///
+#[cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#[cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+/// use ebcc::{ebcc_decode_converted_into, ebcc_encode, EBCCConfig, EBCCUnitConversion};
+/// use ndarray::Array;
+///
+/// # fn main() -> ebcc::EBCCResult<()> {
+/// let kelvin = Array::from_elem((1, 32, 32), 300.0_f32);
+/// let compressed = ebcc_encode(kelvin.view(), &EBCCConfig::max_absolute_error_bounded(0.01))?;
+///
+/// let mut celsius = Array::zeros(kelvin.dim());
+/// let metadata = ebcc_decode_converted_into(
+///     &compressed,
+///     celsius.view_mut(),
+///     EBCCUnitConversion::KELVIN_TO_CELSIUS,
+/// )?;
+/// assert_eq!(metadata.conversion, EBCCUnitConversion::KELVIN_TO_CELSIUS);
+/// assert!(celsius.iter().all(|t| (t - 26.85).abs() <= 0.01));
+/// # Ok(())
+/// # }
+/// ```
+pub fn ebcc_decode_converted_into(
+    compressed_data: &[u8],
+    decompressed_data: ArrayViewMut<f32, EbccDim>,
+    conversion: EBCCUnitConversion,
+) -> EBCCResult<EBCCDecodeMetadata> {
+    if !(conversion.scale.is_finite() && conversion.offset.is_finite()) {
+        return Err(EBCCError::InvalidInput(format!(
+            "Unit conversion requires a finite scale and offset, got scale {} and offset {}",
+            conversion.scale, conversion.offset,
+        )));
+    }
+
+    ebcc_decode_map_into(compressed_data, decompressed_data, |x| conversion.apply(x))?;
+
+    Ok(EBCCDecodeMetadata {
+        header: EBCCHeader::parse(compressed_data)?,
+        conversion,
+    })
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+    use crate::{ebcc_decode_into, testdata, EBCCConfig};
+
+    #[test]
+    fn test_decode_converted() -> EBCCResult<()> {
+        let data = testdata::temperature((3, 32, 48));
+        let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+        let [compressed, stream] = testdata::encode_single_and_stream(data.view(), &config)?;
+        for (compressed, has_header) in [(compressed, true), (stream, false)] {
+            let mut expected = Array::zeros(data.dim());
+            ebcc_decode_into(&compressed, expected.view_mut())?;
+
+            let mut converted = Array::zeros(data.dim());
+            let metadata = ebcc_decode_converted_into(
+                &compressed,
+                converted.view_mut(),
+                EBCCUnitConversion::KELVIN_TO_CELSIUS,
+            )?;
+
+            assert_eq!(metadata.conversion, EBCCUnitConversion::KELVIN_TO_CELSIUS);
+            assert_eq!(metadata.header.is_some(), has_header);
+            assert!(expected
+                .iter()
+                .zip(converted.iter())
+                .all(|(k, c)| (k - 273.15 - c).abs() < 1e-4));
+        }
+
+        assert!(matches!(
+            ebcc_decode_converted_into(
+                &[0],
+                Array::zeros(data.dim()).view_mut(),
+                EBCCUnitConversion::new(f64::NAN, 0.0),
+            ),
+            Err(EBCCError::InvalidInput(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+//! Invariant checks for decompressed data.
+//!
+//! These helpers can be reused by downstream crates, e.g. in their own tests,
+//! to assert that the error bound they requested is honored:
+//!
+#![cfg_attr(all(feature = "encode", feature = "decode"), doc = "```rust")]
+#![cfg_attr(
+    not(all(feature = "encode", feature = "decode")),
+    doc = "```rust,ignore"
+)]
+//! use ebcc::{ebcc_decode_into, ebcc_encode, testdata, EBCCConfig};
+//! use ndarray::Array;
+//!
+//! # fn main() -> ebcc::EBCCResult<()> {
+//! let data = testdata::temperature((1, 64, 64));
+//! let config = EBCCConfig::max_absolute_error_bounded(0.1);
+//!
+//! let compressed = ebcc_encode(data.view(), &config)?;
+//! let mut decompressed = Array::zeros(data.dim());
+//! ebcc_decode_into(&compressed, decompressed.view_mut())?;
+//!
+//! ebcc::verify::check_error_bound(data.view(), decompressed.view(), &config)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ndarray::ArrayView;
+
+use crate::codec::EbccDim;
+use crate::config::{EBCCConfig, EBCCResidualType};
+use crate::error::{EBCCError, EBCCResult};
+
+/// Absolute tolerance of pointwise absolute error bounds, which accounts for
+/// the rounding of the `f32` reconstruction
+pub const ABSOLUTE_ERROR_TOLERANCE: f32 = 1e-6;
+
+/// Relative tolerance of range-relative error bounds, which accounts for the
+/// rounding of the `f32` data range
+pub const RELATIVE_ERROR_TOLERANCE: f32 = 1e-4;
+
+/// Check that the `decoded` data honors the pointwise error bound of the
+/// `config` that the `original` data was compressed with.
+///
+/// - absolute error bounds must hold up to an absolute tolerance of
+///   [`ABSOLUTE_ERROR_TOLERANCE`]
+/// - range-relative error bounds, relative to the range of the `original`
+///   data, must hold up to a relative tolerance of
+///   [`RELATIVE_ERROR_TOLERANCE`]
+/// - JPEG2000-only compression has no pointwise error bound and always passes
+///
+/// Non-finite decoded values always violate the error bound.
+///
+/// # Errors
+///
+/// - [`EBCCError::ShapeMismatch`] if the `original` and `decoded` data have
+///   different shapes
+/// - [`EBCCError::ErrorBoundViolated`] for the first value whose error
+///   exceeds the bound
+pub fn check_error_bound(
+    original: ArrayView<f32, EbccDim>,
+    decoded: ArrayView<f32, EbccDim>,
+    config: &EBCCConfig,
+) -> EBCCResult<()> {
+    if original.dim() != decoded.dim() {
+        return Err(EBCCError::ShapeMismatch {
+            expected: original.dim().into(),
+            actual: decoded.dim().into(),
+        });
+    }
+
+    let Some(bound) = pointwise_error_bound(config, data_range(original)) else {
+        return Ok(());
+    };
+
+    for ((index, &x), &y) in original.indexed_iter().zip(decoded.iter()) {
+        let error = (x - y).abs();
+        if error.is_nan() || error > bound {
+            return Err(EBCCError::ErrorBoundViolated {
+                index: index.into(),
+                error,
+                bound,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Pointwise error bound of the `config`, including the tolerances, for data
+/// with the given `data_range`, or [`None`] for JPEG2000-only compression.
+pub(crate) fn pointwise_error_bound(config: &EBCCConfig, data_range: f32) -> Option<f32> {
+    match config.residual_compression_type {
+        EBCCResidualType::AbsoluteError(error) => Some(error + ABSOLUTE_ERROR_TOLERANCE),
+        EBCCResidualType::RelativeError(error) => {
+            Some(data_range * error * (1.0 + RELATIVE_ERROR_TOLERANCE))
+        }
+        EBCCResidualType::Jpeg2000Only => None,
+    }
+}
+
+/// Range of the finite values of the `data`, which is zero for data without
+/// any finite values.
+pub(crate) fn data_range(data: ArrayView<f32, EbccDim>) -> f32 {
+    let (min, max) = data
+        .iter()
+        .filter(|x| x.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &x| {
+            (min.min(x), max.max(x))
+        });
+
+    if min <= max {
+        max - min
+    } else {
+        0.0
+    }
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+
+    #[test]
+    fn test_check_error_bound() {
+        let original = Array::from_shape_vec((1, 1, 4), vec![0.0, 1.0, 2.0, 10.0])
+            .unwrap_or_else(|_| Array::zeros((1, 1, 4)));
+        let mut decoded = original.clone();
+        decoded.mapv_inplace(|x| x + 0.05);
+
+        let absolute = EBCCConfig::max_absolute_error_bounded(0.1);
+        assert!(check_error_bound(original.view(), decoded.view(), &absolute).is_ok());
+        assert!(matches!(
+            check_error_bound(
+                original.view(),
+                decoded.view(),
+                &EBCCConfig::max_absolute_error_bounded(0.01)
+            ),
+            Err(EBCCError::ErrorBoundViolated {
+                index: [0, 0, 0],
+                ..
+            })
+        ));
+
+        // the data range is 10, so the relative bound is 0.1
+        let relative = EBCCConfig::relative_error_bounded(0.01);
+        assert!(check_error_bound(original.view(), decoded.view(), &relative).is_ok());
+
+        decoded.fill(f32::NAN);
+        assert!(check_error_bound(original.view(), decoded.view(), &absolute).is_err());
+        assert!(check_error_bound(
+            original.view(),
+            decoded.view(),
+            &EBCCConfig::jpeg2000_only(10.0)
+        )
+        .is_ok());
+
+        let other_shape = Array::zeros((1, 2, 2));
+        assert!(matches!(
+            check_error_bound(original.view(), other_shape.view(), &absolute),
+            Err(EBCCError::ShapeMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+//! Runtime report of the versions of the linked C libraries.
+
+use core::ffi::{c_char, CStr};
+use core::fmt;
+
+/// Versions of the EBCC, `OpenJPEG`, and zstd C libraries, see [`version`].
+///
+/// The `OpenJPEG` and zstd versions are queried from the libraries at
+/// runtime, so they report the system libraries when ebcc is built with the
+/// `system-libs` feature. The EBCC C library has no version query, so the
+/// EBCC version is fixed at compile time to the version that the bindings
+/// were generated for, which may differ from a prebuilt `libebcc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct EBCCVersion {
+    /// Version of the EBCC C library that the bindings were generated for
+    pub ebcc: &'static str,
+    /// Version of the `OpenJPEG` library, e.g. `"2.5.0"`
+    pub openjpeg: &'static str,
+    /// Version of the zstd library, e.g. `"1.5.6"`
+    pub zstd: &'static str,
+}
+
+/// Report the versions of the `OpenJPEG` and zstd C libraries that this build
+/// of EBCC is linked with, and the EBCC version of its bindings.
+///
+/// # Examples
+///
+/// ```rust
+/// let version = ebcc::version();
+/// println!("{version}");
+/// assert!(!version.zstd.is_empty());
+/// ```
+#[must_use]
+pub fn version() -> EBCCVersion {
+    #[expect(unsafe_code)]
+    // Safety: both functions take no arguments and return static strings
+    let (openjpeg, zstd) = unsafe {
+        (
+            ebcc_sys::openjpeg::opj_version(),
+            ebcc_sys::zstd::ZSTD_versionString(),
+        )
+    };
+
+    EBCCVersion {
+        ebcc: ebcc_sys::EBCC_VERSION,
+        #[expect(unsafe_code)]
+        // Safety: opj_version returns a static null-terminated string
+        openjpeg: unsafe { static_c_str(openjpeg) },
+        #[expect(unsafe_code)]
+        // Safety: ZSTD_versionString returns a static null-terminated string
+        zstd: unsafe { static_c_str(zstd) },
+    }
+}
+
+impl fmt::Display for EBCCVersion {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "EBCC {}, OpenJPEG {}, zstd {}",
+            self.ebcc, self.openjpeg, self.zstd
+        )
+    }
+}
+
+/// Convert a static C string into a string slice, which is `"unknown"` if
+/// the pointer is null or the string is not UTF-8.
+///
+/// # Safety
+///
+/// If `ptr` is non-null, it must point to a null-terminated string that
+/// lives for the rest of the program.
+#[expect(unsafe_code)]
+pub unsafe fn static_c_str(ptr: *const c_char) -> &'static str {
+    if ptr.is_null() {
+        return "unknown";
+    }
+
+    // Safety: ptr is a non-null static null-terminated string
+    let c_str = unsafe { CStr::from_ptr(ptr) };
+    c_str.to_str().unwrap_or("unknown")
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version() {
+        let version = version();
+
+        assert!(env!("CARGO_PKG_VERSION").ends_with(version.ebcc));
+        assert!(version.zstd.starts_with("1."));
+        assert!(!version.openjpeg.is_empty());
+
+        let report = version.to_string();
+        assert!(report.starts_with("EBCC "));
+        assert!(report.contains(version.zstd));
+    }
+}
@@ -0,0 +1,115 @@
+#![expect(missing_docs)]
+#![expect(unsafe_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ebcc::container::EbccContainerWriter;
+use ebcc::{EBCCBaseMode, EBCCConfig, EBCCResult};
+use ndarray::Array;
+
+#[cfg(feature = "arrow")]
+use ::arrow_array as _;
+#[cfg(feature = "arrow")]
+use ::arrow_schema as _;
+#[cfg(feature = "blake3")]
+use ::blake3 as _;
+#[cfg(feature = "bytemuck")]
+use ::bytemuck as _;
+#[cfg(feature = "half")]
+use ::half as _;
+#[cfg(feature = "mmap")]
+use ::memmap2 as _;
+#[cfg(feature = "nalgebra")]
+use ::nalgebra as _;
+#[cfg(feature = "ndarray015")]
+use ::ndarray015 as _;
+#[cfg(feature = "ndarray017")]
+use ::ndarray017 as _;
+#[cfg(feature = "netcdf")]
+use ::netcdf as _;
+#[cfg(feature = "rayon")]
+use ::rayon as _;
+#[cfg(feature = "serde")]
+use ::serde as _;
+#[cfg(feature = "async")]
+use ::tokio as _;
+#[cfg(feature = "tracing")]
+use ::tracing as _;
+use ::{
+    crc as _, crc32fast as _, criterion as _, ebcc_sys as _, proptest as _, thiserror as _,
+    xxhash_rust as _,
+};
+
+/// Global allocator that tracks the current and peak number of allocated
+/// bytes of the whole process
+struct PeakAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+// Safety: all allocations are forwarded to the system allocator
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Safety: the caller upholds the contract of GlobalAlloc::alloc
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        // Safety: the caller upholds the contract of GlobalAlloc::dealloc
+        unsafe { System.dealloc(ptr, layout) };
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PeakAlloc = PeakAlloc;
+
+/// Sink that only counts the written bytes
+struct CountingSink(u64);
+
+impl Write for CountingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+#[ignore = "writes a container of more than 4 GiB"]
+fn test_container_writer_streams_large_containers() -> EBCCResult<()> {
+    const MEMORY_CAP: usize = 256 * 1024 * 1024;
+    const FRAME_SHAPE: (usize, usize) = (1024, 1024);
+    const FRAMES: usize = 1040;
+
+    // stored frames are not compressed, so each record has at least 4 MiB
+    let config = EBCCConfig::new().with_base_mode(EBCCBaseMode::Stored);
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    let mut frame = Array::zeros(FRAME_SHAPE);
+    let mut writer = EbccContainerWriter::new(CountingSink(0), config, FRAME_SHAPE)?;
+    for t in 0..FRAMES {
+        // distinct frames, such that no record is shared
+        #[expect(clippy::cast_precision_loss)]
+        frame.fill(t as f32);
+        writer.push_frame(frame.view())?;
+    }
+    let CountingSink(written) = writer.finish()?;
+
+    assert!(written > 4 * 1024 * 1024 * 1024);
+    assert!(PEAK.load(Ordering::Relaxed) - baseline < MEMORY_CAP);
+
+    Ok(())
+}
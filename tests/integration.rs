@@ -1,15 +1,46 @@
 #![expect(missing_docs)]
 
-use std::num::NonZeroUsize;
+use std::{io::Cursor, num::NonZeroUsize};
 
+use ebcc::container::{EbccContainer, EbccContainerWriter};
 use ebcc::{
     ebcc_decode_chunking_into, ebcc_decode_into, ebcc_encode, ebcc_encode_chunking,
-    ebcc_encode_chunking_compat, EBCCChunkShape, EBCCCompatChunkShape, EBCCConfig, EBCCError,
-    EBCCResult, EbccDim, EBCC_NDIMS,
+    ebcc_encode_chunking_compat, EBCCBaseCodec, EBCCBaseMode, EBCCChunkShape, EBCCCompatChunkShape,
+    EBCCConfig, EBCCConservation, EBCCError, EBCCErrorKind, EBCCFailure, EBCCFailureCause,
+    EBCCResult, EBCCRoi, EBCCStoredCompression, EBCCTransform, EbccBufferedEncoder, EbccDecoder,
+    EbccDim, EbccStreamEncoder, EBCC_NDIMS,
 };
 use ndarray::Array;
 
-use ::{ebcc_sys as _, thiserror as _};
+#[cfg(feature = "arrow")]
+use ::arrow_array as _;
+#[cfg(feature = "arrow")]
+use ::arrow_schema as _;
+#[cfg(feature = "blake3")]
+use ::blake3 as _;
+#[cfg(feature = "bytemuck")]
+use ::bytemuck as _;
+#[cfg(feature = "half")]
+use ::half as _;
+#[cfg(feature = "mmap")]
+use ::memmap2 as _;
+#[cfg(feature = "nalgebra")]
+use ::nalgebra as _;
+#[cfg(feature = "ndarray015")]
+use ::ndarray015 as _;
+#[cfg(feature = "ndarray017")]
+use ::ndarray017 as _;
+#[cfg(feature = "netcdf")]
+use ::netcdf as _;
+#[cfg(feature = "rayon")]
+use ::rayon as _;
+#[cfg(feature = "serde")]
+use ::serde as _;
+#[cfg(feature = "async")]
+use ::tokio as _;
+#[cfg(feature = "tracing")]
+use ::tracing as _;
+use ::{crc as _, crc32fast as _, criterion as _, proptest as _, thiserror as _, xxhash_rust as _};
 
 #[test]
 fn test_basic_compression_roundtrip() -> EBCCResult<()> {
@@ -347,8 +378,10 @@ fn test_decode_chunking_rejects_same_len_wrong_shape() -> EBCCResult<()> {
 
     assert!(matches!(
         result,
-        Err(EBCCError::InvalidInput(message))
-            if message.contains("Chunked EBCC data has shape [2, 32, 32]")
+        Err(EBCCError::ShapeMismatch {
+            expected: [2, 32, 32],
+            actual: [1, 64, 32],
+        })
     ));
 
     Ok(())
@@ -439,6 +472,43 @@ fn test_error_bounds() -> EBCCResult<()> {
     Ok(())
 }
 
+#[test]
+fn test_c_error_kinds() {
+    let failure = "EBCC ebcc_decode failed for 1024 bytes and returned no output buffer";
+
+    // the category only depends on the typed cause, not on the messages
+    let corrupted = EBCCError::DecompressionError(EBCCFailure::new(
+        "EBCC stored-raw data is corrupted: Data corruption detected",
+        EBCCFailureCause::Zstd {
+            code: ebcc_sys::zstd::ZSTD_error_corruption_detected,
+        },
+    ));
+    assert_eq!(corrupted.kind(), EBCCErrorKind::InvalidInput);
+    let out_of_memory = EBCCError::CompressionError(EBCCFailure::new(
+        "zstd failed to compress 1024 bytes: Allocation error : not enough memory",
+        EBCCFailureCause::Zstd {
+            code: ebcc_sys::zstd::ZSTD_error_memory_allocation,
+        },
+    ));
+    assert_eq!(out_of_memory.kind(), EBCCErrorKind::OutOfMemory);
+    let unknown = EBCCError::DecompressionError(
+        EBCCFailure::new(failure, EBCCFailureCause::NoOutput).with_c_errors(vec![
+            String::from("Not enough memory to decode tile"),
+            String::from("Failed to decode the codestream"),
+        ]),
+    );
+    assert_eq!(unknown.kind(), EBCCErrorKind::Decompression);
+
+    // all reported C error messages are kept in order
+    assert_eq!(
+        unknown.to_string(),
+        format!(
+            "Decompression failed: {failure}, reporting: Not enough memory to decode tile; \
+             Failed to decode the codestream"
+        )
+    );
+}
+
 #[test]
 #[expect(clippy::indexing_slicing)]
 fn test_invalid_inputs() {
@@ -448,7 +518,13 @@ fn test_invalid_inputs() {
     let config = EBCCConfig::new();
 
     let result = ebcc_encode(data_with_nan.view(), &config);
-    assert!(result.is_err());
+    assert!(matches!(
+        result,
+        Err(ref err @ EBCCError::NonFinite {
+            index: [0, 0, 1],
+            ..
+        }) if err.kind() == EBCCErrorKind::InvalidInput
+    ));
 
     // Test with infinite values
     let mut data_with_inf = Array::from_shape_simple_fn((1, 32, 32), || 1.0);
@@ -479,3 +555,121 @@ fn test_config_validation() {
     invalid_config = EBCCConfig::new(); // Zero dimension
     assert!(ebcc_encode(Array::zeros((0, 32, 32)).view(), &invalid_config).is_err());
 }
+
+#[test]
+fn test_config_fingerprints_are_distinct() {
+    let config = EBCCConfig::max_absolute_error_bounded(0.1);
+
+    // every setting that changes the bitstream is turned on one at a time
+    let configs = [
+        config.clone(),
+        config.clone().with_base_cr(20.0),
+        EBCCConfig::relative_error_bounded(0.1),
+        EBCCConfig::jpeg2000_only(10.0),
+        config.clone().with_base_mode(EBCCBaseMode::None),
+        config.clone().with_base_mode(EBCCBaseMode::Stored),
+        config
+            .clone()
+            .with_base_mode(EBCCBaseMode::Stored)
+            .with_stored_compression(EBCCStoredCompression::Zstd),
+        config.clone().with_base_mode(EBCCBaseMode::Layered),
+        config.clone().with_roi(EBCCRoi::new(Array::ones((2, 2)))),
+        config.clone().with_roi(EBCCRoi::new(Array::ones((1, 4)))),
+        config.clone().with_transform(EBCCTransform::Log1p),
+        config.clone().with_transform(EBCCTransform::SignedLog),
+        config.clone().with_transform(EBCCTransform::Linear {
+            scale: 2.0,
+            offset: 1.0,
+        }),
+        config.clone().with_quantile_sketch(),
+        config.clone().with_output_quantization(0.5),
+        config.clone().with_conservation(EBCCConservation::Global),
+        config.clone().with_conservation(EBCCConservation::PerFrame),
+        config.clone().with_value_range(Some(0.0), None),
+        config.clone().with_value_range(None, Some(0.0)),
+        config.clone().with_value_range(Some(0.0), Some(1.0)),
+        config.clone().with_stage("stage"),
+        config.clone().with_stage("stagestage"),
+        config.clone().with_residual_coder("stage"),
+        config.with_base_codec(EBCCBaseCodec::Identity),
+    ];
+
+    for (i, a) in configs.iter().enumerate() {
+        for b in configs.iter().skip(i + 1) {
+            assert_ne!(
+                a.fingerprint(),
+                b.fingerprint(),
+                "{a:?} and {b:?} share a fingerprint"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_public_types_are_send_and_sync() {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<EBCCConfig>();
+    assert_send_sync::<EBCCError>();
+    assert_send_sync::<EbccBufferedEncoder>();
+    assert_send_sync::<EbccDecoder>();
+    assert_send_sync::<EbccStreamEncoder<Vec<u8>>>();
+    assert_send_sync::<EbccContainerWriter<Vec<u8>>>();
+    assert_send_sync::<EbccContainer<Cursor<Vec<u8>>>>();
+}
+
+#[test]
+#[expect(clippy::cast_precision_loss)]
+fn test_concurrent_encode_decode() -> EBCCResult<()> {
+    const THREADS: usize = 8;
+    const ITERATIONS: usize = 16;
+
+    let config = EBCCConfig::max_absolute_error_bounded(0.1);
+    let inputs = (0..THREADS)
+        .map(|seed| {
+            Array::from_shape_fn((2, 32, 48), |(t, y, x)| {
+                ((seed + t) as f32).mul_add(10.0, (y as f32 / 7.0).sin() + (x as f32 / 5.0).cos())
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // sequential reference results
+    let expected = inputs
+        .iter()
+        .map(|data| ebcc_encode(data.view(), &config))
+        .collect::<EBCCResult<Vec<_>>>()?;
+
+    std::thread::scope(|scope| {
+        // spawn all workers before joining any of them
+        #[expect(clippy::needless_collect)]
+        let workers = inputs
+            .iter()
+            .zip(&expected)
+            .map(|(data, expected)| {
+                let config = &config;
+                scope.spawn(move || -> EBCCResult<()> {
+                    let mut encoder = EbccBufferedEncoder::new(config.clone())?;
+                    let mut decoder = EbccDecoder::new();
+                    let mut decompressed = Array::zeros(data.dim());
+
+                    for _ in 0..ITERATIONS {
+                        assert_eq!(&ebcc_encode(data.view(), config)?, expected);
+                        assert_eq!(encoder.encode(data.view())?, expected.as_slice());
+
+                        decoder.decode_into(expected, decompressed.view_mut())?;
+                        assert!(max_abs_error(data, &decompressed) <= 0.1 + 1e-6);
+                    }
+
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .try_for_each(|worker| match worker.join() {
+                Ok(result) => result,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+    })
+}
@@ -0,0 +1,133 @@
+//! Property-based round-trip tests over random shapes, value ranges, and
+//! configurations.
+
+use ebcc::{
+    ebcc_decode_into, ebcc_encode, testdata, verify::check_error_bound, EBCCConfig, EBCCError,
+    EbccDim,
+};
+use ndarray::Array;
+use proptest::prelude::*;
+
+#[cfg(feature = "arrow")]
+use ::arrow_array as _;
+#[cfg(feature = "arrow")]
+use ::arrow_schema as _;
+#[cfg(feature = "blake3")]
+use ::blake3 as _;
+#[cfg(feature = "bytemuck")]
+use ::bytemuck as _;
+#[cfg(feature = "half")]
+use ::half as _;
+#[cfg(feature = "mmap")]
+use ::memmap2 as _;
+#[cfg(feature = "nalgebra")]
+use ::nalgebra as _;
+#[cfg(feature = "ndarray015")]
+use ::ndarray015 as _;
+#[cfg(feature = "ndarray017")]
+use ::ndarray017 as _;
+#[cfg(feature = "netcdf")]
+use ::netcdf as _;
+#[cfg(feature = "rayon")]
+use ::rayon as _;
+#[cfg(feature = "serde")]
+use ::serde as _;
+#[cfg(feature = "async")]
+use ::tokio as _;
+#[cfg(feature = "tracing")]
+use ::tracing as _;
+use ::{crc as _, crc32fast as _, criterion as _, ebcc_sys as _, thiserror as _, xxhash_rust as _};
+
+/// Synthetic field generator from [`testdata`]
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Temperature,
+    Fronts,
+    Precipitation { seed: u64 },
+    Noise { seed: u64 },
+}
+
+impl Field {
+    fn generate(self, shape: (usize, usize, usize)) -> Array<f32, EbccDim> {
+        match self {
+            Self::Temperature => testdata::temperature(shape),
+            Self::Fronts => testdata::fronts(shape),
+            Self::Precipitation { seed } => testdata::precipitation(shape, seed),
+            Self::Noise { seed } => testdata::noise(shape, 1.0, seed),
+        }
+    }
+}
+
+fn shape() -> impl Strategy<Value = (usize, usize, usize)> {
+    (1_usize..=3, 32_usize..=80, 32_usize..=80)
+}
+
+fn field() -> impl Strategy<Value = Field> {
+    prop_oneof![
+        Just(Field::Temperature),
+        Just(Field::Fronts),
+        any::<u64>().prop_map(|seed| Field::Precipitation { seed }),
+        any::<u64>().prop_map(|seed| Field::Noise { seed }),
+    ]
+}
+
+/// Scale and offset that are applied to the field to vary its value range
+fn value_range() -> impl Strategy<Value = (f32, f32)> {
+    (-3.0_f32..3.0, -100.0_f32..100.0).prop_map(|(log_scale, offset)| {
+        let scale = 10.0_f32.powf(log_scale);
+        (scale, offset * scale)
+    })
+}
+
+/// Configuration whose absolute error bound is relative to the `scale` of
+/// the values, so that it stays well above their `f32` precision
+fn config(scale: f32) -> impl Strategy<Value = EBCCConfig> {
+    let base_cr = 2.0_f32..100.0;
+
+    prop_oneof![
+        (1e-3_f32..10.0, base_cr.clone()).prop_map(move |(error, base_cr)| {
+            EBCCConfig::max_absolute_error_bounded(error * scale).with_base_cr(base_cr)
+        }),
+        (1e-4_f32..0.1, base_cr.clone()).prop_map(|(error, base_cr)| {
+            EBCCConfig::relative_error_bounded(error).with_base_cr(base_cr)
+        }),
+        base_cr.prop_map(EBCCConfig::jpeg2000_only),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig { cases: 32, ..ProptestConfig::default() })]
+
+    #[test]
+    fn roundtrip_honors_error_bound(
+        shape in shape(),
+        field in field(),
+        (scale, offset, config) in value_range()
+            .prop_flat_map(|(scale, offset)| (Just(scale), Just(offset), config(scale))),
+    ) {
+        let data = field.generate(shape).mapv(|x| x.mul_add(scale, offset));
+
+        let compressed = ebcc_encode(data.view(), &config)?;
+        let mut decompressed = Array::zeros(data.dim());
+        ebcc_decode_into(&compressed, decompressed.view_mut())?;
+
+        check_error_bound(data.view(), decompressed.view(), &config)?;
+    }
+
+    #[test]
+    fn decode_rejects_other_shapes(
+        shape in shape(),
+        extra_frames in 1_usize..=2,
+        config in config(1.0),
+    ) {
+        let data = testdata::temperature(shape);
+        let compressed = ebcc_encode(data.view(), &config)?;
+
+        let (frames, height, width) = shape;
+        let mut decompressed = Array::zeros((frames + extra_frames, height, width));
+        prop_assert!(matches!(
+            ebcc_decode_into(&compressed, decompressed.view_mut()),
+            Err(EBCCError::ShapeMismatch { .. })
+        ), "decoding into a different shape must fail");
+    }
+}